@@ -0,0 +1,1038 @@
+//! An alternative AST for executable documents, in which names and strings
+//! borrow from the source and nodes are allocated in a bump arena, rather than
+//! being interned and allocated individually. This avoids hashing every name
+//! when parsing requests, at the cost of the analyses in this crate, which
+//! work on [`ast::Document`]. [`Document::to_interned`] converts between the
+//! two
+//!
+//! Only operations and fragments are supported. As with
+//! [`GraphqlParser::parse_query`](crate::GraphqlParser::parse_query), type
+//! system definitions are an error
+
+use bumpalo::collections::Vec as BumpVec;
+use lasso::Rodeo;
+
+use crate::{
+    ast::{self, Keyword, OperationKind, Span, Token},
+    error::GraphqlParseError,
+    lexer::{Lexer, UNICODE_BOM},
+};
+
+pub use bumpalo::Bump;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Document<'a> {
+    pub definitions: &'a [Definition<'a>],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Definition<'a> {
+    Operation(Operation<'a>),
+    Fragment(Fragment<'a>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Operation<'a> {
+    pub kind: OperationKind,
+    pub name: Option<&'a str>,
+    pub variable_definitions: &'a [VariableDefinition<'a>],
+    pub directives: &'a [Directive<'a>],
+    pub selection_set: &'a [Selection<'a>],
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VariableDefinition<'a> {
+    /// The name of the variable, without the `$`
+    pub name: &'a str,
+    pub ty: Type<'a>,
+    pub default: Option<Value<'a>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fragment<'a> {
+    pub name: &'a str,
+    pub on: &'a str,
+    pub directives: &'a [Directive<'a>],
+    pub selection_set: &'a [Selection<'a>],
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Selection<'a> {
+    Field {
+        alias: Option<&'a str>,
+        name: &'a str,
+        arguments: Option<&'a [Argument<'a>]>,
+        directives: &'a [Directive<'a>],
+        selection_set: Option<&'a [Selection<'a>]>,
+        span: Span,
+    },
+    FragmentSpread {
+        name: &'a str,
+        directives: &'a [Directive<'a>],
+        span: Span,
+    },
+    InlineFragment {
+        on: Option<&'a str>,
+        directives: &'a [Directive<'a>],
+        selection_set: &'a [Selection<'a>],
+        span: Span,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Directive<'a> {
+    pub name: &'a str,
+    pub arguments: Option<&'a [Argument<'a>]>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Argument<'a> {
+    pub name: &'a str,
+    pub value: Value<'a>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value<'a> {
+    True,
+    False,
+    Null,
+    /// Strings without escape sequences borrow from the source, and others
+    /// are allocated in the arena
+    String(&'a str),
+    Variable(&'a str),
+    EnumVariant(&'a str),
+    List(&'a [Value<'a>]),
+    /// The fields in source order. Later fields replace earlier fields with
+    /// the same name when converted to [`ast::Value`]
+    Object(&'a [(&'a str, Value<'a>)]),
+    Float(f64),
+    Int(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type<'a> {
+    Named { name: &'a str, nullable: bool },
+    List { base: &'a Type<'a>, nullable: bool },
+}
+
+impl<'a> Document<'a> {
+    pub fn operations(&self) -> impl Iterator<Item = &Operation<'a>> + '_ {
+        self.definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Operation(operation) => Some(operation),
+                Definition::Fragment(..) => None,
+            })
+    }
+
+    pub fn fragments(&self) -> impl Iterator<Item = &Fragment<'a>> + '_ {
+        self.definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Fragment(fragment) => Some(fragment),
+                Definition::Operation(..) => None,
+            })
+    }
+
+    pub fn fragment(&self, name: &str) -> Option<&Fragment<'a>> {
+        self.fragments().find(|fragment| fragment.name == name)
+    }
+
+    /// Intern every name and string into `interner`, building the equivalent
+    /// [`ast::Document`]
+    pub fn to_interned(&self, interner: &mut Rodeo) -> ast::Document {
+        let mut document = ast::Document::new();
+
+        for definition in self.definitions {
+            let definition = match definition {
+                Definition::Operation(operation) => ast::Definition::Operation(ast::Operation {
+                    kind: operation.kind,
+                    name: operation.name.map(|name| interner.get_or_intern(name)),
+                    variable_definitions: operation
+                        .variable_definitions
+                        .iter()
+                        .map(|variable| ast::VariableDefinition {
+                            name: interner.get_or_intern(variable.name),
+                            ty: variable.ty.to_interned(interner),
+                            ty_span: ast::Span::default(),
+                            default: variable.default.map(|value| value.to_interned(interner)),
+                            default_span: ast::ValueSpan::default(),
+                            span: variable.span,
+                        })
+                        .collect(),
+                    directives: intern_directives(operation.directives, interner),
+                    selection_set: intern_selection_set(operation.selection_set, interner),
+                    span: operation.span,
+                }),
+                Definition::Fragment(fragment) => ast::Definition::Fragment(ast::Fragment {
+                    name: interner.get_or_intern(fragment.name),
+                    on: interner.get_or_intern(fragment.on),
+                    directives: intern_directives(fragment.directives, interner),
+                    selection_set: intern_selection_set(fragment.selection_set, interner),
+                    span: fragment.span,
+                }),
+            };
+
+            document.add_definition(definition);
+        }
+
+        document
+    }
+}
+
+impl<'a> Value<'a> {
+    pub fn to_interned(&self, interner: &mut Rodeo) -> ast::Value {
+        match *self {
+            Self::True => ast::Value::True,
+            Self::False => ast::Value::False,
+            Self::Null => ast::Value::Null,
+            Self::String(string) => ast::Value::String(interner.get_or_intern(string)),
+            Self::Variable(name) => ast::Value::Variable(interner.get_or_intern(name)),
+            Self::EnumVariant(name) => ast::Value::EnumVariant(interner.get_or_intern(name)),
+            Self::List(values) => ast::Value::List(
+                values
+                    .iter()
+                    .map(|value| value.to_interned(interner))
+                    .collect(),
+            ),
+            Self::Object(fields) => ast::Value::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| {
+                        (interner.get_or_intern(name), value.to_interned(interner))
+                    })
+                    .collect(),
+            ),
+            Self::Float(float) => ast::Value::Float(float),
+            Self::Int(int) => ast::Value::Int(int),
+        }
+    }
+}
+
+impl<'a> Type<'a> {
+    pub fn to_interned(&self, interner: &mut Rodeo) -> ast::Type {
+        match *self {
+            Self::Named { name, nullable } => ast::Type::Named {
+                name: interner.get_or_intern(name),
+                nullable,
+            },
+            Self::List { base, nullable } => ast::Type::List {
+                base: Box::new(base.to_interned(interner)),
+                nullable,
+            },
+        }
+    }
+}
+
+fn intern_directives(directives: &[Directive], interner: &mut Rodeo) -> Vec<ast::Directive> {
+    directives
+        .iter()
+        .map(|directive| ast::Directive {
+            name: interner.get_or_intern(directive.name),
+            arguments: directive
+                .arguments
+                .map(|arguments| intern_arguments(arguments, interner)),
+            span: directive.span,
+        })
+        .collect()
+}
+
+fn intern_arguments(arguments: &[Argument], interner: &mut Rodeo) -> Vec<ast::Argument> {
+    arguments
+        .iter()
+        .map(|argument| ast::Argument {
+            name: interner.get_or_intern(argument.name),
+            value: argument.value.to_interned(interner),
+            value_span: ast::ValueSpan::default(),
+            span: argument.span,
+        })
+        .collect()
+}
+
+fn intern_selection_set(selection_set: &[Selection], interner: &mut Rodeo) -> Vec<ast::Selection> {
+    selection_set
+        .iter()
+        .map(|selection| match *selection {
+            Selection::Field {
+                alias,
+                name,
+                arguments,
+                directives,
+                selection_set,
+                span,
+            } => ast::Selection::Field {
+                alias: alias.map(|alias| interner.get_or_intern(alias)),
+                name: interner.get_or_intern(name),
+                arguments: arguments.map(|arguments| intern_arguments(arguments, interner)),
+                directives: intern_directives(directives, interner),
+                selection_set: selection_set
+                    .map(|selection_set| intern_selection_set(selection_set, interner)),
+                span,
+            },
+            Selection::FragmentSpread {
+                name,
+                directives,
+                span,
+            } => ast::Selection::FragmentSpread {
+                name: interner.get_or_intern(name),
+                directives: intern_directives(directives, interner),
+                span,
+            },
+            Selection::InlineFragment {
+                on,
+                directives,
+                selection_set,
+                span,
+            } => ast::Selection::InlineFragment {
+                on: on.map(|on| interner.get_or_intern(on)),
+                directives: intern_directives(directives, interner),
+                selection_set: intern_selection_set(selection_set, interner),
+                span,
+            },
+        })
+        .collect()
+}
+
+/// Parse an executable document, allocating its nodes in `bump`
+pub fn parse_query<'a>(
+    buffer: &'a [u8],
+    bump: &'a Bump,
+) -> Result<Document<'a>, GraphqlParseError> {
+    let mut parser = ArenaParser {
+        buffer,
+        bump,
+        cursor: 0,
+        consumed_end: 0,
+        lexer: None,
+    };
+
+    let mut definitions = BumpVec::new_in(bump);
+
+    while let Some(definition) = parser.next_definition()? {
+        definitions.push(definition);
+    }
+
+    Ok(Document {
+        definitions: definitions.into_bump_slice(),
+    })
+}
+
+struct ArenaParser<'a> {
+    buffer: &'a [u8],
+    bump: &'a Bump,
+    cursor: usize,
+    /// The end of the most recently consumed token
+    consumed_end: usize,
+    /// Lexes the tokens that can't be borrowed from the source, such as
+    /// numbers and strings with escape sequences, and tokens that need to be
+    /// reported in errors. Only created once needed
+    lexer: Option<Lexer<'a>>,
+}
+
+impl<'a> ArenaParser<'a> {
+    fn alloc<T>(&self, values: BumpVec<'a, T>) -> &'a [T] {
+        values.into_bump_slice()
+    }
+
+    fn peek_byte(&mut self) -> Option<u8> {
+        self.skip_ignored_characters();
+        self.buffer.get(self.cursor).copied()
+    }
+
+    fn skip_ignored_characters(&mut self) {
+        while let Some(b) = self.buffer.get(self.cursor) {
+            match b {
+                b' ' | b'\t' | b'\n' | b'\r' | b',' => self.cursor += 1,
+                b'#' => {
+                    while !matches!(self.buffer.get(self.cursor), Some(b'\n' | b'\r') | None) {
+                        self.cursor += 1;
+                    }
+                }
+                0xEF if self.buffer[self.cursor..].starts_with(UNICODE_BOM) => {
+                    self.cursor += UNICODE_BOM.len();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn consume_byte_if_eq(&mut self, byte: u8) -> bool {
+        if self.peek_byte() == Some(byte) {
+            self.cursor += 1;
+            self.consumed_end = self.cursor;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_byte(&mut self, byte: u8) -> Result<(), GraphqlParseError> {
+        if self.consume_byte_if_eq(byte) {
+            return Ok(());
+        }
+
+        // defer to the lexer, so that errors match those of the parser
+        let lexer = self.lexer_at(self.cursor);
+        lexer.expect_byte(byte)?;
+        unreachable!("the lexer accepted a byte that the arena parser did not")
+    }
+
+    /// The lexer, moved to `offset`
+    fn lexer_at(&mut self, offset: usize) -> &mut Lexer<'a> {
+        let lexer = self.lexer.get_or_insert_with(|| Lexer::new(self.buffer));
+        lexer.seek(offset);
+        lexer
+    }
+
+    /// Lex the token at the cursor with the lexer, moving past it
+    fn lex_token(&mut self) -> Result<Option<Token>, GraphqlParseError> {
+        let lexer = self.lexer_at(self.cursor);
+        let token = lexer.next_token()?;
+        self.cursor = lexer.last_span().end;
+        self.consumed_end = self.cursor;
+        Ok(token)
+    }
+
+    /// An error for when the token at `start` isn't what we wanted
+    fn unexpected(&mut self, start: usize, expected: &'static str) -> GraphqlParseError {
+        let lexer = self.lexer_at(start);
+
+        match lexer.next_token() {
+            Ok(Some(found)) => GraphqlParseError::UnexpectedToken {
+                found,
+                expected,
+                span: lexer.last_span(),
+            },
+            Ok(None) => GraphqlParseError::UnexpectedEof {
+                expected,
+                span: lexer.last_span(),
+            },
+            Err(error) => error,
+        }
+    }
+
+    /// An error for when the token at `start` isn't `token`
+    fn expected(&mut self, start: usize, token: Token) -> GraphqlParseError {
+        let lexer = self.lexer_at(start);
+
+        match lexer.next_token() {
+            Ok(found) => GraphqlParseError::ExpectedToken {
+                token,
+                found,
+                span: lexer.last_span(),
+            },
+            Err(error) => error,
+        }
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        if cfg!(feature = "spans") {
+            Span::new(start, self.consumed_end)
+        } else {
+            Span::default()
+        }
+    }
+
+    /// The name or keyword at the cursor, without consuming it
+    fn peek_name(&mut self) -> Option<&'a str> {
+        self.skip_ignored_characters();
+
+        let buffer = self.buffer;
+
+        match buffer.get(self.cursor) {
+            Some(b'a'..=b'z' | b'A'..=b'Z' | b'_') => {}
+            _ => return None,
+        }
+
+        let len = buffer[self.cursor..]
+            .iter()
+            .take_while(|b| matches!(b, b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_'))
+            .count();
+
+        Some(std::str::from_utf8(&buffer[self.cursor..self.cursor + len]).unwrap())
+    }
+
+    fn consume_name(&mut self, name: &str) {
+        self.cursor += name.len();
+        self.consumed_end = self.cursor;
+    }
+
+    /// A name or keyword
+    fn expect_name(&mut self) -> Result<&'a str, GraphqlParseError> {
+        match self.peek_name() {
+            Some(name) => {
+                self.consume_name(name);
+                Ok(name)
+            }
+            None => Err(self.unexpected(self.cursor, "a name")),
+        }
+    }
+
+    /// A variable's name, without the `$`, which must be immediately
+    /// followed by the name
+    fn parse_variable(&mut self, expected: &'static str) -> Result<&'a str, GraphqlParseError> {
+        self.skip_ignored_characters();
+
+        let start = self.cursor;
+
+        if self.buffer.get(start) == Some(&b'$') {
+            self.cursor += 1;
+
+            if let Some(name) = self.peek_name().filter(|_| self.cursor == start + 1) {
+                self.consume_name(name);
+                return Ok(name);
+            }
+        }
+
+        Err(self.unexpected(start, expected))
+    }
+
+    fn consume_keyword_if_eq(&mut self, keyword: Keyword) -> bool {
+        if self.peek_name() == Some(keyword.as_str()) {
+            self.consume_name(keyword.as_str());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: Keyword) -> Result<(), GraphqlParseError> {
+        if self.consume_keyword_if_eq(keyword) {
+            Ok(())
+        } else {
+            Err(self.expected(self.cursor, Token::Keyword(keyword)))
+        }
+    }
+
+    fn expect_punctuator(&mut self, byte: u8, token: Token) -> Result<(), GraphqlParseError> {
+        if self.consume_byte_if_eq(byte) {
+            Ok(())
+        } else {
+            Err(self.expected(self.cursor, token))
+        }
+    }
+
+    fn consume_dot_dot_dot(&mut self) -> Result<bool, GraphqlParseError> {
+        if self.peek_byte() != Some(b'.') {
+            return Ok(false);
+        }
+
+        if self.buffer[self.cursor..].starts_with(b"...") {
+            self.cursor += 3;
+            self.consumed_end = self.cursor;
+            return Ok(true);
+        }
+
+        // a `.` or `..`, which the lexer reports
+        Err(self.unexpected(self.cursor, "a selection"))
+    }
+
+    /// A string, borrowed from the source if it has no escape sequences and
+    /// isn't a block string
+    fn parse_string(&mut self) -> Result<&'a str, GraphqlParseError> {
+        let start = self.cursor;
+        let buffer = self.buffer;
+
+        if !buffer[start..].starts_with(b"\"\"\"") {
+            let len = buffer[start + 1..]
+                .iter()
+                .position(|b| matches!(b, b'"' | b'\\' | b'\n'));
+
+            if let Some(len) = len {
+                let end = start + 1 + len;
+
+                if buffer[end] == b'"' {
+                    if let Ok(string) = std::str::from_utf8(&buffer[start + 1..end]) {
+                        self.cursor = end + 1;
+                        self.consumed_end = self.cursor;
+                        return Ok(string);
+                    }
+                }
+            }
+        }
+
+        match self.lex_token()? {
+            Some(Token::String(string)) => {
+                let lexer = self.lexer.as_ref().unwrap();
+                Ok(self.bump.alloc_str(lexer.interner.resolve(&string)))
+            }
+            _ => unreachable!("the lexer lexed a `\"` as something other than a string"),
+        }
+    }
+
+    /// Parse the next definition, or `None` at the end of the input
+    fn next_definition(&mut self) -> Result<Option<Definition<'a>>, GraphqlParseError> {
+        let start = match self.peek_byte() {
+            Some(..) => self.cursor,
+            None => return Ok(None),
+        };
+
+        // descriptions are allowed, but have no meaning on executable
+        // definitions
+        if self.peek_byte() == Some(b'"') {
+            self.parse_string()?;
+        }
+
+        if self.consume_byte_if_eq(b'{') {
+            let selection_set = self.parse_selection_set()?;
+
+            return Ok(Some(Definition::Operation(Operation {
+                kind: OperationKind::Query,
+                name: None,
+                variable_definitions: &[],
+                directives: &[],
+                selection_set,
+                span: self.span_from(start),
+            })));
+        }
+
+        let keyword_start = self.cursor;
+
+        let kind = match self.peek_name() {
+            Some("query") => OperationKind::Query,
+            Some("mutation") => OperationKind::Mutation,
+            Some("subscription") => OperationKind::Subscription,
+            Some("fragment") => {
+                self.consume_name("fragment");
+                return Ok(Some(Definition::Fragment(
+                    self.parse_fragment_definition(start)?,
+                )));
+            }
+            Some(
+                name @ ("enum" | "type" | "union" | "input" | "scalar" | "interface" | "schema"
+                | "directive" | "extend"),
+            ) => {
+                return Err(GraphqlParseError::TypeSystemDefinitionInExecutable {
+                    span: Span::new(keyword_start, keyword_start + name.len()),
+                });
+            }
+            _ => return Err(self.unexpected(keyword_start, "a definition")),
+        };
+
+        self.consume_name(kind.as_str());
+
+        Ok(Some(Definition::Operation(
+            self.parse_operation(kind, start)?,
+        )))
+    }
+
+    fn parse_operation(
+        &mut self,
+        kind: OperationKind,
+        start: usize,
+    ) -> Result<Operation<'a>, GraphqlParseError> {
+        let name = self.peek_name();
+
+        if let Some(name) = name {
+            self.consume_name(name);
+        }
+
+        let variable_definitions = if self.consume_byte_if_eq(b'(') {
+            self.parse_variable_definitions()?
+        } else {
+            &[]
+        };
+
+        let directives = self.parse_optional_directives()?;
+
+        self.expect_punctuator(b'{', Token::OpenCurlyBrace)?;
+
+        let selection_set = self.parse_selection_set()?;
+
+        Ok(Operation {
+            kind,
+            name,
+            variable_definitions,
+            directives,
+            selection_set,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_variable_definitions(
+        &mut self,
+    ) -> Result<&'a [VariableDefinition<'a>], GraphqlParseError> {
+        let mut variable_definitions = BumpVec::new_in(self.bump);
+
+        while !self.consume_byte_if_eq(b')') {
+            let start = self.cursor;
+
+            let name = self.parse_variable("a variable")?;
+
+            self.expect_punctuator(b':', Token::Colon)?;
+
+            let ty = self.parse_type()?;
+
+            let default = if self.consume_byte_if_eq(b'=') {
+                Some(self.parse_value()?)
+            } else {
+                None
+            };
+
+            variable_definitions.push(VariableDefinition {
+                name,
+                ty,
+                default,
+                span: self.span_from(start),
+            });
+        }
+
+        Ok(self.alloc(variable_definitions))
+    }
+
+    fn parse_type(&mut self) -> Result<Type<'a>, GraphqlParseError> {
+        let base = match self.peek_name() {
+            Some(name) => {
+                self.consume_name(name);
+
+                Type::Named {
+                    name,
+                    nullable: true,
+                }
+            }
+            _ if self.consume_byte_if_eq(b'[') => {
+                let base = self.parse_type()?;
+                self.expect_byte(b']')?;
+
+                Type::List {
+                    base: self.bump.alloc(base),
+                    nullable: true,
+                }
+            }
+            _ => return Err(self.unexpected(self.cursor, "a type")),
+        };
+
+        if !self.consume_byte_if_eq(b'!') {
+            return Ok(base);
+        }
+
+        Ok(match base {
+            Type::Named { name, .. } => Type::Named {
+                name,
+                nullable: false,
+            },
+            Type::List { base, .. } => Type::List {
+                base,
+                nullable: false,
+            },
+        })
+    }
+
+    fn parse_fragment_definition(
+        &mut self,
+        start: usize,
+    ) -> Result<Fragment<'a>, GraphqlParseError> {
+        let name = self.expect_name()?;
+
+        self.expect_keyword(Keyword::On)?;
+
+        let on = self.expect_name()?;
+
+        let directives = self.parse_optional_directives()?;
+
+        self.expect_punctuator(b'{', Token::OpenCurlyBrace)?;
+
+        let selection_set = self.parse_selection_set()?;
+
+        Ok(Fragment {
+            name,
+            on,
+            directives,
+            selection_set,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_selection_set(&mut self) -> Result<&'a [Selection<'a>], GraphqlParseError> {
+        let mut selection_set = BumpVec::new_in(self.bump);
+
+        while !self.consume_byte_if_eq(b'}') {
+            let start = self.cursor;
+
+            if self.consume_dot_dot_dot()? {
+                selection_set.push(self.parse_inline_or_spread_fragment(start)?);
+                continue;
+            }
+
+            selection_set.push(self.parse_field()?);
+        }
+
+        Ok(self.alloc(selection_set))
+    }
+
+    fn parse_inline_or_spread_fragment(
+        &mut self,
+        start: usize,
+    ) -> Result<Selection<'a>, GraphqlParseError> {
+        let on = if self.consume_keyword_if_eq(Keyword::On) {
+            Some(self.expect_name()?)
+        } else {
+            None
+        };
+
+        if on.is_some() || matches!(self.peek_byte(), Some(b'@' | b'{')) {
+            let directives = self.parse_optional_directives()?;
+
+            self.expect_punctuator(b'{', Token::OpenCurlyBrace)?;
+
+            let selection_set = self.parse_selection_set()?;
+
+            return Ok(Selection::InlineFragment {
+                on,
+                directives,
+                selection_set,
+                span: self.span_from(start),
+            });
+        }
+
+        let name = self.expect_name()?;
+        let directives = self.parse_optional_directives()?;
+
+        Ok(Selection::FragmentSpread {
+            name,
+            directives,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_field(&mut self) -> Result<Selection<'a>, GraphqlParseError> {
+        let start = self.cursor;
+
+        let alias_or_name = self.expect_name()?;
+
+        let (alias, name) = if self.consume_byte_if_eq(b':') {
+            (Some(alias_or_name), self.expect_name()?)
+        } else {
+            (None, alias_or_name)
+        };
+
+        let arguments = if self.consume_byte_if_eq(b'(') {
+            Some(self.parse_arguments()?)
+        } else {
+            None
+        };
+
+        let directives = self.parse_optional_directives()?;
+
+        let selection_set = if self.consume_byte_if_eq(b'{') {
+            Some(self.parse_selection_set()?)
+        } else {
+            None
+        };
+
+        Ok(Selection::Field {
+            alias,
+            name,
+            arguments,
+            directives,
+            selection_set,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_optional_directives(&mut self) -> Result<&'a [Directive<'a>], GraphqlParseError> {
+        let mut directives = BumpVec::new_in(self.bump);
+
+        while self.consume_byte_if_eq(b'@') {
+            let start = self.cursor - 1;
+
+            let name = self.expect_name()?;
+            let arguments = if self.consume_byte_if_eq(b'(') {
+                Some(self.parse_arguments()?)
+            } else {
+                None
+            };
+
+            directives.push(Directive {
+                name,
+                arguments,
+                span: self.span_from(start),
+            });
+        }
+
+        Ok(self.alloc(directives))
+    }
+
+    fn parse_arguments(&mut self) -> Result<&'a [Argument<'a>], GraphqlParseError> {
+        let mut arguments = BumpVec::new_in(self.bump);
+
+        while !self.consume_byte_if_eq(b')') {
+            let start = self.cursor;
+
+            let name = self.expect_name()?;
+
+            self.expect_byte(b':')?;
+
+            let value = self.parse_value()?;
+
+            arguments.push(Argument {
+                name,
+                value,
+                span: self.span_from(start),
+            });
+        }
+
+        Ok(self.alloc(arguments))
+    }
+
+    fn parse_value(&mut self) -> Result<Value<'a>, GraphqlParseError> {
+        if let Some(name) = self.peek_name() {
+            let value = match name {
+                "true" => Value::True,
+                "false" => Value::False,
+                "null" => Value::Null,
+                _ => Value::EnumVariant(name),
+            };
+
+            self.consume_name(name);
+
+            return Ok(value);
+        }
+
+        Ok(match self.peek_byte() {
+            Some(b'"') => Value::String(self.parse_string()?),
+            Some(b'$') => Value::Variable(self.parse_variable("a value")?),
+            Some(b'[') => {
+                self.cursor += 1;
+
+                let mut values = BumpVec::new_in(self.bump);
+
+                while !self.consume_byte_if_eq(b']') {
+                    values.push(self.parse_value()?);
+                }
+
+                Value::List(self.alloc(values))
+            }
+            Some(b'{') => {
+                self.cursor += 1;
+
+                let mut fields = BumpVec::new_in(self.bump);
+
+                while !self.consume_byte_if_eq(b'}') {
+                    let name = self.expect_name()?;
+
+                    self.expect_byte(b':')?;
+
+                    fields.push((name, self.parse_value()?));
+                }
+
+                Value::Object(self.alloc(fields))
+            }
+            Some(b'-' | b'0'..=b'9') => self.parse_number()?,
+            _ => return Err(self.unexpected(self.cursor, "a value")),
+        })
+    }
+
+    fn parse_number(&mut self) -> Result<Value<'a>, GraphqlParseError> {
+        let start = self.cursor;
+
+        let invalid_number = |parser: &Self| GraphqlParseError::InvalidNumber {
+            span: Span::new(start, parser.cursor),
+        };
+
+        let value = match self.lex_token()? {
+            Some(Token::IntValue(..)) => self.number_text(start).parse().map(Value::Int).ok(),
+            Some(Token::FloatValue(..)) => self
+                .number_text(start)
+                .parse()
+                .ok()
+                .filter(|float: &f64| float.is_finite())
+                .map(Value::Float),
+            _ => unreachable!("the lexer lexed a number as something other than a number"),
+        };
+
+        value.ok_or_else(|| invalid_number(self))
+    }
+
+    /// The source of the number from `start` to the cursor
+    fn number_text(&self, start: usize) -> &'a str {
+        std::str::from_utf8(&self.buffer[start..self.cursor]).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{parse_query, Bump};
+    use crate::{fmt::print_document, GraphqlParser};
+
+    const SOURCES: &[&str] = &[
+        "{ a }",
+        "query Q($a: Int, $b: Int!, $c: [Int!], $d: [Int]!, $e: [[Int!]]! = [[1]]) { a(b: $b) }",
+        "query { a: b(c: \"d\\u00e9\", e: \"\"\"block\"\"\", f: [1, 2.5, true, null, ENUM]) }",
+        "mutation M @a(b: { c: 1 }) { a { ...F ... on T @skip(if: true) { b } } }",
+        "fragment F on T { a } subscription S { b }",
+        "query query($on: on = on) { type: on(on: [on, null]) }",
+    ];
+
+    fn parse_both(source: &str) -> (crate::Document, crate::Document, Rodeo) {
+        let mut interner = Rodeo::default();
+
+        let document =
+            GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner).unwrap();
+
+        let bump = Bump::new();
+        let arena = parse_query(source.as_bytes(), &bump)
+            .unwrap()
+            .to_interned(&mut interner);
+
+        (document, arena, interner)
+    }
+
+    #[test]
+    fn matches_parser() {
+        for source in SOURCES {
+            let (document, arena, interner) = parse_both(source);
+
+            assert_eq!(
+                print_document(&document, &interner),
+                print_document(&arena, &interner),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn non_null_types_match_parser() {
+        let (document, arena, _) = parse_both(SOURCES[1]);
+
+        let types = |document: &crate::Document| {
+            let operation = document.operations.values().next().unwrap();
+
+            operation
+                .variable_definitions
+                .iter()
+                .map(|variable| variable.ty.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(types(&document), types(&arena));
+    }
+
+    #[test]
+    fn errors_match_parser() {
+        for source in [
+            "{ a",
+            "query Q($a: 1) { a }",
+            "{ a(b: $) }",
+            "{ a(b: 1e400) }",
+            "{ . . . F }",
+            "{ ... on { a } }",
+            "type T { a: Int }",
+        ] {
+            let bump = Bump::new();
+
+            let expected = GraphqlParser::parse_query(source.as_bytes()).unwrap_err();
+            let found = parse_query(source.as_bytes(), &bump).unwrap_err();
+
+            assert_eq!(expected.span(), found.span(), "{}", source);
+        }
+    }
+}
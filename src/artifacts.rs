@@ -0,0 +1,524 @@
+//! Per-operation artifacts in the formats client libraries load at runtime
+//!
+//! Apollo Client's persisted query link sends an operation's hash in place
+//! of its text, so its artifact is the normalized text of the operation and
+//! its SHA-256 hash, as in [`persisted`](crate::persisted). Relay executes
+//! concrete requests: a reader fragment, which reads the operation's fields
+//! from the store, a normalization operation, which writes the response into
+//! it with fragment spreads inlined, and the text sent to the server
+
+use std::fmt;
+
+use lasso::{Rodeo, Spur};
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    ast::{Argument, Directive, Document, Operation, Selection, Type, TypeKind, Value},
+    persisted::{normalize_operation, sha256_hex},
+};
+
+/// Relay identifies operations by name, so they must have one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnonymousOperation;
+
+impl fmt::Display for AnonymousOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "relay artifacts can only be generated for named operations"
+        )
+    }
+}
+
+impl std::error::Error for AnonymousOperation {}
+
+/// The artifact Apollo Client's persisted query link reads for `operation`,
+/// which is part of `document`: its name and kind, its text, together with
+/// the fragments it spreads, and the hash of that text
+pub fn apollo_artifact(document: &Document, operation: &Operation, interner: &Rodeo) -> JsonValue {
+    let query = normalize_operation(document, operation, interner);
+
+    json!({
+        "operationName": operation.name.map(|name| interner.resolve(&name)),
+        "operationType": operation.kind.as_str(),
+        "query": query,
+        "extensions": {
+            "persistedQuery": {
+                "version": 1,
+                "sha256Hash": sha256_hex(&query),
+            },
+        },
+    })
+}
+
+/// The concrete request Relay's runtime executes for `operation`, which is
+/// part of `document`. `document` is expected to be valid against `schema`
+pub fn relay_artifact(
+    schema: &Document,
+    document: &Document,
+    operation: &Operation,
+    interner: &Rodeo,
+) -> Result<JsonValue, AnonymousOperation> {
+    let name = interner.resolve(&operation.name.ok_or(AnonymousOperation)?);
+    let root = schema.root_operation_type_or_default(operation.kind, interner);
+    let text = normalize_operation(document, operation, interner);
+
+    let writer = RelayWriter {
+        schema,
+        document,
+        interner,
+    };
+
+    let mut arguments = operation
+        .variable_definitions
+        .iter()
+        .map(|variable| {
+            json!({
+                "defaultValue": variable
+                    .default
+                    .as_ref()
+                    .and_then(|value| value.to_json(interner).ok()),
+                "kind": "LocalArgument",
+                "name": interner.resolve(&variable.name),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    arguments.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+
+    let (reader, normalization) = match root {
+        Some(root) => (
+            writer.selections(&operation.selection_set, root, false, &mut Vec::new()),
+            writer.selections(&operation.selection_set, root, true, &mut Vec::new()),
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+
+    Ok(json!({
+        "fragment": {
+            "argumentDefinitions": arguments,
+            "kind": "Fragment",
+            "metadata": null,
+            "name": name,
+            "selections": reader,
+            "type": root.map(|root| interner.resolve(&root)),
+            "abstractKey": null,
+        },
+        "kind": "Request",
+        "operation": {
+            "argumentDefinitions": arguments,
+            "kind": "Operation",
+            "name": name,
+            "selections": normalization,
+        },
+        "params": {
+            "cacheID": sha256_hex(&text),
+            "id": null,
+            "metadata": {},
+            "name": name,
+            "operationKind": operation.kind.as_str(),
+            "text": text,
+        },
+    }))
+}
+
+struct RelayWriter<'a> {
+    schema: &'a Document,
+    document: &'a Document,
+    interner: &'a Rodeo,
+}
+
+impl RelayWriter<'_> {
+    fn name(&self, name: Spur) -> &str {
+        self.interner.resolve(&name)
+    }
+
+    /// The nodes for `selection_set`, which is on `parent`. Normalization
+    /// nodes inline fragment spreads, and `spread` holds the fragments being
+    /// inlined, so that cycles terminate
+    fn selections(
+        &self,
+        selection_set: &[Selection],
+        parent: Spur,
+        is_normalization: bool,
+        spread: &mut Vec<Spur>,
+    ) -> Vec<JsonValue> {
+        let mut nodes = Vec::new();
+
+        for selection in selection_set {
+            let directives = match selection {
+                Selection::Field { directives, .. }
+                | Selection::FragmentSpread { directives, .. }
+                | Selection::InlineFragment { directives, .. } => directives,
+            };
+
+            let mut selection_nodes = match selection {
+                Selection::Field {
+                    alias,
+                    name,
+                    arguments,
+                    selection_set,
+                    ..
+                } => {
+                    let ty = self.field_type(parent, *name);
+                    let args = self.arguments(arguments.as_deref().unwrap_or_default());
+                    let storage_key = self.storage_key(*name, &args);
+
+                    let mut node = json!({
+                        "alias": alias.map(|alias| self.name(alias)),
+                        "args": if args.is_empty() { JsonValue::Null } else { args.into() },
+                        "kind": "ScalarField",
+                        "name": self.name(*name),
+                        "storageKey": storage_key,
+                    });
+
+                    if let (Some(selection_set), Some(ty)) = (selection_set, ty) {
+                        let concrete_type = (self.schema.type_kind(ty.name())
+                            == Some(TypeKind::Object))
+                        .then(|| self.name(ty.name()));
+
+                        node["kind"] = "LinkedField".into();
+                        node["concreteType"] = concrete_type.into();
+                        node["plural"] = matches!(ty, Type::List { .. }).into();
+                        node["selections"] = self
+                            .selections(selection_set, ty.name(), is_normalization, spread)
+                            .into();
+                    }
+
+                    vec![node]
+                }
+                Selection::InlineFragment {
+                    on: Some(on),
+                    selection_set,
+                    ..
+                } => vec![self.inline_fragment(
+                    *on,
+                    self.selections(selection_set, *on, is_normalization, spread),
+                )],
+                // without a type condition, the selections apply to the parent
+                Selection::InlineFragment {
+                    on: None,
+                    selection_set,
+                    ..
+                } => self.selections(selection_set, parent, is_normalization, spread),
+                Selection::FragmentSpread { name, .. } if !is_normalization => {
+                    vec![json!({
+                        "args": null,
+                        "kind": "FragmentSpread",
+                        "name": self.name(*name),
+                    })]
+                }
+                Selection::FragmentSpread { name, .. } => match self.document.fragments.get(name) {
+                    Some(fragment) if !spread.contains(name) => {
+                        spread.push(*name);
+                        let selections =
+                            self.selections(&fragment.selection_set, fragment.on, true, spread);
+                        spread.pop();
+
+                        if fragment.on == parent {
+                            selections
+                        } else {
+                            vec![self.inline_fragment(fragment.on, selections)]
+                        }
+                    }
+                    _ => Vec::new(),
+                },
+            };
+
+            for directive in directives {
+                selection_nodes = match self.condition(directive) {
+                    Some(Condition::Always) | None => continue,
+                    Some(Condition::Never) => Vec::new(),
+                    Some(Condition::Variable {
+                        variable,
+                        passing_value,
+                    }) => vec![json!({
+                        "condition": self.name(variable),
+                        "kind": "Condition",
+                        "passingValue": passing_value,
+                        "selections": selection_nodes,
+                    })],
+                };
+            }
+
+            nodes.extend(selection_nodes);
+        }
+
+        nodes
+    }
+
+    fn inline_fragment(&self, on: Spur, selections: Vec<JsonValue>) -> JsonValue {
+        let is_abstract = matches!(
+            self.schema.type_kind(on),
+            Some(TypeKind::Interface | TypeKind::Union)
+        );
+
+        json!({
+            "kind": "InlineFragment",
+            "selections": selections,
+            "type": self.name(on),
+            "abstractKey": is_abstract.then(|| format!("__is{}", self.name(on))),
+        })
+    }
+
+    fn field_type(&self, parent: Spur, name: Spur) -> Option<&Type> {
+        self.schema
+            .fields_of(parent)?
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| &field.ty)
+    }
+
+    /// The argument nodes of a field, ordered by name
+    fn arguments(&self, arguments: &[Argument]) -> Vec<JsonValue> {
+        let mut nodes = arguments
+            .iter()
+            .map(|argument| self.argument(self.name(argument.name), &argument.value))
+            .collect::<Vec<_>>();
+
+        nodes.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+
+        nodes
+    }
+
+    /// A `Literal` node for constant values, and a `Variable` node, or a
+    /// `ListValue` or `ObjectValue` node holding one, otherwise
+    fn argument(&self, name: &str, value: &Value) -> JsonValue {
+        if let Ok(value) = value.to_json(self.interner) {
+            return json!({ "kind": "Literal", "name": name, "value": value });
+        }
+
+        match value {
+            Value::Variable(variable) => json!({
+                "kind": "Variable",
+                "name": name,
+                "variableName": self.name(*variable),
+            }),
+            Value::List(values) => json!({
+                "items": values
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, value)| self.argument(&format!("{}.{}", name, idx), value))
+                    .collect::<Vec<_>>(),
+                "kind": "ListValue",
+                "name": name,
+            }),
+            Value::Object(fields) => {
+                let mut fields = fields
+                    .iter()
+                    .map(|(field, value)| self.argument(self.name(*field), value))
+                    .collect::<Vec<_>>();
+
+                fields.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+
+                json!({ "fields": fields, "kind": "ObjectValue", "name": name })
+            }
+            _ => unreachable!("only variables and values containing them fail to convert"),
+        }
+    }
+
+    /// The key a field with constant arguments is stored under, such as
+    /// `friends(first:10)`, or `None` if any argument depends on a variable
+    fn storage_key(&self, name: Spur, arguments: &[JsonValue]) -> Option<String> {
+        if arguments.is_empty() || arguments.iter().any(|arg| arg["kind"] != "Literal") {
+            return None;
+        }
+
+        let arguments = arguments
+            .iter()
+            .map(|arg| {
+                format!(
+                    "{}:{}",
+                    arg["name"].as_str().unwrap_or_default(),
+                    arg["value"]
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Some(format!("{}({})", self.name(name), arguments.join(",")))
+    }
+
+    /// Whether a `@skip` or `@include` directive keeps its selection
+    fn condition(&self, directive: &Directive) -> Option<Condition> {
+        let passing_value = match self.name(directive.name) {
+            "include" => true,
+            "skip" => false,
+            _ => return None,
+        };
+
+        let argument = directive
+            .arguments
+            .iter()
+            .flatten()
+            .find(|argument| self.name(argument.name) == "if")?;
+
+        Some(match argument.value {
+            Value::Variable(variable) => Condition::Variable {
+                variable,
+                passing_value,
+            },
+            Value::True if passing_value => Condition::Always,
+            Value::False if !passing_value => Condition::Always,
+            _ => Condition::Never,
+        })
+    }
+}
+
+enum Condition {
+    Always,
+    Never,
+    /// The selection is kept when `variable` is `passing_value`
+    Variable {
+        variable: Spur,
+        passing_value: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+    use serde_json::json;
+
+    use super::{apollo_artifact, relay_artifact, AnonymousOperation};
+    use crate::{ast::Document, GraphqlParser};
+
+    const SCHEMA: &str = concat!(
+        "type Query { user(id: ID!): User node(id: ID!): Node } ",
+        "interface Node { id: ID! } ",
+        "type User implements Node { id: ID! name: String friends(first: Int): [User!]! }",
+    );
+
+    fn parse(document: &str) -> (Document, Document, Rodeo) {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let document =
+            GraphqlParser::parse_with_interner(document.as_bytes(), &mut interner).unwrap();
+
+        (schema, document, interner)
+    }
+
+    #[test]
+    fn apollo() {
+        let (_, document, interner) =
+            parse("query User { user(id: 1) { ...F } } fragment F on User { id }");
+        let operation = document.operations.values().next().unwrap();
+
+        let artifact = apollo_artifact(&document, operation, &interner);
+
+        assert_eq!(artifact["operationName"], "User");
+        assert_eq!(artifact["operationType"], "query");
+        assert!(artifact["query"]
+            .as_str()
+            .unwrap()
+            .contains("fragment F on User"));
+        assert_eq!(
+            artifact["extensions"]["persistedQuery"]["sha256Hash"]
+                .as_str()
+                .unwrap()
+                .len(),
+            64
+        );
+    }
+
+    #[test]
+    fn relay() {
+        let (schema, document, interner) = parse(concat!(
+            "query User($id: ID!, $full: Boolean = false) { user(id: $id) { ...F } ",
+            "node(id: 1) { ... on User { name @include(if: $full) } } } ",
+            "fragment F on User { id friends(first: 2) { id } }",
+        ));
+        let operation = document.operations.values().next().unwrap();
+
+        let artifact = relay_artifact(&schema, &document, operation, &interner).unwrap();
+
+        assert_eq!(
+            artifact["fragment"]["argumentDefinitions"],
+            json!([
+                { "defaultValue": false, "kind": "LocalArgument", "name": "full" },
+                { "defaultValue": null, "kind": "LocalArgument", "name": "id" },
+            ])
+        );
+        assert_eq!(
+            artifact["fragment"]["selections"][0]["selections"],
+            json!([{ "args": null, "kind": "FragmentSpread", "name": "F" }])
+        );
+        assert_eq!(
+            artifact["operation"]["selections"],
+            json!([
+                {
+                    "alias": null,
+                    "args": [{ "kind": "Variable", "name": "id", "variableName": "id" }],
+                    "concreteType": "User",
+                    "kind": "LinkedField",
+                    "name": "user",
+                    "plural": false,
+                    "selections": [
+                        {
+                            "alias": null,
+                            "args": null,
+                            "kind": "ScalarField",
+                            "name": "id",
+                            "storageKey": null,
+                        },
+                        {
+                            "alias": null,
+                            "args": [{ "kind": "Literal", "name": "first", "value": 2 }],
+                            "concreteType": "User",
+                            "kind": "LinkedField",
+                            "name": "friends",
+                            "plural": true,
+                            "selections": [{
+                                "alias": null,
+                                "args": null,
+                                "kind": "ScalarField",
+                                "name": "id",
+                                "storageKey": null,
+                            }],
+                            "storageKey": "friends(first:2)",
+                        },
+                    ],
+                    "storageKey": null,
+                },
+                {
+                    "alias": null,
+                    "args": [{ "kind": "Literal", "name": "id", "value": 1 }],
+                    "concreteType": null,
+                    "kind": "LinkedField",
+                    "name": "node",
+                    "plural": false,
+                    "selections": [{
+                        "abstractKey": null,
+                        "kind": "InlineFragment",
+                        "selections": [{
+                            "condition": "full",
+                            "kind": "Condition",
+                            "passingValue": true,
+                            "selections": [{
+                                "alias": null,
+                                "args": null,
+                                "kind": "ScalarField",
+                                "name": "name",
+                                "storageKey": null,
+                            }],
+                        }],
+                        "type": "User",
+                    }],
+                    "storageKey": "node(id:1)",
+                },
+            ])
+        );
+        assert_eq!(artifact["params"]["name"], "User");
+        assert_eq!(artifact["params"]["operationKind"], "query");
+    }
+
+    #[test]
+    fn relay_requires_names() {
+        let (schema, document, interner) = parse("{ user(id: 1) { id } }");
+        let operation = document.operations.values().next().unwrap();
+
+        assert_eq!(
+            relay_artifact(&schema, &document, operation, &interner),
+            Err(AnonymousOperation)
+        );
+    }
+}
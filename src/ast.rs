@@ -1,28 +1,435 @@
-use std::{collections::HashMap, fs};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+};
 
 use lasso::{Rodeo, Spur};
 
-use crate::error::GraphqlParseError;
+use crate::{fmt::print_type, parse::GraphqlParser, validation::Literals};
 
-#[derive(Debug, Clone)]
+/// Scalars every schema has, whether or not they are declared
+pub const BUILT_IN_SCALARS: [&str; 5] = ["Int", "Float", "String", "Boolean", "ID"];
+
+/// Directives every schema has, whether or not they are declared
+pub const BUILT_IN_DIRECTIVES: [&str; 4] = ["skip", "include", "deprecated", "specifiedBy"];
+
+/// The reason given to `@deprecated` when none is provided
+pub const DEFAULT_DEPRECATION_REASON: &str = "No longer supported";
+
+/// The built-in scalars, directives and introspection types, as SDL. See
+/// [`Document::add_built_in_definitions`]
+pub const BUILT_IN_DEFINITIONS: &str = r#"
+scalar Int
+scalar Float
+scalar String
+scalar Boolean
+scalar ID
+
+directive @skip(if: Boolean!) on FIELD | FRAGMENT_SPREAD | INLINE_FRAGMENT
+directive @include(if: Boolean!) on FIELD | FRAGMENT_SPREAD | INLINE_FRAGMENT
+directive @deprecated(
+  reason: String = "No longer supported"
+) on FIELD_DEFINITION | ARGUMENT_DEFINITION | INPUT_FIELD_DEFINITION | ENUM_VALUE
+directive @specifiedBy(url: String!) on SCALAR
+
+type __Schema {
+  description: String
+  types: [__Type!]!
+  queryType: __Type!
+  mutationType: __Type
+  subscriptionType: __Type
+  directives: [__Directive!]!
+}
+
+type __Type {
+  kind: __TypeKind!
+  name: String
+  description: String
+  fields(includeDeprecated: Boolean = false): [__Field!]
+  interfaces: [__Type!]
+  possibleTypes: [__Type!]
+  enumValues(includeDeprecated: Boolean = false): [__EnumValue!]
+  inputFields(includeDeprecated: Boolean = false): [__InputValue!]
+  ofType: __Type
+  specifiedByURL: String
+}
+
+enum __TypeKind {
+  SCALAR
+  OBJECT
+  INTERFACE
+  UNION
+  ENUM
+  INPUT_OBJECT
+  LIST
+  NON_NULL
+}
+
+type __Field {
+  name: String!
+  description: String
+  args(includeDeprecated: Boolean = false): [__InputValue!]!
+  type: __Type!
+  isDeprecated: Boolean!
+  deprecationReason: String
+}
+
+type __InputValue {
+  name: String!
+  description: String
+  type: __Type!
+  defaultValue: String
+  isDeprecated: Boolean!
+  deprecationReason: String
+}
+
+type __EnumValue {
+  name: String!
+  description: String
+  isDeprecated: Boolean!
+  deprecationReason: String
+}
+
+type __Directive {
+  name: String!
+  description: String
+  locations: [__DirectiveLocation!]!
+  args(includeDeprecated: Boolean = false): [__InputValue!]!
+  isRepeatable: Boolean!
+}
+
+enum __DirectiveLocation {
+  QUERY
+  MUTATION
+  SUBSCRIPTION
+  FIELD
+  FRAGMENT_DEFINITION
+  FRAGMENT_SPREAD
+  INLINE_FRAGMENT
+  VARIABLE_DEFINITION
+  SCHEMA
+  SCALAR
+  OBJECT
+  FIELD_DEFINITION
+  ARGUMENT_DEFINITION
+  INTERFACE
+  UNION
+  ENUM
+  ENUM_VALUE
+  INPUT_OBJECT
+  INPUT_FIELD_DEFINITION
+}
+"#;
+
+#[derive(Debug, Clone, Default)]
 pub struct Document {
-    pub(crate) operations: HashMap<(Option<Spur>, OperationKind), Operation>,
-    pub(crate) fragments: HashMap<Spur, Fragment>,
-    pub(crate) input_objects: HashMap<Spur, InputObject>,
-    pub(crate) output_objects: HashMap<Spur, ObjectType>,
-    pub(crate) interfaces: HashMap<Spur, Interface>,
-    pub(crate) scalars: HashMap<Spur, Scalar>,
-    pub(crate) unions: HashMap<Spur, Union>,
-    pub(crate) enums: HashMap<Spur, Enum>,
+    pub schema: Option<SchemaDefinition>,
+    pub operations: HashMap<(Option<Spur>, OperationKind), Operation>,
+    pub fragments: HashMap<Spur, Fragment>,
+    pub input_objects: HashMap<Spur, InputObject>,
+    pub output_objects: HashMap<Spur, ObjectType>,
+    pub interfaces: HashMap<Spur, Interface>,
+    pub scalars: HashMap<Spur, Scalar>,
+    pub unions: HashMap<Spur, Union>,
+    pub enums: HashMap<Spur, Enum>,
+    pub directive_definitions: HashMap<Spur, DirectiveDefinition>,
+    pub type_extensions: Vec<TypeExtension>,
+    pub schema_extensions: Vec<SchemaExtension>,
+    /// Definitions that were replaced by a later definition of the same
+    /// name, in the order they were replaced, which is the order they were
+    /// defined in. Types of different kinds share a name, as do named
+    /// operations of different kinds
+    pub duplicates: Vec<Definition>,
 }
 
+#[derive(Debug)]
 pub enum GraphqlSchemaTypeError {
     InterfaceDne(Spur),
+    /// An object or interface lists itself in its `implements` clause
+    SelfImplementation(Spur),
+    /// `ty` implements `interface`, but not the interface `transitive` that
+    /// `interface` itself implements
+    MissingTransitiveInterface {
+        ty: Spur,
+        interface: Spur,
+        transitive: Spur,
+    },
+    MissingInterfaceField {
+        ty: Spur,
+        interface: Spur,
+        field: Spur,
+    },
+    /// The field's type is not the interface field's type or a subtype of it
+    InvalidInterfaceFieldType {
+        ty: Spur,
+        interface: Spur,
+        field: Spur,
+    },
+    MissingInterfaceArgument {
+        ty: Spur,
+        interface: Spur,
+        field: Spur,
+        argument: Spur,
+    },
+    /// The argument's type differs from the interface argument's type
+    InvalidInterfaceArgumentType {
+        ty: Spur,
+        interface: Spur,
+        field: Spur,
+        argument: Spur,
+    },
+    /// A field has a required argument that its interface field lacks
+    RequiredArgumentNotInInterface {
+        ty: Spur,
+        interface: Spur,
+        field: Spur,
+        argument: Spur,
+    },
+    /// An extension names a type that isn't defined, or is defined as a
+    /// different kind of type
+    ExtendedTypeDne(Spur),
+    /// `extend schema` was used without a `schema` definition to extend
+    ExtendedSchemaDne,
+    /// Two types share a name. `first` is the first definition of the name
+    /// and `second` a later one
+    DuplicateTypeDefinition {
+        name: Spur,
+        first: Span,
+        second: Span,
+    },
+    DuplicateDirectiveDefinition {
+        name: Spur,
+        first: Span,
+        second: Span,
+    },
+    DuplicateSchemaDefinition {
+        first: Span,
+        second: Span,
+    },
+    /// The schema definition declares the root type of an operation kind more
+    /// than once
+    DuplicateRootOperationType {
+        kind: OperationKind,
+        first: Span,
+        second: Span,
+    },
+    /// The default value of an argument or input field can't be coerced to
+    /// its type. `coordinate` is the schema coordinate of the argument or
+    /// field, such as `Query.users(first:)`, and `expected` its type
+    InvalidDefaultValue {
+        coordinate: String,
+        expected: String,
+        span: Span,
+    },
+    UnknownDirective {
+        directive: Spur,
+        span: Span,
+    },
+    /// A directive used in a location its definition doesn't list
+    MisplacedDirective {
+        directive: Spur,
+        location: DirectiveLocation,
+        span: Span,
+    },
+    /// A directive that isn't `repeatable` is used more than once in the
+    /// same location
+    DuplicateDirective {
+        directive: Spur,
+        span: Span,
+    },
+    UnknownDirectiveArgument {
+        directive: Spur,
+        argument: Spur,
+        span: Span,
+    },
+    MissingDirectiveArgument {
+        directive: Spur,
+        argument: Spur,
+        span: Span,
+    },
+    /// An argument given to a directive can't be coerced to its type,
+    /// `expected`
+    InvalidDirectiveArgument {
+        directive: Spur,
+        argument: Spur,
+        expected: String,
+        span: Span,
+    },
+}
+
+impl GraphqlSchemaTypeError {
+    /// A human readable description of this error, resolving names through
+    /// the interner the schema was parsed with
+    pub fn message(&self, interner: &Rodeo) -> String {
+        let name = |name: &Spur| interner.resolve(name);
+
+        match self {
+            Self::InterfaceDne(interface) => format!("unknown interface `{}`", name(interface)),
+            Self::SelfImplementation(ty) => format!("`{}` cannot implement itself", name(ty)),
+            Self::MissingTransitiveInterface {
+                ty,
+                interface,
+                transitive,
+            } => format!(
+                "`{}` must implement `{}`, as it is implemented by `{}`",
+                name(ty),
+                name(transitive),
+                name(interface)
+            ),
+            Self::MissingInterfaceField {
+                ty,
+                interface,
+                field,
+            } => format!(
+                "`{}` is missing field `{}` required by `{}`",
+                name(ty),
+                name(field),
+                name(interface)
+            ),
+            Self::InvalidInterfaceFieldType {
+                ty,
+                interface,
+                field,
+            } => format!(
+                "`{}.{}` has a type incompatible with `{}.{}`",
+                name(ty),
+                name(field),
+                name(interface),
+                name(field)
+            ),
+            Self::MissingInterfaceArgument {
+                ty,
+                interface,
+                field,
+                argument,
+            } => format!(
+                "`{}.{}` is missing argument `{}` required by `{}`",
+                name(ty),
+                name(field),
+                name(argument),
+                name(interface)
+            ),
+            Self::InvalidInterfaceArgumentType {
+                ty,
+                interface,
+                field,
+                argument,
+            } => format!(
+                "argument `{}` of `{}.{}` must have the same type as in `{}`",
+                name(argument),
+                name(ty),
+                name(field),
+                name(interface)
+            ),
+            Self::RequiredArgumentNotInInterface {
+                ty,
+                interface,
+                field,
+                argument,
+            } => format!(
+                "`{}.{}` has required argument `{}` not present on `{}`",
+                name(ty),
+                name(field),
+                name(argument),
+                name(interface)
+            ),
+            Self::ExtendedTypeDne(ty) => format!("cannot extend unknown type `{}`", name(ty)),
+            Self::ExtendedSchemaDne => {
+                "cannot extend schema without a schema definition".to_owned()
+            }
+            Self::DuplicateTypeDefinition { name: ty, .. } => {
+                format!("type `{}` is defined more than once", name(ty))
+            }
+            Self::DuplicateDirectiveDefinition {
+                name: directive, ..
+            } => format!("directive `@{}` is defined more than once", name(directive)),
+            Self::DuplicateSchemaDefinition { .. } => {
+                "the schema is defined more than once".to_owned()
+            }
+            Self::DuplicateRootOperationType { kind, .. } => format!(
+                "the root `{}` type is defined more than once",
+                kind.as_str()
+            ),
+            Self::InvalidDefaultValue {
+                coordinate,
+                expected,
+                ..
+            } => format!(
+                "the default value of `{}` is not a valid `{}`",
+                coordinate, expected
+            ),
+            Self::UnknownDirective { directive, .. } => {
+                format!("unknown directive `@{}`", name(directive))
+            }
+            Self::MisplacedDirective {
+                directive,
+                location,
+                ..
+            } => format!(
+                "directive `@{}` may not be used on {}",
+                name(directive),
+                location.as_str()
+            ),
+            Self::DuplicateDirective { directive, .. } => format!(
+                "directive `@{}` is not repeatable, but is used more than once",
+                name(directive)
+            ),
+            Self::UnknownDirectiveArgument {
+                directive,
+                argument,
+                ..
+            } => format!(
+                "`@{}` has no argument `{}`",
+                name(directive),
+                name(argument)
+            ),
+            Self::MissingDirectiveArgument {
+                directive,
+                argument,
+                ..
+            } => format!(
+                "`@{}` requires argument `{}`",
+                name(directive),
+                name(argument)
+            ),
+            Self::InvalidDirectiveArgument {
+                directive,
+                argument,
+                expected,
+                ..
+            } => format!(
+                "argument `{}` of `@{}` expects a value of type `{}`",
+                name(argument),
+                name(directive),
+                expected
+            ),
+        }
+    }
+
+    /// Where the error is in the source, for errors about a single definition
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::DuplicateTypeDefinition { second, .. }
+            | Self::DuplicateDirectiveDefinition { second, .. }
+            | Self::DuplicateSchemaDefinition { second, .. }
+            | Self::DuplicateRootOperationType { second, .. } => Some(*second),
+            Self::InvalidDefaultValue { span, .. }
+            | Self::UnknownDirective { span, .. }
+            | Self::MisplacedDirective { span, .. }
+            | Self::DuplicateDirective { span, .. }
+            | Self::UnknownDirectiveArgument { span, .. }
+            | Self::MissingDirectiveArgument { span, .. }
+            | Self::InvalidDirectiveArgument { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
 }
 
 impl Document {
     pub fn new() -> Self {
         Self {
+            schema: None,
             operations: HashMap::new(),
             fragments: HashMap::new(),
             input_objects: HashMap::new(),
@@ -31,107 +438,1197 @@ impl Document {
             scalars: HashMap::new(),
             unions: HashMap::new(),
             enums: HashMap::new(),
+            directive_definitions: HashMap::new(),
+            type_extensions: Vec::new(),
+            schema_extensions: Vec::new(),
+            duplicates: Vec::new(),
+        }
+    }
+
+    /// The kind of the type named `name`, if it is declared in this document.
+    /// Built-in scalars are only found if they are explicitly declared, or
+    /// added with [`Document::add_built_in_definitions`]
+    pub fn type_kind(&self, name: Spur) -> Option<TypeKind> {
+        Some(if self.scalars.contains_key(&name) {
+            TypeKind::Scalar
+        } else if self.output_objects.contains_key(&name) {
+            TypeKind::Object
+        } else if self.interfaces.contains_key(&name) {
+            TypeKind::Interface
+        } else if self.unions.contains_key(&name) {
+            TypeKind::Union
+        } else if self.enums.contains_key(&name) {
+            TypeKind::Enum
+        } else if self.input_objects.contains_key(&name) {
+            TypeKind::InputObject
+        } else {
+            return None;
+        })
+    }
+
+    /// Add a definition to this document, replacing any existing definition
+    /// of the same name. The replaced definition is kept in `duplicates`
+    pub fn add_definition(&mut self, definition: Definition) {
+        let previous = match definition {
+            Definition::Schema(schema) => self.schema.replace(schema).map(Definition::Schema),
+            Definition::Operation(operation) => {
+                let previous = match operation.name {
+                    Some(name) => [
+                        OperationKind::Query,
+                        OperationKind::Mutation,
+                        OperationKind::Subscription,
+                    ]
+                    .into_iter()
+                    .find_map(|kind| self.operations.remove(&(Some(name), kind))),
+                    None => self.operations.remove(&(None, operation.kind)),
+                };
+
+                self.operations
+                    .insert((operation.name, operation.kind), operation);
+
+                previous.map(Definition::Operation)
+            }
+            Definition::Fragment(fragment) => self
+                .fragments
+                .insert(fragment.name, fragment)
+                .map(Definition::Fragment),
+            Definition::TypeDecl(ty) => {
+                let previous = self.remove_type(ty.name());
+
+                match ty {
+                    TypeDefinition::Scalar(scalar) => {
+                        self.scalars.insert(scalar.name, scalar);
+                    }
+                    TypeDefinition::Object(object) => {
+                        self.output_objects.insert(object.name, object);
+                    }
+                    TypeDefinition::Interface(interface) => {
+                        self.interfaces.insert(interface.name, interface);
+                    }
+                    TypeDefinition::Union(union) => {
+                        self.unions.insert(union.name, union);
+                    }
+                    TypeDefinition::Enum(enum_def) => {
+                        self.enums.insert(enum_def.name, enum_def);
+                    }
+                    TypeDefinition::InputObject(input) => {
+                        self.input_objects.insert(input.name, input);
+                    }
+                }
+
+                previous.map(Definition::TypeDecl)
+            }
+            Definition::Directive(directive) => self
+                .directive_definitions
+                .insert(directive.name, directive)
+                .map(Definition::Directive),
+            Definition::TypeExtension(extension) => {
+                self.type_extensions.push(extension);
+                None
+            }
+            Definition::SchemaExtension(extension) => {
+                self.schema_extensions.push(extension);
+                None
+            }
+        };
+
+        self.duplicates.extend(previous);
+    }
+
+    /// Add the built-in scalars, directives and introspection types, so that
+    /// they can be resolved like any other definition. Definitions already in
+    /// this document are kept, and the built-in definition of the same name
+    /// is put in `duplicates`, to be reported by [`Document::validate`].
+    /// Adding the built-ins again does nothing
+    ///
+    /// The spans of the added definitions point into [`BUILT_IN_DEFINITIONS`]
+    pub fn add_built_in_definitions(&mut self, interner: &mut Rodeo) {
+        // names starting with `__` are reserved, so only the built-ins define
+        // the introspection types
+        if interner
+            .get("__Schema")
+            .is_some_and(|name| self.type_kind(name).is_some())
+        {
+            return;
+        }
+
+        let built_ins =
+            GraphqlParser::parse_schema_with_interner(BUILT_IN_DEFINITIONS.as_bytes(), interner)
+                .expect("built-in definitions are valid");
+
+        let types = built_ins
+            .scalars
+            .into_values()
+            .map(TypeDefinition::Scalar)
+            .chain(
+                built_ins
+                    .output_objects
+                    .into_values()
+                    .map(TypeDefinition::Object),
+            )
+            .chain(built_ins.enums.into_values().map(TypeDefinition::Enum));
+
+        for ty in types {
+            if self.type_kind(ty.name()).is_none() {
+                self.add_definition(Definition::TypeDecl(ty));
+            } else {
+                self.duplicates.push(Definition::TypeDecl(ty));
+            }
+        }
+
+        for (name, directive) in built_ins.directive_definitions {
+            match self.directive_definitions.entry(name) {
+                Entry::Vacant(entry) => {
+                    entry.insert(directive);
+                }
+                Entry::Occupied(..) => self.duplicates.push(Definition::Directive(directive)),
+            }
         }
     }
 
+    /// Remove the type definition named `name`, of any kind
+    fn remove_type(&mut self, name: Spur) -> Option<TypeDefinition> {
+        Some(match self.type_kind(name)? {
+            TypeKind::Scalar => TypeDefinition::Scalar(self.scalars.remove(&name)?),
+            TypeKind::Object => TypeDefinition::Object(self.output_objects.remove(&name)?),
+            TypeKind::Interface => TypeDefinition::Interface(self.interfaces.remove(&name)?),
+            TypeKind::Union => TypeDefinition::Union(self.unions.remove(&name)?),
+            TypeKind::Enum => TypeDefinition::Enum(self.enums.remove(&name)?),
+            TypeKind::InputObject => TypeDefinition::InputObject(self.input_objects.remove(&name)?),
+        })
+    }
+
+    /// A document containing only the operation named `name` and the
+    /// fragments it spreads. With no name, the document must contain a single
+    /// operation, as when executing a request without an operation name
+    pub fn extract_operation(&self, name: Option<&str>, interner: &Rodeo) -> Option<Document> {
+        let operation = match name {
+            Some(name) => {
+                let name = interner.get(name)?;
+
+                [
+                    OperationKind::Query,
+                    OperationKind::Mutation,
+                    OperationKind::Subscription,
+                ]
+                .into_iter()
+                .find_map(|kind| self.operations.get(&(Some(name), kind)))?
+            }
+            None if self.operations.len() == 1 => self.operations.values().next()?,
+            None => return None,
+        };
+
+        Some(self.extract(operation))
+    }
+
+    /// A hash of the canonical form of this document, which is the same for
+    /// documents that differ only in ways that don't change their meaning.
+    /// The hash is stable across runs, platforms and versions of Rust
+    ///
+    /// See [`crate::normalize`]
+    pub fn canonical_hash(&self, interner: &Rodeo) -> u64 {
+        // 64-bit FNV-1a
+        crate::normalize::canonical_text(self, interner)
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+            })
+    }
+
+    /// A document containing only `operation` and the fragments of this
+    /// document it spreads, directly or through other fragments. Spreads of
+    /// undefined fragments are left for validation to report
+    pub fn extract(&self, operation: &Operation) -> Document {
+        let mut document = Document::new();
+
+        let mut pending = Vec::new();
+        collect_fragment_spreads(&operation.selection_set, &mut pending);
+
+        while let Some(name) = pending.pop() {
+            if document.fragments.contains_key(&name) {
+                continue;
+            }
+
+            if let Some(fragment) = self.fragments.get(&name) {
+                collect_fragment_spreads(&fragment.selection_set, &mut pending);
+                document.fragments.insert(name, fragment.clone());
+            }
+        }
+
+        document
+            .operations
+            .insert((operation.name, operation.kind), operation.clone());
+
+        document
+    }
+
+    /// Every type definition in this document, in no particular order
+    pub fn types(&self) -> impl Iterator<Item = TypeDefinitionRef<'_>> {
+        self.scalars
+            .values()
+            .map(TypeDefinitionRef::Scalar)
+            .chain(self.output_objects.values().map(TypeDefinitionRef::Object))
+            .chain(self.interfaces.values().map(TypeDefinitionRef::Interface))
+            .chain(self.unions.values().map(TypeDefinitionRef::Union))
+            .chain(self.enums.values().map(TypeDefinitionRef::Enum))
+            .chain(
+                self.input_objects
+                    .values()
+                    .map(TypeDefinitionRef::InputObject),
+            )
+    }
+
+    /// The type definition named `name`
+    pub fn get_type(&self, name: Spur) -> Option<TypeDefinitionRef<'_>> {
+        Some(match self.type_kind(name)? {
+            TypeKind::Scalar => TypeDefinitionRef::Scalar(&self.scalars[&name]),
+            TypeKind::Object => TypeDefinitionRef::Object(&self.output_objects[&name]),
+            TypeKind::Interface => TypeDefinitionRef::Interface(&self.interfaces[&name]),
+            TypeKind::Union => TypeDefinitionRef::Union(&self.unions[&name]),
+            TypeKind::Enum => TypeDefinitionRef::Enum(&self.enums[&name]),
+            TypeKind::InputObject => TypeDefinitionRef::InputObject(&self.input_objects[&name]),
+        })
+    }
+
+    /// The type definition named `name`, looked up through the interner the
+    /// document was parsed with
+    pub fn get_type_by_name(&self, name: &str, interner: &Rodeo) -> Option<TypeDefinitionRef<'_>> {
+        self.get_type(interner.get(name)?)
+    }
+
+    /// The fields of the object or interface named `name`
+    pub fn fields_of(&self, name: Spur) -> Option<&[FieldDefinition]> {
+        if let Some(obj) = self.output_objects.get(&name) {
+            return Some(obj.fields.as_deref().unwrap_or_default());
+        }
+
+        self.interfaces
+            .get(&name)
+            .map(|interface| interface.fields.as_slice())
+    }
+
+    /// The object types a value of the composite type `name` may have at
+    /// runtime. Empty if `name` isn't a composite type
+    pub fn possible_types(&self, name: Spur) -> Vec<Spur> {
+        match self.type_kind(name) {
+            Some(TypeKind::Object) => vec![name],
+            Some(TypeKind::Interface) => self
+                .output_objects
+                .values()
+                .filter(|obj| obj.implements.iter().any(|ty| ty.0 == name))
+                .map(|obj| obj.name)
+                .collect(),
+            Some(TypeKind::Union) => self.unions[&name].types.iter().map(|ty| ty.0).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The type declared for the given root operation in the `schema`
+    /// definition, if there is one
+    pub fn root_operation_type(&self, kind: OperationKind) -> Option<Spur> {
+        self.schema
+            .as_ref()
+            .and_then(|schema| schema.operation_type(kind))
+            .map(|ty| ty.0)
+    }
+
+    /// Like [`Document::root_operation_type`], but falling back to the
+    /// conventional type names when there is no `schema` definition
+    pub fn root_operation_type_or_default(
+        &self,
+        kind: OperationKind,
+        interner: &Rodeo,
+    ) -> Option<Spur> {
+        if self.schema.is_some() {
+            return self.root_operation_type(kind);
+        }
+
+        let name = match kind {
+            OperationKind::Query => "Query",
+            OperationKind::Mutation => "Mutation",
+            OperationKind::Subscription => "Subscription",
+        };
+
+        interner
+            .get(name)
+            .filter(|name| self.output_objects.contains_key(name))
+    }
+
+    /// Merge every extension into the definition it extends, leaving
+    /// `type_extensions` and `schema_extensions` empty
+    pub fn apply_extensions(&mut self) -> Vec<GraphqlSchemaTypeError> {
+        let mut errors = Vec::new();
+
+        for extension in std::mem::take(&mut self.schema_extensions) {
+            match &mut self.schema {
+                Some(schema) => {
+                    schema.directives.extend(extension.directives);
+                    schema.operation_types.extend(extension.operation_types);
+                }
+                None => errors.push(GraphqlSchemaTypeError::ExtendedSchemaDne),
+            }
+        }
+
+        for extension in std::mem::take(&mut self.type_extensions) {
+            let name = extension.name();
+
+            let applied = match extension {
+                TypeExtension::Scalar(ext) => self
+                    .scalars
+                    .get_mut(&name)
+                    .map(|scalar| scalar.directives.extend(ext.directives)),
+                TypeExtension::Object(ext) => self.output_objects.get_mut(&name).map(|obj| {
+                    obj.implements.extend(ext.implements);
+                    obj.directives.extend(ext.directives);
+
+                    if let Some(fields) = ext.fields {
+                        obj.fields.get_or_insert_with(Vec::new).extend(fields);
+                    }
+                }),
+                TypeExtension::Interface(ext) => self.interfaces.get_mut(&name).map(|interface| {
+                    interface.implements.extend(ext.implements);
+                    interface.directives.extend(ext.directives);
+                    interface.fields.extend(ext.fields);
+                }),
+                TypeExtension::Union(ext) => self.unions.get_mut(&name).map(|union| {
+                    union.directives.extend(ext.directives);
+                    union.types.extend(ext.types);
+                }),
+                TypeExtension::Enum(ext) => self.enums.get_mut(&name).map(|enum_def| {
+                    enum_def.directives.extend(ext.directives);
+                    enum_def.variants.extend(ext.variants);
+                }),
+                TypeExtension::InputObject(ext) => self.input_objects.get_mut(&name).map(|input| {
+                    input.directives.extend(ext.directives);
+
+                    if let Some(fields) = ext.fields {
+                        input.fields.get_or_insert_with(Vec::new).extend(fields);
+                    }
+                }),
+            };
+
+            if applied.is_none() {
+                errors.push(GraphqlSchemaTypeError::ExtendedTypeDne(name));
+            }
+        }
+
+        errors
+    }
+
     pub fn validate(&self) -> Vec<GraphqlSchemaTypeError> {
         let mut errors = Vec::new();
 
+        let types = self
+            .duplicates
+            .iter()
+            .filter_map(|duplicate| match duplicate {
+                Definition::TypeDecl(ty) => Some((ty.name(), ty.span())),
+                _ => None,
+            });
+
+        for (name, first, second) in
+            pair_duplicates(types, |name| self.get_type(name).map(|kept| kept.span()))
+        {
+            errors.push(GraphqlSchemaTypeError::DuplicateTypeDefinition {
+                name,
+                first,
+                second,
+            });
+        }
+
+        let directives = self
+            .duplicates
+            .iter()
+            .filter_map(|duplicate| match duplicate {
+                Definition::Directive(directive) => Some((directive.name, directive.span)),
+                _ => None,
+            });
+
+        for (name, first, second) in pair_duplicates(directives, |name| {
+            self.directive_definitions.get(&name).map(|kept| kept.span)
+        }) {
+            errors.push(GraphqlSchemaTypeError::DuplicateDirectiveDefinition {
+                name,
+                first,
+                second,
+            });
+        }
+
+        let schemas = self
+            .duplicates
+            .iter()
+            .filter_map(|duplicate| match duplicate {
+                Definition::Schema(schema) => Some(((), schema.span)),
+                _ => None,
+            });
+
+        for ((), first, second) in
+            pair_duplicates(schemas, |()| self.schema.as_ref().map(|kept| kept.span))
+        {
+            errors.push(GraphqlSchemaTypeError::DuplicateSchemaDefinition { first, second });
+        }
+
+        if let Some(schema) = &self.schema {
+            for (idx, operation_type) in schema.operation_types.iter().enumerate() {
+                let first = schema.operation_types[..idx]
+                    .iter()
+                    .find(|first| first.kind == operation_type.kind);
+
+                if let Some(first) = first {
+                    errors.push(GraphqlSchemaTypeError::DuplicateRootOperationType {
+                        kind: operation_type.kind,
+                        first: first.span,
+                        second: operation_type.span,
+                    });
+                }
+            }
+        }
+
         for obj in self.output_objects.values() {
-            for interface_name in &obj.implements {
-                let interface = match self.interfaces.get(&interface_name.0) {
-                    Some(i) => i,
+            self.validate_implementations(
+                obj.name,
+                &obj.implements,
+                obj.fields.as_deref().unwrap_or_default(),
+                &mut errors,
+            );
+        }
+
+        for interface in self.interfaces.values() {
+            self.validate_implementations(
+                interface.name,
+                &interface.implements,
+                &interface.fields,
+                &mut errors,
+            );
+        }
+
+        errors
+    }
+
+    /// Check that the default values of field arguments, directive arguments
+    /// and input object fields can be coerced to their types, including the
+    /// items of lists and the fields of input objects. Default values must be
+    /// constant, so may not contain variables
+    pub fn validate_default_values(&self, interner: &Rodeo) -> Vec<GraphqlSchemaTypeError> {
+        let literals = Literals {
+            schema: self,
+            interner,
+            scalars: None,
+            allow_variables: false,
+        };
+
+        let name = |name: &Spur| interner.resolve(name);
+        let mut errors = Vec::new();
+
+        let mut check = |coordinate: &dyn Fn() -> String, definition: &InputObjectField| {
+            let Some(default) = &definition.default else {
+                return;
+            };
+
+            if !literals.is_valid(default, &definition.ty) {
+                errors.push(GraphqlSchemaTypeError::InvalidDefaultValue {
+                    coordinate: coordinate(),
+                    expected: print_type(&definition.ty, interner),
+                    span: definition.span,
+                });
+            }
+        };
+
+        for ty in self.types() {
+            let fields = match ty {
+                TypeDefinitionRef::Object(object) => object.fields.as_deref().unwrap_or_default(),
+                TypeDefinitionRef::Interface(interface) => &interface.fields,
+                TypeDefinitionRef::InputObject(input) => {
+                    for field in input.fields.iter().flatten() {
+                        check(
+                            &|| format!("{}.{}", name(&input.name), name(&field.name)),
+                            field,
+                        );
+                    }
+
+                    continue;
+                }
+                _ => continue,
+            };
+
+            for field in fields {
+                for argument in field.arguments.iter().flatten() {
+                    let coordinate = || {
+                        format!(
+                            "{}.{}({}:)",
+                            name(&ty.name()),
+                            name(&field.name),
+                            name(&argument.name)
+                        )
+                    };
+
+                    check(&coordinate, argument);
+                }
+            }
+        }
+
+        for directive in self.directive_definitions.values() {
+            for argument in directive.arguments.iter().flatten() {
+                let coordinate =
+                    || format!("@{}({}:)", name(&directive.name), name(&argument.name));
+
+                check(&coordinate, argument);
+            }
+        }
+
+        // errors without spans, or at the same position, are ordered by message
+        errors.sort_by_cached_key(|error| {
+            (error.span().map(|span| span.start), error.message(interner))
+        });
+
+        errors
+    }
+
+    /// Check the directives applied throughout the schema: that they're
+    /// defined, used only in the locations they're defined for, used at
+    /// most once per location unless `repeatable`, and given valid arguments
+    ///
+    /// Built-in directives that the schema doesn't define are only checked
+    /// for their location and repetition
+    pub fn validate_directive_usages(&self, interner: &Rodeo) -> Vec<GraphqlSchemaTypeError> {
+        let mut errors = Vec::new();
+
+        let mut check = |directives: &[Directive], location: DirectiveLocation| {
+            self.check_directives(directives, location, interner, &mut errors);
+        };
+
+        if let Some(schema) = &self.schema {
+            check(&schema.directives, DirectiveLocation::Schema);
+        }
+
+        let mut check_fields = |fields: &[FieldDefinition]| {
+            for field in fields {
+                check(&field.directives, DirectiveLocation::FieldDefinition);
+
+                for argument in field.arguments.iter().flatten() {
+                    check(&argument.directives, DirectiveLocation::ArgumentDefinition);
+                }
+            }
+        };
+
+        for ty in self.types() {
+            match ty {
+                TypeDefinitionRef::Object(object) => {
+                    check_fields(object.fields.as_deref().unwrap_or_default())
+                }
+                TypeDefinitionRef::Interface(interface) => check_fields(&interface.fields),
+                _ => {}
+            }
+        }
+
+        for ty in self.types() {
+            let location = match ty.kind() {
+                TypeKind::Scalar => DirectiveLocation::Scalar,
+                TypeKind::Object => DirectiveLocation::Object,
+                TypeKind::Interface => DirectiveLocation::Interface,
+                TypeKind::Union => DirectiveLocation::Union,
+                TypeKind::Enum => DirectiveLocation::Enum,
+                TypeKind::InputObject => DirectiveLocation::InputObject,
+            };
+
+            check(ty.directives(), location);
+
+            match ty {
+                TypeDefinitionRef::Enum(enum_) => {
+                    for variant in &enum_.variants {
+                        check(&variant.directives, DirectiveLocation::EnumValue);
+                    }
+                }
+                TypeDefinitionRef::InputObject(input) => {
+                    for field in input.fields.iter().flatten() {
+                        check(&field.directives, DirectiveLocation::InputFieldDefinition);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for directive in self.directive_definitions.values() {
+            for argument in directive.arguments.iter().flatten() {
+                check(&argument.directives, DirectiveLocation::ArgumentDefinition);
+            }
+        }
+
+        // errors without spans, or at the same position, are ordered by message
+        errors.sort_by_cached_key(|error| {
+            (error.span().map(|span| span.start), error.message(interner))
+        });
+
+        errors
+    }
+
+    fn check_directives(
+        &self,
+        directives: &[Directive],
+        location: DirectiveLocation,
+        interner: &Rodeo,
+        errors: &mut Vec<GraphqlSchemaTypeError>,
+    ) {
+        for (idx, directive) in directives.iter().enumerate() {
+            let definition = self.directive_definitions.get(&directive.name);
+
+            let placement = match definition {
+                Some(definition) => (&*definition.locations, definition.repeatable),
+                None => match built_in_directive_locations(interner.resolve(&directive.name)) {
+                    Some(locations) => (locations, false),
                     None => {
-                        errors.push(GraphqlSchemaTypeError::InterfaceDne(interface_name.0));
+                        errors.push(GraphqlSchemaTypeError::UnknownDirective {
+                            directive: directive.name,
+                            span: directive.span,
+                        });
                         continue;
                     }
-                };
+                },
+            };
 
-                errors.append(&mut obj.validate_implements_fields(&interface.fields));
+            if !placement.0.contains(&location) {
+                errors.push(GraphqlSchemaTypeError::MisplacedDirective {
+                    directive: directive.name,
+                    location,
+                    span: directive.span,
+                });
+            }
+
+            let is_repeated = directives[..idx]
+                .iter()
+                .any(|other| other.name == directive.name);
+
+            if is_repeated && !placement.1 {
+                errors.push(GraphqlSchemaTypeError::DuplicateDirective {
+                    directive: directive.name,
+                    span: directive.span,
+                });
+            }
+
+            if let Some(definition) = definition {
+                self.check_directive_arguments(directive, definition, interner, errors);
+            }
+        }
+    }
+
+    fn check_directive_arguments(
+        &self,
+        directive: &Directive,
+        definition: &DirectiveDefinition,
+        interner: &Rodeo,
+        errors: &mut Vec<GraphqlSchemaTypeError>,
+    ) {
+        let literals = Literals {
+            schema: self,
+            interner,
+            scalars: None,
+            allow_variables: false,
+        };
+
+        let arguments = directive.arguments.as_deref().unwrap_or_default();
+        let definitions = definition.arguments.as_deref().unwrap_or_default();
+
+        for argument in arguments {
+            match definitions.iter().find(|def| def.name == argument.name) {
+                Some(def) if !literals.is_valid(&argument.value, &def.ty) => {
+                    errors.push(GraphqlSchemaTypeError::InvalidDirectiveArgument {
+                        directive: directive.name,
+                        argument: argument.name,
+                        expected: print_type(&def.ty, interner),
+                        span: argument.span,
+                    });
+                }
+                Some(..) => {}
+                None => errors.push(GraphqlSchemaTypeError::UnknownDirectiveArgument {
+                    directive: directive.name,
+                    argument: argument.name,
+                    span: argument.span,
+                }),
+            }
+        }
+
+        for def in definitions {
+            let is_required = !def.ty.is_nullable() && def.default.is_none();
+
+            if is_required && !arguments.iter().any(|argument| argument.name == def.name) {
+                errors.push(GraphqlSchemaTypeError::MissingDirectiveArgument {
+                    directive: directive.name,
+                    argument: def.name,
+                    span: directive.span,
+                });
+            }
+        }
+    }
+
+    /// See https://spec.graphql.org/October2021/#IsValidImplementation()
+    fn validate_implementations(
+        &self,
+        name: Spur,
+        implements: &[NamedType],
+        fields: &[FieldDefinition],
+        errors: &mut Vec<GraphqlSchemaTypeError>,
+    ) {
+        for interface_name in implements {
+            if interface_name.0 == name {
+                errors.push(GraphqlSchemaTypeError::SelfImplementation(name));
+                continue;
+            }
+
+            let interface = match self.interfaces.get(&interface_name.0) {
+                Some(i) => i,
+                None => {
+                    errors.push(GraphqlSchemaTypeError::InterfaceDne(interface_name.0));
+                    continue;
+                }
+            };
+
+            for transitive in &interface.implements {
+                if transitive.0 != name && !implements.contains(transitive) {
+                    errors.push(GraphqlSchemaTypeError::MissingTransitiveInterface {
+                        ty: name,
+                        interface: interface.name,
+                        transitive: transitive.0,
+                    });
+                }
+            }
+
+            errors.append(&mut self.validate_implements_fields(name, fields, interface));
+        }
+    }
+
+    /// Check that the object or interface `name`, with the given `fields`,
+    /// correctly implements every field of `interface`
+    pub fn validate_implements_fields(
+        &self,
+        name: Spur,
+        fields: &[FieldDefinition],
+        interface: &Interface,
+    ) -> Vec<GraphqlSchemaTypeError> {
+        let mut errors = Vec::new();
+
+        for interface_field in &interface.fields {
+            let field = match fields.iter().find(|f| f.name == interface_field.name) {
+                Some(field) => field,
+                None => {
+                    errors.push(GraphqlSchemaTypeError::MissingInterfaceField {
+                        ty: name,
+                        interface: interface.name,
+                        field: interface_field.name,
+                    });
+                    continue;
+                }
+            };
+
+            if !self.is_valid_implementation_field_type(&field.ty, &interface_field.ty) {
+                errors.push(GraphqlSchemaTypeError::InvalidInterfaceFieldType {
+                    ty: name,
+                    interface: interface.name,
+                    field: field.name,
+                });
+            }
+
+            let arguments = field.arguments.as_deref().unwrap_or_default();
+            let interface_arguments = interface_field.arguments.as_deref().unwrap_or_default();
+
+            for interface_argument in interface_arguments {
+                match arguments.iter().find(|a| a.name == interface_argument.name) {
+                    Some(argument) if argument.ty != interface_argument.ty => {
+                        errors.push(GraphqlSchemaTypeError::InvalidInterfaceArgumentType {
+                            ty: name,
+                            interface: interface.name,
+                            field: field.name,
+                            argument: argument.name,
+                        })
+                    }
+                    Some(..) => {}
+                    None => errors.push(GraphqlSchemaTypeError::MissingInterfaceArgument {
+                        ty: name,
+                        interface: interface.name,
+                        field: field.name,
+                        argument: interface_argument.name,
+                    }),
+                }
+            }
+
+            for argument in arguments {
+                let is_required = !argument.ty.is_nullable() && argument.default.is_none();
+
+                if is_required && !interface_arguments.iter().any(|a| a.name == argument.name) {
+                    errors.push(GraphqlSchemaTypeError::RequiredArgumentNotInInterface {
+                        ty: name,
+                        interface: interface.name,
+                        field: field.name,
+                        argument: argument.name,
+                    });
+                }
             }
         }
 
         errors
     }
+
+    /// Whether a field of type `ty` may implement an interface field of type
+    /// `interface_ty`, i.e. whether `ty` is covariant with it
+    ///
+    /// See https://spec.graphql.org/October2021/#IsValidImplementationFieldType()
+    fn is_valid_implementation_field_type(&self, ty: &Type, interface_ty: &Type) -> bool {
+        if ty.is_nullable() && !interface_ty.is_nullable() {
+            return false;
+        }
+
+        match (ty, interface_ty) {
+            (
+                Type::List { base, .. },
+                Type::List {
+                    base: interface_base,
+                    ..
+                },
+            ) => self.is_valid_implementation_field_type(base, interface_base),
+            (
+                Type::Named { name, .. },
+                Type::Named {
+                    name: interface_name,
+                    ..
+                },
+            ) => {
+                if name == interface_name {
+                    return true;
+                }
+
+                if let Some(union) = self.unions.get(interface_name) {
+                    return union.types.iter().any(|member| member.0 == *name);
+                }
+
+                let implements = match self.output_objects.get(name) {
+                    Some(obj) => &obj.implements,
+                    None => match self.interfaces.get(name) {
+                        Some(interface) => &interface.implements,
+                        None => return false,
+                    },
+                };
+
+                implements.iter().any(|ty| ty.0 == *interface_name)
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Definition {
+    Schema(SchemaDefinition),
     Operation(Operation),
     Fragment(Fragment),
     TypeDecl(TypeDefinition),
-    TypeExtension,
+    Directive(DirectiveDefinition),
+    TypeExtension(TypeExtension),
+    SchemaExtension(SchemaExtension),
+}
+
+impl Definition {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Schema(schema) => schema.span,
+            Self::Operation(operation) => operation.span,
+            Self::Fragment(fragment) => fragment.span,
+            Self::TypeDecl(ty) => ty.span(),
+            Self::Directive(directive) => directive.span,
+            Self::TypeExtension(extension) => extension.span(),
+            Self::SchemaExtension(extension) => extension.span,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct FieldDefinition {
-    pub(crate) description: Option<Spur>,
-    pub(crate) name: Spur,
-    pub(crate) ty: Type,
-    pub(crate) arguments: Option<Vec<InputObjectField>>,
-    pub(crate) directives: Vec<Directive>,
+    pub description: Option<Spur>,
+    pub name: Spur,
+    pub ty: Type,
+    pub ty_span: Span,
+    pub arguments: Option<Vec<InputObjectField>>,
+    pub directives: Vec<Directive>,
+    pub span: Span,
+}
+
+impl FieldDefinition {
+    /// The reason this field is deprecated, or `None` if it isn't
+    pub fn deprecation<'a>(&self, interner: &'a Rodeo) -> Option<&'a str> {
+        deprecation(&self.directives, interner)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Argument {
-    pub(crate) name: Spur,
-    pub(crate) value: Value,
+    pub name: Spur,
+    pub value: Value,
+    pub value_span: ValueSpan,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Named { name: Spur, nullable: bool },
+    List { base: Box<Self>, nullable: bool },
+}
+
+impl Type {
+    /// The name of the innermost type, with any list wrappers removed
+    pub fn name(&self) -> Spur {
+        match self {
+            Self::Named { name, .. } => *name,
+            Self::List { base, .. } => base.name(),
+        }
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        match self {
+            Self::Named { nullable, .. } | Self::List { nullable, .. } => *nullable,
+        }
+    }
+
+    pub fn set_nonnullable(&mut self) {
+        match self {
+            Self::Named { nullable, .. } | Self::List { nullable, .. } => *nullable = false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectType {
+    pub implements: Vec<NamedType>,
+    pub description: Option<Spur>,
+    pub name: Spur,
+    pub directives: Vec<Directive>,
+    pub fields: Option<Vec<FieldDefinition>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeKind {
+    Scalar,
+    Object,
+    Interface,
+    Union,
+    Enum,
+    InputObject,
+}
+
+impl TypeKind {
+    /// The name of the kind, as used by `__TypeKind` in introspection
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Scalar => "SCALAR",
+            Self::Object => "OBJECT",
+            Self::Interface => "INTERFACE",
+            Self::Union => "UNION",
+            Self::Enum => "ENUM",
+            Self::InputObject => "INPUT_OBJECT",
+        }
+    }
+
+    /// Object, interface and union types, which require a selection set
+    pub fn is_composite(&self) -> bool {
+        matches!(self, Self::Object | Self::Interface | Self::Union)
+    }
+
+    /// Types that may be used for arguments and variables
+    pub fn is_input(&self) -> bool {
+        matches!(self, Self::Scalar | Self::Enum | Self::InputObject)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeDefinition {
+    Scalar(Scalar),
+    Object(ObjectType),
+    Interface(Interface),
+    Union(Union),
+    Enum(Enum),
+    InputObject(InputObject),
+}
+
+impl TypeDefinition {
+    pub fn name(&self) -> Spur {
+        match self {
+            Self::Scalar(Scalar { name, .. })
+            | Self::Object(ObjectType { name, .. })
+            | Self::Interface(Interface { name, .. })
+            | Self::Union(Union { name, .. })
+            | Self::Enum(Enum { name, .. })
+            | Self::InputObject(InputObject { name, .. }) => *name,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Scalar(Scalar { span, .. })
+            | Self::Object(ObjectType { span, .. })
+            | Self::Interface(Interface { span, .. })
+            | Self::Union(Union { span, .. })
+            | Self::Enum(Enum { span, .. })
+            | Self::InputObject(InputObject { span, .. }) => *span,
+        }
+    }
+}
+
+/// A borrowed [`TypeDefinition`], as stored in a [`Document`]
+#[derive(Debug, Clone, Copy)]
+pub enum TypeDefinitionRef<'a> {
+    Scalar(&'a Scalar),
+    Object(&'a ObjectType),
+    Interface(&'a Interface),
+    Union(&'a Union),
+    Enum(&'a Enum),
+    InputObject(&'a InputObject),
+}
+
+impl<'a> TypeDefinitionRef<'a> {
+    pub fn name(&self) -> Spur {
+        match self {
+            Self::Scalar(Scalar { name, .. })
+            | Self::Object(ObjectType { name, .. })
+            | Self::Interface(Interface { name, .. })
+            | Self::Union(Union { name, .. })
+            | Self::Enum(Enum { name, .. })
+            | Self::InputObject(InputObject { name, .. }) => *name,
+        }
+    }
+
+    pub fn description(&self) -> Option<Spur> {
+        match self {
+            Self::Scalar(Scalar { description, .. })
+            | Self::Object(ObjectType { description, .. })
+            | Self::Interface(Interface { description, .. })
+            | Self::Union(Union { description, .. })
+            | Self::Enum(Enum { description, .. })
+            | Self::InputObject(InputObject { description, .. }) => *description,
+        }
+    }
+
+    pub fn kind(&self) -> TypeKind {
+        match self {
+            Self::Scalar(..) => TypeKind::Scalar,
+            Self::Object(..) => TypeKind::Object,
+            Self::Interface(..) => TypeKind::Interface,
+            Self::Union(..) => TypeKind::Union,
+            Self::Enum(..) => TypeKind::Enum,
+            Self::InputObject(..) => TypeKind::InputObject,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Scalar(Scalar { span, .. })
+            | Self::Object(ObjectType { span, .. })
+            | Self::Interface(Interface { span, .. })
+            | Self::Union(Union { span, .. })
+            | Self::Enum(Enum { span, .. })
+            | Self::InputObject(InputObject { span, .. }) => *span,
+        }
+    }
+
+    pub fn directives(&self) -> &'a [Directive] {
+        match self {
+            Self::Scalar(Scalar { directives, .. })
+            | Self::Object(ObjectType { directives, .. })
+            | Self::Interface(Interface { directives, .. })
+            | Self::Union(Union { directives, .. })
+            | Self::Enum(Enum { directives, .. })
+            | Self::InputObject(InputObject { directives, .. }) => directives,
+        }
+    }
+}
+
+/// The body of an `extend` definition. Extensions reuse the definition types,
+/// with `description` always `None` and any omitted parts left empty
+#[derive(Debug, Clone)]
+pub enum TypeExtension {
+    Scalar(Scalar),
+    Object(ObjectType),
+    Interface(Interface),
+    Union(Union),
+    Enum(Enum),
+    InputObject(InputObject),
 }
 
-#[derive(Debug, Clone)]
-pub enum Type {
-    Named { name: Spur, nullable: bool },
-    List { base: Box<Self>, nullable: bool },
-}
+impl TypeExtension {
+    pub fn name(&self) -> Spur {
+        match self {
+            Self::Scalar(Scalar { name, .. })
+            | Self::Object(ObjectType { name, .. })
+            | Self::Interface(Interface { name, .. })
+            | Self::Union(Union { name, .. })
+            | Self::Enum(Enum { name, .. })
+            | Self::InputObject(InputObject { name, .. }) => *name,
+        }
+    }
 
-impl Type {
-    pub fn set_nonnullable(&mut self) {
+    pub fn span(&self) -> Span {
         match self {
-            Self::Named { nullable, .. } | Self::List { nullable, .. } => *nullable = true,
+            Self::Scalar(Scalar { span, .. })
+            | Self::Object(ObjectType { span, .. })
+            | Self::Interface(Interface { span, .. })
+            | Self::Union(Union { span, .. })
+            | Self::Enum(Enum { span, .. })
+            | Self::InputObject(InputObject { span, .. }) => *span,
         }
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct ObjectType {
-    pub(crate) implements: Vec<NamedType>,
-    pub(crate) description: Option<Spur>,
-    pub(crate) name: Spur,
-    pub(crate) directives: Vec<Directive>,
-    pub(crate) fields: Option<Vec<FieldDefinition>>,
+pub struct SchemaDefinition {
+    pub description: Option<Spur>,
+    pub directives: Vec<Directive>,
+    pub operation_types: Vec<RootOperationTypeDefinition>,
+    pub span: Span,
 }
 
-impl ObjectType {
-    pub fn validate_implements_fields(
-        &self,
-        fields: &[FieldDefinition],
-    ) -> Vec<GraphqlSchemaTypeError> {
-        let mut errors = Vec::new();
-
-        errors
+impl SchemaDefinition {
+    pub fn operation_type(&self, kind: OperationKind) -> Option<&NamedType> {
+        self.operation_types
+            .iter()
+            .find(|operation_type| operation_type.kind == kind)
+            .map(|operation_type| &operation_type.ty)
     }
 }
 
 #[derive(Debug, Clone)]
-pub enum TypeDefinition {
-    Scalar(Scalar),
-    Object(ObjectType),
-    Interface(Interface),
-    Union(Union),
-    Enum(Enum),
-    InputObject(InputObject),
+pub struct SchemaExtension {
+    pub directives: Vec<Directive>,
+    pub operation_types: Vec<RootOperationTypeDefinition>,
+    pub span: Span,
 }
 
+/// A `query: Query` entry inside a `schema { ... }` block
 #[derive(Debug, Clone)]
-pub struct InputObjectField {
-    pub(crate) description: Option<Spur>,
-    pub(crate) name: Spur,
-    pub(crate) ty: Type,
-    pub(crate) default: Option<Value>,
-    pub(crate) directives: Vec<Directive>,
+pub struct RootOperationTypeDefinition {
+    pub kind: OperationKind,
+    pub ty: NamedType,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
+pub struct InputObjectField {
+    pub description: Option<Spur>,
+    pub name: Spur,
+    pub ty: Type,
+    pub ty_span: Span,
+    pub default: Option<Value>,
+    /// The spans of `default`, empty if there is none
+    pub default_span: ValueSpan,
+    pub directives: Vec<Directive>,
+    pub span: Span,
+}
+
+impl InputObjectField {
+    /// The reason this argument or input field is deprecated, or `None` if it
+    /// isn't
+    pub fn deprecation<'a>(&self, interner: &'a Rodeo) -> Option<&'a str> {
+        deprecation(&self.directives, interner)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     True,
     False,
@@ -141,72 +1638,414 @@ pub enum Value {
     EnumVariant(Spur),
     List(Vec<Self>),
     Object(HashMap<Spur, Self>),
-    // todo: numbers
-    Float,
-    Int,
+    Float(f64),
+    Int(i64),
+}
+
+#[cfg(feature = "serde_json")]
+impl Value {
+    /// The value as JSON, with enum values as strings. Fails on the first
+    /// variable, as they have no value yet
+    ///
+    /// Object fields keep their order only if serde_json's `preserve_order`
+    /// feature is enabled
+    pub fn to_json(&self, interner: &Rodeo) -> Result<serde_json::Value, UnresolvedVariable> {
+        self.to_json_with_variables(interner, &mut |_| None)
+    }
+
+    /// Like [`Value::to_json`], replacing each variable with the value
+    /// returned by `variable`, and failing if it returns `None`
+    pub fn to_json_with_variables(
+        &self,
+        interner: &Rodeo,
+        variable: &mut dyn FnMut(Spur) -> Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, UnresolvedVariable> {
+        use serde_json::Value as JsonValue;
+
+        Ok(match self {
+            Self::True => JsonValue::Bool(true),
+            Self::False => JsonValue::Bool(false),
+            Self::Null => JsonValue::Null,
+            Self::String(string) | Self::EnumVariant(string) => {
+                JsonValue::String(interner.resolve(string).to_owned())
+            }
+            Self::Variable(name) => variable(*name).ok_or_else(|| UnresolvedVariable {
+                name: interner.resolve(name).to_owned(),
+            })?,
+            Self::List(values) => values
+                .iter()
+                .map(|value| value.to_json_with_variables(interner, variable))
+                .collect::<Result<_, _>>()?,
+            Self::Object(fields) => JsonValue::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = value.to_json_with_variables(interner, variable)?;
+                        Ok((interner.resolve(name).to_owned(), value))
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            // NaN and infinities become `null`
+            Self::Float(float) => JsonValue::from(*float),
+            Self::Int(int) => JsonValue::from(*int),
+        })
+    }
+
+    /// A value from JSON. Strings become [`Value::String`], since JSON can't
+    /// tell them apart from enum values, and integers too large for an `i64`
+    /// become floats. Strings and object keys are interned into `interner`
+    pub fn from_json(value: &serde_json::Value, interner: &mut Rodeo) -> Self {
+        use serde_json::Value as JsonValue;
+
+        match value {
+            JsonValue::Null => Self::Null,
+            JsonValue::Bool(true) => Self::True,
+            JsonValue::Bool(false) => Self::False,
+            JsonValue::Number(number) => match number.as_i64() {
+                Some(int) => Self::Int(int),
+                None => Self::Float(number.as_f64().unwrap_or(f64::NAN)),
+            },
+            JsonValue::String(string) => Self::String(interner.get_or_intern(string)),
+            JsonValue::Array(values) => Self::List(
+                values
+                    .iter()
+                    .map(|value| Self::from_json(value, interner))
+                    .collect(),
+            ),
+            JsonValue::Object(fields) => Self::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| {
+                        (
+                            interner.get_or_intern(name),
+                            Self::from_json(value, interner),
+                        )
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// The span of a value and of the items and fields nested in it. It's kept
+/// beside the [`Value`] rather than in it, as values are compared
+/// structurally throughout the crate
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValueSpan {
+    pub span: Span,
+    /// The spans of the items of a list, in order
+    pub items: Vec<ValueSpan>,
+    /// The spans of the fields of an input object, by name
+    pub fields: HashMap<Spur, ObjectFieldSpan>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectFieldSpan {
+    /// The span of the whole field, `name: value`
+    pub span: Span,
+    pub value: ValueSpan,
+}
+
+/// A variable was found where a value without variables was needed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedVariable {
+    /// The name of the variable, without the `$`
+    pub name: String,
+}
+
+impl fmt::Display for UnresolvedVariable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "variable `${}` has no value", self.name)
+    }
 }
 
+impl std::error::Error for UnresolvedVariable {}
+
 #[derive(Debug, Clone)]
 pub struct InputObject {
-    pub(crate) description: Option<Spur>,
-    pub(crate) name: Spur,
-    pub(crate) directives: Vec<Directive>,
-    pub(crate) fields: Option<Vec<InputObjectField>>,
+    pub description: Option<Spur>,
+    pub name: Spur,
+    pub directives: Vec<Directive>,
+    pub fields: Option<Vec<InputObjectField>>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct Enum {
-    pub(crate) description: Option<Spur>,
-    pub(crate) name: Spur,
-    pub(crate) directives: Vec<Directive>,
-    pub(crate) variants: Vec<EnumVariant>,
+    pub description: Option<Spur>,
+    pub name: Spur,
+    pub directives: Vec<Directive>,
+    pub variants: Vec<EnumVariant>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct EnumVariant {
-    pub(crate) description: Option<Spur>,
-    pub(crate) name: Spur,
-    pub(crate) directives: Vec<Directive>,
+    pub description: Option<Spur>,
+    pub name: Spur,
+    pub directives: Vec<Directive>,
+    pub span: Span,
+}
+
+impl EnumVariant {
+    /// The reason this enum value is deprecated, or `None` if it isn't
+    pub fn deprecation<'a>(&self, interner: &'a Rodeo) -> Option<&'a str> {
+        deprecation(&self.directives, interner)
+    }
+}
+
+/// Push the name of each fragment spread in `selection_set`, without
+/// descending into those fragments
+fn collect_fragment_spreads(selection_set: &[Selection], names: &mut Vec<Spur>) {
+    for selection in selection_set {
+        match selection {
+            Selection::Field { selection_set, .. } => {
+                collect_fragment_spreads(selection_set.as_deref().unwrap_or_default(), names)
+            }
+            Selection::InlineFragment { selection_set, .. } => {
+                collect_fragment_spreads(selection_set, names)
+            }
+            Selection::FragmentSpread { name, .. } => names.push(*name),
+        }
+    }
+}
+
+/// Pair each of `duplicates`, the replaced definitions of a kind in the order
+/// they were defined, and the definition `kept` in their place with the first
+/// definition of the same name, as `(name, first, second)`
+pub(crate) fn pair_duplicates<K: Copy + PartialEq, V: Copy>(
+    duplicates: impl IntoIterator<Item = (K, V)>,
+    kept: impl Fn(K) -> Option<V>,
+) -> Vec<(K, V, V)> {
+    let mut firsts: Vec<(K, V)> = Vec::new();
+    let mut pairs = Vec::new();
+
+    for (name, duplicate) in duplicates {
+        match firsts.iter().find(|(first_name, _)| *first_name == name) {
+            Some(&(_, first)) => pairs.push((name, first, duplicate)),
+            None => firsts.push((name, duplicate)),
+        }
+    }
+
+    for (name, first) in firsts {
+        if let Some(kept) = kept(name) {
+            pairs.push((name, first, kept));
+        }
+    }
+
+    pairs
+}
+
+/// The locations of the built-in directive `name`, for schemas that use it
+/// without defining it
+pub(crate) fn built_in_directive_locations(name: &str) -> Option<&'static [DirectiveLocation]> {
+    use DirectiveLocation::*;
+
+    Some(match name {
+        "skip" | "include" => &[Field, FragmentSpread, InlineFragment],
+        "deprecated" => &[
+            FieldDefinition,
+            ArgumentDefinition,
+            InputFieldDefinition,
+            EnumValue,
+        ],
+        "specifiedBy" => &[Scalar],
+        _ => return None,
+    })
+}
+
+/// The `reason` of a `@deprecated` directive in `directives`, falling back to
+/// [`DEFAULT_DEPRECATION_REASON`] if it isn't given as a string
+fn deprecation<'a>(directives: &[Directive], interner: &'a Rodeo) -> Option<&'a str> {
+    let deprecated = interner.get("deprecated")?;
+
+    let directive = directives
+        .iter()
+        .find(|directive| directive.name == deprecated)?;
+
+    let reason = directive
+        .arguments
+        .iter()
+        .flatten()
+        .find(|argument| interner.resolve(&argument.name) == "reason")
+        .and_then(|argument| match argument.value {
+            Value::String(reason) => Some(interner.resolve(&reason)),
+            _ => None,
+        });
+
+    Some(reason.unwrap_or(DEFAULT_DEPRECATION_REASON))
 }
 
 #[derive(Debug, Clone)]
 pub struct Directive {
-    pub(crate) name: Spur,
-    pub(crate) arguments: Option<Vec<Argument>>,
+    pub name: Spur,
+    pub arguments: Option<Vec<Argument>>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
-pub struct NamedType(pub(crate) Spur);
+pub struct DirectiveDefinition {
+    pub description: Option<Spur>,
+    pub name: Spur,
+    pub arguments: Option<Vec<InputObjectField>>,
+    pub repeatable: bool,
+    pub locations: Vec<DirectiveLocation>,
+    pub span: Span,
+}
+
+// https://spec.graphql.org/October2021/#DirectiveLocations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DirectiveLocation {
+    Query,
+    Mutation,
+    Subscription,
+    Field,
+    FragmentDefinition,
+    FragmentSpread,
+    InlineFragment,
+    VariableDefinition,
+    Schema,
+    Scalar,
+    Object,
+    FieldDefinition,
+    ArgumentDefinition,
+    Interface,
+    Union,
+    Enum,
+    EnumValue,
+    InputObject,
+    InputFieldDefinition,
+}
+
+impl DirectiveLocation {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "QUERY" => Self::Query,
+            "MUTATION" => Self::Mutation,
+            "SUBSCRIPTION" => Self::Subscription,
+            "FIELD" => Self::Field,
+            "FRAGMENT_DEFINITION" => Self::FragmentDefinition,
+            "FRAGMENT_SPREAD" => Self::FragmentSpread,
+            "INLINE_FRAGMENT" => Self::InlineFragment,
+            "VARIABLE_DEFINITION" => Self::VariableDefinition,
+            "SCHEMA" => Self::Schema,
+            "SCALAR" => Self::Scalar,
+            "OBJECT" => Self::Object,
+            "FIELD_DEFINITION" => Self::FieldDefinition,
+            "ARGUMENT_DEFINITION" => Self::ArgumentDefinition,
+            "INTERFACE" => Self::Interface,
+            "UNION" => Self::Union,
+            "ENUM" => Self::Enum,
+            "ENUM_VALUE" => Self::EnumValue,
+            "INPUT_OBJECT" => Self::InputObject,
+            "INPUT_FIELD_DEFINITION" => Self::InputFieldDefinition,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Query => "QUERY",
+            Self::Mutation => "MUTATION",
+            Self::Subscription => "SUBSCRIPTION",
+            Self::Field => "FIELD",
+            Self::FragmentDefinition => "FRAGMENT_DEFINITION",
+            Self::FragmentSpread => "FRAGMENT_SPREAD",
+            Self::InlineFragment => "INLINE_FRAGMENT",
+            Self::VariableDefinition => "VARIABLE_DEFINITION",
+            Self::Schema => "SCHEMA",
+            Self::Scalar => "SCALAR",
+            Self::Object => "OBJECT",
+            Self::FieldDefinition => "FIELD_DEFINITION",
+            Self::ArgumentDefinition => "ARGUMENT_DEFINITION",
+            Self::Interface => "INTERFACE",
+            Self::Union => "UNION",
+            Self::Enum => "ENUM",
+            Self::EnumValue => "ENUM_VALUE",
+            Self::InputObject => "INPUT_OBJECT",
+            Self::InputFieldDefinition => "INPUT_FIELD_DEFINITION",
+        }
+    }
+
+    /// The location of the directives of an operation of the given kind
+    pub fn of_operation(kind: OperationKind) -> Self {
+        match kind {
+            OperationKind::Query => Self::Query,
+            OperationKind::Mutation => Self::Mutation,
+            OperationKind::Subscription => Self::Subscription,
+        }
+    }
+
+    pub fn is_executable(&self) -> bool {
+        matches!(
+            self,
+            Self::Query
+                | Self::Mutation
+                | Self::Subscription
+                | Self::Field
+                | Self::FragmentDefinition
+                | Self::FragmentSpread
+                | Self::InlineFragment
+                | Self::VariableDefinition
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedType(pub Spur);
 
 #[derive(Debug, Clone)]
 pub struct Union {
-    pub(crate) description: Option<Spur>,
-    pub(crate) name: Spur,
-    pub(crate) types: Vec<NamedType>,
-    pub(crate) directives: Vec<Directive>,
+    pub description: Option<Spur>,
+    pub name: Spur,
+    pub types: Vec<NamedType>,
+    pub directives: Vec<Directive>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct Scalar {
-    pub(crate) description: Option<Spur>,
-    pub(crate) name: Spur,
-    pub(crate) directives: Vec<Directive>,
+    pub description: Option<Spur>,
+    pub name: Spur,
+    pub directives: Vec<Directive>,
+    pub span: Span,
+}
+
+impl Scalar {
+    /// The `url` given to `@specifiedBy`, linking to a specification of the
+    /// scalar's format, or `None` if the scalar isn't annotated with it
+    pub fn specified_by_url<'a>(&self, interner: &'a Rodeo) -> Option<&'a str> {
+        let specified_by = interner.get("specifiedBy")?;
+
+        self.directives
+            .iter()
+            .filter(|directive| directive.name == specified_by)
+            .flat_map(|directive| directive.arguments.iter().flatten())
+            .find(|argument| interner.resolve(&argument.name) == "url")
+            .and_then(|argument| match argument.value {
+                Value::String(url) => Some(interner.resolve(&url)),
+                _ => None,
+            })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Interface {
-    pub(crate) description: Option<Spur>,
-    pub(crate) name: Spur,
-    pub(crate) directives: Vec<Directive>,
-    pub(crate) fields: Vec<FieldDefinition>,
+    pub implements: Vec<NamedType>,
+    pub description: Option<Spur>,
+    pub name: Spur,
+    pub directives: Vec<Directive>,
+    pub fields: Vec<FieldDefinition>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct Fragment {
-    pub(crate) name: Spur,
-    pub(crate) on: Spur,
-    pub(crate) directives: Vec<Directive>,
-    pub(crate) selection_set: Vec<Selection>,
+    pub name: Spur,
+    pub on: Spur,
+    pub directives: Vec<Directive>,
+    pub selection_set: Vec<Selection>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -217,18 +2056,33 @@ pub enum Selection {
         arguments: Option<Vec<Argument>>,
         directives: Vec<Directive>,
         selection_set: Option<Vec<Self>>,
+        span: Span,
     },
     FragmentSpread {
         name: Spur,
         directives: Vec<Directive>,
+        span: Span,
     },
     InlineFragment {
-        on: Spur,
+        /// The type condition. Without one, the fragment applies to the type
+        /// of the enclosing selection set
+        on: Option<Spur>,
         directives: Vec<Directive>,
         selection_set: Vec<Self>,
+        span: Span,
     },
 }
 
+impl Selection {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Field { span, .. }
+            | Self::FragmentSpread { span, .. }
+            | Self::InlineFragment { span, .. } => *span,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Keyword {
     Type,
@@ -247,6 +2101,9 @@ pub enum Keyword {
     Null,
     Interface,
     On,
+    Schema,
+    Directive,
+    Repeatable,
 }
 
 impl Keyword {
@@ -268,6 +2125,9 @@ impl Keyword {
             Self::Null => "null",
             Self::Interface => "interface",
             Self::On => "on",
+            Self::Schema => "schema",
+            Self::Directive => "directive",
+            Self::Repeatable => "repeatable",
         }
     }
 }
@@ -276,10 +2136,13 @@ impl Keyword {
 pub enum Token {
     Name(Spur),
     String(Spur),
+    /// A variable, such as `$a`, holding its name without the `$`
+    Variable(Spur),
     Keyword(Keyword),
 
     // https://spec.graphql.org/June2018/#Punctuator
     Bang,
+    /// A `$` that isn't immediately followed by a name
     Dollar,
     OpenParen,
     CloseParen,
@@ -295,8 +2158,69 @@ pub enum Token {
 
     Ampersand,
 
-    IntValue,
-    FloatValue,
+    /// The source text of an integer literal
+    IntValue(Spur),
+    /// The source text of a float literal
+    FloatValue(Spur),
+}
+
+/// Describes the kind of token, for use in error messages. Names and strings
+/// aren't resolved, as the token doesn't have access to the interner
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let punctuator = match self {
+            Self::Name(..) => return write!(f, "name"),
+            Self::String(..) => return write!(f, "string"),
+            Self::Variable(..) => return write!(f, "variable"),
+            Self::Keyword(keyword) => keyword.as_str(),
+            Self::IntValue(..) => return write!(f, "int"),
+            Self::FloatValue(..) => return write!(f, "float"),
+            Self::Bang => "!",
+            Self::Dollar => "$",
+            Self::OpenParen => "(",
+            Self::CloseParen => ")",
+            Self::DotDotDot => "...",
+            Self::Colon => ":",
+            Self::Eq => "=",
+            Self::AtSign => "@",
+            Self::OpenSquareBrace => "[",
+            Self::CloseSquareBrace => "]",
+            Self::OpenCurlyBrace => "{",
+            Self::Pipe => "|",
+            Self::CloseCurlyBrace => "}",
+            Self::Ampersand => "&",
+        };
+
+        write!(f, "`{}`", punctuator)
+    }
+}
+
+/// A byte range into the source buffer. Nodes that weren't parsed from source,
+/// or were parsed with the `spans` feature disabled, have an empty span at 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+/// A `#` comment, stored as a byte range into the source it was lexed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comment {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Comment {
+    /// The text of the comment, without the leading `#`
+    pub fn text<'a>(&self, source: &'a [u8]) -> Cow<'a, str> {
+        String::from_utf8_lossy(&source[self.start + 1..self.end])
+    }
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
@@ -306,18 +2230,586 @@ pub enum OperationKind {
     Subscription,
 }
 
+impl OperationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Query => "query",
+            Self::Mutation => "mutation",
+            Self::Subscription => "subscription",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Operation {
-    pub(crate) kind: OperationKind,
-    pub(crate) name: Option<Spur>,
-    pub(crate) variable_definitions: Vec<VariableDefinition>,
-    pub(crate) directives: Vec<Directive>,
-    pub(crate) selection_set: Vec<Selection>,
+    pub kind: OperationKind,
+    pub name: Option<Spur>,
+    pub variable_definitions: Vec<VariableDefinition>,
+    pub directives: Vec<Directive>,
+    pub selection_set: Vec<Selection>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct VariableDefinition {
-    pub(crate) name: Spur,
-    pub(crate) ty: Type,
-    pub(crate) default: Option<Value>,
+    pub name: Spur,
+    pub ty: Type,
+    pub ty_span: Span,
+    pub default: Option<Value>,
+    /// The spans of `default`, empty if there is none
+    pub default_span: ValueSpan,
+    pub span: Span,
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::{Rodeo, Spur};
+
+    use super::{Document, GraphqlSchemaTypeError, OperationKind, BUILT_IN_SCALARS};
+    use crate::GraphqlParser;
+
+    #[test]
+    fn apply_extensions() {
+        let mut schema = GraphqlParser::parse(
+            concat!(
+                "scalar Date ",
+                "extend scalar Date @b ",
+                "type Query { a: Int } ",
+                "extend type Query implements Node @c { b: Int } ",
+                "interface Node { id: ID! } ",
+                "extend interface Node @d { name: String } ",
+                "union Result = Query ",
+                "extend union Result @e = Mutation ",
+                "enum Color { RED } ",
+                "extend enum Color @f { GREEN } ",
+                "input Filter { a: Int } ",
+                "extend input Filter @g { b: Int }",
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert!(schema.apply_extensions().is_empty());
+        assert!(schema.type_extensions.is_empty());
+
+        let scalar = schema.scalars.values().next().unwrap();
+        assert_eq!(scalar.directives.len(), 1);
+
+        let query = schema.output_objects.values().next().unwrap();
+        assert_eq!(query.directives.len(), 1);
+        assert_eq!(query.implements.len(), 1);
+        assert_eq!(query.fields.as_ref().unwrap().len(), 2);
+
+        let node = schema.interfaces.values().next().unwrap();
+        assert_eq!(node.directives.len(), 1);
+        assert_eq!(node.fields.len(), 2);
+
+        let result = schema.unions.values().next().unwrap();
+        assert_eq!(result.directives.len(), 1);
+        assert_eq!(result.types.len(), 2);
+
+        let color = schema.enums.values().next().unwrap();
+        assert_eq!(color.directives.len(), 1);
+        assert_eq!(color.variants.len(), 2);
+
+        let filter = schema.input_objects.values().next().unwrap();
+        assert_eq!(filter.directives.len(), 1);
+        assert_eq!(filter.fields.as_ref().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn extending_unknown_types() {
+        let mut schema =
+            GraphqlParser::parse(b"type Query { a: Int } extend type User { name: String }")
+                .unwrap();
+
+        let errors = schema.apply_extensions();
+
+        assert!(
+            matches!(errors[..], [GraphqlSchemaTypeError::ExtendedTypeDne(..)]),
+            "{:?}",
+            errors
+        );
+        assert_eq!(schema.output_objects.len(), 1);
+    }
+
+    /// The number of fields of the root type of `kind`
+    fn root_field_count(document: &Document, kind: OperationKind) -> Option<usize> {
+        let root = document
+            .output_objects
+            .get(&document.root_operation_type(kind)?)?;
+
+        Some(root.fields.as_ref().map_or(0, Vec::len))
+    }
+
+    #[test]
+    fn explicit_root_operation_types() {
+        let schema = GraphqlParser::parse(
+            concat!(
+                "schema { query: A mutation: B subscription: C } ",
+                "type A { a: Int } type B { a: Int b: Int } type C { a: Int b: Int c: Int }",
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(root_field_count(&schema, OperationKind::Query), Some(1));
+        assert_eq!(root_field_count(&schema, OperationKind::Mutation), Some(2));
+        assert_eq!(
+            root_field_count(&schema, OperationKind::Subscription),
+            Some(3)
+        );
+        assert!(schema.validate().is_empty());
+    }
+
+    #[test]
+    fn no_schema_definition() {
+        let schema = GraphqlParser::parse(b"type Query { a: Int }").unwrap();
+
+        assert_eq!(schema.root_operation_type(OperationKind::Query), None);
+    }
+
+    #[test]
+    fn default_root_operation_types() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_schema_with_interner(
+            b"type Query { a: Int } type Mutation { b: Int } scalar Subscription",
+            &mut interner,
+        )
+        .unwrap();
+
+        assert_eq!(schema.root_operation_type(OperationKind::Query), None);
+        assert_eq!(
+            schema.root_operation_type_or_default(OperationKind::Query, &interner),
+            interner.get("Query")
+        );
+        assert_eq!(
+            schema.root_operation_type_or_default(OperationKind::Mutation, &interner),
+            interner.get("Mutation")
+        );
+        // only object types are root operation types
+        assert_eq!(
+            schema.root_operation_type_or_default(OperationKind::Subscription, &interner),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "spans")]
+    fn duplicate_root_operation_types() {
+        let source = "schema { query: A mutation: B query: B } type A { a: Int } type B { b: Int }";
+        let mut interner = Rodeo::default();
+        let document =
+            GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner).unwrap();
+        let text = |span: super::Span| &source[span.start..span.end];
+
+        let errors = document.validate();
+
+        match &errors[..] {
+            [error @ GraphqlSchemaTypeError::DuplicateRootOperationType {
+                kind: OperationKind::Query,
+                first,
+                second,
+            }] => {
+                assert_eq!(
+                    error.message(&interner),
+                    "the root `query` type is defined more than once"
+                );
+                assert_eq!(text(*first), "query: A");
+                assert_eq!(text(*second), "query: B");
+            }
+            errors => panic!("{:?}", errors),
+        }
+
+        // the first declaration is used
+        assert_eq!(
+            document.root_operation_type(OperationKind::Query),
+            interner.get("A")
+        );
+    }
+
+    fn validation_errors(source: &str) -> (Vec<GraphqlSchemaTypeError>, Rodeo) {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse schema");
+
+        (schema.validate(), interner)
+    }
+
+    fn resolve<'a>(interner: &'a Rodeo, names: &[&Spur]) -> Vec<&'a str> {
+        names.iter().map(|name| interner.resolve(name)).collect()
+    }
+
+    #[test]
+    fn missing_interface_field() {
+        let (errors, interner) = validation_errors(
+            "interface Node { id: ID! } type User implements Node { name: String }",
+        );
+
+        assert!(matches!(
+            &errors[..],
+            [GraphqlSchemaTypeError::MissingInterfaceField { ty, interface, field }]
+                if resolve(&interner, &[ty, interface, field]) == ["User", "Node", "id"]
+        ));
+    }
+
+    #[test]
+    fn covariant_field_types() {
+        let (errors, _) = validation_errors(
+            "interface I { a: [Int] b: I } type T implements I { a: [Int!] b: T! }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let (errors, interner) =
+            validation_errors("interface I { id: ID! } type T implements I { id: ID }");
+
+        assert!(matches!(
+            &errors[..],
+            [GraphqlSchemaTypeError::InvalidInterfaceFieldType { ty, interface, field }]
+                if resolve(&interner, &[ty, interface, field]) == ["T", "I", "id"]
+        ));
+    }
+
+    #[test]
+    fn argument_type_mismatch() {
+        let (errors, interner) = validation_errors(
+            "interface I { a(b: Int): Int } type T implements I { a(b: String): Int }",
+        );
+
+        assert!(matches!(
+            &errors[..],
+            [GraphqlSchemaTypeError::InvalidInterfaceArgumentType { ty, interface, field, argument }]
+                if resolve(&interner, &[ty, interface, field, argument]) == ["T", "I", "a", "b"]
+        ));
+    }
+
+    #[test]
+    fn extra_required_argument() {
+        let (errors, interner) = validation_errors(
+            "
+            interface I { a: Int }
+            type T implements I { a(b: Int! c: Int d: Int! = 1): Int }
+            ",
+        );
+
+        assert!(matches!(
+            &errors[..],
+            [GraphqlSchemaTypeError::RequiredArgumentNotInInterface { ty, interface, field, argument }]
+                if resolve(&interner, &[ty, interface, field, argument]) == ["T", "I", "a", "b"]
+        ));
+    }
+
+    #[test]
+    fn missing_transitive_interface() {
+        let (errors, interner) = validation_errors(
+            "
+            interface Node { id: ID! }
+            interface Resource implements Node { id: ID! }
+            type File implements Resource { id: ID! }
+            ",
+        );
+
+        assert!(matches!(
+            &errors[..],
+            [GraphqlSchemaTypeError::MissingTransitiveInterface { ty, interface, transitive }]
+                if resolve(&interner, &[ty, interface, transitive]) == ["File", "Resource", "Node"]
+        ));
+    }
+
+    #[test]
+    fn interface_implementing_itself() {
+        let (errors, interner) = validation_errors("interface Node implements Node { id: ID! }");
+
+        assert!(matches!(
+            &errors[..],
+            [GraphqlSchemaTypeError::SelfImplementation(name)]
+                if resolve(&interner, &[name]) == ["Node"]
+        ));
+    }
+
+    /// The message of each duplicate definition `validate` finds in `source`,
+    /// with the source text of the first definition and the later one
+    #[cfg(feature = "spans")]
+    fn duplicates(source: &str) -> Vec<(String, &str, &str)> {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse schema");
+        let text = |span: super::Span| &source[span.start..span.end];
+
+        document
+            .validate()
+            .iter()
+            .filter_map(|error| match *error {
+                GraphqlSchemaTypeError::DuplicateTypeDefinition { first, second, .. }
+                | GraphqlSchemaTypeError::DuplicateDirectiveDefinition { first, second, .. }
+                | GraphqlSchemaTypeError::DuplicateSchemaDefinition { first, second } => {
+                    Some((error.message(&interner), text(first), text(second)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    #[cfg(feature = "spans")]
+    fn duplicate_types() {
+        assert_eq!(
+            duplicates("type A { a: Int } enum A { B } scalar A"),
+            [
+                (
+                    "type `A` is defined more than once".to_owned(),
+                    "type A { a: Int }",
+                    "enum A { B }"
+                ),
+                (
+                    "type `A` is defined more than once".to_owned(),
+                    "type A { a: Int }",
+                    "scalar A"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "spans")]
+    fn duplicate_directives() {
+        assert_eq!(
+            duplicates("directive @a on FIELD directive @a(b: Int) on QUERY"),
+            [(
+                "directive `@a` is defined more than once".to_owned(),
+                "directive @a on FIELD",
+                "directive @a(b: Int) on QUERY"
+            )]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "spans")]
+    fn duplicate_schemas() {
+        assert_eq!(
+            duplicates("schema { query: A } schema { query: B } type A { a: Int }"),
+            [(
+                "the schema is defined more than once".to_owned(),
+                "schema { query: A }",
+                "schema { query: B }"
+            )]
+        );
+    }
+
+    #[test]
+    fn built_in_definitions() {
+        let mut interner = Rodeo::default();
+        let mut schema =
+            GraphqlParser::parse_schema_with_interner(b"type Query { a: Int }", &mut interner)
+                .unwrap();
+
+        schema.add_built_in_definitions(&mut interner);
+
+        for name in BUILT_IN_SCALARS {
+            assert!(
+                schema.scalars.contains_key(&interner.get(name).unwrap()),
+                "{}",
+                name
+            );
+        }
+
+        for name in ["skip", "include", "deprecated", "specifiedBy"] {
+            assert!(
+                schema
+                    .directive_definitions
+                    .contains_key(&interner.get(name).unwrap()),
+                "{}",
+                name
+            );
+        }
+
+        for name in [
+            "__Schema",
+            "__Type",
+            "__TypeKind",
+            "__Field",
+            "__InputValue",
+            "__EnumValue",
+            "__Directive",
+            "__DirectiveLocation",
+        ] {
+            assert!(
+                schema.get_type_by_name(name, &interner).is_some(),
+                "{}",
+                name
+            );
+        }
+
+        let type_count = schema.types().count();
+
+        schema.add_built_in_definitions(&mut interner);
+
+        assert_eq!(schema.types().count(), type_count);
+        assert_eq!(schema.directive_definitions.len(), 4);
+        assert!(schema.duplicates.is_empty());
+        assert!(schema.validate().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "spans")]
+    fn redefined_built_ins() {
+        let source = "type String { a: Int } directive @skip on FIELD";
+        let mut interner = Rodeo::default();
+        let mut document =
+            GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner).unwrap();
+
+        document.add_built_in_definitions(&mut interner);
+
+        // the user's definitions are kept
+        let string = document.get_type_by_name("String", &interner).unwrap();
+        assert_eq!(string.span(), super::Span::new(0, 22));
+
+        let mut errors = document
+            .validate()
+            .iter()
+            .map(|error| error.message(&interner))
+            .collect::<Vec<_>>();
+        errors.sort();
+
+        assert_eq!(
+            errors,
+            [
+                "directive `@skip` is defined more than once",
+                "type `String` is defined more than once",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn json_round_trip() {
+        use super::Value;
+
+        let mut interner = Rodeo::default();
+        let json = serde_json::json!({
+            "list": [1, 1.5, null, true, false, "a", []],
+            "object": { "int": -2, "float": 2.0, "nested": {} },
+            "null": null,
+        });
+
+        let value = Value::from_json(&json, &mut interner);
+
+        assert_eq!(value.to_json(&interner).unwrap(), json);
+
+        let field = |value: &Value, name: &str| match value {
+            Value::Object(fields) => fields[&interner.get(name).unwrap()].clone(),
+            value => panic!("{:?}", value),
+        };
+
+        // integers and floats are kept apart, even if the float is whole
+        let object = field(&value, "object");
+        assert_eq!(field(&object, "int"), Value::Int(-2));
+        assert_eq!(field(&object, "float"), Value::Float(2.0));
+        assert_eq!(field(&value, "null"), Value::Null);
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn json_of_enums_and_variables() {
+        use super::Value;
+
+        let mut interner = Rodeo::default();
+        let value = Value::List(vec![
+            Value::EnumVariant(interner.get_or_intern("RED")),
+            Value::Variable(interner.get_or_intern("v")),
+        ]);
+
+        assert_eq!(
+            value.to_json(&interner).unwrap_err().to_string(),
+            "variable `$v` has no value"
+        );
+        assert_eq!(
+            value
+                .to_json_with_variables(&interner, &mut |_| Some(serde_json::json!(1)))
+                .unwrap(),
+            serde_json::json!(["RED", 1])
+        );
+    }
+
+    /// The names of the operations and fragments `extract_operation` keeps
+    fn extracted(source: &str, name: Option<&str>) -> Option<(Vec<String>, Vec<String>)> {
+        let mut interner = Rodeo::default();
+        let document =
+            GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner).unwrap();
+        let extracted = document.extract_operation(name, &interner)?;
+
+        let mut operations = extracted
+            .operations
+            .keys()
+            .map(|(name, _)| name.map_or("", |name| interner.resolve(&name)).to_owned())
+            .collect::<Vec<_>>();
+        let mut fragments = extracted
+            .fragments
+            .keys()
+            .map(|name| interner.resolve(name).to_owned())
+            .collect::<Vec<_>>();
+        operations.sort();
+        fragments.sort();
+
+        Some((operations, fragments))
+    }
+
+    #[test]
+    fn extract_named_operation() {
+        let source = concat!(
+            "query A { ...F } ",
+            "mutation B { ...G } ",
+            "fragment F on Query { a } ",
+            "fragment G on Mutation { b }",
+        );
+
+        assert_eq!(
+            extracted(source, Some("A")),
+            Some((vec!["A".to_owned()], vec!["F".to_owned()]))
+        );
+        assert_eq!(
+            extracted(source, Some("B")),
+            Some((vec!["B".to_owned()], vec!["G".to_owned()]))
+        );
+    }
+
+    #[test]
+    fn extract_anonymous_operation() {
+        assert_eq!(
+            extracted("{ ...F } fragment F on Query { a }", None),
+            Some((vec![String::new()], vec!["F".to_owned()]))
+        );
+
+        // without a name, the operation to extract is ambiguous
+        assert_eq!(extracted("query A { a } query B { b }", None), None);
+    }
+
+    #[test]
+    fn extract_unknown_operation() {
+        let source = "query A { a } fragment F on Query { b }";
+
+        // a name the document never mentions, and one that only names a fragment
+        assert_eq!(extracted(source, Some("Missing")), None);
+        assert_eq!(extracted(source, Some("F")), None);
+    }
+
+    #[test]
+    fn extract_transitive_fragments() {
+        let source = concat!(
+            "query A { ...F } ",
+            "fragment F on Query { ...G x { ...H } } ",
+            "fragment G on Query { ...F a } ",
+            "fragment H on X { ... on X { ...I } } ",
+            "fragment I on X { b } ",
+            "fragment Unused on Query { c }",
+        );
+
+        assert_eq!(
+            extracted(source, Some("A")),
+            Some((
+                vec!["A".to_owned()],
+                ["F", "G", "H", "I"].map(str::to_owned).to_vec()
+            ))
+        );
+    }
 }
@@ -0,0 +1,970 @@
+//! Construction of documents in code, for tools that synthesize schemas and
+//! queries without going through source text
+//!
+//! Builders hold names as strings, which are interned when the document is
+//! built. Types and values are written as GraphQL source text, such as
+//! `"[ID!]!"` or `"{ first: 10 }"`, and are parsed when the document is built
+//!
+//! ```
+//! use graphql::builder::{DocumentBuilder, FieldBuilder, ObjectTypeBuilder};
+//! use lasso::Rodeo;
+//!
+//! let mut interner = Rodeo::default();
+//!
+//! let schema = DocumentBuilder::new()
+//!     .object(
+//!         ObjectTypeBuilder::object("Query")
+//!             .field_with(FieldBuilder::new("user", "User").argument("id", "ID!")),
+//!     )
+//!     .object(ObjectTypeBuilder::object("User").field("id", "ID!"))
+//!     .build(&mut interner)
+//!     .unwrap();
+//! ```
+
+use std::fmt;
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        Argument, Definition, Directive, Document, Enum, EnumVariant, FieldDefinition, Fragment,
+        InputObject, InputObjectField, Interface, NamedType, ObjectType, Operation, OperationKind,
+        Scalar, Selection, Span, Type, TypeDefinition, Union, Value, ValueSpan, VariableDefinition,
+    },
+    validation::validate_executable_document,
+    GraphqlParser,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// A type or value that isn't valid GraphQL source text
+    InvalidSyntax { text: String, message: String },
+    /// A type reference to a type that isn't defined
+    UnknownType { name: String },
+    /// The messages of the validation errors of the built document
+    Invalid { messages: Vec<String> },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSyntax { text, message } => write!(f, "`{}`: {}", text, message),
+            Self::UnknownType { name } => write!(f, "unknown type `{}`", name),
+            Self::Invalid { messages } => write!(f, "{}", messages.join("\n")),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// The names of the built-in scalars, which may be referenced without being
+/// defined
+const BUILT_IN_SCALARS: [&str; 5] = ["Int", "Float", "String", "Boolean", "ID"];
+
+#[derive(Debug, Clone, Default)]
+pub struct DocumentBuilder {
+    types: Vec<TypeBuilder>,
+    operations: Vec<OperationBuilder>,
+    fragments: Vec<FragmentBuilder>,
+}
+
+#[derive(Debug, Clone)]
+enum TypeBuilder {
+    Scalar(String),
+    Object(ObjectTypeBuilder),
+    Union(String, Vec<String>),
+    Enum(String, Vec<String>),
+    InputObject(InputObjectBuilder),
+}
+
+impl DocumentBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn scalar(mut self, name: &str) -> Self {
+        self.types.push(TypeBuilder::Scalar(name.to_owned()));
+        self
+    }
+
+    /// Add an object type or interface
+    pub fn object(mut self, object: ObjectTypeBuilder) -> Self {
+        self.types.push(TypeBuilder::Object(object));
+        self
+    }
+
+    pub fn union<'b>(mut self, name: &str, members: impl IntoIterator<Item = &'b str>) -> Self {
+        self.types.push(TypeBuilder::Union(
+            name.to_owned(),
+            members.into_iter().map(str::to_owned).collect(),
+        ));
+        self
+    }
+
+    pub fn enum_type<'b>(mut self, name: &str, values: impl IntoIterator<Item = &'b str>) -> Self {
+        self.types.push(TypeBuilder::Enum(
+            name.to_owned(),
+            values.into_iter().map(str::to_owned).collect(),
+        ));
+        self
+    }
+
+    pub fn input_object(mut self, input: InputObjectBuilder) -> Self {
+        self.types.push(TypeBuilder::InputObject(input));
+        self
+    }
+
+    pub fn operation(mut self, operation: OperationBuilder) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    pub fn fragment(mut self, fragment: FragmentBuilder) -> Self {
+        self.fragments.push(fragment);
+        self
+    }
+
+    /// Build the document, interning its names into `interner`
+    ///
+    /// Type references must name a type of the document or a built-in
+    /// scalar, and the type system definitions must be valid. Operations and
+    /// fragments are only validated by [`DocumentBuilder::build_executable`]
+    pub fn build(self, interner: &mut Rodeo) -> Result<Document, BuildError> {
+        let mut document = Document::new();
+        let mut referenced = Vec::new();
+
+        for ty in self.types {
+            let definition = ty.build(interner, &mut referenced)?;
+            document.add_definition(Definition::TypeDecl(definition));
+        }
+
+        for operation in self.operations {
+            document.add_definition(Definition::Operation(operation.build(interner)?));
+        }
+
+        for fragment in self.fragments {
+            document.add_definition(Definition::Fragment(fragment.build(interner)?));
+        }
+
+        if let Some(name) = referenced.into_iter().find(|name| {
+            !BUILT_IN_SCALARS.contains(&name.as_str())
+                && document.get_type_by_name(name, interner).is_none()
+        }) {
+            return Err(BuildError::UnknownType { name });
+        }
+
+        let messages = document
+            .validate()
+            .into_iter()
+            .chain(document.validate_default_values(interner))
+            .chain(document.validate_directive_usages(interner))
+            .map(|err| err.message(interner))
+            .collect::<Vec<_>>();
+
+        if !messages.is_empty() {
+            return Err(BuildError::Invalid { messages });
+        }
+
+        Ok(document)
+    }
+
+    /// Build an executable document, validating its operations and fragments
+    /// against `schema`, which must have been parsed or built with
+    /// `interner`
+    pub fn build_executable(
+        self,
+        schema: &Document,
+        interner: &mut Rodeo,
+    ) -> Result<Document, BuildError> {
+        let document = self.build(interner)?;
+
+        let messages = validate_executable_document(schema, &document, interner)
+            .into_iter()
+            .map(|err| err.to_string())
+            .collect::<Vec<_>>();
+
+        if !messages.is_empty() {
+            return Err(BuildError::Invalid { messages });
+        }
+
+        Ok(document)
+    }
+}
+
+impl TypeBuilder {
+    /// `referenced` collects the names of the types the definition refers to
+    fn build(
+        self,
+        interner: &mut Rodeo,
+        referenced: &mut Vec<String>,
+    ) -> Result<TypeDefinition, BuildError> {
+        Ok(match self {
+            Self::Scalar(name) => TypeDefinition::Scalar(Scalar {
+                description: None,
+                name: interner.get_or_intern(name),
+                directives: Vec::new(),
+                span: Span::default(),
+            }),
+            Self::Object(object) => object.build(interner, referenced)?,
+            Self::Union(name, members) => {
+                referenced.extend(members.iter().cloned());
+
+                TypeDefinition::Union(Union {
+                    description: None,
+                    name: interner.get_or_intern(name),
+                    types: members
+                        .into_iter()
+                        .map(|member| NamedType(interner.get_or_intern(member)))
+                        .collect(),
+                    directives: Vec::new(),
+                    span: Span::default(),
+                })
+            }
+            Self::Enum(name, values) => TypeDefinition::Enum(Enum {
+                description: None,
+                name: interner.get_or_intern(name),
+                directives: Vec::new(),
+                variants: values
+                    .into_iter()
+                    .map(|value| EnumVariant {
+                        description: None,
+                        name: interner.get_or_intern(value),
+                        directives: Vec::new(),
+                        span: Span::default(),
+                    })
+                    .collect(),
+                span: Span::default(),
+            }),
+            Self::InputObject(input) => TypeDefinition::InputObject(InputObject {
+                description: intern_description(input.description, interner),
+                name: interner.get_or_intern(input.name),
+                directives: Vec::new(),
+                fields: Some(build_input_values(input.fields, interner, referenced)?),
+                span: Span::default(),
+            }),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectTypeBuilder {
+    is_interface: bool,
+    name: String,
+    description: Option<String>,
+    implements: Vec<String>,
+    fields: Vec<FieldBuilder>,
+}
+
+impl ObjectTypeBuilder {
+    pub fn object(name: &str) -> Self {
+        Self {
+            is_interface: false,
+            name: name.to_owned(),
+            description: None,
+            implements: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn interface(name: &str) -> Self {
+        Self {
+            is_interface: true,
+            ..Self::object(name)
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+
+    pub fn implements(mut self, interface: &str) -> Self {
+        self.implements.push(interface.to_owned());
+        self
+    }
+
+    /// Add a field without arguments, of the type written as `ty`
+    pub fn field(self, name: &str, ty: &str) -> Self {
+        self.field_with(FieldBuilder::new(name, ty))
+    }
+
+    pub fn field_with(mut self, field: FieldBuilder) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    fn build(
+        self,
+        interner: &mut Rodeo,
+        referenced: &mut Vec<String>,
+    ) -> Result<TypeDefinition, BuildError> {
+        let name = interner.get_or_intern(self.name);
+        let description = intern_description(self.description, interner);
+        let implements = self
+            .implements
+            .into_iter()
+            .map(|interface| NamedType(interner.get_or_intern(interface)))
+            .collect();
+
+        let fields = self
+            .fields
+            .into_iter()
+            .map(|field| field.build(interner, referenced))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(if self.is_interface {
+            TypeDefinition::Interface(Interface {
+                implements,
+                description,
+                name,
+                directives: Vec::new(),
+                fields,
+                span: Span::default(),
+            })
+        } else {
+            TypeDefinition::Object(ObjectType {
+                implements,
+                description,
+                name,
+                directives: Vec::new(),
+                fields: Some(fields),
+                span: Span::default(),
+            })
+        })
+    }
+}
+
+/// A field of an object type or interface
+#[derive(Debug, Clone)]
+pub struct FieldBuilder {
+    name: String,
+    ty: String,
+    description: Option<String>,
+    arguments: Vec<InputValueBuilder>,
+}
+
+impl FieldBuilder {
+    pub fn new(name: &str, ty: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            ty: ty.to_owned(),
+            description: None,
+            arguments: Vec::new(),
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+
+    pub fn argument(mut self, name: &str, ty: &str) -> Self {
+        self.arguments.push(InputValueBuilder::new(name, ty, None));
+        self
+    }
+
+    pub fn argument_with_default(mut self, name: &str, ty: &str, default: &str) -> Self {
+        self.arguments
+            .push(InputValueBuilder::new(name, ty, Some(default)));
+        self
+    }
+
+    fn build(
+        self,
+        interner: &mut Rodeo,
+        referenced: &mut Vec<String>,
+    ) -> Result<FieldDefinition, BuildError> {
+        let ty = parse_type(&self.ty, interner)?;
+        referenced.push(interner.resolve(&ty.name()).to_owned());
+
+        Ok(FieldDefinition {
+            description: intern_description(self.description, interner),
+            name: interner.get_or_intern(self.name),
+            ty,
+            ty_span: Span::default(),
+            arguments: if self.arguments.is_empty() {
+                None
+            } else {
+                Some(build_input_values(self.arguments, interner, referenced)?)
+            },
+            directives: Vec::new(),
+            span: Span::default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InputObjectBuilder {
+    name: String,
+    description: Option<String>,
+    fields: Vec<InputValueBuilder>,
+}
+
+impl InputObjectBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            description: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+
+    pub fn field(mut self, name: &str, ty: &str) -> Self {
+        self.fields.push(InputValueBuilder::new(name, ty, None));
+        self
+    }
+
+    pub fn field_with_default(mut self, name: &str, ty: &str, default: &str) -> Self {
+        self.fields
+            .push(InputValueBuilder::new(name, ty, Some(default)));
+        self
+    }
+}
+
+/// An argument, input field or variable definition
+#[derive(Debug, Clone)]
+struct InputValueBuilder {
+    name: String,
+    ty: String,
+    default: Option<String>,
+}
+
+impl InputValueBuilder {
+    fn new(name: &str, ty: &str, default: Option<&str>) -> Self {
+        Self {
+            name: name.to_owned(),
+            ty: ty.to_owned(),
+            default: default.map(str::to_owned),
+        }
+    }
+}
+
+fn build_input_values(
+    values: Vec<InputValueBuilder>,
+    interner: &mut Rodeo,
+    referenced: &mut Vec<String>,
+) -> Result<Vec<InputObjectField>, BuildError> {
+    values
+        .into_iter()
+        .map(|value| {
+            let ty = parse_type(&value.ty, interner)?;
+            referenced.push(interner.resolve(&ty.name()).to_owned());
+
+            Ok(InputObjectField {
+                description: None,
+                name: interner.get_or_intern(value.name),
+                ty,
+                ty_span: Span::default(),
+                default: value
+                    .default
+                    .map(|default| parse_value(&default, interner))
+                    .transpose()?,
+                default_span: ValueSpan::default(),
+                directives: Vec::new(),
+                span: Span::default(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct OperationBuilder {
+    kind: OperationKind,
+    name: Option<String>,
+    variables: Vec<InputValueBuilder>,
+    directives: Vec<DirectiveBuilder>,
+    selection_set: SelectionSetBuilder,
+}
+
+impl OperationBuilder {
+    pub fn new(kind: OperationKind) -> Self {
+        Self {
+            kind,
+            name: None,
+            variables: Vec::new(),
+            directives: Vec::new(),
+            selection_set: SelectionSetBuilder::new(),
+        }
+    }
+
+    pub fn query() -> Self {
+        Self::new(OperationKind::Query)
+    }
+
+    pub fn mutation() -> Self {
+        Self::new(OperationKind::Mutation)
+    }
+
+    pub fn subscription() -> Self {
+        Self::new(OperationKind::Subscription)
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Define the variable `$name`, of the type written as `ty`
+    pub fn variable(mut self, name: &str, ty: &str) -> Self {
+        self.variables.push(InputValueBuilder::new(name, ty, None));
+        self
+    }
+
+    pub fn variable_with_default(mut self, name: &str, ty: &str, default: &str) -> Self {
+        self.variables
+            .push(InputValueBuilder::new(name, ty, Some(default)));
+        self
+    }
+
+    pub fn directive(mut self, directive: DirectiveBuilder) -> Self {
+        self.directives.push(directive);
+        self
+    }
+
+    pub fn selection_set(mut self, selection_set: SelectionSetBuilder) -> Self {
+        self.selection_set = selection_set;
+        self
+    }
+
+    fn build(self, interner: &mut Rodeo) -> Result<Operation, BuildError> {
+        let variable_definitions = self
+            .variables
+            .into_iter()
+            .map(|variable| {
+                Ok(VariableDefinition {
+                    name: interner.get_or_intern(variable.name),
+                    ty: parse_type(&variable.ty, interner)?,
+                    ty_span: Span::default(),
+                    default: variable
+                        .default
+                        .map(|default| parse_value(&default, interner))
+                        .transpose()?,
+                    default_span: ValueSpan::default(),
+                    span: Span::default(),
+                })
+            })
+            .collect::<Result<_, BuildError>>()?;
+
+        Ok(Operation {
+            kind: self.kind,
+            name: self.name.map(|name| interner.get_or_intern(name)),
+            variable_definitions,
+            directives: build_directives(self.directives, interner)?,
+            selection_set: self.selection_set.build(interner)?,
+            span: Span::default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FragmentBuilder {
+    name: String,
+    on: String,
+    directives: Vec<DirectiveBuilder>,
+    selection_set: SelectionSetBuilder,
+}
+
+impl FragmentBuilder {
+    pub fn new(name: &str, on: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            on: on.to_owned(),
+            directives: Vec::new(),
+            selection_set: SelectionSetBuilder::new(),
+        }
+    }
+
+    pub fn directive(mut self, directive: DirectiveBuilder) -> Self {
+        self.directives.push(directive);
+        self
+    }
+
+    pub fn selection_set(mut self, selection_set: SelectionSetBuilder) -> Self {
+        self.selection_set = selection_set;
+        self
+    }
+
+    fn build(self, interner: &mut Rodeo) -> Result<Fragment, BuildError> {
+        Ok(Fragment {
+            name: interner.get_or_intern(self.name),
+            on: interner.get_or_intern(self.on),
+            directives: build_directives(self.directives, interner)?,
+            selection_set: self.selection_set.build(interner)?,
+            span: Span::default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SelectionSetBuilder {
+    selections: Vec<SelectionBuilder>,
+}
+
+#[derive(Debug, Clone)]
+enum SelectionBuilder {
+    Field(FieldSelectionBuilder),
+    FragmentSpread(String, Vec<DirectiveBuilder>),
+    InlineFragment(Option<String>, Vec<DirectiveBuilder>, SelectionSetBuilder),
+}
+
+impl SelectionSetBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select the field `name`, without arguments or subselections
+    pub fn field(self, name: &str) -> Self {
+        self.field_with(FieldSelectionBuilder::new(name))
+    }
+
+    pub fn field_with(mut self, field: FieldSelectionBuilder) -> Self {
+        self.selections.push(SelectionBuilder::Field(field));
+        self
+    }
+
+    pub fn fragment_spread(mut self, name: &str) -> Self {
+        self.selections.push(SelectionBuilder::FragmentSpread(
+            name.to_owned(),
+            Vec::new(),
+        ));
+        self
+    }
+
+    pub fn fragment_spread_with(mut self, name: &str, directives: Vec<DirectiveBuilder>) -> Self {
+        self.selections.push(SelectionBuilder::FragmentSpread(
+            name.to_owned(),
+            directives,
+        ));
+        self
+    }
+
+    pub fn inline_fragment(mut self, on: &str, selection_set: SelectionSetBuilder) -> Self {
+        self.selections.push(SelectionBuilder::InlineFragment(
+            Some(on.to_owned()),
+            Vec::new(),
+            selection_set,
+        ));
+        self
+    }
+
+    pub fn inline_fragment_with(
+        mut self,
+        on: &str,
+        directives: Vec<DirectiveBuilder>,
+        selection_set: SelectionSetBuilder,
+    ) -> Self {
+        self.selections.push(SelectionBuilder::InlineFragment(
+            Some(on.to_owned()),
+            directives,
+            selection_set,
+        ));
+        self
+    }
+
+    fn build(self, interner: &mut Rodeo) -> Result<Vec<Selection>, BuildError> {
+        self.selections
+            .into_iter()
+            .map(|selection| {
+                Ok(match selection {
+                    SelectionBuilder::Field(field) => field.build(interner)?,
+                    SelectionBuilder::FragmentSpread(name, directives) => {
+                        Selection::FragmentSpread {
+                            name: interner.get_or_intern(name),
+                            directives: build_directives(directives, interner)?,
+                            span: Span::default(),
+                        }
+                    }
+                    SelectionBuilder::InlineFragment(on, directives, selection_set) => {
+                        Selection::InlineFragment {
+                            on: on.as_ref().map(|on| interner.get_or_intern(on)),
+                            directives: build_directives(directives, interner)?,
+                            selection_set: selection_set.build(interner)?,
+                            span: Span::default(),
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+/// A field selected in a selection set
+#[derive(Debug, Clone)]
+pub struct FieldSelectionBuilder {
+    alias: Option<String>,
+    name: String,
+    arguments: Vec<(String, String)>,
+    directives: Vec<DirectiveBuilder>,
+    selection_set: Option<SelectionSetBuilder>,
+}
+
+impl FieldSelectionBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            alias: None,
+            name: name.to_owned(),
+            arguments: Vec::new(),
+            directives: Vec::new(),
+            selection_set: None,
+        }
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.alias = Some(alias.to_owned());
+        self
+    }
+
+    /// Pass the value written as `value`, such as `"$id"` or `"[1, 2]"`
+    pub fn argument(mut self, name: &str, value: &str) -> Self {
+        self.arguments.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn directive(mut self, directive: DirectiveBuilder) -> Self {
+        self.directives.push(directive);
+        self
+    }
+
+    pub fn selection_set(mut self, selection_set: SelectionSetBuilder) -> Self {
+        self.selection_set = Some(selection_set);
+        self
+    }
+
+    fn build(self, interner: &mut Rodeo) -> Result<Selection, BuildError> {
+        let arguments = build_arguments(self.arguments, interner)?;
+
+        Ok(Selection::Field {
+            alias: self.alias.map(|alias| interner.get_or_intern(alias)),
+            name: interner.get_or_intern(self.name),
+            arguments: if arguments.is_empty() {
+                None
+            } else {
+                Some(arguments)
+            },
+            directives: build_directives(self.directives, interner)?,
+            selection_set: self
+                .selection_set
+                .map(|selection_set| selection_set.build(interner))
+                .transpose()?,
+            span: Span::default(),
+        })
+    }
+}
+
+/// A directive applied to an operation, fragment or selection
+#[derive(Debug, Clone)]
+pub struct DirectiveBuilder {
+    name: String,
+    arguments: Vec<(String, String)>,
+}
+
+impl DirectiveBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            arguments: Vec::new(),
+        }
+    }
+
+    /// Pass the value written as `value`, such as `"$id"` or `"true"`
+    pub fn argument(mut self, name: &str, value: &str) -> Self {
+        self.arguments.push((name.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+fn build_directives(
+    directives: Vec<DirectiveBuilder>,
+    interner: &mut Rodeo,
+) -> Result<Vec<Directive>, BuildError> {
+    directives
+        .into_iter()
+        .map(|directive| {
+            let arguments = build_arguments(directive.arguments, interner)?;
+
+            Ok(Directive {
+                name: interner.get_or_intern(directive.name),
+                arguments: if arguments.is_empty() {
+                    None
+                } else {
+                    Some(arguments)
+                },
+                span: Span::default(),
+            })
+        })
+        .collect()
+}
+
+fn build_arguments(
+    arguments: Vec<(String, String)>,
+    interner: &mut Rodeo,
+) -> Result<Vec<Argument>, BuildError> {
+    arguments
+        .into_iter()
+        .map(|(name, value)| {
+            Ok(Argument {
+                name: interner.get_or_intern(name),
+                value: parse_value(&value, interner)?,
+                value_span: ValueSpan::default(),
+                span: Span::default(),
+            })
+        })
+        .collect()
+}
+
+fn intern_description(description: Option<String>, interner: &mut Rodeo) -> Option<Spur> {
+    description.map(|description| interner.get_or_intern(description))
+}
+
+fn parse_type(text: &str, interner: &mut Rodeo) -> Result<Type, BuildError> {
+    GraphqlParser::parse_type_with_interner(text.as_bytes(), interner).map_err(|err| {
+        BuildError::InvalidSyntax {
+            text: text.to_owned(),
+            message: err.to_string(),
+        }
+    })
+}
+
+fn parse_value(text: &str, interner: &mut Rodeo) -> Result<Value, BuildError> {
+    GraphqlParser::parse_value_with_interner(text.as_bytes(), interner).map_err(|err| {
+        BuildError::InvalidSyntax {
+            text: text.to_owned(),
+            message: err.to_string(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{
+        BuildError, DirectiveBuilder, DocumentBuilder, FieldBuilder, FieldSelectionBuilder,
+        FragmentBuilder, InputObjectBuilder, ObjectTypeBuilder, OperationBuilder,
+        SelectionSetBuilder,
+    };
+    use crate::{ast::Document, fmt::print_document};
+
+    fn schema(interner: &mut Rodeo) -> Document {
+        DocumentBuilder::new()
+            .object(
+                ObjectTypeBuilder::object("Query").field_with(
+                    FieldBuilder::new("users", "[User!]!")
+                        .argument_with_default("first", "Int", "10")
+                        .argument("filter", "Filter"),
+                ),
+            )
+            .object(ObjectTypeBuilder::interface("Node").field("id", "ID!"))
+            .object(
+                ObjectTypeBuilder::object("User")
+                    .description("A user")
+                    .implements("Node")
+                    .field("id", "ID!")
+                    .field("role", "Role"),
+            )
+            .enum_type("Role", ["ADMIN", "USER"])
+            .input_object(InputObjectBuilder::new("Filter").field("role", "Role"))
+            .build(interner)
+            .unwrap()
+    }
+
+    #[test]
+    fn builds_schemas() {
+        let mut interner = Rodeo::default();
+        let schema = schema(&mut interner);
+
+        assert_eq!(
+            print_document(&schema, &interner),
+            concat!(
+                "interface Node {\n  id: ID!\n}\n\n",
+                "type Query {\n  users(first: Int = 10, filter: Filter): [User!]!\n}\n\n",
+                "\"\"\"A user\"\"\"\ntype User implements Node {\n  id: ID!\n  role: Role\n}\n\n",
+                "enum Role {\n  ADMIN\n  USER\n}\n\n",
+                "input Filter {\n  role: Role\n}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn builds_operations() {
+        let mut interner = Rodeo::default();
+        let schema = schema(&mut interner);
+
+        let query = DocumentBuilder::new()
+            .operation(
+                OperationBuilder::query()
+                    .name("Users")
+                    .variable("admins", "Boolean!")
+                    .selection_set(
+                        SelectionSetBuilder::new().field_with(
+                            FieldSelectionBuilder::new("users")
+                                .alias("admins")
+                                .argument("filter", "{ role: ADMIN }")
+                                .directive(
+                                    DirectiveBuilder::new("include").argument("if", "$admins"),
+                                )
+                                .selection_set(SelectionSetBuilder::new().fragment_spread("F")),
+                        ),
+                    ),
+            )
+            .fragment(
+                FragmentBuilder::new("F", "Node").selection_set(
+                    SelectionSetBuilder::new()
+                        .field("id")
+                        .inline_fragment("User", SelectionSetBuilder::new().field("role")),
+                ),
+            )
+            .build_executable(&schema, &mut interner)
+            .unwrap();
+
+        assert_eq!(
+            print_document(&query, &interner),
+            concat!(
+                "query Users($admins: Boolean!) {\n",
+                "  admins: users(filter: {role: ADMIN}) @include(if: $admins) {\n",
+                "    ...F\n  }\n}\n\n",
+                "fragment F on Node {\n  id\n  ... on User {\n    role\n  }\n}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn build_errors() {
+        let mut interner = Rodeo::default();
+
+        assert_eq!(
+            DocumentBuilder::new()
+                .object(ObjectTypeBuilder::object("Query").field("a", "[Int"))
+                .build(&mut interner)
+                .unwrap_err()
+                .to_string(),
+            "`[Int`: expected `]`, found end of input"
+        );
+        assert_eq!(
+            DocumentBuilder::new()
+                .object(ObjectTypeBuilder::object("Query").field("user", "User"))
+                .build(&mut interner)
+                .unwrap_err(),
+            BuildError::UnknownType {
+                name: "User".to_owned()
+            }
+        );
+
+        let schema = schema(&mut interner);
+
+        assert!(matches!(
+            DocumentBuilder::new()
+                .operation(
+                    OperationBuilder::query()
+                        .selection_set(SelectionSetBuilder::new().field("missing")),
+                )
+                .build_executable(&schema, &mut interner),
+            Err(BuildError::Invalid { messages }) if messages.len() == 1
+        ));
+    }
+}
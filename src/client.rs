@@ -0,0 +1,559 @@
+//! A GraphQL-over-HTTP client
+//!
+//! Requests are POSTed as JSON, built by [`request_body`]. Servers are asked
+//! for `application/graphql-response+json`, whose bodies are GraphQL
+//! responses whatever the status code, falling back to `application/json`,
+//! where only successful responses are
+//!
+//! Failures to reach the server or to read its response are reported as
+//! [`ClientError::Transport`] and [`ClientError::Status`], separately from
+//! the errors of a GraphQL response, which are [`ClientError::Graphql`]
+//!
+//! See https://graphql.github.io/graphql-over-http/draft/
+
+use std::fmt;
+
+use lasso::Rodeo;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value as JsonValue};
+
+use crate::{
+    ast::Document,
+    error::LineColumn,
+    execute::PathSegment,
+    introspection::{document_from_introspection, IntrospectionError, INTROSPECTION_QUERY},
+    request::{request_body, RequestError},
+};
+
+const GRAPHQL_RESPONSE: &str = "application/graphql-response+json";
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The document doesn't contain the operation to execute
+    Request(RequestError),
+    InvalidHeader {
+        name: String,
+    },
+    /// The request couldn't be sent, or its response couldn't be read
+    Transport(reqwest::Error),
+    /// The server responded with an error status and a body that isn't a
+    /// GraphQL response
+    Status {
+        status: u16,
+        body: String,
+    },
+    /// The body of the response isn't a well-formed GraphQL response
+    InvalidResponse {
+        message: String,
+    },
+    /// The response contains errors
+    Graphql(Vec<ResponseError>),
+    /// The data of the response couldn't be deserialized into the requested
+    /// type
+    Deserialize(serde_json::Error),
+    /// The result of the introspection query couldn't be read as a schema
+    Introspection(IntrospectionError),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(err) => write!(f, "{}", err),
+            Self::InvalidHeader { name } => write!(f, "invalid header `{}`", name),
+            Self::Transport(err) => write!(f, "request failed: {}", err),
+            Self::Status { status, body } if body.is_empty() => {
+                write!(f, "server responded with status {}", status)
+            }
+            Self::Status { status, body } => {
+                write!(f, "server responded with status {}: {}", status, body)
+            }
+            Self::InvalidResponse { message } => write!(f, "invalid response: {}", message),
+            Self::Graphql(errors) => {
+                let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+                write!(f, "{}", messages.join("\n"))
+            }
+            Self::Deserialize(err) => write!(f, "could not deserialize data: {}", err),
+            Self::Introspection(err) => write!(f, "invalid introspection result: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<RequestError> for ClientError {
+    fn from(err: RequestError) -> Self {
+        Self::Request(err)
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Transport(err)
+    }
+}
+
+/// An error in a GraphQL response
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseError {
+    pub message: String,
+    pub locations: Vec<LineColumn>,
+    /// The response path of the field that failed, which is empty for errors
+    /// that prevented execution from starting
+    pub path: Vec<PathSegment>,
+    pub extensions: Option<Map<String, JsonValue>>,
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            let path = self
+                .path
+                .iter()
+                .map(PathSegment::to_string)
+                .collect::<Vec<_>>();
+
+            write!(f, "{}: ", path.join("."))?;
+        }
+
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ResponseError {
+    pub(crate) fn from_json(value: &JsonValue) -> Option<Self> {
+        let locations = match value.get("locations") {
+            Some(JsonValue::Array(locations)) => locations
+                .iter()
+                .map(|location| {
+                    Some(LineColumn {
+                        line: location.get("line")?.as_u64()? as usize,
+                        column: location.get("column")?.as_u64()? as usize,
+                    })
+                })
+                .collect::<Option<_>>()?,
+            None | Some(JsonValue::Null) => Vec::new(),
+            Some(..) => return None,
+        };
+
+        let path = match value.get("path") {
+            Some(JsonValue::Array(path)) => path
+                .iter()
+                .map(|segment| match segment {
+                    JsonValue::String(key) => Some(PathSegment::Key(key.clone())),
+                    JsonValue::Number(idx) => Some(PathSegment::Index(idx.as_u64()? as usize)),
+                    _ => None,
+                })
+                .collect::<Option<_>>()?,
+            None | Some(JsonValue::Null) => Vec::new(),
+            Some(..) => return None,
+        };
+
+        let extensions = match value.get("extensions") {
+            Some(JsonValue::Object(extensions)) => Some(extensions.clone()),
+            None | Some(JsonValue::Null) => None,
+            Some(..) => return None,
+        };
+
+        Some(Self {
+            message: value.get("message")?.as_str()?.to_owned(),
+            locations,
+            path,
+            extensions,
+        })
+    }
+}
+
+/// A GraphQL response, which has data, errors or both
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    /// `None` if execution didn't start
+    pub data: Option<JsonValue>,
+    pub errors: Vec<ResponseError>,
+    pub extensions: Option<Map<String, JsonValue>>,
+}
+
+impl Response {
+    pub fn from_json(value: JsonValue) -> Result<Self, ClientError> {
+        let invalid = |message: &str| ClientError::InvalidResponse {
+            message: message.to_owned(),
+        };
+
+        let mut response = match value {
+            JsonValue::Object(response) => response,
+            _ => return Err(invalid("expected an object")),
+        };
+
+        let errors = match response.remove("errors") {
+            Some(JsonValue::Array(errors)) => errors
+                .iter()
+                .map(ResponseError::from_json)
+                .collect::<Option<_>>()
+                .ok_or_else(|| invalid("malformed error"))?,
+            None => Vec::new(),
+            Some(..) => return Err(invalid("`errors` is not a list")),
+        };
+
+        let data = response.remove("data");
+
+        if data.is_none() && errors.is_empty() {
+            return Err(invalid("expected `data` or `errors`"));
+        }
+
+        let extensions = match response.remove("extensions") {
+            Some(JsonValue::Object(extensions)) => Some(extensions),
+            None | Some(JsonValue::Null) => None,
+            Some(..) => return Err(invalid("`extensions` is not an object")),
+        };
+
+        Ok(Self {
+            data,
+            errors,
+            extensions,
+        })
+    }
+
+    /// Deserialize `data`, failing if the response has any errors. Partial
+    /// data can still be read from `data` when there are errors
+    pub fn into_data<T: DeserializeOwned>(self) -> Result<T, ClientError> {
+        if !self.errors.is_empty() {
+            return Err(ClientError::Graphql(self.errors));
+        }
+
+        serde_json::from_value(self.data.unwrap_or_default()).map_err(ClientError::Deserialize)
+    }
+}
+
+/// The response to a request, given its status, `Content-Type` and body
+fn read_response(
+    status: u16,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Result<Response, ClientError> {
+    let is_success = (200..300).contains(&status);
+    let is_graphql_response =
+        content_type.is_some_and(|content_type| content_type.starts_with(GRAPHQL_RESPONSE));
+
+    let status_error = || ClientError::Status {
+        status,
+        body: String::from_utf8_lossy(body).into_owned(),
+    };
+
+    if !is_success && !is_graphql_response {
+        return Err(status_error());
+    }
+
+    match serde_json::from_slice(body) {
+        Ok(value) => Response::from_json(value),
+        Err(..) if !is_success => Err(status_error()),
+        Err(err) => Err(ClientError::InvalidResponse {
+            message: err.to_string(),
+        }),
+    }
+}
+
+/// A client for a single GraphQL endpoint
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    endpoint: String,
+    headers: HeaderMap,
+}
+
+impl Client {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self::with_http_client(reqwest::Client::new(), endpoint)
+    }
+
+    /// A client that sends requests with `http`, such as one configured with
+    /// a proxy or timeouts
+    pub fn with_http_client(http: reqwest::Client, endpoint: impl Into<String>) -> Self {
+        Self {
+            http,
+            endpoint: endpoint.into(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Send the header `name` with every request, such as `Authorization`
+    pub fn header(&mut self, name: &str, value: &str) -> Result<&mut Self, ClientError> {
+        let invalid = || ClientError::InvalidHeader {
+            name: name.to_owned(),
+        };
+
+        self.headers.append(
+            HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?,
+            HeaderValue::from_str(value).map_err(|_| invalid())?,
+        );
+
+        Ok(self)
+    }
+
+    /// Execute `query`, which was parsed into `document`. See
+    /// [`request_body`] for how the operation is chosen
+    pub async fn execute(
+        &self,
+        document: &Document,
+        query: &str,
+        operation_name: Option<&str>,
+        variables: Map<String, JsonValue>,
+        interner: &Rodeo,
+    ) -> Result<Response, ClientError> {
+        let body = request_body(document, query, operation_name, variables, interner)?;
+
+        self.send(&body).await
+    }
+
+    /// Fetch the server's schema with the [standard introspection
+    /// query](INTROSPECTION_QUERY). Names are interned into `interner`
+    pub async fn introspect(&self, interner: &mut Rodeo) -> Result<Document, ClientError> {
+        let body = json!({
+            "query": INTROSPECTION_QUERY,
+            "operationName": "IntrospectionQuery",
+            "variables": {},
+        });
+
+        let data = self.send(&body).await?.into_data::<JsonValue>()?;
+
+        document_from_introspection(&data, interner).map_err(ClientError::Introspection)
+    }
+
+    /// POST a request `body` that was already built
+    pub async fn send(&self, body: &JsonValue) -> Result<Response, ClientError> {
+        let response = self
+            .http
+            .post(&self.endpoint)
+            .headers(self.headers.clone())
+            .header(
+                ACCEPT,
+                format!("{}, application/json;q=0.9", GRAPHQL_RESPONSE),
+            )
+            .json(body)
+            .send()
+            .await?;
+
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .map(str::to_owned);
+
+        let body = response.bytes().await?;
+
+        read_response(status, content_type.as_deref(), &body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use lasso::Rodeo;
+    use serde_json::{json, Map};
+
+    use super::{read_response, Client, ClientError, Response, ResponseError};
+    use crate::{
+        error::LineColumn, execute::PathSegment, fmt::print_document,
+        introspection::introspection_from_document, GraphqlParser,
+    };
+
+    const JSON: Option<&str> = Some("application/json");
+    const GRAPHQL_RESPONSE: Option<&str> = Some("application/graphql-response+json; charset=utf-8");
+
+    #[test]
+    fn data_and_errors() {
+        let response = read_response(
+            200,
+            JSON,
+            br#"{
+                "data": { "user": null },
+                "errors": [{
+                    "message": "not found",
+                    "locations": [{ "line": 1, "column": 3 }],
+                    "path": ["user", 0],
+                    "extensions": { "code": "NOT_FOUND" }
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            response,
+            Response {
+                data: Some(json!({ "user": null })),
+                errors: vec![ResponseError {
+                    message: "not found".to_owned(),
+                    locations: vec![LineColumn { line: 1, column: 3 }],
+                    path: vec![PathSegment::Key("user".to_owned()), PathSegment::Index(0)],
+                    extensions: json!({ "code": "NOT_FOUND" }).as_object().cloned(),
+                }],
+                extensions: None,
+            }
+        );
+        assert_eq!(
+            response
+                .into_data::<serde_json::Value>()
+                .unwrap_err()
+                .to_string(),
+            "user.0: not found"
+        );
+    }
+
+    #[test]
+    fn deserializes_data() {
+        let response = read_response(200, JSON, br#"{ "data": { "ids": [1, 2] } }"#).unwrap();
+
+        let data = response
+            .into_data::<std::collections::HashMap<String, Vec<u32>>>()
+            .unwrap();
+
+        assert_eq!(data["ids"], [1, 2]);
+    }
+
+    #[test]
+    fn error_statuses() {
+        // application/json responses are only GraphQL responses when successful
+        assert!(matches!(
+            read_response(400, JSON, br#"{ "errors": [{ "message": "bad" }] }"#),
+            Err(ClientError::Status { status: 400, .. })
+        ));
+        assert!(matches!(
+            read_response(
+                400,
+                GRAPHQL_RESPONSE,
+                br#"{ "errors": [{ "message": "bad" }] }"#
+            ),
+            Ok(Response { data: None, .. })
+        ));
+        assert!(matches!(
+            read_response(502, GRAPHQL_RESPONSE, b"Bad Gateway"),
+            Err(ClientError::Status { status: 502, .. })
+        ));
+        assert!(matches!(
+            read_response(200, JSON, br#"{ "errors": {} }"#),
+            Err(ClientError::InvalidResponse { .. })
+        ));
+        assert!(matches!(
+            read_response(200, JSON, b"{}"),
+            Err(ClientError::InvalidResponse { .. })
+        ));
+    }
+
+    /// Serve a single request, responding with `response`, and return the
+    /// request as received
+    fn serve(response: String) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/graphql", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+
+            // the body is JSON, so ends in a closing brace
+            while !request.ends_with(b"}") {
+                let len = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..len]);
+            }
+
+            write!(
+                stream,
+                concat!(
+                    "HTTP/1.1 200 OK\r\n",
+                    "Content-Type: application/graphql-response+json\r\n",
+                    "Content-Length: {}\r\n",
+                    "Connection: close\r\n\r\n{}",
+                ),
+                response.len(),
+                response
+            )
+            .unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn executes_over_http() {
+        let (endpoint, server) = serve(r#"{"data":{"a":1}}"#.to_owned());
+
+        let query = "query A { a }";
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(query.as_bytes(), &mut interner).unwrap();
+
+        let mut client = Client::new(endpoint);
+        client.header("Authorization", "Bearer token").unwrap();
+
+        let response = runtime()
+            .block_on(client.execute(&document, query, Some("A"), Map::new(), &interner))
+            .unwrap();
+
+        assert_eq!(response.data, Some(json!({ "a": 1 })));
+
+        let request = server.join().unwrap();
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        let head = head.to_ascii_lowercase();
+
+        assert!(head.starts_with("post /graphql http/1.1\r\n"));
+        assert!(head.contains("authorization: bearer token\r\n"));
+        assert!(head.contains("accept: application/graphql-response+json"));
+        assert!(head.contains("content-type: application/json"));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(body).unwrap(),
+            json!({ "query": query, "operationName": "A", "variables": {} })
+        );
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn introspects_schema() {
+        let schema = "type Query { users(first: Int = 10): [User!]! } type User { id: ID! }";
+
+        let mut interner = Rodeo::default();
+        let document =
+            GraphqlParser::parse_with_interner(schema.as_bytes(), &mut interner).unwrap();
+        let introspection = introspection_from_document(&document, &interner).unwrap();
+
+        let (endpoint, server) = serve(json!({ "data": introspection }).to_string());
+
+        let mut interner = Rodeo::default();
+        let fetched = runtime()
+            .block_on(Client::new(endpoint).introspect(&mut interner))
+            .unwrap();
+
+        assert_eq!(
+            print_document(&fetched, &interner),
+            concat!(
+                "schema {\n  query: Query\n}\n\n",
+                "type Query {\n  users(first: Int = 10): [User!]!\n}\n\n",
+                "type User {\n  id: ID!\n}\n",
+            )
+        );
+        assert!(server.join().unwrap().contains("IntrospectionQuery"));
+    }
+
+    #[test]
+    fn invalid_header() {
+        assert_eq!(
+            Client::new("http://localhost")
+                .header("bad header", "value")
+                .unwrap_err()
+                .to_string(),
+            "invalid header `bad header`"
+        );
+    }
+}
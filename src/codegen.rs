@@ -0,0 +1,1413 @@
+//! Generation of Rust types from a schema, and from the operations run
+//! against it
+//!
+//! For a schema, objects and input objects become structs, enums become enums,
+//! and interfaces and unions become enums with a variant for each of their
+//! possible types. Nullable types are wrapped in `Option` and lists become
+//! `Vec`s.
+//!
+//! For an operation, a struct is generated for its variables and for each
+//! selection set in its response. These refer to the schema's enums, input
+//! objects and custom scalars by name, so the output of
+//! [`generate_rust_types`] needs to be in scope.
+//!
+//! [`generate_typescript_operations`] generates TypeScript interfaces for the
+//! variables and responses of operations instead, along with the schema types
+//! they refer to.
+
+use std::collections::{HashMap, HashSet};
+
+use lasso::{Rodeo, Spur};
+
+use crate::ast::{
+    Directive, Document, Enum, FieldDefinition, InputObjectField, Operation, Selection, Type,
+    TypeDefinitionRef, TypeKind, BUILT_IN_SCALARS,
+};
+
+#[derive(Debug, Clone)]
+pub struct RustCodegenConfig {
+    /// Traits derived by every generated type. Enums additionally derive
+    /// `Copy`, `PartialEq`, `Eq` and `Hash`
+    pub derives: Vec<String>,
+    /// The Rust type to use for a scalar, by scalar name. This overrides the
+    /// mapping of built-in scalars, and custom scalars without an entry become
+    /// aliases of `String`
+    pub scalars: HashMap<String, String>,
+    /// Emit `#[serde(...)]` attributes so that generated types serialize using
+    /// their GraphQL names. `Serialize` and `Deserialize` still have to be
+    /// added to `derives`
+    pub serde: bool,
+}
+
+impl Default for RustCodegenConfig {
+    fn default() -> Self {
+        Self {
+            derives: vec!["Debug".to_owned(), "Clone".to_owned()],
+            scalars: HashMap::new(),
+            serde: false,
+        }
+    }
+}
+
+const RUST_KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+/// Rust source declaring a type for every type in `schema`, in alphabetical
+/// order
+pub fn generate_rust_types(
+    schema: &Document,
+    interner: &Rodeo,
+    config: &RustCodegenConfig,
+) -> String {
+    let mut writer = RustWriter {
+        schema,
+        interner,
+        config,
+        buffer: String::new(),
+    };
+
+    writer.write_document();
+
+    writer.buffer
+}
+
+/// Rust source declaring the variables and response types of every
+/// operation in `document`, ordered by operation name
+///
+/// `document` is expected to be valid against `schema`. Fields selected by
+/// fragments that don't always apply, or that may be skipped with `@skip` or
+/// `@include`, become `Option`s.
+pub fn generate_rust_operations(
+    schema: &Document,
+    document: &Document,
+    interner: &Rodeo,
+    config: &RustCodegenConfig,
+) -> String {
+    let mut writer = OperationWriter {
+        writer: RustWriter {
+            schema,
+            interner,
+            config,
+            buffer: String::new(),
+        },
+        collector: FieldCollector {
+            schema,
+            document,
+            interner,
+        },
+    };
+
+    let mut operations = document.operations.values().collect::<Vec<_>>();
+    operations.sort_by_key(|operation| operation.name.map(|name| interner.resolve(&name)));
+
+    for operation in operations {
+        writer.write_operation(operation);
+    }
+
+    writer.writer.buffer
+}
+
+/// `camelCase` or `SCREAMING_CASE` as `snake_case`
+pub(crate) fn to_snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake = String::with_capacity(name.len());
+
+    for (idx, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && idx > 0 {
+            let prev = chars[idx - 1];
+            let next_is_lower = chars.get(idx + 1).is_some_and(char::is_ascii_lowercase);
+
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+
+        snake.push(c.to_ascii_lowercase());
+    }
+
+    snake
+}
+
+/// `camelCase` or `SCREAMING_CASE` as `PascalCase`
+fn to_pascal_case(name: &str) -> String {
+    to_snake_case(name)
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// `name` as a valid Rust identifier
+fn escape_ident(name: String) -> String {
+    match name.as_str() {
+        // these can't be raw identifiers
+        "crate" | "self" | "Self" | "super" => name + "_",
+        _ if RUST_KEYWORDS.contains(&name.as_str()) => format!("r#{}", name),
+        _ => name,
+    }
+}
+
+struct RustWriter<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+    config: &'a RustCodegenConfig,
+    buffer: String,
+}
+
+impl<'a> RustWriter<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn write_document(&mut self) {
+        let mut types = self
+            .schema
+            .types()
+            .filter(|ty| !self.name(ty.name()).starts_with("__"))
+            .collect::<Vec<_>>();
+
+        types.sort_by_key(|ty| self.name(ty.name()));
+
+        for ty in types {
+            match ty {
+                TypeDefinitionRef::Scalar(scalar) => {
+                    let name = self.name(scalar.name);
+
+                    if BUILT_IN_SCALARS.contains(&name) || self.config.scalars.contains_key(name) {
+                        continue;
+                    }
+
+                    self.write_description(scalar.description, "");
+                    self.buffer
+                        .push_str(&format!("pub type {} = String;\n\n", name));
+                }
+                TypeDefinitionRef::Object(object) => self.write_struct(
+                    object.name,
+                    object.description,
+                    object.fields.as_deref().unwrap_or_default(),
+                ),
+                TypeDefinitionRef::Interface(interface) => {
+                    self.write_abstract_enum(interface.name, interface.description)
+                }
+                TypeDefinitionRef::Union(union) => {
+                    self.write_abstract_enum(union.name, union.description)
+                }
+                TypeDefinitionRef::Enum(enum_def) => self.write_enum(enum_def),
+                TypeDefinitionRef::InputObject(input) => self.write_input_object(
+                    input.name,
+                    input.description,
+                    input.fields.as_deref().unwrap_or_default(),
+                ),
+            }
+        }
+    }
+
+    fn write_description(&mut self, description: Option<Spur>, indent: &str) {
+        if let Some(description) = description {
+            for line in self.name(description).lines() {
+                self.buffer.push_str(indent);
+                self.buffer.push_str("///");
+
+                if !line.is_empty() {
+                    self.buffer.push(' ');
+                    self.buffer.push_str(line);
+                }
+
+                self.buffer.push('\n');
+            }
+        }
+    }
+
+    fn write_derives(&mut self, extra: &[&str]) {
+        let mut derives = self
+            .config
+            .derives
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+
+        for derive in extra {
+            if !derives.contains(derive) {
+                derives.push(derive);
+            }
+        }
+
+        if !derives.is_empty() {
+            self.buffer
+                .push_str(&format!("#[derive({})]\n", derives.join(", ")));
+        }
+    }
+
+    fn write_rename(&mut self, rust_name: &str, graphql_name: &str, indent: &str) {
+        if self.config.serde && rust_name.trim_start_matches("r#") != graphql_name {
+            self.buffer.push_str(&format!(
+                "{}#[serde(rename = \"{}\")]\n",
+                indent, graphql_name
+            ));
+        }
+    }
+
+    fn write_struct(&mut self, name: Spur, description: Option<Spur>, fields: &[FieldDefinition]) {
+        self.write_description(description, "");
+        self.write_derives(&[]);
+        self.buffer
+            .push_str(&format!("pub struct {} {{\n", self.name(name)));
+
+        for field in fields {
+            let field_name = escape_ident(to_snake_case(self.name(field.name)));
+            let ty = self.rust_type(&field.ty, Some(name));
+
+            self.write_description(field.description, "    ");
+            self.write_rename(&field_name, self.name(field.name), "    ");
+            self.buffer
+                .push_str(&format!("    pub {}: {},\n", field_name, ty));
+        }
+
+        self.buffer.push_str("}\n\n");
+    }
+
+    fn write_input_object(
+        &mut self,
+        name: Spur,
+        description: Option<Spur>,
+        fields: &[InputObjectField],
+    ) {
+        self.write_description(description, "");
+        self.write_derives(&[]);
+        self.buffer
+            .push_str(&format!("pub struct {} {{\n", self.name(name)));
+
+        let mut required = Vec::new();
+        let mut optional = Vec::new();
+
+        for field in fields {
+            let field_name = escape_ident(to_snake_case(self.name(field.name)));
+            let is_optional = field.ty.is_nullable() || field.default.is_some();
+
+            let inner = self.non_null_rust_type(&field.ty, Some(name));
+
+            self.write_description(field.description, "    ");
+            self.write_rename(&field_name, self.name(field.name), "    ");
+
+            if is_optional {
+                if self.config.serde {
+                    self.buffer
+                        .push_str("    #[serde(skip_serializing_if = \"Option::is_none\")]\n");
+                }
+
+                self.buffer
+                    .push_str(&format!("    pub {}: Option<{}>,\n", field_name, inner));
+                optional.push((field_name, inner));
+            } else {
+                self.buffer
+                    .push_str(&format!("    pub {}: {},\n", field_name, inner));
+                required.push((field_name, inner));
+            }
+        }
+
+        self.buffer.push_str("}\n\n");
+
+        // a constructor taking the required fields, and a builder method for
+        // each optional one
+        self.buffer
+            .push_str(&format!("impl {} {{\n    pub fn new(", self.name(name)));
+
+        let params = required
+            .iter()
+            .map(|(field_name, ty)| format!("{}: {}", field_name, ty))
+            .collect::<Vec<_>>();
+
+        self.buffer.push_str(&params.join(", "));
+        self.buffer.push_str(") -> Self {\n        Self {\n");
+
+        for (field_name, _) in &required {
+            self.buffer
+                .push_str(&format!("            {},\n", field_name));
+        }
+
+        for (field_name, _) in &optional {
+            self.buffer
+                .push_str(&format!("            {}: None,\n", field_name));
+        }
+
+        self.buffer.push_str("        }\n    }\n");
+
+        for (field_name, ty) in &optional {
+            self.buffer.push_str(&format!(
+                "\n    pub fn {name}(mut self, {name}: {ty}) -> Self {{\n",
+                name = field_name,
+                ty = ty
+            ));
+            self.buffer.push_str(&format!(
+                "        self.{name} = Some({name});\n        self\n    }}\n",
+                name = field_name
+            ));
+        }
+
+        self.buffer.push_str("}\n\n");
+    }
+
+    fn write_enum(&mut self, enum_def: &Enum) {
+        self.write_description(enum_def.description, "");
+        self.write_derives(&["Copy", "PartialEq", "Eq", "Hash"]);
+        self.buffer
+            .push_str(&format!("pub enum {} {{\n", self.name(enum_def.name)));
+
+        for variant in &enum_def.variants {
+            let variant_name = escape_ident(to_pascal_case(self.name(variant.name)));
+
+            self.write_description(variant.description, "    ");
+            self.write_rename(&variant_name, self.name(variant.name), "    ");
+            self.buffer.push_str(&format!("    {},\n", variant_name));
+        }
+
+        self.buffer.push_str("}\n\n");
+    }
+
+    /// An interface or union, as an enum of its possible types
+    fn write_abstract_enum(&mut self, name: Spur, description: Option<Spur>) {
+        let mut possible_types = self.schema.possible_types(name);
+        possible_types.sort_by_key(|&ty| self.name(ty));
+
+        self.write_description(description, "");
+        self.write_derives(&[]);
+
+        if self.config.serde {
+            self.buffer.push_str("#[serde(tag = \"__typename\")]\n");
+        }
+
+        self.buffer
+            .push_str(&format!("pub enum {} {{\n", self.name(name)));
+
+        for ty in possible_types {
+            let variant = if self.reaches(ty, name, &mut HashSet::new()) {
+                format!("Box<{}>", self.name(ty))
+            } else {
+                self.name(ty).to_owned()
+            };
+
+            self.buffer
+                .push_str(&format!("    {}({}),\n", self.name(ty), variant));
+        }
+
+        self.buffer.push_str("}\n\n");
+    }
+
+    /// The Rust type of a field. References to types that contain `parent`
+    /// are boxed, so that the generated types have a finite size
+    fn rust_type(&self, ty: &Type, parent: Option<Spur>) -> String {
+        let inner = self.non_null_rust_type(ty, parent);
+
+        if ty.is_nullable() {
+            format!("Option<{}>", inner)
+        } else {
+            inner
+        }
+    }
+
+    /// The Rust type of a field, ignoring whether it is nullable
+    fn non_null_rust_type(&self, ty: &Type, parent: Option<Spur>) -> String {
+        match ty {
+            Type::Named { name, .. } => {
+                let inner = self.named_type(*name);
+
+                let is_recursive =
+                    parent.is_some_and(|parent| self.reaches(*name, parent, &mut HashSet::new()));
+
+                if is_recursive {
+                    format!("Box<{}>", inner)
+                } else {
+                    inner
+                }
+            }
+            // a `Vec` is already a pointer, so its items never need boxing
+            Type::List { base, .. } => format!("Vec<{}>", self.rust_type(base, None)),
+        }
+    }
+
+    fn named_type(&self, name: Spur) -> String {
+        let name = self.name(name);
+
+        if let Some(ty) = self.config.scalars.get(name) {
+            return ty.clone();
+        }
+
+        match name {
+            "Int" => "i32",
+            "Float" => "f64",
+            "String" | "ID" => "String",
+            "Boolean" => "bool",
+            _ => name,
+        }
+        .to_owned()
+    }
+
+    /// Whether a value of type `from` may contain a value of type `target`
+    /// without any indirection
+    fn reaches(&self, from: Spur, target: Spur, visited: &mut HashSet<Spur>) -> bool {
+        if from == target {
+            return true;
+        }
+
+        if !visited.insert(from) {
+            return false;
+        }
+
+        let embedded = match self.schema.type_kind(from) {
+            Some(TypeKind::Object) => self
+                .schema
+                .fields_of(from)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|field| embedded_type(&field.ty))
+                .collect(),
+            Some(TypeKind::Interface) | Some(TypeKind::Union) => self.schema.possible_types(from),
+            Some(TypeKind::InputObject) => self.schema.input_objects[&from]
+                .fields
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|field| embedded_type(&field.ty))
+                .collect(),
+            Some(TypeKind::Scalar) | Some(TypeKind::Enum) | None => Vec::new(),
+        };
+
+        embedded
+            .into_iter()
+            .any(|ty| self.reaches(ty, target, visited))
+    }
+}
+
+/// The type stored inline by a field of type `ty`, which is none for lists
+fn embedded_type(ty: &Type) -> Option<Spur> {
+    match ty {
+        Type::Named { name, .. } => Some(*name),
+        Type::List { .. } => None,
+    }
+}
+
+/// A response key in a selection set, along with every selection of it
+struct CollectedField<'a> {
+    key: Spur,
+    name: Spur,
+    /// The type the field was selected on, which is narrower than the parent
+    /// type when selected by a fragment on one of its possible types
+    parent_type: Spur,
+    selection_sets: Vec<&'a [Selection]>,
+    /// Whether the field may be missing from the response
+    is_conditional: bool,
+}
+
+struct OperationWriter<'a> {
+    writer: RustWriter<'a>,
+    collector: FieldCollector<'a>,
+}
+
+impl<'a> OperationWriter<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.writer.name(name)
+    }
+
+    fn write_operation(&mut self, operation: &'a Operation) {
+        let operation_name = match operation.name {
+            Some(name) => to_pascal_case(self.name(name)),
+            None => to_pascal_case(operation.kind.as_str()),
+        };
+
+        self.write_variables(&operation_name, operation);
+
+        let root = match self
+            .writer
+            .schema
+            .root_operation_type_or_default(operation.kind, self.writer.interner)
+        {
+            Some(root) => root,
+            None => return,
+        };
+
+        self.write_selection_struct(
+            format!("{}Response", operation_name),
+            root,
+            vec![&operation.selection_set],
+        );
+    }
+
+    fn write_variables(&mut self, operation_name: &str, operation: &Operation) {
+        self.writer.write_derives(&[]);
+        self.writer
+            .buffer
+            .push_str(&format!("pub struct {}Variables {{\n", operation_name));
+
+        for variable in &operation.variable_definitions {
+            let field_name = escape_ident(to_snake_case(self.name(variable.name)));
+            let ty = self.writer.rust_type(&variable.ty, None);
+
+            self.writer
+                .write_rename(&field_name, self.name(variable.name), "    ");
+
+            if self.writer.config.serde && variable.ty.is_nullable() {
+                self.writer
+                    .buffer
+                    .push_str("    #[serde(skip_serializing_if = \"Option::is_none\")]\n");
+            }
+
+            self.writer
+                .buffer
+                .push_str(&format!("    pub {}: {},\n", field_name, ty));
+        }
+
+        self.writer.buffer.push_str("}\n\n");
+    }
+
+    /// A struct for the fields selected from `parent_type`, followed by the
+    /// structs of any fields with selection sets of their own
+    fn write_selection_struct(
+        &mut self,
+        struct_name: String,
+        parent_type: Spur,
+        selection_sets: Vec<&'a [Selection]>,
+    ) {
+        let mut fields = Vec::new();
+
+        for selection_set in selection_sets {
+            self.collector
+                .collect_fields(parent_type, selection_set, false, &mut fields);
+        }
+
+        let mut children = Vec::new();
+
+        self.writer.write_derives(&[]);
+        self.writer
+            .buffer
+            .push_str(&format!("pub struct {} {{\n", struct_name));
+
+        for field in fields {
+            let key = self.name(field.key);
+            let field_name = escape_ident(to_snake_case(key));
+
+            let definition = self
+                .writer
+                .schema
+                .fields_of(field.parent_type)
+                .and_then(|fields| fields.iter().find(|def| def.name == field.name));
+
+            let mut ty = match definition {
+                Some(definition) if field.selection_sets.is_empty() => {
+                    self.writer.rust_type(&definition.ty, None)
+                }
+                Some(definition) => {
+                    let child_name = format!("{}{}", struct_name, to_pascal_case(key));
+                    let ty = wrap_type(&definition.ty, child_name.clone());
+
+                    children.push((child_name, definition.ty.name(), field.selection_sets));
+
+                    ty
+                }
+                None if self.name(field.name) == "__typename" => "String".to_owned(),
+                // unknown fields are left for validation to report
+                None => continue,
+            };
+
+            if field.is_conditional && !ty.starts_with("Option<") {
+                ty = format!("Option<{}>", ty);
+            }
+
+            if let Some(definition) = definition {
+                self.writer
+                    .write_description(definition.description, "    ");
+            }
+
+            self.writer.write_rename(&field_name, key, "    ");
+            self.writer
+                .buffer
+                .push_str(&format!("    pub {}: {},\n", field_name, ty));
+        }
+
+        self.writer.buffer.push_str("}\n\n");
+
+        for (child_name, child_type, selection_sets) in children {
+            self.write_selection_struct(child_name, child_type, selection_sets);
+        }
+    }
+}
+
+/// Collects the fields of selection sets, through the fragments of
+/// `document`
+struct FieldCollector<'a> {
+    schema: &'a Document,
+    document: &'a Document,
+    interner: &'a Rodeo,
+}
+
+impl<'a> FieldCollector<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    /// Flatten the fields of `selection_set`, including those selected by
+    /// fragments, merging selections of the same response key
+    fn collect_fields(
+        &self,
+        parent_type: Spur,
+        selection_set: &'a [Selection],
+        is_conditional: bool,
+        fields: &mut Vec<CollectedField<'a>>,
+    ) {
+        for selection in selection_set {
+            match selection {
+                Selection::Field {
+                    alias,
+                    name,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    let key = alias.unwrap_or(*name);
+                    let is_conditional =
+                        is_conditional || self.has_conditional_directive(directives);
+
+                    let field = match fields.iter_mut().find(|field| field.key == key) {
+                        Some(field) => {
+                            field.is_conditional &= is_conditional;
+                            field
+                        }
+                        None => {
+                            fields.push(CollectedField {
+                                key,
+                                name: *name,
+                                parent_type,
+                                selection_sets: Vec::new(),
+                                is_conditional,
+                            });
+                            fields.last_mut().unwrap()
+                        }
+                    };
+
+                    if let Some(selection_set) = selection_set {
+                        field.selection_sets.push(selection_set);
+                    }
+                }
+                Selection::FragmentSpread {
+                    name, directives, ..
+                } => {
+                    let fragment = match self.document.fragments.get(name) {
+                        Some(fragment) => fragment,
+                        None => continue,
+                    };
+
+                    let is_conditional = is_conditional
+                        || self.has_conditional_directive(directives)
+                        || !self.always_applies(fragment.on, parent_type);
+
+                    self.collect_fields(
+                        self.narrowest_type(fragment.on, parent_type),
+                        &fragment.selection_set,
+                        is_conditional,
+                        fields,
+                    );
+                }
+                Selection::InlineFragment {
+                    on,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    let on = on.unwrap_or(parent_type);
+                    let is_conditional = is_conditional
+                        || self.has_conditional_directive(directives)
+                        || !self.always_applies(on, parent_type);
+
+                    self.collect_fields(
+                        self.narrowest_type(on, parent_type),
+                        selection_set,
+                        is_conditional,
+                        fields,
+                    );
+                }
+            }
+        }
+    }
+
+    /// The type to look up the fields of a fragment on: the type it is spread
+    /// into if that is an object type the fragment always applies to, and
+    /// otherwise its type condition. An abstract type can have fewer fields
+    /// than the type condition even when the fragment always applies, such as
+    /// an interface with a single implementation
+    fn narrowest_type(&self, type_condition: Spur, parent_type: Spur) -> Spur {
+        let is_object = self.schema.output_objects.contains_key(&parent_type);
+
+        if is_object && self.always_applies(type_condition, parent_type) {
+            parent_type
+        } else {
+            type_condition
+        }
+    }
+
+    /// Whether `directives` include `@skip` or `@include`
+    fn has_conditional_directive(&self, directives: &[Directive]) -> bool {
+        directives
+            .iter()
+            .any(|directive| matches!(self.name(directive.name), "skip" | "include"))
+    }
+
+    /// Whether a fragment on `type_condition` applies to every value of
+    /// `parent_type`
+    fn always_applies(&self, type_condition: Spur, parent_type: Spur) -> bool {
+        let possible_types = self.schema.possible_types(type_condition);
+
+        self.schema
+            .possible_types(parent_type)
+            .iter()
+            .all(|ty| possible_types.contains(ty))
+    }
+}
+
+/// `base` wrapped in the lists and `Option`s of `ty`
+fn wrap_type(ty: &Type, base: String) -> String {
+    let inner = match ty {
+        Type::Named { .. } => base,
+        Type::List { base: item, .. } => format!("Vec<{}>", wrap_type(item, base)),
+    };
+
+    if ty.is_nullable() {
+        format!("Option<{}>", inner)
+    } else {
+        inner
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TypeScriptCodegenConfig {
+    /// The TypeScript type to use for a scalar, by scalar name. This overrides
+    /// the mapping of built-in scalars, and custom scalars without an entry
+    /// become aliases of `unknown`
+    pub scalars: HashMap<String, String>,
+}
+
+/// TypeScript declaring the variables and response types of every operation
+/// in `document`, ordered by operation name, preceded by the enums, input
+/// objects and custom scalars they refer to
+///
+/// Nullable types become unions with `null`, lists become `Array`s and enums
+/// become unions of string literals. Like [`generate_rust_operations`],
+/// fields that may be missing from the response are optional
+pub fn generate_typescript_operations(
+    schema: &Document,
+    document: &Document,
+    interner: &Rodeo,
+    config: &TypeScriptCodegenConfig,
+) -> String {
+    let mut writer = TypeScriptWriter {
+        collector: FieldCollector {
+            schema,
+            document,
+            interner,
+        },
+        config,
+        referenced: HashSet::new(),
+        buffer: String::new(),
+    };
+
+    let mut operations = document.operations.values().collect::<Vec<_>>();
+    operations.sort_by_key(|operation| operation.name.map(|name| interner.resolve(&name)));
+
+    for operation in operations {
+        writer.write_operation(operation);
+    }
+
+    writer.declarations() + &writer.buffer
+}
+
+struct TypeScriptWriter<'a> {
+    collector: FieldCollector<'a>,
+    config: &'a TypeScriptCodegenConfig,
+    /// The enums, input objects and custom scalars referred to so far
+    referenced: HashSet<Spur>,
+    buffer: String,
+}
+
+impl<'a> TypeScriptWriter<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.collector.name(name)
+    }
+
+    fn write_operation(&mut self, operation: &'a Operation) {
+        let operation_name = match operation.name {
+            Some(name) => to_pascal_case(self.name(name)),
+            None => to_pascal_case(operation.kind.as_str()),
+        };
+
+        let mut variables = String::new();
+
+        for variable in &operation.variable_definitions {
+            let optional = if variable.ty.is_nullable() { "?" } else { "" };
+            let ty = self.typescript_type(&variable.ty);
+
+            variables.push_str(&format!(
+                "  {}{}: {};
+",
+                self.name(variable.name),
+                optional,
+                ty
+            ));
+        }
+
+        self.buffer.push_str(&format!(
+            "export interface {}Variables {{\n{}}}\n\n",
+            operation_name, variables
+        ));
+
+        let schema = self.collector.schema;
+
+        let root =
+            match schema.root_operation_type_or_default(operation.kind, self.collector.interner) {
+                Some(root) => root,
+                None => return,
+            };
+
+        let response = self.object_type(root, vec![&operation.selection_set], 0);
+
+        self.buffer.push_str(&format!(
+            "export interface {}Response {}\n\n",
+            operation_name, response
+        ));
+    }
+
+    /// An object type literal for the fields selected from `parent_type`,
+    /// nested `depth` levels deep
+    fn object_type(
+        &mut self,
+        parent_type: Spur,
+        selection_sets: Vec<&'a [Selection]>,
+        depth: usize,
+    ) -> String {
+        let mut fields = Vec::new();
+
+        for selection_set in selection_sets {
+            self.collector
+                .collect_fields(parent_type, selection_set, false, &mut fields);
+        }
+
+        let indent = "  ".repeat(depth + 1);
+        let mut object = "{\n".to_owned();
+
+        for field in fields {
+            let definition = self
+                .collector
+                .schema
+                .fields_of(field.parent_type)
+                .and_then(|fields| fields.iter().find(|def| def.name == field.name));
+
+            let ty = match definition {
+                Some(definition) if field.selection_sets.is_empty() => {
+                    self.typescript_type(&definition.ty)
+                }
+                Some(definition) => {
+                    let inner =
+                        self.object_type(definition.ty.name(), field.selection_sets, depth + 1);
+
+                    wrap_typescript_type(&definition.ty, inner)
+                }
+                None if self.name(field.name) == "__typename" => "string".to_owned(),
+                // unknown fields are left for validation to report
+                None => continue,
+            };
+
+            if let Some(description) = definition.and_then(|definition| definition.description) {
+                write_doc_comment(&mut object, self.name(description), &indent);
+            }
+
+            let optional = if field.is_conditional { "?" } else { "" };
+
+            object.push_str(&format!(
+                "{}{}{}: {};\n",
+                indent,
+                self.name(field.key),
+                optional,
+                ty
+            ));
+        }
+
+        object.push_str(&"  ".repeat(depth));
+        object.push('}');
+
+        object
+    }
+
+    fn typescript_type(&mut self, ty: &Type) -> String {
+        let inner = match ty {
+            Type::Named { name, .. } => self.named_type(*name),
+            Type::List { base, .. } => format!("Array<{}>", self.typescript_type(base)),
+        };
+
+        if ty.is_nullable() {
+            format!("{} | null", inner)
+        } else {
+            inner
+        }
+    }
+
+    fn named_type(&mut self, name: Spur) -> String {
+        let ty = self.name(name);
+
+        if let Some(ty) = self.config.scalars.get(ty) {
+            return ty.clone();
+        }
+
+        match ty {
+            "Int" | "Float" => "number",
+            "String" | "ID" => "string",
+            "Boolean" => "boolean",
+            _ => {
+                self.referenced.insert(name);
+                ty
+            }
+        }
+        .to_owned()
+    }
+
+    /// The declarations of the referenced enums, input objects and custom
+    /// scalars, along with those the input objects refer to, in alphabetical
+    /// order
+    fn declarations(&mut self) -> String {
+        let schema = self.collector.schema;
+
+        let mut pending = self.referenced.iter().copied().collect::<Vec<_>>();
+        let mut declarations = Vec::new();
+        let mut declared = HashSet::new();
+
+        while let Some(name) = pending.pop() {
+            if !declared.insert(name) {
+                continue;
+            }
+
+            let declaration = match schema.get_type(name) {
+                Some(TypeDefinitionRef::Scalar(scalar))
+                    if !BUILT_IN_SCALARS.contains(&self.name(name))
+                        && !self.config.scalars.contains_key(self.name(name)) =>
+                {
+                    let mut declaration = String::new();
+
+                    if let Some(description) = scalar.description {
+                        write_doc_comment(&mut declaration, self.name(description), "");
+                    }
+
+                    declaration + &format!("export type {} = unknown;\n\n", self.name(name))
+                }
+                Some(TypeDefinitionRef::Enum(enum_def)) => {
+                    let mut declaration = String::new();
+
+                    if let Some(description) = enum_def.description {
+                        write_doc_comment(&mut declaration, self.name(description), "");
+                    }
+
+                    let values = enum_def
+                        .variants
+                        .iter()
+                        .map(|variant| format!("\"{}\"", self.name(variant.name)))
+                        .collect::<Vec<_>>();
+
+                    declaration
+                        + &format!(
+                            "export type {} = {};\n\n",
+                            self.name(name),
+                            values.join(" | ")
+                        )
+                }
+                Some(TypeDefinitionRef::InputObject(input)) => {
+                    let mut declaration = String::new();
+
+                    if let Some(description) = input.description {
+                        write_doc_comment(&mut declaration, self.name(description), "");
+                    }
+
+                    declaration.push_str(&format!("export interface {} {{\n", self.name(name)));
+
+                    for field in input.fields.iter().flatten() {
+                        if let Some(description) = field.description {
+                            write_doc_comment(&mut declaration, self.name(description), "  ");
+                        }
+
+                        let optional = if field.ty.is_nullable() { "?" } else { "" };
+                        let ty = self.typescript_type(&field.ty);
+
+                        pending.push(field.ty.name());
+
+                        declaration.push_str(&format!(
+                            "  {}{}: {};\n",
+                            self.name(field.name),
+                            optional,
+                            ty
+                        ));
+                    }
+
+                    declaration + "}\n\n"
+                }
+                _ => continue,
+            };
+
+            declarations.push((self.name(name), declaration));
+        }
+
+        declarations.sort_unstable();
+
+        declarations
+            .into_iter()
+            .map(|(_, declaration)| declaration)
+            .collect()
+    }
+}
+
+/// `base` wrapped in the lists and `null` unions of `ty`
+fn wrap_typescript_type(ty: &Type, base: String) -> String {
+    let inner = match ty {
+        Type::Named { .. } => base,
+        Type::List { base: item, .. } => format!("Array<{}>", wrap_typescript_type(item, base)),
+    };
+
+    if ty.is_nullable() {
+        format!("{} | null", inner)
+    } else {
+        inner
+    }
+}
+
+/// `description` as a JSDoc comment
+fn write_doc_comment(buffer: &mut String, description: &str, indent: &str) {
+    let lines = description.lines().collect::<Vec<_>>();
+
+    if let [line] = lines.as_slice() {
+        buffer.push_str(&format!("{}/** {} */\n", indent, line));
+        return;
+    }
+
+    buffer.push_str(&format!("{}/**\n", indent));
+
+    for line in lines {
+        buffer.push_str(&format!("{} *", indent));
+
+        if !line.is_empty() {
+            buffer.push(' ');
+            buffer.push_str(line);
+        }
+
+        buffer.push('\n');
+    }
+
+    buffer.push_str(&format!("{} */\n", indent));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lasso::Rodeo;
+
+    use super::{
+        generate_rust_operations, generate_rust_types, generate_typescript_operations,
+        RustCodegenConfig, TypeScriptCodegenConfig,
+    };
+    use crate::GraphqlParser;
+
+    const SCHEMA: &str = r#"
+type Query { user(id: ID!): User users: [User!]! node: Node }
+"A user"
+type User implements Node { id: ID! type: String role: Role tags: [[String]!] joined: Date }
+interface Node { id: ID! }
+union SearchResult = User
+enum Role { ADMIN READ_ONLY }
+input Filter { role: Role, self: Boolean! }
+scalar Date
+"#;
+
+    fn operations(source: &str) -> String {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse operation");
+
+        generate_rust_operations(&schema, &document, &interner, &RustCodegenConfig::default())
+    }
+
+    fn types(config: &RustCodegenConfig) -> String {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+
+        generate_rust_types(&schema, &interner, config)
+    }
+
+    #[test]
+    fn schema_types() {
+        let expected = r#"pub type Date = String;
+
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub role: Option<Role>,
+    pub self_: bool,
+}
+
+impl Filter {
+    pub fn new(self_: bool) -> Self {
+        Self {
+            self_,
+            role: None,
+        }
+    }
+
+    pub fn role(mut self, role: Role) -> Self {
+        self.role = Some(role);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    User(User),
+}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub user: Option<User>,
+    pub users: Vec<User>,
+    pub node: Option<Node>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    Admin,
+    ReadOnly,
+}
+
+#[derive(Debug, Clone)]
+pub enum SearchResult {
+    User(User),
+}
+
+/// A user
+#[derive(Debug, Clone)]
+pub struct User {
+    pub id: String,
+    pub r#type: Option<String>,
+    pub role: Option<Role>,
+    pub tags: Option<Vec<Vec<Option<String>>>>,
+    pub joined: Option<Date>,
+}
+
+"#;
+
+        assert_eq!(types(&RustCodegenConfig::default()), expected);
+    }
+
+    #[test]
+    fn serde_attributes() {
+        let config = RustCodegenConfig {
+            derives: vec!["Serialize".to_owned()],
+            serde: true,
+            ..RustCodegenConfig::default()
+        };
+
+        let types = types(&config);
+
+        assert!(types.contains("#[derive(Serialize)]\npub struct Filter {"));
+        assert!(types.contains("    #[serde(rename = \"self\")]\n    pub self_: bool,"));
+        assert!(types.contains("#[serde(tag = \"__typename\")]\npub enum Node {"));
+        assert!(types.contains("    #[serde(rename = \"READ_ONLY\")]\n    ReadOnly,"));
+    }
+
+    #[test]
+    fn scalar_mapping() {
+        let config = RustCodegenConfig {
+            scalars: HashMap::from([
+                ("Date".to_owned(), "chrono::NaiveDate".to_owned()),
+                ("ID".to_owned(), "u64".to_owned()),
+            ]),
+            ..RustCodegenConfig::default()
+        };
+
+        let types = types(&config);
+
+        // mapped scalars are used directly rather than through an alias
+        assert!(!types.contains("pub type Date"), "{}", types);
+        assert!(
+            types.contains("    pub joined: Option<chrono::NaiveDate>,"),
+            "{}",
+            types
+        );
+        assert!(types.contains("    pub id: u64,"), "{}", types);
+    }
+
+    #[test]
+    fn recursive_types_are_boxed() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(
+            b"input Filter { not: Filter, and: [Filter!] } type Tree { parent: Tree! }",
+            &mut interner,
+        )
+        .unwrap();
+
+        let types = generate_rust_types(&schema, &interner, &RustCodegenConfig::default());
+
+        assert!(
+            types.contains("    pub not: Option<Box<Filter>>,"),
+            "{}",
+            types
+        );
+        assert!(
+            types.contains("    pub and: Option<Vec<Filter>>,"),
+            "{}",
+            types
+        );
+        assert!(types.contains("    pub parent: Box<Tree>,"), "{}", types);
+    }
+
+    #[test]
+    fn operation_types() {
+        let expected = r#"#[derive(Debug, Clone)]
+pub struct UserQueryVariables {
+    pub id: String,
+    pub filter: Option<Filter>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserQueryResponse {
+    pub me: Option<UserQueryResponseMe>,
+    pub users: Vec<UserQueryResponseUsers>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserQueryResponseMe {
+    pub id: String,
+    pub role: Option<Role>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserQueryResponseUsers {
+    pub r#type: Option<String>,
+}
+
+"#;
+
+        let operations = operations(concat!(
+            "query UserQuery($id: ID!, $filter: Filter) ",
+            "{ me: user(id: $id) { id role } users { type } }",
+        ));
+
+        assert_eq!(operations, expected);
+    }
+
+    #[test]
+    fn conditional_fields_are_optional() {
+        let operations = operations(concat!(
+            "query Q($a: Boolean!) { users { id @skip(if: $a) ...F @include(if: $a) } } ",
+            "fragment F on User { userId: id }",
+        ));
+
+        assert!(
+            operations.contains("    pub id: Option<String>,"),
+            "{}",
+            operations
+        );
+        assert!(
+            operations.contains("    pub user_id: Option<String>,"),
+            "{}",
+            operations
+        );
+    }
+
+    #[test]
+    fn fragments_on_a_narrower_type() {
+        // `Node` has a single implementation, so the fragment always applies,
+        // but `joined` is only a field of `User`
+        let operations = operations("{ node { id ... on User { joined } } }");
+
+        assert!(operations.contains("    pub id: String,"), "{}", operations);
+        assert!(
+            operations.contains("    pub joined: Option<Date>,"),
+            "{}",
+            operations
+        );
+    }
+
+    fn typescript(source: &str, config: &TypeScriptCodegenConfig) -> String {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse operation");
+
+        generate_typescript_operations(&schema, &document, &interner, config)
+    }
+
+    #[test]
+    fn typescript_operation_types() {
+        let expected = concat!(
+            "export interface Filter {\n",
+            "  role?: Role | null;\n",
+            "  self: boolean;\n",
+            "}\n\n",
+            "export type Role = \"ADMIN\" | \"READ_ONLY\";\n\n",
+            "export interface UserQueryVariables {\n",
+            "  id: string;\n",
+            "  filter?: Filter | null;\n",
+            "}\n\n",
+            "export interface UserQueryResponse {\n",
+            "  me: {\n",
+            "    id: string;\n",
+            "    tags: Array<Array<string | null>> | null;\n",
+            "    joined?: Date | null;\n",
+            "  } | null;\n",
+            "  users: Array<{\n",
+            "    type: string | null;\n",
+            "  }>;\n",
+            "}\n\n",
+        );
+
+        let operations = typescript(
+            concat!(
+                "query UserQuery($id: ID!, $filter: Filter) ",
+                "{ me: user(id: $id) { id tags ...F @include(if: true) } users { type } } ",
+                "fragment F on User { joined }",
+            ),
+            &TypeScriptCodegenConfig {
+                scalars: HashMap::from([("Date".to_owned(), "Date".to_owned())]),
+            },
+        );
+
+        assert_eq!(operations, expected);
+    }
+
+    #[test]
+    fn typescript_custom_scalars_and_descriptions() {
+        let operations = typescript(
+            "{ user(id: 1) { joined role } }",
+            &TypeScriptCodegenConfig::default(),
+        );
+
+        assert!(
+            operations.starts_with(concat!(
+                "export type Date = unknown;\n\n",
+                "export type Role = \"ADMIN\" | \"READ_ONLY\";\n\n",
+                "export interface QueryVariables {\n}\n\n",
+            )),
+            "{}",
+            operations
+        );
+        assert!(
+            operations.contains("    joined: Date | null;\n    role: Role | null;\n"),
+            "{}",
+            operations
+        );
+    }
+}
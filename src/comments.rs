@@ -0,0 +1,483 @@
+//! Migration of `#` comments to descriptions, for schemas written before
+//! descriptions were part of the spec
+//!
+//! A block of comments on consecutive lines becomes the description of the
+//! definition, field, argument, input field or enum value directly after it.
+//! The rest of the source is left as it was, so comments that aren't
+//! converted are kept. Blocks where it isn't clear what they describe, or
+//! that can't become a description, are reported as [`Ambiguity`]s instead
+//!
+//! Blocks are matched to what follows them by span, so this needs the `spans`
+//! feature
+
+use std::{collections::HashMap, fmt};
+
+use lasso::Spur;
+
+use crate::{
+    ast::{
+        Comment, Definition, EnumVariant, FieldDefinition, InputObjectField, Span, TypeDefinition,
+        TypeDefinitionRef, TypeExtension,
+    },
+    fmt::write_description,
+    GraphqlParseError, GraphqlParser,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// The source, with each converted block of comments replaced by a
+    /// description
+    pub source: String,
+    /// The number of blocks converted
+    pub converted: usize,
+    /// The blocks that weren't converted, in source order
+    pub ambiguities: Vec<Ambiguity>,
+}
+
+/// A block of comments that was left as it was
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ambiguity {
+    /// The span of the block, from the first `#` to the end of the last
+    /// comment
+    pub span: Span,
+    pub kind: AmbiguityKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmbiguityKind {
+    /// The comment follows other tokens on its line, so it may describe them
+    /// rather than what follows it
+    Trailing,
+    /// A blank line separates the comments from what follows them
+    Detached,
+    /// What follows the comments already has a description
+    AlreadyDescribed,
+    /// What follows the comments can't have a description, such as an
+    /// operation, an extension or the end of a block
+    NotDescribable,
+}
+
+impl fmt::Display for AmbiguityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Trailing => "the comment follows other tokens on its line",
+            Self::Detached => "a blank line separates the comments from what follows them",
+            Self::AlreadyDescribed => "what follows the comments already has a description",
+            Self::NotDescribable => "what follows the comments can't have a description",
+        })
+    }
+}
+
+/// Convert the `#` comments in `source` that directly precede type system
+/// definitions, and the fields, arguments, input fields and enum values in
+/// them, to descriptions
+pub fn comments_to_descriptions(source: &[u8]) -> Result<Migration, GraphqlParseError> {
+    let (document, comments) = GraphqlParser::parse_with_comments(source)?;
+
+    let mut targets = Targets::default();
+
+    if let Some(schema) = &document.schema {
+        targets.add(schema.span, schema.description);
+    }
+
+    for ty in document.types() {
+        targets.add(ty.span(), ty.description());
+        targets.members(ty);
+    }
+
+    for directive in document.directive_definitions.values() {
+        targets.add(directive.span, directive.description);
+        targets.input_values(directive.arguments.as_deref().unwrap_or_default());
+    }
+
+    // extensions can't have descriptions, but what they add can
+    for extension in &document.type_extensions {
+        targets.members(extension_ref(extension));
+    }
+
+    for definition in &document.duplicates {
+        match definition {
+            Definition::Schema(schema) => targets.add(schema.span, schema.description),
+            Definition::TypeDecl(ty) => {
+                let ty = definition_ref(ty);
+                targets.add(ty.span(), ty.description());
+                targets.members(ty);
+            }
+            Definition::Directive(directive) => {
+                targets.add(directive.span, directive.description);
+                targets.input_values(directive.arguments.as_deref().unwrap_or_default());
+            }
+            Definition::TypeExtension(extension) => targets.members(extension_ref(extension)),
+            Definition::Operation(..)
+            | Definition::Fragment(..)
+            | Definition::SchemaExtension(..) => {}
+        }
+    }
+
+    let mut edits = Vec::new();
+    let mut ambiguities = Vec::new();
+
+    for block in blocks(source, &comments) {
+        let description = block.text(source);
+
+        if description.is_empty() {
+            continue;
+        }
+
+        let (next, line_breaks) = skip_ignored(source, block.span.end);
+
+        let kind = match targets.0.get(&next) {
+            _ if block.is_trailing => AmbiguityKind::Trailing,
+            _ if line_breaks > 1 => AmbiguityKind::Detached,
+            Some(true) => AmbiguityKind::AlreadyDescribed,
+            Some(false) => {
+                edits.push((block.span, description, block.indent(source)));
+                continue;
+            }
+            None => AmbiguityKind::NotDescribable,
+        };
+
+        ambiguities.push(Ambiguity {
+            span: block.span,
+            kind,
+        });
+    }
+
+    let converted = edits.len();
+    let mut migrated = source.to_vec();
+
+    // from the end, so the spans of earlier blocks stay correct
+    for (span, description, indent) in edits.into_iter().rev() {
+        let mut replacement = String::new();
+        write_description(&mut replacement, &description, &indent);
+
+        migrated.splice(span.start..span.end, replacement.into_bytes());
+    }
+
+    Ok(Migration {
+        source: String::from_utf8_lossy(&migrated).into_owned(),
+        converted,
+        ambiguities,
+    })
+}
+
+/// The start of each definition, field, argument, input field and enum value
+/// that can have a description, and whether it has one. Their spans start at
+/// their description
+#[derive(Default)]
+struct Targets(HashMap<usize, bool>);
+
+impl Targets {
+    fn add(&mut self, span: Span, description: Option<Spur>) {
+        self.0.insert(span.start, description.is_some());
+    }
+
+    fn members(&mut self, ty: TypeDefinitionRef) {
+        match ty {
+            TypeDefinitionRef::Object(object) => {
+                self.fields(object.fields.as_deref().unwrap_or_default());
+            }
+            TypeDefinitionRef::Interface(interface) => self.fields(&interface.fields),
+            TypeDefinitionRef::InputObject(input) => {
+                self.input_values(input.fields.as_deref().unwrap_or_default());
+            }
+            TypeDefinitionRef::Enum(enum_def) => self.variants(&enum_def.variants),
+            TypeDefinitionRef::Scalar(..) | TypeDefinitionRef::Union(..) => {}
+        }
+    }
+
+    fn fields(&mut self, fields: &[FieldDefinition]) {
+        for field in fields {
+            self.add(field.span, field.description);
+            self.input_values(field.arguments.as_deref().unwrap_or_default());
+        }
+    }
+
+    fn input_values(&mut self, values: &[InputObjectField]) {
+        for value in values {
+            self.add(value.span, value.description);
+        }
+    }
+
+    fn variants(&mut self, variants: &[EnumVariant]) {
+        for variant in variants {
+            self.add(variant.span, variant.description);
+        }
+    }
+}
+
+fn definition_ref(ty: &TypeDefinition) -> TypeDefinitionRef<'_> {
+    match ty {
+        TypeDefinition::Scalar(scalar) => TypeDefinitionRef::Scalar(scalar),
+        TypeDefinition::Object(object) => TypeDefinitionRef::Object(object),
+        TypeDefinition::Interface(interface) => TypeDefinitionRef::Interface(interface),
+        TypeDefinition::Union(union) => TypeDefinitionRef::Union(union),
+        TypeDefinition::Enum(enum_def) => TypeDefinitionRef::Enum(enum_def),
+        TypeDefinition::InputObject(input) => TypeDefinitionRef::InputObject(input),
+    }
+}
+
+fn extension_ref(extension: &TypeExtension) -> TypeDefinitionRef<'_> {
+    match extension {
+        TypeExtension::Scalar(scalar) => TypeDefinitionRef::Scalar(scalar),
+        TypeExtension::Object(object) => TypeDefinitionRef::Object(object),
+        TypeExtension::Interface(interface) => TypeDefinitionRef::Interface(interface),
+        TypeExtension::Union(union) => TypeDefinitionRef::Union(union),
+        TypeExtension::Enum(enum_def) => TypeDefinitionRef::Enum(enum_def),
+        TypeExtension::InputObject(input) => TypeDefinitionRef::InputObject(input),
+    }
+}
+
+/// Comments on consecutive lines, or a single comment following other tokens
+/// on its line
+struct Block<'a> {
+    comments: &'a [Comment],
+    span: Span,
+    is_trailing: bool,
+}
+
+impl Block<'_> {
+    /// The text of the comments, without the `#` and a single space after it,
+    /// trailing whitespace, or blank lines at the start or end
+    fn text(&self, source: &[u8]) -> String {
+        let lines = self
+            .comments
+            .iter()
+            .map(|comment| {
+                let text = comment.text(source);
+                let text = text.strip_prefix(' ').unwrap_or(&text);
+
+                text.trim_end().to_owned()
+            })
+            .collect::<Vec<_>>();
+
+        let first = lines.iter().position(|line| !line.is_empty());
+        let last = lines.iter().rposition(|line| !line.is_empty());
+
+        match (first, last) {
+            (Some(first), Some(last)) => lines[first..=last].join("\n"),
+            _ => String::new(),
+        }
+    }
+
+    /// The whitespace before the block on its first line
+    fn indent(&self, source: &[u8]) -> String {
+        let start = line_start(source, self.span.start);
+
+        String::from_utf8_lossy(&source[start..self.span.start]).into_owned()
+    }
+}
+
+fn blocks<'a>(source: &[u8], comments: &'a [Comment]) -> Vec<Block<'a>> {
+    let mut blocks = Vec::<Block>::new();
+
+    for (idx, comment) in comments.iter().enumerate() {
+        let is_trailing = !source[line_start(source, comment.start)..comment.start]
+            .iter()
+            .all(|b| matches!(b, b' ' | b'\t'));
+
+        if let Some(block) = blocks.last_mut() {
+            let between = &source[block.span.end..comment.start];
+            let is_next_line =
+                between.iter().all(u8::is_ascii_whitespace) && skip_ignored(between, 0).1 == 1;
+
+            if !block.is_trailing && !is_trailing && is_next_line {
+                let first = idx - block.comments.len();
+                block.comments = &comments[first..=idx];
+                block.span.end = comment.end;
+                continue;
+            }
+        }
+
+        blocks.push(Block {
+            comments: std::slice::from_ref(comment),
+            span: Span::new(comment.start, comment.end),
+            is_trailing,
+        });
+    }
+
+    blocks
+}
+
+/// The offset of the start of the line containing `offset`
+fn line_start(source: &[u8], offset: usize) -> usize {
+    source[..offset]
+        .iter()
+        .rposition(|b| matches!(b, b'\n' | b'\r'))
+        .map_or(0, |idx| idx + 1)
+}
+
+/// The offset of the first byte from `offset` that isn't whitespace or a
+/// comma, and the number of line breaks before it
+fn skip_ignored(source: &[u8], mut offset: usize) -> (usize, usize) {
+    let mut line_breaks = 0;
+
+    while let Some(&b) = source.get(offset) {
+        match b {
+            b'\n' => line_breaks += 1,
+            // `\r\n` is a single line break
+            b'\r' if source.get(offset + 1) != Some(&b'\n') => line_breaks += 1,
+            b' ' | b'\t' | b',' | b'\r' => {}
+            _ => break,
+        }
+
+        offset += 1;
+    }
+
+    (offset, line_breaks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{comments_to_descriptions, AmbiguityKind};
+
+    fn migrate(source: &str) -> (String, Vec<(String, AmbiguityKind)>) {
+        let migration = comments_to_descriptions(source.as_bytes()).unwrap();
+
+        let ambiguities = migration
+            .ambiguities
+            .iter()
+            .map(|ambiguity| {
+                (
+                    source[ambiguity.span.start..ambiguity.span.end].to_owned(),
+                    ambiguity.kind,
+                )
+            })
+            .collect();
+
+        (migration.source, ambiguities)
+    }
+
+    #[test]
+    fn converts_comments() {
+        let source = r#"# A user
+type User {
+  # The user's name,
+  #   indented
+  #
+  # and a second paragraph
+  name(
+    # The format
+    format: String
+  ): String
+  # "Quoted"
+  email: String
+  #
+  # surrounded by blank lines
+  #
+  age: Int
+}
+
+extend type User {
+  # Added later
+  id: ID!
+}
+
+# A role
+enum Role {
+  #ADMIN
+  ADMIN
+}
+
+# Trailing backslash\
+scalar Path
+"#;
+
+        let expected = r#""""A user"""
+type User {
+  """
+  The user's name,
+    indented
+
+  and a second paragraph
+  """
+  name(
+    """The format"""
+    format: String
+  ): String
+  """
+  "Quoted"
+  """
+  email: String
+  """surrounded by blank lines"""
+  age: Int
+}
+
+extend type User {
+  """Added later"""
+  id: ID!
+}
+
+"""A role"""
+enum Role {
+  """ADMIN"""
+  ADMIN
+}
+
+"Trailing backslash\\"
+scalar Path
+"#;
+
+        let (migrated, ambiguities) = migrate(source);
+
+        assert_eq!(migrated, expected);
+        assert_eq!(ambiguities, []);
+    }
+
+    #[test]
+    fn reports_ambiguities() {
+        let source = r#"# Copyright
+
+type A { # trailing
+  # already
+  "described"
+  b: Int
+  # at the end
+}
+
+# not describable
+extend type A @a
+
+# an operation
+query { a }
+# end of input
+"#;
+
+        let (migrated, ambiguities) = migrate(source);
+
+        assert_eq!(migrated, source);
+        assert_eq!(
+            ambiguities,
+            [
+                ("# Copyright".to_owned(), AmbiguityKind::Detached),
+                ("# trailing".to_owned(), AmbiguityKind::Trailing),
+                ("# already".to_owned(), AmbiguityKind::AlreadyDescribed),
+                ("# at the end".to_owned(), AmbiguityKind::NotDescribable),
+                (
+                    "# not describable".to_owned(),
+                    AmbiguityKind::NotDescribable
+                ),
+                ("# an operation".to_owned(), AmbiguityKind::NotDescribable),
+                ("# end of input".to_owned(), AmbiguityKind::NotDescribable),
+            ]
+        );
+    }
+
+    #[test]
+    fn converted_schema_parses_with_descriptions() {
+        let source = "# Q\r\ntype Query {\r\n  # a\r\n  # b\r\n  a: Int\r\n}\r\n";
+
+        let migrated = comments_to_descriptions(source.as_bytes()).unwrap();
+        assert_eq!(migrated.converted, 2);
+
+        let mut interner = lasso::Rodeo::default();
+        let document =
+            crate::GraphqlParser::parse_with_interner(migrated.source.as_bytes(), &mut interner)
+                .unwrap();
+        let query = document.output_objects.values().next().unwrap();
+        let field = &query.fields.as_ref().unwrap()[0];
+
+        assert_eq!(interner.resolve(&query.description.unwrap()), "Q");
+        assert_eq!(interner.resolve(&field.description.unwrap()), "a\nb");
+    }
+}
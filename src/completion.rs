@@ -0,0 +1,1027 @@
+//! Schema-aware completion of executable documents, the building block for
+//! editors and the language server
+//!
+//! The source is only lexed up to the cursor, rather than parsed, so that
+//! completions are available while the document is incomplete. Type system
+//! definitions in the source are skipped, and nothing is completed inside them
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        DirectiveLocation, Document, InputObjectField, Keyword, OperationKind, Token, Type,
+        TypeKind, BUILT_IN_SCALARS,
+    },
+    fmt::print_type,
+    lexer::Lexer,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionKind,
+    /// The type of a field, argument or variable, the kind of a type, or the
+    /// arguments of a directive
+    pub detail: Option<String>,
+    pub description: Option<String>,
+    /// The reason given for deprecating a field, argument or enum value
+    pub deprecation: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompletionKind {
+    Field,
+    Argument,
+    InputField,
+    EnumValue,
+    Directive,
+    Type,
+    Fragment,
+    Variable,
+    Keyword,
+}
+
+/// The completions valid at byte `offset` of the executable document
+/// `source`, given the schema it's written against. A name the cursor is at
+/// the end of is treated as a prefix to filter the completions by
+///
+/// Names in `schema` must have been interned into `interner`. No completions
+/// are returned inside strings and comments, or if the source before the
+/// cursor can't be lexed
+pub fn complete(
+    schema: &Document,
+    interner: &Rodeo,
+    source: &str,
+    offset: usize,
+) -> Vec<CompletionItem> {
+    let offset = offset.min(source.len());
+
+    if !source.is_char_boundary(offset) {
+        return Vec::new();
+    }
+
+    let mut lexer = Lexer::new(&source.as_bytes()[..offset]);
+    let mut tokens = Vec::new();
+
+    for token in lexer.by_ref() {
+        match token {
+            Ok(token) => tokens.push(token),
+            Err(..) => return Vec::new(),
+        }
+    }
+
+    let names = lexer.interner();
+    let last_end = tokens.last().map_or(0, |token| token.span.end);
+
+    if is_in_comment(&source[last_end..offset]) {
+        return Vec::new();
+    }
+
+    // a name or variable the cursor is at the end of is still being typed
+    let mut prefix = "";
+    let mut is_variable = false;
+
+    if let Some(last) = tokens.last().filter(|token| token.span.end == offset) {
+        match last.token {
+            Token::Variable(name) => {
+                prefix = names.resolve(&name);
+                is_variable = true;
+                tokens.pop();
+            }
+            Token::Dollar => {
+                is_variable = true;
+                tokens.pop();
+            }
+            ref token => {
+                if let Some(name) = name_of(token, names) {
+                    prefix = name;
+                    tokens.pop();
+                }
+            }
+        }
+    }
+
+    let mut completer = Completer {
+        schema,
+        interner,
+        names,
+        stack: Vec::new(),
+        expect: Expect::Definition,
+        variables: Vec::new(),
+    };
+
+    for token in &tokens {
+        completer.token(&token.token);
+    }
+
+    let mut items = if is_variable {
+        completer.variables()
+    } else {
+        completer.completions(source)
+    };
+
+    items.retain(|item| item.label.starts_with(prefix));
+
+    items
+}
+
+/// Whether the text between the last token and the cursor ends in a comment
+fn is_in_comment(gap: &str) -> bool {
+    gap.rsplit_once('#')
+        .is_some_and(|(_, comment)| !comment.contains(['\n', '\r']))
+}
+
+/// The text of a name token. Keywords are only reserved in some positions,
+/// so may also be names
+fn name_of<'a>(token: &Token, names: &'a Rodeo) -> Option<&'a str> {
+    match token {
+        Token::Name(name) => Some(names.resolve(name)),
+        Token::Keyword(keyword) => Some(keyword.as_str()),
+        _ => None,
+    }
+}
+
+/// Something that may be followed by arguments, directives and a selection
+/// set, such as a field or an operation
+#[derive(Debug, Clone, Copy)]
+struct Head<'a> {
+    location: DirectiveLocation,
+    arguments: Option<&'a [InputObjectField]>,
+    /// The type selected from by a following selection set, if one may
+    /// follow, which is `None` if it isn't known
+    selection_set: Option<Option<Spur>>,
+}
+
+impl Head<'_> {
+    fn is_operation(&self) -> bool {
+        matches!(
+            self.location,
+            DirectiveLocation::Query
+                | DirectiveLocation::Mutation
+                | DirectiveLocation::Subscription
+        )
+    }
+}
+
+/// A bracketed list the cursor may be inside of
+enum Frame<'a> {
+    SelectionSet(Option<Spur>),
+    Arguments {
+        definitions: &'a [InputObjectField],
+        given: Vec<String>,
+        head: Head<'a>,
+    },
+    VariableDefinitions(Head<'a>),
+    List(Option<Type>),
+    Object {
+        ty: Option<Spur>,
+        given: Vec<String>,
+    },
+}
+
+/// What is expected after the tokens seen so far
+#[derive(Debug, Clone)]
+enum Expect<'a> {
+    Definition,
+    /// Inside a type system definition, at the given depth of braces
+    TypeSystem(usize),
+    FragmentName,
+    FragmentOn,
+    TypeCondition(DirectiveLocation),
+    Selection,
+    /// After `...`, either a fragment name or an inline fragment
+    Spread,
+    Head(Head<'a>),
+    DirectiveName(Head<'a>),
+    Directive {
+        arguments: Option<&'a [InputObjectField]>,
+        head: Head<'a>,
+    },
+    ArgumentName,
+    Colon(Option<Type>),
+    Value(Option<Type>),
+    ObjectFieldName,
+    Variable,
+    VariableType,
+    VariableTypeEnd(Option<Spur>),
+}
+
+struct Completer<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+    /// The interner the lexed tokens were interned into
+    names: &'a Rodeo,
+    stack: Vec<Frame<'a>>,
+    expect: Expect<'a>,
+    /// The variables defined by the current operation, along with their
+    /// types as written
+    variables: Vec<(String, String)>,
+}
+
+impl<'a> Completer<'a> {
+    fn token(&mut self, token: &Token) {
+        while !self.step(token) {}
+    }
+
+    /// What is expected after a complete value, selection or definition in
+    /// the innermost frame
+    fn base(&self) -> Expect<'a> {
+        match self.stack.last() {
+            None => Expect::Definition,
+            Some(Frame::SelectionSet(..)) => Expect::Selection,
+            Some(Frame::Arguments { .. }) => Expect::ArgumentName,
+            Some(Frame::VariableDefinitions(..)) => Expect::Variable,
+            Some(Frame::List(item)) => Expect::Value(item.clone()),
+            Some(Frame::Object { .. }) => Expect::ObjectFieldName,
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Spur> {
+        self.interner.get(name)
+    }
+
+    fn root(&self, kind: OperationKind) -> Option<Spur> {
+        self.schema
+            .root_operation_type_or_default(kind, self.interner)
+    }
+
+    fn selection_type(&self) -> Option<Spur> {
+        match self.stack.last() {
+            Some(Frame::SelectionSet(ty)) => *ty,
+            _ => None,
+        }
+    }
+
+    /// Update the state for `token`, returning `false` if the token ended
+    /// the current construct and should be handled again in the new state
+    fn step(&mut self, token: &Token) -> bool {
+        if let Expect::TypeSystem(depth) = self.expect {
+            self.expect = match token {
+                Token::OpenCurlyBrace => Expect::TypeSystem(depth + 1),
+                Token::CloseCurlyBrace => Expect::TypeSystem(depth.saturating_sub(1)),
+                Token::Keyword(
+                    Keyword::Query | Keyword::Mutation | Keyword::Subscription | Keyword::Fragment,
+                ) if depth == 0 => {
+                    self.expect = Expect::Definition;
+                    return false;
+                }
+                _ => Expect::TypeSystem(depth),
+            };
+
+            return true;
+        }
+
+        match (token, self.stack.last()) {
+            (
+                Token::CloseParen,
+                Some(Frame::Arguments { head, .. } | Frame::VariableDefinitions(head)),
+            ) => {
+                self.expect = Expect::Head(*head);
+                self.stack.pop();
+                return true;
+            }
+            (Token::CloseSquareBrace, Some(Frame::List(..)))
+            | (Token::CloseCurlyBrace, Some(Frame::SelectionSet(..) | Frame::Object { .. })) => {
+                self.stack.pop();
+                self.expect = self.base();
+                return true;
+            }
+            _ => {}
+        }
+
+        let name = name_of(token, self.names);
+
+        match self.expect.clone() {
+            Expect::TypeSystem(..) => unreachable!("handled above"),
+            Expect::Definition => match token {
+                Token::Keyword(
+                    keyword @ (Keyword::Query | Keyword::Mutation | Keyword::Subscription),
+                ) => {
+                    let (kind, location) = match keyword {
+                        Keyword::Query => (OperationKind::Query, DirectiveLocation::Query),
+                        Keyword::Mutation => (OperationKind::Mutation, DirectiveLocation::Mutation),
+                        _ => (OperationKind::Subscription, DirectiveLocation::Subscription),
+                    };
+
+                    self.variables.clear();
+                    self.expect = Expect::Head(Head {
+                        location,
+                        arguments: None,
+                        selection_set: Some(self.root(kind)),
+                    });
+                }
+                Token::Keyword(Keyword::Fragment) => {
+                    self.variables.clear();
+                    self.expect = Expect::FragmentName;
+                }
+                Token::OpenCurlyBrace => {
+                    self.variables.clear();
+                    self.stack
+                        .push(Frame::SelectionSet(self.root(OperationKind::Query)));
+                    self.expect = Expect::Selection;
+                }
+                _ => self.expect = Expect::TypeSystem(0),
+            },
+            Expect::FragmentName => {
+                if name.is_none() {
+                    self.expect = Expect::Definition;
+                    return false;
+                }
+
+                self.expect = Expect::FragmentOn;
+            }
+            Expect::FragmentOn => {
+                if *token != Token::Keyword(Keyword::On) {
+                    self.expect = Expect::Definition;
+                    return false;
+                }
+
+                self.expect = Expect::TypeCondition(DirectiveLocation::FragmentDefinition);
+            }
+            Expect::TypeCondition(location) => {
+                let ty = name.and_then(|name| self.lookup(name));
+
+                self.expect = Expect::Head(Head {
+                    location,
+                    arguments: None,
+                    selection_set: Some(ty),
+                });
+
+                if name.is_none() {
+                    return false;
+                }
+            }
+            Expect::Selection => match token {
+                Token::DotDotDot => self.expect = Expect::Spread,
+                _ => {
+                    if let Some(name) = name {
+                        let field = self
+                            .selection_type()
+                            .and_then(|ty| self.schema.fields_of(ty))
+                            .and_then(|fields| {
+                                fields
+                                    .iter()
+                                    .find(|field| Some(field.name) == self.lookup(name))
+                            });
+
+                        self.expect = Expect::Head(Head {
+                            location: DirectiveLocation::Field,
+                            arguments: field.and_then(|field| field.arguments.as_deref()),
+                            selection_set: Some(field.map(|field| field.ty.name())),
+                        });
+                    }
+                }
+            },
+            Expect::Spread => match token {
+                Token::Keyword(Keyword::On) => {
+                    self.expect = Expect::TypeCondition(DirectiveLocation::InlineFragment);
+                }
+                Token::AtSign | Token::OpenCurlyBrace => {
+                    self.expect = Expect::Head(Head {
+                        location: DirectiveLocation::InlineFragment,
+                        arguments: None,
+                        selection_set: Some(self.selection_type()),
+                    });
+
+                    return false;
+                }
+                _ if name.is_some() => {
+                    self.expect = Expect::Head(Head {
+                        location: DirectiveLocation::FragmentSpread,
+                        arguments: None,
+                        selection_set: None,
+                    });
+                }
+                _ => {
+                    self.expect = Expect::Selection;
+                    return false;
+                }
+            },
+            Expect::Head(head) => match token {
+                Token::OpenParen if head.is_operation() => {
+                    self.stack.push(Frame::VariableDefinitions(head));
+                    self.expect = Expect::Variable;
+                }
+                Token::OpenParen => {
+                    self.stack.push(Frame::Arguments {
+                        definitions: head.arguments.unwrap_or_default(),
+                        given: Vec::new(),
+                        head,
+                    });
+                    self.expect = Expect::ArgumentName;
+                }
+                Token::AtSign => self.expect = Expect::DirectiveName(head),
+                Token::OpenCurlyBrace => {
+                    self.stack
+                        .push(Frame::SelectionSet(head.selection_set.flatten()));
+                    self.expect = Expect::Selection;
+                }
+                // an alias, followed by the name of the field
+                Token::Colon if head.location == DirectiveLocation::Field => {
+                    self.expect = Expect::Selection;
+                }
+                // the name of the operation
+                Token::Name(..) if head.is_operation() => {}
+                _ => {
+                    self.expect = self.base();
+                    return false;
+                }
+            },
+            Expect::DirectiveName(head) => {
+                let Some(name) = name else {
+                    self.expect = Expect::Head(head);
+                    return false;
+                };
+
+                let arguments = self
+                    .lookup(name)
+                    .and_then(|name| self.schema.directive_definitions.get(&name))
+                    .and_then(|directive| directive.arguments.as_deref());
+
+                self.expect = Expect::Directive { arguments, head };
+            }
+            Expect::Directive { arguments, head } => {
+                if *token != Token::OpenParen {
+                    self.expect = Expect::Head(head);
+                    return false;
+                }
+
+                self.stack.push(Frame::Arguments {
+                    definitions: arguments.unwrap_or_default(),
+                    given: Vec::new(),
+                    head,
+                });
+                self.expect = Expect::ArgumentName;
+            }
+            Expect::ArgumentName => {
+                if let (
+                    Some(name),
+                    Some(Frame::Arguments {
+                        definitions, given, ..
+                    }),
+                ) = (name, self.stack.last_mut())
+                {
+                    let ty = definitions
+                        .iter()
+                        .find(|argument| self.interner.resolve(&argument.name) == name)
+                        .map(|argument| argument.ty.clone());
+
+                    given.push(name.to_owned());
+                    self.expect = Expect::Colon(ty);
+                }
+            }
+            Expect::ObjectFieldName => {
+                if let (Some(name), Some(Frame::Object { ty, given })) =
+                    (name, self.stack.last_mut())
+                {
+                    let ty = ty
+                        .and_then(|ty| self.schema.input_objects.get(&ty))
+                        .and_then(|object| object.fields.as_deref())
+                        .and_then(|fields| {
+                            fields
+                                .iter()
+                                .find(|field| self.interner.resolve(&field.name) == name)
+                        })
+                        .map(|field| field.ty.clone());
+
+                    given.push(name.to_owned());
+                    self.expect = Expect::Colon(ty);
+                }
+            }
+            Expect::Colon(ty) => {
+                if *token != Token::Colon {
+                    self.expect = self.base();
+                    return false;
+                }
+
+                self.expect = Expect::Value(ty);
+            }
+            Expect::Value(ty) => match token {
+                Token::OpenSquareBrace => {
+                    let item = match ty {
+                        Some(Type::List { base, .. }) => Some(*base),
+                        ty => ty,
+                    };
+
+                    self.stack.push(Frame::List(item.clone()));
+                    self.expect = Expect::Value(item);
+                }
+                Token::OpenCurlyBrace => {
+                    let ty = ty
+                        .map(|ty| ty.name())
+                        .filter(|ty| self.schema.input_objects.contains_key(ty));
+
+                    self.stack.push(Frame::Object {
+                        ty,
+                        given: Vec::new(),
+                    });
+                    self.expect = Expect::ObjectFieldName;
+                }
+                Token::Dollar => {}
+                _ => self.expect = self.base(),
+            },
+            Expect::Variable => match token {
+                Token::Variable(name) => {
+                    let name = self.names.resolve(name).to_owned();
+                    self.variables.push((name, String::new()));
+                }
+                Token::Colon => self.expect = Expect::VariableType,
+                _ => {}
+            },
+            Expect::VariableType | Expect::VariableTypeEnd(..) => match token {
+                Token::OpenSquareBrace => self.push_variable_type("["),
+                Token::CloseSquareBrace => self.push_variable_type("]"),
+                Token::Bang => self.push_variable_type("!"),
+                Token::Eq => {
+                    let ty = match self.expect {
+                        Expect::VariableTypeEnd(ty) => ty,
+                        _ => None,
+                    };
+
+                    self.expect = Expect::Value(ty.map(|name| Type::Named {
+                        name,
+                        nullable: true,
+                    }));
+                }
+                Token::AtSign => {
+                    self.expect = Expect::DirectiveName(Head {
+                        location: DirectiveLocation::VariableDefinition,
+                        arguments: None,
+                        selection_set: None,
+                    });
+                }
+                _ if matches!(self.expect, Expect::VariableType) && name.is_some() => {
+                    let name = name.unwrap_or_default();
+
+                    self.push_variable_type(name);
+                    self.expect = Expect::VariableTypeEnd(self.lookup(name));
+                }
+                _ => {
+                    self.expect = Expect::Variable;
+                    return false;
+                }
+            },
+        }
+
+        true
+    }
+
+    fn push_variable_type(&mut self, text: &str) {
+        if let Some((_, ty)) = self.variables.last_mut() {
+            ty.push_str(text);
+        }
+    }
+
+    fn variables(&self) -> Vec<CompletionItem> {
+        if !matches!(self.expect, Expect::Value(..)) {
+            return Vec::new();
+        }
+
+        self.variables
+            .iter()
+            .map(|(name, ty)| CompletionItem {
+                label: name.clone(),
+                kind: CompletionKind::Variable,
+                detail: Some(ty.clone()),
+                description: None,
+                deprecation: None,
+            })
+            .collect()
+    }
+
+    fn completions(&self, source: &str) -> Vec<CompletionItem> {
+        match &self.expect {
+            Expect::Definition => ["query", "mutation", "subscription", "fragment"]
+                .into_iter()
+                .map(keyword)
+                .collect(),
+            Expect::FragmentOn => vec![keyword("on")],
+            Expect::TypeCondition(..) => self.types(|kind| kind.is_composite()),
+            Expect::VariableType => self.types(|kind| kind.is_input()),
+            Expect::Selection => self.fields(),
+            Expect::Spread => {
+                let mut items = fragments(source);
+                items.push(keyword("on"));
+                items
+            }
+            // a name after a complete selection starts the next one
+            Expect::Head(head) | Expect::Directive { head, .. }
+                if !head.is_operation() && matches!(self.base(), Expect::Selection) =>
+            {
+                self.fields()
+            }
+            Expect::DirectiveName(head) => self.directives(head.location),
+            Expect::ArgumentName => match self.stack.last() {
+                Some(Frame::Arguments {
+                    definitions, given, ..
+                }) => self.arguments(definitions, given, CompletionKind::Argument),
+                _ => Vec::new(),
+            },
+            Expect::ObjectFieldName => match self.stack.last() {
+                Some(Frame::Object {
+                    ty: Some(ty),
+                    given,
+                }) => {
+                    let fields = self
+                        .schema
+                        .input_objects
+                        .get(ty)
+                        .and_then(|object| object.fields.as_deref())
+                        .unwrap_or_default();
+
+                    self.arguments(fields, given, CompletionKind::InputField)
+                }
+                _ => Vec::new(),
+            },
+            Expect::Value(Some(ty)) => self.values(ty),
+            _ => Vec::new(),
+        }
+    }
+
+    fn description(&self, description: Option<Spur>) -> Option<String> {
+        description.map(|description| self.interner.resolve(&description).to_owned())
+    }
+
+    fn fields(&self) -> Vec<CompletionItem> {
+        let Some(ty) = self.selection_type() else {
+            return Vec::new();
+        };
+
+        let mut items = self
+            .schema
+            .fields_of(ty)
+            .unwrap_or_default()
+            .iter()
+            .map(|field| CompletionItem {
+                label: self.interner.resolve(&field.name).to_owned(),
+                kind: CompletionKind::Field,
+                detail: Some(print_type(&field.ty, self.interner)),
+                description: self.description(field.description),
+                deprecation: field.deprecation(self.interner).map(str::to_owned),
+            })
+            .collect::<Vec<_>>();
+
+        items.push(CompletionItem {
+            label: "__typename".to_owned(),
+            kind: CompletionKind::Field,
+            detail: Some("String!".to_owned()),
+            description: Some("The name of the object type being selected from".to_owned()),
+            deprecation: None,
+        });
+
+        items
+    }
+
+    /// Arguments or input fields from `definitions` that haven't been
+    /// given yet
+    fn arguments(
+        &self,
+        definitions: &[InputObjectField],
+        given: &[String],
+        kind: CompletionKind,
+    ) -> Vec<CompletionItem> {
+        definitions
+            .iter()
+            .map(|argument| CompletionItem {
+                label: self.interner.resolve(&argument.name).to_owned(),
+                kind,
+                detail: Some(print_type(&argument.ty, self.interner)),
+                description: self.description(argument.description),
+                deprecation: argument.deprecation(self.interner).map(str::to_owned),
+            })
+            .filter(|item| !given.contains(&item.label))
+            .collect()
+    }
+
+    fn types(&self, filter: impl Fn(TypeKind) -> bool) -> Vec<CompletionItem> {
+        let mut items = self
+            .schema
+            .types()
+            .filter(|ty| filter(ty.kind()))
+            .map(|ty| CompletionItem {
+                label: self.interner.resolve(&ty.name()).to_owned(),
+                kind: CompletionKind::Type,
+                detail: Some(ty.kind().as_str().to_owned()),
+                description: self.description(ty.description()),
+                deprecation: None,
+            })
+            .collect::<Vec<_>>();
+
+        if filter(TypeKind::Scalar) {
+            for scalar in BUILT_IN_SCALARS {
+                if !items.iter().any(|item| item.label == scalar) {
+                    items.push(CompletionItem {
+                        label: scalar.to_owned(),
+                        kind: CompletionKind::Type,
+                        detail: Some("SCALAR".to_owned()),
+                        description: None,
+                        deprecation: None,
+                    });
+                }
+            }
+        }
+
+        items.sort_by(|a, b| a.label.cmp(&b.label));
+
+        items
+    }
+
+    /// Directives that may be used at `location`, including `@skip` and
+    /// `@include` when the schema doesn't define them
+    fn directives(&self, location: DirectiveLocation) -> Vec<CompletionItem> {
+        let mut items = self
+            .schema
+            .directive_definitions
+            .values()
+            .filter(|directive| directive.locations.contains(&location))
+            .map(|directive| CompletionItem {
+                label: self.interner.resolve(&directive.name).to_owned(),
+                kind: CompletionKind::Directive,
+                detail: directive.arguments.as_ref().map(|arguments| {
+                    let arguments = arguments
+                        .iter()
+                        .map(|argument| {
+                            format!(
+                                "{}: {}",
+                                self.interner.resolve(&argument.name),
+                                print_type(&argument.ty, self.interner)
+                            )
+                        })
+                        .collect::<Vec<_>>();
+
+                    format!("({})", arguments.join(", "))
+                }),
+                description: self.description(directive.description),
+                deprecation: None,
+            })
+            .collect::<Vec<_>>();
+
+        let applies_to_selections = matches!(
+            location,
+            DirectiveLocation::Field
+                | DirectiveLocation::FragmentSpread
+                | DirectiveLocation::InlineFragment
+        );
+
+        if applies_to_selections {
+            for (name, description) in [
+                ("skip", "Skip this selection when `if` is true"),
+                ("include", "Only include this selection when `if` is true"),
+            ] {
+                if !items.iter().any(|item| item.label == name) {
+                    items.push(CompletionItem {
+                        label: name.to_owned(),
+                        kind: CompletionKind::Directive,
+                        detail: Some("(if: Boolean!)".to_owned()),
+                        description: Some(description.to_owned()),
+                        deprecation: None,
+                    });
+                }
+            }
+        }
+
+        items.sort_by(|a, b| a.label.cmp(&b.label));
+
+        items
+    }
+
+    /// Enum values, booleans and `null`, as allowed by `ty`
+    fn values(&self, ty: &Type) -> Vec<CompletionItem> {
+        let name = ty.name();
+
+        let mut items = match self.schema.enums.get(&name) {
+            Some(enum_) => enum_
+                .variants
+                .iter()
+                .map(|variant| CompletionItem {
+                    label: self.interner.resolve(&variant.name).to_owned(),
+                    kind: CompletionKind::EnumValue,
+                    detail: Some(self.interner.resolve(&name).to_owned()),
+                    description: self.description(variant.description),
+                    deprecation: variant.deprecation(self.interner).map(str::to_owned),
+                })
+                .collect(),
+            None if matches!(ty, Type::Named { .. })
+                && self.interner.resolve(&name) == "Boolean" =>
+            {
+                vec![keyword("true"), keyword("false")]
+            }
+            None => Vec::new(),
+        };
+
+        if ty.is_nullable() {
+            items.push(keyword("null"));
+        }
+
+        items
+    }
+}
+
+fn keyword(keyword: &str) -> CompletionItem {
+    CompletionItem {
+        label: keyword.to_owned(),
+        kind: CompletionKind::Keyword,
+        detail: None,
+        description: None,
+        deprecation: None,
+    }
+}
+
+/// The fragments defined anywhere in `source`, in the order they're defined
+fn fragments(source: &str) -> Vec<CompletionItem> {
+    let mut lexer = Lexer::new(source.as_bytes());
+    let mut tokens = Vec::new();
+
+    while let Ok(Some(token)) = lexer.next_token() {
+        tokens.push(token);
+    }
+
+    let names = lexer.interner();
+
+    tokens
+        .windows(4)
+        .filter_map(|window| match window {
+            [Token::Keyword(Keyword::Fragment), name, Token::Keyword(Keyword::On), ty] => {
+                Some(CompletionItem {
+                    label: name_of(name, names)?.to_owned(),
+                    kind: CompletionKind::Fragment,
+                    detail: Some(format!("on {}", name_of(ty, names)?)),
+                    description: None,
+                    deprecation: None,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{complete, CompletionItem, CompletionKind};
+    use crate::GraphqlParser;
+
+    const SCHEMA: &str = r#"
+directive @cached(ttl: Int) on FIELD | QUERY
+directive @key(fields: String!) on OBJECT
+
+type Query {
+  "Look up a user by id"
+  user(id: ID!, role: Role): User
+  users(filter: Filter, first: Int): [User!]!
+  search(term: String!): [SearchResult!]!
+}
+type User {
+  id: ID!
+  name: String @deprecated(reason: "use `fullName`")
+  fullName: String
+  friends(online: Boolean): [User!]!
+}
+type Post { title: String }
+union SearchResult = User | Post
+input Filter { role: Role, name: String, nested: Filter }
+enum Role { ADMIN USER GUEST @deprecated }
+"#;
+
+    /// The completions at the `|` in `source`
+    fn completions(source: &str) -> Vec<CompletionItem> {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let offset = source.find('|').unwrap();
+        let source = source.replace('|', "");
+
+        complete(&schema, &interner, &source, offset)
+    }
+
+    fn labels(source: &str) -> Vec<String> {
+        completions(source)
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(labels("{ |"), ["user", "users", "search", "__typename"]);
+        assert_eq!(
+            labels("query Q { user(id: 1) { |} }"),
+            labels("{ user { | } }")
+        );
+        assert_eq!(labels("{ user { id f| }"), ["fullName", "friends"]);
+        assert_eq!(labels("{ user { friends { n| } } }"), ["name"]);
+        assert_eq!(labels("{ alias: us|"), ["user", "users"]);
+        assert_eq!(labels("{ search { |"), ["__typename"]);
+        assert_eq!(labels("{ user { ... on Post { |"), ["title", "__typename"]);
+        assert_eq!(labels("fragment F on User { id |"), labels("{ user { |"));
+    }
+
+    #[test]
+    fn field_details() {
+        let user = completions("{ u|").remove(0);
+
+        assert_eq!(user.kind, CompletionKind::Field);
+        assert_eq!(user.detail.as_deref(), Some("User"));
+        assert_eq!(user.description.as_deref(), Some("Look up a user by id"));
+
+        let name = completions("{ user { na|").remove(0);
+
+        assert_eq!(name.deprecation.as_deref(), Some("use `fullName`"));
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(labels("{ user(|"), ["id", "role"]);
+        assert_eq!(labels("{ user(id: 1, |"), ["role"]);
+        assert_eq!(labels("{ user { friends(|"), ["online"]);
+        assert_eq!(labels("{ user @cached(|"), ["ttl"]);
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(
+            labels("{ user(id: 1, role: |"),
+            ["ADMIN", "USER", "GUEST", "null"]
+        );
+        assert_eq!(labels("{ user(id: 1, role: G|"), ["GUEST"]);
+        assert_eq!(
+            labels("{ user { friends(online: |"),
+            ["true", "false", "null"]
+        );
+        assert_eq!(labels("{ users(filter: { |"), ["role", "name", "nested"]);
+        assert_eq!(
+            labels("{ users(filter: { role: ADMIN, |"),
+            ["name", "nested"]
+        );
+        assert_eq!(
+            labels("{ users(filter: { nested: { role: |"),
+            labels("{ user(role: |")
+        );
+        assert_eq!(labels("{ users(first: |"), ["null"]);
+    }
+
+    #[test]
+    fn variables() {
+        let source = "query Q($id: ID!, $roles: [Role!]) { user(id: $|";
+
+        assert_eq!(labels(source), ["id", "roles"]);
+        assert_eq!(completions(source)[1].detail.as_deref(), Some("[Role!]"));
+        assert_eq!(
+            labels("query Q($id: ID!, $roles: [Role]) { user(id: $r|"),
+            ["roles"]
+        );
+        assert!(labels("query Q($id: ID!) { user { $|").is_empty());
+    }
+
+    #[test]
+    fn directives() {
+        assert_eq!(labels("{ user @|"), ["cached", "include", "skip"]);
+        assert_eq!(labels("query Q @|"), ["cached"]);
+        assert_eq!(labels("{ ... on User @|"), ["include", "skip"]);
+        assert_eq!(labels("{ user @skip(if: true) @in|"), ["include"]);
+        assert!(labels("query Q($a: Int @|").is_empty());
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(
+            labels("{ ... on |"),
+            ["Post", "Query", "SearchResult", "User"]
+        );
+        assert_eq!(labels("fragment F on U|"), ["User"]);
+        assert_eq!(
+            labels("query Q($a: |"),
+            ["Boolean", "Filter", "Float", "ID", "Int", "Role", "String"]
+        );
+        assert_eq!(labels("query Q($a: [R|"), ["Role"]);
+    }
+
+    #[test]
+    fn keywords_and_fragments() {
+        assert_eq!(
+            labels("|"),
+            ["query", "mutation", "subscription", "fragment"]
+        );
+        assert_eq!(labels("{ user { id } }\nf|"), ["fragment"]);
+        assert_eq!(labels("fragment F |"), ["on"]);
+
+        let source = "{ user { ...| } }\nfragment Friend on User { id }";
+
+        assert_eq!(labels(source), ["Friend", "on"]);
+        assert_eq!(completions(source)[0].detail.as_deref(), Some("on User"));
+    }
+
+    #[test]
+    fn type_system_definitions_are_skipped() {
+        assert!(labels("type Foo { |").is_empty());
+        assert_eq!(labels("type Foo { query: Int }\nquery { |"), labels("{ |"));
+    }
+
+    #[test]
+    fn nothing_in_strings_comments_or_names() {
+        assert!(labels("{ user(id: \"|").is_empty());
+        assert!(labels("{ # |").is_empty());
+        assert!(labels("query Na|").is_empty());
+        assert_eq!(labels("{ # comment\n  u|"), ["user", "users"]);
+    }
+}
@@ -0,0 +1,1040 @@
+//! Composition of subgraph schemas into a supergraph schema, which a router
+//! plans operations across the subgraphs with
+//!
+//! Types with the same name are merged: objects and interfaces take the fields
+//! of every subgraph, unions and enums take every member, and input objects
+//! take the fields that every subgraph defines. The supergraph records which
+//! subgraphs define each type and resolve each field with the directives of the
+//! join spec. Other directives are left out, except for `@deprecated`
+//!
+//! See https://specs.apollo.dev/join/v0.3
+
+use std::fmt;
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        Directive, FieldDefinition, InputObjectField, NamedType, OperationKind, Selection, Span,
+        TypeDefinitionRef, TypeKind, Value,
+    },
+    federation::{FederationVersion, Subgraph},
+    fmt::{print_type, print_value},
+};
+
+/// The url of the join spec, which the supergraph links
+pub const JOIN_SPEC_URL: &str = "https://specs.apollo.dev/join/v0.3";
+
+/// The definitions that the supergraph's directives and graph enum need
+const JOIN_DEFINITIONS: &str = concat!(
+    "directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE\n\n",
+    "directive @join__field(graph: join__Graph, requires: join__FieldSet, ",
+    "provides: join__FieldSet, type: String, external: Boolean, override: String, ",
+    "usedOverridden: Boolean) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION\n\n",
+    "directive @join__graph(name: String!, url: String!) on ENUM_VALUE\n\n",
+    "directive @join__implements(graph: join__Graph!, interface: String!) ",
+    "repeatable on OBJECT | INTERFACE\n\n",
+    "directive @join__type(graph: join__Graph!, key: join__FieldSet, ",
+    "extension: Boolean! = false, resolvable: Boolean! = true, ",
+    "isInterfaceObject: Boolean! = false) ",
+    "repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR\n\n",
+    "directive @join__unionMember(graph: join__Graph!, member: String!) ",
+    "repeatable on UNION\n\n",
+    "directive @link(url: String, as: String, for: link__Purpose, ",
+    "import: [link__Import]) repeatable on SCHEMA\n\n",
+    "scalar join__FieldSet\n\n",
+    "scalar link__Import\n\n",
+    "enum link__Purpose {\n",
+    "  SECURITY\n",
+    "  EXECUTION\n",
+    "}\n\n",
+);
+
+/// A definition in one of the subgraphs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubgraphLocation {
+    pub subgraph: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompositionError {
+    pub kind: CompositionErrorKind,
+    /// The conflicting definitions
+    pub locations: Vec<SubgraphLocation>,
+}
+
+impl fmt::Display for CompositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for CompositionError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompositionErrorKind {
+    /// A type is defined as different kinds of type by different subgraphs
+    TypeKindMismatch { type_name: String },
+    /// A field or input field has different types in different subgraphs
+    FieldTypeMismatch { coordinate: String },
+    /// A field is resolved by more than one subgraph, but isn't `@shareable`
+    /// in all of them
+    FieldNotShareable { coordinate: String },
+    /// A field is `@external` in every subgraph that defines it
+    UnresolvedExternalField { coordinate: String },
+    /// A type is an entity in one subgraph, but has no `@key` in `subgraph`
+    MissingKey { type_name: String, subgraph: String },
+    /// A non-null input field is missing from `subgraph`, so it couldn't be
+    /// given to that subgraph
+    MissingRequiredInputField {
+        coordinate: String,
+        subgraph: String,
+    },
+}
+
+impl fmt::Display for CompositionErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TypeKindMismatch { type_name } => write!(
+                f,
+                "`{}` is defined as different kinds of type in different subgraphs",
+                type_name
+            ),
+            Self::FieldTypeMismatch { coordinate } => write!(
+                f,
+                "`{}` has different types in different subgraphs",
+                coordinate
+            ),
+            Self::FieldNotShareable { coordinate } => write!(
+                f,
+                concat!(
+                    "`{}` is resolved by more than one subgraph, but isn't marked ",
+                    "`@shareable` in all of them",
+                ),
+                coordinate
+            ),
+            Self::UnresolvedExternalField { coordinate } => write!(
+                f,
+                "`{}` is marked `@external` in every subgraph that defines it",
+                coordinate
+            ),
+            Self::MissingKey {
+                type_name,
+                subgraph,
+            } => write!(
+                f,
+                "`{}` is an entity, but has no `@key` in subgraph `{}`",
+                type_name, subgraph
+            ),
+            Self::MissingRequiredInputField {
+                coordinate,
+                subgraph,
+            } => write!(
+                f,
+                "`{}` is non-null, but isn't defined by subgraph `{}`",
+                coordinate, subgraph
+            ),
+        }
+    }
+}
+
+/// Compose `subgraphs` into the SDL of a supergraph schema, with the types
+/// in alphabetical order
+pub fn compose_supergraph(
+    subgraphs: &[Subgraph],
+    interner: &Rodeo,
+) -> Result<String, Vec<CompositionError>> {
+    let mut composer = Composer {
+        subgraphs,
+        interner,
+        graphs: subgraphs
+            .iter()
+            .map(|subgraph| graph_name(&subgraph.name))
+            .collect(),
+        errors: Vec::new(),
+        buffer: String::new(),
+    };
+
+    composer.write_schema();
+
+    let mut names = subgraphs
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, subgraph)| subgraph.schema.types().map(move |ty| (idx, ty.name())))
+        .filter(|&(idx, name)| !composer.is_federation_type(idx, name))
+        .map(|(_, name)| name)
+        .collect::<Vec<_>>();
+
+    names.sort_by_key(|name| interner.resolve(name));
+    names.dedup();
+
+    for name in names {
+        composer.write_type(name);
+    }
+
+    if !composer.errors.is_empty() {
+        return Err(composer.errors);
+    }
+
+    Ok(composer.buffer.trim_end().to_owned() + "\n")
+}
+
+/// The value of `join__Graph` for the subgraph `name`
+fn graph_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+            _ => '_',
+        })
+        .collect()
+}
+
+struct Composer<'a> {
+    subgraphs: &'a [Subgraph],
+    interner: &'a Rodeo,
+    /// The `join__Graph` value of each subgraph
+    graphs: Vec<String>,
+    errors: Vec<CompositionError>,
+    buffer: String,
+}
+
+impl<'a> Composer<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn string(&self, value: Spur) -> String {
+        print_value(&Value::String(value), self.interner)
+    }
+
+    fn location(&self, subgraph: usize, span: Span) -> SubgraphLocation {
+        SubgraphLocation {
+            subgraph: self.subgraphs[subgraph].name.clone(),
+            span,
+        }
+    }
+
+    fn error(&mut self, kind: CompositionErrorKind, locations: Vec<SubgraphLocation>) {
+        self.errors.push(CompositionError { kind, locations });
+    }
+
+    /// Whether `name` is one of the types a subgraph defines for the router,
+    /// or imports from the federation spec
+    fn is_federation_type(&self, subgraph: usize, name: Spur) -> bool {
+        let name = self.name(name);
+
+        name.starts_with('_')
+            || name.starts_with("link__")
+            || name.starts_with("federation__")
+            || name == self.subgraphs[subgraph].directive_name("FieldSet")
+    }
+
+    fn directive<'b>(
+        &self,
+        subgraph: usize,
+        directives: &'b [Directive],
+        name: &str,
+    ) -> Option<&'b Directive> {
+        let name = self.subgraphs[subgraph].directive_name(name);
+
+        directives
+            .iter()
+            .find(|directive| self.name(directive.name) == name)
+    }
+
+    /// The `fields` argument of the federation directive `name`, as a string
+    fn field_set_argument(
+        &self,
+        subgraph: usize,
+        directives: &[Directive],
+        name: &str,
+    ) -> Option<String> {
+        let directive = self.directive(subgraph, directives, name)?;
+
+        directive
+            .arguments
+            .iter()
+            .flatten()
+            .find(|argument| self.name(argument.name) == "fields")
+            .map(|argument| print_value(&argument.value, self.interner))
+    }
+
+    fn write_schema(&mut self) {
+        self.buffer.push_str(concat!(
+            "schema\n",
+            "  @link(url: \"https://specs.apollo.dev/link/v1.0\")\n",
+        ));
+        self.buffer.push_str(&format!(
+            "  @link(url: \"{}\", for: EXECUTION)\n{{\n",
+            JOIN_SPEC_URL
+        ));
+
+        for kind in [
+            OperationKind::Query,
+            OperationKind::Mutation,
+            OperationKind::Subscription,
+        ] {
+            let root = self.subgraphs.iter().find_map(|subgraph| {
+                subgraph
+                    .schema
+                    .root_operation_type_or_default(kind, self.interner)
+            });
+
+            if let Some(root) = root {
+                self.buffer
+                    .push_str(&format!("  {}: {}\n", kind.as_str(), self.name(root)));
+            }
+        }
+
+        self.buffer.push_str("}\n\n");
+        self.buffer.push_str(JOIN_DEFINITIONS);
+        self.buffer.push_str("enum join__Graph {\n");
+
+        for (subgraph, graph) in self.subgraphs.iter().zip(&self.graphs) {
+            self.buffer.push_str(&format!(
+                "  {} @join__graph(name: {:?}, url: {:?})\n",
+                graph, subgraph.name, subgraph.url
+            ));
+        }
+
+        self.buffer.push_str("}\n\n");
+    }
+
+    fn write_description(&mut self, description: Option<Spur>, indent: &str) {
+        if let Some(description) = description {
+            let description = self.string(description);
+            self.buffer
+                .push_str(&format!("{}{}\n", indent, description));
+        }
+    }
+
+    /// ` @deprecated`, with the reason it is given in `directives`, if any
+    fn deprecation(&self, directives: &[Directive]) -> String {
+        let directive = directives
+            .iter()
+            .find(|directive| self.name(directive.name) == "deprecated");
+
+        let reason = directive
+            .and_then(|directive| directive.arguments.as_deref())
+            .and_then(|arguments| {
+                arguments
+                    .iter()
+                    .find(|argument| self.name(argument.name) == "reason")
+            });
+
+        match (directive, reason) {
+            (Some(..), Some(reason)) => format!(
+                " @deprecated(reason: {})",
+                print_value(&reason.value, self.interner)
+            ),
+            (Some(..), None) => " @deprecated".to_owned(),
+            (None, _) => String::new(),
+        }
+    }
+
+    fn write_type(&mut self, name: Spur) {
+        let definitions = self
+            .subgraphs
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, subgraph)| Some((idx, subgraph.schema.get_type(name)?)))
+            .collect::<Vec<_>>();
+
+        let kind = definitions[0].1.kind();
+
+        if definitions.iter().any(|(_, ty)| ty.kind() != kind) {
+            let locations = definitions
+                .iter()
+                .map(|(idx, ty)| self.location(*idx, ty.span()))
+                .collect();
+
+            self.error(
+                CompositionErrorKind::TypeKindMismatch {
+                    type_name: self.name(name).to_owned(),
+                },
+                locations,
+            );
+
+            return;
+        }
+
+        let description = definitions.iter().find_map(|(_, ty)| ty.description());
+        self.write_description(description, "");
+
+        match kind {
+            TypeKind::Object | TypeKind::Interface => self.write_composite(name, &definitions),
+            TypeKind::Union => self.write_union(name, &definitions),
+            TypeKind::Enum => self.write_enum(name, &definitions),
+            TypeKind::InputObject => self.write_input_object(name, &definitions),
+            TypeKind::Scalar => {
+                self.buffer
+                    .push_str(&format!("scalar {}\n", self.name(name)));
+                self.write_join_types(name, &definitions);
+                self.buffer.push('\n');
+            }
+        }
+    }
+
+    fn write_join_types(&mut self, name: Spur, definitions: &[(usize, TypeDefinitionRef<'_>)]) {
+        for &(idx, _) in definitions {
+            let entity = self.subgraphs[idx].federation.entity(name);
+
+            match entity {
+                Some(entity) if !entity.keys.is_empty() => {
+                    for key in &entity.keys {
+                        let mut arguments = format!(
+                            "graph: {}, key: {}",
+                            self.graphs[idx],
+                            self.string(key.fields.source)
+                        );
+
+                        if entity.is_extension {
+                            arguments.push_str(", extension: true");
+                        }
+
+                        if !key.resolvable {
+                            arguments.push_str(", resolvable: false");
+                        }
+
+                        self.buffer
+                            .push_str(&format!("  @join__type({})\n", arguments));
+                    }
+                }
+                _ => self
+                    .buffer
+                    .push_str(&format!("  @join__type(graph: {})\n", self.graphs[idx])),
+            }
+        }
+    }
+
+    /// Check that a type that is an entity in one subgraph is an entity in
+    /// every subgraph
+    fn check_keys(&mut self, name: Spur, definitions: &[(usize, TypeDefinitionRef<'_>)]) {
+        let is_entity = |idx: usize| self.subgraphs[idx].federation.entity(name).is_some();
+
+        if !definitions.iter().any(|&(idx, _)| is_entity(idx)) {
+            return;
+        }
+
+        let missing = definitions
+            .iter()
+            .filter(|&&(idx, _)| !is_entity(idx))
+            .map(|(idx, ty)| (*idx, ty.span()))
+            .collect::<Vec<_>>();
+
+        for (idx, span) in missing {
+            self.error(
+                CompositionErrorKind::MissingKey {
+                    type_name: self.name(name).to_owned(),
+                    subgraph: self.subgraphs[idx].name.clone(),
+                },
+                vec![self.location(idx, span)],
+            );
+        }
+    }
+
+    fn write_composite(&mut self, name: Spur, definitions: &[(usize, TypeDefinitionRef<'_>)]) {
+        self.check_keys(name, definitions);
+
+        let parts = definitions
+            .iter()
+            .map(|(idx, ty)| match ty {
+                TypeDefinitionRef::Object(object) => (
+                    *idx,
+                    object.implements.as_slice(),
+                    object.fields.as_deref().unwrap_or_default(),
+                    object.directives.as_slice(),
+                ),
+                TypeDefinitionRef::Interface(interface) => (
+                    *idx,
+                    interface.implements.as_slice(),
+                    interface.fields.as_slice(),
+                    interface.directives.as_slice(),
+                ),
+                _ => unreachable!("only objects and interfaces are composites"),
+            })
+            .collect::<Vec<_>>();
+
+        let mut interfaces: Vec<Spur> = Vec::new();
+
+        for NamedType(interface) in parts.iter().flat_map(|(_, implements, ..)| *implements) {
+            if !interfaces.contains(interface) {
+                interfaces.push(*interface);
+            }
+        }
+
+        let keyword = match definitions[0].1 {
+            TypeDefinitionRef::Interface(..) => "interface",
+            _ => "type",
+        };
+
+        self.buffer
+            .push_str(&format!("{} {}", keyword, self.name(name)));
+
+        if !interfaces.is_empty() {
+            let names = interfaces
+                .iter()
+                .map(|interface| self.name(*interface))
+                .collect::<Vec<_>>();
+
+            self.buffer
+                .push_str(&format!(" implements {}", names.join(" & ")));
+        }
+
+        self.buffer.push('\n');
+
+        for (idx, implements, ..) in &parts {
+            for NamedType(interface) in *implements {
+                self.buffer.push_str(&format!(
+                    "  @join__implements(graph: {}, interface: \"{}\")\n",
+                    self.graphs[*idx],
+                    self.name(*interface)
+                ));
+            }
+        }
+
+        self.write_join_types(name, definitions);
+        self.buffer.push_str("{\n");
+
+        let mut field_names: Vec<Spur> = Vec::new();
+
+        for field in parts.iter().flat_map(|(_, _, fields, _)| *fields) {
+            let is_internal = matches!(self.name(field.name), "_service" | "_entities");
+
+            if !is_internal && !field_names.contains(&field.name) {
+                field_names.push(field.name);
+            }
+        }
+
+        for field_name in field_names {
+            let fields = parts
+                .iter()
+                .filter_map(|(idx, _, fields, type_directives)| {
+                    let field = fields.iter().find(|field| field.name == field_name)?;
+                    Some((*idx, field, *type_directives))
+                })
+                .collect::<Vec<_>>();
+
+            self.write_field(name, &fields, parts.len());
+        }
+
+        self.buffer.push_str("}\n\n");
+    }
+
+    /// Write the field defined by each of `fields`, along with the
+    /// directives of the type it is defined on. `type_count` is the number of
+    /// subgraphs that define the type
+    fn write_field(
+        &mut self,
+        parent: Spur,
+        fields: &[(usize, &FieldDefinition, &[Directive])],
+        type_count: usize,
+    ) {
+        let (_, first, _) = fields[0];
+        let coordinate = format!("{}.{}", self.name(parent), self.name(first.name));
+        let ty = print_type(&first.ty, self.interner);
+
+        let locations = |composer: &Self, fields: &[(usize, &FieldDefinition, &[Directive])]| {
+            fields
+                .iter()
+                .map(|(idx, field, _)| composer.location(*idx, field.span))
+                .collect::<Vec<_>>()
+        };
+
+        if fields
+            .iter()
+            .any(|(_, field, _)| print_type(&field.ty, self.interner) != ty)
+        {
+            let locations = locations(self, fields);
+            self.error(
+                CompositionErrorKind::FieldTypeMismatch { coordinate },
+                locations,
+            );
+            return;
+        }
+
+        let resolving = fields
+            .iter()
+            .filter(|(idx, field, _)| {
+                self.directive(*idx, &field.directives, "external")
+                    .is_none()
+            })
+            .copied()
+            .collect::<Vec<_>>();
+
+        if resolving.is_empty() {
+            let locations = locations(self, fields);
+            self.error(
+                CompositionErrorKind::UnresolvedExternalField { coordinate },
+                locations,
+            );
+            return;
+        }
+
+        let is_shareable =
+            |&(idx, field, type_directives): &(usize, &FieldDefinition, &[Directive])| {
+                let is_key_field =
+                    self.subgraphs[idx]
+                        .federation
+                        .entity(parent)
+                        .is_some_and(|entity| {
+                            entity
+                                .keys
+                                .iter()
+                                .any(|key| selects(&key.fields.selection_set, field.name))
+                        });
+
+                is_key_field
+                    || self
+                        .directive(idx, &field.directives, "shareable")
+                        .is_some()
+                    || self.directive(idx, type_directives, "shareable").is_some()
+            };
+
+        // Federation 1 lets any subgraph resolve value type fields
+        let is_federation_2 =
+            |idx: usize| self.subgraphs[idx].federation.version == FederationVersion::V2;
+
+        if resolving.len() > 1
+            && resolving.iter().any(|&(idx, ..)| is_federation_2(idx))
+            && !resolving.iter().all(is_shareable)
+        {
+            let locations = locations(self, &resolving);
+            self.error(
+                CompositionErrorKind::FieldNotShareable { coordinate },
+                locations,
+            );
+            return;
+        }
+
+        let mut joins = Vec::new();
+
+        for &(idx, field, _) in fields {
+            let mut arguments = format!("graph: {}", self.graphs[idx]);
+
+            if let Some(requires) = self.field_set_argument(idx, &field.directives, "requires") {
+                arguments.push_str(&format!(", requires: {}", requires));
+            }
+
+            if let Some(provides) = self.field_set_argument(idx, &field.directives, "provides") {
+                arguments.push_str(&format!(", provides: {}", provides));
+            }
+
+            if self.directive(idx, &field.directives, "external").is_some() {
+                arguments.push_str(", external: true");
+            }
+
+            joins.push(arguments);
+        }
+
+        // fields that every subgraph defining the type resolves in the same
+        // way don't need to say which subgraphs resolve them
+        let needs_joins =
+            fields.len() != type_count || joins.iter().any(|arguments| arguments.contains(','));
+
+        self.write_description(first.description, "  ");
+        self.buffer
+            .push_str(&format!("  {}", self.name(first.name)));
+
+        if let Some(arguments) = &first.arguments {
+            self.write_arguments(arguments);
+        }
+
+        self.buffer.push_str(&format!(": {}", ty));
+        self.buffer.push_str(&self.deprecation(&first.directives));
+
+        if needs_joins {
+            for arguments in joins {
+                self.buffer
+                    .push_str(&format!(" @join__field({})", arguments));
+            }
+        }
+
+        self.buffer.push('\n');
+    }
+
+    fn write_arguments(&mut self, arguments: &[InputObjectField]) {
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.input_value(argument))
+            .collect::<Vec<_>>();
+
+        self.buffer.push_str(&format!("({})", arguments.join(", ")));
+    }
+
+    /// An argument or input field, as `name: Type = default`
+    fn input_value(&self, field: &InputObjectField) -> String {
+        let mut value = format!(
+            "{}: {}",
+            self.name(field.name),
+            print_type(&field.ty, self.interner)
+        );
+
+        if let Some(default) = &field.default {
+            value.push_str(&format!(" = {}", print_value(default, self.interner)));
+        }
+
+        value + &self.deprecation(&field.directives)
+    }
+
+    fn write_union(&mut self, name: Spur, definitions: &[(usize, TypeDefinitionRef<'_>)]) {
+        self.buffer
+            .push_str(&format!("union {}\n", self.name(name)));
+        self.write_join_types(name, definitions);
+
+        let mut members: Vec<Spur> = Vec::new();
+
+        for (idx, ty) in definitions {
+            if let TypeDefinitionRef::Union(union) = ty {
+                for NamedType(member) in &union.types {
+                    self.buffer.push_str(&format!(
+                        "  @join__unionMember(graph: {}, member: \"{}\")\n",
+                        self.graphs[*idx],
+                        self.name(*member)
+                    ));
+
+                    if !members.contains(member) {
+                        members.push(*member);
+                    }
+                }
+            }
+        }
+
+        let members = members
+            .iter()
+            .map(|member| self.name(*member))
+            .collect::<Vec<_>>();
+
+        self.buffer
+            .push_str(&format!(" = {}\n\n", members.join(" | ")));
+    }
+
+    fn write_enum(&mut self, name: Spur, definitions: &[(usize, TypeDefinitionRef<'_>)]) {
+        self.buffer.push_str(&format!("enum {}\n", self.name(name)));
+        self.write_join_types(name, definitions);
+        self.buffer.push_str("{\n");
+
+        let enums = definitions
+            .iter()
+            .filter_map(|(idx, ty)| match ty {
+                TypeDefinitionRef::Enum(enum_def) => Some((*idx, *enum_def)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut values: Vec<Spur> = Vec::new();
+
+        for (_, enum_def) in &enums {
+            for variant in &enum_def.variants {
+                if !values.contains(&variant.name) {
+                    values.push(variant.name);
+                }
+            }
+        }
+
+        for value in values {
+            let variants = enums
+                .iter()
+                .filter_map(|(idx, enum_def)| {
+                    let variant = enum_def
+                        .variants
+                        .iter()
+                        .find(|variant| variant.name == value)?;
+                    Some((*idx, variant))
+                })
+                .collect::<Vec<_>>();
+
+            let (_, first) = variants[0];
+
+            self.write_description(first.description, "  ");
+            self.buffer.push_str(&format!(
+                "  {}{}",
+                self.name(value),
+                self.deprecation(&first.directives)
+            ));
+
+            for (idx, _) in variants {
+                self.buffer
+                    .push_str(&format!(" @join__enumValue(graph: {})", self.graphs[idx]));
+            }
+
+            self.buffer.push('\n');
+        }
+
+        self.buffer.push_str("}\n\n");
+    }
+
+    /// Input objects only take the fields every subgraph defines, as the
+    /// router passes them on to each subgraph
+    fn write_input_object(&mut self, name: Spur, definitions: &[(usize, TypeDefinitionRef<'_>)]) {
+        self.buffer
+            .push_str(&format!("input {}\n", self.name(name)));
+        self.write_join_types(name, definitions);
+        self.buffer.push_str("{\n");
+
+        let inputs = definitions
+            .iter()
+            .filter_map(|(idx, ty)| match ty {
+                TypeDefinitionRef::InputObject(input) => {
+                    Some((*idx, input.fields.as_deref().unwrap_or_default()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut field_names: Vec<Spur> = Vec::new();
+
+        for field in inputs.iter().flat_map(|(_, fields)| *fields) {
+            if !field_names.contains(&field.name) {
+                field_names.push(field.name);
+            }
+        }
+
+        for field_name in field_names {
+            let coordinate = format!("{}.{}", self.name(name), self.name(field_name));
+
+            let fields = inputs
+                .iter()
+                .filter_map(|(idx, fields)| {
+                    Some((*idx, fields.iter().find(|field| field.name == field_name)?))
+                })
+                .collect::<Vec<_>>();
+
+            let (_, first) = fields[0];
+            let ty = print_type(&first.ty, self.interner);
+
+            if fields
+                .iter()
+                .any(|(_, field)| print_type(&field.ty, self.interner) != ty)
+            {
+                let locations = fields
+                    .iter()
+                    .map(|(idx, field)| self.location(*idx, field.span))
+                    .collect();
+
+                self.error(
+                    CompositionErrorKind::FieldTypeMismatch { coordinate },
+                    locations,
+                );
+                continue;
+            }
+
+            if fields.len() == inputs.len() {
+                self.write_description(first.description, "  ");
+                let value = self.input_value(first);
+                self.buffer.push_str(&format!("  {}\n", value));
+                continue;
+            }
+
+            let required = fields
+                .iter()
+                .find(|(_, field)| !field.ty.is_nullable() && field.default.is_none());
+
+            if let Some(&(required_idx, required)) = required {
+                for (idx, ty) in definitions {
+                    if !fields.iter().any(|(field_idx, _)| field_idx == idx) {
+                        self.error(
+                            CompositionErrorKind::MissingRequiredInputField {
+                                coordinate: coordinate.clone(),
+                                subgraph: self.subgraphs[*idx].name.clone(),
+                            },
+                            vec![
+                                self.location(required_idx, required.span),
+                                self.location(*idx, ty.span()),
+                            ],
+                        );
+                    }
+                }
+            }
+        }
+
+        self.buffer.push_str("}\n\n");
+    }
+}
+
+/// Whether `selections` selects the field `name`
+fn selects(selections: &[Selection], name: Spur) -> bool {
+    selections.iter().any(
+        |selection| matches!(selection, Selection::Field { name: field, .. } if *field == name),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{compose_supergraph, CompositionError};
+    use crate::{federation::Subgraph, GraphqlParser};
+
+    const FEDERATION_2: &str = concat!(
+        "extend schema @link(url: \"https://specs.apollo.dev/federation/v2.3\", ",
+        "import: [\"@key\", \"@external\", \"@requires\", \"@provides\", \"@shareable\"]) ",
+    );
+
+    fn compose(schemas: &[(&str, &str)]) -> Result<String, Vec<CompositionError>> {
+        let mut interner = Rodeo::default();
+
+        let subgraphs = schemas
+            .iter()
+            .map(|(name, schema)| {
+                let document = GraphqlParser::parse_with_interner(
+                    format!("{}{}", FEDERATION_2, schema).as_bytes(),
+                    &mut interner,
+                )
+                .unwrap();
+
+                let (subgraph, errors) = Subgraph::new(*name, document, &mut interner);
+                assert!(errors.is_empty(), "{:?}", errors);
+
+                subgraph.with_url(format!("http://{}", name))
+            })
+            .collect::<Vec<_>>();
+
+        compose_supergraph(&subgraphs, &interner)
+    }
+
+    #[test]
+    fn supergraph() {
+        let supergraph = compose(&[
+            (
+                "accounts",
+                concat!(
+                    "type Query { me: User } ",
+                    "type User @key(fields: \"id\") { id: ID! name: String @deprecated ",
+                    "status: Status! } ",
+                    "enum Status { ACTIVE BANNED }",
+                ),
+            ),
+            (
+                "reviews",
+                concat!(
+                    "type User @key(fields: \"id\") { id: ID! ",
+                    "name: String @external reviews(first: Int = 5): [Review!]! ",
+                    "@requires(fields: \"name\") } ",
+                    "type Review { body: String } ",
+                    "enum Status { ACTIVE DELETED } ",
+                    "union Content = Review",
+                ),
+            ),
+        ])
+        .unwrap();
+
+        assert!(
+            supergraph.contains(concat!(
+                "{\n  query: Query\n}\n\n",
+                "directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE\n\n",
+            )),
+            "{}",
+            supergraph
+        );
+
+        let expected = concat!(
+            "enum join__Graph {\n",
+            "  ACCOUNTS @join__graph(name: \"accounts\", url: \"http://accounts\")\n",
+            "  REVIEWS @join__graph(name: \"reviews\", url: \"http://reviews\")\n",
+            "}\n\n",
+            "union Content\n",
+            "  @join__type(graph: REVIEWS)\n",
+            "  @join__unionMember(graph: REVIEWS, member: \"Review\")\n",
+            " = Review\n\n",
+            "type Query\n",
+            "  @join__type(graph: ACCOUNTS)\n",
+            "{\n",
+            "  me: User\n",
+            "}\n\n",
+            "type Review\n",
+            "  @join__type(graph: REVIEWS)\n",
+            "{\n",
+            "  body: String\n",
+            "}\n\n",
+            "enum Status\n",
+            "  @join__type(graph: ACCOUNTS)\n",
+            "  @join__type(graph: REVIEWS)\n",
+            "{\n",
+            "  ACTIVE @join__enumValue(graph: ACCOUNTS) @join__enumValue(graph: REVIEWS)\n",
+            "  BANNED @join__enumValue(graph: ACCOUNTS)\n",
+            "  DELETED @join__enumValue(graph: REVIEWS)\n",
+            "}\n\n",
+            "type User\n",
+            "  @join__type(graph: ACCOUNTS, key: \"id\")\n",
+            "  @join__type(graph: REVIEWS, key: \"id\")\n",
+            "{\n",
+            "  id: ID!\n",
+            "  name: String @deprecated @join__field(graph: ACCOUNTS) ",
+            "@join__field(graph: REVIEWS, external: true)\n",
+            "  status: Status! @join__field(graph: ACCOUNTS)\n",
+            "  reviews(first: Int = 5): [Review!]! ",
+            "@join__field(graph: REVIEWS, requires: \"name\")\n",
+            "}\n",
+        );
+
+        assert!(supergraph.ends_with(expected), "{}", supergraph);
+
+        let mut interner = Rodeo::default();
+        let document =
+            GraphqlParser::parse_with_interner(supergraph.as_bytes(), &mut interner).unwrap();
+        assert!(document.validate_directive_usages(&interner).is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let errors = compose(&[
+            (
+                "a",
+                concat!(
+                    "type Query { user: User } ",
+                    "type User @key(fields: \"id\") { id: ID! name: String } ",
+                    "type Post { title: String } ",
+                    "input Filter { limit: Int! }",
+                ),
+            ),
+            (
+                "b",
+                concat!(
+                    "type Query { posts: [Post] } ",
+                    "type User { nickname: String } ",
+                    "interface Post { title: String } ",
+                    "type Comment @shareable { id: ID! body: String } ",
+                    "input Filter { offset: Int }",
+                ),
+            ),
+            (
+                "c",
+                concat!(
+                    "type Comment @shareable { id: ID! body: String! } ",
+                    "type Query { user: User @shareable } ",
+                    "type User @key(fields: \"id\") { id: ID! } ",
+                ),
+            ),
+        ])
+        .unwrap_err();
+
+        let messages = errors
+            .iter()
+            .map(|error| {
+                let subgraphs = error
+                    .locations
+                    .iter()
+                    .map(|location| location.subgraph.as_str())
+                    .collect::<Vec<_>>();
+
+                format!("{} ({})", error, subgraphs.join(", "))
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "`Comment.body` has different types in different subgraphs (b, c)",
+                "`Filter.limit` is non-null, but isn't defined by subgraph `b` (a, b)",
+                "`Post` is defined as different kinds of type in different subgraphs (a, b)",
+                concat!(
+                    "`Query.user` is resolved by more than one subgraph, but isn't marked ",
+                    "`@shareable` in all of them (a, c)",
+                ),
+                "`User` is an entity, but has no `@key` in subgraph `b` (b)",
+            ]
+        );
+    }
+}
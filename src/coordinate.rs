@@ -0,0 +1,372 @@
+//! Schema coordinates, which name a type, field, input field, enum value,
+//! argument or directive of a schema as text, such as `User`, `User.name`,
+//! `User.friends(first:)`, `@deprecated` or `@deprecated(reason:)`
+//!
+//! Coordinates are how [`crate::diff`] and [`crate::usage`] identify the parts
+//! of a schema they report on. See the
+//! [Schema Coordinates RFC](https://github.com/graphql/graphql-wg/blob/main/rfcs/SchemaCoordinates.md)
+
+use std::{fmt, str::FromStr};
+
+use lasso::Rodeo;
+
+use crate::ast::{
+    DirectiveDefinition, Document, EnumVariant, FieldDefinition, InputObjectField,
+    TypeDefinitionRef,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SchemaCoordinate {
+    /// `User`
+    Type(String),
+    /// `User.name`, naming a field, an input field or an enum value
+    Member { ty: String, member: String },
+    /// `User.friends(first:)`
+    Argument {
+        ty: String,
+        field: String,
+        argument: String,
+    },
+    /// `@deprecated`
+    Directive(String),
+    /// `@deprecated(reason:)`
+    DirectiveArgument { directive: String, argument: String },
+}
+
+/// The definition a [`SchemaCoordinate`] names
+#[derive(Debug, Clone, Copy)]
+pub enum SchemaElement<'a> {
+    Type(TypeDefinitionRef<'a>),
+    Field(&'a FieldDefinition),
+    InputField(&'a InputObjectField),
+    EnumValue(&'a EnumVariant),
+    /// An argument of a field or directive
+    Argument(&'a InputObjectField),
+    Directive(&'a DirectiveDefinition),
+}
+
+impl SchemaCoordinate {
+    pub fn member(ty: &str, member: &str) -> Self {
+        Self::Member {
+            ty: ty.to_owned(),
+            member: member.to_owned(),
+        }
+    }
+
+    pub fn directive(name: &str) -> Self {
+        Self::Directive(name.to_owned())
+    }
+
+    /// The coordinate of the argument `argument` of this field or directive,
+    /// or `None` if this doesn't name something that can have arguments
+    pub fn argument(&self, argument: &str) -> Option<Self> {
+        Some(match self {
+            Self::Member { ty, member } => Self::Argument {
+                ty: ty.clone(),
+                field: member.clone(),
+                argument: argument.to_owned(),
+            },
+            Self::Directive(directive) => Self::DirectiveArgument {
+                directive: directive.clone(),
+                argument: argument.to_owned(),
+            },
+            Self::Type(..) | Self::Argument { .. } | Self::DirectiveArgument { .. } => return None,
+        })
+    }
+
+    /// The coordinate of the type, field or directive this is part of, or
+    /// `None` for types and directives
+    pub fn parent(&self) -> Option<Self> {
+        Some(match self {
+            Self::Member { ty, .. } => Self::Type(ty.clone()),
+            Self::Argument { ty, field, .. } => Self::member(ty, field),
+            Self::DirectiveArgument { directive, .. } => Self::directive(directive),
+            Self::Type(..) | Self::Directive(..) => return None,
+        })
+    }
+
+    /// The definition in `schema` this coordinate names, if there is one
+    pub fn resolve<'a>(&self, schema: &'a Document, interner: &Rodeo) -> Option<SchemaElement<'a>> {
+        let find_argument = |arguments: Option<&'a [InputObjectField]>, argument: &str| {
+            let argument = interner.get(argument)?;
+
+            arguments
+                .unwrap_or_default()
+                .iter()
+                .find(|definition| definition.name == argument)
+                .map(SchemaElement::Argument)
+        };
+
+        match self {
+            Self::Type(ty) => schema
+                .get_type_by_name(ty, interner)
+                .map(SchemaElement::Type),
+            Self::Member { ty, member } => {
+                let member = interner.get(member)?;
+
+                match schema.get_type_by_name(ty, interner)? {
+                    TypeDefinitionRef::Object(object) => object
+                        .fields
+                        .iter()
+                        .flatten()
+                        .find(|field| field.name == member)
+                        .map(SchemaElement::Field),
+                    TypeDefinitionRef::Interface(interface) => interface
+                        .fields
+                        .iter()
+                        .find(|field| field.name == member)
+                        .map(SchemaElement::Field),
+                    TypeDefinitionRef::InputObject(input) => input
+                        .fields
+                        .iter()
+                        .flatten()
+                        .find(|field| field.name == member)
+                        .map(SchemaElement::InputField),
+                    TypeDefinitionRef::Enum(enum_def) => enum_def
+                        .variants
+                        .iter()
+                        .find(|variant| variant.name == member)
+                        .map(SchemaElement::EnumValue),
+                    TypeDefinitionRef::Scalar(..) | TypeDefinitionRef::Union(..) => None,
+                }
+            }
+            Self::Argument {
+                ty,
+                field,
+                argument,
+            } => {
+                let field = interner.get(field)?;
+                let field = schema
+                    .fields_of(interner.get(ty)?)?
+                    .iter()
+                    .find(|definition| definition.name == field)?;
+
+                find_argument(field.arguments.as_deref(), argument)
+            }
+            Self::Directive(directive) => schema
+                .directive_definitions
+                .get(&interner.get(directive)?)
+                .map(SchemaElement::Directive),
+            Self::DirectiveArgument {
+                directive,
+                argument,
+            } => {
+                let directive = schema
+                    .directive_definitions
+                    .get(&interner.get(directive)?)?;
+
+                find_argument(directive.arguments.as_deref(), argument)
+            }
+        }
+    }
+}
+
+impl fmt::Display for SchemaCoordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Type(ty) => write!(f, "{}", ty),
+            Self::Member { ty, member } => write!(f, "{}.{}", ty, member),
+            Self::Argument {
+                ty,
+                field,
+                argument,
+            } => write!(f, "{}.{}({}:)", ty, field, argument),
+            Self::Directive(directive) => write!(f, "@{}", directive),
+            Self::DirectiveArgument {
+                directive,
+                argument,
+            } => write!(f, "@{}({}:)", directive, argument),
+        }
+    }
+}
+
+impl FromStr for SchemaCoordinate {
+    type Err = InvalidCoordinate;
+
+    /// Coordinates may not contain whitespace or comments
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCoordinate(s.to_owned());
+
+        let (path, argument) = match s.strip_suffix(":)") {
+            Some(rest) => {
+                let (path, argument) = rest.split_once('(').ok_or_else(invalid)?;
+                (path, Some(argument))
+            }
+            None => (s, None),
+        };
+
+        if !argument.is_none_or(is_name) {
+            return Err(invalid());
+        }
+
+        let coordinate = match (path.strip_prefix('@'), path.split_once('.'), argument) {
+            (Some(directive), _, None) if is_name(directive) => Self::directive(directive),
+            (Some(directive), _, Some(argument)) if is_name(directive) => Self::DirectiveArgument {
+                directive: directive.to_owned(),
+                argument: argument.to_owned(),
+            },
+            (None, None, None) if is_name(path) => Self::Type(path.to_owned()),
+            (None, Some((ty, member)), argument) if is_name(ty) && is_name(member) => {
+                let member = Self::member(ty, member);
+
+                match argument {
+                    Some(argument) => member.argument(argument).ok_or_else(invalid)?,
+                    None => member,
+                }
+            }
+            _ => return Err(invalid()),
+        };
+
+        Ok(coordinate)
+    }
+}
+
+/// Text that isn't a schema coordinate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidCoordinate(pub String);
+
+impl fmt::Display for InvalidCoordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` is not a valid schema coordinate", self.0)
+    }
+}
+
+impl std::error::Error for InvalidCoordinate {}
+
+/// Whether `s` is a GraphQL name, such as `user` or `_Entity2`
+fn is_name(s: &str) -> bool {
+    let mut bytes = s.bytes();
+
+    matches!(bytes.next(), Some(b'a'..=b'z' | b'A'..=b'Z' | b'_'))
+        && bytes.all(|b| matches!(b, b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{SchemaCoordinate, SchemaElement};
+    use crate::GraphqlParser;
+
+    #[test]
+    fn round_trip() {
+        for coordinate in [
+            "User",
+            "User.name",
+            "User.friends(first:)",
+            "@deprecated",
+            "@deprecated(reason:)",
+            "_Entity2.__typename",
+        ] {
+            let parsed = coordinate.parse::<SchemaCoordinate>().unwrap();
+
+            assert_eq!(parsed.to_string(), coordinate);
+        }
+    }
+
+    #[test]
+    fn invalid_coordinates() {
+        for coordinate in [
+            "",
+            "User.",
+            ".name",
+            "User.name.first",
+            "User(first:)",
+            "User.friends(first)",
+            "User.friends(:)",
+            "User.friends(first:)(last:)",
+            "@",
+            "@deprecated.reason",
+            "User. name",
+            "1User",
+            "User.friends(first: )",
+        ] {
+            assert!(
+                coordinate.parse::<SchemaCoordinate>().is_err(),
+                "{}",
+                coordinate
+            );
+        }
+    }
+
+    #[test]
+    fn parents() {
+        let parent = |coordinate: &str| {
+            coordinate
+                .parse::<SchemaCoordinate>()
+                .unwrap()
+                .parent()
+                .map(|parent| parent.to_string())
+        };
+
+        assert_eq!(
+            parent("User.friends(first:)").as_deref(),
+            Some("User.friends")
+        );
+        assert_eq!(parent("User.friends").as_deref(), Some("User"));
+        assert_eq!(parent("@cached(ttl:)").as_deref(), Some("@cached"));
+        assert_eq!(parent("User"), None);
+        assert_eq!(parent("@cached"), None);
+    }
+
+    #[test]
+    fn resolve() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_schema_with_interner(
+            concat!(
+                "type User implements Node { id: ID! friends(first: Int): [User] } ",
+                "interface Node { id: ID! } ",
+                "input Filter { name: String } ",
+                "enum Role { ADMIN } ",
+                "union Result = User ",
+                "directive @cached(ttl: Int) on FIELD_DEFINITION",
+            )
+            .as_bytes(),
+            &mut interner,
+        )
+        .unwrap();
+
+        let resolve = |coordinate: &str| {
+            coordinate
+                .parse::<SchemaCoordinate>()
+                .unwrap()
+                .resolve(&schema, &interner)
+        };
+
+        assert!(matches!(resolve("User"), Some(SchemaElement::Type(..))));
+        assert!(matches!(resolve("User.id"), Some(SchemaElement::Field(..))));
+        assert!(matches!(resolve("Node.id"), Some(SchemaElement::Field(..))));
+        assert!(matches!(
+            resolve("User.friends(first:)"),
+            Some(SchemaElement::Argument(..))
+        ));
+        assert!(matches!(
+            resolve("Filter.name"),
+            Some(SchemaElement::InputField(..))
+        ));
+        assert!(matches!(
+            resolve("Role.ADMIN"),
+            Some(SchemaElement::EnumValue(..))
+        ));
+        assert!(matches!(
+            resolve("@cached"),
+            Some(SchemaElement::Directive(..))
+        ));
+        assert!(matches!(
+            resolve("@cached(ttl:)"),
+            Some(SchemaElement::Argument(..))
+        ));
+
+        for missing in [
+            "Missing",
+            "User.name",
+            "User.id(first:)",
+            "Result.User",
+            "Role.USER",
+            "@missing",
+            "@cached(size:)",
+        ] {
+            assert!(resolve(missing).is_none(), "{}", missing);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Reporting of deprecated fields, arguments, input fields and enum values
+//! used by the operations and fragments of an executable document
+
+use std::fmt;
+
+use lasso::{Rodeo, Spur};
+
+use crate::ast::{Argument, Document, InputObjectField, Selection, Span, Type, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeprecatedUsage {
+    /// The schema coordinate of what was used, such as `User.name`,
+    /// `Query.user(id:)` or `Role.ADMIN`
+    pub coordinate: String,
+    pub reason: String,
+    /// Where the usage is: the enclosing operation or fragment, such as
+    /// `query GetUser` or `fragment UserFields`, followed by the response keys
+    /// of the fields leading to it
+    pub path: Vec<String>,
+    /// The field, argument or variable definition the usage is in
+    pub span: Span,
+}
+
+impl fmt::Display for DeprecatedUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join("."))?;
+        }
+
+        write!(f, "`{}` is deprecated: {}", self.coordinate, self.reason)
+    }
+}
+
+/// Find every use of a deprecated element of `schema` in `document`, in
+/// source order. Fragments are checked once where they are defined, rather
+/// than at each spread
+///
+/// Both documents must have been parsed with the same `interner`
+pub fn find_deprecated_usages(
+    schema: &Document,
+    document: &Document,
+    interner: &Rodeo,
+) -> Vec<DeprecatedUsage> {
+    let mut finder = DeprecationFinder {
+        schema,
+        interner,
+        usages: Vec::new(),
+        path: Vec::new(),
+        span: Span::default(),
+    };
+
+    for operation in document.operations.values() {
+        finder.path = vec![match operation.name {
+            Some(name) => format!("{} {}", operation.kind.as_str(), finder.name(name)),
+            None => operation.kind.as_str().to_owned(),
+        }];
+
+        for variable in &operation.variable_definitions {
+            if let Some(default) = &variable.default {
+                finder.span = variable.span;
+                finder.check_value(default, &variable.ty);
+            }
+        }
+
+        if let Some(root) = schema.root_operation_type_or_default(operation.kind, interner) {
+            finder.check_selection_set(&operation.selection_set, root);
+        }
+    }
+
+    for fragment in document.fragments.values() {
+        finder.path = vec![format!("fragment {}", finder.name(fragment.name))];
+        finder.check_selection_set(&fragment.selection_set, fragment.on);
+    }
+
+    finder.usages.sort_by_key(|usage| usage.span.start);
+
+    finder.usages
+}
+
+struct DeprecationFinder<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+    usages: Vec<DeprecatedUsage>,
+    path: Vec<String>,
+    span: Span,
+}
+
+impl<'a> DeprecationFinder<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn report(&mut self, coordinate: String, reason: &str) {
+        self.usages.push(DeprecatedUsage {
+            coordinate,
+            reason: reason.to_owned(),
+            path: self.path.clone(),
+            span: self.span,
+        });
+    }
+
+    fn check_selection_set(&mut self, selection_set: &[Selection], parent_type: Spur) {
+        for selection in selection_set {
+            match selection {
+                Selection::Field {
+                    alias,
+                    name,
+                    arguments,
+                    selection_set,
+                    span,
+                    ..
+                } => {
+                    // unknown fields are reported by validation
+                    let field = match self
+                        .schema
+                        .fields_of(parent_type)
+                        .and_then(|fields| fields.iter().find(|field| field.name == *name))
+                    {
+                        Some(field) => field,
+                        None => continue,
+                    };
+
+                    let coordinate = format!("{}.{}", self.name(parent_type), self.name(*name));
+
+                    self.span = *span;
+
+                    if let Some(reason) = field.deprecation(self.interner) {
+                        self.report(coordinate.clone(), reason);
+                    }
+
+                    self.check_arguments(
+                        &coordinate,
+                        arguments.as_deref().unwrap_or_default(),
+                        field.arguments.as_deref().unwrap_or_default(),
+                    );
+
+                    if let Some(selection_set) = selection_set {
+                        self.path.push(self.name(alias.unwrap_or(*name)).to_owned());
+                        self.check_selection_set(selection_set, field.ty.name());
+                        self.path.pop();
+                    }
+                }
+                Selection::InlineFragment {
+                    on, selection_set, ..
+                } => self.check_selection_set(selection_set, on.unwrap_or(parent_type)),
+                Selection::FragmentSpread { .. } => {}
+            }
+        }
+    }
+
+    /// `field` is the coordinate of the field the arguments are given to
+    fn check_arguments(
+        &mut self,
+        field: &str,
+        arguments: &[Argument],
+        definitions: &[InputObjectField],
+    ) {
+        let field_span = self.span;
+
+        for argument in arguments {
+            let definition = match definitions.iter().find(|def| def.name == argument.name) {
+                Some(definition) => definition,
+                None => continue,
+            };
+
+            self.span = argument.span;
+
+            if let Some(reason) = definition.deprecation(self.interner) {
+                self.report(format!("{}({}:)", field, self.name(argument.name)), reason);
+            }
+
+            self.check_value(&argument.value, &definition.ty);
+        }
+
+        self.span = field_span;
+    }
+
+    /// Check the enum values and input object fields in a literal `value` of
+    /// type `ty`
+    fn check_value(&mut self, value: &Value, ty: &Type) {
+        match value {
+            Value::EnumVariant(variant) => {
+                let type_name = ty.name();
+
+                let reason = self
+                    .schema
+                    .enums
+                    .get(&type_name)
+                    .and_then(|enum_def| enum_def.variants.iter().find(|v| v.name == *variant))
+                    .and_then(|variant| variant.deprecation(self.interner));
+
+                if let Some(reason) = reason {
+                    let coordinate = format!("{}.{}", self.name(type_name), self.name(*variant));
+                    self.report(coordinate, reason);
+                }
+            }
+            Value::List(values) => {
+                // a single value may be given where a list is expected
+                let item_ty = match ty {
+                    Type::List { base, .. } => base,
+                    ty => ty,
+                };
+
+                for value in values {
+                    self.check_value(value, item_ty);
+                }
+            }
+            Value::Object(fields) => {
+                let type_name = ty.name();
+
+                let definitions = match self.schema.input_objects.get(&type_name) {
+                    Some(input) => input.fields.as_deref().unwrap_or_default(),
+                    None => return,
+                };
+
+                let mut fields = fields.iter().collect::<Vec<_>>();
+                fields.sort_by_key(|(name, _)| self.name(**name));
+
+                for (name, value) in fields {
+                    let definition = match definitions.iter().find(|def| def.name == *name) {
+                        Some(definition) => definition,
+                        None => continue,
+                    };
+
+                    if let Some(reason) = definition.deprecation(self.interner) {
+                        let coordinate = format!("{}.{}", self.name(type_name), self.name(*name));
+                        self.report(coordinate, reason);
+                    }
+
+                    self.check_value(value, &definition.ty);
+                }
+            }
+            Value::True
+            | Value::False
+            | Value::Null
+            | Value::String(..)
+            | Value::Variable(..)
+            | Value::Float(..)
+            | Value::Int(..) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::find_deprecated_usages;
+    use crate::GraphqlParser;
+
+    const SCHEMA: &str = r#"
+type Query {
+  user(id: ID, login: String @deprecated(reason: "use `id`")): User
+  users(filter: Filter, roles: [Role!]): [User!]!
+}
+type User { id: ID! name: String @deprecated(reason: "use `fullName`") fullName: String }
+input Filter { role: Role, old: Boolean @deprecated }
+enum Role { ADMIN USER GUEST @deprecated(reason: "no longer used") }
+"#;
+
+    /// The deprecated usages in `source`, as their messages
+    fn usages(source: &str) -> Vec<String> {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse document");
+
+        find_deprecated_usages(&schema, &document, &interner)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn fields_and_arguments() {
+        assert_eq!(
+            usages("query Q { user(login: \"a\") { id name fullName } }"),
+            [
+                "query Q: `Query.user(login:)` is deprecated: use `id`",
+                "query Q.user: `User.name` is deprecated: use `fullName`",
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_values_and_input_fields() {
+        assert_eq!(
+            usages(concat!(
+                "query($f: Filter = { old: true }) ",
+                "{ a: users(filter: { role: GUEST }, roles: [ADMIN, GUEST]) { id } ",
+                "b: users(roles: GUEST) { id } }",
+            )),
+            [
+                "query: `Filter.old` is deprecated: No longer supported",
+                "query: `Role.GUEST` is deprecated: no longer used",
+                "query: `Role.GUEST` is deprecated: no longer used",
+                "query: `Role.GUEST` is deprecated: no longer used",
+            ]
+        );
+    }
+
+    #[test]
+    fn fragments_are_checked_once() {
+        assert_eq!(
+            usages(concat!(
+                "query A { user { ...F } } query B { users { ...F } } ",
+                "fragment F on User { friend: name }",
+            )),
+            ["fragment F: `User.name` is deprecated: use `fullName`"]
+        );
+    }
+
+    #[test]
+    fn no_deprecated_usages() {
+        assert_eq!(
+            usages("{ user(id: 1) { id fullName } users(roles: [ADMIN]) { id } }"),
+            Vec::<String>::new()
+        );
+    }
+}
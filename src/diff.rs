@@ -0,0 +1,1004 @@
+//! Comparison of two schemas, classifying each difference by whether it may
+//! break existing clients
+//!
+//! The classification follows graphql-js' `findBreakingChanges` and
+//! `findDangerousChanges`
+
+use std::{collections::HashMap, fmt};
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        DirectiveDefinition, Document, EnumVariant, FieldDefinition, InputObjectField, NamedType,
+        OperationKind, Type, TypeDefinitionRef, TypeKind,
+    },
+    coordinate::SchemaCoordinate,
+    fmt::{print_type, print_value},
+    usage::SchemaUsage,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Criticality {
+    /// Existing queries or clients may stop working
+    Breaking,
+    /// Existing queries keep working, but clients may see values they don't
+    /// expect, such as a new enum value
+    Dangerous,
+    Safe,
+}
+
+impl Criticality {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Breaking => "breaking",
+            Self::Dangerous => "dangerous",
+            Self::Safe => "safe",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaChange {
+    pub criticality: Criticality,
+    pub kind: ChangeKind,
+    /// The changed schema member, such as `User`, `User.name`,
+    /// `User.name(format:)`, `Color.RED` or `@cached(ttl:)`
+    pub path: String,
+    /// Whether the change was downgraded to safe by [`apply_usage`], as no
+    /// operation uses the part of the schema it affects
+    pub is_unused: bool,
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.kind)?;
+
+        if self.is_unused {
+            write!(f, " (unused)")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    TypeAdded,
+    TypeRemoved,
+    TypeKindChanged {
+        old: TypeKind,
+        new: TypeKind,
+    },
+    RootTypeChanged {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    FieldAdded,
+    FieldRemoved,
+    FieldTypeChanged {
+        old: String,
+        new: String,
+    },
+    ArgumentAdded {
+        is_required: bool,
+    },
+    ArgumentRemoved,
+    ArgumentTypeChanged {
+        old: String,
+        new: String,
+    },
+    ArgumentDefaultChanged {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    InputFieldAdded {
+        is_required: bool,
+    },
+    InputFieldRemoved,
+    InputFieldTypeChanged {
+        old: String,
+        new: String,
+    },
+    EnumValueAdded,
+    EnumValueRemoved,
+    UnionMemberAdded {
+        member: String,
+    },
+    UnionMemberRemoved {
+        member: String,
+    },
+    InterfaceAdded {
+        interface: String,
+    },
+    InterfaceRemoved {
+        interface: String,
+    },
+    DirectiveAdded,
+    DirectiveRemoved,
+    DirectiveRepeatableAdded,
+    DirectiveRepeatableRemoved,
+    DirectiveLocationAdded {
+        location: &'static str,
+    },
+    DirectiveLocationRemoved {
+        location: &'static str,
+    },
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn or_none(value: &Option<String>) -> &str {
+            value.as_deref().unwrap_or("none")
+        }
+
+        match self {
+            Self::TypeAdded => write!(f, "type added"),
+            Self::TypeRemoved => write!(f, "type removed"),
+            Self::TypeKindChanged { old, new } => {
+                write!(f, "type changed from {} to {}", old.as_str(), new.as_str())
+            }
+            Self::RootTypeChanged { old, new } => write!(
+                f,
+                "root type changed from `{}` to `{}`",
+                or_none(old),
+                or_none(new)
+            ),
+            Self::FieldAdded => write!(f, "field added"),
+            Self::FieldRemoved => write!(f, "field removed"),
+            Self::FieldTypeChanged { old, new } => {
+                write!(f, "field type changed from `{}` to `{}`", old, new)
+            }
+            Self::ArgumentAdded { is_required: true } => write!(f, "required argument added"),
+            Self::ArgumentAdded { is_required: false } => write!(f, "optional argument added"),
+            Self::ArgumentRemoved => write!(f, "argument removed"),
+            Self::ArgumentTypeChanged { old, new } => {
+                write!(f, "argument type changed from `{}` to `{}`", old, new)
+            }
+            Self::ArgumentDefaultChanged { old, new } => write!(
+                f,
+                "default value changed from `{}` to `{}`",
+                or_none(old),
+                or_none(new)
+            ),
+            Self::InputFieldAdded { is_required: true } => write!(f, "required input field added"),
+            Self::InputFieldAdded { is_required: false } => {
+                write!(f, "optional input field added")
+            }
+            Self::InputFieldRemoved => write!(f, "input field removed"),
+            Self::InputFieldTypeChanged { old, new } => {
+                write!(f, "input field type changed from `{}` to `{}`", old, new)
+            }
+            Self::EnumValueAdded => write!(f, "enum value added"),
+            Self::EnumValueRemoved => write!(f, "enum value removed"),
+            Self::UnionMemberAdded { member } => write!(f, "`{}` added to union", member),
+            Self::UnionMemberRemoved { member } => write!(f, "`{}` removed from union", member),
+            Self::InterfaceAdded { interface } => write!(f, "now implements `{}`", interface),
+            Self::InterfaceRemoved { interface } => {
+                write!(f, "no longer implements `{}`", interface)
+            }
+            Self::DirectiveAdded => write!(f, "directive added"),
+            Self::DirectiveRemoved => write!(f, "directive removed"),
+            Self::DirectiveRepeatableAdded => write!(f, "directive made repeatable"),
+            Self::DirectiveRepeatableRemoved => write!(f, "directive no longer repeatable"),
+            Self::DirectiveLocationAdded { location } => write!(f, "location {} added", location),
+            Self::DirectiveLocationRemoved { location } => {
+                write!(f, "location {} removed", location)
+            }
+        }
+    }
+}
+
+/// Every difference between the schemas `old` and `new`, sorted by path
+///
+/// Both documents must have been parsed with the same `interner`, and should
+/// have had [`Document::apply_extensions`] called on them
+pub fn diff_schemas(old: &Document, new: &Document, interner: &Rodeo) -> Vec<SchemaChange> {
+    let mut differ = SchemaDiffer {
+        interner,
+        changes: Vec::new(),
+    };
+
+    differ.diff_root_types(old, new);
+    differ.diff_types(old, new);
+    differ.diff_directives(&old.directive_definitions, &new.directive_definitions);
+
+    differ
+        .changes
+        .sort_by(|a, b| a.path.cmp(&b.path).then(a.criticality.cmp(&b.criticality)));
+
+    differ.changes
+}
+
+/// Reclassify `changes` by their actual impact on the operations recorded in
+/// `usage`, which was computed against the old schema `old`. Breaking and
+/// dangerous changes to fields, arguments, directives and output types that
+/// no operation uses are downgraded to safe
+///
+/// Changes to enum values, input types and the root types are left alone, as
+/// operations may use them through the values of variables, which `usage`
+/// can't see
+pub fn apply_usage(
+    changes: &mut [SchemaChange],
+    old: &Document,
+    usage: &SchemaUsage,
+    interner: &Rodeo,
+) {
+    for change in changes {
+        if change.criticality == Criticality::Safe {
+            continue;
+        }
+
+        let is_unused = affected_coordinate(change, old, interner)
+            .and_then(|coordinate| usage.count(&coordinate.to_string()))
+            == Some(0);
+
+        if is_unused {
+            change.criticality = Criticality::Safe;
+            change.is_unused = true;
+        }
+    }
+}
+
+/// The coordinate of the old schema that must be used for `change` to affect
+/// an operation, or `None` if its uses can't be known
+fn affected_coordinate(
+    change: &SchemaChange,
+    old: &Document,
+    interner: &Rodeo,
+) -> Option<SchemaCoordinate> {
+    let coordinate = change.path.parse::<SchemaCoordinate>().ok()?;
+
+    match &change.kind {
+        ChangeKind::TypeRemoved | ChangeKind::TypeKindChanged { .. } => {
+            let ty = old.get_type_by_name(&change.path, interner)?;
+
+            match ty.kind() {
+                TypeKind::Object | TypeKind::Interface | TypeKind::Union => Some(coordinate),
+                TypeKind::Scalar | TypeKind::Enum | TypeKind::InputObject => None,
+            }
+        }
+        ChangeKind::UnionMemberAdded { .. } | ChangeKind::UnionMemberRemoved { .. } => {
+            Some(coordinate)
+        }
+        ChangeKind::InterfaceAdded { interface } | ChangeKind::InterfaceRemoved { interface } => {
+            Some(SchemaCoordinate::Type(interface.clone()))
+        }
+        ChangeKind::FieldRemoved
+        | ChangeKind::FieldTypeChanged { .. }
+        | ChangeKind::ArgumentRemoved
+        | ChangeKind::DirectiveRemoved
+        | ChangeKind::DirectiveRepeatableRemoved
+        | ChangeKind::DirectiveLocationRemoved { .. } => Some(coordinate),
+        // an argument that operations never pass can still become required,
+        // so these depend on the field or directive being used at all
+        ChangeKind::ArgumentAdded { .. }
+        | ChangeKind::ArgumentTypeChanged { .. }
+        | ChangeKind::ArgumentDefaultChanged { .. } => coordinate.parent(),
+        ChangeKind::TypeAdded
+        | ChangeKind::RootTypeChanged { .. }
+        | ChangeKind::FieldAdded
+        | ChangeKind::InputFieldAdded { .. }
+        | ChangeKind::InputFieldRemoved
+        | ChangeKind::InputFieldTypeChanged { .. }
+        | ChangeKind::EnumValueAdded
+        | ChangeKind::EnumValueRemoved
+        | ChangeKind::DirectiveAdded
+        | ChangeKind::DirectiveRepeatableAdded
+        | ChangeKind::DirectiveLocationAdded { .. } => None,
+    }
+}
+
+struct SchemaDiffer<'a> {
+    interner: &'a Rodeo,
+    changes: Vec<SchemaChange>,
+}
+
+/// The members of `old` and `new`, matched up by name
+fn by_name<'b, T, K: PartialEq>(
+    old: &'b [T],
+    new: &'b [T],
+    name: impl Fn(&T) -> K,
+) -> (Vec<&'b T>, Vec<(&'b T, &'b T)>, Vec<&'b T>) {
+    let mut removed = Vec::new();
+    let mut kept = Vec::new();
+
+    for old_member in old {
+        match new
+            .iter()
+            .find(|new_member| name(new_member) == name(old_member))
+        {
+            Some(new_member) => kept.push((old_member, new_member)),
+            None => removed.push(old_member),
+        }
+    }
+
+    let added = new
+        .iter()
+        .filter(|new_member| {
+            !old.iter()
+                .any(|old_member| name(old_member) == name(new_member))
+        })
+        .collect();
+
+    (removed, kept, added)
+}
+
+impl<'a> SchemaDiffer<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn change(&mut self, criticality: Criticality, kind: ChangeKind, path: impl ToString) {
+        self.changes.push(SchemaChange {
+            criticality,
+            kind,
+            path: path.to_string(),
+            is_unused: false,
+        });
+    }
+
+    fn root_type(&self, document: &Document, kind: OperationKind) -> Option<String> {
+        document
+            .root_operation_type_or_default(kind, self.interner)
+            .map(|name| self.name(name).to_owned())
+    }
+
+    fn diff_root_types(&mut self, old: &Document, new: &Document) {
+        for kind in [
+            OperationKind::Query,
+            OperationKind::Mutation,
+            OperationKind::Subscription,
+        ] {
+            let old_root = self.root_type(old, kind);
+            let new_root = self.root_type(new, kind);
+
+            if old_root == new_root {
+                continue;
+            }
+
+            let criticality = if old_root.is_none() {
+                Criticality::Safe
+            } else {
+                Criticality::Breaking
+            };
+
+            self.change(
+                criticality,
+                ChangeKind::RootTypeChanged {
+                    old: old_root,
+                    new: new_root,
+                },
+                format!("schema.{}", kind.as_str()),
+            );
+        }
+    }
+
+    fn diff_types(&mut self, old: &Document, new: &Document) {
+        for old_ty in old.types() {
+            let name = self.name(old_ty.name()).to_owned();
+
+            let new_ty = match new.get_type(old_ty.name()) {
+                Some(new_ty) => new_ty,
+                None => {
+                    self.change(Criticality::Breaking, ChangeKind::TypeRemoved, name);
+                    continue;
+                }
+            };
+
+            match (old_ty, new_ty) {
+                (TypeDefinitionRef::Scalar(..), TypeDefinitionRef::Scalar(..)) => {}
+                (TypeDefinitionRef::Object(old_obj), TypeDefinitionRef::Object(new_obj)) => {
+                    self.diff_interfaces(&name, &old_obj.implements, &new_obj.implements);
+                    self.diff_fields(
+                        &name,
+                        old_obj.fields.as_deref().unwrap_or_default(),
+                        new_obj.fields.as_deref().unwrap_or_default(),
+                    );
+                }
+                (
+                    TypeDefinitionRef::Interface(old_interface),
+                    TypeDefinitionRef::Interface(new_interface),
+                ) => {
+                    self.diff_interfaces(
+                        &name,
+                        &old_interface.implements,
+                        &new_interface.implements,
+                    );
+                    self.diff_fields(&name, &old_interface.fields, &new_interface.fields);
+                }
+                (TypeDefinitionRef::Union(old_union), TypeDefinitionRef::Union(new_union)) => {
+                    self.diff_union_members(&name, &old_union.types, &new_union.types);
+                }
+                (TypeDefinitionRef::Enum(old_enum), TypeDefinitionRef::Enum(new_enum)) => {
+                    self.diff_enum_values(&name, &old_enum.variants, &new_enum.variants);
+                }
+                (
+                    TypeDefinitionRef::InputObject(old_input),
+                    TypeDefinitionRef::InputObject(new_input),
+                ) => {
+                    self.diff_input_fields(
+                        &name,
+                        old_input.fields.as_deref().unwrap_or_default(),
+                        new_input.fields.as_deref().unwrap_or_default(),
+                    );
+                }
+                _ => self.change(
+                    Criticality::Breaking,
+                    ChangeKind::TypeKindChanged {
+                        old: old_ty.kind(),
+                        new: new_ty.kind(),
+                    },
+                    name,
+                ),
+            }
+        }
+
+        for new_ty in new.types() {
+            if old.get_type(new_ty.name()).is_none() {
+                self.change(
+                    Criticality::Safe,
+                    ChangeKind::TypeAdded,
+                    self.name(new_ty.name()).to_owned(),
+                );
+            }
+        }
+    }
+
+    fn diff_interfaces(&mut self, ty: &str, old: &[NamedType], new: &[NamedType]) {
+        let (removed, _, added) = by_name(old, new, |interface| interface.0);
+
+        for interface in removed {
+            self.change(
+                Criticality::Breaking,
+                ChangeKind::InterfaceRemoved {
+                    interface: self.name(interface.0).to_owned(),
+                },
+                ty.to_owned(),
+            );
+        }
+
+        for interface in added {
+            self.change(
+                Criticality::Dangerous,
+                ChangeKind::InterfaceAdded {
+                    interface: self.name(interface.0).to_owned(),
+                },
+                ty.to_owned(),
+            );
+        }
+    }
+
+    fn diff_union_members(&mut self, ty: &str, old: &[NamedType], new: &[NamedType]) {
+        let (removed, _, added) = by_name(old, new, |member| member.0);
+
+        for member in removed {
+            self.change(
+                Criticality::Breaking,
+                ChangeKind::UnionMemberRemoved {
+                    member: self.name(member.0).to_owned(),
+                },
+                ty.to_owned(),
+            );
+        }
+
+        for member in added {
+            self.change(
+                Criticality::Dangerous,
+                ChangeKind::UnionMemberAdded {
+                    member: self.name(member.0).to_owned(),
+                },
+                ty.to_owned(),
+            );
+        }
+    }
+
+    fn diff_enum_values(&mut self, ty: &str, old: &[EnumVariant], new: &[EnumVariant]) {
+        let (removed, _, added) = by_name(old, new, |variant| variant.name);
+
+        for variant in removed {
+            let path = SchemaCoordinate::member(ty, self.name(variant.name));
+            self.change(Criticality::Breaking, ChangeKind::EnumValueRemoved, path);
+        }
+
+        for variant in added {
+            let path = SchemaCoordinate::member(ty, self.name(variant.name));
+            self.change(Criticality::Dangerous, ChangeKind::EnumValueAdded, path);
+        }
+    }
+
+    fn diff_fields(&mut self, ty: &str, old: &[FieldDefinition], new: &[FieldDefinition]) {
+        let (removed, kept, added) = by_name(old, new, |field| field.name);
+
+        for field in removed {
+            let path = SchemaCoordinate::member(ty, self.name(field.name));
+            self.change(Criticality::Breaking, ChangeKind::FieldRemoved, path);
+        }
+
+        for (old_field, new_field) in kept {
+            let path = SchemaCoordinate::member(ty, self.name(old_field.name));
+
+            if old_field.ty != new_field.ty {
+                let criticality = if is_safe_output_type_change(&old_field.ty, &new_field.ty) {
+                    Criticality::Safe
+                } else {
+                    Criticality::Breaking
+                };
+
+                self.change(
+                    criticality,
+                    ChangeKind::FieldTypeChanged {
+                        old: print_type(&old_field.ty, self.interner),
+                        new: print_type(&new_field.ty, self.interner),
+                    },
+                    path.clone(),
+                );
+            }
+
+            self.diff_arguments(
+                &path,
+                old_field.arguments.as_deref().unwrap_or_default(),
+                new_field.arguments.as_deref().unwrap_or_default(),
+            );
+        }
+
+        for field in added {
+            let path = SchemaCoordinate::member(ty, self.name(field.name));
+            self.change(Criticality::Safe, ChangeKind::FieldAdded, path);
+        }
+    }
+
+    /// `parent` is the field or directive the arguments belong to
+    fn diff_arguments(
+        &mut self,
+        parent: &SchemaCoordinate,
+        old: &[InputObjectField],
+        new: &[InputObjectField],
+    ) {
+        let (removed, kept, added) = by_name(old, new, |argument| argument.name);
+
+        for argument in removed {
+            let path = argument_of(parent, self.name(argument.name));
+            self.change(Criticality::Breaking, ChangeKind::ArgumentRemoved, path);
+        }
+
+        for (old_argument, new_argument) in kept {
+            let path = argument_of(parent, self.name(old_argument.name));
+
+            if old_argument.ty != new_argument.ty {
+                let criticality = if is_safe_input_type_change(&old_argument.ty, &new_argument.ty) {
+                    Criticality::Safe
+                } else {
+                    Criticality::Breaking
+                };
+
+                self.change(
+                    criticality,
+                    ChangeKind::ArgumentTypeChanged {
+                        old: print_type(&old_argument.ty, self.interner),
+                        new: print_type(&new_argument.ty, self.interner),
+                    },
+                    path.clone(),
+                );
+            }
+
+            let old_default = old_argument
+                .default
+                .as_ref()
+                .map(|value| print_value(value, self.interner));
+            let new_default = new_argument
+                .default
+                .as_ref()
+                .map(|value| print_value(value, self.interner));
+
+            if old_default != new_default {
+                self.change(
+                    Criticality::Dangerous,
+                    ChangeKind::ArgumentDefaultChanged {
+                        old: old_default,
+                        new: new_default,
+                    },
+                    path,
+                );
+            }
+        }
+
+        for argument in added {
+            let path = argument_of(parent, self.name(argument.name));
+            let is_required = is_required(argument);
+
+            let criticality = if is_required {
+                Criticality::Breaking
+            } else {
+                Criticality::Dangerous
+            };
+
+            self.change(criticality, ChangeKind::ArgumentAdded { is_required }, path);
+        }
+    }
+
+    fn diff_input_fields(&mut self, ty: &str, old: &[InputObjectField], new: &[InputObjectField]) {
+        let (removed, kept, added) = by_name(old, new, |field| field.name);
+
+        for field in removed {
+            let path = SchemaCoordinate::member(ty, self.name(field.name));
+            self.change(Criticality::Breaking, ChangeKind::InputFieldRemoved, path);
+        }
+
+        for (old_field, new_field) in kept {
+            if old_field.ty == new_field.ty {
+                continue;
+            }
+
+            let criticality = if is_safe_input_type_change(&old_field.ty, &new_field.ty) {
+                Criticality::Safe
+            } else {
+                Criticality::Breaking
+            };
+
+            self.change(
+                criticality,
+                ChangeKind::InputFieldTypeChanged {
+                    old: print_type(&old_field.ty, self.interner),
+                    new: print_type(&new_field.ty, self.interner),
+                },
+                SchemaCoordinate::member(ty, self.name(old_field.name)),
+            );
+        }
+
+        for field in added {
+            let path = SchemaCoordinate::member(ty, self.name(field.name));
+            let is_required = is_required(field);
+
+            let criticality = if is_required {
+                Criticality::Breaking
+            } else {
+                Criticality::Dangerous
+            };
+
+            self.change(
+                criticality,
+                ChangeKind::InputFieldAdded { is_required },
+                path,
+            );
+        }
+    }
+
+    fn diff_directives(
+        &mut self,
+        old: &HashMap<Spur, DirectiveDefinition>,
+        new: &HashMap<Spur, DirectiveDefinition>,
+    ) {
+        for (name, old_directive) in old {
+            let path = SchemaCoordinate::directive(self.name(*name));
+
+            let new_directive = match new.get(name) {
+                Some(new_directive) => new_directive,
+                None => {
+                    self.change(Criticality::Breaking, ChangeKind::DirectiveRemoved, path);
+                    continue;
+                }
+            };
+
+            self.diff_arguments(
+                &path,
+                old_directive.arguments.as_deref().unwrap_or_default(),
+                new_directive.arguments.as_deref().unwrap_or_default(),
+            );
+
+            match (old_directive.repeatable, new_directive.repeatable) {
+                (true, false) => self.change(
+                    Criticality::Breaking,
+                    ChangeKind::DirectiveRepeatableRemoved,
+                    path.clone(),
+                ),
+                (false, true) => self.change(
+                    Criticality::Safe,
+                    ChangeKind::DirectiveRepeatableAdded,
+                    path.clone(),
+                ),
+                _ => {}
+            }
+
+            let (removed, _, added) =
+                by_name(&old_directive.locations, &new_directive.locations, |l| *l);
+
+            for location in removed {
+                self.change(
+                    Criticality::Breaking,
+                    ChangeKind::DirectiveLocationRemoved {
+                        location: location.as_str(),
+                    },
+                    path.clone(),
+                );
+            }
+
+            for location in added {
+                self.change(
+                    Criticality::Safe,
+                    ChangeKind::DirectiveLocationAdded {
+                        location: location.as_str(),
+                    },
+                    path.clone(),
+                );
+            }
+        }
+
+        for name in new.keys() {
+            if !old.contains_key(name) {
+                let path = SchemaCoordinate::directive(self.name(*name));
+                self.change(Criticality::Safe, ChangeKind::DirectiveAdded, path);
+            }
+        }
+    }
+}
+
+fn argument_of(parent: &SchemaCoordinate, argument: &str) -> SchemaCoordinate {
+    parent
+        .argument(argument)
+        .expect("only fields and directives have arguments")
+}
+
+fn is_required(input_value: &InputObjectField) -> bool {
+    !input_value.ty.is_nullable() && input_value.default.is_none()
+}
+
+/// Whether a field's type may change from `old` to `new` without breaking
+/// queries. Output types may only become more specific, i.e. non-null
+fn is_safe_output_type_change(old: &Type, new: &Type) -> bool {
+    if !old.is_nullable() && new.is_nullable() {
+        return false;
+    }
+
+    match (old, new) {
+        (Type::Named { name: old, .. }, Type::Named { name: new, .. }) => old == new,
+        (Type::List { base: old, .. }, Type::List { base: new, .. }) => {
+            is_safe_output_type_change(old, new)
+        }
+        _ => false,
+    }
+}
+
+/// Whether an argument or input field's type may change from `old` to `new`
+/// without breaking queries. Input types may only become less specific, i.e.
+/// nullable
+fn is_safe_input_type_change(old: &Type, new: &Type) -> bool {
+    if old.is_nullable() && !new.is_nullable() {
+        return false;
+    }
+
+    match (old, new) {
+        (Type::Named { name: old, .. }, Type::Named { name: new, .. }) => old == new,
+        (Type::List { base: old, .. }, Type::List { base: new, .. }) => {
+            is_safe_input_type_change(old, new)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{
+        apply_usage, diff_schemas,
+        Criticality::{self, *},
+    };
+    use crate::{usage::SchemaUsage, GraphqlParser};
+
+    /// The changes from `old` to `new`, as their criticality and message
+    fn changes(old: &str, new: &str) -> Vec<(Criticality, String)> {
+        let mut interner = Rodeo::default();
+
+        let mut parse = |source: &str| {
+            let mut document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+                .expect("failed to parse schema");
+            assert!(document.apply_extensions().is_empty());
+            document
+        };
+
+        let old = parse(old);
+        let new = parse(new);
+
+        diff_schemas(&old, &new, &interner)
+            .into_iter()
+            .map(|change| (change.criticality, change.to_string()))
+            .collect()
+    }
+
+    fn assert_changes(old: &str, new: &str, expected: &[(Criticality, &str)]) {
+        let expected = expected
+            .iter()
+            .map(|(criticality, message)| (*criticality, message.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(changes(old, new), expected);
+    }
+
+    #[test]
+    fn identical_schemas() {
+        let schema = "type Query { a(b: Int = 1): [String!]! } enum E { A } union U = Query";
+
+        assert_eq!(changes(schema, schema), []);
+    }
+
+    #[test]
+    fn types() {
+        assert_changes(
+            "type Query { a: Int } type T { a: Int } enum E { A }",
+            "type Query { a: Int } scalar T input E { a: Int } type New { a: Int }",
+            &[
+                (Breaking, "E: type changed from ENUM to INPUT_OBJECT"),
+                (Safe, "New: type added"),
+                (Breaking, "T: type changed from OBJECT to SCALAR"),
+            ],
+        );
+
+        assert_changes(
+            "type Query { a: Int } type Old { a: Int }",
+            "type Query { a: Int }",
+            &[(Breaking, "Old: type removed")],
+        );
+    }
+
+    #[test]
+    fn fields() {
+        assert_changes(
+            "type Query { a: Int b: String! c: [Int] d: Int }",
+            "type Query { a: Int! b: String c: [Int!] e: Int }",
+            &[
+                (Safe, "Query.a: field type changed from `Int` to `Int!`"),
+                (
+                    Breaking,
+                    "Query.b: field type changed from `String!` to `String`",
+                ),
+                (Safe, "Query.c: field type changed from `[Int]` to `[Int!]`"),
+                (Breaking, "Query.d: field removed"),
+                (Safe, "Query.e: field added"),
+            ],
+        );
+    }
+
+    #[test]
+    fn arguments() {
+        assert_changes(
+            "type Query { a(x: Int!, y: Int = 1, old: Int): Int }",
+            "type Query { a(x: Int, y: Int = 2, z: Int, w: Int!): Int }",
+            &[
+                (Breaking, "Query.a(old:): argument removed"),
+                (Breaking, "Query.a(w:): required argument added"),
+                (
+                    Safe,
+                    "Query.a(x:): argument type changed from `Int!` to `Int`",
+                ),
+                (
+                    Dangerous,
+                    "Query.a(y:): default value changed from `1` to `2`",
+                ),
+                (Dangerous, "Query.a(z:): optional argument added"),
+            ],
+        );
+    }
+
+    #[test]
+    fn input_fields() {
+        assert_changes(
+            "type Query { a: Int } input In { a: Int b: Int! c: Int }",
+            "type Query { a: Int } input In { a: Int! b: Int d: Int! e: Int }",
+            &[
+                (
+                    Breaking,
+                    "In.a: input field type changed from `Int` to `Int!`",
+                ),
+                (Safe, "In.b: input field type changed from `Int!` to `Int`"),
+                (Breaking, "In.c: input field removed"),
+                (Breaking, "In.d: required input field added"),
+                (Dangerous, "In.e: optional input field added"),
+            ],
+        );
+    }
+
+    #[test]
+    fn enums_unions_and_interfaces() {
+        assert_changes(
+            concat!(
+                "type Query implements I { a: Int } type T { a: Int } ",
+                "interface I { a: Int } interface J { a: Int } enum E { A B } union U = Query | T",
+            ),
+            concat!(
+                "type Query implements J { a: Int } type T { a: Int } ",
+                "interface I { a: Int } interface J { a: Int } enum E { A C } union U = Query",
+            ),
+            &[
+                (Breaking, "E.B: enum value removed"),
+                (Dangerous, "E.C: enum value added"),
+                (Breaking, "Query: no longer implements `I`"),
+                (Dangerous, "Query: now implements `J`"),
+                (Breaking, "U: `T` removed from union"),
+            ],
+        );
+    }
+
+    #[test]
+    fn directives() {
+        assert_changes(
+            concat!(
+                "type Query { a: Int } ",
+                "directive @d(a: Int) repeatable on FIELD | QUERY directive @o on FIELD",
+            ),
+            concat!(
+                "type Query { a: Int } ",
+                "directive @d(a: Int = 1) on FIELD | MUTATION directive @n on FIELD",
+            ),
+            &[
+                (Breaking, "@d: directive no longer repeatable"),
+                (Breaking, "@d: location QUERY removed"),
+                (Safe, "@d: location MUTATION added"),
+                (
+                    Dangerous,
+                    "@d(a:): default value changed from `none` to `1`",
+                ),
+                (Safe, "@n: directive added"),
+                (Breaking, "@o: directive removed"),
+            ],
+        );
+    }
+
+    #[test]
+    fn root_types() {
+        assert_changes(
+            "schema { query: Query mutation: M } type Query { a: Int } type M { a: Int }",
+            "schema { query: Query } type Query { a: Int } type M { a: Int }",
+            &[(
+                Breaking,
+                "schema.mutation: root type changed from `M` to `none`",
+            )],
+        );
+    }
+
+    #[test]
+    fn unused_changes_are_safe() {
+        let mut interner = Rodeo::default();
+
+        let mut parse = |source: &str| {
+            GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+                .expect("failed to parse document")
+        };
+
+        let old = parse(concat!(
+            "type Query { user(id: ID): User legacy: String role: Role } ",
+            "type User { id: ID! name: String } type Admin { id: ID! } ",
+            "enum Role { ADMIN USER }",
+        ));
+        let new = parse(concat!(
+            "type Query { user(id: ID!): User role: Role } ",
+            "type User { id: ID! } enum Role { ADMIN }",
+        ));
+        let operation = parse("{ user { id } }");
+
+        let mut usage = SchemaUsage::new(&old, &interner);
+        usage.record(&old, &operation, &interner);
+
+        let mut changes = diff_schemas(&old, &new, &interner);
+        apply_usage(&mut changes, &old, &usage, &interner);
+
+        let changes = changes
+            .into_iter()
+            .map(|change| (change.criticality, change.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            changes,
+            [
+                (Safe, "Admin: type removed (unused)".to_owned()),
+                (Safe, "Query.legacy: field removed (unused)".to_owned()),
+                (
+                    Breaking,
+                    "Query.user(id:): argument type changed from `ID` to `ID!`".to_owned()
+                ),
+                (Breaking, "Role.USER: enum value removed".to_owned()),
+                (Safe, "User.name: field removed (unused)".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,839 @@
+//! Static documentation generated from the descriptions of a schema: an
+//! index page listing every type and directive, and a page per type with its
+//! fields, arguments, values and deprecations. References to types defined in
+//! the schema link to their pages
+//!
+//! Descriptions are CommonMark, so they're copied as is into Markdown pages,
+//! and escaped as plain text in HTML pages
+//!
+//! Type extensions should have been applied with
+//! [`Document::apply_extensions`] first, as they aren't documented
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        Directive, DirectiveDefinition, Document, Enum, FieldDefinition, InputObject,
+        InputObjectField, OperationKind, Type, TypeDefinitionRef, TypeKind,
+    },
+    fmt::print_value,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocsFormat {
+    Markdown,
+    Html,
+}
+
+impl DocsFormat {
+    /// The extension of the pages generated in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DocsConfig {
+    pub format: DocsFormat,
+    /// The heading of the index page
+    pub title: String,
+}
+
+impl Default for DocsConfig {
+    fn default() -> Self {
+        Self {
+            format: DocsFormat::Markdown,
+            title: "Schema".to_owned(),
+        }
+    }
+}
+
+/// A generated page, to be written to `path` relative to the output directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocsPage {
+    pub path: String,
+    pub content: String,
+}
+
+/// The documentation of `schema`: `index.md` or `index.html`, followed by a
+/// page named after each type in alphabetical order. Introspection types
+/// aren't documented
+pub fn generate_docs(schema: &Document, interner: &Rodeo, config: &DocsConfig) -> Vec<DocsPage> {
+    let generator = DocsGenerator {
+        schema,
+        interner,
+        format: config.format,
+    };
+
+    let mut types = schema
+        .types()
+        .filter(|ty| !generator.name(ty.name()).starts_with("__"))
+        .collect::<Vec<_>>();
+
+    types.sort_by_key(|ty| generator.name(ty.name()));
+
+    let mut pages = vec![DocsPage {
+        path: format!("index.{}", config.format.extension()),
+        content: generator.index(&config.title, &types),
+    }];
+
+    pages.extend(types.iter().map(|ty| DocsPage {
+        path: generator.page_path(ty.name()),
+        content: generator.type_page(ty),
+    }));
+
+    pages
+}
+
+struct DocsGenerator<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+    format: DocsFormat,
+}
+
+impl DocsGenerator<'_> {
+    fn name(&self, name: Spur) -> &str {
+        self.interner.resolve(&name)
+    }
+
+    fn page_path(&self, name: Spur) -> String {
+        format!("{}.{}", self.name(name), self.format.extension())
+    }
+
+    fn index(&self, title: &str, types: &[TypeDefinitionRef]) -> String {
+        let mut page = Page::new(self.format, title);
+
+        page.heading(1, &self.text(title));
+
+        if let Some(schema) = &self.schema.schema {
+            page.description(schema.description.map(|description| self.name(description)));
+        }
+
+        let roots = [
+            OperationKind::Query,
+            OperationKind::Mutation,
+            OperationKind::Subscription,
+        ]
+        .into_iter()
+        .filter_map(|kind| {
+            let ty = self
+                .schema
+                .root_operation_type_or_default(kind, self.interner)?;
+
+            Some(format!("{}: {}", kind.as_str(), self.link(ty)))
+        })
+        .collect::<Vec<_>>();
+
+        if !roots.is_empty() {
+            page.heading(2, "Operations");
+            page.list(&roots);
+        }
+
+        let sections = [
+            (TypeKind::Object, "Objects"),
+            (TypeKind::Interface, "Interfaces"),
+            (TypeKind::Union, "Unions"),
+            (TypeKind::Enum, "Enums"),
+            (TypeKind::InputObject, "Input objects"),
+            (TypeKind::Scalar, "Scalars"),
+        ];
+
+        for (kind, heading) in sections {
+            let items = types
+                .iter()
+                .filter(|ty| ty.kind() == kind)
+                .map(|ty| match ty.description() {
+                    Some(description) => format!(
+                        "{}: {}",
+                        self.link(ty.name()),
+                        self.summary(self.name(description))
+                    ),
+                    None => self.link(ty.name()),
+                })
+                .collect::<Vec<_>>();
+
+            if !items.is_empty() {
+                page.heading(2, heading);
+                page.list(&items);
+            }
+        }
+
+        let mut directives = self
+            .schema
+            .directive_definitions
+            .values()
+            .collect::<Vec<_>>();
+
+        directives.sort_by_key(|directive| self.name(directive.name));
+
+        if !directives.is_empty() {
+            page.heading(2, "Directives");
+        }
+
+        for directive in directives {
+            self.directive_definition(&mut page, directive);
+        }
+
+        page.finish()
+    }
+
+    fn directive_definition(&self, page: &mut Page, directive: &DirectiveDefinition) {
+        page.heading(3, &self.code(&format!("@{}", self.name(directive.name))));
+        page.description(
+            directive
+                .description
+                .map(|description| self.name(description)),
+        );
+
+        let locations = directive
+            .locations
+            .iter()
+            .map(|location| self.code(location.as_str()))
+            .collect::<Vec<_>>();
+
+        let repeatable = if directive.repeatable {
+            ", and may be repeated"
+        } else {
+            ""
+        };
+
+        page.paragraph(&format!(
+            "Applies to {}{}",
+            locations.join(", "),
+            repeatable
+        ));
+
+        self.arguments(page, directive.arguments.as_deref().unwrap_or_default());
+    }
+
+    fn type_page(&self, ty: &TypeDefinitionRef) -> String {
+        let name = self.name(ty.name());
+        let mut page = Page::new(self.format, name);
+
+        page.heading(1, &self.text(name));
+        page.paragraph(&self.emphasis(match ty.kind() {
+            TypeKind::Scalar => "Scalar",
+            TypeKind::Object => "Object",
+            TypeKind::Interface => "Interface",
+            TypeKind::Union => "Union",
+            TypeKind::Enum => "Enum",
+            TypeKind::InputObject => "Input object",
+        }));
+        page.description(ty.description().map(|description| self.name(description)));
+        self.applied_directives(&mut page, ty.directives());
+
+        match ty {
+            TypeDefinitionRef::Scalar(scalar) => {
+                if let Some(url) = scalar.specified_by_url(self.interner) {
+                    page.paragraph(&format!("Specified by {}", self.url(url)));
+                }
+            }
+            TypeDefinitionRef::Object(object) => {
+                self.type_list(
+                    &mut page,
+                    "Implements",
+                    object.implements.iter().map(|ty| ty.0),
+                );
+                self.fields(&mut page, object.fields.as_deref().unwrap_or_default());
+            }
+            TypeDefinitionRef::Interface(interface) => {
+                self.type_list(
+                    &mut page,
+                    "Implements",
+                    interface.implements.iter().map(|ty| ty.0),
+                );
+                self.fields(&mut page, &interface.fields);
+
+                let mut implementations = self.schema.possible_types(interface.name);
+
+                implementations.sort_by_key(|name| self.name(*name));
+
+                self.type_list(&mut page, "Implemented by", implementations);
+            }
+            TypeDefinitionRef::Union(union) => {
+                self.type_list(&mut page, "Members", union.types.iter().map(|ty| ty.0));
+            }
+            TypeDefinitionRef::Enum(enum_ty) => self.values(&mut page, enum_ty),
+            TypeDefinitionRef::InputObject(input) => self.input_fields(&mut page, input),
+        }
+
+        page.finish()
+    }
+
+    fn type_list(&self, page: &mut Page, heading: &str, types: impl IntoIterator<Item = Spur>) {
+        let items = types
+            .into_iter()
+            .map(|ty| self.link(ty))
+            .collect::<Vec<_>>();
+
+        if !items.is_empty() {
+            page.heading(2, heading);
+            page.list(&items);
+        }
+    }
+
+    fn fields(&self, page: &mut Page, fields: &[FieldDefinition]) {
+        if fields.is_empty() {
+            return;
+        }
+
+        page.heading(2, "Fields");
+
+        for field in fields {
+            page.heading(
+                3,
+                &format!(
+                    "{}: {}",
+                    self.code(self.name(field.name)),
+                    self.type_ref(&field.ty)
+                ),
+            );
+            self.deprecation(page, field.deprecation(self.interner));
+            page.description(field.description.map(|description| self.name(description)));
+            self.applied_directives(page, &field.directives);
+            self.arguments(page, field.arguments.as_deref().unwrap_or_default());
+        }
+    }
+
+    fn arguments(&self, page: &mut Page, arguments: &[InputObjectField]) {
+        if !arguments.is_empty() {
+            page.table(
+                &["Argument", "Type", "Default", "Description"],
+                arguments
+                    .iter()
+                    .map(|argument| self.input_value_row(argument)),
+            );
+        }
+    }
+
+    fn input_fields(&self, page: &mut Page, input: &InputObject) {
+        let fields = input.fields.as_deref().unwrap_or_default();
+
+        if !fields.is_empty() {
+            page.heading(2, "Fields");
+            page.table(
+                &["Field", "Type", "Default", "Description"],
+                fields.iter().map(|field| self.input_value_row(field)),
+            );
+        }
+    }
+
+    fn input_value_row(&self, value: &InputObjectField) -> Vec<String> {
+        vec![
+            self.code(self.name(value.name)),
+            self.type_ref(&value.ty),
+            value
+                .default
+                .as_ref()
+                .map(|default| self.code(&print_value(default, self.interner)))
+                .unwrap_or_default(),
+            self.cell_description(value.description, value.deprecation(self.interner)),
+        ]
+    }
+
+    fn values(&self, page: &mut Page, enum_ty: &Enum) {
+        if !enum_ty.variants.is_empty() {
+            page.heading(2, "Values");
+            page.table(
+                &["Value", "Description"],
+                enum_ty.variants.iter().map(|variant| {
+                    vec![
+                        self.code(self.name(variant.name)),
+                        self.cell_description(
+                            variant.description,
+                            variant.deprecation(self.interner),
+                        ),
+                    ]
+                }),
+            );
+        }
+    }
+
+    fn deprecation(&self, page: &mut Page, reason: Option<&str>) {
+        if let Some(reason) = reason {
+            page.paragraph(&format!(
+                "{} {}",
+                self.strong("Deprecated:"),
+                self.text(reason)
+            ));
+        }
+    }
+
+    /// Directives applied to a definition, other than `@deprecated`, which is
+    /// documented separately
+    fn applied_directives(&self, page: &mut Page, directives: &[Directive]) {
+        let directives = directives
+            .iter()
+            .filter(|directive| self.name(directive.name) != "deprecated")
+            .map(|directive| self.code(&self.directive(directive)))
+            .collect::<Vec<_>>();
+
+        if !directives.is_empty() {
+            page.paragraph(&format!("Directives: {}", directives.join(" ")));
+        }
+    }
+
+    fn directive(&self, directive: &Directive) -> String {
+        let mut text = format!("@{}", self.name(directive.name));
+
+        if let Some(arguments) = &directive.arguments {
+            let arguments = arguments
+                .iter()
+                .map(|argument| {
+                    format!(
+                        "{}: {}",
+                        self.name(argument.name),
+                        print_value(&argument.value, self.interner)
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            text.push_str(&format!("({})", arguments.join(", ")));
+        }
+
+        text
+    }
+
+    /// A description and deprecation reason on a single line, for a table
+    /// cell
+    fn cell_description(&self, description: Option<Spur>, deprecation: Option<&str>) -> String {
+        let mut cell = String::new();
+
+        if let Some(reason) = deprecation {
+            cell.push_str(&format!(
+                "{} {}",
+                self.strong("Deprecated:"),
+                self.text(reason)
+            ));
+        }
+
+        if let Some(description) = description {
+            if !cell.is_empty() {
+                cell.push(' ');
+            }
+
+            cell.push_str(&self.summary(self.name(description)));
+        }
+
+        cell
+    }
+
+    /// `description` on a single line
+    fn summary(&self, description: &str) -> String {
+        let line = description.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        match self.format {
+            DocsFormat::Markdown => line.replace('|', "\\|"),
+            DocsFormat::Html => escape_html(&line),
+        }
+    }
+
+    /// `ty` with its list and non-null wrappers, linking to the named type
+    fn type_ref(&self, ty: &Type) -> String {
+        match ty {
+            Type::Named { name, nullable } => {
+                format!("{}{}", self.link(*name), if *nullable { "" } else { "!" })
+            }
+            Type::List { base, nullable } => {
+                format!(
+                    "[{}]{}",
+                    self.type_ref(base),
+                    if *nullable { "" } else { "!" }
+                )
+            }
+        }
+    }
+
+    /// A link to the page of the type named `name`, or just its name if it
+    /// isn't defined in the schema
+    fn link(&self, name: Spur) -> String {
+        let code = self.code(self.name(name));
+
+        if self.schema.type_kind(name).is_none() || self.name(name).starts_with("__") {
+            return code;
+        }
+
+        match self.format {
+            DocsFormat::Markdown => format!("[{}]({})", code, self.page_path(name)),
+            DocsFormat::Html => format!("<a href=\"{}\">{}</a>", self.page_path(name), code),
+        }
+    }
+
+    fn url(&self, url: &str) -> String {
+        match self.format {
+            DocsFormat::Markdown => format!("<{}>", url),
+            DocsFormat::Html => format!("<a href=\"{0}\">{0}</a>", escape_html(url)),
+        }
+    }
+
+    fn code(&self, text: &str) -> String {
+        match self.format {
+            DocsFormat::Markdown if text.contains('`') => format!("`` {} ``", text),
+            DocsFormat::Markdown => format!("`{}`", text.replace('|', "\\|")),
+            DocsFormat::Html => format!("<code>{}</code>", escape_html(text)),
+        }
+    }
+
+    fn strong(&self, text: &str) -> String {
+        match self.format {
+            DocsFormat::Markdown => format!("**{}**", text),
+            DocsFormat::Html => format!("<strong>{}</strong>", escape_html(text)),
+        }
+    }
+
+    fn emphasis(&self, text: &str) -> String {
+        match self.format {
+            DocsFormat::Markdown => format!("*{}*", text),
+            DocsFormat::Html => format!("<em>{}</em>", escape_html(text)),
+        }
+    }
+
+    fn text(&self, text: &str) -> String {
+        match self.format {
+            DocsFormat::Markdown => text.to_owned(),
+            DocsFormat::Html => escape_html(text),
+        }
+    }
+}
+
+/// The blocks of a page. Inline content passed to it is already formatted
+struct Page {
+    format: DocsFormat,
+    buffer: String,
+}
+
+impl Page {
+    fn new(format: DocsFormat, title: &str) -> Self {
+        let buffer = match format {
+            DocsFormat::Markdown => String::new(),
+            DocsFormat::Html => format!(
+                concat!(
+                    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+                    "<title>{}</title>\n</head>\n<body>\n",
+                ),
+                escape_html(title)
+            ),
+        };
+
+        Self { format, buffer }
+    }
+
+    fn heading(&mut self, level: usize, content: &str) {
+        match self.format {
+            DocsFormat::Markdown => {
+                self.start_block();
+                self.buffer
+                    .push_str(&format!("{} {}\n", "#".repeat(level), content));
+            }
+            DocsFormat::Html => self
+                .buffer
+                .push_str(&format!("<h{0}>{1}</h{0}>\n", level, content)),
+        }
+    }
+
+    fn paragraph(&mut self, content: &str) {
+        match self.format {
+            DocsFormat::Markdown => {
+                self.start_block();
+                self.buffer.push_str(content);
+                self.buffer.push('\n');
+            }
+            DocsFormat::Html => self.buffer.push_str(&format!("<p>{}</p>\n", content)),
+        }
+    }
+
+    /// A description, as paragraphs separated by blank lines
+    fn description(&mut self, description: Option<&str>) {
+        let description = match description {
+            Some(description) if !description.trim().is_empty() => description.trim(),
+            _ => return,
+        };
+
+        match self.format {
+            DocsFormat::Markdown => self.paragraph(description),
+            DocsFormat::Html => {
+                for paragraph in description.split("\n\n") {
+                    self.paragraph(&escape_html(paragraph.trim()));
+                }
+            }
+        }
+    }
+
+    fn list(&mut self, items: &[String]) {
+        match self.format {
+            DocsFormat::Markdown => {
+                self.start_block();
+
+                for item in items {
+                    self.buffer.push_str(&format!("- {}\n", item));
+                }
+            }
+            DocsFormat::Html => {
+                self.buffer.push_str("<ul>\n");
+
+                for item in items {
+                    self.buffer.push_str(&format!("<li>{}</li>\n", item));
+                }
+
+                self.buffer.push_str("</ul>\n");
+            }
+        }
+    }
+
+    fn table(&mut self, headers: &[&str], rows: impl Iterator<Item = Vec<String>>) {
+        match self.format {
+            DocsFormat::Markdown => {
+                self.start_block();
+                self.buffer
+                    .push_str(&format!("| {} |\n", headers.join(" | ")));
+                self.buffer
+                    .push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+
+                for row in rows {
+                    self.buffer.push_str(&format!("| {} |\n", row.join(" | ")));
+                }
+            }
+            DocsFormat::Html => {
+                self.buffer.push_str("<table>\n<tr>");
+
+                for header in headers {
+                    self.buffer.push_str(&format!("<th>{}</th>", header));
+                }
+
+                self.buffer.push_str("</tr>\n");
+
+                for row in rows {
+                    self.buffer.push_str("<tr>");
+
+                    for cell in row {
+                        self.buffer.push_str(&format!("<td>{}</td>", cell));
+                    }
+
+                    self.buffer.push_str("</tr>\n");
+                }
+
+                self.buffer.push_str("</table>\n");
+            }
+        }
+    }
+
+    /// Separate a Markdown block from the one before it
+    fn start_block(&mut self) {
+        if !self.buffer.is_empty() {
+            self.buffer.push('\n');
+        }
+    }
+
+    fn finish(mut self) -> String {
+        if self.format == DocsFormat::Html {
+            self.buffer.push_str("</body>\n</html>\n");
+        }
+
+        self.buffer
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{generate_docs, DocsConfig, DocsFormat, DocsPage};
+    use crate::GraphqlParser;
+
+    const SCHEMA: &str = r#"
+"""
+The user directory
+"""
+schema { query: Query }
+
+"Limits the rate of calls"
+directive @rateLimit(max: Int = 10) repeatable on FIELD_DEFINITION
+
+type Query {
+  "Look up a user"
+  user(id: ID!, login: String @deprecated(reason: "use `id`")): User @rateLimit(max: 5)
+  node(id: ID!): Node
+}
+
+interface Node { id: ID! }
+
+"A person with an account"
+type User implements Node {
+  id: ID!
+  name: String @deprecated(reason: "use `fullName`")
+  fullName: String
+  roles: [Role!]!
+}
+
+enum Role { ADMIN "Can only read" VIEWER GUEST @deprecated }
+
+scalar DateTime @specifiedBy(url: "https://tools.ietf.org/html/rfc3339")
+"#;
+
+    fn docs(format: DocsFormat) -> Vec<DocsPage> {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_schema_with_interner(SCHEMA.as_bytes(), &mut interner)
+            .expect("failed to parse schema");
+
+        let config = DocsConfig {
+            format,
+            ..DocsConfig::default()
+        };
+
+        generate_docs(&schema, &interner, &config)
+    }
+
+    fn page<'a>(pages: &'a [DocsPage], path: &str) -> &'a str {
+        &pages
+            .iter()
+            .find(|page| page.path == path)
+            .unwrap_or_else(|| panic!("missing page {}", path))
+            .content
+    }
+
+    #[test]
+    fn markdown_pages() {
+        let pages = docs(DocsFormat::Markdown);
+
+        assert_eq!(
+            pages
+                .iter()
+                .map(|page| page.path.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "index.md",
+                "DateTime.md",
+                "Node.md",
+                "Query.md",
+                "Role.md",
+                "User.md"
+            ]
+        );
+
+        assert_eq!(
+            page(&pages, "index.md"),
+            concat!(
+                "# Schema\n",
+                "\n",
+                "The user directory\n",
+                "\n",
+                "## Operations\n",
+                "\n",
+                "- query: [`Query`](Query.md)\n",
+                "\n",
+                "## Objects\n",
+                "\n",
+                "- [`Query`](Query.md)\n",
+                "- [`User`](User.md): A person with an account\n",
+                "\n",
+                "## Interfaces\n",
+                "\n",
+                "- [`Node`](Node.md)\n",
+                "\n",
+                "## Enums\n",
+                "\n",
+                "- [`Role`](Role.md)\n",
+                "\n",
+                "## Scalars\n",
+                "\n",
+                "- [`DateTime`](DateTime.md)\n",
+                "\n",
+                "## Directives\n",
+                "\n",
+                "### `@rateLimit`\n",
+                "\n",
+                "Limits the rate of calls\n",
+                "\n",
+                "Applies to `FIELD_DEFINITION`, and may be repeated\n",
+                "\n",
+                "| Argument | Type | Default | Description |\n",
+                "| --- | --- | --- | --- |\n",
+                "| `max` | `Int` | `10` |  |\n",
+            )
+        );
+
+        assert_eq!(
+            page(&pages, "Query.md"),
+            concat!(
+                "# Query\n",
+                "\n",
+                "*Object*\n",
+                "\n",
+                "## Fields\n",
+                "\n",
+                "### `user`: [`User`](User.md)\n",
+                "\n",
+                "Look up a user\n",
+                "\n",
+                "Directives: `@rateLimit(max: 5)`\n",
+                "\n",
+                "| Argument | Type | Default | Description |\n",
+                "| --- | --- | --- | --- |\n",
+                "| `id` | `ID`! |  |  |\n",
+                "| `login` | `String` |  | **Deprecated:** use `id` |\n",
+                "\n",
+                "### `node`: [`Node`](Node.md)\n",
+                "\n",
+                "| Argument | Type | Default | Description |\n",
+                "| --- | --- | --- | --- |\n",
+                "| `id` | `ID`! |  |  |\n",
+            )
+        );
+    }
+
+    #[test]
+    fn cross_links_and_deprecations() {
+        let pages = docs(DocsFormat::Markdown);
+
+        let user = page(&pages, "User.md");
+
+        assert!(user.contains("## Implements\n\n- [`Node`](Node.md)\n"));
+        assert!(user.contains("### `name`: `String`\n\n**Deprecated:** use `fullName`\n"));
+        assert!(user.contains("### `roles`: [[`Role`](Role.md)!]!\n"));
+
+        assert!(page(&pages, "Node.md").contains("## Implemented by\n\n- [`User`](User.md)\n"));
+        assert!(page(&pages, "Role.md").contains(concat!(
+            "| `VIEWER` | Can only read |\n",
+            "| `GUEST` | **Deprecated:** No longer supported |\n",
+        )));
+        assert!(page(&pages, "DateTime.md")
+            .contains("Specified by <https://tools.ietf.org/html/rfc3339>\n"));
+    }
+
+    #[test]
+    fn html_pages() {
+        let pages = docs(DocsFormat::Html);
+
+        assert_eq!(pages[0].path, "index.html");
+
+        let user = page(&pages, "User.html");
+
+        assert!(user.starts_with("<!DOCTYPE html>\n"));
+        assert!(user.contains("<title>User</title>"));
+        assert!(user.contains("<p>A person with an account</p>\n"));
+        assert!(user.contains(concat!(
+            "<h3><code>roles</code>: ",
+            "[<a href=\"Role.html\"><code>Role</code></a>!]!</h3>\n",
+        )));
+        assert!(user.ends_with("</body>\n</html>\n"));
+    }
+}
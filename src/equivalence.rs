@@ -0,0 +1,1133 @@
+//! Structural equality of ASTs, ignoring where in the source each node came
+//! from
+//!
+//! Two nodes are equivalent if they'd print the same way: spans are ignored,
+//! while names, descriptions, values and the order of fields, arguments,
+//! directives and selections are compared. Definitions are compared by name,
+//! so the order they appear in doesn't matter. Both nodes must use the same
+//! interner, since names are compared by key
+
+use std::{collections::HashMap, hash::Hash};
+
+use crate::ast::{
+    Argument, Definition, Directive, DirectiveDefinition, Document, Enum, EnumVariant,
+    FieldDefinition, Fragment, InputObject, InputObjectField, Interface, ObjectType, Operation,
+    RootOperationTypeDefinition, Scalar, SchemaDefinition, SchemaExtension, Selection,
+    TypeDefinition, TypeExtension, Union, VariableDefinition,
+};
+
+pub trait Equivalent {
+    /// Whether `self` and `other` are the same, other than their spans
+    fn equivalent(&self, other: &Self) -> bool;
+}
+
+impl<T: Equivalent> Equivalent for [T] {
+    fn equivalent(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a.equivalent(b))
+    }
+}
+
+impl<T: Equivalent> Equivalent for Vec<T> {
+    fn equivalent(&self, other: &Self) -> bool {
+        self.as_slice().equivalent(other.as_slice())
+    }
+}
+
+impl<T: Equivalent> Equivalent for Option<T> {
+    fn equivalent(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Some(a), Some(b)) => a.equivalent(b),
+            (None, None) => true,
+            (Some(..), None) | (None, Some(..)) => false,
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: Equivalent> Equivalent for HashMap<K, V> {
+    fn equivalent(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, a)| other.get(key).is_some_and(|b| a.equivalent(b)))
+    }
+}
+
+impl Equivalent for Document {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            schema,
+            operations,
+            fragments,
+            input_objects,
+            output_objects,
+            interfaces,
+            scalars,
+            unions,
+            enums,
+            directive_definitions,
+            type_extensions,
+            schema_extensions,
+            duplicates,
+        } = self;
+
+        schema.equivalent(&other.schema)
+            && operations.equivalent(&other.operations)
+            && fragments.equivalent(&other.fragments)
+            && input_objects.equivalent(&other.input_objects)
+            && output_objects.equivalent(&other.output_objects)
+            && interfaces.equivalent(&other.interfaces)
+            && scalars.equivalent(&other.scalars)
+            && unions.equivalent(&other.unions)
+            && enums.equivalent(&other.enums)
+            && directive_definitions.equivalent(&other.directive_definitions)
+            && type_extensions.equivalent(&other.type_extensions)
+            && schema_extensions.equivalent(&other.schema_extensions)
+            && duplicates.equivalent(&other.duplicates)
+    }
+}
+
+impl Equivalent for Definition {
+    fn equivalent(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Schema(a), Self::Schema(b)) => a.equivalent(b),
+            (Self::Operation(a), Self::Operation(b)) => a.equivalent(b),
+            (Self::Fragment(a), Self::Fragment(b)) => a.equivalent(b),
+            (Self::TypeDecl(a), Self::TypeDecl(b)) => a.equivalent(b),
+            (Self::Directive(a), Self::Directive(b)) => a.equivalent(b),
+            (Self::TypeExtension(a), Self::TypeExtension(b)) => a.equivalent(b),
+            (Self::SchemaExtension(a), Self::SchemaExtension(b)) => a.equivalent(b),
+            _ => false,
+        }
+    }
+}
+
+impl Equivalent for SchemaDefinition {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            description,
+            directives,
+            operation_types,
+            span: _,
+        } = self;
+
+        *description == other.description
+            && directives.equivalent(&other.directives)
+            && operation_types.equivalent(&other.operation_types)
+    }
+}
+
+impl Equivalent for SchemaExtension {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            directives,
+            operation_types,
+            span: _,
+        } = self;
+
+        directives.equivalent(&other.directives)
+            && operation_types.equivalent(&other.operation_types)
+    }
+}
+
+impl Equivalent for RootOperationTypeDefinition {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self { kind, ty, span: _ } = self;
+
+        *kind == other.kind && *ty == other.ty
+    }
+}
+
+impl Equivalent for TypeDefinition {
+    fn equivalent(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Scalar(a), Self::Scalar(b)) => a.equivalent(b),
+            (Self::Object(a), Self::Object(b)) => a.equivalent(b),
+            (Self::Interface(a), Self::Interface(b)) => a.equivalent(b),
+            (Self::Union(a), Self::Union(b)) => a.equivalent(b),
+            (Self::Enum(a), Self::Enum(b)) => a.equivalent(b),
+            (Self::InputObject(a), Self::InputObject(b)) => a.equivalent(b),
+            _ => false,
+        }
+    }
+}
+
+impl Equivalent for TypeExtension {
+    fn equivalent(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Scalar(a), Self::Scalar(b)) => a.equivalent(b),
+            (Self::Object(a), Self::Object(b)) => a.equivalent(b),
+            (Self::Interface(a), Self::Interface(b)) => a.equivalent(b),
+            (Self::Union(a), Self::Union(b)) => a.equivalent(b),
+            (Self::Enum(a), Self::Enum(b)) => a.equivalent(b),
+            (Self::InputObject(a), Self::InputObject(b)) => a.equivalent(b),
+            _ => false,
+        }
+    }
+}
+
+impl Equivalent for Scalar {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            description,
+            name,
+            directives,
+            span: _,
+        } = self;
+
+        *description == other.description
+            && *name == other.name
+            && directives.equivalent(&other.directives)
+    }
+}
+
+impl Equivalent for ObjectType {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            implements,
+            description,
+            name,
+            directives,
+            fields,
+            span: _,
+        } = self;
+
+        *implements == other.implements
+            && *description == other.description
+            && *name == other.name
+            && directives.equivalent(&other.directives)
+            && fields.equivalent(&other.fields)
+    }
+}
+
+impl Equivalent for Interface {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            implements,
+            description,
+            name,
+            directives,
+            fields,
+            span: _,
+        } = self;
+
+        *implements == other.implements
+            && *description == other.description
+            && *name == other.name
+            && directives.equivalent(&other.directives)
+            && fields.equivalent(&other.fields)
+    }
+}
+
+impl Equivalent for Union {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            description,
+            name,
+            types,
+            directives,
+            span: _,
+        } = self;
+
+        *description == other.description
+            && *name == other.name
+            && *types == other.types
+            && directives.equivalent(&other.directives)
+    }
+}
+
+impl Equivalent for Enum {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            description,
+            name,
+            directives,
+            variants,
+            span: _,
+        } = self;
+
+        *description == other.description
+            && *name == other.name
+            && directives.equivalent(&other.directives)
+            && variants.equivalent(&other.variants)
+    }
+}
+
+impl Equivalent for EnumVariant {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            description,
+            name,
+            directives,
+            span: _,
+        } = self;
+
+        *description == other.description
+            && *name == other.name
+            && directives.equivalent(&other.directives)
+    }
+}
+
+impl Equivalent for InputObject {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            description,
+            name,
+            directives,
+            fields,
+            span: _,
+        } = self;
+
+        *description == other.description
+            && *name == other.name
+            && directives.equivalent(&other.directives)
+            && fields.equivalent(&other.fields)
+    }
+}
+
+impl Equivalent for FieldDefinition {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            description,
+            name,
+            ty,
+            ty_span: _,
+            arguments,
+            directives,
+            span: _,
+        } = self;
+
+        *description == other.description
+            && *name == other.name
+            && *ty == other.ty
+            && arguments.equivalent(&other.arguments)
+            && directives.equivalent(&other.directives)
+    }
+}
+
+impl Equivalent for InputObjectField {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            description,
+            name,
+            ty,
+            ty_span: _,
+            default,
+            default_span: _,
+            directives,
+            span: _,
+        } = self;
+
+        *description == other.description
+            && *name == other.name
+            && *ty == other.ty
+            && *default == other.default
+            && directives.equivalent(&other.directives)
+    }
+}
+
+impl Equivalent for DirectiveDefinition {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            description,
+            name,
+            arguments,
+            repeatable,
+            locations,
+            span: _,
+        } = self;
+
+        *description == other.description
+            && *name == other.name
+            && arguments.equivalent(&other.arguments)
+            && *repeatable == other.repeatable
+            && *locations == other.locations
+    }
+}
+
+impl Equivalent for Directive {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            name,
+            arguments,
+            span: _,
+        } = self;
+
+        *name == other.name && arguments.equivalent(&other.arguments)
+    }
+}
+
+impl Equivalent for Argument {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            name,
+            value,
+            value_span: _,
+            span: _,
+        } = self;
+
+        *name == other.name && *value == other.value
+    }
+}
+
+impl Equivalent for Operation {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            kind,
+            name,
+            variable_definitions,
+            directives,
+            selection_set,
+            span: _,
+        } = self;
+
+        *kind == other.kind
+            && *name == other.name
+            && variable_definitions.equivalent(&other.variable_definitions)
+            && directives.equivalent(&other.directives)
+            && selection_set.equivalent(&other.selection_set)
+    }
+}
+
+impl Equivalent for VariableDefinition {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            name,
+            ty,
+            ty_span: _,
+            default,
+            default_span: _,
+            span: _,
+        } = self;
+
+        *name == other.name && *ty == other.ty && *default == other.default
+    }
+}
+
+impl Equivalent for Fragment {
+    fn equivalent(&self, other: &Self) -> bool {
+        let Self {
+            name,
+            on,
+            directives,
+            selection_set,
+            span: _,
+        } = self;
+
+        *name == other.name
+            && *on == other.on
+            && directives.equivalent(&other.directives)
+            && selection_set.equivalent(&other.selection_set)
+    }
+}
+
+impl Equivalent for Selection {
+    fn equivalent(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Field {
+                    alias,
+                    name,
+                    arguments,
+                    directives,
+                    selection_set,
+                    span: _,
+                },
+                Self::Field {
+                    alias: other_alias,
+                    name: other_name,
+                    arguments: other_arguments,
+                    directives: other_directives,
+                    selection_set: other_selection_set,
+                    span: _,
+                },
+            ) => {
+                alias == other_alias
+                    && name == other_name
+                    && arguments.equivalent(other_arguments)
+                    && directives.equivalent(other_directives)
+                    && selection_set.equivalent(other_selection_set)
+            }
+            (
+                Self::FragmentSpread {
+                    name,
+                    directives,
+                    span: _,
+                },
+                Self::FragmentSpread {
+                    name: other_name,
+                    directives: other_directives,
+                    span: _,
+                },
+            ) => name == other_name && directives.equivalent(other_directives),
+            (
+                Self::InlineFragment {
+                    on,
+                    directives,
+                    selection_set,
+                    span: _,
+                },
+                Self::InlineFragment {
+                    on: other_on,
+                    directives: other_directives,
+                    selection_set: other_selection_set,
+                    span: _,
+                },
+            ) => {
+                on == other_on
+                    && directives.equivalent(other_directives)
+                    && selection_set.equivalent(other_selection_set)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::{Key, Rodeo, Spur};
+    use proptest::{collection, option, prelude::*, sample};
+
+    use super::Equivalent;
+    use crate::{
+        ast::{
+            Argument, Definition, Directive, DirectiveDefinition, DirectiveLocation, Document,
+            Enum, EnumVariant, FieldDefinition, Fragment, InputObject, InputObjectField, Interface,
+            NamedType, ObjectType, Operation, OperationKind, RootOperationTypeDefinition, Scalar,
+            SchemaDefinition, SchemaExtension, Selection, Span, Type, TypeDefinition,
+            TypeExtension, Union, Value, ValueSpan, VariableDefinition,
+        },
+        fmt::print_document,
+        GraphqlParser,
+    };
+
+    /// The names generated documents use, including keywords, which are
+    /// names everywhere they're allowed. `true`, `false` and `null` can't be
+    /// enum values, so they're left out
+    const NAMES: &[&str] = &[
+        "a",
+        "b",
+        "c",
+        "_d",
+        "e1",
+        "Query",
+        "User",
+        "on",
+        "type",
+        "query",
+        "fragment",
+        "input",
+        "extend",
+        "repeatable",
+    ];
+
+    /// The strings generated documents use, as descriptions and string values
+    const STRINGS: &[&str] = &[
+        "",
+        "plain",
+        "with \"quotes\"",
+        "\"\"\"",
+        "ends with a quote\"",
+        "multi\nline",
+        "  indented\n    lines",
+        "\nleading newline",
+        "trailing newline\n",
+        "   ",
+        "back\\slash",
+        "tab\tand\r\ncarriage return",
+        "unicode é ☃ 𝄞",
+        "control \u{1} \u{7f}",
+    ];
+
+    /// An interner whose keys are the indices of [`NAMES`] followed by those
+    /// of [`STRINGS`], which the generators below produce directly
+    fn interner() -> Rodeo {
+        let mut interner = Rodeo::default();
+
+        for (idx, string) in NAMES.iter().chain(STRINGS).enumerate() {
+            assert_eq!(interner.get_or_intern(string).into_usize(), idx);
+        }
+
+        interner
+    }
+
+    fn key(idx: usize) -> Spur {
+        Spur::try_from_usize(idx).unwrap()
+    }
+
+    fn name() -> impl Strategy<Value = Spur> {
+        (0..NAMES.len()).prop_map(key)
+    }
+
+    /// A name other than `on`, which can't name a fragment
+    fn fragment_name() -> impl Strategy<Value = Spur> {
+        name().prop_filter("`on` can't name a fragment", |name| {
+            NAMES[name.into_usize()] != "on"
+        })
+    }
+
+    fn string() -> impl Strategy<Value = Spur> {
+        (0..STRINGS.len()).prop_map(|idx| key(NAMES.len() + idx))
+    }
+
+    fn description() -> impl Strategy<Value = Option<Spur>> {
+        option::of(string())
+    }
+
+    fn ty() -> impl Strategy<Value = Type> {
+        let named =
+            (name(), any::<bool>()).prop_map(|(name, nullable)| Type::Named { name, nullable });
+
+        named.prop_recursive(3, 8, 1, |base| {
+            (base, any::<bool>()).prop_map(|(base, nullable)| Type::List {
+                base: Box::new(base),
+                nullable,
+            })
+        })
+    }
+
+    /// A value, which may contain variables if `is_const` is false
+    fn value(is_const: bool) -> impl Strategy<Value = Value> {
+        let float = prop_oneof![
+            proptest::num::f64::NORMAL,
+            proptest::num::f64::SUBNORMAL,
+            proptest::num::f64::ZERO,
+        ];
+
+        let leaf = prop_oneof![
+            Just(Value::True),
+            Just(Value::False),
+            Just(Value::Null),
+            string().prop_map(Value::String),
+            name().prop_map(Value::EnumVariant),
+            any::<i64>().prop_map(Value::Int),
+            float.prop_map(Value::Float),
+        ];
+
+        let leaf = if is_const {
+            leaf.boxed()
+        } else {
+            prop_oneof![leaf, name().prop_map(Value::Variable)].boxed()
+        };
+
+        leaf.prop_recursive(3, 16, 3, |value| {
+            prop_oneof![
+                collection::vec(value.clone(), 0..3).prop_map(Value::List),
+                collection::hash_map(name(), value, 0..3).prop_map(Value::Object),
+            ]
+        })
+    }
+
+    /// `None`, or `Some` of a non-empty list, as an empty list can't be
+    /// written
+    fn non_empty<T: Strategy>(element: T) -> impl Strategy<Value = Option<Vec<T::Value>>> {
+        option::of(collection::vec(element, 1..3))
+    }
+
+    fn arguments(is_const: bool) -> impl Strategy<Value = Option<Vec<Argument>>> {
+        non_empty(
+            (name(), value(is_const)).prop_map(|(name, value)| Argument {
+                name,
+                value,
+                value_span: ValueSpan::default(),
+                span: Span::default(),
+            }),
+        )
+    }
+
+    fn directives(is_const: bool) -> impl Strategy<Value = Vec<Directive>> {
+        collection::vec(
+            (name(), arguments(is_const)).prop_map(|(name, arguments)| Directive {
+                name,
+                arguments,
+                span: Span::default(),
+            }),
+            0..3,
+        )
+    }
+
+    fn named_types() -> impl Strategy<Value = Vec<NamedType>> {
+        collection::vec(name().prop_map(NamedType), 0..3)
+    }
+
+    fn input_value_definition() -> impl Strategy<Value = InputObjectField> {
+        (
+            description(),
+            name(),
+            ty(),
+            option::of(value(true)),
+            directives(true),
+        )
+            .prop_map(
+                |(description, name, ty, default, directives)| InputObjectField {
+                    description,
+                    name,
+                    ty,
+                    ty_span: Span::default(),
+                    default,
+                    default_span: ValueSpan::default(),
+                    directives,
+                    span: Span::default(),
+                },
+            )
+    }
+
+    fn field_definition() -> impl Strategy<Value = FieldDefinition> {
+        (
+            description(),
+            name(),
+            ty(),
+            non_empty(input_value_definition()),
+            directives(true),
+        )
+            .prop_map(
+                |(description, name, ty, arguments, directives)| FieldDefinition {
+                    description,
+                    name,
+                    ty,
+                    ty_span: Span::default(),
+                    arguments,
+                    directives,
+                    span: Span::default(),
+                },
+            )
+    }
+
+    fn field_definitions() -> impl Strategy<Value = Vec<FieldDefinition>> {
+        collection::vec(field_definition(), 0..3)
+    }
+
+    fn enum_variant() -> impl Strategy<Value = EnumVariant> {
+        (description(), name(), directives(true)).prop_map(|(description, name, directives)| {
+            EnumVariant {
+                description,
+                name,
+                directives,
+                span: Span::default(),
+            }
+        })
+    }
+
+    /// A type definition named `name`, without a description if it's an
+    /// extension
+    fn type_definition(name: Spur, is_extension: bool) -> impl Strategy<Value = TypeDefinition> {
+        let description = if is_extension {
+            Just(None).boxed()
+        } else {
+            description().boxed()
+        };
+
+        (
+            description,
+            directives(true),
+            named_types(),
+            field_definitions(),
+            collection::vec(enum_variant(), 0..3),
+            non_empty(input_value_definition()),
+        )
+            .prop_flat_map(
+                move |(description, directives, types, fields, variants, input_fields)| {
+                    let fields_or_none = (!fields.is_empty()).then(|| fields.clone());
+
+                    prop_oneof![
+                        Just(TypeDefinition::Scalar(Scalar {
+                            description,
+                            name,
+                            directives: directives.clone(),
+                            span: Span::default(),
+                        })),
+                        Just(TypeDefinition::Object(ObjectType {
+                            implements: types.clone(),
+                            description,
+                            name,
+                            directives: directives.clone(),
+                            fields: fields_or_none,
+                            span: Span::default(),
+                        })),
+                        Just(TypeDefinition::Interface(Interface {
+                            implements: types.clone(),
+                            description,
+                            name,
+                            directives: directives.clone(),
+                            fields,
+                            span: Span::default(),
+                        })),
+                        Just(TypeDefinition::Union(Union {
+                            description,
+                            name,
+                            types,
+                            directives: directives.clone(),
+                            span: Span::default(),
+                        })),
+                        Just(TypeDefinition::Enum(Enum {
+                            description,
+                            name,
+                            directives: directives.clone(),
+                            variants,
+                            span: Span::default(),
+                        })),
+                        Just(TypeDefinition::InputObject(InputObject {
+                            description,
+                            name,
+                            directives,
+                            fields: input_fields,
+                            span: Span::default(),
+                        })),
+                    ]
+                },
+            )
+    }
+
+    /// Whether an extension has anything to extend its type with, since
+    /// `extend type A` alone can't be parsed
+    fn extends_anything(extension: &TypeDefinition) -> bool {
+        match extension {
+            TypeDefinition::Scalar(scalar) => !scalar.directives.is_empty(),
+            TypeDefinition::Object(object) => {
+                !object.implements.is_empty()
+                    || !object.directives.is_empty()
+                    || object.fields.is_some()
+            }
+            TypeDefinition::Interface(interface) => {
+                !interface.implements.is_empty()
+                    || !interface.directives.is_empty()
+                    || !interface.fields.is_empty()
+            }
+            TypeDefinition::Union(union) => !union.types.is_empty() || !union.directives.is_empty(),
+            TypeDefinition::Enum(enum_def) => {
+                !enum_def.variants.is_empty() || !enum_def.directives.is_empty()
+            }
+            TypeDefinition::InputObject(input) => {
+                input.fields.is_some() || !input.directives.is_empty()
+            }
+        }
+    }
+
+    fn type_extension() -> impl Strategy<Value = TypeExtension> {
+        name()
+            .prop_flat_map(|name| type_definition(name, true))
+            .prop_filter("an extension must extend something", extends_anything)
+            .prop_map(|extension| match extension {
+                TypeDefinition::Scalar(scalar) => TypeExtension::Scalar(scalar),
+                TypeDefinition::Object(object) => TypeExtension::Object(object),
+                TypeDefinition::Interface(interface) => TypeExtension::Interface(interface),
+                TypeDefinition::Union(union) => TypeExtension::Union(union),
+                TypeDefinition::Enum(enum_def) => TypeExtension::Enum(enum_def),
+                TypeDefinition::InputObject(input) => TypeExtension::InputObject(input),
+            })
+    }
+
+    fn operation_kind() -> impl Strategy<Value = OperationKind> {
+        prop_oneof![
+            Just(OperationKind::Query),
+            Just(OperationKind::Mutation),
+            Just(OperationKind::Subscription),
+        ]
+    }
+
+    fn operation_types(min: usize) -> impl Strategy<Value = Vec<RootOperationTypeDefinition>> {
+        let kinds = vec![
+            OperationKind::Query,
+            OperationKind::Mutation,
+            OperationKind::Subscription,
+        ];
+
+        sample::subsequence(kinds, min..=3).prop_flat_map(|kinds| {
+            collection::vec(name(), kinds.len()).prop_map(move |types| {
+                kinds
+                    .iter()
+                    .zip(types)
+                    .map(|(kind, ty)| RootOperationTypeDefinition {
+                        kind: *kind,
+                        ty: NamedType(ty),
+                        span: Span::default(),
+                    })
+                    .collect()
+            })
+        })
+    }
+
+    fn schema_definition() -> impl Strategy<Value = SchemaDefinition> {
+        (description(), directives(true), operation_types(1)).prop_map(
+            |(description, directives, operation_types)| SchemaDefinition {
+                description,
+                directives,
+                operation_types,
+                span: Span::default(),
+            },
+        )
+    }
+
+    fn schema_extension() -> impl Strategy<Value = SchemaExtension> {
+        (directives(true), operation_types(0))
+            .prop_filter(
+                "an extension must extend something",
+                |(directives, types)| !directives.is_empty() || !types.is_empty(),
+            )
+            .prop_map(|(directives, operation_types)| SchemaExtension {
+                directives,
+                operation_types,
+                span: Span::default(),
+            })
+    }
+
+    fn directive_definition(name: Spur) -> impl Strategy<Value = DirectiveDefinition> {
+        let locations = [
+            "QUERY",
+            "FIELD",
+            "FRAGMENT_SPREAD",
+            "SCHEMA",
+            "OBJECT",
+            "FIELD_DEFINITION",
+            "ARGUMENT_DEFINITION",
+            "ENUM_VALUE",
+        ]
+        .map(|location| DirectiveLocation::from_name(location).unwrap());
+
+        (
+            description(),
+            non_empty(input_value_definition()),
+            any::<bool>(),
+            sample::subsequence(locations.to_vec(), 1..4),
+        )
+            .prop_map(move |(description, arguments, repeatable, locations)| {
+                DirectiveDefinition {
+                    description,
+                    name,
+                    arguments,
+                    repeatable,
+                    locations,
+                    span: Span::default(),
+                }
+            })
+    }
+
+    fn selection_set() -> impl Strategy<Value = Vec<Selection>> {
+        let leaf = prop_oneof![
+            (
+                option::of(name()),
+                name(),
+                arguments(false),
+                directives(false)
+            )
+                .prop_map(|(alias, name, arguments, directives)| Selection::Field {
+                    alias,
+                    name,
+                    arguments,
+                    directives,
+                    selection_set: None,
+                    span: Span::default(),
+                }),
+            (fragment_name(), directives(false)).prop_map(|(name, directives)| {
+                Selection::FragmentSpread {
+                    name,
+                    directives,
+                    span: Span::default(),
+                }
+            }),
+        ];
+
+        let selection = leaf.prop_recursive(3, 16, 3, |selection| {
+            let selection_set = collection::vec(selection, 1..3);
+
+            prop_oneof![
+                (
+                    option::of(name()),
+                    name(),
+                    arguments(false),
+                    directives(false),
+                    selection_set.clone(),
+                )
+                    .prop_map(
+                        |(alias, name, arguments, directives, selection_set)| {
+                            Selection::Field {
+                                alias,
+                                name,
+                                arguments,
+                                directives,
+                                selection_set: Some(selection_set),
+                                span: Span::default(),
+                            }
+                        }
+                    ),
+                (option::of(name()), directives(false), selection_set).prop_map(
+                    |(on, directives, selection_set)| Selection::InlineFragment {
+                        on,
+                        directives,
+                        selection_set,
+                        span: Span::default(),
+                    }
+                ),
+            ]
+        });
+
+        collection::vec(selection, 1..3)
+    }
+
+    fn variable_definition() -> impl Strategy<Value = VariableDefinition> {
+        (name(), ty(), option::of(value(true))).prop_map(|(name, ty, default)| VariableDefinition {
+            name,
+            ty,
+            ty_span: Span::default(),
+            default,
+            default_span: ValueSpan::default(),
+            span: Span::default(),
+        })
+    }
+
+    fn operation(name: Option<Spur>, kind: OperationKind) -> impl Strategy<Value = Operation> {
+        (
+            collection::vec(variable_definition(), 0..3),
+            directives(false),
+            selection_set(),
+        )
+            .prop_map(
+                move |(variable_definitions, directives, selection_set)| Operation {
+                    kind,
+                    name,
+                    variable_definitions,
+                    directives,
+                    selection_set,
+                    span: Span::default(),
+                },
+            )
+    }
+
+    fn fragment(name: Spur) -> impl Strategy<Value = Fragment> {
+        (self::name(), directives(false), selection_set()).prop_map(
+            move |(on, directives, selection_set)| Fragment {
+                name,
+                on,
+                directives,
+                selection_set,
+                span: Span::default(),
+            },
+        )
+    }
+
+    /// A strategy for each entry of a map whose keys are generated by `key`,
+    /// with values generated from their key by `value`
+    fn map_of<K, S>(
+        key: impl Strategy<Value = K>,
+        value: impl Fn(K) -> S + Clone,
+        size: std::ops::Range<usize>,
+    ) -> impl Strategy<Value = Vec<S::Value>>
+    where
+        K: std::hash::Hash + Eq + Clone + std::fmt::Debug,
+        S: Strategy,
+    {
+        collection::hash_set(key, size)
+            .prop_flat_map(move |keys| keys.into_iter().map(value.clone()).collect::<Vec<_>>())
+    }
+
+    /// A random document that can be printed and parsed again. Every
+    /// definition has a unique name, so none are duplicates
+    fn document() -> impl Strategy<Value = Document> {
+        let types = map_of(name(), |name| type_definition(name, false), 0..5);
+        let directive_definitions = map_of(name(), directive_definition, 0..3);
+        // operations of different kinds can't share a name either
+        let operations = map_of(
+            option::of(name()),
+            |name| operation_kind().prop_flat_map(move |kind| operation(name, kind)),
+            0..3,
+        );
+        let fragments = map_of(fragment_name(), fragment, 0..3);
+
+        (
+            option::of(schema_definition()),
+            types,
+            directive_definitions,
+            collection::vec(type_extension(), 0..3),
+            collection::vec(schema_extension(), 0..2),
+            operations,
+            fragments,
+        )
+            .prop_map(
+                |(
+                    schema,
+                    types,
+                    directive_definitions,
+                    type_extensions,
+                    schema_extensions,
+                    operations,
+                    fragments,
+                )| {
+                    let mut document = Document::new();
+
+                    document.schema = schema;
+                    document.type_extensions = type_extensions;
+                    document.schema_extensions = schema_extensions;
+
+                    let definitions = types
+                        .into_iter()
+                        .map(Definition::TypeDecl)
+                        .chain(directive_definitions.into_iter().map(Definition::Directive))
+                        .chain(operations.into_iter().map(Definition::Operation))
+                        .chain(fragments.into_iter().map(Definition::Fragment));
+
+                    for definition in definitions {
+                        document.add_definition(definition);
+                    }
+
+                    assert!(document.duplicates.is_empty());
+
+                    document
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn print_parse_round_trip(document in document()) {
+            let mut interner = interner();
+
+            let printed = print_document(&document, &interner);
+            let parsed = GraphqlParser::parse_with_interner(printed.as_bytes(), &mut interner)
+                .map_err(|err| TestCaseError::fail(format!("{:?}\n{}", err, printed)))?;
+
+            prop_assert!(parsed.equivalent(&document), "{}", printed);
+        }
+    }
+
+    fn parse(source: &str, interner: &mut Rodeo) -> Document {
+        GraphqlParser::parse_with_interner(source.as_bytes(), interner).unwrap()
+    }
+
+    #[test]
+    fn ignores_spans_and_definition_order() {
+        let mut interner = Rodeo::default();
+
+        let a = parse("type A { b: Int } scalar C", &mut interner);
+        let b = parse("scalar C\n\n type A {\n  b: Int\n}", &mut interner);
+
+        assert!(a.equivalent(&b));
+    }
+
+    #[test]
+    fn compares_everything_else() {
+        let mut interner = Rodeo::default();
+
+        let document = parse(
+            r#""A" type A { b(c: Int = 1): Int @d c: [Int!] } query Q($v: Int) { b(c: $v) { ... on A { c } } }"#,
+            &mut interner,
+        );
+
+        for different in [
+            r#""B" type A { b(c: Int = 1): Int @d c: [Int!] } query Q($v: Int) { b(c: $v) { ... on A { c } } }"#,
+            r#""A" type A { c: [Int!] b(c: Int = 1): Int @d } query Q($v: Int) { b(c: $v) { ... on A { c } } }"#,
+            r#""A" type A { b(c: Int = 2): Int @d c: [Int!] } query Q($v: Int) { b(c: $v) { ... on A { c } } }"#,
+            r#""A" type A { b(c: Int = 1): Int c: [Int!] } query Q($v: Int) { b(c: $v) { ... on A { c } } }"#,
+            r#""A" type A { b(c: Int = 1): Int @d c: [Int] } query Q($v: Int) { b(c: $v) { ... on A { c } } }"#,
+            r#""A" type A { b(c: Int = 1): Int @d c: [Int!] } query Q($v: Int!) { b(c: $v) { ... on A { c } } }"#,
+            r#""A" type A { b(c: Int = 1): Int @d c: [Int!] } query Q($v: Int) { b(c: 1) { ... on A { c } } }"#,
+            r#""A" type A { b(c: Int = 1): Int @d c: [Int!] } query Q($v: Int) { b(c: $v) { ... { c } } }"#,
+            r#""A" type A { b(c: Int = 1): Int @d c: [Int!] } query R($v: Int) { b(c: $v) { ... on A { c } } }"#,
+            r#""A" interface A { b(c: Int = 1): Int @d c: [Int!] } query Q($v: Int) { b(c: $v) { ... on A { c } } }"#,
+        ] {
+            assert!(
+                !document.equivalent(&parse(different, &mut interner)),
+                "{}",
+                different
+            );
+        }
+    }
+}
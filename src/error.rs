@@ -1,7 +1,212 @@
-use crate::ast::Token;
+use std::fmt;
+
+use crate::ast::{Span, Token};
 
 #[derive(Debug)]
 pub enum GraphqlParseError {
-    ExpectedChar { token: char, found: Option<char> },
-    ExpectedToken { token: Token, found: Option<Token> },
+    ExpectedChar {
+        token: char,
+        found: Option<char>,
+        span: Span,
+    },
+    ExpectedToken {
+        token: Token,
+        found: Option<Token>,
+        span: Span,
+    },
+    UnexpectedToken {
+        found: Token,
+        expected: &'static str,
+        span: Span,
+    },
+    UnexpectedEof {
+        expected: &'static str,
+        span: Span,
+    },
+    /// A byte that can't start any token
+    InvalidCharacter {
+        found: char,
+        span: Span,
+    },
+    /// A malformed int or float literal, such as `01` or `1.`
+    InvalidNumber {
+        span: Span,
+    },
+    /// A `.` or `..` that isn't part of a `...`
+    InvalidToken {
+        found: &'static str,
+        span: Span,
+    },
+    InvalidEscapeSequence {
+        span: Span,
+    },
+    InvalidUtf8 {
+        span: Span,
+    },
+    /// A description was given for a definition that can't have one, such as
+    /// a type extension
+    UnexpectedDescription {
+        span: Span,
+    },
+    UnknownDirectiveLocation {
+        name: String,
+        span: Span,
+    },
+    /// An operation or fragment in a document parsed as a schema
+    ExecutableDefinitionInSchema {
+        span: Span,
+    },
+    /// A type system definition or extension in a document parsed as an
+    /// executable document
+    TypeSystemDefinitionInExecutable {
+        span: Span,
+    },
+    /// The document went over one of the [`ParseLimits`] it was parsed with
+    ///
+    /// [`ParseLimits`]: crate::lexer::ParseLimits
+    LimitExceeded {
+        limit: ParseLimit,
+        max: usize,
+        span: Span,
+    },
+}
+
+/// Which of the [`ParseLimits`] a document went over
+///
+/// [`ParseLimits`]: crate::lexer::ParseLimits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseLimit {
+    Tokens,
+    Depth,
+    StringLength,
+    InputSize,
+}
+
+impl GraphqlParseError {
+    /// The byte range in the source that caused this error
+    pub fn span(&self) -> Span {
+        match self {
+            Self::ExpectedChar { span, .. }
+            | Self::ExpectedToken { span, .. }
+            | Self::UnexpectedToken { span, .. }
+            | Self::UnexpectedEof { span, .. }
+            | Self::InvalidCharacter { span, .. }
+            | Self::InvalidNumber { span }
+            | Self::InvalidToken { span, .. }
+            | Self::InvalidEscapeSequence { span }
+            | Self::InvalidUtf8 { span }
+            | Self::UnexpectedDescription { span }
+            | Self::UnknownDirectiveLocation { span, .. }
+            | Self::ExecutableDefinitionInSchema { span }
+            | Self::TypeSystemDefinitionInExecutable { span }
+            | Self::LimitExceeded { span, .. } => *span,
+        }
+    }
+
+    /// The line and column this error starts at in `source`, which must be the
+    /// buffer that was parsed
+    pub fn location(&self, source: &[u8]) -> LineColumn {
+        self.span().start_location(source)
+    }
+}
+
+impl fmt::Display for GraphqlParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ExpectedChar { token, found, .. } => match found {
+                Some(found) => write!(f, "expected `{}`, found `{}`", token, found),
+                None => write!(f, "expected `{}`, found end of input", token),
+            },
+            Self::ExpectedToken { token, found, .. } => match found {
+                Some(found) => write!(f, "expected {}, found {}", token, found),
+                None => write!(f, "expected {}, found end of input", token),
+            },
+            Self::UnexpectedToken {
+                found, expected, ..
+            } => write!(f, "expected {}, found {}", expected, found),
+            Self::UnexpectedEof { expected, .. } => {
+                write!(f, "expected {}, found end of input", expected)
+            }
+            Self::InvalidCharacter { found, .. } => write!(f, "unexpected character {:?}", found),
+            Self::InvalidNumber { .. } => write!(f, "invalid number"),
+            Self::InvalidToken { found, .. } => write!(f, "expected `...`, found `{}`", found),
+            Self::InvalidEscapeSequence { .. } => write!(f, "invalid escape sequence"),
+            Self::InvalidUtf8 { .. } => write!(f, "invalid utf-8"),
+            Self::UnexpectedDescription { .. } => {
+                write!(f, "descriptions are not allowed here")
+            }
+            Self::UnknownDirectiveLocation { name, .. } => {
+                write!(f, "unknown directive location `{}`", name)
+            }
+            Self::ExecutableDefinitionInSchema { .. } => {
+                write!(f, "operations and fragments are not allowed in a schema")
+            }
+            Self::TypeSystemDefinitionInExecutable { .. } => write!(
+                f,
+                "type system definitions are not allowed in an executable document"
+            ),
+            Self::LimitExceeded { limit, max, .. } => match limit {
+                ParseLimit::Tokens => write!(f, "the document has more than {} tokens", max),
+                ParseLimit::Depth => {
+                    write!(f, "the document is nested more than {} levels deep", max)
+                }
+                ParseLimit::StringLength => write!(f, "string is longer than {} bytes", max),
+                ParseLimit::InputSize => write!(f, "the document is larger than {} bytes", max),
+            },
+        }
+    }
+}
+
+impl std::error::Error for GraphqlParseError {}
+
+/// A 1-based position in a source buffer. Columns count characters, not bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for LineColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl Span {
+    pub fn start_location(&self, source: &[u8]) -> LineColumn {
+        LineColumn::from_offset(source, self.start)
+    }
+
+    pub fn end_location(&self, source: &[u8]) -> LineColumn {
+        LineColumn::from_offset(source, self.end)
+    }
+}
+
+impl LineColumn {
+    pub fn from_offset(source: &[u8], offset: usize) -> Self {
+        let mut line = 1;
+        let mut line_start = 0;
+
+        let prefix = &source[..offset.min(source.len())];
+
+        for (idx, &b) in prefix.iter().enumerate() {
+            // `\r\n` counts as a single line terminator
+            let is_terminator =
+                b == b'\n' || (b == b'\r' && source.get(idx + 1).copied() != Some(b'\n'));
+
+            if is_terminator {
+                line += 1;
+                line_start = idx + 1;
+            }
+        }
+
+        let column = prefix[line_start..]
+            .iter()
+            // skip utf-8 continuation bytes so multi-byte characters count once
+            .filter(|&&b| b & 0xC0 != 0x80)
+            .count()
+            + 1;
+
+        Self { line, column }
+    }
 }
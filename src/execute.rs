@@ -0,0 +1,1729 @@
+//! Execution of operations against a schema
+//!
+//! Field values are produced by a [`Resolver`], and then completed against the
+//! schema: checked against their declared types, with sub-selections executed
+//! for objects and errors propagated to the nearest nullable field.
+//!
+//! With the `async` feature, [`execute_async`] does the same using an
+//! `AsyncResolver`, whose fields are resolved concurrently.
+//!
+//! The document is expected to have passed validation. Introspection fields
+//! other than `__typename` aren't supported.
+//!
+//! See https://spec.graphql.org/October2021/#sec-Execution
+
+#[cfg(feature = "async")]
+use std::cell::RefCell;
+use std::{collections::HashMap, fmt};
+
+#[cfg(feature = "async")]
+use futures_util::future::{join_all, LocalBoxFuture};
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        Argument, Directive, Document, FieldDefinition, InputObjectField, Operation, OperationKind,
+        Selection, Type, TypeKind, Value, BUILT_IN_SCALARS,
+    },
+    scalar::ScalarRegistry,
+};
+
+/// Produces the values of fields. `Object` is whatever represents an object
+/// for this resolver, such as a database row
+pub trait Resolver {
+    type Object;
+
+    /// The value of `field.name` on `parent`, an object of type
+    /// `field.parent_type`
+    fn resolve_field(
+        &self,
+        parent: &Self::Object,
+        field: &FieldContext<'_>,
+    ) -> Result<ResolvedValue<Self::Object>, FieldError>;
+
+    /// The name of the object type of `object`, which was returned for a
+    /// field of the interface or union `abstract_type`
+    fn resolve_type(&self, object: &Self::Object, abstract_type: &str) -> Option<String>;
+
+    /// Coercions for custom scalars, used for their arguments, variables and
+    /// results
+    fn scalars(&self) -> Option<&ScalarRegistry> {
+        None
+    }
+}
+
+/// A value returned by a resolver, before it is checked against the schema
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedValue<O> {
+    Null,
+    Boolean(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// The name of an enum value
+    Enum(String),
+    List(Vec<Self>),
+    Object(O),
+}
+
+/// An error returned by a resolver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+/// The field being resolved
+#[derive(Debug)]
+pub struct FieldContext<'a> {
+    pub parent_type: &'a str,
+    pub name: &'a str,
+    /// The field's arguments, with variables substituted and defaults applied
+    pub arguments: HashMap<&'a str, Value>,
+    pub path: &'a [PathSegment],
+    pub interner: &'a Rodeo,
+}
+
+impl<'a> FieldContext<'a> {
+    pub fn argument(&self, name: &str) -> Option<&Value> {
+        self.arguments.get(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "{}", key),
+            Self::Index(idx) => write!(f, "{}", idx),
+        }
+    }
+}
+
+/// A value in the response, with object fields in the order they were
+/// selected
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseValue {
+    Null,
+    Boolean(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Self>),
+    Object(Vec<(String, Self)>),
+}
+
+#[cfg(feature = "serde_json")]
+impl ResponseValue {
+    /// The value as JSON. Object fields keep their order only if serde_json's
+    /// `preserve_order` feature is enabled
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value as JsonValue;
+
+        match self {
+            Self::Null => JsonValue::Null,
+            Self::Boolean(b) => JsonValue::Bool(*b),
+            Self::Int(int) => JsonValue::from(*int),
+            Self::Float(float) => JsonValue::from(*float),
+            Self::String(s) => JsonValue::String(s.clone()),
+            Self::List(values) => values.iter().map(Self::to_json).collect(),
+            Self::Object(fields) => JsonValue::Object(
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_json()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionError {
+    pub message: String,
+    /// The response path of the field that failed, which is empty for errors
+    /// that prevented execution from starting
+    pub path: Vec<PathSegment>,
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            let path = self
+                .path
+                .iter()
+                .map(PathSegment::to_string)
+                .collect::<Vec<_>>();
+
+            write!(f, "{}: ", path.join("."))?;
+        }
+
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionResult {
+    /// `None` if execution could not start, and `Some(ResponseValue::Null)`
+    /// if an error in a non-null root field nulled the whole response
+    pub data: Option<ResponseValue>,
+    pub errors: Vec<ExecutionError>,
+}
+
+#[cfg(feature = "serde_json")]
+impl ExecutionResult {
+    /// The result in the shape of a GraphQL response
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut response = serde_json::Map::new();
+
+        if !self.errors.is_empty() {
+            let errors = self
+                .errors
+                .iter()
+                .map(|err| {
+                    let path = err
+                        .path
+                        .iter()
+                        .map(|segment| match segment {
+                            PathSegment::Key(key) => serde_json::Value::from(key.as_str()),
+                            PathSegment::Index(idx) => serde_json::Value::from(*idx),
+                        })
+                        .collect::<Vec<_>>();
+
+                    if path.is_empty() {
+                        serde_json::json!({ "message": err.message })
+                    } else {
+                        serde_json::json!({ "message": err.message, "path": path })
+                    }
+                })
+                .collect();
+
+            response.insert("errors".to_owned(), serde_json::Value::Array(errors));
+        }
+
+        if let Some(data) = &self.data {
+            response.insert("data".to_owned(), data.to_json());
+        }
+
+        serde_json::Value::Object(response)
+    }
+}
+
+/// The operation to execute
+#[derive(Debug, Clone, Copy)]
+pub struct ExecutionRequest<'a> {
+    pub document: &'a Document,
+    /// Which operation in `document` to run, which may be left out if there is
+    /// only one
+    pub operation_name: Option<&'a str>,
+    /// Variable values, already converted from the request's serialization
+    pub variables: &'a HashMap<Spur, Value>,
+}
+
+/// Execute a query or mutation, starting with `root` as the value of the root
+/// type
+///
+/// The schema and request document must have been parsed with `interner`
+pub fn execute<R: Resolver>(
+    schema: &Document,
+    interner: &Rodeo,
+    request: ExecutionRequest<'_>,
+    resolver: &R,
+    root: &R::Object,
+) -> ExecutionResult {
+    let (context, operation, root_type) =
+        match ExecutionContext::new(schema, interner, request, resolver.scalars()) {
+            Ok(start) => start,
+            Err(errors) => return ExecutionResult { data: None, errors },
+        };
+
+    let mut executor = Executor {
+        context,
+        resolver,
+        errors: Vec::new(),
+        path: Vec::new(),
+    };
+
+    // this executor is synchronous, so mutation fields are always executed
+    // serially, as the spec requires
+    let data = executor
+        .execute_selection_set(&[&operation.selection_set], root_type, root)
+        .unwrap_or(ResponseValue::Null);
+
+    ExecutionResult {
+        data: Some(data),
+        errors: executor.errors,
+    }
+}
+
+/// The value was nulled because of an error, which has already been recorded,
+/// and that null must propagate to the parent field
+struct Propagate;
+
+/// Errors in a nullable position become null, and stop propagating
+fn null_if_nullable(
+    ty: &Type,
+    result: Result<ResponseValue, Propagate>,
+) -> Result<ResponseValue, Propagate> {
+    match result {
+        Err(Propagate) if ty.is_nullable() => Ok(ResponseValue::Null),
+        result => result,
+    }
+}
+
+/// How a completed value is produced from a resolved one, which depends on
+/// the kind of the field's type
+enum Completion<'a> {
+    Leaf(ResponseValue),
+    List(&'a Type),
+    /// An object of the given type, whose selection sets still need executing
+    Object(Spur),
+    /// An interface or union
+    Abstract(Spur),
+}
+
+/// The parts of execution that don't depend on how fields are resolved
+struct ExecutionContext<'a> {
+    schema: &'a Document,
+    document: &'a Document,
+    interner: &'a Rodeo,
+    scalars: Option<&'a ScalarRegistry>,
+    /// Coerced variable values, by name
+    variables: HashMap<Spur, Value>,
+}
+
+impl<'a> ExecutionContext<'a> {
+    /// Find the operation to execute and coerce its variables, returning the
+    /// operation's root type
+    fn new(
+        schema: &'a Document,
+        interner: &'a Rodeo,
+        request: ExecutionRequest<'a>,
+        scalars: Option<&'a ScalarRegistry>,
+    ) -> Result<(Self, &'a Operation, Spur), Vec<ExecutionError>> {
+        let request_error = |message: String| ExecutionError {
+            message,
+            path: Vec::new(),
+        };
+
+        let mut context = Self {
+            schema,
+            document: request.document,
+            interner,
+            scalars,
+            variables: HashMap::new(),
+        };
+
+        let operation = context
+            .get_operation(request.operation_name)
+            .map_err(|message| vec![request_error(message)])?;
+
+        if operation.kind == OperationKind::Subscription {
+            return Err(vec![request_error(
+                "subscriptions can't be executed".to_owned(),
+            )]);
+        }
+
+        context.variables = context
+            .coerce_variable_values(operation, request.variables)
+            .map_err(|messages| messages.into_iter().map(request_error).collect::<Vec<_>>())?;
+
+        let root_type = schema
+            .root_operation_type_or_default(operation.kind, interner)
+            .ok_or_else(|| {
+                vec![request_error(format!(
+                    "the schema has no {} type",
+                    operation.kind.as_str()
+                ))]
+            })?;
+
+        Ok((context, operation, root_type))
+    }
+
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    /// See https://spec.graphql.org/October2021/#GetOperation()
+    fn get_operation(&self, operation_name: Option<&str>) -> Result<&'a Operation, String> {
+        let mut operations = self.document.operations.values();
+
+        match operation_name {
+            Some(operation_name) => operations
+                .find(|operation| {
+                    operation.name.map(|name| self.name(name)) == Some(operation_name)
+                })
+                .ok_or_else(|| format!("unknown operation `{}`", operation_name)),
+            None => match (operations.next(), operations.next()) {
+                (Some(operation), None) => Ok(operation),
+                (None, _) => Err("the document contains no operations".to_owned()),
+                (Some(..), Some(..)) => Err(
+                    "an operation name is required when the document contains more than one \
+                     operation"
+                        .to_owned(),
+                ),
+            },
+        }
+    }
+
+    /// See https://spec.graphql.org/October2021/#CoerceVariableValues()
+    fn coerce_variable_values(
+        &self,
+        operation: &Operation,
+        provided: &HashMap<Spur, Value>,
+    ) -> Result<HashMap<Spur, Value>, Vec<String>> {
+        let mut coerced = HashMap::new();
+        let mut errors = Vec::new();
+
+        for definition in &operation.variable_definitions {
+            let name = self.name(definition.name);
+
+            let value = match (provided.get(&definition.name), &definition.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => default,
+                (None, None) if definition.ty.is_nullable() => continue,
+                (None, None) => {
+                    errors.push(format!("variable `${}` is required", name));
+                    continue;
+                }
+            };
+
+            match self.coerce_input_value(value, &definition.ty, None) {
+                Ok(value) => {
+                    coerced.insert(definition.name, value);
+                }
+                Err(message) => errors.push(format!("variable `${}`: {}", name, message)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(coerced)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Check `value` against `ty`, substituting variables (if `variables` is
+    /// given) and applying input object field defaults
+    ///
+    /// See https://spec.graphql.org/October2021/#sec-Input-Values
+    fn coerce_input_value(
+        &self,
+        value: &Value,
+        ty: &Type,
+        variables: Option<&HashMap<Spur, Value>>,
+    ) -> Result<Value, String> {
+        if let (Value::Variable(name), Some(variables)) = (value, variables) {
+            return match variables.get(name) {
+                Some(value) => Ok(value.clone()),
+                None if ty.is_nullable() => Ok(Value::Null),
+                None => Err(format!("variable `${}` was not provided", self.name(*name))),
+            };
+        }
+
+        if let Value::Null = value {
+            return if ty.is_nullable() {
+                Ok(Value::Null)
+            } else {
+                Err("null given for a non-null type".to_owned())
+            };
+        }
+
+        let name = match ty {
+            Type::List { base, .. } => {
+                return match value {
+                    Value::List(values) => values
+                        .iter()
+                        .map(|value| self.coerce_input_value(value, base, variables))
+                        .collect::<Result<_, _>>()
+                        .map(Value::List),
+                    // a single item is coerced to a list of one
+                    value => Ok(Value::List(vec![
+                        self.coerce_input_value(value, base, variables)?
+                    ])),
+                };
+            }
+            Type::Named { name, .. } => *name,
+        };
+
+        let expected = || format!("expected a value of type `{}`", self.name(name));
+
+        match self.schema.type_kind(name) {
+            Some(TypeKind::Enum) => match value {
+                Value::EnumVariant(variant)
+                    if self.schema.enums[&name]
+                        .variants
+                        .iter()
+                        .any(|v| v.name == *variant) =>
+                {
+                    Ok(value.clone())
+                }
+                _ => Err(expected()),
+            },
+            Some(TypeKind::InputObject) => {
+                let fields = match value {
+                    Value::Object(fields) => fields,
+                    _ => return Err(expected()),
+                };
+
+                let definitions = self.schema.input_objects[&name]
+                    .fields
+                    .as_deref()
+                    .unwrap_or_default();
+
+                if let Some(unknown) = fields
+                    .keys()
+                    .find(|field| !definitions.iter().any(|def| def.name == **field))
+                {
+                    return Err(format!(
+                        "`{}` has no field `{}`",
+                        self.name(name),
+                        self.name(*unknown)
+                    ));
+                }
+
+                self.coerce_input_fields(definitions, fields, variables)
+                    .map(Value::Object)
+            }
+            Some(TypeKind::Scalar) | None => {
+                let is_valid = match self.name(name) {
+                    "Int" => matches!(value, Value::Int(int) if i32::try_from(*int).is_ok()),
+                    "Float" => matches!(value, Value::Int(..) | Value::Float(..)),
+                    "String" => matches!(value, Value::String(..)),
+                    "Boolean" => matches!(value, Value::True | Value::False),
+                    "ID" => matches!(value, Value::String(..) | Value::Int(..)),
+                    custom => {
+                        // custom scalars may accept any value, unless a
+                        // coercion is registered for them
+                        if let Some(parsed) = self
+                            .scalars
+                            .and_then(|scalars| scalars.parse(custom, value, self.interner))
+                        {
+                            return parsed;
+                        }
+
+                        true
+                    }
+                };
+
+                match value {
+                    Value::Int(int) if is_valid && self.name(name) == "Float" => {
+                        Ok(Value::Float(*int as f64))
+                    }
+                    _ if is_valid => Ok(value.clone()),
+                    _ => Err(expected()),
+                }
+            }
+            Some(..) => Err(format!("`{}` is not an input type", self.name(name))),
+        }
+    }
+
+    /// Coerce the arguments of a field or the fields of an input object
+    ///
+    /// See https://spec.graphql.org/October2021/#CoerceArgumentValues()
+    fn coerce_input_fields(
+        &self,
+        definitions: &[InputObjectField],
+        values: &HashMap<Spur, Value>,
+        variables: Option<&HashMap<Spur, Value>>,
+    ) -> Result<HashMap<Spur, Value>, String> {
+        let mut coerced = HashMap::new();
+
+        for definition in definitions {
+            let name = self.name(definition.name);
+
+            // a variable that wasn't provided is treated as if the argument
+            // was left out
+            let value = match values.get(&definition.name) {
+                Some(Value::Variable(variable))
+                    if variables.is_some_and(|variables| !variables.contains_key(variable)) =>
+                {
+                    None
+                }
+                value => value,
+            };
+
+            let value = match (value, &definition.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => default,
+                (None, None) if definition.ty.is_nullable() => continue,
+                (None, None) => return Err(format!("`{}` is required", name)),
+            };
+
+            let value = self
+                .coerce_input_value(value, &definition.ty, variables)
+                .map_err(|message| format!("`{}`: {}", name, message))?;
+
+            coerced.insert(definition.name, value);
+        }
+
+        Ok(coerced)
+    }
+
+    /// Group the fields of `selection_set` by response key, in the order they
+    /// were first selected
+    ///
+    /// See https://spec.graphql.org/October2021/#CollectFields()
+    fn collect_fields(
+        &self,
+        object_type: Spur,
+        selection_set: &'a [Selection],
+        fields: &mut Vec<(Spur, Vec<&'a Selection>)>,
+    ) {
+        self.collect_fields_inner(object_type, selection_set, fields, &mut Vec::new());
+    }
+
+    fn collect_fields_inner(
+        &self,
+        object_type: Spur,
+        selection_set: &'a [Selection],
+        fields: &mut Vec<(Spur, Vec<&'a Selection>)>,
+        visited_fragments: &mut Vec<Spur>,
+    ) {
+        for selection in selection_set {
+            let directives = match selection {
+                Selection::Field { directives, .. }
+                | Selection::FragmentSpread { directives, .. }
+                | Selection::InlineFragment { directives, .. } => directives,
+            };
+
+            if !self.should_include(directives) {
+                continue;
+            }
+
+            match selection {
+                Selection::Field { alias, name, .. } => {
+                    let key = alias.unwrap_or(*name);
+
+                    match fields.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, selections)) => selections.push(selection),
+                        None => fields.push((key, vec![selection])),
+                    }
+                }
+                Selection::FragmentSpread { name, .. } => {
+                    if visited_fragments.contains(name) {
+                        continue;
+                    }
+
+                    visited_fragments.push(*name);
+
+                    let fragment = match self.document.fragments.get(name) {
+                        Some(fragment) => fragment,
+                        None => continue,
+                    };
+
+                    if self.does_fragment_type_apply(object_type, fragment.on) {
+                        self.collect_fields_inner(
+                            object_type,
+                            &fragment.selection_set,
+                            fields,
+                            visited_fragments,
+                        );
+                    }
+                }
+                Selection::InlineFragment {
+                    on, selection_set, ..
+                } => {
+                    if on.is_none_or(|on| self.does_fragment_type_apply(object_type, on)) {
+                        self.collect_fields_inner(
+                            object_type,
+                            selection_set,
+                            fields,
+                            visited_fragments,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Evaluate `@skip` and `@include`
+    fn should_include(&self, directives: &[Directive]) -> bool {
+        directives.iter().all(|directive| {
+            let condition = directive
+                .arguments
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .find(|argument| self.name(argument.name) == "if")
+                .map(|argument| match &argument.value {
+                    Value::Variable(name) => self.variables.get(name),
+                    value => Some(value),
+                });
+
+            let is_true = matches!(condition, Some(Some(Value::True)));
+
+            match self.name(directive.name) {
+                "skip" => !is_true,
+                "include" => is_true,
+                _ => true,
+            }
+        })
+    }
+
+    fn does_fragment_type_apply(&self, object_type: Spur, type_condition: Spur) -> bool {
+        self.schema
+            .possible_types(type_condition)
+            .contains(&object_type)
+    }
+
+    fn field_definition(&self, object_type: Spur, name: Spur) -> Option<&'a FieldDefinition> {
+        self.schema
+            .fields_of(object_type)?
+            .iter()
+            .find(|field| field.name == name)
+    }
+
+    fn coerce_argument_values(
+        &self,
+        definition: &FieldDefinition,
+        arguments: &[Argument],
+    ) -> Result<HashMap<&'a str, Value>, String> {
+        let values = arguments
+            .iter()
+            .map(|argument| (argument.name, argument.value.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let coerced = self.coerce_input_fields(
+            definition.arguments.as_deref().unwrap_or_default(),
+            &values,
+            Some(&self.variables),
+        )?;
+
+        Ok(coerced
+            .into_iter()
+            .map(|(name, value)| (self.name(name), value))
+            .collect())
+    }
+
+    /// The name and arguments of a collected field
+    fn field_selection(&self, selection: &'a Selection) -> (Spur, &'a [Argument]) {
+        match selection {
+            Selection::Field {
+                name, arguments, ..
+            } => (*name, arguments.as_deref().unwrap_or_default()),
+            _ => unreachable!("only fields are collected"),
+        }
+    }
+
+    /// The selection sets of every selection of a field, merged when
+    /// completing an object value
+    fn sub_selection_sets(&self, selections: &[&'a Selection]) -> Vec<&'a [Selection]> {
+        selections
+            .iter()
+            .filter_map(|selection| match selection {
+                Selection::Field {
+                    selection_set: Some(selection_set),
+                    ..
+                } => Some(selection_set.as_slice()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The first step of completing a non-null `value` of type `ty`, which
+    /// finishes the completion of scalars and enums
+    ///
+    /// See https://spec.graphql.org/October2021/#CompleteValue()
+    fn begin_completion<'b, O>(
+        &self,
+        ty: &'b Type,
+        value: &ResolvedValue<O>,
+    ) -> Result<Completion<'b>, String> {
+        let name = match ty {
+            Type::List { base, .. } => {
+                return match value {
+                    ResolvedValue::List(..) => Ok(Completion::List(base)),
+                    _ => Err("expected a list to be returned".to_owned()),
+                };
+            }
+            Type::Named { name, .. } => *name,
+        };
+
+        let kind = self.schema.type_kind(name).or_else(|| {
+            BUILT_IN_SCALARS
+                .contains(&self.name(name))
+                .then_some(TypeKind::Scalar)
+        });
+
+        match (kind, value) {
+            (Some(TypeKind::Scalar), value) => {
+                self.serialize_scalar(name, value).map(Completion::Leaf)
+            }
+            (
+                Some(TypeKind::Enum),
+                ResolvedValue::Enum(variant) | ResolvedValue::String(variant),
+            ) => self.schema.enums[&name]
+                .variants
+                .iter()
+                .find(|v| self.name(v.name) == variant)
+                .map(|v| Completion::Leaf(ResponseValue::String(self.name(v.name).to_owned())))
+                .ok_or_else(|| format!("expected a value of the enum `{}`", self.name(name))),
+            (Some(TypeKind::Enum), _) => Err(format!(
+                "expected a value of the enum `{}`",
+                self.name(name)
+            )),
+            (Some(TypeKind::Object), ResolvedValue::Object(..)) => Ok(Completion::Object(name)),
+            (Some(TypeKind::Interface) | Some(TypeKind::Union), ResolvedValue::Object(..)) => {
+                Ok(Completion::Abstract(name))
+            }
+            (Some(TypeKind::Object) | Some(TypeKind::Interface) | Some(TypeKind::Union), _) => {
+                Err(format!("expected an object of type `{}`", self.name(name)))
+            }
+            (Some(TypeKind::InputObject) | None, _) => {
+                Err(format!("`{}` is not an output type", self.name(name)))
+            }
+        }
+    }
+
+    /// Check the type name given by a resolver for a value of an interface or
+    /// union
+    ///
+    /// See https://spec.graphql.org/October2021/#ResolveAbstractType()
+    fn check_resolved_type(
+        &self,
+        abstract_type: Spur,
+        object_type: Option<String>,
+    ) -> Result<Spur, String> {
+        object_type
+            .and_then(|name| self.interner.get(name))
+            .filter(|name| self.schema.possible_types(abstract_type).contains(name))
+            .ok_or_else(|| {
+                format!(
+                    "could not resolve a possible type of `{}`",
+                    self.name(abstract_type)
+                )
+            })
+    }
+
+    /// See https://spec.graphql.org/October2021/#sec-Scalars.Result-Coercion-and-Serialization
+    fn serialize_scalar<O>(
+        &self,
+        name: Spur,
+        value: &ResolvedValue<O>,
+    ) -> Result<ResponseValue, String> {
+        let serialized = match (self.name(name), value) {
+            ("Int", ResolvedValue::Int(int)) if i32::try_from(*int).is_ok() => {
+                Some(ResponseValue::Int(*int))
+            }
+            ("Float", ResolvedValue::Int(int)) => Some(ResponseValue::Float(*int as f64)),
+            ("Float", ResolvedValue::Float(float)) if float.is_finite() => {
+                Some(ResponseValue::Float(*float))
+            }
+            ("String", ResolvedValue::String(s)) => Some(ResponseValue::String(s.clone())),
+            ("Boolean", ResolvedValue::Boolean(b)) => Some(ResponseValue::Boolean(*b)),
+            ("ID", ResolvedValue::String(s)) => Some(ResponseValue::String(s.clone())),
+            ("ID", ResolvedValue::Int(int)) => Some(ResponseValue::String(int.to_string())),
+            ("Int" | "Float" | "String" | "Boolean" | "ID", _) => None,
+            // custom scalars are serialized as given, unless a coercion is
+            // registered for them
+            (custom, value) => {
+                let value = leaf_value(value);
+
+                match (self.scalars, value) {
+                    (Some(scalars), Some(value)) => match scalars.serialize(custom, &value) {
+                        Some(serialized) => return serialized,
+                        None => Some(value),
+                    },
+                    (_, value) => value,
+                }
+            }
+        };
+
+        serialized
+            .ok_or_else(|| format!("could not serialize a value of type `{}`", self.name(name)))
+    }
+}
+
+struct Executor<'a, R: Resolver> {
+    context: ExecutionContext<'a>,
+    resolver: &'a R,
+    errors: Vec<ExecutionError>,
+    path: Vec<PathSegment>,
+}
+
+impl<'a, R: Resolver> Executor<'a, R> {
+    fn error(&mut self, message: String) {
+        self.errors.push(ExecutionError {
+            message,
+            path: self.path.clone(),
+        });
+    }
+
+    /// Execute the selection sets of every selection of a single response
+    /// key, which are merged into one object
+    ///
+    /// See https://spec.graphql.org/October2021/#ExecuteSelectionSet()
+    fn execute_selection_set(
+        &mut self,
+        selection_sets: &[&'a [Selection]],
+        object_type: Spur,
+        object: &R::Object,
+    ) -> Result<ResponseValue, Propagate> {
+        let mut fields = Vec::new();
+
+        for selection_set in selection_sets {
+            self.context
+                .collect_fields(object_type, selection_set, &mut fields);
+        }
+
+        let mut response = Vec::with_capacity(fields.len());
+        let mut is_null = false;
+
+        for (key, selections) in fields {
+            let key = self.context.name(key);
+
+            self.path.push(PathSegment::Key(key.to_owned()));
+            let value = self.execute_field(object_type, object, &selections);
+            self.path.pop();
+
+            match value {
+                Ok(value) => response.push((key.to_owned(), value)),
+                // keep executing the remaining fields so that their errors
+                // are reported too
+                Err(Propagate) => is_null = true,
+            }
+        }
+
+        if is_null {
+            return Err(Propagate);
+        }
+
+        Ok(ResponseValue::Object(response))
+    }
+
+    /// See https://spec.graphql.org/October2021/#ExecuteField()
+    fn execute_field(
+        &mut self,
+        object_type: Spur,
+        object: &R::Object,
+        selections: &[&'a Selection],
+    ) -> Result<ResponseValue, Propagate> {
+        let (name, arguments) = self.context.field_selection(selections[0]);
+
+        if self.context.name(name) == "__typename" {
+            return Ok(ResponseValue::String(
+                self.context.name(object_type).to_owned(),
+            ));
+        }
+
+        let definition = match self.context.field_definition(object_type, name) {
+            Some(definition) => definition,
+            None => {
+                self.error(format!(
+                    "`{}` has no field `{}`",
+                    self.context.name(object_type),
+                    self.context.name(name)
+                ));
+                return Ok(ResponseValue::Null);
+            }
+        };
+
+        let value = self
+            .context
+            .coerce_argument_values(definition, arguments)
+            .and_then(|arguments| {
+                let field = FieldContext {
+                    parent_type: self.context.name(object_type),
+                    name: self.context.name(name),
+                    arguments,
+                    path: &self.path,
+                    interner: self.context.interner,
+                };
+
+                self.resolver
+                    .resolve_field(object, &field)
+                    .map_err(|err| err.message)
+            });
+
+        let result = match value {
+            Ok(value) => self.complete_value(&definition.ty, selections, value),
+            Err(message) => {
+                self.error(message);
+                Err(Propagate)
+            }
+        };
+
+        null_if_nullable(&definition.ty, result)
+    }
+
+    /// See https://spec.graphql.org/October2021/#CompleteValue()
+    fn complete_value(
+        &mut self,
+        ty: &Type,
+        selections: &[&'a Selection],
+        value: ResolvedValue<R::Object>,
+    ) -> Result<ResponseValue, Propagate> {
+        if let ResolvedValue::Null = value {
+            if ty.is_nullable() {
+                return Ok(ResponseValue::Null);
+            }
+
+            self.error("null returned for a non-null field".to_owned());
+            return Err(Propagate);
+        }
+
+        let completion = match self.context.begin_completion(ty, &value) {
+            Ok(completion) => completion,
+            Err(message) => {
+                self.error(message);
+                return Err(Propagate);
+            }
+        };
+
+        let (object_type, object) = match (completion, value) {
+            (Completion::Leaf(value), _) => return Ok(value),
+            (Completion::List(item_ty), ResolvedValue::List(values)) => {
+                let mut completed = Vec::with_capacity(values.len());
+                let mut is_null = false;
+
+                for (idx, value) in values.into_iter().enumerate() {
+                    self.path.push(PathSegment::Index(idx));
+                    let result = self.complete_value(item_ty, selections, value);
+                    self.path.pop();
+
+                    match null_if_nullable(item_ty, result) {
+                        Ok(value) => completed.push(value),
+                        Err(Propagate) => is_null = true,
+                    }
+                }
+
+                if is_null {
+                    return Err(Propagate);
+                }
+
+                return Ok(ResponseValue::List(completed));
+            }
+            (Completion::Object(object_type), ResolvedValue::Object(object)) => {
+                (object_type, object)
+            }
+            (Completion::Abstract(abstract_type), ResolvedValue::Object(object)) => {
+                let object_type = self
+                    .resolver
+                    .resolve_type(&object, self.context.name(abstract_type));
+
+                match self.context.check_resolved_type(abstract_type, object_type) {
+                    Ok(object_type) => (object_type, object),
+                    Err(message) => {
+                        self.error(message);
+                        return Err(Propagate);
+                    }
+                }
+            }
+            _ => unreachable!("the completion matches the shape of the value"),
+        };
+
+        let selection_sets = self.context.sub_selection_sets(selections);
+
+        self.execute_selection_set(&selection_sets, object_type, &object)
+    }
+}
+
+/// A future produced by an [`AsyncResolver`]
+#[cfg(feature = "async")]
+pub type ResolverFuture<'a, O> = LocalBoxFuture<'a, Result<ResolvedValue<O>, FieldError>>;
+
+/// Like [`Resolver`], but producing field values asynchronously
+#[cfg(feature = "async")]
+pub trait AsyncResolver {
+    type Object;
+
+    /// The value of `field.name` on `parent`, an object of type
+    /// `field.parent_type`
+    fn resolve_field<'a>(
+        &'a self,
+        parent: &'a Self::Object,
+        field: &'a FieldContext<'a>,
+    ) -> ResolverFuture<'a, Self::Object>;
+
+    /// The name of the object type of `object`, which was returned for a
+    /// field of the interface or union `abstract_type`
+    fn resolve_type(&self, object: &Self::Object, abstract_type: &str) -> Option<String>;
+
+    /// Coercions for custom scalars, used for their arguments, variables and
+    /// results
+    fn scalars(&self) -> Option<&ScalarRegistry> {
+        None
+    }
+}
+
+/// Like [`execute`], but resolving the fields of a selection set, and the
+/// items of a list, concurrently. The root fields of a mutation are resolved
+/// one at a time, in order
+///
+/// Since the interner isn't `Sync`, the returned future isn't `Send`, but it
+/// doesn't depend on any particular runtime
+#[cfg(feature = "async")]
+pub async fn execute_async<R: AsyncResolver>(
+    schema: &Document,
+    interner: &Rodeo,
+    request: ExecutionRequest<'_>,
+    resolver: &R,
+    root: &R::Object,
+) -> ExecutionResult {
+    let (context, operation, root_type) =
+        match ExecutionContext::new(schema, interner, request, resolver.scalars()) {
+            Ok(start) => start,
+            Err(errors) => return ExecutionResult { data: None, errors },
+        };
+
+    let executor = AsyncExecutor {
+        context,
+        resolver,
+        errors: RefCell::new(Vec::new()),
+    };
+
+    let data = executor
+        .execute_selection_set(
+            vec![&operation.selection_set],
+            root_type,
+            root,
+            Vec::new(),
+            operation.kind == OperationKind::Mutation,
+        )
+        .await
+        .unwrap_or(ResponseValue::Null);
+
+    ExecutionResult {
+        data: Some(data),
+        errors: executor.errors.into_inner(),
+    }
+}
+
+#[cfg(feature = "async")]
+struct AsyncExecutor<'a, R: AsyncResolver> {
+    context: ExecutionContext<'a>,
+    resolver: &'a R,
+    /// Errors from every field, in the order they occurred
+    errors: RefCell<Vec<ExecutionError>>,
+}
+
+#[cfg(feature = "async")]
+impl<'a, R: AsyncResolver> AsyncExecutor<'a, R> {
+    fn error(&self, path: Vec<PathSegment>, message: String) {
+        self.errors
+            .borrow_mut()
+            .push(ExecutionError { message, path });
+    }
+
+    /// See [`Executor::execute_selection_set`]. If `is_serial` is set, each
+    /// field is resolved only once the previous one has completed
+    fn execute_selection_set<'b>(
+        &'b self,
+        selection_sets: Vec<&'a [Selection]>,
+        object_type: Spur,
+        object: &'b R::Object,
+        path: Vec<PathSegment>,
+        is_serial: bool,
+    ) -> LocalBoxFuture<'b, Result<ResponseValue, Propagate>> {
+        Box::pin(async move {
+            let mut fields = Vec::new();
+
+            for selection_set in selection_sets {
+                self.context
+                    .collect_fields(object_type, selection_set, &mut fields);
+            }
+
+            let futures = fields.into_iter().map(|(key, selections)| {
+                let key = self.context.name(key);
+
+                let mut path = path.clone();
+                path.push(PathSegment::Key(key.to_owned()));
+
+                async move {
+                    let value = self
+                        .execute_field(object_type, object, selections, path)
+                        .await;
+
+                    (key, value)
+                }
+            });
+
+            let results = if is_serial {
+                let mut results = Vec::new();
+
+                for future in futures {
+                    results.push(future.await);
+                }
+
+                results
+            } else {
+                join_all(futures).await
+            };
+
+            let mut response = Vec::with_capacity(results.len());
+            let mut is_null = false;
+
+            for (key, value) in results {
+                match value {
+                    Ok(value) => response.push((key.to_owned(), value)),
+                    Err(Propagate) => is_null = true,
+                }
+            }
+
+            if is_null {
+                return Err(Propagate);
+            }
+
+            Ok(ResponseValue::Object(response))
+        })
+    }
+
+    /// See [`Executor::execute_field`]
+    async fn execute_field(
+        &self,
+        object_type: Spur,
+        object: &R::Object,
+        selections: Vec<&'a Selection>,
+        path: Vec<PathSegment>,
+    ) -> Result<ResponseValue, Propagate> {
+        let (name, arguments) = self.context.field_selection(selections[0]);
+
+        if self.context.name(name) == "__typename" {
+            return Ok(ResponseValue::String(
+                self.context.name(object_type).to_owned(),
+            ));
+        }
+
+        let definition = match self.context.field_definition(object_type, name) {
+            Some(definition) => definition,
+            None => {
+                self.error(
+                    path,
+                    format!(
+                        "`{}` has no field `{}`",
+                        self.context.name(object_type),
+                        self.context.name(name)
+                    ),
+                );
+                return Ok(ResponseValue::Null);
+            }
+        };
+
+        let arguments = match self.context.coerce_argument_values(definition, arguments) {
+            Ok(arguments) => arguments,
+            Err(message) => {
+                self.error(path, message);
+                return null_if_nullable(&definition.ty, Err(Propagate));
+            }
+        };
+
+        let field = FieldContext {
+            parent_type: self.context.name(object_type),
+            name: self.context.name(name),
+            arguments,
+            path: &path,
+            interner: self.context.interner,
+        };
+
+        let value = self.resolver.resolve_field(object, &field).await;
+
+        let result = match value {
+            Ok(value) => {
+                self.complete_value(&definition.ty, selections, value, path.clone())
+                    .await
+            }
+            Err(err) => {
+                self.error(path.clone(), err.message);
+                Err(Propagate)
+            }
+        };
+
+        null_if_nullable(&definition.ty, result)
+    }
+
+    /// See [`Executor::complete_value`]
+    fn complete_value<'b>(
+        &'b self,
+        ty: &'b Type,
+        selections: Vec<&'a Selection>,
+        value: ResolvedValue<R::Object>,
+        path: Vec<PathSegment>,
+    ) -> LocalBoxFuture<'b, Result<ResponseValue, Propagate>>
+    where
+        R::Object: 'b,
+    {
+        Box::pin(async move {
+            if let ResolvedValue::Null = value {
+                if ty.is_nullable() {
+                    return Ok(ResponseValue::Null);
+                }
+
+                self.error(path, "null returned for a non-null field".to_owned());
+                return Err(Propagate);
+            }
+
+            let completion = match self.context.begin_completion(ty, &value) {
+                Ok(completion) => completion,
+                Err(message) => {
+                    self.error(path, message);
+                    return Err(Propagate);
+                }
+            };
+
+            let (object_type, object) = match (completion, value) {
+                (Completion::Leaf(value), _) => return Ok(value),
+                (Completion::List(item_ty), ResolvedValue::List(values)) => {
+                    let futures = values.into_iter().enumerate().map(|(idx, value)| {
+                        let mut path = path.clone();
+                        path.push(PathSegment::Index(idx));
+
+                        let result = self.complete_value(item_ty, selections.clone(), value, path);
+
+                        async move { null_if_nullable(item_ty, result.await) }
+                    });
+
+                    let completed = join_all(futures)
+                        .await
+                        .into_iter()
+                        .collect::<Result<_, _>>()?;
+
+                    return Ok(ResponseValue::List(completed));
+                }
+                (Completion::Object(object_type), ResolvedValue::Object(object)) => {
+                    (object_type, object)
+                }
+                (Completion::Abstract(abstract_type), ResolvedValue::Object(object)) => {
+                    let object_type = self
+                        .resolver
+                        .resolve_type(&object, self.context.name(abstract_type));
+
+                    match self.context.check_resolved_type(abstract_type, object_type) {
+                        Ok(object_type) => (object_type, object),
+                        Err(message) => {
+                            self.error(path, message);
+                            return Err(Propagate);
+                        }
+                    }
+                }
+                _ => unreachable!("the completion matches the shape of the value"),
+            };
+
+            let selection_sets = self.context.sub_selection_sets(&selections);
+
+            self.execute_selection_set(selection_sets, object_type, &object, path, false)
+                .await
+        })
+    }
+}
+
+/// A resolved value without any objects, as a response value
+fn leaf_value<O>(value: &ResolvedValue<O>) -> Option<ResponseValue> {
+    Some(match value {
+        ResolvedValue::Null => ResponseValue::Null,
+        ResolvedValue::Boolean(b) => ResponseValue::Boolean(*b),
+        ResolvedValue::Int(int) => ResponseValue::Int(*int),
+        ResolvedValue::Float(float) => ResponseValue::Float(*float),
+        ResolvedValue::String(s) | ResolvedValue::Enum(s) => ResponseValue::String(s.clone()),
+        ResolvedValue::List(values) => {
+            ResponseValue::List(values.iter().map(leaf_value).collect::<Option<_>>()?)
+        }
+        ResolvedValue::Object(..) => return None,
+    })
+}
+
+#[cfg(all(test, feature = "serde_json"))]
+mod tests {
+    use std::collections::HashMap;
+
+    use lasso::{Rodeo, Spur};
+    use serde_json::{json, Value as JsonValue};
+
+    use super::{
+        execute, ExecutionRequest, ExecutionResult, FieldContext, FieldError, ResolvedValue,
+        Resolver, ResponseValue,
+    };
+    use crate::{ast::Value, GraphqlParser};
+
+    const SCHEMA: &str = "
+type Query {
+  user(id: Int!): User
+  users: [User!]!
+  node(id: Int!): Node
+  broken: String
+  brokenRequired: String!
+  role: Role
+}
+type Mutation { rename(name: String!): String }
+interface Node { id: Int! }
+type User implements Node { id: Int! name: String! friend: User best: User! }
+type Post implements Node { id: Int! }
+enum Role { ADMIN USER }
+";
+
+    #[derive(Debug, Clone)]
+    enum Object {
+        Root,
+        User(i64),
+        Post(i64),
+    }
+
+    /// Users 1 to 3 exist, each befriending the next. User 3 has no friend,
+    /// and resolving `best` on them fails
+    struct TestResolver;
+
+    impl Resolver for TestResolver {
+        type Object = Object;
+
+        fn resolve_field(
+            &self,
+            parent: &Object,
+            field: &FieldContext<'_>,
+        ) -> Result<ResolvedValue<Object>, FieldError> {
+            let id = |field: &FieldContext| match field.argument("id") {
+                Some(Value::Int(id)) => *id,
+                other => panic!("unexpected id {:?}", other),
+            };
+
+            Ok(match (parent, field.name) {
+                (Object::Root, "user") if (1..=3).contains(&id(field)) => {
+                    ResolvedValue::Object(Object::User(id(field)))
+                }
+                (Object::Root, "user") => ResolvedValue::Null,
+                (Object::Root, "users") => ResolvedValue::List(
+                    (1..=3)
+                        .map(|id| ResolvedValue::Object(Object::User(id)))
+                        .collect(),
+                ),
+                (Object::Root, "node") if id(field) > 100 => {
+                    ResolvedValue::Object(Object::Post(id(field)))
+                }
+                (Object::Root, "node") => ResolvedValue::Object(Object::User(id(field))),
+                (Object::Root, "broken" | "brokenRequired") => {
+                    return Err(FieldError::new("broken"))
+                }
+                (Object::Root, "role") => ResolvedValue::Enum("ADMIN".to_owned()),
+                (Object::Root, "rename") => match field.argument("name") {
+                    Some(Value::String(name)) => {
+                        ResolvedValue::String(field.interner.resolve(name).to_owned())
+                    }
+                    other => panic!("unexpected name {:?}", other),
+                },
+                (Object::User(id) | Object::Post(id), "id") => ResolvedValue::Int(*id),
+                (Object::User(id), "name") => ResolvedValue::String(format!("user {}", id)),
+                (Object::User(3), "friend") => ResolvedValue::Null,
+                (Object::User(3), "best") => return Err(FieldError::new("no best friend")),
+                (Object::User(id), "friend" | "best") => {
+                    ResolvedValue::Object(Object::User(id + 1))
+                }
+                (parent, name) => panic!("unexpected field {:?}.{}", parent, name),
+            })
+        }
+
+        fn resolve_type(&self, object: &Object, _abstract_type: &str) -> Option<String> {
+            match object {
+                Object::Root => None,
+                Object::User(..) => Some("User".to_owned()),
+                Object::Post(..) => Some("Post".to_owned()),
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl super::AsyncResolver for TestResolver {
+        type Object = Object;
+
+        fn resolve_field<'a>(
+            &'a self,
+            parent: &'a Object,
+            field: &'a FieldContext<'a>,
+        ) -> super::ResolverFuture<'a, Object> {
+            let value = Resolver::resolve_field(self, parent, field);
+
+            Box::pin(async move { value })
+        }
+
+        fn resolve_type(&self, object: &Object, abstract_type: &str) -> Option<String> {
+            Resolver::resolve_type(self, object, abstract_type)
+        }
+    }
+
+    fn run(source: &str, operation_name: Option<&str>, variables: &[(&str, Value)]) -> JsonValue {
+        run_result(source, operation_name, variables).to_json()
+    }
+
+    fn run_result(
+        source: &str,
+        operation_name: Option<&str>,
+        variables: &[(&str, Value)],
+    ) -> ExecutionResult {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse operation");
+
+        let variables = variables
+            .iter()
+            .map(|(name, value)| (interner.get_or_intern(name), value.clone()))
+            .collect::<HashMap<Spur, Value>>();
+
+        let request = ExecutionRequest {
+            document: &document,
+            operation_name,
+            variables: &variables,
+        };
+
+        execute(&schema, &interner, request, &TestResolver, &Object::Root)
+    }
+
+    #[test]
+    fn nested_fields() {
+        let response = run(
+            concat!(
+                "{ a: user(id: 1) { id name friend { name friend { name friend { name } } } } ",
+                "users { __typename id } role }",
+            ),
+            None,
+            &[],
+        );
+
+        assert_eq!(
+            response,
+            json!({
+                "data": {
+                    "a": {
+                        "id": 1,
+                        "name": "user 1",
+                        "friend": {
+                            "name": "user 2",
+                            "friend": { "name": "user 3", "friend": null },
+                        },
+                    },
+                    "users": [
+                        { "__typename": "User", "id": 1 },
+                        { "__typename": "User", "id": 2 },
+                        { "__typename": "User", "id": 3 },
+                    ],
+                    "role": "ADMIN",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn fields_keep_selection_order() {
+        let result = run_result(
+            "{ user(id: 1) { name id ... on User { name b: id } } }",
+            None,
+            &[],
+        );
+
+        let user = ResponseValue::Object(vec![
+            (
+                "name".to_owned(),
+                ResponseValue::String("user 1".to_owned()),
+            ),
+            ("id".to_owned(), ResponseValue::Int(1)),
+            ("b".to_owned(), ResponseValue::Int(1)),
+        ]);
+
+        assert_eq!(
+            result.data,
+            Some(ResponseValue::Object(vec![("user".to_owned(), user)]))
+        );
+        assert_eq!(result.errors, []);
+    }
+
+    #[test]
+    fn abstract_types() {
+        let response = run(
+            concat!(
+                "{ user: node(id: 1) { __typename id ... on User { name } } ",
+                "post: node(id: 101) { __typename id ...U } } ",
+                "fragment U on User { name }",
+            ),
+            None,
+            &[],
+        );
+
+        assert_eq!(
+            response,
+            json!({
+                "data": {
+                    "user": { "__typename": "User", "id": 1, "name": "user 1" },
+                    "post": { "__typename": "Post", "id": 101 },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn skip_and_include() {
+        let response = run(
+            concat!(
+                "query($yes: Boolean!) { user(id: 1) { ",
+                "a: id @skip(if: $yes) b: id @include(if: $yes) c: id @skip(if: false) ",
+                "... on User @include(if: false) { d: id } } }",
+            ),
+            None,
+            &[("yes", Value::True)],
+        );
+
+        assert_eq!(response, json!({ "data": { "user": { "b": 1, "c": 1 } } }));
+    }
+
+    #[test]
+    fn inline_fragments_without_type_condition() {
+        let response = run(
+            "{ user(id: 1) { ... { id } ... @include(if: false) { name } } }",
+            None,
+            &[],
+        );
+
+        assert_eq!(response, json!({ "data": { "user": { "id": 1 } } }));
+    }
+
+    #[test]
+    fn variables() {
+        let query = "query($id: Int!) { user(id: $id) { name } }";
+
+        assert_eq!(
+            run(query, None, &[("id", Value::Int(2))]),
+            json!({ "data": { "user": { "name": "user 2" } } })
+        );
+
+        assert_eq!(
+            run(query, None, &[]),
+            json!({ "errors": [{ "message": "variable `$id` is required" }] })
+        );
+
+        assert_eq!(
+            run("query($id: Int = 3) { user(id: $id) { name } }", None, &[]),
+            json!({ "data": { "user": { "name": "user 3" } } })
+        );
+    }
+
+    #[test]
+    fn errors_in_nullable_fields() {
+        assert_eq!(
+            run("{ broken user(id: 1) { id } }", None, &[]),
+            json!({
+                "data": { "broken": null, "user": { "id": 1 } },
+                "errors": [{ "message": "broken", "path": ["broken"] }],
+            })
+        );
+    }
+
+    #[test]
+    fn errors_propagate_to_nullable_parent() {
+        assert_eq!(
+            run("{ user(id: 2) { friend { best { id } } } }", None, &[]),
+            json!({
+                "data": { "user": { "friend": null } },
+                "errors": [{ "message": "no best friend", "path": ["user", "friend", "best"] }],
+            })
+        );
+
+        // each of `best`, the list items and `users` are non-null, so the
+        // error nulls the whole response
+        assert_eq!(
+            run("{ users { best { id } } }", None, &[]),
+            json!({
+                "data": null,
+                "errors": [{ "message": "no best friend", "path": ["users", 2, "best"] }],
+            })
+        );
+
+        assert_eq!(
+            run("{ brokenRequired }", None, &[]),
+            json!({
+                "data": null,
+                "errors": [{ "message": "broken", "path": ["brokenRequired"] }],
+            })
+        );
+    }
+
+    #[test]
+    fn operation_selection() {
+        let source = "query A { user(id: 1) { id } } query B { user(id: 2) { id } }";
+
+        assert_eq!(
+            run(source, Some("B"), &[]),
+            json!({ "data": { "user": { "id": 2 } } })
+        );
+
+        assert_eq!(
+            run(source, Some("C"), &[]),
+            json!({ "errors": [{ "message": "unknown operation `C`" }] })
+        );
+
+        let result = run_result(source, None, &[]);
+        assert_eq!(result.data, None);
+        assert_eq!(result.errors.len(), 1);
+
+        let result = run_result("subscription { users { id } }", None, &[]);
+        assert_eq!(result.data, None);
+        assert_eq!(result.errors[0].message, "subscriptions can't be executed");
+    }
+
+    #[test]
+    fn mutations() {
+        assert_eq!(
+            run(
+                "mutation { a: rename(name: \"x\") b: rename(name: \"y\") }",
+                None,
+                &[]
+            ),
+            json!({ "data": { "a": "x", "b": "y" } })
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_execution_matches() {
+        use futures_util::FutureExt;
+
+        use super::execute_async;
+
+        let sources = [
+            "{ a: user(id: 1) { id name friend { name friend { name friend { name } } } } }",
+            "{ node(id: 1) { __typename ... on User { name } } users { id } }",
+            "{ broken user(id: 2) { friend { best { id } } } }",
+            "{ users { best { id } } }",
+            "mutation { a: rename(name: \"x\") b: rename(name: \"y\") }",
+            "query A { role } query B { role }",
+        ];
+
+        for source in sources {
+            let mut interner = Rodeo::default();
+            let schema =
+                GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+            let document =
+                GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner).unwrap();
+
+            let variables = HashMap::new();
+            let request = ExecutionRequest {
+                document: &document,
+                operation_name: None,
+                variables: &variables,
+            };
+
+            let result = execute_async(&schema, &interner, request, &TestResolver, &Object::Root)
+                .now_or_never()
+                .expect("resolvers never wait");
+
+            assert_eq!(result, run_result(source, None, &[]), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! Refactorings that factor parts of operations out: selection sets repeated
+//! across a document become shared fragments, and literal arguments become
+//! variables
+//!
+//! Both resolve the types of selections through the schema, so fields it
+//! doesn't define are left as they are
+
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{Document, Fragment, Selection, Span, Value, ValueSpan, VariableDefinition},
+    fmt::print_selection_set,
+};
+
+/// Replace the selection sets of fields that are repeated in `document`,
+/// which must have been parsed with `interner`, with spreads of new
+/// fragments, named after their type, such as `UserFields`
+///
+/// Only selection sets of at least two selections that appear at least
+/// twice are factored out, largest first, so a repeated selection set
+/// inside a larger repeated one ends up in the larger one's fragment
+pub fn factor_fragments(schema: &Document, document: &Document, interner: &mut Rodeo) -> Document {
+    let mut factored = document.clone();
+
+    loop {
+        let mut counts = HashMap::new();
+
+        for_each_root(schema, &factored, interner, |selection_set, parent| {
+            count_selection_sets(schema, selection_set, parent, interner, &mut counts);
+        });
+
+        let candidate = counts
+            .into_iter()
+            .filter(|(_, (count, _))| *count >= 2)
+            .max_by(|((_, a), _), ((_, b), _)| a.len().cmp(&b.len()).then_with(|| b.cmp(a)));
+
+        let ((on, key), (_, selection_set)) = match candidate {
+            Some(candidate) => candidate,
+            None => break,
+        };
+
+        let name = fragment_name(&factored, on, interner);
+
+        for operation in factored.operations.values_mut() {
+            if let Some(root) = schema.root_operation_type_or_default(operation.kind, interner) {
+                let selection_set = &mut operation.selection_set;
+                replace_selection_sets(schema, selection_set, root, (on, &key), name, interner);
+            }
+        }
+
+        for fragment in factored.fragments.values_mut() {
+            let (selection_set, parent) = (&mut fragment.selection_set, fragment.on);
+            replace_selection_sets(schema, selection_set, parent, (on, &key), name, interner);
+        }
+
+        factored.fragments.insert(
+            name,
+            Fragment {
+                name,
+                on,
+                directives: Vec::new(),
+                selection_set,
+                span: Span::default(),
+            },
+        );
+    }
+
+    factored
+}
+
+/// Call `f` with the selection set of each operation and fragment of
+/// `document`, and the type it's on
+fn for_each_root(
+    schema: &Document,
+    document: &Document,
+    interner: &Rodeo,
+    mut f: impl FnMut(&[Selection], Spur),
+) {
+    for operation in document.operations.values() {
+        if let Some(root) = schema.root_operation_type_or_default(operation.kind, interner) {
+            f(&operation.selection_set, root);
+        }
+    }
+
+    for fragment in document.fragments.values() {
+        f(&fragment.selection_set, fragment.on);
+    }
+}
+
+/// The named type of the field `name` of `parent`, if `schema` defines it
+fn field_type(schema: &Document, parent: Spur, name: Spur) -> Option<Spur> {
+    schema
+        .fields_of(parent)?
+        .iter()
+        .find(|field| field.name == name)
+        .map(|field| field.ty.name())
+}
+
+/// Count the selection sets of the fields of `selection_set` by their type
+/// and printed form, keeping one of each
+fn count_selection_sets(
+    schema: &Document,
+    selection_set: &[Selection],
+    parent: Spur,
+    interner: &Rodeo,
+    counts: &mut HashMap<(Spur, String), (usize, Vec<Selection>)>,
+) {
+    for selection in selection_set {
+        match selection {
+            Selection::Field {
+                name,
+                selection_set: Some(selection_set),
+                ..
+            } => {
+                let ty = match field_type(schema, parent, *name) {
+                    Some(ty) => ty,
+                    None => continue,
+                };
+
+                if selection_set.len() >= 2 {
+                    let key = print_selection_set(selection_set, interner);
+                    counts
+                        .entry((ty, key))
+                        .or_insert_with(|| (0, selection_set.clone()))
+                        .0 += 1;
+                }
+
+                count_selection_sets(schema, selection_set, ty, interner, counts);
+            }
+            Selection::InlineFragment {
+                on, selection_set, ..
+            } => count_selection_sets(
+                schema,
+                selection_set,
+                on.unwrap_or(parent),
+                interner,
+                counts,
+            ),
+            Selection::Field { .. } | Selection::FragmentSpread { .. } => {}
+        }
+    }
+}
+
+/// Replace the selection sets of the fields of `selection_set`, which is on
+/// `parent`, that have the type and printed form `key` with a spread of
+/// `fragment`
+fn replace_selection_sets(
+    schema: &Document,
+    selection_set: &mut [Selection],
+    parent: Spur,
+    key: (Spur, &str),
+    fragment: Spur,
+    interner: &Rodeo,
+) {
+    for selection in selection_set {
+        match selection {
+            Selection::Field {
+                name,
+                selection_set: Some(selection_set),
+                ..
+            } => {
+                let ty = match field_type(schema, parent, *name) {
+                    Some(ty) => ty,
+                    None => continue,
+                };
+
+                if ty == key.0 && print_selection_set(selection_set, interner) == key.1 {
+                    *selection_set = vec![Selection::FragmentSpread {
+                        name: fragment,
+                        directives: Vec::new(),
+                        span: Span::default(),
+                    }];
+                } else {
+                    replace_selection_sets(schema, selection_set, ty, key, fragment, interner);
+                }
+            }
+            Selection::InlineFragment {
+                on, selection_set, ..
+            } => replace_selection_sets(
+                schema,
+                selection_set,
+                on.unwrap_or(parent),
+                key,
+                fragment,
+                interner,
+            ),
+            Selection::Field { .. } | Selection::FragmentSpread { .. } => {}
+        }
+    }
+}
+
+/// `{Type}Fields`, followed by a number if a fragment already has that name
+fn fragment_name(document: &Document, on: Spur, interner: &mut Rodeo) -> Spur {
+    let base = format!("{}Fields", interner.resolve(&on));
+
+    let mut name = interner.get_or_intern(&base);
+    let mut suffix = 2;
+
+    while document.fragments.contains_key(&name) {
+        name = interner.get_or_intern(format!("{}{}", base, suffix));
+        suffix += 1;
+    }
+
+    name
+}
+
+/// Replace the literal arguments of the fields selected by the operations
+/// of `document`, which must have been parsed with `interner`, with new
+/// variables, defined with the argument's type and the literal as their
+/// default, so the operations behave as before when the variables aren't
+/// given
+///
+/// Variables are named after their argument, followed by a number if the
+/// operation already has a variable with that name. Fragments are left as
+/// they are, as they can't define variables, and `null` literals aren't
+/// lifted
+pub fn factor_variables(schema: &Document, document: &Document, interner: &mut Rodeo) -> Document {
+    let mut factored = document.clone();
+
+    for operation in factored.operations.values_mut() {
+        let root = match schema.root_operation_type_or_default(operation.kind, interner) {
+            Some(root) => root,
+            None => continue,
+        };
+
+        let mut lifter = VariableLifter {
+            schema,
+            interner: &mut *interner,
+            names: operation
+                .variable_definitions
+                .iter()
+                .map(|variable| variable.name)
+                .collect(),
+            definitions: Vec::new(),
+        };
+
+        lifter.lift(&mut operation.selection_set, root);
+
+        operation.variable_definitions.extend(lifter.definitions);
+    }
+
+    factored
+}
+
+struct VariableLifter<'a> {
+    schema: &'a Document,
+    interner: &'a mut Rodeo,
+    /// The names of the operation's variables
+    names: HashSet<Spur>,
+    definitions: Vec<VariableDefinition>,
+}
+
+impl VariableLifter<'_> {
+    fn lift(&mut self, selection_set: &mut [Selection], parent: Spur) {
+        for selection in selection_set {
+            match selection {
+                Selection::Field {
+                    name,
+                    arguments,
+                    selection_set,
+                    ..
+                } => {
+                    let field = match self
+                        .schema
+                        .fields_of(parent)
+                        .and_then(|fields| fields.iter().find(|field| field.name == *name))
+                    {
+                        Some(field) => field,
+                        None => continue,
+                    };
+
+                    for argument in arguments.iter_mut().flatten() {
+                        let definition = match field
+                            .arguments
+                            .iter()
+                            .flatten()
+                            .find(|definition| definition.name == argument.name)
+                        {
+                            Some(definition) => definition,
+                            None => continue,
+                        };
+
+                        if matches!(argument.value, Value::Null) || !is_constant(&argument.value) {
+                            continue;
+                        }
+
+                        let variable = self.variable_name(argument.name);
+                        let value = mem::replace(&mut argument.value, Value::Variable(variable));
+
+                        self.definitions.push(VariableDefinition {
+                            name: variable,
+                            ty: definition.ty.clone(),
+                            ty_span: Span::default(),
+                            default: Some(value),
+                            default_span: ValueSpan::default(),
+                            span: Span::default(),
+                        });
+                    }
+
+                    if let Some(selection_set) = selection_set {
+                        self.lift(selection_set, field.ty.name());
+                    }
+                }
+                Selection::InlineFragment {
+                    on, selection_set, ..
+                } => self.lift(selection_set, on.unwrap_or(parent)),
+                Selection::FragmentSpread { .. } => {}
+            }
+        }
+    }
+
+    /// `argument`, or `argument` followed by the first number that makes it
+    /// unique within the operation
+    fn variable_name(&mut self, argument: Spur) -> Spur {
+        let base = self.interner.resolve(&argument).to_owned();
+
+        let mut name = argument;
+        let mut suffix = 2;
+
+        while self.names.contains(&name) {
+            name = self.interner.get_or_intern(format!("{}{}", base, suffix));
+            suffix += 1;
+        }
+
+        self.names.insert(name);
+
+        name
+    }
+}
+
+/// Whether `value` contains no variables
+fn is_constant(value: &Value) -> bool {
+    match value {
+        Value::Variable(..) => false,
+        Value::List(values) => values.iter().all(is_constant),
+        Value::Object(fields) => fields.values().all(is_constant),
+        Value::True
+        | Value::False
+        | Value::Null
+        | Value::String(..)
+        | Value::EnumVariant(..)
+        | Value::Float(..)
+        | Value::Int(..) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{factor_fragments, factor_variables};
+    use crate::{ast::Document, fmt::print_minified, GraphqlParser};
+
+    const SCHEMA: &str = concat!(
+        "type Query { user(id: ID!): User users(first: Int = 10, role: Role): [User!]! } ",
+        "type User { id: ID! name: String friends(first: Int): [User!]! } ",
+        "enum Role { ADMIN USER }",
+    );
+
+    fn factor(document: &str, factor: fn(&Document, &Document, &mut Rodeo) -> Document) -> String {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let document =
+            GraphqlParser::parse_with_interner(document.as_bytes(), &mut interner).unwrap();
+
+        let factored = factor(&schema, &document, &mut interner);
+
+        print_minified(&factored, &interner)
+    }
+
+    #[test]
+    fn repeated_selection_sets() {
+        assert_eq!(
+            factor(
+                concat!(
+                    "query A { user(id: 1) { id name friends { id name } } } ",
+                    "query B { users { id name } } ",
+                    "fragment UserFields on User { id }",
+                ),
+                factor_fragments
+            ),
+            concat!(
+                "query A{user(id:1){id name friends{...UserFields2}}}",
+                "query B{users{...UserFields2}}",
+                "fragment UserFields on User{id}",
+                "fragment UserFields2 on User{id name}",
+            )
+        );
+    }
+
+    #[test]
+    fn nested_repeated_selection_sets() {
+        assert_eq!(
+            factor(
+                concat!(
+                    "query A { user(id: 1) { id friends { id name } } } ",
+                    "query B { users { id friends { id name } } } ",
+                    "query C { users { id name } }",
+                ),
+                factor_fragments
+            ),
+            concat!(
+                "query A{user(id:1){...UserFields}}",
+                "query B{users{...UserFields}}",
+                "query C{users{...UserFields2}}",
+                "fragment UserFields on User{id friends{...UserFields2}}",
+                "fragment UserFields2 on User{id name}",
+            )
+        );
+    }
+
+    #[test]
+    fn literal_arguments() {
+        assert_eq!(
+            factor(
+                concat!(
+                    "query A($first: Int) { user(id: 1) { friends(first: $first) { id } } ",
+                    "users(first: 2, role: ADMIN) { friends(first: null) { id } } }",
+                ),
+                factor_variables
+            ),
+            concat!(
+                "query A($first:Int$id:ID!=1$first2:Int=2$role:Role=ADMIN)",
+                "{user(id:$id){friends(first:$first){id}}",
+                "users(first:$first2 role:$role){friends(first:null){id}}}",
+            )
+        );
+    }
+}
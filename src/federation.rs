@@ -0,0 +1,1115 @@
+//! Apollo Federation subgraph schemas
+//!
+//! A subgraph marks the types other subgraphs can reference as entities with
+//! `@key`, and says which of their fields it resolves itself with
+//! `@external`, `@requires`, `@provides` and `@shareable`. Federation 2
+//! subgraphs opt in with `extend schema @link(url:
+//! "https://specs.apollo.dev/federation/v2.0")`, which also decides what the
+//! federation directives are called in the schema
+//!
+//! See https://www.apollographql.com/docs/federation/subgraph-spec
+
+use std::fmt;
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        Definition, Directive, Document, FieldDefinition, OperationKind, Selection, Span,
+        TypeDefinition, TypeExtension, TypeKind, Value,
+    },
+    parse::GraphqlParser,
+};
+
+/// The url of the federation spec, without its version
+pub const FEDERATION_SPEC_URL: &str = "https://specs.apollo.dev/federation";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FederationVersion {
+    /// The schema doesn't `@link` the federation spec, and uses the
+    /// federation directives by their bare names
+    V1,
+    V2,
+}
+
+/// A `@link` on the schema, importing names from another spec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub url: String,
+    /// The prefix given to names from the linked spec that aren't imported,
+    /// such as `federation` in `@federation__key`
+    pub namespace: String,
+    pub imports: Vec<LinkImport>,
+    pub span: Span,
+}
+
+/// A name imported by a `@link`. Directive names start with `@`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkImport {
+    pub name: String,
+    /// The name the import has in this schema, which is `name` unless it is
+    /// renamed with `{ name: "@key", as: "@myKey" }`
+    pub alias: String,
+}
+
+impl Link {
+    /// The url without its version, such as
+    /// `https://specs.apollo.dev/federation`
+    pub fn identity(&self) -> &str {
+        match split_version(&self.url) {
+            Some((identity, _)) => identity,
+            None => self.url.trim_end_matches('/'),
+        }
+    }
+
+    /// The version at the end of the url, such as `v2.3`
+    pub fn version(&self) -> Option<&str> {
+        split_version(&self.url).map(|(_, version)| version)
+    }
+
+    /// The name `name` from the linked spec has in this schema
+    pub fn local_name(&self, name: &str) -> String {
+        if let Some(import) = self.imports.iter().find(|import| import.name == name) {
+            return import.alias.clone();
+        }
+
+        match name.strip_prefix('@') {
+            Some(name) => format!("@{}__{}", self.namespace, name),
+            None => format!("{}__{}", self.namespace, name),
+        }
+    }
+}
+
+/// Split `https://specs.apollo.dev/federation/v2.3` into the identity and
+/// `v2.3`
+fn split_version(url: &str) -> Option<(&str, &str)> {
+    let (identity, version) = url.trim_end_matches('/').rsplit_once('/')?;
+
+    let is_version = version
+        .strip_prefix('v')
+        .and_then(|version| version.split_once('.'))
+        .is_some_and(|(major, minor)| {
+            !major.is_empty()
+                && !minor.is_empty()
+                && major
+                    .bytes()
+                    .chain(minor.bytes())
+                    .all(|b| b.is_ascii_digit())
+        });
+
+    is_version.then_some((identity, version))
+}
+
+/// A selection set given as a string, such as the `fields` of a `@key`
+#[derive(Debug, Clone)]
+pub struct FieldSet {
+    /// The string as written
+    pub source: Spur,
+    pub selection_set: Vec<Selection>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntityKey {
+    pub fields: FieldSet,
+    /// Whether this subgraph can resolve the entity from this key. Keys with
+    /// `resolvable: false` only let the subgraph reference the entity
+    pub resolvable: bool,
+    pub span: Span,
+}
+
+/// A field of an entity, and whether this subgraph resolves it
+#[derive(Debug, Clone)]
+pub struct EntityField {
+    pub name: Spur,
+    /// Marked `@external`, so it is resolved by another subgraph
+    pub external: bool,
+    /// Marked `@shareable`, so other subgraphs may resolve it as well
+    pub shareable: bool,
+    /// The fields of the entity this field needs from other subgraphs
+    pub requires: Option<FieldSet>,
+    /// The fields of the returned type this subgraph can resolve through
+    /// this field, even if it doesn't own them
+    pub provides: Option<FieldSet>,
+    pub span: Span,
+}
+
+impl EntityField {
+    /// Whether this subgraph resolves this field itself
+    pub fn is_owned(&self) -> bool {
+        !self.external
+    }
+}
+
+/// An object or interface with at least one `@key`
+#[derive(Debug, Clone)]
+pub struct Entity {
+    pub name: Spur,
+    pub kind: TypeKind,
+    /// The entity is only declared with `extend type`, as Federation 1
+    /// subgraphs do for entities that originate in another subgraph
+    pub is_extension: bool,
+    pub keys: Vec<EntityKey>,
+    /// The fields of the definition and of any extensions
+    pub fields: Vec<EntityField>,
+    pub span: Span,
+}
+
+impl Entity {
+    /// Whether a router can fetch this entity from this subgraph
+    pub fn is_resolvable(&self) -> bool {
+        self.keys.iter().any(|key| key.resolvable)
+    }
+
+    pub fn owned_fields(&self) -> impl Iterator<Item = &EntityField> {
+        self.fields.iter().filter(|field| field.is_owned())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FederationError {
+    pub kind: FederationErrorKind,
+    /// The directive the error was found in
+    pub span: Span,
+}
+
+impl fmt::Display for FederationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for FederationError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FederationErrorKind {
+    /// A `@link` without a string `url`, or with an `import` that isn't a
+    /// string or a `{ name, as }` object
+    InvalidLink { reason: String },
+    /// A `fields` argument that is missing, isn't a string or isn't a
+    /// selection set. `coordinate` is the type or field the directive is on
+    InvalidFieldSet {
+        directive: String,
+        coordinate: String,
+        reason: String,
+    },
+    /// A field set selects a field that its type doesn't have
+    UnknownFieldSetField {
+        directive: String,
+        coordinate: String,
+        type_name: String,
+        field: String,
+    },
+    /// `@requires` selects a field that isn't marked `@external`
+    RequiresNonExternalField { coordinate: String, field: String },
+}
+
+impl fmt::Display for FederationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLink { reason } => write!(f, "invalid `@link`: {}", reason),
+            Self::InvalidFieldSet {
+                directive,
+                coordinate,
+                reason,
+            } => write!(
+                f,
+                "invalid field set in `{}` on `{}`: {}",
+                directive, coordinate, reason
+            ),
+            Self::UnknownFieldSetField {
+                directive,
+                coordinate,
+                type_name,
+                field,
+            } => write!(
+                f,
+                "`{}` on `{}` selects `{}.{}`, which doesn't exist",
+                directive, coordinate, type_name, field
+            ),
+            Self::RequiresNonExternalField { coordinate, field } => write!(
+                f,
+                "`@requires` on `{}` selects `{}`, which isn't marked `@external`",
+                coordinate, field
+            ),
+        }
+    }
+}
+
+/// The entities of a subgraph schema and the links it imports the federation
+/// directives with
+#[derive(Debug, Clone)]
+pub struct FederatedSchema {
+    pub version: FederationVersion,
+    pub links: Vec<Link>,
+    /// Ordered by name
+    pub entities: Vec<Entity>,
+}
+
+impl FederatedSchema {
+    /// Find the entities of a subgraph schema. `interner` must be the one
+    /// `document` was parsed with, and is used to parse field sets
+    ///
+    /// Extensions don't need to have been applied, as Federation 1 subgraphs
+    /// extend entities that they don't define
+    pub fn from_document(
+        document: &Document,
+        interner: &mut Rodeo,
+    ) -> (Self, Vec<FederationError>) {
+        let mut errors = Vec::new();
+
+        let links = find_links(document, interner, &mut errors);
+
+        let federation = links
+            .iter()
+            .find(|link| link.identity() == FEDERATION_SPEC_URL);
+
+        let version = match federation {
+            Some(..) => FederationVersion::V2,
+            None => FederationVersion::V1,
+        };
+
+        let directive_name = |name: &str| match federation {
+            Some(link) => link.local_name(&format!("@{}", name))[1..].to_owned(),
+            None => name.to_owned(),
+        };
+
+        let mut finder = EntityFinder {
+            document,
+            interner,
+            errors,
+            key: directive_name("key"),
+            external: directive_name("external"),
+            requires: directive_name("requires"),
+            provides: directive_name("provides"),
+            shareable: directive_name("shareable"),
+        };
+
+        let mut entities = Vec::new();
+
+        for name in finder.type_names() {
+            if let Some(entity) = finder.entity(name) {
+                entities.push(entity);
+            }
+        }
+
+        entities.sort_by(|a, b| {
+            let name = |entity: &Entity| finder.interner.resolve(&entity.name);
+
+            name(a).cmp(name(b))
+        });
+
+        (
+            Self {
+                version,
+                links,
+                entities,
+            },
+            finder.errors,
+        )
+    }
+
+    pub fn entity(&self, name: Spur) -> Option<&Entity> {
+        self.entities.iter().find(|entity| entity.name == name)
+    }
+
+    /// Add what a subgraph schema needs for a router to query it: the `_Any`
+    /// scalar, the `_Service` type and `Query._service`, plus the `_Entity`
+    /// union and `Query._entities` if any object entity is resolvable.
+    /// Definitions and fields already in `document` are kept
+    ///
+    /// The spans of the added definitions don't point into `document`'s source
+    pub fn add_subgraph_definitions(&self, document: &mut Document, interner: &mut Rodeo) {
+        let query = document
+            .root_operation_type_or_default(OperationKind::Query, interner)
+            .map_or("Query", |name| interner.resolve(&name))
+            .to_owned();
+
+        let entity_names = self
+            .entities
+            .iter()
+            .filter(|entity| entity.kind == TypeKind::Object && entity.is_resolvable())
+            .map(|entity| interner.resolve(&entity.name))
+            .collect::<Vec<_>>();
+
+        let mut sdl = String::from("scalar _Any\n\ntype _Service {\n  sdl: String\n}\n\n");
+
+        sdl.push_str(&format!("type {} {{\n", query));
+
+        if !entity_names.is_empty() {
+            sdl.push_str("  _entities(representations: [_Any!]!): [_Entity]!\n");
+        }
+
+        sdl.push_str("  _service: _Service!\n}\n");
+
+        if !entity_names.is_empty() {
+            sdl.push_str(&format!("\nunion _Entity = {}\n", entity_names.join(" | ")));
+        }
+
+        let mut definitions = GraphqlParser::parse_schema_with_interner(sdl.as_bytes(), interner)
+            .expect("subgraph definitions are valid");
+
+        let query = interner.get_or_intern(query);
+
+        let query_type = definitions
+            .output_objects
+            .remove(&query)
+            .expect("subgraph definitions include the query type");
+
+        let types = definitions
+            .scalars
+            .into_values()
+            .map(TypeDefinition::Scalar)
+            .chain(
+                definitions
+                    .output_objects
+                    .into_values()
+                    .map(TypeDefinition::Object),
+            )
+            .chain(definitions.unions.into_values().map(TypeDefinition::Union));
+
+        for ty in types {
+            if document.type_kind(ty.name()).is_none() {
+                document.add_definition(Definition::TypeDecl(ty));
+            }
+        }
+
+        match document.output_objects.get_mut(&query) {
+            Some(object) => {
+                let fields = object.fields.get_or_insert_with(Vec::new);
+
+                for field in query_type.fields.into_iter().flatten() {
+                    if !fields.iter().any(|existing| existing.name == field.name) {
+                        fields.push(field);
+                    }
+                }
+            }
+            None => {
+                document.add_definition(Definition::TypeDecl(TypeDefinition::Object(query_type)))
+            }
+        }
+    }
+}
+
+/// A subgraph schema, along with the name it is known by to the router
+#[derive(Debug, Clone)]
+pub struct Subgraph {
+    pub name: String,
+    /// Where the router sends requests to the subgraph, if known
+    pub url: String,
+    /// The schema, with its extensions merged into the types they extend
+    pub schema: Document,
+    pub federation: FederatedSchema,
+}
+
+impl Subgraph {
+    /// Find the entities of `schema` and apply its extensions. Types that are
+    /// only declared with `extend type`, as in Federation 1 subgraphs, are
+    /// given a definition from their first extension
+    pub fn new(
+        name: impl Into<String>,
+        mut schema: Document,
+        interner: &mut Rodeo,
+    ) -> (Self, Vec<FederationError>) {
+        let (federation, errors) = FederatedSchema::from_document(&schema, interner);
+
+        for extension in std::mem::take(&mut schema.type_extensions) {
+            if schema.type_kind(extension.name()).is_some() {
+                schema.type_extensions.push(extension);
+                continue;
+            }
+
+            let definition = match extension {
+                TypeExtension::Scalar(scalar) => TypeDefinition::Scalar(scalar),
+                TypeExtension::Object(object) => TypeDefinition::Object(object),
+                TypeExtension::Interface(interface) => TypeDefinition::Interface(interface),
+                TypeExtension::Union(union) => TypeDefinition::Union(union),
+                TypeExtension::Enum(enum_def) => TypeDefinition::Enum(enum_def),
+                TypeExtension::InputObject(input) => TypeDefinition::InputObject(input),
+            };
+
+            schema.add_definition(Definition::TypeDecl(definition));
+        }
+
+        // the only errors left are schema extensions without a schema
+        // definition, which the default root types stand in for
+        let _ = schema.apply_extensions();
+
+        (
+            Self {
+                name: name.into(),
+                url: String::new(),
+                schema,
+                federation,
+            },
+            errors,
+        )
+    }
+
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// The name the federation directive `@{name}` has in this schema,
+    /// without the `@`
+    pub fn directive_name(&self, name: &str) -> String {
+        match self
+            .federation
+            .links
+            .iter()
+            .find(|link| link.identity() == FEDERATION_SPEC_URL)
+        {
+            Some(link) => link.local_name(&format!("@{}", name))[1..].to_owned(),
+            None => name.to_owned(),
+        }
+    }
+}
+
+/// The `@link`s on the schema definition and schema extensions
+fn find_links(
+    document: &Document,
+    interner: &Rodeo,
+    errors: &mut Vec<FederationError>,
+) -> Vec<Link> {
+    let directives = document
+        .schema
+        .iter()
+        .flat_map(|schema| &schema.directives)
+        .chain(
+            document
+                .schema_extensions
+                .iter()
+                .flat_map(|extension| &extension.directives),
+        )
+        .filter(|directive| interner.resolve(&directive.name) == "link");
+
+    let mut links = Vec::new();
+
+    for directive in directives {
+        match parse_link(directive, interner) {
+            Ok(link) => links.push(link),
+            Err(reason) => errors.push(FederationError {
+                kind: FederationErrorKind::InvalidLink { reason },
+                span: directive.span,
+            }),
+        }
+    }
+
+    links
+}
+
+fn parse_link(directive: &Directive, interner: &Rodeo) -> Result<Link, String> {
+    let string = |value: Option<&Value>| match value {
+        Some(Value::String(value)) => Some(interner.resolve(value).to_owned()),
+        _ => None,
+    };
+
+    let url = string(argument(directive, "url", interner))
+        .ok_or_else(|| "`url` must be given as a string".to_owned())?;
+
+    let mut link = Link {
+        url,
+        namespace: String::new(),
+        imports: Vec::new(),
+        span: directive.span,
+    };
+
+    link.namespace = match string(argument(directive, "as", interner)) {
+        Some(namespace) => namespace,
+        None => link
+            .identity()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_owned(),
+    };
+
+    let imports = match argument(directive, "import", interner) {
+        Some(Value::List(imports)) => imports.as_slice(),
+        Some(Value::Null) | None => &[],
+        Some(..) => return Err("`import` must be a list".to_owned()),
+    };
+
+    for import in imports {
+        let import = match import {
+            Value::String(name) => {
+                let name = interner.resolve(name).to_owned();
+
+                LinkImport {
+                    alias: name.clone(),
+                    name,
+                }
+            }
+            Value::Object(fields) => {
+                let field = |name: &str| {
+                    fields
+                        .iter()
+                        .find(|(field, _)| interner.resolve(field) == name)
+                        .map(|(_, value)| value)
+                };
+
+                let name = string(field("name"))
+                    .ok_or_else(|| "an import must have a string `name`".to_owned())?;
+
+                LinkImport {
+                    alias: string(field("as")).unwrap_or_else(|| name.clone()),
+                    name,
+                }
+            }
+            _ => return Err("an import must be a string or an object".to_owned()),
+        };
+
+        link.imports.push(import);
+    }
+
+    Ok(link)
+}
+
+fn argument<'a>(directive: &'a Directive, name: &str, interner: &Rodeo) -> Option<&'a Value> {
+    directive
+        .arguments
+        .iter()
+        .flatten()
+        .find(|argument| interner.resolve(&argument.name) == name)
+        .map(|argument| &argument.value)
+}
+
+/// The directives and fields given for an object or interface, by its
+/// definition or by an extension
+struct TypePart<'a> {
+    kind: TypeKind,
+    is_extension: bool,
+    directives: &'a [Directive],
+    fields: &'a [FieldDefinition],
+    span: Span,
+}
+
+struct EntityFinder<'a> {
+    document: &'a Document,
+    interner: &'a mut Rodeo,
+    errors: Vec<FederationError>,
+    /// The names of the federation directives in this schema
+    key: String,
+    external: String,
+    requires: String,
+    provides: String,
+    shareable: String,
+}
+
+impl<'a> EntityFinder<'a> {
+    fn name(&self, name: Spur) -> &str {
+        self.interner.resolve(&name)
+    }
+
+    fn has_directive(&self, directives: &[Directive], name: &str) -> bool {
+        directives
+            .iter()
+            .any(|directive| self.name(directive.name) == name)
+    }
+
+    fn find_directive<'b>(&self, directives: &'b [Directive], name: &str) -> Option<&'b Directive> {
+        directives
+            .iter()
+            .find(|directive| self.name(directive.name) == name)
+    }
+
+    /// The names of every object and interface, including those only
+    /// declared by extensions
+    fn type_names(&self) -> Vec<Spur> {
+        let mut names = self
+            .document
+            .output_objects
+            .keys()
+            .chain(self.document.interfaces.keys())
+            .copied()
+            .collect::<Vec<_>>();
+
+        for extension in &self.document.type_extensions {
+            if let TypeExtension::Object(..) | TypeExtension::Interface(..) = extension {
+                if !names.contains(&extension.name()) {
+                    names.push(extension.name());
+                }
+            }
+        }
+
+        names
+    }
+
+    fn type_parts(&self, name: Spur) -> Vec<TypePart<'a>> {
+        let document = self.document;
+
+        let definition = document
+            .output_objects
+            .get(&name)
+            .map(|object| TypePart {
+                kind: TypeKind::Object,
+                is_extension: false,
+                directives: &object.directives,
+                fields: object.fields.as_deref().unwrap_or_default(),
+                span: object.span,
+            })
+            .or_else(|| {
+                document.interfaces.get(&name).map(|interface| TypePart {
+                    kind: TypeKind::Interface,
+                    is_extension: false,
+                    directives: &interface.directives,
+                    fields: &interface.fields,
+                    span: interface.span,
+                })
+            });
+
+        let extensions = document
+            .type_extensions
+            .iter()
+            .filter_map(|extension| match extension {
+                TypeExtension::Object(object) if object.name == name => Some(TypePart {
+                    kind: TypeKind::Object,
+                    is_extension: true,
+                    directives: &object.directives,
+                    fields: object.fields.as_deref().unwrap_or_default(),
+                    span: object.span,
+                }),
+                TypeExtension::Interface(interface) if interface.name == name => Some(TypePart {
+                    kind: TypeKind::Interface,
+                    is_extension: true,
+                    directives: &interface.directives,
+                    fields: &interface.fields,
+                    span: interface.span,
+                }),
+                _ => None,
+            });
+
+        definition.into_iter().chain(extensions).collect()
+    }
+
+    /// The fields of the type named `name`, including those added by
+    /// extensions
+    fn fields_of(&self, name: Spur) -> Vec<&'a FieldDefinition> {
+        let extensions = self
+            .document
+            .type_extensions
+            .iter()
+            .filter(|extension| extension.name() == name)
+            .flat_map(|extension| match extension {
+                TypeExtension::Object(object) => object.fields.as_deref().unwrap_or_default(),
+                TypeExtension::Interface(interface) => &interface.fields,
+                _ => &[],
+            });
+
+        self.document
+            .fields_of(name)
+            .unwrap_or_default()
+            .iter()
+            .chain(extensions)
+            .collect()
+    }
+
+    fn entity(&mut self, name: Spur) -> Option<Entity> {
+        let parts = self.type_parts(name);
+
+        if !parts
+            .iter()
+            .any(|part| self.has_directive(part.directives, &self.key))
+        {
+            return None;
+        }
+
+        let type_name = self.name(name).to_owned();
+
+        let mut keys = Vec::new();
+        let mut fields = Vec::new();
+
+        for part in &parts {
+            for directive in part.directives {
+                if self.name(directive.name) != self.key {
+                    continue;
+                }
+
+                let resolvable = !matches!(
+                    argument(directive, "resolvable", self.interner),
+                    Some(Value::False)
+                );
+
+                if let Some(field_set) = self.checked_field_set(directive, &type_name, name) {
+                    keys.push(EntityKey {
+                        fields: field_set,
+                        resolvable,
+                        span: directive.span,
+                    });
+                }
+            }
+
+            // on a type, `@external` and `@shareable` apply to each of the
+            // fields declared with it
+            let all_external = self.has_directive(part.directives, &self.external);
+            let all_shareable = self.has_directive(part.directives, &self.shareable);
+
+            for field in part.fields {
+                fields.push(self.entity_field(field, name, all_external, all_shareable));
+            }
+        }
+
+        for field in &fields {
+            let requires = match &field.requires {
+                Some(requires) => requires,
+                None => continue,
+            };
+
+            for selection in &requires.selection_set {
+                let required = match selection {
+                    Selection::Field { name, span, .. } => (*name, *span),
+                    _ => continue,
+                };
+
+                let is_external = fields
+                    .iter()
+                    .any(|field| field.name == required.0 && field.external);
+
+                if !is_external && self.name(required.0) != "__typename" {
+                    self.errors.push(FederationError {
+                        kind: FederationErrorKind::RequiresNonExternalField {
+                            coordinate: format!("{}.{}", type_name, self.name(field.name)),
+                            field: self.name(required.0).to_owned(),
+                        },
+                        span: field.span,
+                    });
+                }
+            }
+        }
+
+        let first = &parts[0];
+
+        Some(Entity {
+            name,
+            kind: first.kind,
+            is_extension: parts.iter().all(|part| part.is_extension),
+            keys,
+            fields,
+            span: first.span,
+        })
+    }
+
+    fn entity_field(
+        &mut self,
+        field: &FieldDefinition,
+        parent_type: Spur,
+        all_external: bool,
+        all_shareable: bool,
+    ) -> EntityField {
+        let coordinate = format!("{}.{}", self.name(parent_type), self.name(field.name));
+
+        let requires = match self.find_directive(&field.directives, &self.requires) {
+            Some(directive) => self.checked_field_set(directive, &coordinate, parent_type),
+            None => None,
+        };
+
+        let provides = match self.find_directive(&field.directives, &self.provides) {
+            Some(directive) => self.checked_field_set(directive, &coordinate, field.ty.name()),
+            None => None,
+        };
+
+        EntityField {
+            name: field.name,
+            external: all_external || self.has_directive(&field.directives, &self.external),
+            shareable: all_shareable || self.has_directive(&field.directives, &self.shareable),
+            requires,
+            provides,
+            span: field.span,
+        }
+    }
+
+    /// Parse the `fields` argument of `directive`, and check it against the
+    /// type named `type_name`
+    fn checked_field_set(
+        &mut self,
+        directive: &Directive,
+        coordinate: &str,
+        type_name: Spur,
+    ) -> Option<FieldSet> {
+        let field_set = self.field_set(directive, coordinate)?;
+
+        self.check_field_set(directive, coordinate, &field_set.selection_set, type_name);
+
+        Some(field_set)
+    }
+
+    /// Parse the `fields` argument of `directive`
+    fn field_set(&mut self, directive: &Directive, coordinate: &str) -> Option<FieldSet> {
+        let source = match argument(directive, "fields", self.interner) {
+            Some(Value::String(source)) => *source,
+            _ => {
+                self.invalid_field_set(directive, coordinate, "`fields` must be a string".into());
+                return None;
+            }
+        };
+
+        let buffer = self.name(source).to_owned();
+
+        match GraphqlParser::parse_field_set_with_interner(buffer.as_bytes(), self.interner) {
+            Ok(selection_set) if selection_set.is_empty() => {
+                self.invalid_field_set(directive, coordinate, "no fields are selected".into());
+                None
+            }
+            Ok(selection_set) => Some(FieldSet {
+                source,
+                selection_set,
+            }),
+            Err(err) => {
+                self.invalid_field_set(directive, coordinate, err.to_string());
+                None
+            }
+        }
+    }
+
+    fn invalid_field_set(&mut self, directive: &Directive, coordinate: &str, reason: String) {
+        self.errors.push(FederationError {
+            kind: FederationErrorKind::InvalidFieldSet {
+                directive: format!("@{}", self.name(directive.name)),
+                coordinate: coordinate.to_owned(),
+                reason,
+            },
+            span: directive.span,
+        });
+    }
+
+    /// Check that each field selected by a field set exists on `type_name`
+    fn check_field_set(
+        &mut self,
+        directive: &Directive,
+        coordinate: &str,
+        selection_set: &[Selection],
+        type_name: Spur,
+    ) {
+        for selection in selection_set {
+            match selection {
+                Selection::Field {
+                    name,
+                    selection_set,
+                    ..
+                } => {
+                    if self.name(*name) == "__typename" {
+                        continue;
+                    }
+
+                    let field = self
+                        .fields_of(type_name)
+                        .into_iter()
+                        .find(|field| field.name == *name);
+
+                    match (field, selection_set) {
+                        (Some(field), Some(selection_set)) => self.check_field_set(
+                            directive,
+                            coordinate,
+                            selection_set,
+                            field.ty.name(),
+                        ),
+                        (Some(..), None) => {}
+                        (None, _) => self.errors.push(FederationError {
+                            kind: FederationErrorKind::UnknownFieldSetField {
+                                directive: format!("@{}", self.name(directive.name)),
+                                coordinate: coordinate.to_owned(),
+                                type_name: self.name(type_name).to_owned(),
+                                field: self.name(*name).to_owned(),
+                            },
+                            span: directive.span,
+                        }),
+                    }
+                }
+                Selection::InlineFragment {
+                    on, selection_set, ..
+                } => self.check_field_set(
+                    directive,
+                    coordinate,
+                    selection_set,
+                    on.unwrap_or(type_name),
+                ),
+                Selection::FragmentSpread { .. } => self.invalid_field_set(
+                    directive,
+                    coordinate,
+                    "fragment spreads can't be used in a field set".into(),
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{EntityField, FederatedSchema, FederationErrorKind, FederationVersion};
+    use crate::{fmt::print_document, GraphqlParser};
+
+    fn federated(source: &str) -> (FederatedSchema, Vec<FederationErrorKind>, Rodeo) {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse schema");
+
+        let (schema, errors) = FederatedSchema::from_document(&document, &mut interner);
+
+        (
+            schema,
+            errors.into_iter().map(|error| error.kind).collect(),
+            interner,
+        )
+    }
+
+    /// The names of the fields of `entity` matching `filter`
+    fn field_names<'a>(
+        schema: &FederatedSchema,
+        interner: &'a Rodeo,
+        entity: &str,
+        filter: impl Fn(&EntityField) -> bool,
+    ) -> Vec<&'a str> {
+        schema
+            .entity(interner.get(entity).unwrap())
+            .unwrap()
+            .fields
+            .iter()
+            .filter(|field| filter(field))
+            .map(|field| interner.resolve(&field.name))
+            .collect()
+    }
+
+    #[test]
+    fn federation_2_entities() {
+        let (schema, errors, interner) = federated(concat!(
+            "extend schema @link(url: \"https://specs.apollo.dev/federation/v2.3\", ",
+            "import: [\"@key\", { name: \"@shareable\", as: \"@share\" }]) ",
+            "type User @key(fields: \"id\") @key(fields: \"email\", resolvable: false) ",
+            "{ id: ID! email: String name: String @share ",
+            "reviews: [Review] @federation__provides(fields: \"body\") } ",
+            "type Review @key(fields: \"id\") { id: ID! body: String @federation__external ",
+            "author: User @federation__requires(fields: \"body\") } ",
+            "type Query { me: User }",
+        ));
+
+        assert_eq!(errors, []);
+        assert_eq!(schema.version, FederationVersion::V2);
+
+        let link = &schema.links[0];
+        assert_eq!(link.identity(), "https://specs.apollo.dev/federation");
+        assert_eq!(link.version(), Some("v2.3"));
+        assert_eq!(link.namespace, "federation");
+        assert_eq!(link.local_name("@shareable"), "@share");
+        assert_eq!(link.local_name("@external"), "@federation__external");
+
+        let names = schema
+            .entities
+            .iter()
+            .map(|entity| interner.resolve(&entity.name))
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Review", "User"]);
+
+        let user = &schema.entities[1];
+        assert_eq!(user.keys.len(), 2);
+        assert!(user.keys[0].resolvable);
+        assert!(!user.keys[1].resolvable);
+        assert!(user.is_resolvable());
+
+        assert_eq!(
+            field_names(&schema, &interner, "User", |field| field.shareable),
+            ["name"]
+        );
+        assert_eq!(
+            field_names(&schema, &interner, "User", |field| field.provides.is_some()),
+            ["reviews"]
+        );
+        assert_eq!(
+            field_names(&schema, &interner, "Review", |field| field.is_owned()),
+            ["id", "author"]
+        );
+        assert_eq!(
+            field_names(&schema, &interner, "Review", |field| field
+                .requires
+                .is_some()),
+            ["author"]
+        );
+    }
+
+    #[test]
+    fn federation_1_entities() {
+        let (schema, errors, interner) = federated(concat!(
+            "extend type User @key(fields: \"id\") { id: ID! @external reviews: [String] } ",
+            "type Query { me: User }",
+        ));
+
+        assert_eq!(errors, []);
+        assert_eq!(schema.version, FederationVersion::V1);
+
+        let user = &schema.entities[0];
+        assert!(user.is_extension);
+        assert_eq!(
+            user.owned_fields()
+                .map(|field| interner.resolve(&field.name))
+                .collect::<Vec<_>>(),
+            ["reviews"]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let (_, errors, _) = federated(concat!(
+            "type A @key(fields: \"{\") @key(fields: \"nope\") @key(fields: 1) ",
+            "{ id: ID! b: Int c: Int @requires(fields: \"b\") } ",
+            "extend schema @link(import: [\"@key\"])",
+        ));
+
+        let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert_eq!(messages.len(), 5, "{:#?}", messages);
+        assert!(matches!(errors[0], FederationErrorKind::InvalidLink { .. }));
+        assert!(errors.iter().any(|error| matches!(
+            error,
+            FederationErrorKind::UnknownFieldSetField { field, .. } if field == "nope"
+        )));
+        assert!(errors.iter().any(|error| matches!(
+            error,
+            FederationErrorKind::RequiresNonExternalField { coordinate, field }
+                if coordinate == "A.c" && field == "b"
+        )));
+        assert_eq!(
+            errors
+                .iter()
+                .filter(|error| matches!(error, FederationErrorKind::InvalidFieldSet { .. }))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn subgraph_definitions() {
+        let source = concat!(
+            "type User @key(fields: \"id\") { id: ID! } ",
+            "type Ref @key(fields: \"id\", resolvable: false) { id: ID! } ",
+            "type Query { me: User }",
+        );
+
+        let mut interner = Rodeo::default();
+        let mut document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse schema");
+
+        let (schema, errors) = FederatedSchema::from_document(&document, &mut interner);
+        assert_eq!(errors, []);
+
+        schema.add_subgraph_definitions(&mut document, &mut interner);
+        let printed = print_document(&document, &interner);
+
+        assert!(printed.contains("scalar _Any\n"), "{}", printed);
+        assert!(
+            printed.contains("type _Service {\n  sdl: String\n}"),
+            "{}",
+            printed
+        );
+        assert!(printed.contains("union _Entity = User\n"), "{}", printed);
+        assert!(
+            printed.contains(concat!(
+                "type Query {\n  me: User\n",
+                "  _entities(representations: [_Any!]!): [_Entity]!\n",
+                "  _service: _Service!\n}",
+            )),
+            "{}",
+            printed
+        );
+    }
+}
@@ -0,0 +1,422 @@
+//! Inlining of fragments. Each fragment spread and inline fragment of an
+//! operation is replaced by the fields it selects, and fields with the same
+//! response key are merged, as CollectFields does during execution. Selection
+//! sets on interfaces and unions become one inline fragment for each object
+//! type that has fields selected on it
+//!
+//! `@skip` and `@include` on a fragment are moved to each of the fields it
+//! selects, so that the flattened operation selects the same fields for any
+//! variable values. Fields are only merged if their directives are the same
+//!
+//! See https://spec.graphql.org/October2021/#CollectFields()
+
+use lasso::{Rodeo, Spur};
+
+use crate::ast::{Directive, Document, Operation, Selection, Span, TypeKind, Value};
+
+/// Inline the fragments of `operation`, which is part of `document`. Returns
+/// `None` if `schema` has no root type for the kind of operation
+///
+/// Fields that `schema` doesn't define are kept, but their selection sets
+/// are left as they are
+pub fn flatten_operation(
+    schema: &Document,
+    document: &Document,
+    operation: &Operation,
+    interner: &Rodeo,
+) -> Option<Operation> {
+    let root = schema.root_operation_type_or_default(operation.kind, interner)?;
+
+    let flattener = Flattener {
+        schema,
+        document,
+        interner,
+    };
+
+    Some(Operation {
+        selection_set: flattener
+            .flatten_selection_sets(&[(&operation.selection_set, Vec::new())], root),
+        ..operation.clone()
+    })
+}
+
+/// A selection set, along with the fragment spreads that were being inlined
+/// where it was found, which guard against cycles
+type NestedSelectionSet<'a> = (&'a [Selection], Vec<Spur>);
+
+/// The selections with the same response key and directives, merged into
+/// one field
+struct CollectedField<'a> {
+    /// The first of the merged selections
+    field: &'a Selection,
+    key: Spur,
+    directives: Vec<Directive>,
+    selection_sets: Vec<NestedSelectionSet<'a>>,
+}
+
+struct Flattener<'a> {
+    schema: &'a Document,
+    document: &'a Document,
+    interner: &'a Rodeo,
+}
+
+impl<'a> Flattener<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    /// Flatten the merged `selection_sets`, which are selected on the type
+    /// named `parent_type`
+    fn flatten_selection_sets(
+        &self,
+        selection_sets: &[NestedSelectionSet<'a>],
+        parent_type: Spur,
+    ) -> Vec<Selection> {
+        match self.schema.type_kind(parent_type) {
+            Some(TypeKind::Object) => self.object_fields(selection_sets, parent_type),
+            Some(TypeKind::Interface | TypeKind::Union) => {
+                let mut object_types = self.schema.possible_types(parent_type);
+                object_types.sort_by_key(|name| self.name(*name));
+
+                object_types
+                    .into_iter()
+                    .filter_map(|object_type| {
+                        let fields = self.object_fields(selection_sets, object_type);
+
+                        (!fields.is_empty()).then_some(Selection::InlineFragment {
+                            on: Some(object_type),
+                            directives: Vec::new(),
+                            selection_set: fields,
+                            span: Span::default(),
+                        })
+                    })
+                    .collect()
+            }
+            _ => selection_sets
+                .iter()
+                .flat_map(|(selection_set, _)| selection_set.iter().cloned())
+                .collect(),
+        }
+    }
+
+    /// The merged fields selected on `object_type`, with their own selection
+    /// sets flattened
+    fn object_fields(
+        &self,
+        selection_sets: &[NestedSelectionSet<'a>],
+        object_type: Spur,
+    ) -> Vec<Selection> {
+        let mut fields = Vec::new();
+
+        for (selection_set, inlining) in selection_sets {
+            self.collect_fields(
+                object_type,
+                selection_set,
+                &[],
+                &mut fields,
+                &mut inlining.clone(),
+            );
+        }
+
+        fields
+            .into_iter()
+            .map(|collected| self.merge_field(collected, object_type))
+            .collect()
+    }
+
+    fn merge_field(&self, collected: CollectedField<'a>, object_type: Spur) -> Selection {
+        let (alias, name, arguments, span) = match collected.field {
+            Selection::Field {
+                alias,
+                name,
+                arguments,
+                span,
+                ..
+            } => (*alias, *name, arguments.clone(), *span),
+            _ => unreachable!("only fields are collected"),
+        };
+
+        let field_type = self
+            .schema
+            .fields_of(object_type)
+            .and_then(|fields| fields.iter().find(|field| field.name == name))
+            .map(|field| field.ty.name());
+
+        let selection_set = match field_type {
+            _ if collected.selection_sets.is_empty() => None,
+            Some(field_type) => {
+                Some(self.flatten_selection_sets(&collected.selection_sets, field_type))
+            }
+            None => Some(
+                collected
+                    .selection_sets
+                    .iter()
+                    .flat_map(|(selection_set, _)| selection_set.iter().cloned())
+                    .collect(),
+            ),
+        };
+
+        Selection::Field {
+            alias,
+            name,
+            arguments,
+            directives: collected.directives,
+            selection_set,
+            span,
+        }
+    }
+
+    /// `conditions` are the `@skip` and `@include` directives of the
+    /// fragments being inlined, and `inlining` is the stack of fragment
+    /// spreads being inlined, which guards against cycles
+    fn collect_fields(
+        &self,
+        object_type: Spur,
+        selection_set: &'a [Selection],
+        conditions: &[Directive],
+        fields: &mut Vec<CollectedField<'a>>,
+        inlining: &mut Vec<Spur>,
+    ) {
+        for selection in selection_set {
+            match selection {
+                Selection::Field {
+                    alias,
+                    name,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    let key = alias.unwrap_or(*name);
+
+                    let directives = conditions
+                        .iter()
+                        .chain(directives)
+                        .cloned()
+                        .collect::<Vec<_>>();
+
+                    let existing = fields.iter_mut().find(|field| {
+                        field.key == key && same_directives(&field.directives, &directives)
+                    });
+
+                    let field = match existing {
+                        Some(field) => field,
+                        None => {
+                            fields.push(CollectedField {
+                                field: selection,
+                                key,
+                                directives,
+                                selection_sets: Vec::new(),
+                            });
+
+                            fields.last_mut().unwrap()
+                        }
+                    };
+
+                    if let Some(selection_set) = selection_set {
+                        field.selection_sets.push((selection_set, inlining.clone()));
+                    }
+                }
+                Selection::FragmentSpread {
+                    name, directives, ..
+                } => {
+                    if inlining.contains(name) {
+                        continue;
+                    }
+
+                    let fragment = match self.document.fragments.get(name) {
+                        Some(fragment) => fragment,
+                        None => continue,
+                    };
+
+                    if !self.does_fragment_type_apply(object_type, fragment.on) {
+                        continue;
+                    }
+
+                    let conditions = self.with_conditions(conditions, directives);
+
+                    inlining.push(*name);
+                    self.collect_fields(
+                        object_type,
+                        &fragment.selection_set,
+                        &conditions,
+                        fields,
+                        inlining,
+                    );
+                    inlining.pop();
+                }
+                Selection::InlineFragment {
+                    on,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    if !on.is_none_or(|on| self.does_fragment_type_apply(object_type, on)) {
+                        continue;
+                    }
+
+                    let conditions = self.with_conditions(conditions, directives);
+
+                    self.collect_fields(object_type, selection_set, &conditions, fields, inlining);
+                }
+            }
+        }
+    }
+
+    /// `conditions` followed by the `@skip` and `@include` of `directives`
+    fn with_conditions(
+        &self,
+        conditions: &[Directive],
+        directives: &[Directive],
+    ) -> Vec<Directive> {
+        let new_conditions = directives
+            .iter()
+            .filter(|directive| matches!(self.name(directive.name), "skip" | "include"));
+
+        conditions.iter().chain(new_conditions).cloned().collect()
+    }
+
+    fn does_fragment_type_apply(&self, object_type: Spur, type_condition: Spur) -> bool {
+        self.schema
+            .possible_types(type_condition)
+            .contains(&object_type)
+    }
+}
+
+/// Whether the directives have the same names and arguments, ignoring spans
+fn same_directives(a: &[Directive], b: &[Directive]) -> bool {
+    fn arguments(directive: &Directive) -> Vec<(Spur, &Value)> {
+        directive
+            .arguments
+            .iter()
+            .flatten()
+            .map(|argument| (argument.name, &argument.value))
+            .collect()
+    }
+
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(a, b)| a.name == b.name && arguments(a) == arguments(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::flatten_operation;
+    use crate::{ast::Document, fmt::print_document, GraphqlParser};
+
+    const SCHEMA: &str = "
+type Query { user: User node: Node search: [SearchResult!]! }
+interface Node { id: ID! }
+type User implements Node { id: ID! name: String friend: User }
+type Post implements Node { id: ID! title: String }
+union SearchResult = User | Post
+";
+
+    /// The operations of `source`, flattened and printed
+    fn flatten(source: &str) -> String {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse operation");
+
+        let mut flattened = Document::new();
+
+        for (key, operation) in &document.operations {
+            let operation = flatten_operation(&schema, &document, operation, &interner).unwrap();
+            flattened.operations.insert(*key, operation);
+        }
+
+        print_document(&flattened, &interner)
+    }
+
+    #[test]
+    fn inlines_fragments_and_merges_fields() {
+        let flattened = flatten(concat!(
+            "{ user { ...F name friend { id } friend { name } } } ",
+            "fragment F on User { id name }",
+        ));
+
+        assert_eq!(
+            flattened,
+            "{\n  user {\n    id\n    name\n    friend {\n      id\n      name\n    }\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn abstract_types_split_by_object_type() {
+        let flattened = flatten(concat!(
+            "{ node { id ... on User { name } ...P } ",
+            "search { ... on Node { id } ... on Post { title } } } ",
+            "fragment P on Post { title }",
+        ));
+
+        let expected = "{
+  node {
+    ... on Post {
+      id
+      title
+    }
+    ... on User {
+      id
+      name
+    }
+  }
+  search {
+    ... on Post {
+      id
+      title
+    }
+    ... on User {
+      id
+    }
+  }
+}
+";
+
+        assert_eq!(flattened, expected);
+    }
+
+    #[test]
+    fn conditional_fragments() {
+        let flattened = flatten(concat!(
+            "query($a: Boolean!) { user { ...F @skip(if: $a) name } } ",
+            "fragment F on User { id name }",
+        ));
+
+        let expected = "query($a: Boolean!) {
+  user {
+    id @skip(if: $a)
+    name @skip(if: $a)
+    name
+  }
+}
+";
+
+        assert_eq!(flattened, expected);
+    }
+
+    #[test]
+    fn inline_fragments_without_type_condition() {
+        let flattened =
+            flatten("query($a: Boolean!) { user { ... { id } ... @include(if: $a) { name } } }");
+
+        let expected = "query($a: Boolean!) {
+  user {
+    id
+    name @include(if: $a)
+  }
+}
+";
+
+        assert_eq!(flattened, expected);
+    }
+
+    #[test]
+    fn fragment_cycles_terminate() {
+        let flattened = flatten("{ user { ...F } } fragment F on User { friend { ...F } }");
+
+        assert!(flattened.contains("friend"), "{}", flattened);
+    }
+}
@@ -0,0 +1,1124 @@
+//! Serialization of a [`Document`] back into GraphQL source text
+
+use std::{collections::HashMap, fmt::Write};
+
+use lasso::{Rodeo, Spur};
+
+use crate::ast::{
+    Argument, Directive, DirectiveDefinition, Document, Enum, EnumVariant, FieldDefinition,
+    Fragment, InputObject, InputObjectField, Interface, NamedType, ObjectType, Operation,
+    OperationKind, RootOperationTypeDefinition, Scalar, SchemaDefinition, SchemaExtension,
+    Selection, Type, TypeExtension, Union, Value, VariableDefinition,
+};
+
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    /// The string used for a single level of indentation
+    pub indent: String,
+    /// Whether to print the descriptions of type system definitions
+    pub descriptions: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            indent: "  ".to_owned(),
+            descriptions: true,
+        }
+    }
+}
+
+/// Print `document` using the default [`PrintOptions`]
+///
+/// `interner` must be the interner the document was parsed with
+pub fn print_document(document: &Document, interner: &Rodeo) -> String {
+    print_document_with_options(document, interner, &PrintOptions::default())
+}
+
+pub fn print_document_with_options(
+    document: &Document,
+    interner: &Rodeo,
+    options: &PrintOptions,
+) -> String {
+    let mut printer = Printer::new(interner, options);
+
+    printer.print_document(document);
+
+    printer.finish()
+}
+
+/// Print `document` as the shortest equivalent source text, without
+/// descriptions. Tokens are only separated where they would otherwise be read
+/// as one token
+pub fn print_minified(document: &Document, interner: &Rodeo) -> String {
+    let options = PrintOptions {
+        descriptions: false,
+        ..PrintOptions::default()
+    };
+
+    minify(&print_document_with_options(document, interner, &options))
+}
+
+/// Remove the whitespace and commas between the tokens of printed source
+/// text, which contains no block strings or comments
+pub(crate) fn minify(source: &str) -> String {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut minified = String::with_capacity(source.len());
+    let mut chars = source.chars();
+    let mut separated = false;
+    let mut after_empty_string = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\n' | '\t' | '\r' | ',' => {
+                separated = true;
+                continue;
+            }
+            '"' => {
+                // `""` followed by another string would be read as the start
+                // of a block string
+                if after_empty_string {
+                    minified.push(' ');
+                }
+
+                let start = minified.len();
+
+                minified.push('"');
+
+                while let Some(c) = chars.next() {
+                    minified.push(c);
+
+                    match c {
+                        '\\' => minified.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+
+                after_empty_string = minified.len() - start == 2;
+            }
+            c => {
+                let needs_space =
+                    minified.chars().next_back().is_some_and(is_name_char) && is_name_char(c);
+
+                if separated && needs_space {
+                    minified.push(' ');
+                }
+
+                minified.push(c);
+                after_empty_string = false;
+            }
+        }
+
+        separated = false;
+    }
+
+    minified
+}
+
+/// Whether `description` keeps its value when printed as a block string.
+/// Block strings drop leading and trailing blank lines and the indentation
+/// common to their lines, normalize line endings, and can't end in `\`
+/// without escaping their closing quotes
+fn is_block_string_safe(description: &str) -> bool {
+    let is_blank = |line: &str| line.chars().all(|c| c == ' ' || c == '\t');
+    let is_indented = |line: &str| line.starts_with([' ', '\t']);
+
+    let lines = description.split('\n').collect::<Vec<_>>();
+
+    !description.ends_with('\\')
+        && !description.contains('\r')
+        && !is_blank(lines[0])
+        && !is_blank(lines[lines.len() - 1])
+        && !lines
+            .iter()
+            .filter(|line| !is_blank(line))
+            .all(|line| is_indented(line))
+}
+
+/// Write `description` as a block string if it keeps its value as one, or as
+/// a string otherwise. The lines of a block string are indented by `indent`,
+/// which should be the indentation of the line it starts on
+pub(crate) fn write_description(buffer: &mut String, description: &str, indent: &str) {
+    if !is_block_string_safe(description) {
+        write_string(buffer, description);
+        return;
+    }
+
+    let escaped = description.replace("\"\"\"", "\\\"\"\"");
+
+    if escaped.contains('\n') || escaped.ends_with('"') {
+        buffer.push_str("\"\"\"\n");
+
+        for line in escaped.lines() {
+            if !line.is_empty() {
+                buffer.push_str(indent);
+                buffer.push_str(line);
+            }
+            buffer.push('\n');
+        }
+
+        buffer.push_str(indent);
+        buffer.push_str("\"\"\"");
+    } else {
+        write!(buffer, "\"\"\"{}\"\"\"", escaped).unwrap();
+    }
+}
+
+fn write_string(buffer: &mut String, string: &str) {
+    buffer.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => buffer.push_str("\\\""),
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            '\t' => buffer.push_str("\\t"),
+            '\u{8}' => buffer.push_str("\\b"),
+            '\u{c}' => buffer.push_str("\\f"),
+            c if c.is_control() => write!(buffer, "\\u{:04X}", c as u32).unwrap(),
+            c => buffer.push(c),
+        }
+    }
+
+    buffer.push('"');
+}
+
+/// Print a single value literal
+pub fn print_value(value: &Value, interner: &Rodeo) -> String {
+    let options = PrintOptions::default();
+    let mut printer = Printer::new(interner, &options);
+
+    printer.print_value(value);
+
+    printer.finish()
+}
+
+/// Print a type reference, such as `[String!]`
+pub fn print_type(ty: &Type, interner: &Rodeo) -> String {
+    let options = PrintOptions::default();
+    let mut printer = Printer::new(interner, &options);
+
+    printer.print_type(ty);
+
+    printer.finish()
+}
+
+/// Print the selections of a selection set, inside its braces
+pub fn print_selection_set(selection_set: &[Selection], interner: &Rodeo) -> String {
+    let options = PrintOptions::default();
+    let mut printer = Printer::new(interner, &options);
+
+    printer.print_selection_set(selection_set);
+
+    printer.finish()
+}
+
+/// Writes GraphQL source text into an internal buffer. Definitions held in
+/// hash maps are printed sorted by name so that output is stable
+pub struct Printer<'a> {
+    interner: &'a Rodeo,
+    options: &'a PrintOptions,
+    buffer: String,
+    depth: usize,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(interner: &'a Rodeo, options: &'a PrintOptions) -> Self {
+        Self {
+            interner,
+            options,
+            buffer: String::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.buffer
+    }
+
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn sorted<'b, T>(&self, definitions: &'b HashMap<Spur, T>) -> Vec<(&'b Spur, &'b T)> {
+        let mut definitions = definitions.iter().collect::<Vec<_>>();
+
+        definitions.sort_by_key(|(name, _)| self.name(**name));
+
+        definitions
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.depth {
+            self.buffer.push_str(&self.options.indent);
+        }
+    }
+
+    /// Separates top level definitions with a blank line
+    fn start_definition(&mut self) {
+        if !self.buffer.is_empty() {
+            self.buffer.push('\n');
+        }
+    }
+
+    pub fn print_document(&mut self, document: &Document) {
+        if let Some(schema) = &document.schema {
+            self.start_definition();
+            self.print_schema_definition(schema);
+        }
+
+        for (_, directive) in self.sorted(&document.directive_definitions) {
+            self.start_definition();
+            self.print_directive_definition(directive);
+        }
+
+        for (_, scalar) in self.sorted(&document.scalars) {
+            self.start_definition();
+            self.print_scalar(scalar, false);
+        }
+
+        for (_, interface) in self.sorted(&document.interfaces) {
+            self.start_definition();
+            self.print_interface(interface, false);
+        }
+
+        for (_, object) in self.sorted(&document.output_objects) {
+            self.start_definition();
+            self.print_object_type(object, false);
+        }
+
+        for (_, union) in self.sorted(&document.unions) {
+            self.start_definition();
+            self.print_union(union, false);
+        }
+
+        for (_, enum_def) in self.sorted(&document.enums) {
+            self.start_definition();
+            self.print_enum(enum_def, false);
+        }
+
+        for (_, input) in self.sorted(&document.input_objects) {
+            self.start_definition();
+            self.print_input_object(input, false);
+        }
+
+        for extension in &document.schema_extensions {
+            self.start_definition();
+            self.print_schema_extension(extension);
+        }
+
+        for extension in &document.type_extensions {
+            self.start_definition();
+            self.print_type_extension(extension);
+        }
+
+        let mut operations = document.operations.values().collect::<Vec<_>>();
+
+        operations.sort_by_key(|operation| {
+            (
+                operation.name.map(|name| self.name(name)),
+                operation.kind as u8,
+            )
+        });
+
+        for operation in operations {
+            self.start_definition();
+            self.print_operation(operation);
+        }
+
+        for (_, fragment) in self.sorted(&document.fragments) {
+            self.start_definition();
+            self.print_fragment(fragment);
+        }
+    }
+
+    fn print_description(&mut self, description: Option<Spur>) {
+        let description = match description {
+            Some(description) if self.options.descriptions => self.name(description),
+            _ => return,
+        };
+
+        self.write_indent();
+        let indent = self.options.indent.repeat(self.depth);
+        write_description(&mut self.buffer, description, &indent);
+        self.buffer.push('\n');
+    }
+
+    fn print_directives(&mut self, directives: &[Directive]) {
+        for directive in directives {
+            write!(self.buffer, " @{}", self.name(directive.name)).unwrap();
+
+            if let Some(arguments) = &directive.arguments {
+                self.print_arguments(arguments);
+            }
+        }
+    }
+
+    fn print_arguments(&mut self, arguments: &[Argument]) {
+        self.buffer.push('(');
+
+        for (idx, argument) in arguments.iter().enumerate() {
+            if idx != 0 {
+                self.buffer.push_str(", ");
+            }
+
+            write!(self.buffer, "{}: ", self.name(argument.name)).unwrap();
+            self.print_value(&argument.value);
+        }
+
+        self.buffer.push(')');
+    }
+
+    pub fn print_value(&mut self, value: &Value) {
+        match value {
+            Value::True => self.buffer.push_str("true"),
+            Value::False => self.buffer.push_str("false"),
+            Value::Null => self.buffer.push_str("null"),
+            Value::String(string) => self.print_string(self.name(*string)),
+            Value::Variable(name) => write!(self.buffer, "${}", self.name(*name)).unwrap(),
+            Value::EnumVariant(name) => self.buffer.push_str(self.name(*name)),
+            Value::List(values) => {
+                self.buffer.push('[');
+
+                for (idx, value) in values.iter().enumerate() {
+                    if idx != 0 {
+                        self.buffer.push_str(", ");
+                    }
+
+                    self.print_value(value);
+                }
+
+                self.buffer.push(']');
+            }
+            Value::Object(fields) => {
+                self.buffer.push('{');
+
+                for (idx, (name, value)) in self.sorted(fields).into_iter().enumerate() {
+                    if idx != 0 {
+                        self.buffer.push_str(", ");
+                    }
+
+                    write!(self.buffer, "{}: ", self.name(*name)).unwrap();
+                    self.print_value(value);
+                }
+
+                self.buffer.push('}');
+            }
+            // `{:?}` always includes a `.` or exponent, so the value is lexed as
+            // a float again
+            Value::Float(float) => write!(self.buffer, "{:?}", float).unwrap(),
+            Value::Int(int) => write!(self.buffer, "{}", int).unwrap(),
+        }
+    }
+
+    fn print_string(&mut self, string: &str) {
+        write_string(&mut self.buffer, string);
+    }
+
+    pub fn print_type(&mut self, ty: &Type) {
+        let nullable = match ty {
+            Type::Named { name, nullable } => {
+                self.buffer.push_str(self.name(*name));
+                *nullable
+            }
+            Type::List { base, nullable } => {
+                self.buffer.push('[');
+                self.print_type(base);
+                self.buffer.push(']');
+                *nullable
+            }
+        };
+
+        if !nullable {
+            self.buffer.push('!');
+        }
+    }
+
+    fn print_named_types(&mut self, types: &[NamedType], separator: &str) {
+        for (idx, ty) in types.iter().enumerate() {
+            if idx != 0 {
+                self.buffer.push_str(separator);
+            }
+
+            self.buffer.push_str(self.name(ty.0));
+        }
+    }
+
+    fn open_block(&mut self) {
+        self.buffer.push_str(" {\n");
+        self.depth += 1;
+    }
+
+    fn close_block(&mut self) {
+        self.depth -= 1;
+        self.write_indent();
+        self.buffer.push('}');
+    }
+
+    fn print_schema_definition(&mut self, schema: &SchemaDefinition) {
+        self.print_description(schema.description);
+        self.buffer.push_str("schema");
+        self.print_directives(&schema.directives);
+        self.print_root_operation_types(&schema.operation_types);
+        self.buffer.push('\n');
+    }
+
+    fn print_schema_extension(&mut self, extension: &SchemaExtension) {
+        self.buffer.push_str("extend schema");
+        self.print_directives(&extension.directives);
+
+        if !extension.operation_types.is_empty() {
+            self.print_root_operation_types(&extension.operation_types);
+        }
+
+        self.buffer.push('\n');
+    }
+
+    fn print_root_operation_types(&mut self, operation_types: &[RootOperationTypeDefinition]) {
+        self.open_block();
+
+        for operation_type in operation_types {
+            self.write_indent();
+            writeln!(
+                self.buffer,
+                "{}: {}",
+                operation_type.kind.as_str(),
+                self.name(operation_type.ty.0)
+            )
+            .unwrap();
+        }
+
+        self.close_block();
+    }
+
+    fn print_directive_definition(&mut self, directive: &DirectiveDefinition) {
+        self.print_description(directive.description);
+        write!(self.buffer, "directive @{}", self.name(directive.name)).unwrap();
+
+        if let Some(arguments) = &directive.arguments {
+            self.print_argument_definitions(arguments);
+        }
+
+        if directive.repeatable {
+            self.buffer.push_str(" repeatable");
+        }
+
+        self.buffer.push_str(" on ");
+
+        for (idx, location) in directive.locations.iter().enumerate() {
+            if idx != 0 {
+                self.buffer.push_str(" | ");
+            }
+
+            self.buffer.push_str(location.as_str());
+        }
+
+        self.buffer.push('\n');
+    }
+
+    fn print_type_extension(&mut self, extension: &TypeExtension) {
+        match extension {
+            TypeExtension::Scalar(scalar) => self.print_scalar(scalar, true),
+            TypeExtension::Object(object) => self.print_object_type(object, true),
+            TypeExtension::Interface(interface) => self.print_interface(interface, true),
+            TypeExtension::Union(union) => self.print_union(union, true),
+            TypeExtension::Enum(enum_def) => self.print_enum(enum_def, true),
+            TypeExtension::InputObject(input) => self.print_input_object(input, true),
+        }
+    }
+
+    fn print_type_header(
+        &mut self,
+        description: Option<Spur>,
+        keyword: &str,
+        name: Spur,
+        is_extension: bool,
+    ) {
+        self.print_description(description);
+
+        if is_extension {
+            self.buffer.push_str("extend ");
+        }
+
+        write!(self.buffer, "{} {}", keyword, self.name(name)).unwrap();
+    }
+
+    fn print_scalar(&mut self, scalar: &Scalar, is_extension: bool) {
+        self.print_type_header(scalar.description, "scalar", scalar.name, is_extension);
+        self.print_directives(&scalar.directives);
+        self.buffer.push('\n');
+    }
+
+    fn print_object_type(&mut self, object: &ObjectType, is_extension: bool) {
+        self.print_type_header(object.description, "type", object.name, is_extension);
+
+        if !object.implements.is_empty() {
+            self.buffer.push_str(" implements ");
+            self.print_named_types(&object.implements, " & ");
+        }
+
+        self.print_directives(&object.directives);
+
+        if let Some(fields) = &object.fields {
+            self.print_field_definitions(fields);
+        }
+
+        self.buffer.push('\n');
+    }
+
+    fn print_interface(&mut self, interface: &Interface, is_extension: bool) {
+        self.print_type_header(
+            interface.description,
+            "interface",
+            interface.name,
+            is_extension,
+        );
+
+        if !interface.implements.is_empty() {
+            self.buffer.push_str(" implements ");
+            self.print_named_types(&interface.implements, " & ");
+        }
+
+        self.print_directives(&interface.directives);
+
+        if !interface.fields.is_empty() {
+            self.print_field_definitions(&interface.fields);
+        }
+
+        self.buffer.push('\n');
+    }
+
+    fn print_union(&mut self, union: &Union, is_extension: bool) {
+        self.print_type_header(union.description, "union", union.name, is_extension);
+        self.print_directives(&union.directives);
+
+        if !union.types.is_empty() {
+            self.buffer.push_str(" = ");
+            self.print_named_types(&union.types, " | ");
+        }
+
+        self.buffer.push('\n');
+    }
+
+    fn print_enum(&mut self, enum_def: &Enum, is_extension: bool) {
+        self.print_type_header(enum_def.description, "enum", enum_def.name, is_extension);
+        self.print_directives(&enum_def.directives);
+
+        if !enum_def.variants.is_empty() {
+            self.open_block();
+
+            for variant in &enum_def.variants {
+                self.print_enum_variant(variant);
+            }
+
+            self.close_block();
+        }
+
+        self.buffer.push('\n');
+    }
+
+    fn print_enum_variant(&mut self, variant: &EnumVariant) {
+        self.print_description(variant.description);
+        self.write_indent();
+        self.buffer.push_str(self.name(variant.name));
+        self.print_directives(&variant.directives);
+        self.buffer.push('\n');
+    }
+
+    fn print_input_object(&mut self, input: &InputObject, is_extension: bool) {
+        self.print_type_header(input.description, "input", input.name, is_extension);
+        self.print_directives(&input.directives);
+
+        if let Some(fields) = &input.fields {
+            self.open_block();
+
+            for field in fields {
+                self.print_description(field.description);
+                self.write_indent();
+                self.print_input_value_definition(field);
+                self.buffer.push('\n');
+            }
+
+            self.close_block();
+        }
+
+        self.buffer.push('\n');
+    }
+
+    fn print_field_definitions(&mut self, fields: &[FieldDefinition]) {
+        self.open_block();
+
+        for field in fields {
+            self.print_description(field.description);
+            self.write_indent();
+            self.buffer.push_str(self.name(field.name));
+
+            if let Some(arguments) = &field.arguments {
+                self.print_argument_definitions(arguments);
+            }
+
+            self.buffer.push_str(": ");
+            self.print_type(&field.ty);
+            self.print_directives(&field.directives);
+            self.buffer.push('\n');
+        }
+
+        self.close_block();
+    }
+
+    /// Arguments are printed inline unless one of them has a description, in
+    /// which case each gets its own line
+    fn print_argument_definitions(&mut self, arguments: &[InputObjectField]) {
+        if arguments
+            .iter()
+            .all(|argument| argument.description.is_none())
+        {
+            self.buffer.push('(');
+
+            for (idx, argument) in arguments.iter().enumerate() {
+                if idx != 0 {
+                    self.buffer.push_str(", ");
+                }
+
+                self.print_input_value_definition(argument);
+            }
+
+            self.buffer.push(')');
+
+            return;
+        }
+
+        self.buffer.push_str("(\n");
+        self.depth += 1;
+
+        for argument in arguments {
+            self.print_description(argument.description);
+            self.write_indent();
+            self.print_input_value_definition(argument);
+            self.buffer.push('\n');
+        }
+
+        self.depth -= 1;
+        self.write_indent();
+        self.buffer.push(')');
+    }
+
+    fn print_input_value_definition(&mut self, field: &InputObjectField) {
+        write!(self.buffer, "{}: ", self.name(field.name)).unwrap();
+        self.print_type(&field.ty);
+
+        if let Some(default) = &field.default {
+            self.buffer.push_str(" = ");
+            self.print_value(default);
+        }
+
+        self.print_directives(&field.directives);
+    }
+
+    /// Queries without a name, variables or directives are printed in the
+    /// shorthand form, unless something was printed before them. After a
+    /// definition without a body, such as `scalar Date`, the selection set
+    /// would be read as that definition's body
+    pub fn print_operation(&mut self, operation: &Operation) {
+        let is_shorthand = operation.kind == OperationKind::Query
+            && operation.name.is_none()
+            && operation.variable_definitions.is_empty()
+            && operation.directives.is_empty()
+            && self.buffer.is_empty();
+
+        if is_shorthand {
+            self.buffer.push('{');
+            self.depth += 1;
+            self.buffer.push('\n');
+            self.print_selections(&operation.selection_set);
+            self.close_block();
+            self.buffer.push('\n');
+            return;
+        }
+
+        self.buffer.push_str(operation.kind.as_str());
+
+        if let Some(name) = operation.name {
+            write!(self.buffer, " {}", self.name(name)).unwrap();
+        }
+
+        if !operation.variable_definitions.is_empty() {
+            self.print_variable_definitions(&operation.variable_definitions);
+        }
+
+        self.print_directives(&operation.directives);
+        self.print_selection_set(&operation.selection_set);
+        self.buffer.push('\n');
+    }
+
+    fn print_variable_definitions(&mut self, variable_definitions: &[VariableDefinition]) {
+        self.buffer.push('(');
+
+        for (idx, variable) in variable_definitions.iter().enumerate() {
+            if idx != 0 {
+                self.buffer.push_str(", ");
+            }
+
+            write!(self.buffer, "${}: ", self.name(variable.name)).unwrap();
+            self.print_type(&variable.ty);
+
+            if let Some(default) = &variable.default {
+                self.buffer.push_str(" = ");
+                self.print_value(default);
+            }
+        }
+
+        self.buffer.push(')');
+    }
+
+    pub fn print_fragment(&mut self, fragment: &Fragment) {
+        write!(
+            self.buffer,
+            "fragment {} on {}",
+            self.name(fragment.name),
+            self.name(fragment.on)
+        )
+        .unwrap();
+        self.print_directives(&fragment.directives);
+        self.print_selection_set(&fragment.selection_set);
+        self.buffer.push('\n');
+    }
+
+    pub fn print_selection_set(&mut self, selection_set: &[Selection]) {
+        self.open_block();
+        self.print_selections(selection_set);
+        self.close_block();
+    }
+
+    fn print_selections(&mut self, selection_set: &[Selection]) {
+        for selection in selection_set {
+            self.write_indent();
+
+            match selection {
+                Selection::Field {
+                    alias,
+                    name,
+                    arguments,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    if let Some(alias) = alias {
+                        write!(self.buffer, "{}: ", self.name(*alias)).unwrap();
+                    }
+
+                    self.buffer.push_str(self.name(*name));
+
+                    if let Some(arguments) = arguments {
+                        self.print_arguments(arguments);
+                    }
+
+                    self.print_directives(directives);
+
+                    if let Some(selection_set) = selection_set {
+                        self.print_selection_set(selection_set);
+                    }
+                }
+                Selection::FragmentSpread {
+                    name, directives, ..
+                } => {
+                    write!(self.buffer, "...{}", self.name(*name)).unwrap();
+                    self.print_directives(directives);
+                }
+                Selection::InlineFragment {
+                    on,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    self.buffer.push_str("...");
+                    if let Some(on) = on {
+                        write!(self.buffer, " on {}", self.name(*on)).unwrap();
+                    }
+                    self.print_directives(directives);
+                    self.print_selection_set(selection_set);
+                }
+            }
+
+            self.buffer.push('\n');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{print_document, PrintOptions, Printer};
+    use crate::{
+        ast::{Type, Value, VariableDefinition},
+        GraphqlParser,
+    };
+
+    const SOURCE: &str = r#""""
+The schema
+"""
+schema @a(b: 1) { query: Query mutation: Mutation }
+extend schema { subscription: Subscription }
+"A directive" directive @a(b: Int = 1 "arg" c: [String!]!)
+  repeatable on FIELD_DEFINITION | SCHEMA | OBJECT
+scalar Date @specifiedBy(url: "https://example.com")
+type Query implements Node & Entity @a { "field" id: ID! old: String @deprecated(reason: "no")
+  list(first: Int = 10, after: String): [[Item!]]! }
+interface Node { id: ID! }
+interface Entity implements Node { id: ID! }
+union Item = Query | Other
+enum Color { RED @deprecated GREEN "blue" BLUE }
+input Filter { color: Color = RED, nested: Filter, tags: [String!] = ["a", "b"],
+  obj: Obj = { a: 1, b: { c: null } } }
+extend type Query { extra: Float }
+extend union Item = Third
+extend enum Color { ALPHA }
+extend input Filter { more: Boolean }
+extend interface Node @a
+extend scalar Date @a
+query Q($id: ID! = "x", $f: Filter, $list: [[Int!]]! = [[1, -2.5e3]]) @a(b: 2) {
+  alias: item(id: $id, f: { color: RED, tags: [] }) @include(if: true) { ...F ... on Query { id } }
+  s: str(a: "esc\"aped \\ é", b: """block "string" with \""" """) }
+mutation { do(x: $v) }
+subscription S { tick }
+fragment F on Query @a { id }
+"#;
+
+    const PRINTED: &str = r#""""The schema"""
+schema @a(b: 1) {
+  query: Query
+  mutation: Mutation
+}
+
+"""A directive"""
+directive @a(
+  b: Int = 1
+  """arg"""
+  c: [String!]!
+) repeatable on FIELD_DEFINITION | SCHEMA | OBJECT
+
+scalar Date @specifiedBy(url: "https://example.com")
+
+interface Entity implements Node {
+  id: ID!
+}
+
+interface Node {
+  id: ID!
+}
+
+type Query implements Node & Entity @a {
+  """field"""
+  id: ID!
+  old: String @deprecated(reason: "no")
+  list(first: Int = 10, after: String): [[Item!]]!
+}
+
+union Item = Query | Other
+
+enum Color {
+  RED @deprecated
+  GREEN
+  """blue"""
+  BLUE
+}
+
+input Filter {
+  color: Color = RED
+  nested: Filter
+  tags: [String!] = ["a", "b"]
+  obj: Obj = {a: 1, b: {c: null}}
+}
+
+extend schema {
+  subscription: Subscription
+}
+
+extend type Query {
+  extra: Float
+}
+
+extend union Item = Third
+
+extend enum Color {
+  ALPHA
+}
+
+extend input Filter {
+  more: Boolean
+}
+
+extend interface Node @a
+
+extend scalar Date @a
+
+mutation {
+  do(x: $v)
+}
+
+query Q($id: ID! = "x", $f: Filter, $list: [[Int!]]! = [[1, -2500.0]]) @a(b: 2) {
+  alias: item(id: $id, f: {color: RED, tags: []}) @include(if: true) {
+    ...F
+    ... on Query {
+      id
+    }
+  }
+  s: str(a: "esc\"aped \\ é", b: "block \"string\" with \"\"\" ")
+}
+
+subscription S {
+  tick
+}
+
+fragment F on Query @a {
+  id
+}
+"#;
+
+    fn print(source: &str) -> String {
+        let mut interner = Rodeo::default();
+        let document =
+            GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner).unwrap();
+
+        print_document(&document, &interner)
+    }
+
+    fn print_type(ty: &Type, interner: &Rodeo) -> String {
+        let options = PrintOptions::default();
+        let mut printer = Printer::new(interner, &options);
+        printer.print_type(ty);
+        printer.finish()
+    }
+
+    fn print_value(value: &Value, interner: &Rodeo) -> String {
+        let options = PrintOptions::default();
+        let mut printer = Printer::new(interner, &options);
+        printer.print_value(value);
+        printer.finish()
+    }
+
+    /// The variable definitions of the only operation in `source`
+    fn variables(source: &str, interner: &mut Rodeo) -> Vec<VariableDefinition> {
+        let mut document = GraphqlParser::parse_with_interner(source.as_bytes(), interner).unwrap();
+        let (_, operation) = document.operations.drain().next().unwrap();
+
+        operation.variable_definitions
+    }
+
+    #[test]
+    fn inline_fragments_without_type_condition() {
+        assert_eq!(
+            print("{ a ... { b } ... @c { d } }"),
+            "{\n  a\n  ... {\n    b\n  }\n  ... @c {\n    d\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn shorthand_only_first() {
+        assert_eq!(print("{ a }"), "{\n  a\n}\n");
+        // `scalar S { a }` would be read as a scalar with a body
+        assert_eq!(print("scalar S { a }"), "scalar S\n\nquery {\n  a\n}\n");
+    }
+
+    #[test]
+    fn prints_every_definition() {
+        assert_eq!(print(SOURCE), PRINTED);
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(print(PRINTED), PRINTED);
+    }
+
+    /// The description of the only scalar in `source`
+    fn description(source: &str) -> Option<String> {
+        let mut interner = Rodeo::default();
+        let document =
+            GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner).unwrap();
+        let scalar = document.scalars.values().next().unwrap();
+
+        scalar
+            .description
+            .map(|description| interner.resolve(&description).to_owned())
+    }
+
+    #[test]
+    fn round_trip_descriptions() {
+        for source in [
+            r#""""block""" scalar S"#,
+            r#""multi\nline \"quoted\"" scalar S"#,
+            r#""ends with a quote\"" scalar S"#,
+            r#""ends with a backslash\\" scalar S"#,
+            r#""\nleading newline" scalar S"#,
+            r#""trailing newline\n" scalar S"#,
+            r#""   " scalar S"#,
+            r#""" scalar S"#,
+            r#""  indented\n  lines" scalar S"#,
+            r#""carriage\r\nreturn" scalar S"#,
+        ] {
+            let printed = print(source);
+
+            assert_eq!(description(&printed), description(source), "{}", printed);
+        }
+    }
+
+    #[test]
+    fn round_trip_types() {
+        let mut interner = Rodeo::default();
+
+        for ty in [
+            "Int",
+            "Int!",
+            "[Int]",
+            "[Int!]",
+            "[Int]!",
+            "[Int!]!",
+            "[[Int!]]!",
+        ] {
+            let source = format!("query Q($a: {}) {{ a }}", ty);
+            let variable = &variables(&source, &mut interner)[0];
+
+            assert_eq!(print_type(&variable.ty, &interner), ty);
+        }
+    }
+
+    #[test]
+    fn non_null_types_round_trip() {
+        let printed = print("query Q($b: Int!, $c: [Int!], $e: [Int!]!, $f: [[Int!]]!) { a }");
+
+        assert!(
+            printed.contains("($b: Int!, $c: [Int!], $e: [Int!]!, $f: [[Int!]]!)"),
+            "{}",
+            printed
+        );
+    }
+
+    #[test]
+    fn round_trip_values() {
+        let mut interner = Rodeo::default();
+
+        for value in [
+            "1",
+            "-1.5",
+            "\"a \\\"b\\\" \\\\ \\n\"",
+            "true",
+            "null",
+            "ENUM",
+            "$var",
+            "[1, [2], []]",
+            "{a: 1, b: {c: [null]}}",
+        ] {
+            let source = format!("query Q($a: Int = {}) {{ a }}", value);
+            let variable = &variables(&source, &mut interner)[0];
+
+            assert_eq!(
+                print_value(variable.default.as_ref().unwrap(), &interner),
+                value
+            );
+        }
+    }
+}
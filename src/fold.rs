@@ -0,0 +1,365 @@
+//! Evaluation of `@skip` and `@include` ahead of execution. Given the values
+//! of an operation's variables, selections that won't be executed are removed
+//! and the conditions of the rest are dropped, leaving the effective query.
+//! Two requests that select the same fields fold to the same document, which
+//! makes the result suitable for normalizing persisted queries and for
+//! computing cache keys
+//!
+//! Conditions on variables that have no value and no default are left in
+//! place, so a document can be folded with only some of its variables
+
+use std::collections::{HashMap, HashSet};
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{Directive, Document, Operation, Selection, Value},
+    visit::{walk_document, walk_value, Visitor},
+};
+
+/// The effective form of `operation`, which is part of `document`, for the
+/// given `variables`. The result contains only the operation and the
+/// fragments it still spreads, and variables that are no longer used are
+/// removed from the operation's definitions
+///
+/// Selection sets can't be empty, so if every selection of one is removed,
+/// the first is kept, with its conditions replaced by their values
+pub fn fold_conditions(
+    document: &Document,
+    operation: &Operation,
+    variables: &HashMap<Spur, Value>,
+    interner: &Rodeo,
+) -> Document {
+    let folder = Folder {
+        operation,
+        variables,
+        interner,
+    };
+
+    let mut folded = document.clone();
+
+    for fragment in folded.fragments.values_mut() {
+        fragment.selection_set = folder.fold_selection_set(&fragment.selection_set);
+    }
+
+    let operation = Operation {
+        selection_set: folder.fold_selection_set(&operation.selection_set),
+        ..operation.clone()
+    };
+
+    let mut folded = folded.extract(&operation);
+
+    let mut usages = VariableUsages::default();
+    walk_document(&mut usages, &folded);
+
+    for operation in folded.operations.values_mut() {
+        operation
+            .variable_definitions
+            .retain(|variable| usages.0.contains(&variable.name));
+    }
+
+    folded
+}
+
+struct Folder<'a> {
+    operation: &'a Operation,
+    variables: &'a HashMap<Spur, Value>,
+    interner: &'a Rodeo,
+}
+
+impl Folder<'_> {
+    fn fold_selection_set(&self, selection_set: &[Selection]) -> Vec<Selection> {
+        let mut folded = Vec::new();
+        let mut first_excluded = None;
+
+        for selection in selection_set {
+            let (is_included, selection) = self.fold_selection(selection);
+
+            if is_included {
+                folded.push(selection);
+            } else if first_excluded.is_none() {
+                first_excluded = Some(selection);
+            }
+        }
+
+        if folded.is_empty() {
+            folded.extend(first_excluded);
+        }
+
+        folded
+    }
+
+    /// Whether `selection` is executed, along with its folded form. An
+    /// excluded selection keeps its conditions, with their values
+    /// substituted
+    fn fold_selection(&self, selection: &Selection) -> (bool, Selection) {
+        let (directives, is_included) = match selection {
+            Selection::Field { directives, .. }
+            | Selection::FragmentSpread { directives, .. }
+            | Selection::InlineFragment { directives, .. } => self.fold_directives(directives),
+        };
+
+        let selection = match selection {
+            Selection::Field {
+                alias,
+                name,
+                arguments,
+                selection_set,
+                span,
+                ..
+            } => Selection::Field {
+                alias: *alias,
+                name: *name,
+                arguments: arguments.clone(),
+                directives,
+                selection_set: selection_set
+                    .as_ref()
+                    .map(|selection_set| self.fold_selection_set(selection_set)),
+                span: *span,
+            },
+            Selection::FragmentSpread { name, span, .. } => Selection::FragmentSpread {
+                name: *name,
+                directives,
+                span: *span,
+            },
+            Selection::InlineFragment {
+                on,
+                selection_set,
+                span,
+                ..
+            } => Selection::InlineFragment {
+                on: *on,
+                directives,
+                selection_set: self.fold_selection_set(selection_set),
+                span: *span,
+            },
+        };
+
+        (is_included, selection)
+    }
+
+    /// The directives to keep, and whether the selection they're on is
+    /// executed
+    fn fold_directives(&self, directives: &[Directive]) -> (Vec<Directive>, bool) {
+        let mut kept = Vec::new();
+        let mut evaluated = Vec::new();
+
+        for directive in directives {
+            match self.evaluate(directive) {
+                Some(condition) => evaluated.push((directive, condition)),
+                None => kept.push(directive.clone()),
+            }
+        }
+
+        let is_included = evaluated.iter().all(|(_, condition)| condition.is_included);
+
+        if !is_included {
+            kept.extend(
+                evaluated
+                    .into_iter()
+                    .map(|(directive, condition)| condition.substitute(directive)),
+            );
+        }
+
+        (kept, is_included)
+    }
+
+    /// The value of the condition of a `@skip` or `@include` directive, or
+    /// `None` for any other directive or one whose condition isn't known
+    fn evaluate(&self, directive: &Directive) -> Option<Condition> {
+        let is_skip = match self.interner.resolve(&directive.name) {
+            "skip" => true,
+            "include" => false,
+            _ => return None,
+        };
+
+        let argument = directive
+            .arguments
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .find(|argument| self.interner.resolve(&argument.name) == "if")?;
+
+        let value = match &argument.value {
+            Value::Variable(name) => self.variable(*name)?,
+            value => value,
+        };
+
+        let value = match value {
+            Value::True => true,
+            Value::False => false,
+            _ => return None,
+        };
+
+        Some(Condition {
+            argument: argument.name,
+            value,
+            is_included: value != is_skip,
+        })
+    }
+
+    /// The value of the variable `name`, falling back to its default
+    fn variable(&self, name: Spur) -> Option<&Value> {
+        self.variables.get(&name).or_else(|| {
+            self.operation
+                .variable_definitions
+                .iter()
+                .find(|variable| variable.name == name)?
+                .default
+                .as_ref()
+        })
+    }
+}
+
+/// The value of the `if` argument of a `@skip` or `@include` directive
+struct Condition {
+    argument: Spur,
+    value: bool,
+    is_included: bool,
+}
+
+impl Condition {
+    /// `directive`, with the value of its condition as a literal
+    fn substitute(&self, directive: &Directive) -> Directive {
+        let mut directive = directive.clone();
+
+        for argument in directive.arguments.iter_mut().flatten() {
+            if argument.name == self.argument {
+                argument.value = if self.value {
+                    Value::True
+                } else {
+                    Value::False
+                };
+            }
+        }
+
+        directive
+    }
+}
+
+/// The variables referenced anywhere in a document
+#[derive(Default)]
+struct VariableUsages(HashSet<Spur>);
+
+impl<'a> Visitor<'a> for VariableUsages {
+    fn visit_value(&mut self, value: &'a Value) {
+        if let Value::Variable(name) = value {
+            self.0.insert(*name);
+        }
+
+        walk_value(self, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lasso::Rodeo;
+
+    use super::fold_conditions;
+    use crate::{ast::Value, fmt::print_minified, GraphqlParser};
+
+    /// The single operation of `source`, folded with the variables
+    /// `variables` and printed
+    fn fold(source: &str, variables: &[(&str, Value)]) -> String {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse operation");
+
+        let variables = variables
+            .iter()
+            .map(|(name, value)| (interner.get_or_intern(name), value.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let operation = document.operations.values().next().unwrap();
+
+        print_minified(
+            &fold_conditions(&document, operation, &variables, &interner),
+            &interner,
+        )
+    }
+
+    #[test]
+    fn literal_conditions() {
+        assert_eq!(
+            fold(
+                "{ a @skip(if: true) b @skip(if: false) c @include(if: true) }",
+                &[]
+            ),
+            "{b c}"
+        );
+    }
+
+    #[test]
+    fn variable_conditions() {
+        let source = "query Q($a: Boolean!) { x @include(if: $a) y @skip(if: $a) }";
+
+        assert_eq!(fold(source, &[("a", Value::True)]), "query Q{x}");
+        assert_eq!(fold(source, &[("a", Value::False)]), "query Q{y}");
+    }
+
+    #[test]
+    fn unknown_variables_keep_their_conditions() {
+        assert_eq!(
+            fold(
+                "query Q($a: Boolean!, $b: Boolean!) { x @include(if: $a) @skip(if: $b) y }",
+                &[("b", Value::False)]
+            ),
+            "query Q($a:Boolean!){x@include(if:$a)y}"
+        );
+    }
+
+    #[test]
+    fn default_values() {
+        assert_eq!(
+            fold("query Q($a: Boolean = false) { x @include(if: $a) y }", &[]),
+            "query Q{y}"
+        );
+        assert_eq!(
+            fold(
+                "query Q($a: Boolean = false) { x @include(if: $a) y }",
+                &[("a", Value::True)]
+            ),
+            "query Q{x y}"
+        );
+    }
+
+    #[test]
+    fn pruned_fragments_are_removed() {
+        assert_eq!(
+            fold(
+                concat!(
+                    "query Q($a: Boolean!) { user { id ...F @include(if: $a) } } ",
+                    "fragment F on User { name }",
+                ),
+                &[("a", Value::False)]
+            ),
+            "query Q{user{id}}"
+        );
+        assert_eq!(
+            fold(
+                concat!(
+                    "{ user { id ... on User @skip(if: false) { name } } ...G } ",
+                    "fragment G on Query { me @include(if: false) { id } other }",
+                ),
+                &[]
+            ),
+            "{user{id...on User{name}}...G}fragment G on Query{other}"
+        );
+    }
+
+    #[test]
+    fn empty_selection_sets_keep_a_selection() {
+        assert_eq!(
+            fold(
+                concat!(
+                    "query Q($a: Boolean!, $b: Int) ",
+                    "{ user { a @skip(if: $a) b(n: $b) @skip(if: $a) } }",
+                ),
+                &[("a", Value::True)]
+            ),
+            "query Q{user{a@skip(if:true)}}"
+        );
+    }
+}
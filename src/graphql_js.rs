@@ -0,0 +1,757 @@
+//! Conversion of a [`Document`] into the JSON form of the AST produced by
+//! graphql-js, for use with existing JavaScript tooling
+//!
+//! Nodes have no `loc`, as if parsed with `noLocation: true`. The order of
+//! definitions isn't kept, so they are emitted in the same order as
+//! [`print_document`](crate::fmt::print_document)
+//!
+//! See https://github.com/graphql/graphql-js/blob/main/src/language/ast.ts
+
+use std::collections::HashMap;
+
+use lasso::{Rodeo, Spur};
+use serde_json::{json, Map, Value as JsonValue};
+
+use crate::{
+    ast::{
+        Argument, Directive, DirectiveDefinition, Document, Enum, EnumVariant, FieldDefinition,
+        Fragment, InputObject, InputObjectField, Interface, NamedType, ObjectType, Operation,
+        RootOperationTypeDefinition, Scalar, SchemaDefinition, SchemaExtension, Selection, Type,
+        TypeExtension, Union, Value, VariableDefinition,
+    },
+    fmt::print_value,
+};
+
+/// The graphql-js `DocumentNode` for `document`
+///
+/// `interner` must be the interner the document was parsed with
+pub fn graphql_js_ast_from_document(document: &Document, interner: &Rodeo) -> JsonValue {
+    GraphqlJsWriter { interner }.document(document)
+}
+
+struct GraphqlJsWriter<'a> {
+    interner: &'a Rodeo,
+}
+
+/// Build a node of the given kind. `fields` must be a JSON object
+fn node(kind: &str, fields: JsonValue) -> JsonValue {
+    let mut node = Map::new();
+
+    node.insert("kind".to_owned(), json!(kind));
+
+    if let JsonValue::Object(fields) = fields {
+        node.extend(fields);
+    }
+
+    JsonValue::Object(node)
+}
+
+impl<'a> GraphqlJsWriter<'a> {
+    fn name_str(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn name(&self, name: Spur) -> JsonValue {
+        node("Name", json!({ "value": self.name_str(name) }))
+    }
+
+    fn sorted<'b, T>(&self, definitions: &'b HashMap<Spur, T>) -> Vec<&'b T> {
+        let mut definitions = definitions.iter().collect::<Vec<_>>();
+
+        definitions.sort_by_key(|(name, _)| self.name_str(**name));
+
+        definitions.into_iter().map(|(_, def)| def).collect()
+    }
+
+    fn document(&self, document: &Document) -> JsonValue {
+        let mut definitions = Vec::new();
+
+        if let Some(schema) = &document.schema {
+            definitions.push(self.schema_definition(schema));
+        }
+
+        for directive in self.sorted(&document.directive_definitions) {
+            definitions.push(self.directive_definition(directive));
+        }
+
+        for scalar in self.sorted(&document.scalars) {
+            definitions.push(self.scalar(scalar, false));
+        }
+
+        for interface in self.sorted(&document.interfaces) {
+            definitions.push(self.interface(interface, false));
+        }
+
+        for object in self.sorted(&document.output_objects) {
+            definitions.push(self.object_type(object, false));
+        }
+
+        for union in self.sorted(&document.unions) {
+            definitions.push(self.union(union, false));
+        }
+
+        for enum_def in self.sorted(&document.enums) {
+            definitions.push(self.enum_type(enum_def, false));
+        }
+
+        for input in self.sorted(&document.input_objects) {
+            definitions.push(self.input_object(input, false));
+        }
+
+        for extension in &document.schema_extensions {
+            definitions.push(self.schema_extension(extension));
+        }
+
+        for extension in &document.type_extensions {
+            definitions.push(self.type_extension(extension));
+        }
+
+        let mut operations = document.operations.values().collect::<Vec<_>>();
+
+        operations.sort_by_key(|operation| {
+            (
+                operation.name.map(|name| self.name_str(name)),
+                operation.kind as u8,
+            )
+        });
+
+        for operation in operations {
+            definitions.push(self.operation(operation));
+        }
+
+        for fragment in self.sorted(&document.fragments) {
+            definitions.push(self.fragment(fragment));
+        }
+
+        node("Document", json!({ "definitions": definitions }))
+    }
+
+    /// Insert the `description` field, which graphql-js leaves undefined if
+    /// there is no description
+    fn with_description(&self, description: Option<Spur>, mut node: JsonValue) -> JsonValue {
+        if let Some(description) = description {
+            node["description"] = self.string_value(self.name_str(description), true);
+        }
+
+        node
+    }
+
+    fn string_value(&self, value: &str, block: bool) -> JsonValue {
+        node("StringValue", json!({ "value": value, "block": block }))
+    }
+
+    fn schema_definition(&self, schema: &SchemaDefinition) -> JsonValue {
+        self.with_description(
+            schema.description,
+            node(
+                "SchemaDefinition",
+                json!({
+                    "directives": self.directives(&schema.directives),
+                    "operationTypes": self.operation_types(&schema.operation_types),
+                }),
+            ),
+        )
+    }
+
+    fn schema_extension(&self, extension: &SchemaExtension) -> JsonValue {
+        node(
+            "SchemaExtension",
+            json!({
+                "directives": self.directives(&extension.directives),
+                "operationTypes": self.operation_types(&extension.operation_types),
+            }),
+        )
+    }
+
+    fn operation_types(&self, operation_types: &[RootOperationTypeDefinition]) -> Vec<JsonValue> {
+        operation_types
+            .iter()
+            .map(|operation_type| {
+                node(
+                    "OperationTypeDefinition",
+                    json!({
+                        "operation": operation_type.kind.as_str(),
+                        "type": self.named_type(&operation_type.ty),
+                    }),
+                )
+            })
+            .collect()
+    }
+
+    fn directive_definition(&self, directive: &DirectiveDefinition) -> JsonValue {
+        let locations = directive
+            .locations
+            .iter()
+            .map(|location| node("Name", json!({ "value": location.as_str() })))
+            .collect::<Vec<_>>();
+
+        let arguments = self.input_values(directive.arguments.as_deref().unwrap_or_default());
+
+        self.with_description(
+            directive.description,
+            node(
+                "DirectiveDefinition",
+                json!({
+                    "name": self.name(directive.name),
+                    "arguments": arguments,
+                    "repeatable": directive.repeatable,
+                    "locations": locations,
+                }),
+            ),
+        )
+    }
+
+    /// The kind of a type definition or extension, such as
+    /// `ObjectTypeDefinition` or `ObjectTypeExtension`
+    fn type_kind(kind: &str, is_extension: bool) -> String {
+        if is_extension {
+            format!("{}TypeExtension", kind)
+        } else {
+            format!("{}TypeDefinition", kind)
+        }
+    }
+
+    fn scalar(&self, scalar: &Scalar, is_extension: bool) -> JsonValue {
+        self.with_description(
+            scalar.description,
+            node(
+                &Self::type_kind("Scalar", is_extension),
+                json!({
+                    "name": self.name(scalar.name),
+                    "directives": self.directives(&scalar.directives),
+                }),
+            ),
+        )
+    }
+
+    fn object_type(&self, object: &ObjectType, is_extension: bool) -> JsonValue {
+        self.with_description(
+            object.description,
+            node(
+                &Self::type_kind("Object", is_extension),
+                json!({
+                    "name": self.name(object.name),
+                    "interfaces": self.named_types(&object.implements),
+                    "directives": self.directives(&object.directives),
+                    "fields": self.fields(object.fields.as_deref().unwrap_or_default()),
+                }),
+            ),
+        )
+    }
+
+    fn interface(&self, interface: &Interface, is_extension: bool) -> JsonValue {
+        self.with_description(
+            interface.description,
+            node(
+                &Self::type_kind("Interface", is_extension),
+                json!({
+                    "name": self.name(interface.name),
+                    "interfaces": self.named_types(&interface.implements),
+                    "directives": self.directives(&interface.directives),
+                    "fields": self.fields(&interface.fields),
+                }),
+            ),
+        )
+    }
+
+    fn union(&self, union: &Union, is_extension: bool) -> JsonValue {
+        self.with_description(
+            union.description,
+            node(
+                &Self::type_kind("Union", is_extension),
+                json!({
+                    "name": self.name(union.name),
+                    "directives": self.directives(&union.directives),
+                    "types": self.named_types(&union.types),
+                }),
+            ),
+        )
+    }
+
+    fn enum_type(&self, enum_def: &Enum, is_extension: bool) -> JsonValue {
+        let values = enum_def
+            .variants
+            .iter()
+            .map(|variant| self.enum_value_definition(variant))
+            .collect::<Vec<_>>();
+
+        self.with_description(
+            enum_def.description,
+            node(
+                &Self::type_kind("Enum", is_extension),
+                json!({
+                    "name": self.name(enum_def.name),
+                    "directives": self.directives(&enum_def.directives),
+                    "values": values,
+                }),
+            ),
+        )
+    }
+
+    fn enum_value_definition(&self, variant: &EnumVariant) -> JsonValue {
+        self.with_description(
+            variant.description,
+            node(
+                "EnumValueDefinition",
+                json!({
+                    "name": self.name(variant.name),
+                    "directives": self.directives(&variant.directives),
+                }),
+            ),
+        )
+    }
+
+    fn input_object(&self, input: &InputObject, is_extension: bool) -> JsonValue {
+        self.with_description(
+            input.description,
+            node(
+                &Self::type_kind("InputObject", is_extension),
+                json!({
+                    "name": self.name(input.name),
+                    "directives": self.directives(&input.directives),
+                    "fields": self.input_values(input.fields.as_deref().unwrap_or_default()),
+                }),
+            ),
+        )
+    }
+
+    fn type_extension(&self, extension: &TypeExtension) -> JsonValue {
+        match extension {
+            TypeExtension::Scalar(scalar) => self.scalar(scalar, true),
+            TypeExtension::Object(object) => self.object_type(object, true),
+            TypeExtension::Interface(interface) => self.interface(interface, true),
+            TypeExtension::Union(union) => self.union(union, true),
+            TypeExtension::Enum(enum_def) => self.enum_type(enum_def, true),
+            TypeExtension::InputObject(input) => self.input_object(input, true),
+        }
+    }
+
+    fn fields(&self, fields: &[FieldDefinition]) -> Vec<JsonValue> {
+        fields
+            .iter()
+            .map(|field| {
+                let arguments = self.input_values(field.arguments.as_deref().unwrap_or_default());
+
+                self.with_description(
+                    field.description,
+                    node(
+                        "FieldDefinition",
+                        json!({
+                            "name": self.name(field.name),
+                            "arguments": arguments,
+                            "type": self.type_ref(&field.ty),
+                            "directives": self.directives(&field.directives),
+                        }),
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn input_values(&self, input_values: &[InputObjectField]) -> Vec<JsonValue> {
+        input_values
+            .iter()
+            .map(|input_value| {
+                let mut definition = node(
+                    "InputValueDefinition",
+                    json!({
+                        "name": self.name(input_value.name),
+                        "type": self.type_ref(&input_value.ty),
+                        "directives": self.directives(&input_value.directives),
+                    }),
+                );
+
+                if let Some(default) = &input_value.default {
+                    definition["defaultValue"] = self.value(default);
+                }
+
+                self.with_description(input_value.description, definition)
+            })
+            .collect()
+    }
+
+    fn operation(&self, operation: &Operation) -> JsonValue {
+        let variable_definitions = operation
+            .variable_definitions
+            .iter()
+            .map(|variable| self.variable_definition(variable))
+            .collect::<Vec<_>>();
+
+        let mut node = node(
+            "OperationDefinition",
+            json!({
+                "operation": operation.kind.as_str(),
+                "variableDefinitions": variable_definitions,
+                "directives": self.directives(&operation.directives),
+                "selectionSet": self.selection_set(&operation.selection_set),
+            }),
+        );
+
+        if let Some(name) = operation.name {
+            node["name"] = self.name(name);
+        }
+
+        node
+    }
+
+    fn variable_definition(&self, variable: &VariableDefinition) -> JsonValue {
+        let mut node = node(
+            "VariableDefinition",
+            json!({
+                "variable": self.variable(variable.name),
+                "type": self.type_ref(&variable.ty),
+                "directives": [],
+            }),
+        );
+
+        if let Some(default) = &variable.default {
+            node["defaultValue"] = self.value(default);
+        }
+
+        node
+    }
+
+    fn fragment(&self, fragment: &Fragment) -> JsonValue {
+        node(
+            "FragmentDefinition",
+            json!({
+                "name": self.name(fragment.name),
+                "typeCondition": self.named_type(&NamedType(fragment.on)),
+                "directives": self.directives(&fragment.directives),
+                "selectionSet": self.selection_set(&fragment.selection_set),
+            }),
+        )
+    }
+
+    fn selection_set(&self, selection_set: &[Selection]) -> JsonValue {
+        let selections = selection_set
+            .iter()
+            .map(|selection| self.selection(selection))
+            .collect::<Vec<_>>();
+
+        node("SelectionSet", json!({ "selections": selections }))
+    }
+
+    fn selection(&self, selection: &Selection) -> JsonValue {
+        match selection {
+            Selection::Field {
+                alias,
+                name,
+                arguments,
+                directives,
+                selection_set,
+                ..
+            } => {
+                let mut node = node(
+                    "Field",
+                    json!({
+                        "name": self.name(*name),
+                        "arguments": self.arguments(arguments.as_deref().unwrap_or_default()),
+                        "directives": self.directives(directives),
+                    }),
+                );
+
+                if let Some(alias) = alias {
+                    node["alias"] = self.name(*alias);
+                }
+
+                if let Some(selection_set) = selection_set {
+                    node["selectionSet"] = self.selection_set(selection_set);
+                }
+
+                node
+            }
+            Selection::FragmentSpread {
+                name, directives, ..
+            } => node(
+                "FragmentSpread",
+                json!({
+                    "name": self.name(*name),
+                    "directives": self.directives(directives),
+                }),
+            ),
+            Selection::InlineFragment {
+                on,
+                directives,
+                selection_set,
+                ..
+            } => node(
+                "InlineFragment",
+                json!({
+                    "typeCondition": on.map(|on| self.named_type(&NamedType(on))),
+                    "directives": self.directives(directives),
+                    "selectionSet": self.selection_set(selection_set),
+                }),
+            ),
+        }
+    }
+
+    fn directives(&self, directives: &[Directive]) -> Vec<JsonValue> {
+        directives
+            .iter()
+            .map(|directive| {
+                let arguments = self.arguments(directive.arguments.as_deref().unwrap_or_default());
+
+                node(
+                    "Directive",
+                    json!({
+                        "name": self.name(directive.name),
+                        "arguments": arguments,
+                    }),
+                )
+            })
+            .collect()
+    }
+
+    fn arguments(&self, arguments: &[Argument]) -> Vec<JsonValue> {
+        arguments
+            .iter()
+            .map(|argument| {
+                node(
+                    "Argument",
+                    json!({
+                        "name": self.name(argument.name),
+                        "value": self.value(&argument.value),
+                    }),
+                )
+            })
+            .collect()
+    }
+
+    fn variable(&self, name: Spur) -> JsonValue {
+        node("Variable", json!({ "name": self.name(name) }))
+    }
+
+    fn value(&self, value: &Value) -> JsonValue {
+        match value {
+            Value::True => node("BooleanValue", json!({ "value": true })),
+            Value::False => node("BooleanValue", json!({ "value": false })),
+            Value::Null => node("NullValue", json!({})),
+            Value::String(s) => self.string_value(self.name_str(*s), false),
+            Value::Variable(name) => self.variable(*name),
+            Value::EnumVariant(name) => node("EnumValue", json!({ "value": self.name_str(*name) })),
+            // graphql-js keeps numbers as their source text
+            Value::Int(..) => node(
+                "IntValue",
+                json!({ "value": print_value(value, self.interner) }),
+            ),
+            Value::Float(..) => node(
+                "FloatValue",
+                json!({ "value": print_value(value, self.interner) }),
+            ),
+            Value::List(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| self.value(value))
+                    .collect::<Vec<_>>();
+
+                node("ListValue", json!({ "values": values }))
+            }
+            Value::Object(fields) => {
+                let mut fields = fields.iter().collect::<Vec<_>>();
+
+                fields.sort_by_key(|(name, _)| self.name_str(**name));
+
+                let fields = fields
+                    .into_iter()
+                    .map(|(name, value)| {
+                        node(
+                            "ObjectField",
+                            json!({
+                                "name": self.name(*name),
+                                "value": self.value(value),
+                            }),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                node("ObjectValue", json!({ "fields": fields }))
+            }
+        }
+    }
+
+    fn named_type(&self, ty: &NamedType) -> JsonValue {
+        node("NamedType", json!({ "name": self.name(ty.0) }))
+    }
+
+    fn named_types(&self, types: &[NamedType]) -> Vec<JsonValue> {
+        types.iter().map(|ty| self.named_type(ty)).collect()
+    }
+
+    fn type_ref(&self, ty: &Type) -> JsonValue {
+        let (inner, nullable) = match ty {
+            Type::Named { name, nullable } => (self.named_type(&NamedType(*name)), *nullable),
+            Type::List { base, nullable } => (
+                node("ListType", json!({ "type": self.type_ref(base) })),
+                *nullable,
+            ),
+        };
+
+        if nullable {
+            inner
+        } else {
+            node("NonNullType", json!({ "type": inner }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+    use serde_json::json;
+
+    use super::graphql_js_ast_from_document;
+    use crate::GraphqlParser;
+
+    fn ast(source: &str) -> serde_json::Value {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse document");
+
+        graphql_js_ast_from_document(&document, &interner)
+    }
+
+    fn name(value: &str) -> serde_json::Value {
+        json!({ "kind": "Name", "value": value })
+    }
+
+    fn named_type(value: &str) -> serde_json::Value {
+        json!({ "kind": "NamedType", "name": name(value) })
+    }
+
+    fn selection_set(selections: serde_json::Value) -> serde_json::Value {
+        json!({ "kind": "SelectionSet", "selections": selections })
+    }
+
+    #[test]
+    fn operation() {
+        let ast = ast("query Q($a: [Int!]! = [1]) { x: f(a: $a) { ...F ... on T { g } } }");
+
+        assert_eq!(
+            ast,
+            json!({
+                "kind": "Document",
+                "definitions": [{
+                    "kind": "OperationDefinition",
+                    "operation": "query",
+                    "name": name("Q"),
+                    "variableDefinitions": [{
+                        "kind": "VariableDefinition",
+                        "variable": { "kind": "Variable", "name": name("a") },
+                        "type": {
+                            "kind": "NonNullType",
+                            "type": {
+                                "kind": "ListType",
+                                "type": { "kind": "NonNullType", "type": named_type("Int") },
+                            },
+                        },
+                        "defaultValue": {
+                            "kind": "ListValue",
+                            "values": [{ "kind": "IntValue", "value": "1" }],
+                        },
+                        "directives": [],
+                    }],
+                    "directives": [],
+                    "selectionSet": selection_set(json!([{
+                        "kind": "Field",
+                        "alias": name("x"),
+                        "name": name("f"),
+                        "arguments": [{
+                            "kind": "Argument",
+                            "name": name("a"),
+                            "value": { "kind": "Variable", "name": name("a") },
+                        }],
+                        "directives": [],
+                        "selectionSet": selection_set(json!([
+                            { "kind": "FragmentSpread", "name": name("F"), "directives": [] },
+                            {
+                                "kind": "InlineFragment",
+                                "typeCondition": named_type("T"),
+                                "directives": [],
+                                "selectionSet": selection_set(json!([{
+                                    "kind": "Field",
+                                    "name": name("g"),
+                                    "arguments": [],
+                                    "directives": [],
+                                }])),
+                            },
+                        ])),
+                    }])),
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn values() {
+        let ast = ast("{ f(a: { c: RED, d: \"s\", e: null, f: 1.5, g: true, h: [] }) }");
+
+        let fields = &ast["definitions"][0]["selectionSet"]["selections"][0]["arguments"][0]
+            ["value"]["fields"];
+
+        let values = fields
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| {
+                (
+                    field["name"]["value"].as_str().unwrap(),
+                    field["value"].clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            values,
+            [
+                ("c", json!({ "kind": "EnumValue", "value": "RED" })),
+                (
+                    "d",
+                    json!({ "kind": "StringValue", "value": "s", "block": false })
+                ),
+                ("e", json!({ "kind": "NullValue" })),
+                ("f", json!({ "kind": "FloatValue", "value": "1.5" })),
+                ("g", json!({ "kind": "BooleanValue", "value": true })),
+                ("h", json!({ "kind": "ListValue", "values": [] })),
+            ]
+        );
+    }
+
+    #[test]
+    fn definition_kinds() {
+        let ast = ast(concat!(
+            "schema { query: T } directive @k(a: Int) repeatable on OBJECT scalar S ",
+            "interface I { f: Int } type T implements I @k { f(a: Int = 1): [T]! } union U = T ",
+            "enum E { A } input In { a: Int } extend type T { g: Int } ",
+            "extend schema @k fragment F on T { f }",
+        ));
+
+        let kinds = ast["definitions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|definition| definition["kind"].as_str().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            kinds,
+            [
+                "SchemaDefinition",
+                "DirectiveDefinition",
+                "ScalarTypeDefinition",
+                "InterfaceTypeDefinition",
+                "ObjectTypeDefinition",
+                "UnionTypeDefinition",
+                "EnumTypeDefinition",
+                "InputObjectTypeDefinition",
+                "SchemaExtension",
+                "ObjectTypeExtension",
+                "FragmentDefinition",
+            ]
+        );
+
+        let directive = &ast["definitions"][1];
+        assert_eq!(directive["repeatable"], true);
+        assert_eq!(directive["locations"], json!([name("OBJECT")]));
+    }
+}
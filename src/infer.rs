@@ -0,0 +1,333 @@
+//! Resolution of the schema types of the fields selected by executable
+//! documents. [`infer_types`] resolves every field of a document once, into a
+//! side table that codegen, hover information and response validation can
+//! look types up in, without deriving them again
+//!
+//! Fields are identified by the operation or fragment they're in and the
+//! index of each selection on the way to them, so that fields spread in many
+//! places are resolved once, against the type condition of their fragment
+
+use std::collections::HashMap;
+
+use lasso::{Rodeo, Spur};
+
+use crate::ast::{Document, Operation, OperationKind, Selection, Type};
+
+/// The operation or fragment a selection is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Root {
+    Operation {
+        name: Option<Spur>,
+        kind: OperationKind,
+    },
+    Fragment(Spur),
+}
+
+/// A selection of an operation or fragment: the index of each selection on
+/// the way to it, starting from the root's selection set and descending
+/// through fields and inline fragments
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SelectionPath {
+    pub root: Root,
+    pub indices: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldType {
+    /// The type the field is selected on
+    pub parent: Spur,
+    /// The type of the field, as declared by the schema
+    pub ty: Type,
+}
+
+/// The types of the fields selected by a document
+#[derive(Debug, Clone, Default)]
+pub struct SelectionTypes {
+    fields: HashMap<SelectionPath, FieldType>,
+}
+
+impl SelectionTypes {
+    /// The type of the field at `path`, or `None` if the selection isn't a
+    /// field the schema defines
+    pub fn get(&self, path: &SelectionPath) -> Option<&FieldType> {
+        self.fields.get(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&SelectionPath, &FieldType)> {
+        self.fields.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+/// Resolve the type of every field selected by the operations and fragments
+/// of `document` through `schema`. Fields the schema doesn't define are left
+/// out, along with everything selected below them
+pub fn infer_types(schema: &Document, document: &Document, interner: &Rodeo) -> SelectionTypes {
+    let mut inferrer = Inferrer {
+        schema,
+        interner,
+        types: SelectionTypes::default(),
+        indices: Vec::new(),
+    };
+
+    for operation in document.operations.values() {
+        let root = Root::Operation {
+            name: operation.name,
+            kind: operation.kind,
+        };
+
+        if let Some(ty) = schema.root_operation_type_or_default(operation.kind, interner) {
+            inferrer.infer_selection_set(root, &operation.selection_set, ty);
+        }
+    }
+
+    for fragment in document.fragments.values() {
+        inferrer.infer_selection_set(
+            Root::Fragment(fragment.name),
+            &fragment.selection_set,
+            fragment.on,
+        );
+    }
+
+    inferrer.types
+}
+
+struct Inferrer<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+    types: SelectionTypes,
+    indices: Vec<usize>,
+}
+
+impl Inferrer<'_> {
+    fn infer_selection_set(&mut self, root: Root, selection_set: &[Selection], parent: Spur) {
+        for (idx, selection) in selection_set.iter().enumerate() {
+            self.indices.push(idx);
+
+            match selection {
+                Selection::Field {
+                    name,
+                    selection_set,
+                    ..
+                } => {
+                    if let Some(ty) = field_type(self.schema, parent, *name, self.interner) {
+                        if let Some(selection_set) = selection_set {
+                            self.infer_selection_set(root, selection_set, ty.name());
+                        }
+
+                        self.types.fields.insert(
+                            SelectionPath {
+                                root,
+                                indices: self.indices.clone(),
+                            },
+                            FieldType { parent, ty },
+                        );
+                    }
+                }
+                Selection::InlineFragment {
+                    on, selection_set, ..
+                } => self.infer_selection_set(root, selection_set, on.unwrap_or(parent)),
+                Selection::FragmentSpread { .. } => {}
+            }
+
+            self.indices.pop();
+        }
+    }
+}
+
+/// The type of the field `name` of `parent`, including `__typename`
+fn field_type(schema: &Document, parent: Spur, name: Spur, interner: &Rodeo) -> Option<Type> {
+    if interner.resolve(&name) == "__typename" {
+        return Some(Type::Named {
+            name: interner.get("String")?,
+            nullable: false,
+        });
+    }
+
+    schema
+        .fields_of(parent)?
+        .iter()
+        .find(|field| field.name == name)
+        .map(|field| field.ty.clone())
+}
+
+/// The type of the field that `operation`, which is part of `document`,
+/// selects at the response path `path`, such as `["user", "friends", "name"]`.
+/// Fields are found by their response key, through fragment spreads and
+/// inline fragments, and the first field with each key is followed
+///
+/// Returns `None` if no field is selected at `path`, or the schema doesn't
+/// define it
+pub fn type_of_selection(
+    schema: &Document,
+    document: &Document,
+    operation: &Operation,
+    path: &[&str],
+    interner: &Rodeo,
+) -> Option<Type> {
+    let mut parent = schema.root_operation_type_or_default(operation.kind, interner)?;
+    let mut selection_set = operation.selection_set.as_slice();
+    let mut ty = None;
+
+    for key in path {
+        let (field_parent, name, subselections) = find_field(
+            document,
+            selection_set,
+            parent,
+            key,
+            &mut Vec::new(),
+            interner,
+        )?;
+
+        let field_ty = field_type(schema, field_parent, name, interner)?;
+
+        parent = field_ty.name();
+        selection_set = subselections;
+        ty = Some(field_ty);
+    }
+
+    ty
+}
+
+/// The first field of `selection_set`, which is on `parent`, with the
+/// response key `key`, along with the type it's selected on and its
+/// selection set. `spreads` holds the fragments being searched, so that
+/// fragments that spread themselves are only searched once
+fn find_field<'a>(
+    document: &'a Document,
+    selection_set: &'a [Selection],
+    parent: Spur,
+    key: &str,
+    spreads: &mut Vec<Spur>,
+    interner: &Rodeo,
+) -> Option<(Spur, Spur, &'a [Selection])> {
+    for selection in selection_set {
+        let found = match selection {
+            Selection::Field {
+                alias,
+                name,
+                selection_set,
+                ..
+            } => (interner.resolve(&alias.unwrap_or(*name)) == key)
+                .then(|| (parent, *name, selection_set.as_deref().unwrap_or_default())),
+            Selection::InlineFragment {
+                on, selection_set, ..
+            } => find_field(
+                document,
+                selection_set,
+                on.unwrap_or(parent),
+                key,
+                spreads,
+                interner,
+            ),
+            Selection::FragmentSpread { name, .. } => match document.fragments.get(name) {
+                Some(fragment) if !spreads.contains(name) => {
+                    spreads.push(*name);
+                    let found = find_field(
+                        document,
+                        &fragment.selection_set,
+                        fragment.on,
+                        key,
+                        spreads,
+                        interner,
+                    );
+                    spreads.pop();
+
+                    found
+                }
+                _ => None,
+            },
+        };
+
+        if found.is_some() {
+            return found;
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{infer_types, type_of_selection, Root, SelectionPath};
+    use crate::{ast::OperationKind, fmt::print_type, GraphqlParser};
+
+    const SCHEMA: &str = concat!(
+        "type Query { node(id: ID!): Node user: User } ",
+        "interface Node { id: ID! } ",
+        "type User implements Node { id: ID! name: String friends: [User!]! }",
+    );
+
+    const QUERY: &str = concat!(
+        "query Q { user { friends { first: name } ...F } node(id: 1) { ... on User { name } } } ",
+        "fragment F on User { id __typename unknown }",
+    );
+
+    #[test]
+    fn types_of_response_paths() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let document = GraphqlParser::parse_with_interner(QUERY.as_bytes(), &mut interner).unwrap();
+        let operation = document.operations.values().next().unwrap();
+
+        let ty = |path: &[&str]| {
+            type_of_selection(&schema, &document, operation, path, &interner)
+                .map(|ty| print_type(&ty, &interner))
+        };
+
+        assert_eq!(ty(&["user"]).as_deref(), Some("User"));
+        assert_eq!(ty(&["user", "friends"]).as_deref(), Some("[User!]!"));
+        assert_eq!(ty(&["user", "friends", "first"]).as_deref(), Some("String"));
+        assert_eq!(ty(&["user", "id"]).as_deref(), Some("ID!"));
+        assert_eq!(ty(&["node", "name"]).as_deref(), Some("String"));
+        assert_eq!(ty(&["user", "name"]), None);
+        assert_eq!(ty(&["user", "unknown"]), None);
+    }
+
+    #[test]
+    fn side_table() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let document = GraphqlParser::parse_with_interner(QUERY.as_bytes(), &mut interner).unwrap();
+
+        let types = infer_types(&schema, &document, &interner);
+
+        let query = Root::Operation {
+            name: interner.get("Q"),
+            kind: OperationKind::Query,
+        };
+        let fragment = Root::Fragment(interner.get("F").unwrap());
+
+        let ty = |root, indices: &[usize]| {
+            types
+                .get(&SelectionPath {
+                    root,
+                    indices: indices.to_vec(),
+                })
+                .map(|field| {
+                    format!(
+                        "{}: {}",
+                        interner.resolve(&field.parent),
+                        print_type(&field.ty, &interner)
+                    )
+                })
+        };
+
+        assert_eq!(types.len(), 7);
+        assert_eq!(ty(query, &[0, 0]).as_deref(), Some("User: [User!]!"));
+        assert_eq!(ty(query, &[0, 0, 0]).as_deref(), Some("User: String"));
+        assert_eq!(ty(query, &[1]).as_deref(), Some("Query: Node"));
+        assert_eq!(ty(query, &[1, 0, 0]).as_deref(), Some("User: String"));
+        assert_eq!(ty(fragment, &[1]).as_deref(), Some("User: String!"));
+        assert_eq!(ty(fragment, &[2]), None);
+    }
+}
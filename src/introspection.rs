@@ -0,0 +1,1096 @@
+//! Conversion between the JSON result of the standard introspection query and
+//! a [`Document`]
+//!
+//! See https://spec.graphql.org/October2021/#sec-Introspection
+
+use std::fmt;
+
+use lasso::{Rodeo, Spur};
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    ast::{
+        Argument, Directive, DirectiveDefinition, DirectiveLocation, Document, Enum, EnumVariant,
+        FieldDefinition, InputObject, InputObjectField, Interface, NamedType, ObjectType,
+        OperationKind, RootOperationTypeDefinition, Scalar, SchemaDefinition, Span, Type, TypeKind,
+        Union, Value, ValueSpan, BUILT_IN_DIRECTIVES, BUILT_IN_SCALARS,
+    },
+    error::GraphqlParseError,
+    fmt::print_value,
+    parse::GraphqlParser,
+};
+
+/// The standard introspection query, whose result can be read by
+/// [`document_from_introspection`]
+///
+/// It selects `specifiedByURL` and `isRepeatable`, which servers that predate
+/// the October 2021 spec may not support
+pub const INTROSPECTION_QUERY: &str = "query IntrospectionQuery {
+  __schema {
+    description
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types { ...FullType }
+    directives {
+      name
+      description
+      isRepeatable
+      locations
+      args { ...InputValue }
+    }
+  }
+}
+
+fragment FullType on __Type {
+  kind
+  name
+  description
+  specifiedByURL
+  fields(includeDeprecated: true) {
+    name
+    description
+    args { ...InputValue }
+    type { ...TypeRef }
+    isDeprecated
+    deprecationReason
+  }
+  inputFields { ...InputValue }
+  interfaces { ...TypeRef }
+  enumValues(includeDeprecated: true) {
+    name
+    description
+    isDeprecated
+    deprecationReason
+  }
+  possibleTypes { ...TypeRef }
+}
+
+fragment InputValue on __InputValue {
+  name
+  description
+  type { ...TypeRef }
+  defaultValue
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType {
+    kind
+    name
+    ofType {
+      kind
+      name
+      ofType {
+        kind
+        name
+        ofType {
+          kind
+          name
+          ofType {
+            kind
+            name
+            ofType {
+              kind
+              name
+              ofType { kind name }
+            }
+          }
+        }
+      }
+    }
+  }
+}
+";
+
+#[derive(Debug)]
+pub enum IntrospectionError {
+    /// A required key was missing, or had a value of the wrong JSON type
+    InvalidField {
+        field: &'static str,
+    },
+    UnknownTypeKind(String),
+    UnknownDirectiveLocation(String),
+    /// A `defaultValue` that isn't a valid GraphQL value literal
+    InvalidDefaultValue(GraphqlParseError),
+    /// A type is referenced that the document doesn't define
+    UnknownType(String),
+}
+
+impl fmt::Display for IntrospectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidField { field } => write!(f, "missing or invalid field `{}`", field),
+            Self::UnknownTypeKind(kind) => write!(f, "unknown type kind `{}`", kind),
+            Self::UnknownDirectiveLocation(location) => {
+                write!(f, "unknown directive location `{}`", location)
+            }
+            Self::InvalidDefaultValue(err) => write!(f, "invalid default value: {}", err),
+            Self::UnknownType(name) => write!(f, "unknown type `{}`", name),
+        }
+    }
+}
+
+impl std::error::Error for IntrospectionError {}
+
+/// Build a schema document from an introspection result. `json` may be the
+/// whole response (`{ "data": { "__schema": ... } }`), the `data` object, or
+/// the `__schema` object itself
+pub fn document_from_introspection(
+    json: &JsonValue,
+    interner: &mut Rodeo,
+) -> Result<Document, IntrospectionError> {
+    let json = json.get("data").unwrap_or(json);
+    let schema = json.get("__schema").unwrap_or(json);
+
+    IntrospectionReader { interner }.read_schema(schema)
+}
+
+struct IntrospectionReader<'a> {
+    interner: &'a mut Rodeo,
+}
+
+fn field<'a>(
+    json: &'a JsonValue,
+    field: &'static str,
+) -> Result<&'a JsonValue, IntrospectionError> {
+    json.get(field)
+        .ok_or(IntrospectionError::InvalidField { field })
+}
+
+fn str_field<'a>(json: &'a JsonValue, field: &'static str) -> Result<&'a str, IntrospectionError> {
+    json.get(field)
+        .and_then(JsonValue::as_str)
+        .ok_or(IntrospectionError::InvalidField { field })
+}
+
+/// A string field that may be missing or `null`
+fn optional_str_field<'a>(json: &'a JsonValue, field: &'static str) -> Option<&'a str> {
+    json.get(field).and_then(JsonValue::as_str)
+}
+
+/// An array field, where a missing or `null` field is treated as empty
+fn array_field<'a>(
+    json: &'a JsonValue,
+    field: &'static str,
+) -> Result<&'a [JsonValue], IntrospectionError> {
+    match json.get(field) {
+        None | Some(JsonValue::Null) => Ok(&[]),
+        Some(JsonValue::Array(values)) => Ok(values),
+        Some(..) => Err(IntrospectionError::InvalidField { field }),
+    }
+}
+
+impl IntrospectionReader<'_> {
+    fn intern(&mut self, s: &str) -> Spur {
+        self.interner.get_or_intern(s)
+    }
+
+    fn optional_description(&mut self, json: &JsonValue) -> Option<Spur> {
+        optional_str_field(json, "description").map(|description| self.intern(description))
+    }
+
+    fn read_schema(&mut self, schema: &JsonValue) -> Result<Document, IntrospectionError> {
+        let mut document = Document::new();
+
+        let mut operation_types = Vec::new();
+
+        for (kind, key) in [
+            (OperationKind::Query, "queryType"),
+            (OperationKind::Mutation, "mutationType"),
+            (OperationKind::Subscription, "subscriptionType"),
+        ] {
+            if let Some(name) = schema
+                .get(key)
+                .and_then(|ty| optional_str_field(ty, "name"))
+            {
+                operation_types.push(RootOperationTypeDefinition {
+                    kind,
+                    ty: NamedType(self.intern(name)),
+                    span: Span::default(),
+                });
+            }
+        }
+
+        document.schema = Some(SchemaDefinition {
+            description: self.optional_description(schema),
+            directives: Vec::new(),
+            operation_types,
+            span: Span::default(),
+        });
+
+        for ty in array_field(schema, "types")? {
+            let name = str_field(ty, "name")?;
+
+            if name.starts_with("__") || BUILT_IN_SCALARS.contains(&name) {
+                continue;
+            }
+
+            self.read_type(ty, &mut document)?;
+        }
+
+        for directive in array_field(schema, "directives")? {
+            if BUILT_IN_DIRECTIVES.contains(&str_field(directive, "name")?) {
+                continue;
+            }
+
+            let directive = self.read_directive(directive)?;
+
+            document
+                .directive_definitions
+                .insert(directive.name, directive);
+        }
+
+        Ok(document)
+    }
+
+    fn read_type(
+        &mut self,
+        ty: &JsonValue,
+        document: &mut Document,
+    ) -> Result<(), IntrospectionError> {
+        let name = self.intern(str_field(ty, "name")?);
+        let description = self.optional_description(ty);
+
+        match str_field(ty, "kind")? {
+            "SCALAR" => {
+                let mut directives = Vec::new();
+
+                if let Some(url) = optional_str_field(ty, "specifiedByURL") {
+                    let url = Value::String(self.intern(url));
+                    directives.push(self.directive("specifiedBy", "url", url));
+                }
+
+                document.scalars.insert(
+                    name,
+                    Scalar {
+                        description,
+                        name,
+                        directives,
+                        span: Span::default(),
+                    },
+                );
+            }
+            "OBJECT" => {
+                let implements = self.read_named_types(array_field(ty, "interfaces")?)?;
+                let fields = self.read_fields(ty)?;
+
+                document.output_objects.insert(
+                    name,
+                    ObjectType {
+                        implements,
+                        description,
+                        name,
+                        directives: Vec::new(),
+                        fields: Some(fields),
+                        span: Span::default(),
+                    },
+                );
+            }
+            "INTERFACE" => {
+                // older servers don't support interfaces implementing interfaces
+                let implements = match ty.get("interfaces") {
+                    Some(JsonValue::Null) | None => Vec::new(),
+                    Some(..) => self.read_named_types(array_field(ty, "interfaces")?)?,
+                };
+                let fields = self.read_fields(ty)?;
+
+                document.interfaces.insert(
+                    name,
+                    Interface {
+                        implements,
+                        description,
+                        name,
+                        directives: Vec::new(),
+                        fields,
+                        span: Span::default(),
+                    },
+                );
+            }
+            "UNION" => {
+                let types = self.read_named_types(array_field(ty, "possibleTypes")?)?;
+
+                document.unions.insert(
+                    name,
+                    Union {
+                        description,
+                        name,
+                        types,
+                        directives: Vec::new(),
+                        span: Span::default(),
+                    },
+                );
+            }
+            "ENUM" => {
+                let variants = array_field(ty, "enumValues")?
+                    .iter()
+                    .map(|variant| {
+                        Ok(EnumVariant {
+                            description: self.optional_description(variant),
+                            name: self.intern(str_field(variant, "name")?),
+                            directives: self.read_deprecation(variant),
+                            span: Span::default(),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, IntrospectionError>>()?;
+
+                document.enums.insert(
+                    name,
+                    Enum {
+                        description,
+                        name,
+                        directives: Vec::new(),
+                        variants,
+                        span: Span::default(),
+                    },
+                );
+            }
+            "INPUT_OBJECT" => {
+                let fields = self.read_input_values(array_field(ty, "inputFields")?)?;
+
+                document.input_objects.insert(
+                    name,
+                    InputObject {
+                        description,
+                        name,
+                        directives: Vec::new(),
+                        fields: Some(fields),
+                        span: Span::default(),
+                    },
+                );
+            }
+            kind => return Err(IntrospectionError::UnknownTypeKind(kind.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    fn read_named_types(
+        &mut self,
+        types: &[JsonValue],
+    ) -> Result<Vec<NamedType>, IntrospectionError> {
+        types
+            .iter()
+            .map(|ty| Ok(NamedType(self.intern(str_field(ty, "name")?))))
+            .collect()
+    }
+
+    fn read_fields(&mut self, ty: &JsonValue) -> Result<Vec<FieldDefinition>, IntrospectionError> {
+        array_field(ty, "fields")?
+            .iter()
+            .map(|field| {
+                let arguments = self.read_input_values(array_field(field, "args")?)?;
+
+                Ok(FieldDefinition {
+                    description: self.optional_description(field),
+                    name: self.intern(str_field(field, "name")?),
+                    ty: self.read_type_ref(self::field(field, "type")?)?,
+                    ty_span: Span::default(),
+                    arguments: if arguments.is_empty() {
+                        None
+                    } else {
+                        Some(arguments)
+                    },
+                    directives: self.read_deprecation(field),
+                    span: Span::default(),
+                })
+            })
+            .collect()
+    }
+
+    fn read_input_values(
+        &mut self,
+        values: &[JsonValue],
+    ) -> Result<Vec<InputObjectField>, IntrospectionError> {
+        values
+            .iter()
+            .map(|value| {
+                let default = match optional_str_field(value, "defaultValue") {
+                    Some(default) => Some(
+                        GraphqlParser::parse_value_with_interner(default.as_bytes(), self.interner)
+                            .map_err(IntrospectionError::InvalidDefaultValue)?,
+                    ),
+                    None => None,
+                };
+
+                Ok(InputObjectField {
+                    description: self.optional_description(value),
+                    name: self.intern(str_field(value, "name")?),
+                    ty: self.read_type_ref(field(value, "type")?)?,
+                    ty_span: Span::default(),
+                    default,
+                    default_span: ValueSpan::default(),
+                    directives: self.read_deprecation(value),
+                    span: Span::default(),
+                })
+            })
+            .collect()
+    }
+
+    /// Unwrap `NON_NULL` and `LIST` type references
+    fn read_type_ref(&mut self, ty: &JsonValue) -> Result<Type, IntrospectionError> {
+        Ok(match str_field(ty, "kind")? {
+            "NON_NULL" => {
+                let mut inner = self.read_type_ref(field(ty, "ofType")?)?;
+
+                match &mut inner {
+                    Type::Named { nullable, .. } | Type::List { nullable, .. } => *nullable = false,
+                }
+
+                inner
+            }
+            "LIST" => Type::List {
+                base: Box::new(self.read_type_ref(field(ty, "ofType")?)?),
+                nullable: true,
+            },
+            _ => Type::Named {
+                name: self.intern(str_field(ty, "name")?),
+                nullable: true,
+            },
+        })
+    }
+
+    fn read_deprecation(&mut self, json: &JsonValue) -> Vec<Directive> {
+        if json.get("isDeprecated").and_then(JsonValue::as_bool) != Some(true) {
+            return Vec::new();
+        }
+
+        let directive = match optional_str_field(json, "deprecationReason") {
+            Some(reason) => {
+                let reason = Value::String(self.intern(reason));
+                self.directive("deprecated", "reason", reason)
+            }
+            None => Directive {
+                name: self.intern("deprecated"),
+                arguments: None,
+                span: Span::default(),
+            },
+        };
+
+        vec![directive]
+    }
+
+    /// A directive with a single argument
+    fn directive(&mut self, name: &str, argument: &str, value: Value) -> Directive {
+        Directive {
+            name: self.intern(name),
+            arguments: Some(vec![Argument {
+                name: self.intern(argument),
+                value,
+                value_span: ValueSpan::default(),
+                span: Span::default(),
+            }]),
+            span: Span::default(),
+        }
+    }
+
+    fn read_directive(
+        &mut self,
+        directive: &JsonValue,
+    ) -> Result<DirectiveDefinition, IntrospectionError> {
+        let arguments = self.read_input_values(array_field(directive, "args")?)?;
+
+        let locations = array_field(directive, "locations")?
+            .iter()
+            .map(|location| {
+                let location = location
+                    .as_str()
+                    .ok_or(IntrospectionError::InvalidField { field: "locations" })?;
+
+                DirectiveLocation::from_name(location).ok_or_else(|| {
+                    IntrospectionError::UnknownDirectiveLocation(location.to_owned())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(DirectiveDefinition {
+            description: self.optional_description(directive),
+            name: self.intern(str_field(directive, "name")?),
+            arguments: if arguments.is_empty() {
+                None
+            } else {
+                Some(arguments)
+            },
+            repeatable: directive
+                .get("isRepeatable")
+                .and_then(JsonValue::as_bool)
+                .unwrap_or(false),
+            locations,
+            span: Span::default(),
+        })
+    }
+}
+
+pub use crate::ast::DEFAULT_DEPRECATION_REASON;
+
+/// Render a schema document as the result of the standard introspection query,
+/// in the shape `{ "__schema": { ... } }`. Built-in scalars and directives are
+/// included, the `__`-prefixed introspection types are not. Type extensions
+/// should be applied with [`Document::apply_extensions`] beforehand
+pub fn introspection_from_document(
+    document: &Document,
+    interner: &Rodeo,
+) -> Result<JsonValue, IntrospectionError> {
+    IntrospectionWriter { document, interner }.write_schema()
+}
+
+struct IntrospectionWriter<'a> {
+    document: &'a Document,
+    interner: &'a Rodeo,
+}
+
+impl<'a> IntrospectionWriter<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn description(&self, description: Option<Spur>) -> JsonValue {
+        description.map_or(JsonValue::Null, |description| self.name(description).into())
+    }
+
+    fn root_operation_type(&self, kind: OperationKind) -> JsonValue {
+        let name = self
+            .document
+            .root_operation_type_or_default(kind, self.interner);
+
+        name.map_or(JsonValue::Null, |name| json!({ "name": self.name(name) }))
+    }
+
+    fn write_schema(&self) -> Result<JsonValue, IntrospectionError> {
+        let document = self.document;
+
+        let mut types = Vec::new();
+
+        for name in BUILT_IN_SCALARS {
+            let is_redefined = self
+                .interner
+                .get(name)
+                .is_some_and(|name| document.scalars.contains_key(&name));
+
+            if !is_redefined {
+                types.push(json!({
+                    "kind": "SCALAR",
+                    "name": name,
+                    "description": null,
+                    "specifiedByURL": null,
+                    "fields": null,
+                    "inputFields": null,
+                    "interfaces": null,
+                    "enumValues": null,
+                    "possibleTypes": null,
+                }));
+            }
+        }
+
+        for scalar in document.scalars.values() {
+            let specified_by = scalar.specified_by_url(self.interner);
+
+            types.push(json!({
+                "kind": "SCALAR",
+                "name": self.name(scalar.name),
+                "description": self.description(scalar.description),
+                "specifiedByURL": specified_by,
+                "fields": null,
+                "inputFields": null,
+                "interfaces": null,
+                "enumValues": null,
+                "possibleTypes": null,
+            }));
+        }
+
+        for object in document.output_objects.values() {
+            types.push(json!({
+                "kind": "OBJECT",
+                "name": self.name(object.name),
+                "description": self.description(object.description),
+                "specifiedByURL": null,
+                "fields": self.fields(object.fields.as_deref().unwrap_or_default())?,
+                "inputFields": null,
+                "interfaces": self.named_types(&object.implements)?,
+                "enumValues": null,
+                "possibleTypes": null,
+            }));
+        }
+
+        for interface in document.interfaces.values() {
+            let mut implementations = document
+                .output_objects
+                .values()
+                .filter(|object| {
+                    object
+                        .implements
+                        .iter()
+                        .any(|implemented| implemented.0 == interface.name)
+                })
+                .map(|object| json!({ "kind": "OBJECT", "name": self.name(object.name) }))
+                .collect::<Vec<_>>();
+
+            sort_by_name(&mut implementations);
+
+            types.push(json!({
+                "kind": "INTERFACE",
+                "name": self.name(interface.name),
+                "description": self.description(interface.description),
+                "specifiedByURL": null,
+                "fields": self.fields(&interface.fields)?,
+                "inputFields": null,
+                "interfaces": self.named_types(&interface.implements)?,
+                "enumValues": null,
+                "possibleTypes": implementations,
+            }));
+        }
+
+        for union in document.unions.values() {
+            types.push(json!({
+                "kind": "UNION",
+                "name": self.name(union.name),
+                "description": self.description(union.description),
+                "specifiedByURL": null,
+                "fields": null,
+                "inputFields": null,
+                "interfaces": null,
+                "enumValues": null,
+                "possibleTypes": self.named_types(&union.types)?,
+            }));
+        }
+
+        for enum_def in document.enums.values() {
+            let variants = enum_def
+                .variants
+                .iter()
+                .map(|variant| {
+                    let (is_deprecated, deprecation_reason) =
+                        deprecation(variant.deprecation(self.interner));
+
+                    json!({
+                        "name": self.name(variant.name),
+                        "description": self.description(variant.description),
+                        "isDeprecated": is_deprecated,
+                        "deprecationReason": deprecation_reason,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            types.push(json!({
+                "kind": "ENUM",
+                "name": self.name(enum_def.name),
+                "description": self.description(enum_def.description),
+                "specifiedByURL": null,
+                "fields": null,
+                "inputFields": null,
+                "interfaces": null,
+                "enumValues": variants,
+                "possibleTypes": null,
+            }));
+        }
+
+        for input in document.input_objects.values() {
+            types.push(json!({
+                "kind": "INPUT_OBJECT",
+                "name": self.name(input.name),
+                "description": self.description(input.description),
+                "specifiedByURL": null,
+                "fields": null,
+                "inputFields": self.input_values(input.fields.as_deref().unwrap_or_default())?,
+                "interfaces": null,
+                "enumValues": null,
+                "possibleTypes": null,
+            }));
+        }
+
+        sort_by_name(&mut types);
+
+        let mut directives = built_in_directives();
+
+        directives.retain(|directive| {
+            let is_redefined = directive["name"]
+                .as_str()
+                .and_then(|name| self.interner.get(name))
+                .is_some_and(|name| document.directive_definitions.contains_key(&name));
+
+            !is_redefined
+        });
+
+        for directive in document.directive_definitions.values() {
+            let locations = directive
+                .locations
+                .iter()
+                .map(DirectiveLocation::as_str)
+                .collect::<Vec<_>>();
+
+            directives.push(json!({
+                "name": self.name(directive.name),
+                "description": self.description(directive.description),
+                "isRepeatable": directive.repeatable,
+                "locations": locations,
+                "args": self.input_values(directive.arguments.as_deref().unwrap_or_default())?,
+            }));
+        }
+
+        sort_by_name(&mut directives);
+
+        Ok(json!({
+            "__schema": {
+                "description": self.description(document.schema.as_ref().and_then(|schema| schema.description)),
+                "queryType": self.root_operation_type(OperationKind::Query),
+                "mutationType": self.root_operation_type(OperationKind::Mutation),
+                "subscriptionType": self.root_operation_type(OperationKind::Subscription),
+                "types": types,
+                "directives": directives,
+            }
+        }))
+    }
+
+    fn named_types(&self, types: &[NamedType]) -> Result<Vec<JsonValue>, IntrospectionError> {
+        types
+            .iter()
+            .map(|ty| {
+                Ok(json!({
+                    "kind": self.type_kind(ty.0)?,
+                    "name": self.name(ty.0),
+                }))
+            })
+            .collect()
+    }
+
+    fn type_kind(&self, name: Spur) -> Result<&'static str, IntrospectionError> {
+        match self.document.type_kind(name) {
+            Some(kind) => Ok(kind.as_str()),
+            None if BUILT_IN_SCALARS.contains(&self.name(name)) => Ok(TypeKind::Scalar.as_str()),
+            None => Err(IntrospectionError::UnknownType(self.name(name).to_owned())),
+        }
+    }
+
+    fn type_ref(&self, ty: &Type) -> Result<JsonValue, IntrospectionError> {
+        let (inner, nullable) = match ty {
+            Type::Named { name, nullable } => (
+                json!({
+                    "kind": self.type_kind(*name)?,
+                    "name": self.name(*name),
+                    "ofType": null,
+                }),
+                *nullable,
+            ),
+            Type::List { base, nullable } => (
+                json!({
+                    "kind": "LIST",
+                    "name": null,
+                    "ofType": self.type_ref(base)?,
+                }),
+                *nullable,
+            ),
+        };
+
+        Ok(if nullable {
+            inner
+        } else {
+            json!({ "kind": "NON_NULL", "name": null, "ofType": inner })
+        })
+    }
+
+    fn fields(&self, fields: &[FieldDefinition]) -> Result<Vec<JsonValue>, IntrospectionError> {
+        fields
+            .iter()
+            .map(|field| {
+                let (is_deprecated, deprecation_reason) =
+                    deprecation(field.deprecation(self.interner));
+
+                Ok(json!({
+                    "name": self.name(field.name),
+                    "description": self.description(field.description),
+                    "args": self.input_values(field.arguments.as_deref().unwrap_or_default())?,
+                    "type": self.type_ref(&field.ty)?,
+                    "isDeprecated": is_deprecated,
+                    "deprecationReason": deprecation_reason,
+                }))
+            })
+            .collect()
+    }
+
+    fn input_values(
+        &self,
+        values: &[InputObjectField],
+    ) -> Result<Vec<JsonValue>, IntrospectionError> {
+        values
+            .iter()
+            .map(|value| {
+                let (is_deprecated, deprecation_reason) =
+                    deprecation(value.deprecation(self.interner));
+
+                Ok(json!({
+                    "name": self.name(value.name),
+                    "description": self.description(value.description),
+                    "type": self.type_ref(&value.ty)?,
+                    "defaultValue": value
+                        .default
+                        .as_ref()
+                        .map(|default| print_value(default, self.interner)),
+                    "isDeprecated": is_deprecated,
+                    "deprecationReason": deprecation_reason,
+                }))
+            })
+            .collect()
+    }
+}
+
+/// `(isDeprecated, deprecationReason)` for an element deprecated for `reason`
+fn deprecation(reason: Option<&str>) -> (bool, JsonValue) {
+    match reason {
+        Some(reason) => (true, reason.into()),
+        None => (false, JsonValue::Null),
+    }
+}
+
+/// The value of `argument` on the first directive named `directive`
+fn sort_by_name(values: &mut [JsonValue]) {
+    values.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+}
+
+fn built_in_directives() -> Vec<JsonValue> {
+    let arg = |name: &str, ty: JsonValue, default: JsonValue| {
+        json!({
+            "name": name,
+            "description": null,
+            "type": ty,
+            "defaultValue": default,
+            "isDeprecated": false,
+            "deprecationReason": null,
+        })
+    };
+
+    let non_null = |name: &str| {
+        json!({
+            "kind": "NON_NULL",
+            "name": null,
+            "ofType": { "kind": "SCALAR", "name": name, "ofType": null },
+        })
+    };
+
+    let nullable_string = json!({ "kind": "SCALAR", "name": "String", "ofType": null });
+
+    let directive = |name: &str, locations: &[&str], args: Vec<JsonValue>| {
+        json!({
+            "name": name,
+            "description": null,
+            "isRepeatable": false,
+            "locations": locations,
+            "args": args,
+        })
+    };
+
+    vec![
+        directive(
+            "skip",
+            &["FIELD", "FRAGMENT_SPREAD", "INLINE_FRAGMENT"],
+            vec![arg("if", non_null("Boolean"), JsonValue::Null)],
+        ),
+        directive(
+            "include",
+            &["FIELD", "FRAGMENT_SPREAD", "INLINE_FRAGMENT"],
+            vec![arg("if", non_null("Boolean"), JsonValue::Null)],
+        ),
+        directive(
+            "deprecated",
+            &[
+                "FIELD_DEFINITION",
+                "ARGUMENT_DEFINITION",
+                "INPUT_FIELD_DEFINITION",
+                "ENUM_VALUE",
+            ],
+            vec![arg(
+                "reason",
+                nullable_string,
+                format!("{:?}", DEFAULT_DEPRECATION_REASON).into(),
+            )],
+        ),
+        directive(
+            "specifiedBy",
+            &["SCALAR"],
+            vec![arg("url", non_null("String"), JsonValue::Null)],
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+    use serde_json::json;
+
+    use super::{document_from_introspection, introspection_from_document, IntrospectionError};
+    use crate::{fmt::print_document, GraphqlParser};
+
+    const SCHEMA: &str = r#""The schema"
+schema { query: Query mutation: Mutation }
+"A directive" directive @cached(ttl: Int = 60, scopes: [String!]) repeatable on FIELD_DEFINITION
+scalar Date @specifiedBy(url: "https://example.com/date")
+type Query implements Node {
+  id: ID!
+  "A list" items(first: Int = 10, filter: Filter): [[Item!]]!
+  old: String @deprecated(reason: "use `id`")
+}
+type Mutation { touch(at: Date!): Boolean }
+interface Node { id: ID! }
+union Item = Query | Mutation
+enum Color { RED @deprecated(reason: "No longer supported") GREEN }
+input Filter { color: Color = RED, tags: [String!] = ["a"], nested: Filter }
+"#;
+
+    fn round_trip(source: &str) -> (String, String) {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse schema");
+
+        let json = introspection_from_document(&document, &interner).unwrap();
+        let read = document_from_introspection(&json, &mut interner).unwrap();
+
+        (
+            print_document(&document, &interner),
+            print_document(&read, &interner),
+        )
+    }
+
+    #[test]
+    fn introspection_query_parses() {
+        let document = GraphqlParser::parse(super::INTROSPECTION_QUERY.as_bytes()).unwrap();
+
+        assert_eq!(document.operations.len(), 1);
+        assert_eq!(document.fragments.len(), 3);
+    }
+
+    /// Only directives that introspection exposes, `@deprecated` and
+    /// `@specifiedBy`, are used in [`SCHEMA`], as others are lost
+    #[test]
+    fn round_trip_through_introspection() {
+        let (original, read) = round_trip(SCHEMA);
+
+        assert_eq!(original, read);
+    }
+
+    #[test]
+    fn writes_introspection_shape() {
+        let mut interner = Rodeo::default();
+        let document =
+            GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+
+        let json = introspection_from_document(&document, &interner).unwrap();
+        let schema = &json["__schema"];
+
+        assert_eq!(schema["queryType"], json!({ "name": "Query" }));
+        assert_eq!(schema["subscriptionType"], json!(null));
+
+        let ty = |name: &str| {
+            schema["types"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|ty| ty["name"] == name)
+                .unwrap_or_else(|| panic!("missing type {}", name))
+                .clone()
+        };
+
+        assert_eq!(ty("String")["kind"], "SCALAR");
+        assert_eq!(ty("Date")["specifiedByURL"], "https://example.com/date");
+        assert_eq!(
+            ty("Node")["possibleTypes"],
+            json!([{ "kind": "OBJECT", "name": "Query" }])
+        );
+
+        let id = &ty("Query")["fields"][0];
+        assert_eq!(id["name"], "id");
+        assert_eq!(
+            id["type"],
+            json!({
+                "kind": "NON_NULL",
+                "name": null,
+                "ofType": { "kind": "SCALAR", "name": "ID", "ofType": null },
+            })
+        );
+
+        let filter = &ty("Filter")["inputFields"];
+        assert_eq!(filter[0]["defaultValue"], "RED");
+        assert_eq!(filter[1]["defaultValue"], "[\"a\"]");
+
+        let red = &ty("Color")["enumValues"][0];
+        assert_eq!(red["isDeprecated"], true);
+        assert_eq!(red["deprecationReason"], "No longer supported");
+    }
+
+    fn read_error(json: serde_json::Value) -> IntrospectionError {
+        document_from_introspection(&json, &mut Rodeo::default()).unwrap_err()
+    }
+
+    #[test]
+    fn accepts_response_data_or_schema() {
+        let schema = json!({
+            "queryType": { "name": "Query" },
+            "types": [{
+                "kind": "OBJECT",
+                "name": "Query",
+                "fields": [{
+                    "name": "a",
+                    "args": [],
+                    "type": { "kind": "SCALAR", "name": "Int", "ofType": null },
+                }],
+                "interfaces": [],
+            }],
+            "directives": [],
+        });
+
+        let mut interner = Rodeo::default();
+        let mut printed = Vec::new();
+
+        for json in [
+            schema.clone(),
+            json!({ "__schema": schema.clone() }),
+            json!({ "data": { "__schema": schema } }),
+        ] {
+            let document = document_from_introspection(&json, &mut interner).unwrap();
+            printed.push(print_document(&document, &interner));
+        }
+
+        assert!(
+            printed[0].contains("type Query {\n  a: Int\n}"),
+            "{}",
+            printed[0]
+        );
+        assert_eq!(printed[0], printed[1]);
+        assert_eq!(printed[0], printed[2]);
+    }
+
+    #[test]
+    fn read_errors() {
+        assert!(matches!(
+            read_error(json!({ "types": [{ "kind": "OBJECT" }] })),
+            IntrospectionError::InvalidField { field: "name" }
+        ));
+
+        assert!(matches!(
+            read_error(json!({ "types": [{ "kind": "THING", "name": "A" }] })),
+            IntrospectionError::UnknownTypeKind(kind) if kind == "THING"
+        ));
+
+        assert!(matches!(
+            read_error(json!({
+                "types": [],
+                "directives": [{ "name": "a", "args": [], "locations": ["NOWHERE"] }],
+            })),
+            IntrospectionError::UnknownDirectiveLocation(location) if location == "NOWHERE"
+        ));
+
+        assert!(matches!(
+            read_error(json!({
+                "types": [{
+                    "kind": "INPUT_OBJECT",
+                    "name": "A",
+                    "inputFields": [{
+                        "name": "a",
+                        "type": { "kind": "SCALAR", "name": "Int" },
+                        "defaultValue": "{",
+                    }],
+                }],
+            })),
+            IntrospectionError::InvalidDefaultValue(..)
+        ));
+    }
+}
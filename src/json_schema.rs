@@ -0,0 +1,422 @@
+//! Export of a schema's types as JSON Schema, and of a schema as an OpenAPI
+//! document describing its endpoint, for tooling that doesn't understand
+//! GraphQL, such as API gateways and documentation portals
+//!
+//! Objects and input objects become objects whose non-null fields are
+//! required, enums become string enums, and unions, along with interfaces
+//! that have implementations, become a `oneOf` of their possible types.
+//! Nullable types also accept `null`. Arguments aren't represented
+
+use lasso::{Rodeo, Spur};
+use serde_json::{json, Map, Value as JsonValue};
+
+use crate::ast::{
+    Document, FieldDefinition, InputObjectField, OperationKind, Type, TypeDefinitionRef,
+};
+
+#[derive(Debug, Clone)]
+pub struct OpenApiConfig {
+    pub title: String,
+    pub version: String,
+    /// The path the GraphQL endpoint is served at
+    pub path: String,
+}
+
+impl Default for OpenApiConfig {
+    fn default() -> Self {
+        Self {
+            title: "GraphQL API".to_owned(),
+            version: "1.0.0".to_owned(),
+            path: "/graphql".to_owned(),
+        }
+    }
+}
+
+/// A JSON Schema (draft 2020-12) document with a definition in `$defs` for
+/// every type in `schema` other than the built-in scalars
+pub fn json_schema_from_document(schema: &Document, interner: &Rodeo) -> JsonValue {
+    let writer = SchemaWriter {
+        schema,
+        interner,
+        refs: "#/$defs/",
+    };
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$defs": writer.definitions(),
+    })
+}
+
+/// An OpenAPI 3.1 document with a single POST operation at `config.path`,
+/// which takes a GraphQL request and responds with the query type's data,
+/// and the schema's types as component schemas
+pub fn openapi_from_document(
+    schema: &Document,
+    interner: &Rodeo,
+    config: &OpenApiConfig,
+) -> JsonValue {
+    let writer = SchemaWriter {
+        schema,
+        interner,
+        refs: "#/components/schemas/",
+    };
+
+    let data = match schema.root_operation_type_or_default(OperationKind::Query, interner) {
+        Some(query) => writer.nullable(writer.reference(query)),
+        None => json!({}),
+    };
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": config.title,
+            "version": config.version,
+        },
+        "paths": {
+            config.path.as_str(): {
+                "post": {
+                    "operationId": "graphql",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "query": { "type": "string" },
+                                        "operationName": { "type": ["string", "null"] },
+                                        "variables": { "type": ["object", "null"] },
+                                    },
+                                    "required": ["query"],
+                                },
+                            },
+                        },
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "The result of executing the operation",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "data": data,
+                                            "errors": {
+                                                "type": "array",
+                                                "items": {
+                                                    "type": "object",
+                                                    "properties": {
+                                                        "message": { "type": "string" },
+                                                    },
+                                                    "required": ["message"],
+                                                },
+                                            },
+                                        },
+                                    },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+        },
+        "components": {
+            "schemas": writer.definitions(),
+        },
+    })
+}
+
+struct SchemaWriter<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+    /// The prefix of references to definitions
+    refs: &'a str,
+}
+
+impl SchemaWriter<'_> {
+    fn name(&self, name: Spur) -> &str {
+        self.interner.resolve(&name)
+    }
+
+    fn definitions(&self) -> Map<String, JsonValue> {
+        self.schema
+            .types()
+            .filter(|ty| !self.name(ty.name()).starts_with("__") && !self.is_built_in(ty.name()))
+            .map(|ty| (self.name(ty.name()).to_owned(), self.definition(&ty)))
+            .collect()
+    }
+
+    fn is_built_in(&self, name: Spur) -> bool {
+        matches!(
+            self.name(name),
+            "Int" | "Float" | "String" | "ID" | "Boolean"
+        )
+    }
+
+    fn definition(&self, ty: &TypeDefinitionRef<'_>) -> JsonValue {
+        let mut definition = match ty {
+            TypeDefinitionRef::Scalar(..) => json!({}),
+            TypeDefinitionRef::Object(object) => {
+                self.object(object.fields.as_deref().unwrap_or_default())
+            }
+            TypeDefinitionRef::Interface(interface) => {
+                let possible_types = self.schema.possible_types(interface.name);
+
+                if possible_types.is_empty() {
+                    self.object(&interface.fields)
+                } else {
+                    self.one_of(possible_types)
+                }
+            }
+            TypeDefinitionRef::Union(union) => {
+                self.one_of(union.types.iter().map(|ty| ty.0).collect())
+            }
+            TypeDefinitionRef::Enum(enum_def) => json!({
+                "type": "string",
+                "enum": enum_def
+                    .variants
+                    .iter()
+                    .map(|variant| self.name(variant.name))
+                    .collect::<Vec<_>>(),
+            }),
+            TypeDefinitionRef::InputObject(input) => {
+                self.input_object(input.fields.as_deref().unwrap_or_default())
+            }
+        };
+
+        if let Some(description) = ty.description() {
+            definition["description"] = self.name(description).into();
+        }
+
+        definition
+    }
+
+    fn object(&self, fields: &[FieldDefinition]) -> JsonValue {
+        let properties = fields
+            .iter()
+            .map(|field| {
+                let mut property = self.field_type(&field.ty);
+
+                if let Some(description) = field.description {
+                    property = with(property, "description", self.name(description).into());
+                }
+
+                if field.deprecation(self.interner).is_some() {
+                    property = with(property, "deprecated", true.into());
+                }
+
+                (self.name(field.name).to_owned(), property)
+            })
+            .collect::<Map<_, _>>();
+
+        let required = fields
+            .iter()
+            .filter(|field| !field.ty.is_nullable())
+            .map(|field| self.name(field.name))
+            .collect::<Vec<_>>();
+
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        })
+    }
+
+    /// Like [`SchemaWriter::object`], where fields with defaults aren't
+    /// required either
+    fn input_object(&self, fields: &[InputObjectField]) -> JsonValue {
+        let properties = fields
+            .iter()
+            .map(|field| {
+                let mut property = self.field_type(&field.ty);
+
+                if let Some(description) = field.description {
+                    property = with(property, "description", self.name(description).into());
+                }
+
+                if let Some(default) = field
+                    .default
+                    .as_ref()
+                    .and_then(|default| default.to_json(self.interner).ok())
+                {
+                    property = with(property, "default", default);
+                }
+
+                if field.deprecation(self.interner).is_some() {
+                    property = with(property, "deprecated", true.into());
+                }
+
+                (self.name(field.name).to_owned(), property)
+            })
+            .collect::<Map<_, _>>();
+
+        let required = fields
+            .iter()
+            .filter(|field| !field.ty.is_nullable() && field.default.is_none())
+            .map(|field| self.name(field.name))
+            .collect::<Vec<_>>();
+
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        })
+    }
+
+    fn one_of(&self, mut types: Vec<Spur>) -> JsonValue {
+        types.sort_by_key(|ty| self.name(*ty));
+
+        json!({
+            "oneOf": types
+                .into_iter()
+                .map(|ty| self.reference(ty))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn field_type(&self, ty: &Type) -> JsonValue {
+        let inner = match ty {
+            Type::Named { name, .. } => self.reference(*name),
+            Type::List { base, .. } => json!({
+                "type": "array",
+                "items": self.field_type(base),
+            }),
+        };
+
+        if ty.is_nullable() {
+            self.nullable(inner)
+        } else {
+            inner
+        }
+    }
+
+    /// The schema of a value of the named type `name`, inline for built-in
+    /// scalars and a reference to its definition otherwise
+    fn reference(&self, name: Spur) -> JsonValue {
+        match self.name(name) {
+            "Int" => json!({ "type": "integer", "format": "int32" }),
+            "Float" => json!({ "type": "number" }),
+            "String" | "ID" => json!({ "type": "string" }),
+            "Boolean" => json!({ "type": "boolean" }),
+            name => json!({ "$ref": format!("{}{}", self.refs, name) }),
+        }
+    }
+
+    /// `schema`, also accepting `null`
+    fn nullable(&self, schema: JsonValue) -> JsonValue {
+        match schema.get("type") {
+            Some(JsonValue::String(ty)) => with(schema.clone(), "type", json!([ty, "null"])),
+            _ => json!({ "anyOf": [schema, { "type": "null" }] }),
+        }
+    }
+}
+
+/// `schema` with `key` set to `value`. References can't have siblings in
+/// older drafts, so they're wrapped in an `allOf` first
+fn with(schema: JsonValue, key: &str, value: JsonValue) -> JsonValue {
+    let mut schema = if schema.get("$ref").is_some() {
+        json!({ "allOf": [schema] })
+    } else {
+        schema
+    };
+
+    schema[key] = value;
+
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+    use serde_json::json;
+
+    use super::{json_schema_from_document, openapi_from_document, OpenApiConfig};
+    use crate::GraphqlParser;
+
+    const SCHEMA: &str = concat!(
+        "type Query { user(id: ID!): User search: [SearchResult!]! } ",
+        "\"A user\" type User implements Node { id: ID! name: String @deprecated role: Role } ",
+        "interface Node { id: ID! } ",
+        "union SearchResult = User | Post ",
+        "type Post { title: String! } ",
+        "enum Role { ADMIN USER } ",
+        "input Filter { role: Role = ADMIN, limit: Int! }",
+    );
+
+    #[test]
+    fn json_schema() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+
+        let json_schema = json_schema_from_document(&schema, &interner);
+        let defs = &json_schema["$defs"];
+
+        assert_eq!(
+            defs["User"],
+            json!({
+                "type": "object",
+                "description": "A user",
+                "properties": {
+                    "id": { "type": "string" },
+                    "name": { "type": ["string", "null"], "deprecated": true },
+                    "role": { "anyOf": [{ "$ref": "#/$defs/Role" }, { "type": "null" }] },
+                },
+                "required": ["id"],
+                "additionalProperties": false,
+            })
+        );
+        assert_eq!(
+            defs["Query"]["properties"]["search"],
+            json!({ "type": "array", "items": { "$ref": "#/$defs/SearchResult" } })
+        );
+        assert_eq!(
+            defs["SearchResult"],
+            json!({ "oneOf": [{ "$ref": "#/$defs/Post" }, { "$ref": "#/$defs/User" }] })
+        );
+        assert_eq!(
+            defs["Node"],
+            json!({ "oneOf": [{ "$ref": "#/$defs/User" }] })
+        );
+        assert_eq!(
+            defs["Role"],
+            json!({ "type": "string", "enum": ["ADMIN", "USER"] })
+        );
+        assert_eq!(
+            defs["Filter"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "role": {
+                        "anyOf": [{ "$ref": "#/$defs/Role" }, { "type": "null" }],
+                        "default": "ADMIN",
+                    },
+                    "limit": { "type": "integer", "format": "int32" },
+                },
+                "required": ["limit"],
+                "additionalProperties": false,
+            })
+        );
+        assert!(defs.get("String").is_none());
+    }
+
+    #[test]
+    fn openapi() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+
+        let openapi = openapi_from_document(&schema, &interner, &OpenApiConfig::default());
+        let response = &openapi["paths"]["/graphql"]["post"]["responses"]["200"];
+
+        assert_eq!(openapi["openapi"], "3.1.0");
+        assert_eq!(
+            response["content"]["application/json"]["schema"]["properties"]["data"],
+            json!({ "anyOf": [{ "$ref": "#/components/schemas/Query" }, { "type": "null" }] })
+        );
+        assert_eq!(
+            openapi["components"]["schemas"]["Post"]["properties"]["title"],
+            json!({ "type": "string" })
+        );
+    }
+}
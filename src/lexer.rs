@@ -1,14 +1,77 @@
+//! The tokens of a GraphQL document, for tools such as syntax highlighters
+//! that don't need a parsed document
+//!
+//! [`Lexer`] is an iterator of tokens and their spans. Names and strings are
+//! interned, and can be resolved with [`Lexer::interner`]. Lexing can start at
+//! any offset with [`Lexer::seek`], and continues after an invalid token
+//!
+//! [`ParseLimits`] bound how much work an untrusted document can cause,
+//! failing with [`GraphqlParseError::LimitExceeded`] as soon as one is
+//! exceeded
+
+use std::collections::VecDeque;
+
 use lasso::Rodeo;
 
 use crate::{
-    ast::{Keyword, Token},
-    error::GraphqlParseError,
+    ast::{Comment, Keyword, Span, Token},
+    error::{GraphqlParseError, ParseLimit},
 };
 
+pub(crate) const UNICODE_BOM: &[u8] = "\u{feff}".as_bytes();
+
+/// Hard limits on a document, for parsing untrusted input such as client
+/// requests. Each limit is off when `None`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// The most tokens the document may contain
+    pub max_tokens: Option<usize>,
+    /// The most selection sets, lists and input objects that may be nested
+    /// in one another. Only enforced by the parser
+    pub max_depth: Option<usize>,
+    /// The most bytes a string may have, not counting its quotes. Block
+    /// strings are measured before their indentation is removed
+    pub max_string_length: Option<usize>,
+    /// The most bytes the document may have
+    pub max_input_size: Option<usize>,
+}
+
+/// A token, along with the byte range of the source it was lexed from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
 pub struct Lexer<'a> {
     buffer: &'a [u8],
     cursor: usize,
-    pub interner: Rodeo,
+    /// The span of the most recently lexed token
+    token_span: Span,
+    /// The span of the most recently consumed token or punctuator, which
+    /// unlike `token_span` isn't changed by peeking
+    consumed_span: Span,
+    pub(crate) interner: Rodeo,
+    /// Comments skipped so far, only recorded when the lexer was created with
+    /// [`Lexer::with_comments`]
+    comments: Option<Vec<Comment>>,
+    /// Tokens lexed ahead of the cursor by peeking, so that they aren't
+    /// lexed again when peeked or consumed. Only valid while the first one
+    /// starts at the cursor
+    peeked: VecDeque<PeekedToken>,
+    pub(crate) limits: ParseLimits,
+    /// Tokens consumed so far, counted against [`ParseLimits::max_tokens`]
+    token_count: usize,
+    /// An input found to be too large by [`Lexer::set_limits`], returned in
+    /// place of the first token
+    input_size_error: Option<GraphqlParseError>,
+    /// Whether a limit was exceeded, after which there are no more tokens
+    is_fused: bool,
+}
+
+struct PeekedToken {
+    token: Token,
+    span: Span,
 }
 
 impl<'a> Lexer<'a> {
@@ -16,15 +79,64 @@ impl<'a> Lexer<'a> {
         Self {
             buffer,
             cursor: 0,
+            token_span: Span::default(),
+            consumed_span: Span::default(),
             interner: Rodeo::default(),
+            comments: None,
+            peeked: VecDeque::new(),
+            limits: ParseLimits::default(),
+            token_count: 0,
+            input_size_error: None,
+            is_fused: false,
+        }
+    }
+
+    pub fn with_interner(buffer: &'a [u8], interner: Rodeo) -> Self {
+        Self {
+            interner,
+            ..Self::new(buffer)
+        }
+    }
+
+    pub fn with_comments(buffer: &'a [u8]) -> Self {
+        Self {
+            comments: Some(Vec::new()),
+            ..Self::new(buffer)
         }
     }
 
+    /// Fail once the input goes over `limits`. The first error for a limit
+    /// is the last item lexed, as lexing stops there
+    pub fn set_limits(&mut self, limits: ParseLimits) {
+        self.limits = limits;
+        self.input_size_error = match limits.max_input_size {
+            Some(max) if self.buffer.len() > max => Some(GraphqlParseError::LimitExceeded {
+                limit: ParseLimit::InputSize,
+                max,
+                span: Span::new(max, self.buffer.len()),
+            }),
+            _ => None,
+        };
+    }
+
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        self.comments.take().unwrap_or_default()
+    }
+
+    /// The interner names and strings in lexed tokens are interned into
+    pub fn interner(&self) -> &Rodeo {
+        &self.interner
+    }
+
+    pub fn into_interner(self) -> Rodeo {
+        self.interner
+    }
+
     fn next_byte(&mut self) -> Option<u8> {
-        self.buffer.get(self.cursor).copied().map(|b| {
-            self.cursor += 1;
-            b
-        })
+        self.buffer
+            .get(self.cursor)
+            .copied()
+            .inspect(|_| self.cursor += 1)
     }
 
     pub(crate) fn peek_byte(&mut self) -> Option<u8> {
@@ -35,17 +147,64 @@ impl<'a> Lexer<'a> {
         self.cursor -= 1;
     }
 
-    pub fn expect_byte(&mut self, byte: u8) -> Result<(), GraphqlParseError> {
+    /// The span of the token most recently returned by [`Lexer::next_token`]
+    /// or [`Lexer::peek_token`]
+    pub fn last_span(&self) -> Span {
+        self.token_span
+    }
+
+    /// The span of the token or punctuator most recently consumed by
+    /// [`Lexer::next_token`], [`Lexer::expect_byte`] or
+    /// [`Lexer::consume_byte_if_eq`]
+    pub(crate) fn consumed_span(&self) -> Span {
+        self.consumed_span
+    }
+
+    /// Skip any whitespace and comments, returning the offset the next token
+    /// starts at
+    pub(crate) fn next_token_start(&mut self) -> usize {
+        self.skip_ignored_characters();
+        self.cursor
+    }
+
+    /// Move the cursor to `offset`, such as to resume lexing from an earlier
+    /// point after an error. `offset` should be at the start of a token, or
+    /// of whitespace or a comment
+    pub fn seek(&mut self, offset: usize) {
+        self.cursor = offset;
+    }
+
+    /// Whether `offset` is the first byte of a line
+    pub(crate) fn starts_line(&self, offset: usize) -> bool {
+        offset == 0 || matches!(self.buffer.get(offset - 1), Some(b'\n' | b'\r'))
+    }
+
+    /// The span of the byte just consumed, or an empty span at the cursor if
+    /// we're at the end of input
+    fn previous_byte_span(&self, consumed: bool) -> Span {
+        if consumed {
+            Span::new(self.cursor - 1, self.cursor)
+        } else {
+            Span::new(self.cursor, self.cursor)
+        }
+    }
+
+    pub(crate) fn expect_byte(&mut self, byte: u8) -> Result<(), GraphqlParseError> {
         self.skip_ignored_characters();
         match self.next_byte() {
-            Some(next) if next == byte => Ok(()),
+            Some(next) if next == byte => {
+                self.consumed_span = self.previous_byte_span(true);
+                self.count_token(self.consumed_span)
+            }
             Some(next) => Err(GraphqlParseError::ExpectedChar {
                 token: byte as char,
                 found: Some(next as char),
+                span: self.previous_byte_span(true),
             }),
             None => Err(GraphqlParseError::ExpectedChar {
                 token: byte as char,
                 found: None,
+                span: self.previous_byte_span(false),
             }),
         }
     }
@@ -60,17 +219,40 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    pub fn consume_byte_if_eq(&mut self, byte: u8) -> bool {
+    pub(crate) fn consume_byte_if_eq(&mut self, byte: u8) -> bool {
         self.skip_ignored_characters();
 
-        let next = self.next_byte();
+        match self.next_byte() {
+            // at the limit, leave the byte to be lexed as a token, which
+            // fails with the error this can't return
+            Some(next) if next == byte && !self.is_at_token_limit() => {
+                self.consumed_span = self.previous_byte_span(true);
+                self.token_count += 1;
+                true
+            }
+            Some(..) => {
+                self.go_back();
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// A variable, given its already consumed `$`. No whitespace is allowed
+    /// between the `$` and the name
+    fn lex_variable(&mut self) -> Token {
+        let start = self.cursor;
 
-        if Some(byte) != next {
-            self.go_back();
-            return false;
+        match self.peek_byte() {
+            Some(b'a'..=b'z' | b'A'..=b'Z' | b'_') => {}
+            _ => return Token::Dollar,
         }
 
-        true
+        while self.consume_byte_if_name_body() {}
+
+        let name = std::str::from_utf8(&self.buffer[start..self.cursor]).unwrap();
+
+        Token::Variable(self.interner.get_or_intern(name))
     }
 
     fn lex_identifier(&mut self) -> Token {
@@ -99,124 +281,471 @@ impl<'a> Lexer<'a> {
             "null" => Token::Keyword(Keyword::Null),
             "interface" => Token::Keyword(Keyword::Interface),
             "on" => Token::Keyword(Keyword::On),
+            "schema" => Token::Keyword(Keyword::Schema),
+            "directive" => Token::Keyword(Keyword::Directive),
+            "repeatable" => Token::Keyword(Keyword::Repeatable),
             _ => Token::Name(self.interner.get_or_intern(ident)),
         }
     }
 
+    /// The next token, without consuming it. The token is only lexed once,
+    /// however many times it is peeked before being consumed
     pub fn peek_token(&mut self) -> Result<Option<Token>, GraphqlParseError> {
+        self.peek_nth_token(0)
+    }
+
+    /// The token `n` tokens after the next one, without consuming any. Errors
+    /// aren't kept, so an invalid token is lexed again each time it is peeked
+    pub fn peek_nth_token(&mut self, n: usize) -> Result<Option<Token>, GraphqlParseError> {
+        if self.check_fused()? {
+            return Ok(None);
+        }
+
         self.skip_ignored_characters();
 
-        let start = self.cursor;
+        if self
+            .peeked
+            .front()
+            .is_some_and(|peeked| peeked.span.start != self.cursor)
+        {
+            self.peeked.clear();
+        }
+
+        let cursor = self.cursor;
+        let consumed_span = self.consumed_span;
 
-        let token = self.next_token();
+        while self.peeked.len() <= n {
+            if let Some(last) = self.peeked.back() {
+                self.cursor = last.span.end;
+                self.skip_ignored_characters();
+            }
 
-        self.cursor = start;
+            let start = self.cursor;
 
-        token
+            match self.lex_token() {
+                Ok(Some(token)) => self.peeked.push_back(PeekedToken {
+                    token,
+                    span: Span::new(start, self.cursor),
+                }),
+                result => {
+                    self.token_span = Span::new(start, self.cursor);
+                    self.cursor = cursor;
+                    self.consumed_span = consumed_span;
+
+                    return result;
+                }
+            }
+        }
+
+        self.cursor = cursor;
+        self.consumed_span = consumed_span;
+
+        let peeked = &self.peeked[n];
+        self.token_span = peeked.span;
+
+        Ok(Some(peeked.token.clone()))
     }
 
     fn skip_ignored_characters(&mut self) {
         while let Some(b) = self.peek_byte() {
-            if matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b',') {
-                self.next_byte();
-            } else {
-                return;
+            match b {
+                b' ' | b'\t' | b'\n' | b'\r' | b',' => {
+                    self.next_byte();
+                }
+                b'#' => self.skip_comment(),
+                // the byte order mark, U+FEFF
+                0xEF if self.buffer[self.cursor..].starts_with(UNICODE_BOM) => {
+                    self.cursor += UNICODE_BOM.len();
+                }
+                _ => return,
             }
         }
     }
 
-    // todo: more complex parsing rules for this, but works for now
-    //
-    // see https://spec.graphql.org/June2018/#BlockStringValue()
+    // see https://spec.graphql.org/June2018/#sec-Comments
+    fn skip_comment(&mut self) {
+        let start = self.cursor;
+
+        while let Some(b) = self.peek_byte() {
+            if matches!(b, b'\n' | b'\r') {
+                break;
+            }
+
+            self.next_byte();
+        }
+
+        let end = self.cursor;
+
+        if let Some(comments) = &mut self.comments {
+            // peeking re-lexes the same input, so only record comments we
+            // haven't seen yet
+            if comments.last().is_none_or(|last| last.end <= start) {
+                comments.push(Comment { start, end });
+            }
+        }
+    }
+
+    // see https://spec.graphql.org/October2021/#sec-String-Value
     fn lex_block_string(&mut self) -> Result<Token, GraphqlParseError> {
-        let mut buffer = String::new();
+        let start = self.cursor - 3;
+        let mut raw = String::new();
 
         while let Some(byte) = self.next_byte() {
-            if byte != b'"' {
-                buffer.push(byte as char);
-                continue;
-            }
+            match byte {
+                b'"' if self.buffer[self.cursor..].starts_with(b"\"\"") => {
+                    self.cursor += 2;
 
-            let next_is_quote = self.next_byte() == Some(b'"');
-            let two_from_now_is_quote = self.next_byte() == Some(b'"');
+                    let value = block_string_value(&raw);
 
-            if next_is_quote && two_from_now_is_quote {
-                return Ok(Token::String(self.interner.get_or_intern(buffer.trim())));
+                    return Ok(Token::String(self.interner.get_or_intern(value)));
+                }
+                // `\"""` is the only escape sequence in block strings
+                b'\\' if self.buffer[self.cursor..].starts_with(b"\"\"\"") => {
+                    self.cursor += 3;
+                    raw.push_str("\"\"\"");
+                }
+                b if b.is_ascii() => raw.push(b as char),
+                b => raw.push(self.lex_utf8_char(b)?),
             }
 
-            self.go_back();
-            self.go_back();
+            self.check_string_length(start, raw.len())?;
         }
 
         Err(GraphqlParseError::ExpectedChar {
             token: '"',
             found: None,
+            span: self.previous_byte_span(false),
         })
     }
 
-    fn lex_string(&mut self) -> Result<Token, GraphqlParseError> {
-        let has_two_quotes = self.consume_byte_if_eq(b'"');
-        let has_three_quotes = self.consume_byte_if_eq(b'"');
+    /// Decode a multi-byte utf-8 character, given its already consumed first
+    /// byte
+    fn lex_utf8_char(&mut self, first: u8) -> Result<char, GraphqlParseError> {
+        let start = self.cursor - 1;
 
-        let is_triple = if has_three_quotes {
-            true
-        // empty string ""
-        } else if has_two_quotes {
-            return Ok(Token::String(self.interner.get_or_intern("")));
-        } else {
-            false
+        let len = match first {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => {
+                return Err(GraphqlParseError::InvalidUtf8 {
+                    span: Span::new(start, self.cursor),
+                })
+            }
         };
 
-        if is_triple {
-            return self.lex_block_string();
+        let end = (start + len).min(self.buffer.len());
+
+        match std::str::from_utf8(&self.buffer[start..end]) {
+            Ok(c) => {
+                self.cursor = end;
+                Ok(c.chars().next().unwrap())
+            }
+            Err(..) => Err(GraphqlParseError::InvalidUtf8 {
+                span: Span::new(start, self.cursor),
+            }),
         }
+    }
 
-        let mut buffer = String::new();
+    fn lex_string(&mut self) -> Result<Token, GraphqlParseError> {
+        let start = self.cursor - 1;
 
-        let mut is_escaped = false;
+        if self.consume_byte_if_eq_no_skip(b'"') {
+            if self.consume_byte_if_eq_no_skip(b'"') {
+                return self.lex_block_string();
+            }
+
+            // empty string ""
+            return Ok(Token::String(self.interner.get_or_intern("")));
+        }
+
+        let mut buffer = String::new();
 
         while let Some(b) = self.next_byte() {
             match b {
-                b'"' if is_escaped => buffer.push('"'),
-                b'\\' if is_escaped => buffer.push('\\'),
-                b'/' if is_escaped => buffer.push('/'),
-                b'n' if is_escaped => buffer.push('\n'),
-                b'b' if is_escaped => todo!(),
-                b'f' if is_escaped => todo!(),
-                b'u' if is_escaped => todo!(),
-                b'r' if is_escaped => buffer.push('\r'),
-                b't' if is_escaped => buffer.push('\t'),
-                b'\\' => is_escaped = true,
+                b'\\' => buffer.push(self.lex_escape_sequence()?),
                 b'\n' => {
                     return Err(GraphqlParseError::ExpectedChar {
                         token: '"',
                         found: Some('\n'),
+                        span: self.previous_byte_span(true),
                     })
                 }
                 b'"' => return Ok(Token::String(self.interner.get_or_intern(buffer))),
-                c => buffer.push(c as char),
+                b if b.is_ascii() => buffer.push(b as char),
+                b => buffer.push(self.lex_utf8_char(b)?),
             }
+
+            self.check_string_length(start, buffer.len())?;
         }
 
         Err(GraphqlParseError::ExpectedChar {
             token: '"',
             found: None,
+            span: self.previous_byte_span(false),
+        })
+    }
+
+    // see https://spec.graphql.org/October2021/#EscapedCharacter and
+    // https://spec.graphql.org/October2021/#EscapedUnicode
+    fn lex_escape_sequence(&mut self) -> Result<char, GraphqlParseError> {
+        let start = self.cursor - 1;
+
+        let invalid_escape = |lexer: &mut Self| {
+            // end the span after the character, rather than inside it
+            while lexer
+                .buffer
+                .get(lexer.cursor)
+                .is_some_and(|&b| b & 0b1100_0000 == 0b1000_0000)
+            {
+                lexer.cursor += 1;
+            }
+
+            GraphqlParseError::InvalidEscapeSequence {
+                span: Span::new(start, lexer.cursor),
+            }
+        };
+
+        Ok(match self.next_byte() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                let code_unit = self
+                    .lex_escaped_code_unit()
+                    .ok_or_else(|| invalid_escape(self))?;
+
+                // characters outside the basic multilingual plane are written
+                // as a surrogate pair, such as `\uD83D\uDE00`
+                if (0xD800..=0xDBFF).contains(&code_unit) {
+                    if !self.buffer[self.cursor..].starts_with(b"\\u") {
+                        return Err(invalid_escape(self));
+                    }
+
+                    self.cursor += 2;
+
+                    let trailing = self
+                        .lex_escaped_code_unit()
+                        .filter(|trailing| (0xDC00..=0xDFFF).contains(trailing))
+                        .ok_or_else(|| invalid_escape(self))?;
+
+                    let code_point = 0x10000 + ((code_unit - 0xD800) << 10) + (trailing - 0xDC00);
+
+                    char::from_u32(code_point).unwrap()
+                } else {
+                    // a lone trailing surrogate isn't a valid `char`
+                    char::from_u32(code_unit).ok_or_else(|| invalid_escape(self))?
+                }
+            }
+            Some(..) | None => return Err(invalid_escape(self)),
         })
     }
 
+    /// The four hex digits following `\u`
+    fn lex_escaped_code_unit(&mut self) -> Option<u32> {
+        let mut code_unit = 0;
+
+        for _ in 0..4 {
+            let digit = self.next_byte().and_then(|b| (b as char).to_digit(16))?;
+
+            code_unit = code_unit * 16 + digit;
+        }
+
+        Some(code_unit)
+    }
+
+    fn consume_digits(&mut self) -> usize {
+        let start = self.cursor;
+
+        while let Some(b'0'..=b'9') = self.peek_byte() {
+            self.next_byte();
+        }
+
+        self.cursor - start
+    }
+
+    // see https://spec.graphql.org/June2018/#sec-Int-Value and
+    // https://spec.graphql.org/June2018/#sec-Float-Value
+    fn lex_number(&mut self) -> Result<Token, GraphqlParseError> {
+        let start = self.cursor;
+
+        let invalid_number = |lexer: &Self| GraphqlParseError::InvalidNumber {
+            span: Span::new(start, lexer.cursor),
+        };
+
+        self.consume_byte_if_eq_no_skip(b'-');
+
+        let integer_start = self.cursor;
+
+        if self.consume_digits() == 0 {
+            return Err(invalid_number(self));
+        }
+
+        // leading zeros are not allowed
+        if self.buffer[integer_start] == b'0' && self.cursor - integer_start > 1 {
+            return Err(invalid_number(self));
+        }
+
+        let mut is_float = false;
+
+        if self.consume_byte_if_eq_no_skip(b'.') {
+            is_float = true;
+
+            if self.consume_digits() == 0 {
+                return Err(invalid_number(self));
+            }
+        }
+
+        if self.consume_byte_if_eq_no_skip(b'e') || self.consume_byte_if_eq_no_skip(b'E') {
+            is_float = true;
+
+            if !self.consume_byte_if_eq_no_skip(b'+') {
+                self.consume_byte_if_eq_no_skip(b'-');
+            }
+
+            if self.consume_digits() == 0 {
+                return Err(invalid_number(self));
+            }
+        }
+
+        // a number may not be immediately followed by a name or a `.`
+        if let Some(b'.' | b'a'..=b'z' | b'A'..=b'Z' | b'_') = self.peek_byte() {
+            self.next_byte();
+            return Err(invalid_number(self));
+        }
+
+        let text = std::str::from_utf8(&self.buffer[start..self.cursor]).unwrap();
+
+        Ok(if is_float {
+            Token::FloatValue(self.interner.get_or_intern(text))
+        } else {
+            Token::IntValue(self.interner.get_or_intern(text))
+        })
+    }
+
+    fn consume_byte_if_eq_no_skip(&mut self, byte: u8) -> bool {
+        if self.peek_byte() == Some(byte) {
+            self.next_byte();
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn next_token(&mut self) -> Result<Option<Token>, GraphqlParseError> {
+        if self.check_fused()? {
+            return Ok(None);
+        }
+
         self.skip_ignored_characters();
 
+        let start = self.cursor;
+
+        let token = match self.peeked.pop_front() {
+            Some(peeked) if peeked.span.start == start => {
+                self.cursor = peeked.span.end;
+                Ok(Some(peeked.token))
+            }
+            _ => {
+                self.peeked.clear();
+                self.lex_token()
+            }
+        };
+
+        self.token_span = Span::new(start, self.cursor);
+        self.consumed_span = self.token_span;
+
+        if let Ok(Some(..)) = token {
+            self.count_token(self.token_span)?;
+        }
+
+        token
+    }
+
+    /// Whether lexing has stopped after a limit was exceeded, failing instead
+    /// if the input was too large and that hasn't been reported yet
+    fn check_fused(&mut self) -> Result<bool, GraphqlParseError> {
+        if let Some(error) = self.input_size_error.take() {
+            self.is_fused = true;
+            return Err(error);
+        }
+
+        Ok(self.is_fused)
+    }
+
+    /// An error for going over `limit`, stopping lexing so that it isn't
+    /// reported again
+    pub(crate) fn limit_exceeded(
+        &mut self,
+        limit: ParseLimit,
+        max: usize,
+        span: Span,
+    ) -> GraphqlParseError {
+        self.is_fused = true;
+
+        GraphqlParseError::LimitExceeded { limit, max, span }
+    }
+
+    fn is_at_token_limit(&self) -> bool {
+        self.limits
+            .max_tokens
+            .is_some_and(|max| self.token_count >= max)
+    }
+
+    /// Count a consumed token, failing if it puts us over the limit
+    fn count_token(&mut self, span: Span) -> Result<(), GraphqlParseError> {
+        self.token_count += 1;
+
+        match self.limits.max_tokens {
+            Some(max) if self.token_count > max => {
+                Err(self.limit_exceeded(ParseLimit::Tokens, max, span))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail if a string starting at `start` has a value of `len` bytes, more
+    /// than the limit
+    fn check_string_length(&mut self, start: usize, len: usize) -> Result<(), GraphqlParseError> {
+        match self.limits.max_string_length {
+            Some(max) if len > max => {
+                let span = Span::new(start, self.cursor);
+
+                Err(self.limit_exceeded(ParseLimit::StringLength, max, span))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn lex_token(&mut self) -> Result<Option<Token>, GraphqlParseError> {
         Ok(Some(match self.next_byte() {
-            Some(b' ' | b'\t' | b'\n' | b'\r' | b',') => return self.next_token(),
-            Some(b'#') => todo!("comment"),
             Some(b'!') => Token::Bang,
-            Some(b'$') => Token::Dollar,
+            Some(b'$') => self.lex_variable(),
             Some(b'(') => Token::OpenParen,
             Some(b')') => Token::CloseParen,
+            // `...` is the only punctuator longer than a byte, and may not
+            // contain whitespace
             Some(b'.') => {
-                self.expect_byte(b'.')?;
-                self.expect_byte(b'.')?;
+                let start = self.cursor - 1;
+
+                if !self.consume_byte_if_eq_no_skip(b'.') {
+                    return Err(GraphqlParseError::InvalidToken {
+                        found: ".",
+                        span: Span::new(start, self.cursor),
+                    });
+                }
+
+                if !self.consume_byte_if_eq_no_skip(b'.') {
+                    return Err(GraphqlParseError::InvalidToken {
+                        found: "..",
+                        span: Span::new(start, self.cursor),
+                    });
+                }
 
                 Token::DotDotDot
             }
@@ -233,10 +762,331 @@ impl<'a> Lexer<'a> {
                 self.go_back();
                 self.lex_identifier()
             }
-            Some(b'0'..=b'9') => todo!(),
+            Some(b'-' | b'0'..=b'9') => {
+                self.go_back();
+                self.lex_number()?
+            }
             Some(b'"') => self.lex_string()?,
             None => return Ok(None),
-            _ => todo!(),
+            Some(b) if !b.is_ascii() => {
+                let start = self.cursor - 1;
+                let found = self.lex_utf8_char(b)?;
+
+                return Err(GraphqlParseError::InvalidCharacter {
+                    found,
+                    span: Span::new(start, self.cursor),
+                });
+            }
+            Some(b) => {
+                return Err(GraphqlParseError::InvalidCharacter {
+                    found: b as char,
+                    span: self.previous_byte_span(true),
+                })
+            }
         }))
     }
 }
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<SpannedToken, GraphqlParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_token() {
+            Ok(Some(token)) => Some(Ok(SpannedToken {
+                token,
+                span: self.token_span,
+            })),
+            Ok(None) => None,
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+/// Remove the common indentation and any leading or trailing blank lines from
+/// the raw contents of a block string
+///
+/// See https://spec.graphql.org/October2021/#BlockStringValue()
+fn block_string_value(raw: &str) -> String {
+    let normalized = raw.replace("\r\n", "\n");
+
+    let lines = normalized.split(['\n', '\r']).collect::<Vec<_>>();
+
+    let is_blank = |line: &str| line.bytes().all(|b| b == b' ' || b == b'\t');
+
+    let common_indent = lines
+        .iter()
+        .skip(1)
+        .filter(|line| !is_blank(line))
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+
+    let lines = lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            if idx == 0 {
+                line
+            } else {
+                &line[common_indent.min(line.len())..]
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let first = lines.iter().position(|line| !is_blank(line));
+    let last = lines.iter().rposition(|line| !is_blank(line));
+
+    match (first, last) {
+        (Some(first), Some(last)) => lines[first..=last].join("\n"),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lexer, ParseLimits};
+    use crate::{
+        ast::{Span, Token},
+        GraphqlParseError, ParseLimit,
+    };
+
+    const SOURCE: &str = "query Q($a: [Int!]) { a(b: \"c\", d: 1.5) ... F }";
+
+    fn tokens(source: &str) -> Vec<Token> {
+        let mut lexer = Lexer::new(source.as_bytes());
+        let mut tokens = Vec::new();
+
+        while let Some(token) = lexer.next_token().unwrap() {
+            tokens.push(token);
+        }
+
+        tokens
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        assert_eq!(tokens("# a\nb # c d\r\ne #"), tokens("b e"));
+        assert_eq!(tokens("\"# a\" # b"), tokens("\"# a\""));
+    }
+
+    fn first_error(source: &str) -> GraphqlParseError {
+        let mut lexer = Lexer::new(source.as_bytes());
+
+        loop {
+            match lexer.next_token() {
+                Ok(Some(..)) => {}
+                Ok(None) => panic!("expected an error"),
+                Err(err) => return err,
+            }
+        }
+    }
+
+    /// The value of the string token `source` is made of
+    fn string_value(source: &str) -> String {
+        let mut lexer = Lexer::new(source.as_bytes());
+
+        match lexer.next_token().unwrap() {
+            Some(Token::String(value)) => lexer.interner.resolve(&value).to_owned(),
+            token => panic!("expected a string, found {:?}", token),
+        }
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        assert_eq!(string_value(r#""\u00e9\u4E2D""#), "é中");
+        assert_eq!(string_value(r#""\uD83D\uDE00""#), "😀");
+    }
+
+    #[test]
+    fn multi_byte_utf8_round_trips() {
+        assert_eq!(string_value("\"é中😀\""), "é中😀");
+        assert_eq!(string_value("\"\"\"é中😀\"\"\""), "é中😀");
+    }
+
+    #[test]
+    fn lone_surrogates_are_invalid() {
+        for (source, escape) in [
+            (r#""\uD83D""#, r"\uD83D"),
+            (r#""\uD83Dx""#, r"\uD83D"),
+            (r#""\uD83D\u0041""#, r"\uD83D\u0041"),
+            (r#""\uDE00""#, r"\uDE00"),
+        ] {
+            let err = first_error(source);
+
+            assert!(
+                matches!(err, GraphqlParseError::InvalidEscapeSequence { .. }),
+                "{}: {:?}",
+                source,
+                err
+            );
+            assert_eq!(&source[err.span().start..err.span().end], escape);
+        }
+    }
+
+    /// Every token of `lexer` with its span, consumed by [`Lexer::next_token`]
+    fn remaining_tokens(lexer: &mut Lexer) -> Vec<(Token, Span)> {
+        let mut tokens = Vec::new();
+
+        while let Some(token) = lexer.next_token().unwrap() {
+            tokens.push((token, lexer.last_span()));
+        }
+
+        tokens
+    }
+
+    #[test]
+    fn peeking_does_not_consume() {
+        let mut peeking = Lexer::new(SOURCE.as_bytes());
+        let mut tokens = Vec::new();
+
+        loop {
+            let peeked = peeking.peek_token().unwrap();
+            let peeked_span = peeking.last_span();
+
+            assert!(peeking.peek_nth_token(2).is_ok());
+            assert_eq!(peeking.peek_token().unwrap(), peeked);
+
+            let next = peeking.next_token().unwrap();
+            assert_eq!(next, peeked);
+
+            let Some(token) = next else {
+                break;
+            };
+
+            assert_eq!(peeking.last_span(), peeked_span);
+            tokens.push((token, peeked_span));
+        }
+
+        assert_eq!(tokens, remaining_tokens(&mut Lexer::new(SOURCE.as_bytes())));
+    }
+
+    #[test]
+    fn peek_nth_token() {
+        let mut lexer = Lexer::new(SOURCE.as_bytes());
+        let expected = remaining_tokens(&mut Lexer::new(SOURCE.as_bytes()));
+
+        for (n, (token, span)) in expected.iter().enumerate().rev() {
+            assert_eq!(lexer.peek_nth_token(n).unwrap().as_ref(), Some(token));
+            assert_eq!(lexer.last_span(), *span);
+        }
+
+        assert_eq!(lexer.peek_nth_token(expected.len()).unwrap(), None);
+        assert_eq!(remaining_tokens(&mut lexer), expected);
+    }
+
+    #[test]
+    fn seek_invalidates_peeked_tokens() {
+        let mut lexer = Lexer::new(b"a b c");
+
+        let a = lexer.peek_nth_token(0).unwrap();
+        lexer.peek_nth_token(2).unwrap();
+
+        lexer.seek(2);
+        let b = lexer.next_token().unwrap();
+        assert_eq!(lexer.last_span(), Span::new(2, 3));
+        assert_ne!(a, b);
+
+        lexer.seek(0);
+        assert_eq!(lexer.next_token().unwrap(), a);
+        assert_eq!(lexer.next_token().unwrap(), b);
+        assert_eq!(lexer.last_span(), Span::new(2, 3));
+    }
+
+    #[test]
+    fn consuming_a_byte_invalidates_peeked_tokens() {
+        let mut lexer = Lexer::new(b"{ a }");
+
+        assert_eq!(lexer.peek_token().unwrap(), Some(Token::OpenCurlyBrace));
+        lexer.expect_byte(b'{').unwrap();
+
+        assert!(matches!(lexer.next_token().unwrap(), Some(Token::Name(..))));
+        assert!(lexer.consume_byte_if_eq(b'}'));
+        assert_eq!(lexer.peek_token().unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_tokens_are_not_cached() {
+        let mut lexer = Lexer::new(b"a ?");
+
+        assert!(lexer.peek_nth_token(1).is_err());
+        assert!(lexer.peek_nth_token(1).is_err());
+        assert!(matches!(lexer.next_token().unwrap(), Some(Token::Name(..))));
+        assert!(lexer.next_token().is_err());
+    }
+
+    #[test]
+    fn variables() {
+        let mut lexer = Lexer::new(b"$a $_b1 $ c");
+        let tokens = remaining_tokens(&mut lexer);
+        let name = |name: &str| lexer.interner().get(name).unwrap();
+
+        assert_eq!(
+            tokens,
+            [
+                (Token::Variable(name("a")), Span::new(0, 2)),
+                (Token::Variable(name("_b1")), Span::new(3, 7)),
+                (Token::Dollar, Span::new(8, 9)),
+                (Token::Name(name("c")), Span::new(10, 11)),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_escape_span_ends_after_character() {
+        let source = "\"\\é\"";
+
+        let span = first_error(source).span();
+
+        assert_eq!(span, Span::new(1, 4));
+        assert_eq!(&source[span.start..span.end], "\\é");
+    }
+
+    #[test]
+    fn invalid_unicode_escape_span_ends_after_character() {
+        let source = "\"\\u00é\"";
+
+        let span = first_error(source).span();
+
+        assert_eq!(&source[span.start..span.end], "\\u00é");
+    }
+
+    #[test]
+    fn oversized_input_is_reported_once() {
+        let mut lexer = Lexer::new(b"{ a b c }");
+        lexer.set_limits(ParseLimits {
+            max_input_size: Some(3),
+            ..ParseLimits::default()
+        });
+
+        let items = lexer.collect::<Vec<_>>();
+
+        assert_eq!(items.len(), 1);
+        assert!(
+            matches!(
+                items[0],
+                Err(GraphqlParseError::LimitExceeded {
+                    limit: ParseLimit::InputSize,
+                    max: 3,
+                    ..
+                })
+            ),
+            "{:?}",
+            items
+        );
+    }
+
+    #[test]
+    fn lexing_stops_at_token_limit() {
+        let mut lexer = Lexer::new(b"{ a b c }");
+        lexer.set_limits(ParseLimits {
+            max_tokens: Some(2),
+            ..ParseLimits::default()
+        });
+
+        let items = lexer.collect::<Vec<_>>();
+
+        assert_eq!(items.len(), 3);
+        assert!(items[..2].iter().all(Result::is_ok));
+        assert_eq!(items[2].as_ref().unwrap_err().span(), Span::new(4, 5));
+    }
+}
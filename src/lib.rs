@@ -0,0 +1,74 @@
+pub use crate::{
+    ast::Document,
+    error::{GraphqlParseError, LineColumn, ParseLimit},
+    parse::{Definitions, GraphqlParser, ParseResult},
+};
+
+#[cfg(feature = "arena")]
+pub mod arena;
+#[cfg(feature = "persisted-queries")]
+pub mod artifacts;
+pub mod ast;
+pub mod builder;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod codegen;
+#[cfg(feature = "spans")]
+pub mod comments;
+pub mod completion;
+pub mod composition;
+pub mod coordinate;
+pub mod deprecation;
+pub mod diff;
+pub mod docs;
+pub mod equivalence;
+mod error;
+pub mod execute;
+pub mod factor;
+pub mod federation;
+pub mod flatten;
+pub mod fmt;
+pub mod fold;
+#[cfg(feature = "serde_json")]
+pub mod graphql_js;
+pub mod infer;
+#[cfg(feature = "serde_json")]
+pub mod introspection;
+#[cfg(feature = "serde_json")]
+pub mod json_schema;
+pub mod lexer;
+pub mod limits;
+pub mod lint;
+#[cfg(feature = "serde_json")]
+pub mod lsp;
+pub mod normalize;
+mod parse;
+#[cfg(feature = "persisted-queries")]
+pub mod persisted;
+#[cfg(feature = "project")]
+pub mod project;
+pub mod proto;
+pub mod query_plan;
+pub mod refactor;
+#[cfg(feature = "registry")]
+pub mod registry;
+pub mod relay;
+#[cfg(feature = "rayon")]
+mod remap;
+#[cfg(feature = "serde_json")]
+pub mod request;
+pub mod scalar;
+pub mod sort;
+pub mod source_map;
+pub mod sql;
+#[cfg(feature = "subscriptions")]
+pub mod subscriptions;
+pub mod subset;
+pub mod type_graph;
+pub mod usage;
+pub mod validation;
+#[cfg(feature = "serde_json")]
+pub mod variables;
+pub mod visit;
+#[cfg(feature = "watch")]
+pub mod watch;
@@ -0,0 +1,523 @@
+//! Limits on the size and shape of an operation, for rejecting expensive
+//! requests before executing them
+//!
+//! Each limit implements [`OperationLimit`], so a server can hold a list of
+//! them and check every incoming operation against it. Fragment spreads are
+//! followed into the fragments of the document the operation is from, with
+//! each fragment measured once no matter how often it is spread, and spreads
+//! that cycle back into a fragment being measured count for nothing
+
+use std::{collections::HashMap, fmt};
+
+use lasso::Spur;
+
+use crate::ast::{Argument, Directive, Document, Operation, OperationKind, Selection, Type, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitViolation {
+    /// The operation selects more than `max` fields
+    Complexity { max: usize, actual: usize },
+    /// The operation nests selection sets more than `max` fields deep
+    Depth { max: usize, actual: usize },
+    /// The operation selects more than `max` fields under an alias
+    AliasCount { max: usize, actual: usize },
+    /// The operation, with the fragments it spreads, is written with more
+    /// than `max` tokens
+    TokenCount { max: usize, actual: usize },
+}
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Complexity { max, actual } => write!(
+                f,
+                "the operation selects {} fields, more than the limit of {}",
+                actual, max
+            ),
+            Self::Depth { max, actual } => write!(
+                f,
+                "the operation is {} fields deep, more than the limit of {}",
+                actual, max
+            ),
+            Self::AliasCount { max, actual } => write!(
+                f,
+                "the operation has {} aliases, more than the limit of {}",
+                actual, max
+            ),
+            Self::TokenCount { max, actual } => write!(
+                f,
+                "the operation has {} tokens, more than the limit of {}",
+                actual, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LimitViolation {}
+
+/// A limit an operation must be within in order to be executed
+pub trait OperationLimit {
+    /// Check `operation`, whose fragments are defined in `document`
+    fn check(&self, operation: &Operation, document: &Document) -> Result<(), LimitViolation>;
+}
+
+/// Limits the number of fields an operation selects, counting the fields of a
+/// fragment each time it's spread
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComplexityLimit {
+    pub max: usize,
+}
+
+impl ComplexityLimit {
+    pub const fn new(max: usize) -> Self {
+        Self { max }
+    }
+}
+
+impl OperationLimit for ComplexityLimit {
+    fn check(&self, operation: &Operation, document: &Document) -> Result<(), LimitViolation> {
+        let actual = Measurer::new(document).measure(operation).fields;
+
+        if actual > self.max {
+            return Err(LimitViolation::Complexity {
+                max: self.max,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Limits how deeply the selection sets of an operation are nested, where the
+/// fields selected on the root operation type are 1 deep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthLimit {
+    pub max: usize,
+}
+
+impl DepthLimit {
+    pub const fn new(max: usize) -> Self {
+        Self { max }
+    }
+}
+
+impl OperationLimit for DepthLimit {
+    fn check(&self, operation: &Operation, document: &Document) -> Result<(), LimitViolation> {
+        let actual = operation_depth(operation, document);
+
+        if actual > self.max {
+            return Err(LimitViolation::Depth {
+                max: self.max,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Limits the number of aliased fields an operation selects, as aliases let a
+/// single request resolve the same field many times over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AliasCountLimit {
+    pub max: usize,
+}
+
+impl AliasCountLimit {
+    pub const fn new(max: usize) -> Self {
+        Self { max }
+    }
+}
+
+impl OperationLimit for AliasCountLimit {
+    fn check(&self, operation: &Operation, document: &Document) -> Result<(), LimitViolation> {
+        let actual = Measurer::new(document).measure(operation).aliases;
+
+        if actual > self.max {
+            return Err(LimitViolation::AliasCount {
+                max: self.max,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Limits the number of tokens the operation and the fragments it spreads are
+/// written with, counting each fragment once. Tokens are counted from the
+/// parsed operation, so commas, comments and an omitted `query` keyword aren't
+/// counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenCountLimit {
+    pub max: usize,
+}
+
+impl TokenCountLimit {
+    pub const fn new(max: usize) -> Self {
+        Self { max }
+    }
+}
+
+impl OperationLimit for TokenCountLimit {
+    fn check(&self, operation: &Operation, document: &Document) -> Result<(), LimitViolation> {
+        let actual = count_tokens(operation, document);
+
+        if actual > self.max {
+            return Err(LimitViolation::TokenCount {
+                max: self.max,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// How deeply the selection sets of `operation` are nested, as checked by
+/// [`DepthLimit`] and the `max-selection-depth` lint
+pub(crate) fn operation_depth(operation: &Operation, document: &Document) -> usize {
+    Measurer::new(document).measure(operation).depth
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Measure {
+    fields: usize,
+    aliases: usize,
+    depth: usize,
+}
+
+/// Measures selection sets, remembering the measure of each fragment so that
+/// fragments spreading others many times over can't blow up the traversal
+struct Measurer<'a> {
+    document: &'a Document,
+    /// The measure of each fragment, or `None` while it's being measured
+    fragments: HashMap<Spur, Option<Measure>>,
+}
+
+impl<'a> Measurer<'a> {
+    fn new(document: &'a Document) -> Self {
+        Self {
+            document,
+            fragments: HashMap::new(),
+        }
+    }
+
+    fn measure(&mut self, operation: &Operation) -> Measure {
+        self.measure_selection_set(&operation.selection_set)
+    }
+
+    fn measure_selection_set(&mut self, selection_set: &[Selection]) -> Measure {
+        let mut measure = Measure::default();
+
+        for selection in selection_set {
+            let inner = match selection {
+                Selection::Field {
+                    alias,
+                    selection_set,
+                    ..
+                } => {
+                    let children = selection_set
+                        .as_deref()
+                        .map(|selection_set| self.measure_selection_set(selection_set))
+                        .unwrap_or_default();
+
+                    Measure {
+                        fields: children.fields.saturating_add(1),
+                        aliases: children.aliases.saturating_add(alias.is_some() as usize),
+                        depth: children.depth + 1,
+                    }
+                }
+                Selection::FragmentSpread { name, .. } => self.measure_fragment(*name),
+                Selection::InlineFragment { selection_set, .. } => {
+                    self.measure_selection_set(selection_set)
+                }
+            };
+
+            measure.fields = measure.fields.saturating_add(inner.fields);
+            measure.aliases = measure.aliases.saturating_add(inner.aliases);
+            measure.depth = measure.depth.max(inner.depth);
+        }
+
+        measure
+    }
+
+    fn measure_fragment(&mut self, name: Spur) -> Measure {
+        match self.fragments.get(&name) {
+            Some(Some(measure)) => return *measure,
+            // a cycle, which validation reports
+            Some(None) => return Measure::default(),
+            None => {}
+        }
+
+        // unknown fragments are reported by validation
+        let fragment = match self.document.fragments.get(&name) {
+            Some(fragment) => fragment,
+            None => return Measure::default(),
+        };
+
+        self.fragments.insert(name, None);
+
+        let measure = self.measure_selection_set(&fragment.selection_set);
+
+        self.fragments.insert(name, Some(measure));
+
+        measure
+    }
+}
+
+fn count_tokens(operation: &Operation, document: &Document) -> usize {
+    let mut counter = TokenCounter {
+        document,
+        fragments: Vec::new(),
+        tokens: 0,
+    };
+
+    let is_shorthand = operation.kind == OperationKind::Query
+        && operation.name.is_none()
+        && operation.variable_definitions.is_empty()
+        && operation.directives.is_empty();
+
+    if !is_shorthand {
+        counter.tokens += 1;
+    }
+
+    if operation.name.is_some() {
+        counter.tokens += 1;
+    }
+
+    if !operation.variable_definitions.is_empty() {
+        counter.tokens += 2;
+
+        for variable in &operation.variable_definitions {
+            // `$name:`
+            counter.tokens += 2;
+            counter.count_type(&variable.ty);
+
+            if let Some(default) = &variable.default {
+                counter.tokens += 1;
+                counter.count_value(default);
+            }
+        }
+    }
+
+    counter.count_directives(&operation.directives);
+    counter.count_selection_set(&operation.selection_set);
+
+    let mut next = 0;
+
+    while let Some(&name) = counter.fragments.get(next) {
+        next += 1;
+
+        if let Some(fragment) = document.fragments.get(&name) {
+            // `fragment Name on Type`
+            counter.tokens += 4;
+            counter.count_directives(&fragment.directives);
+            counter.count_selection_set(&fragment.selection_set);
+        }
+    }
+
+    counter.tokens
+}
+
+struct TokenCounter<'a> {
+    document: &'a Document,
+    /// The fragments spread so far, in the order they were first spread
+    fragments: Vec<Spur>,
+    tokens: usize,
+}
+
+impl TokenCounter<'_> {
+    fn count_selection_set(&mut self, selection_set: &[Selection]) {
+        self.tokens += 2;
+
+        for selection in selection_set {
+            match selection {
+                Selection::Field {
+                    alias,
+                    arguments,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    self.tokens += if alias.is_some() { 3 } else { 1 };
+
+                    if let Some(arguments) = arguments {
+                        self.count_arguments(arguments);
+                    }
+
+                    self.count_directives(directives);
+
+                    if let Some(selection_set) = selection_set {
+                        self.count_selection_set(selection_set);
+                    }
+                }
+                Selection::FragmentSpread {
+                    name, directives, ..
+                } => {
+                    self.tokens += 2;
+                    self.count_directives(directives);
+
+                    if !self.fragments.contains(name) && self.document.fragments.contains_key(name)
+                    {
+                        self.fragments.push(*name);
+                    }
+                }
+                Selection::InlineFragment {
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    // `... on Type`
+                    self.tokens += 3;
+                    self.count_directives(directives);
+                    self.count_selection_set(selection_set);
+                }
+            }
+        }
+    }
+
+    fn count_directives(&mut self, directives: &[Directive]) {
+        for directive in directives {
+            self.tokens += 2;
+
+            if let Some(arguments) = &directive.arguments {
+                self.count_arguments(arguments);
+            }
+        }
+    }
+
+    fn count_arguments(&mut self, arguments: &[Argument]) {
+        self.tokens += 2;
+
+        for argument in arguments {
+            self.tokens += 2;
+            self.count_value(&argument.value);
+        }
+    }
+
+    fn count_value(&mut self, value: &Value) {
+        match value {
+            Value::List(values) => {
+                self.tokens += 2;
+
+                for value in values {
+                    self.count_value(value);
+                }
+            }
+            Value::Object(fields) => {
+                self.tokens += 2;
+
+                for value in fields.values() {
+                    self.tokens += 2;
+                    self.count_value(value);
+                }
+            }
+            _ => self.tokens += 1,
+        }
+    }
+
+    fn count_type(&mut self, ty: &Type) {
+        match ty {
+            Type::Named { .. } => self.tokens += 1,
+            Type::List { base, .. } => {
+                self.tokens += 2;
+                self.count_type(base);
+            }
+        }
+
+        if !ty.is_nullable() {
+            self.tokens += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{
+        AliasCountLimit, ComplexityLimit, DepthLimit, LimitViolation, OperationLimit,
+        TokenCountLimit,
+    };
+    use crate::{ast::Operation, Document, GraphqlParser};
+
+    fn parse(source: &str, interner: &mut Rodeo) -> Document {
+        GraphqlParser::parse_query_with_interner(source.as_bytes(), interner)
+            .expect("failed to parse document")
+    }
+
+    fn operation(document: &Document) -> &Operation {
+        document.operations.values().next().unwrap()
+    }
+
+    #[test]
+    fn fragments_are_followed() {
+        let mut interner = Rodeo::default();
+        let document = parse(
+            "
+            query { viewer { ...Friends } }
+            fragment Friends on User { a: friends { ...Names } b: friends { ...Names } }
+            fragment Names on User { first last }
+            ",
+            &mut interner,
+        );
+        let operation = operation(&document);
+
+        assert_eq!(
+            ComplexityLimit::new(6).check(operation, &document),
+            Err(LimitViolation::Complexity { max: 6, actual: 7 })
+        );
+        assert_eq!(ComplexityLimit::new(7).check(operation, &document), Ok(()));
+        assert_eq!(
+            DepthLimit::new(2).check(operation, &document),
+            Err(LimitViolation::Depth { max: 2, actual: 3 })
+        );
+        assert_eq!(
+            AliasCountLimit::new(1).check(operation, &document),
+            Err(LimitViolation::AliasCount { max: 1, actual: 2 })
+        );
+    }
+
+    #[test]
+    fn fragment_cycles_terminate() {
+        let mut interner = Rodeo::default();
+        let document = parse(
+            "
+            { ...A }
+            fragment A on Query { a ...B }
+            fragment B on Query { b ...A }
+            ",
+            &mut interner,
+        );
+
+        assert_eq!(
+            ComplexityLimit::new(1).check(operation(&document), &document),
+            Err(LimitViolation::Complexity { max: 1, actual: 2 })
+        );
+    }
+
+    #[test]
+    fn token_count() {
+        let mut interner = Rodeo::default();
+        let document = parse(
+            "
+            query User($id: ID!) { user(id: $id) @cached { ...Fields } }
+            fragment Fields on User { name friends(first: 10) { name } }
+            fragment Unused on User { name }
+            ",
+            &mut interner,
+        );
+        let operation = operation(&document);
+
+        assert_eq!(TokenCountLimit::new(38).check(operation, &document), Ok(()));
+        assert_eq!(
+            TokenCountLimit::new(37).check(operation, &document),
+            Err(LimitViolation::TokenCount {
+                max: 37,
+                actual: 38
+            })
+        );
+    }
+}
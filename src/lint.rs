@@ -0,0 +1,820 @@
+//! Linting of schemas and executable documents against configurable rules
+//!
+//! A [`Linter`] holds a registry of [`Rule`]s, each of which is allowed,
+//! warned about or denied according to its [`LintConfig`]. Unlike validation
+//! errors, lints are about style and conventions, so a document with lints is
+//! still valid
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{Document, Span, TypeDefinitionRef, BUILT_IN_SCALARS},
+    deprecation::find_deprecated_usages,
+    limits::operation_depth,
+    relay::check_connections,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Level {
+    /// The rule isn't checked
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Warn => "warn",
+            Self::Deny => "deny",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = UnknownLevel;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "allow" => Self::Allow,
+            "warn" => Self::Warn,
+            "deny" => Self::Deny,
+            _ => return Err(UnknownLevel(s.to_owned())),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownLevel(pub String);
+
+impl fmt::Display for UnknownLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown lint level `{}`, expected `allow`, `warn` or `deny`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownLevel {}
+
+/// The level of each rule, by name. Rules that aren't configured use their
+/// [`Rule::default_level`]
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    pub levels: HashMap<String, Level>,
+}
+
+impl LintConfig {
+    pub fn set(&mut self, rule: &str, level: Level) {
+        self.levels.insert(rule.to_owned(), level);
+    }
+
+    pub fn level(&self, rule: &dyn Rule) -> Level {
+        self.levels
+            .get(rule.name())
+            .copied()
+            .unwrap_or_else(|| rule.default_level())
+    }
+}
+
+/// What a rule is checked against. Type system rules check `schema`, and
+/// executable rules check `document`, if there is one
+pub struct LintContext<'a> {
+    pub schema: &'a Document,
+    pub document: Option<&'a Document>,
+    pub interner: &'a Rodeo,
+}
+
+impl<'a> LintContext<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+}
+
+/// A problem found by a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The schema coordinate of what the violation is about, such as
+    /// `User.name`, or the name of the operation for executable rules
+    pub coordinate: String,
+    pub message: String,
+    pub span: Span,
+}
+
+pub trait Rule {
+    /// The name the rule is configured by, such as `field-naming`
+    fn name(&self) -> &'static str;
+
+    fn default_level(&self) -> Level {
+        Level::Warn
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Violation>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintDiagnostic {
+    pub rule: &'static str,
+    /// Either [`Level::Warn`] or [`Level::Deny`]
+    pub level: Level,
+    pub coordinate: String,
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for LintDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            Level::Deny => "error",
+            _ => "warning",
+        };
+
+        write!(f, "{}[{}]: {}", level, self.rule, self.message)
+    }
+}
+
+/// A registry of rules, along with the level each is checked at
+pub struct Linter {
+    rules: Vec<Box<dyn Rule>>,
+    pub config: LintConfig,
+}
+
+impl Default for Linter {
+    /// A linter with every built-in rule, at its default level
+    fn default() -> Self {
+        let mut linter = Self::new(LintConfig::default());
+
+        linter.register(FieldNaming);
+        linter.register(RequireDescriptions);
+        linter.register(NoDeprecatedUsage);
+        linter.register(AlphabetizedFields);
+        linter.register(MaxSelectionDepth::default());
+        linter.register(RelayConnections);
+
+        linter
+    }
+}
+
+impl Linter {
+    /// A linter without any rules
+    pub fn new(config: LintConfig) -> Self {
+        Self {
+            rules: Vec::new(),
+            config,
+        }
+    }
+
+    /// Add `rule`, replacing any rule with the same name
+    pub fn register(&mut self, rule: impl Rule + 'static) {
+        self.rules.retain(|other| other.name() != rule.name());
+        self.rules.push(Box::new(rule));
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &dyn Rule> {
+        self.rules.iter().map(|rule| &**rule)
+    }
+
+    /// Configured rule names that don't match any registered rule, which
+    /// are likely typos
+    pub fn unknown_rules(&self) -> Vec<&str> {
+        let mut unknown = self
+            .config
+            .levels
+            .keys()
+            .map(String::as_str)
+            .filter(|name| self.rules().all(|rule| rule.name() != *name))
+            .collect::<Vec<_>>();
+
+        unknown.sort_unstable();
+
+        unknown
+    }
+
+    /// Check every rule that isn't allowed, returning the violations in
+    /// source order. `schema` and `document` must have been parsed with the
+    /// same `interner`
+    pub fn lint(
+        &self,
+        schema: &Document,
+        document: Option<&Document>,
+        interner: &Rodeo,
+    ) -> Vec<LintDiagnostic> {
+        let context = LintContext {
+            schema,
+            document,
+            interner,
+        };
+
+        let mut diagnostics = Vec::new();
+
+        for rule in self.rules() {
+            let level = self.config.level(rule);
+
+            if level == Level::Allow {
+                continue;
+            }
+
+            diagnostics.extend(
+                rule.check(&context)
+                    .into_iter()
+                    .map(|violation| LintDiagnostic {
+                        rule: rule.name(),
+                        level,
+                        coordinate: violation.coordinate,
+                        message: violation.message,
+                        span: violation.span,
+                    }),
+            );
+        }
+
+        // diagnostics without spans, or at the same position, are ordered by
+        // the coordinate they're about
+        diagnostics.sort_by(|a, b| {
+            (a.span.start, &a.coordinate, &a.message).cmp(&(
+                b.span.start,
+                &b.coordinate,
+                &b.message,
+            ))
+        });
+
+        diagnostics
+    }
+}
+
+/// Whether `name` is one of the built-in scalars or introspection types,
+/// which rules about the schema's own types don't apply to
+fn is_built_in(name: &str) -> bool {
+    name.starts_with("__") || BUILT_IN_SCALARS.contains(&name)
+}
+
+/// Types are PascalCase, fields, arguments and directives are camelCase, and
+/// enum values are SCREAMING_SNAKE_CASE
+pub struct FieldNaming;
+
+impl FieldNaming {
+    fn is_pascal_case(name: &str) -> bool {
+        name.starts_with(|c: char| c.is_ascii_uppercase()) && !name.contains('_')
+    }
+
+    fn is_camel_case(name: &str) -> bool {
+        name.starts_with(|c: char| c.is_ascii_lowercase()) && !name.contains('_')
+    }
+
+    fn is_screaming_snake_case(name: &str) -> bool {
+        !name.contains(|c: char| c.is_ascii_lowercase())
+    }
+}
+
+impl Rule for FieldNaming {
+    fn name(&self) -> &'static str {
+        "field-naming"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        let mut check = |coordinate: String,
+                         name: Spur,
+                         what: &str,
+                         case: &str,
+                         is_valid: fn(&str) -> bool,
+                         span| {
+            let name = context.name(name);
+
+            if !name.starts_with("__") && !is_valid(name) {
+                violations.push(Violation {
+                    coordinate,
+                    message: format!("{} `{}` should be {}", what, name, case),
+                    span,
+                });
+            }
+        };
+
+        for ty in context.schema.types() {
+            let type_name = context.name(ty.name());
+
+            check(
+                type_name.to_owned(),
+                ty.name(),
+                "type",
+                "PascalCase",
+                Self::is_pascal_case,
+                ty.span(),
+            );
+
+            let (fields, input_fields) = match ty {
+                TypeDefinitionRef::Object(object) => (object.fields.as_deref(), None),
+                TypeDefinitionRef::Interface(interface) => (Some(&*interface.fields), None),
+                TypeDefinitionRef::InputObject(input) => (None, input.fields.as_deref()),
+                TypeDefinitionRef::Enum(enum_) => {
+                    for variant in &enum_.variants {
+                        check(
+                            format!("{}.{}", type_name, context.name(variant.name)),
+                            variant.name,
+                            "enum value",
+                            "SCREAMING_SNAKE_CASE",
+                            Self::is_screaming_snake_case,
+                            variant.span,
+                        );
+                    }
+
+                    (None, None)
+                }
+                _ => (None, None),
+            };
+
+            for field in fields.unwrap_or_default() {
+                let coordinate = format!("{}.{}", type_name, context.name(field.name));
+
+                check(
+                    coordinate.clone(),
+                    field.name,
+                    "field",
+                    "camelCase",
+                    Self::is_camel_case,
+                    field.span,
+                );
+
+                for argument in field.arguments.as_deref().unwrap_or_default() {
+                    check(
+                        format!("{}({}:)", coordinate, context.name(argument.name)),
+                        argument.name,
+                        "argument",
+                        "camelCase",
+                        Self::is_camel_case,
+                        argument.span,
+                    );
+                }
+            }
+
+            for field in input_fields.unwrap_or_default() {
+                check(
+                    format!("{}.{}", type_name, context.name(field.name)),
+                    field.name,
+                    "input field",
+                    "camelCase",
+                    Self::is_camel_case,
+                    field.span,
+                );
+            }
+        }
+
+        for directive in context.schema.directive_definitions.values() {
+            check(
+                format!("@{}", context.name(directive.name)),
+                directive.name,
+                "directive",
+                "camelCase",
+                Self::is_camel_case,
+                directive.span,
+            );
+        }
+
+        violations
+    }
+}
+
+/// Every type defined by the schema has a description
+pub struct RequireDescriptions;
+
+impl Rule for RequireDescriptions {
+    fn name(&self) -> &'static str {
+        "require-descriptions"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Violation> {
+        context
+            .schema
+            .types()
+            .filter(|ty| ty.description().is_none() && !is_built_in(context.name(ty.name())))
+            .map(|ty| Violation {
+                coordinate: context.name(ty.name()).to_owned(),
+                message: format!("type `{}` has no description", context.name(ty.name())),
+                span: ty.span(),
+            })
+            .collect()
+    }
+}
+
+/// The document doesn't use deprecated fields, arguments, input fields or
+/// enum values
+pub struct NoDeprecatedUsage;
+
+impl Rule for NoDeprecatedUsage {
+    fn name(&self) -> &'static str {
+        "no-deprecated-usage"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Violation> {
+        let Some(document) = context.document else {
+            return Vec::new();
+        };
+
+        find_deprecated_usages(context.schema, document, context.interner)
+            .into_iter()
+            .map(|usage| Violation {
+                message: usage.to_string(),
+                coordinate: usage.coordinate,
+                span: usage.span,
+            })
+            .collect()
+    }
+}
+
+/// The fields of object, interface and input object types are defined in
+/// alphabetical order
+pub struct AlphabetizedFields;
+
+impl Rule for AlphabetizedFields {
+    fn name(&self) -> &'static str {
+        "alphabetized-fields"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Allow
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        for ty in context.schema.types() {
+            let fields = match ty {
+                TypeDefinitionRef::Object(object) => object
+                    .fields
+                    .iter()
+                    .flatten()
+                    .map(|field| (field.name, field.span))
+                    .collect(),
+                TypeDefinitionRef::Interface(interface) => interface
+                    .fields
+                    .iter()
+                    .map(|field| (field.name, field.span))
+                    .collect(),
+                TypeDefinitionRef::InputObject(input) => input
+                    .fields
+                    .iter()
+                    .flatten()
+                    .map(|field| (field.name, field.span))
+                    .collect(),
+                _ => Vec::new(),
+            };
+
+            for pair in fields.windows(2) {
+                let (previous, (name, span)) = (context.name(pair[0].0), pair[1]);
+                let name = context.name(name);
+
+                if name < previous {
+                    let ty = context.name(ty.name());
+
+                    violations.push(Violation {
+                        coordinate: format!("{}.{}", ty, name),
+                        message: format!(
+                            "`{0}.{1}` should come before `{0}.{2}`",
+                            ty, name, previous
+                        ),
+                        span,
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+/// Operations don't nest fields more than `max` deep, counting through
+/// fragment spreads
+pub struct MaxSelectionDepth {
+    pub max: usize,
+}
+
+impl Default for MaxSelectionDepth {
+    fn default() -> Self {
+        Self { max: 10 }
+    }
+}
+
+impl Rule for MaxSelectionDepth {
+    fn name(&self) -> &'static str {
+        "max-selection-depth"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Violation> {
+        let Some(document) = context.document else {
+            return Vec::new();
+        };
+
+        document
+            .operations
+            .values()
+            .filter_map(|operation| {
+                let depth = operation_depth(operation, document);
+
+                if depth <= self.max {
+                    return None;
+                }
+
+                let name = match operation.name {
+                    Some(name) => format!("{} `{}`", operation.kind.as_str(), context.name(name)),
+                    None => format!("anonymous {}", operation.kind.as_str()),
+                };
+
+                Some(Violation {
+                    coordinate: operation
+                        .name
+                        .map_or_else(String::new, |name| context.name(name).to_owned()),
+                    message: format!(
+                        "{} is nested {} fields deep, more than the maximum of {}",
+                        name, depth, self.max
+                    ),
+                    span: operation.span,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Relay-style connection types, and the fields returning them, follow the
+/// cursor connections specification
+pub struct RelayConnections;
+
+impl Rule for RelayConnections {
+    fn name(&self) -> &'static str {
+        "relay-connections"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Violation> {
+        check_connections(context.schema, context.interner)
+            .into_iter()
+            .map(|problem| Violation {
+                message: problem.to_string(),
+                coordinate: problem.coordinate,
+                span: problem.span,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{
+        Level, LintConfig, LintDiagnostic, Linter, MaxSelectionDepth, RelayConnections, Rule,
+    };
+    use crate::GraphqlParser;
+
+    const SCHEMA: &str = r#"
+"The root"
+type Query {
+  user(id: ID!, legacy_id: Int): User
+  users: [User!]!
+}
+"A user"
+type User {
+  name: String @deprecated
+  id: ID!
+  friends: [User!]!
+}
+type post_comment { body: String }
+enum Role { ADMIN, guest }
+directive @Cached on FIELD
+"#;
+
+    fn lint(linter: &Linter, document: Option<&str>) -> Vec<LintDiagnostic> {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let document = document.map(|document| {
+            GraphqlParser::parse_with_interner(document.as_bytes(), &mut interner).unwrap()
+        });
+
+        linter.lint(&schema, document.as_ref(), &interner)
+    }
+
+    /// The diagnostics from only the rule named `rule`, as strings
+    fn messages(rule: &str, document: Option<&str>) -> Vec<String> {
+        let mut linter = Linter::default();
+
+        let names = linter.rules().map(Rule::name).collect::<Vec<_>>();
+
+        for name in names {
+            let level = if name == rule {
+                Level::Warn
+            } else {
+                Level::Allow
+            };
+
+            linter.config.set(name, level);
+        }
+
+        lint(&linter, document)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// The `expected` diagnostics, given in source order along with the
+    /// coordinate each is about, in the order they're reported
+    fn ordered(mut expected: Vec<(&str, &str)>) -> Vec<String> {
+        // without spans, diagnostics are ordered by coordinate
+        if !cfg!(feature = "spans") {
+            expected.sort();
+        }
+
+        expected
+            .into_iter()
+            .map(|(_, message)| message.to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn field_naming() {
+        assert_eq!(
+            messages("field-naming", None),
+            ordered(vec![
+                (
+                    "Query.user(legacy_id:)",
+                    "warning[field-naming]: argument `legacy_id` should be camelCase"
+                ),
+                (
+                    "post_comment",
+                    "warning[field-naming]: type `post_comment` should be PascalCase"
+                ),
+                (
+                    "Role.guest",
+                    "warning[field-naming]: enum value `guest` should be SCREAMING_SNAKE_CASE"
+                ),
+                (
+                    "@Cached",
+                    "warning[field-naming]: directive `Cached` should be camelCase"
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn require_descriptions() {
+        assert_eq!(
+            messages("require-descriptions", None),
+            ordered(vec![
+                (
+                    "post_comment",
+                    "warning[require-descriptions]: type `post_comment` has no description"
+                ),
+                (
+                    "Role",
+                    "warning[require-descriptions]: type `Role` has no description"
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn no_deprecated_usage() {
+        assert_eq!(
+            messages("no-deprecated-usage", Some("{ user(id: 1) { id name } }")),
+            [concat!(
+                "warning[no-deprecated-usage]: query.user: ",
+                "`User.name` is deprecated: No longer supported"
+            )]
+        );
+        assert!(messages("no-deprecated-usage", None).is_empty());
+    }
+
+    #[test]
+    fn alphabetized_fields() {
+        assert_eq!(
+            messages("alphabetized-fields", None),
+            ordered(vec![
+                (
+                    "User.id",
+                    "warning[alphabetized-fields]: `User.id` should come before `User.name`"
+                ),
+                (
+                    "User.friends",
+                    "warning[alphabetized-fields]: `User.friends` should come before `User.id`"
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn max_selection_depth() {
+        let document = "
+query Deep { user(id: 1) { friends { ...Friends } } }
+query Shallow { users { id } }
+fragment Friends on User { friends { id ...Friends } }";
+
+        let mut linter = Linter::new(LintConfig::default());
+        linter.register(MaxSelectionDepth { max: 3 });
+
+        assert_eq!(
+            lint(&linter, Some(document))
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [concat!(
+                "warning[max-selection-depth]: query `Deep` is nested 4 fields deep, ",
+                "more than the maximum of 3"
+            )]
+        );
+    }
+
+    #[test]
+    fn max_selection_depth_expands_each_fragment_once() {
+        let mut document = String::from("query Wide { ...F0 }\n");
+        for i in 0..40 {
+            document.push_str(&format!(
+                "fragment F{i} on Query {{ a {{ ...F{next} }} b {{ ...F{next} }} }}\n",
+                next = i + 1
+            ));
+        }
+        document.push_str("fragment F40 on Query { c }");
+
+        let mut linter = Linter::new(LintConfig::default());
+        linter.register(MaxSelectionDepth { max: 100 });
+
+        assert!(lint(&linter, Some(&document)).is_empty());
+
+        let mut linter = Linter::new(LintConfig::default());
+        linter.register(MaxSelectionDepth { max: 40 });
+
+        assert_eq!(
+            lint(&linter, Some(&document))
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [concat!(
+                "warning[max-selection-depth]: query `Wide` is nested 41 fields deep, ",
+                "more than the maximum of 40"
+            )]
+        );
+    }
+
+    #[test]
+    fn relay_connections() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(
+            concat!(
+                "type Query { users(first: Int): UserConnection } ",
+                "type UserConnection { edges: [UserEdge] pageInfo: PageInfo! } ",
+                "type UserEdge { node: String cursor: String! } ",
+                "type PageInfo { hasNextPage: Boolean! hasPreviousPage: Boolean! ",
+                "startCursor: String endCursor: String }",
+            )
+            .as_bytes(),
+            &mut interner,
+        )
+        .unwrap();
+
+        let mut linter = Linter::new(LintConfig::default());
+        linter.register(RelayConnections);
+
+        assert_eq!(
+            linter
+                .lint(&schema, None, &interner)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [concat!(
+                "warning[relay-connections]: `Query.users` takes `first` without `after`; ",
+                "add `after: String`"
+            )]
+        );
+    }
+
+    #[test]
+    fn levels() {
+        let mut linter = Linter::default();
+
+        // only warned about by default
+        assert!(lint(&linter, None)
+            .iter()
+            .all(|diagnostic| diagnostic.level == Level::Warn));
+        assert!(!lint(&linter, None)
+            .iter()
+            .any(|diagnostic| diagnostic.rule == "alphabetized-fields"));
+
+        linter.config.set("field-naming", Level::Deny);
+        linter.config.set("require-descriptions", Level::Allow);
+        linter.config.set("no-such-rule", Level::Warn);
+
+        let diagnostics = lint(&linter, None);
+
+        assert!(diagnostics.iter().all(|diagnostic| {
+            diagnostic.rule == "field-naming" && diagnostic.level == Level::Deny
+        }));
+        assert_eq!(diagnostics.len(), 4);
+        assert_eq!(linter.unknown_rules(), ["no-such-rule"]);
+        assert_eq!("deny".parse(), Ok(Level::Deny));
+        assert_eq!(
+            "error".parse::<Level>().unwrap_err().to_string(),
+            "unknown lint level `error`, expected `allow`, `warn` or `deny`"
+        );
+    }
+}
@@ -0,0 +1,737 @@
+//! A language server for GraphQL documents, speaking the Language Server
+//! Protocol as JSON-RPC over any reader and writer, usually stdin and stdout
+//!
+//! It reports parse errors, and validation errors against a configured
+//! schema, as diagnostics. It also supports going to the definition of types
+//! and fragments, hovering for descriptions, and completing fields,
+//! arguments, directives, types and values. Documents are only synced in
+//! full, and positions are counted in UTF-16 code units, the protocol's
+//! default
+//!
+//! See https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use lasso::{Rodeo, Spur};
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    ast::{Document, Selection, Span, TypeKind},
+    completion::{complete, CompletionKind},
+    error::GraphqlParseError,
+    fmt::print_type,
+    parse::GraphqlParser,
+    validation::validate_executable_document,
+};
+
+// https://www.jsonrpc.org/specification#error_object
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+const SEVERITY_ERROR: i64 = 1;
+const COMPLETION_TAG_DEPRECATED: i64 = 1;
+const TEXT_DOCUMENT_SYNC_FULL: i64 = 1;
+
+/// An open document, or the schema, along with the result of parsing it
+struct SourceFile {
+    uri: String,
+    source: String,
+    document: Document,
+    errors: Vec<GraphqlParseError>,
+}
+
+pub struct LanguageServer {
+    /// Shared by the schema and every open document, so that names can be
+    /// compared between them
+    interner: Rodeo,
+    schema: Option<SourceFile>,
+    documents: HashMap<String, SourceFile>,
+    is_shutting_down: bool,
+}
+
+impl Default for LanguageServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LanguageServer {
+    /// A server without a schema, which only reports parse errors and can't
+    /// complete fields
+    pub fn new() -> Self {
+        Self {
+            interner: Rodeo::default(),
+            schema: None,
+            documents: HashMap::new(),
+            is_shutting_down: false,
+        }
+    }
+
+    /// A server validating documents against the schema in `source`. `uri`
+    /// is where go to definition points for the types it defines, and while
+    /// a document with the same uri is open, its contents are used as the
+    /// schema instead
+    pub fn with_schema(uri: String, source: String) -> Self {
+        let mut server = Self::new();
+        server.set_schema(uri, source);
+        server
+    }
+
+    fn set_schema(&mut self, uri: String, source: String) {
+        let mut schema = self.parse(uri, source);
+
+        // errors in extensions are the schema's problem, not the documents'
+        let _ = schema.document.apply_extensions();
+
+        self.schema = Some(schema);
+    }
+
+    fn parse(&mut self, uri: String, source: String) -> SourceFile {
+        let (document, errors) =
+            GraphqlParser::parse_with_recovery_and_interner(source.as_bytes(), &mut self.interner);
+
+        SourceFile {
+            uri,
+            source,
+            document,
+            errors,
+        }
+    }
+
+    /// Read messages from `input` and write responses and notifications to
+    /// `output` until the client sends `exit`
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        while let Some(body) = read_message(&mut input)? {
+            let messages = match serde_json::from_slice::<JsonValue>(&body) {
+                Ok(message) if message["method"] == "exit" => return Ok(()),
+                Ok(message) => self.handle_message(&message),
+                Err(err) => vec![error_response(
+                    JsonValue::Null,
+                    PARSE_ERROR,
+                    err.to_string(),
+                )],
+            };
+
+            for message in messages {
+                write_message(&mut output, &message)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle a single request or notification, returning the messages to
+    /// send back to the client
+    pub fn handle_message(&mut self, message: &JsonValue) -> Vec<JsonValue> {
+        let method = match message["method"].as_str() {
+            Some(method) => method,
+            // a response to a request we never send
+            None => return Vec::new(),
+        };
+
+        let params = &message["params"];
+
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => return self.handle_notification(method, params),
+        };
+
+        if self.is_shutting_down {
+            return vec![error_response(
+                id,
+                INVALID_REQUEST,
+                "the server is shutting down".to_owned(),
+            )];
+        }
+
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": TEXT_DOCUMENT_SYNC_FULL,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": {
+                    "name": "graphql",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "shutdown" => {
+                self.is_shutting_down = true;
+                Some(JsonValue::Null)
+            }
+            "textDocument/hover" => self.with_position(params, Self::hover),
+            "textDocument/definition" => self.with_position(params, Self::definition),
+            "textDocument/completion" => self.with_position(params, Self::completion),
+            _ => {
+                return vec![error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    format!("unknown method `{}`", method),
+                )]
+            }
+        };
+
+        match result {
+            Some(result) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            None => vec![error_response(
+                id,
+                INVALID_PARAMS,
+                "missing or invalid params".to_owned(),
+            )],
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &JsonValue) -> Vec<JsonValue> {
+        let uri = match params["textDocument"]["uri"].as_str() {
+            Some(uri) => uri.to_owned(),
+            None => return Vec::new(),
+        };
+
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            // with full sync, the last change holds the whole document
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![publish_diagnostics(&uri, Vec::new())];
+            }
+            _ => return Vec::new(),
+        };
+
+        let text = match text {
+            Some(text) => text.to_owned(),
+            None => return Vec::new(),
+        };
+
+        if self.schema.as_ref().is_some_and(|schema| schema.uri == uri) {
+            self.set_schema(uri, text);
+
+            // a change to the schema may fix or break any open document
+            let mut uris = self.documents.keys().cloned().collect::<Vec<_>>();
+            uris.sort_unstable();
+
+            return std::iter::once(self.schema_diagnostics())
+                .chain(
+                    uris.iter()
+                        .map(|uri| self.diagnostics(&self.documents[uri])),
+                )
+                .collect();
+        }
+
+        let file = self.parse(uri.clone(), text);
+        let diagnostics = self.diagnostics(&file);
+
+        self.documents.insert(uri, file);
+
+        vec![diagnostics]
+    }
+
+    /// Parse errors in the schema. Its other errors are left to `validate`
+    fn schema_diagnostics(&self) -> JsonValue {
+        let schema = self.schema.as_ref().unwrap();
+
+        let diagnostics = schema
+            .errors
+            .iter()
+            .map(|err| diagnostic(&schema.source, err.span(), err.to_string()))
+            .collect();
+
+        publish_diagnostics(&schema.uri, diagnostics)
+    }
+
+    fn diagnostics(&self, file: &SourceFile) -> JsonValue {
+        let mut diagnostics = file
+            .errors
+            .iter()
+            .map(|err| diagnostic(&file.source, err.span(), err.to_string()))
+            .collect::<Vec<_>>();
+
+        let is_executable =
+            !file.document.operations.is_empty() || !file.document.fragments.is_empty();
+
+        // a partially parsed document would report spurious errors, such as
+        // for fragments whose definitions failed to parse
+        if let (Some(schema), true, true) = (&self.schema, file.errors.is_empty(), is_executable) {
+            let mut errors =
+                validate_executable_document(&schema.document, &file.document, &self.interner);
+
+            errors.sort_by_key(|err| err.span.start);
+
+            diagnostics.extend(
+                errors
+                    .iter()
+                    .map(|err| diagnostic(&file.source, err.span, err.kind.to_string())),
+            );
+        }
+
+        publish_diagnostics(&file.uri, diagnostics)
+    }
+
+    /// Call `f` with the document and byte offset given by the position in
+    /// `params`. Returns `None` if the params are invalid, and `null` if the
+    /// document isn't open
+    fn with_position(
+        &self,
+        params: &JsonValue,
+        f: impl Fn(&Self, &SourceFile, usize) -> Option<JsonValue>,
+    ) -> Option<JsonValue> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+
+        let file = match self.documents.get(uri) {
+            Some(file) => file,
+            None => match &self.schema {
+                Some(schema) if schema.uri == uri => schema,
+                _ => return Some(JsonValue::Null),
+            },
+        };
+
+        let offset = offset_of_position(&file.source, line, character);
+
+        Some(f(self, file, offset).unwrap_or(JsonValue::Null))
+    }
+
+    fn hover(&self, file: &SourceFile, offset: usize) -> Option<JsonValue> {
+        let (word, span) = word_at(&file.source, offset)?;
+        let name = self.interner.get(word)?;
+
+        let (signature, description) = match self.locate(file, offset) {
+            Some(SelectionContext {
+                parent_type,
+                field: Some(field),
+            }) if field == name => {
+                let field = self
+                    .schema_document()?
+                    .fields_of(parent_type)?
+                    .iter()
+                    .find(|definition| definition.name == field)?;
+
+                let signature = format!("{}: {}", word, print_type(&field.ty, &self.interner));
+
+                (signature, field.description)
+            }
+            _ => {
+                let ty = self
+                    .schema_document()
+                    .and_then(|schema| schema.get_type(name))
+                    .or_else(|| file.document.get_type(name))?;
+
+                let signature = format!("{} {}", type_keyword(ty.kind()), word);
+
+                (signature, ty.description())
+            }
+        };
+
+        Some(json!({
+            "contents": {
+                "kind": "markdown",
+                "value": self.hover_markdown(&signature, description),
+            },
+            "range": range(&file.source, span),
+        }))
+    }
+
+    fn hover_markdown(&self, signature: &str, description: Option<Spur>) -> String {
+        match description {
+            Some(description) => format!(
+                "```graphql\n{}\n```\n\n{}",
+                signature,
+                self.interner.resolve(&description)
+            ),
+            None => format!("```graphql\n{}\n```", signature),
+        }
+    }
+
+    fn definition(&self, file: &SourceFile, offset: usize) -> Option<JsonValue> {
+        let (word, span) = word_at(&file.source, offset)?;
+        let name = self.interner.get(word)?;
+
+        let before = file.source[..span.start].trim_end();
+
+        if before.ends_with("...") || before.ends_with("fragment") {
+            // fragments may be defined in any open document
+            let mut files = self.documents.values().collect::<Vec<_>>();
+            files.sort_by_key(|other| other.uri != file.uri);
+
+            return files.into_iter().find_map(|file| {
+                let fragment = file.document.fragments.get(&name)?;
+                Some(location(file, fragment.span))
+            });
+        }
+
+        if let Some(ty) = file.document.get_type(name) {
+            return Some(location(file, ty.span()));
+        }
+
+        let schema = self.schema.as_ref()?;
+        let ty = schema.document.get_type(name)?;
+
+        Some(location(schema, ty.span()))
+    }
+
+    fn completion(&self, file: &SourceFile, offset: usize) -> Option<JsonValue> {
+        let schema = self.schema_document()?;
+
+        let items = complete(schema, &self.interner, &file.source, offset)
+            .into_iter()
+            .map(|item| {
+                let mut json = json!({
+                    "label": item.label,
+                    "kind": completion_kind(item.kind),
+                });
+
+                if let Some(detail) = item.detail {
+                    json["detail"] = detail.into();
+                }
+
+                if let Some(description) = item.description {
+                    json["documentation"] = description.into();
+                }
+
+                if item.deprecation.is_some() {
+                    json["tags"] = json!([COMPLETION_TAG_DEPRECATED]);
+                }
+
+                json
+            })
+            .collect();
+
+        Some(JsonValue::Array(items))
+    }
+
+    fn schema_document(&self) -> Option<&Document> {
+        self.schema.as_ref().map(|schema| &schema.document)
+    }
+
+    /// The type whose fields may be selected at `offset`, and the field whose
+    /// name or arguments `offset` is in, if any
+    fn locate(&self, file: &SourceFile, offset: usize) -> Option<SelectionContext> {
+        let schema = self.schema_document()?;
+
+        for operation in file.document.operations.values() {
+            if contains(operation.span, offset) {
+                let root = schema.root_operation_type_or_default(operation.kind, &self.interner)?;
+
+                return self.locate_in_selection_set(file, &operation.selection_set, root, offset);
+            }
+        }
+
+        for fragment in file.document.fragments.values() {
+            if contains(fragment.span, offset) {
+                return self.locate_in_selection_set(
+                    file,
+                    &fragment.selection_set,
+                    fragment.on,
+                    offset,
+                );
+            }
+        }
+
+        None
+    }
+
+    fn locate_in_selection_set(
+        &self,
+        file: &SourceFile,
+        selection_set: &[Selection],
+        parent_type: Spur,
+        offset: usize,
+    ) -> Option<SelectionContext> {
+        let selection = match selection_set
+            .iter()
+            .find(|selection| contains(selection.span(), offset))
+        {
+            Some(selection) => selection,
+            None => {
+                return Some(SelectionContext {
+                    parent_type,
+                    field: None,
+                })
+            }
+        };
+
+        match selection {
+            Selection::Field {
+                name,
+                selection_set: Some(selection_set),
+                span,
+                ..
+            } if selection_set_start(&file.source, *span).is_some_and(|start| offset > start) => {
+                let field_type = self
+                    .schema_document()?
+                    .fields_of(parent_type)?
+                    .iter()
+                    .find(|field| field.name == *name)?
+                    .ty
+                    .name();
+
+                self.locate_in_selection_set(file, selection_set, field_type, offset)
+            }
+            Selection::Field { name, .. } => Some(SelectionContext {
+                parent_type,
+                field: Some(*name),
+            }),
+            Selection::InlineFragment {
+                on, selection_set, ..
+            } => {
+                self.locate_in_selection_set(file, selection_set, on.unwrap_or(parent_type), offset)
+            }
+            Selection::FragmentSpread { .. } => None,
+        }
+    }
+}
+
+struct SelectionContext {
+    parent_type: Spur,
+    field: Option<Spur>,
+}
+
+/// The protocol's `CompletionItemKind` closest to `kind`
+fn completion_kind(kind: CompletionKind) -> i64 {
+    match kind {
+        CompletionKind::Directive => 3,
+        CompletionKind::Field => 5,
+        CompletionKind::Variable => 6,
+        CompletionKind::Type => 7,
+        CompletionKind::Argument | CompletionKind::InputField => 10,
+        CompletionKind::Keyword => 14,
+        CompletionKind::Fragment => 18,
+        CompletionKind::EnumValue => 20,
+    }
+}
+
+fn type_keyword(kind: TypeKind) -> &'static str {
+    match kind {
+        TypeKind::Scalar => "scalar",
+        TypeKind::Object => "type",
+        TypeKind::Interface => "interface",
+        TypeKind::Union => "union",
+        TypeKind::Enum => "enum",
+        TypeKind::InputObject => "input",
+    }
+}
+
+/// Whether `offset` is inside `span`, including just after its last byte,
+/// where the cursor is after typing it
+fn contains(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+/// The offset of the `{` opening the selection set of the field at `span`,
+/// skipping any inside its arguments or directive arguments
+fn selection_set_start(source: &str, span: Span) -> Option<usize> {
+    let mut depth = 0_usize;
+    let mut in_string = false;
+    let mut bytes = source.as_bytes()[span.start..span.end].iter().enumerate();
+
+    while let Some((idx, b)) = bytes.next() {
+        match b {
+            b'\\' if in_string => {
+                bytes.next();
+            }
+            b'"' => in_string = !in_string,
+            _ if in_string => {}
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            b'{' if depth == 0 => return Some(span.start + idx),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// The name at or just before `offset`, along with its span
+fn word_at(source: &str, offset: usize) -> Option<(&str, Span)> {
+    let is_name = |b: &u8| b.is_ascii_alphanumeric() || *b == b'_';
+
+    let bytes = source.as_bytes();
+    let offset = offset.min(bytes.len());
+
+    let start = bytes[..offset]
+        .iter()
+        .rposition(|b| !is_name(b))
+        .map_or(0, |idx| idx + 1);
+    let end = bytes[offset..]
+        .iter()
+        .position(|b| !is_name(b))
+        .map_or(bytes.len(), |idx| offset + idx);
+
+    if start == end {
+        return None;
+    }
+
+    Some((&source[start..end], Span::new(start, end)))
+}
+
+/// Convert an LSP position, counted in UTF-16 code units, into a byte offset
+fn offset_of_position(source: &str, line: usize, character: usize) -> usize {
+    let mut offset = 0;
+
+    for _ in 0..line {
+        match source[offset..].find('\n') {
+            Some(idx) => offset += idx + 1,
+            None => return source.len(),
+        }
+    }
+
+    let mut units = 0;
+
+    for (idx, c) in source[offset..].char_indices() {
+        if units >= character || c == '\n' {
+            return offset + idx;
+        }
+
+        units += c.len_utf16();
+    }
+
+    source.len()
+}
+
+fn position(source: &str, offset: usize) -> JsonValue {
+    // spans may end inside a character, such as that of an invalid escape
+    let mut offset = offset.min(source.len());
+
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    let prefix = &source[..offset];
+
+    let line = prefix.matches('\n').count();
+    let line_start = prefix.rfind('\n').map_or(0, |idx| idx + 1);
+    let character = prefix[line_start..].encode_utf16().count();
+
+    json!({ "line": line, "character": character })
+}
+
+fn range(source: &str, span: Span) -> JsonValue {
+    json!({
+        "start": position(source, span.start),
+        "end": position(source, span.end),
+    })
+}
+
+fn location(file: &SourceFile, span: Span) -> JsonValue {
+    json!({ "uri": file.uri, "range": range(&file.source, span) })
+}
+
+fn diagnostic(source: &str, span: Span, message: String) -> JsonValue {
+    json!({
+        "range": range(source, span),
+        "severity": SEVERITY_ERROR,
+        "source": "graphql",
+        "message": message,
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<JsonValue>) -> JsonValue {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn error_response(id: JsonValue, code: i64, message: String) -> JsonValue {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Read the body of the next message, or `None` at the end of the input
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let header = line.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+
+    let mut body = vec![0; content_length];
+    input.read_exact(&mut body)?;
+
+    Ok(Some(body))
+}
+
+fn write_message(output: &mut impl Write, message: &JsonValue) -> io::Result<()> {
+    let body = message.to_string();
+
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{position, LanguageServer};
+
+    #[test]
+    fn position_inside_character() {
+        let source = "{ a(s: \"é\") }";
+
+        // the second byte of `é`
+        assert_eq!(position(source, 9), json!({ "line": 0, "character": 8 }));
+        assert_eq!(position(source, 100), json!({ "line": 0, "character": 13 }));
+    }
+
+    #[test]
+    fn open_document_with_invalid_escape() {
+        let mut server = LanguageServer::new();
+
+        let responses = server.handle_message(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": {
+                    "uri": "file:///query.graphql",
+                    "languageId": "graphql",
+                    "version": 1,
+                    "text": "{ a(s: \"\\é\") }",
+                },
+            },
+        }));
+
+        let diagnostics = &responses[0]["params"]["diagnostics"];
+
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert_eq!(
+            diagnostics[0]["range"]["end"],
+            json!({ "line": 0, "character": 10 })
+        );
+    }
+}
@@ -1,16 +1,1766 @@
-use parse::GraphqlParser;
+#[cfg(feature = "watch")]
+use std::time::Instant;
+use std::{collections::HashMap, env, fs, io, path::Path, process};
 
-mod ast;
-mod error;
-mod lexer;
-mod parse;
+#[cfg(feature = "persisted-queries")]
+use graphql::artifacts::{apollo_artifact, relay_artifact};
+#[cfg(feature = "client")]
+use graphql::client::Client;
+#[cfg(feature = "spans")]
+use graphql::comments::comments_to_descriptions;
+#[cfg(feature = "project")]
+use graphql::project::{Project, ProjectFile};
+#[cfg(feature = "registry")]
+use graphql::registry::{PublishOutcome, Registry, RegistryError, SchemaVersion};
+#[cfg(feature = "watch")]
+use graphql::watch::ProjectWatcher;
+
+use graphql::{
+    codegen::{generate_typescript_operations, TypeScriptCodegenConfig},
+    composition::compose_supergraph,
+    diff::{apply_usage, diff_schemas, Criticality},
+    docs::{generate_docs, DocsConfig, DocsFormat},
+    federation::Subgraph,
+    fmt::{print_document, print_minified},
+    graphql_js::graphql_js_ast_from_document,
+    introspection::{document_from_introspection, introspection_from_document},
+    json_schema::{json_schema_from_document, openapi_from_document, OpenApiConfig},
+    lsp::LanguageServer,
+    proto::{proto_from_document, ProtoConfig},
+    sql::sql_from_document,
+    subset::schema_subset,
+    type_graph::{TypeGraph, TypeGraphOptions},
+    usage::SchemaUsage,
+    validation::validate_executable_document,
+    variables::coerce_variable_values,
+    Document, GraphqlParseError, GraphqlParser, LineColumn,
+};
+use lasso::Rodeo;
+use serde_json::{json, Value as JsonValue};
+
+const USAGE: &str = "usage: graphql <command> [--json] <args>
+
+commands:
+    parse <file>                    parse a document and print its AST
+    validate <schema> [query...]    validate a schema, and queries against it
+    validate [--watch]              validate the project described by the graphql
+                                    config file for the current directory, and
+                                    with --watch, revalidate it as files change
+    format [--minify] [--sort] <file>
+                                    print a document in canonical form, or as
+                                    the shortest equivalent text. Types are
+                                    grouped by kind and sorted by name within
+                                    each kind, and with --sort, their fields,
+                                    arguments and other contents are sorted by
+                                    name too. Fails if the document has
+                                    comments or duplicate definitions, which
+                                    would be lost
+    introspect <file>               convert a schema to introspection JSON, or an
+                                    introspection result (*.json) to a schema
+    introspect [--header <header>...] <url>
+                                    fetch the schema of a server by introspection
+                                    and write it to schema.graphql
+    diff <old> <new>                list the changes between two schemas, failing
+                                    if any of them are breaking
+    diff [--usage <report>] [--operations <path>...] <old> <new>
+                                    treat changes to parts of the old schema that
+                                    a usage report (from `usage --json`) or the
+                                    operations in the given files and directories
+                                    never use as safe
+    check-variables <schema> <operation> <variables>
+                                    coerce a JSON file of variables against the
+                                    variable definitions of the only operation
+                                    in a file, printing the coerced values or
+                                    an error for each invalid value
+    usage <schema> <path...>        report how often the operations in the given
+                                    files and directories use each part of a
+                                    schema, and which parts they never use
+    subset <schema> <path...>       print the part of a schema that the
+                                    operations in the given files and
+                                    directories use, and the types it needs
+    describe-comments <file>        print a schema with the `#` comments
+                                    directly before its definitions, fields,
+                                    arguments and enum values converted to
+                                    descriptions, and report the comments it
+                                    couldn't convert
+    codegen --target <target> <schema> <path...>
+                                    generate code for the operations in the given
+                                    files and directories. Targets are
+                                    `typescript`, which prints the types of their
+                                    variables and responses, and `apollo` and
+                                    `relay`, which print persisted query and
+                                    concrete request artifacts by operation name
+    export --format <format> <schema>
+                                    convert a schema to another format: a JSON
+                                    Schema of its types (`json-schema`), an
+                                    OpenAPI document for its endpoint (`openapi`),
+                                    protobuf definitions (`proto`), tables for
+                                    its `@table` object types (`sql`), or a graph
+                                    of the references between its types (`dot`
+                                    or `mermaid`)
+    export --format <dot|mermaid> [--focus <type>] [--depth <n>]
+           [--exclude-scalars] <schema>
+                                    graph only the types within n references of
+                                    a type (1 by default), or without scalars
+    docs [--format <format>] <schema> <dir>
+                                    write documentation of a schema to a
+                                    directory, as `markdown` (the default) or
+                                    `html` pages
+    compose <subgraph...>           compose federated subgraph schemas into a
+                                    supergraph schema, naming each subgraph
+                                    after its file
+    registry publish [--message <message>] <dir> <schema>
+                                    store a schema as the next version in the
+                                    registry in a directory
+    registry check <dir> <schema>   compare a schema against the latest version
+                                    in a registry, failing on breaking changes
+    registry history <dir>          list the versions in a registry
+    lsp [schema]                    run a language server over stdin and stdout,
+                                    validating documents against the schema
+
+with --json, output and errors are printed as JSON";
+
+/// An error to report, in either text or JSON form
+struct Diagnostic {
+    file: String,
+    message: String,
+    location: Option<LineColumn>,
+    /// Where in an operation or fragment a validation error occurred
+    path: Vec<String>,
+}
+
+impl Diagnostic {
+    fn new(file: &str, message: String) -> Self {
+        Self {
+            file: file.to_owned(),
+            message,
+            location: None,
+            path: Vec::new(),
+        }
+    }
+
+    fn from_parse_error(file: &str, source: &[u8], err: &GraphqlParseError) -> Self {
+        Self {
+            location: Some(err.location(source)),
+            ..Self::new(file, err.to_string())
+        }
+    }
+
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "file": self.file,
+            "message": self.message,
+            "line": self.location.map(|location| location.line),
+            "column": self.location.map(|location| location.column),
+            "path": self.path,
+        })
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.file)?;
+
+        if let Some(location) = self.location {
+            write!(f, "{}:", location)?;
+        }
+
+        if !self.path.is_empty() {
+            write!(f, " {}:", self.path.join("."))?;
+        }
+
+        write!(f, " {}", self.message)
+    }
+}
+
+/// The output of a successful command, or the errors that prevented it
+type CommandResult = Result<Output, Vec<Diagnostic>>;
+
+enum Output {
+    Text(String),
+    Json(JsonValue),
+    /// Output to print before exiting with a failure, such as the changes
+    /// found by `diff` when some of them are breaking
+    Failure(Box<Output>),
+    /// Output to print along with diagnostics that don't fail the command
+    Warnings(Box<Output>, Vec<Diagnostic>),
+}
+
+/// The flags and positional arguments of a command line
+#[derive(Default)]
+struct CommandLine {
+    is_json: bool,
+    is_minified: bool,
+    is_sorted: bool,
+    #[cfg(feature = "project")]
+    is_watching: bool,
+    #[cfg(feature = "client")]
+    headers: Vec<String>,
+    #[cfg(feature = "registry")]
+    message: Option<String>,
+    usage_report: Option<String>,
+    operations: Vec<String>,
+    target: Option<String>,
+    export_format: Option<String>,
+    graph_filter: GraphFilter,
+    /// The command followed by its arguments
+    args: Vec<String>,
+}
+
+impl CommandLine {
+    /// Parse the arguments after the program name. Flags may appear anywhere
+    fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut command_line = Self::default();
+        let mut args = args.into_iter();
+
+        // `--usage`, `--operations`, `--target`, `--format`, `--focus`, `--depth`, `--header` and
+        // `--message` take the next argument as their value
+        while let Some(arg) = args.next() {
+            if arg == "--json" {
+                command_line.is_json = true;
+            } else if arg == "--minify" {
+                command_line.is_minified = true;
+            } else if arg == "--sort" {
+                command_line.is_sorted = true;
+            } else if arg == "--usage" {
+                command_line.usage_report = args.next();
+            } else if arg == "--operations" {
+                command_line.operations.extend(args.next());
+            } else if arg == "--target" {
+                command_line.target = args.next();
+            } else if arg == "--format" {
+                command_line.export_format = args.next();
+            } else if arg == "--focus" {
+                command_line.graph_filter.focus = args.next();
+            } else if arg == "--depth" {
+                command_line.graph_filter.depth = args.next();
+            } else if arg == "--exclude-scalars" {
+                command_line.graph_filter.exclude_scalars = true;
+            } else {
+                #[cfg(feature = "project")]
+                if arg == "--watch" {
+                    command_line.is_watching = true;
+                    continue;
+                }
+
+                #[cfg(feature = "client")]
+                if arg == "--header" {
+                    command_line.headers.extend(args.next());
+                    continue;
+                }
+
+                #[cfg(feature = "registry")]
+                if arg == "--message" {
+                    command_line.message = args.next();
+                    continue;
+                }
+
+                command_line.args.push(arg);
+            }
+        }
+
+        command_line
+    }
+
+    /// Run the command, or return `None` if there is no command with these
+    /// arguments
+    fn run(&self) -> Option<CommandResult> {
+        let is_json = self.is_json;
+        let usage_report = self.usage_report.as_deref();
+        let target = self.target.as_deref();
+        let export_format = self.export_format.as_deref();
+
+        Some(match self.args.split_first()? {
+            (command, [file]) if command == "parse" => parse(file, is_json),
+            #[cfg(feature = "project")]
+            (command, []) if command == "validate" => validate_project(self.is_watching, is_json),
+            (command, [schema, queries @ ..]) if command == "validate" => {
+                validate(schema, queries, is_json)
+            }
+            (command, [file]) if command == "format" => {
+                format(file, self.is_minified, self.is_sorted, is_json)
+            }
+            #[cfg(feature = "client")]
+            (command, [url]) if command == "introspect" && is_url(url) => {
+                fetch_schema(url, &self.headers, is_json)
+            }
+            (command, [file]) if command == "introspect" => introspect(file),
+            (command, [old, new]) if command == "diff" => {
+                diff(old, new, usage_report, &self.operations, is_json)
+            }
+            (command, [schema, operation, variables]) if command == "check-variables" => {
+                check_variables(schema, operation, variables)
+            }
+            (command, [schema, paths @ ..]) if command == "usage" && !paths.is_empty() => {
+                usage(schema, paths, is_json)
+            }
+            (command, [schema, paths @ ..]) if command == "subset" && !paths.is_empty() => {
+                subset(schema, paths)
+            }
+            #[cfg(feature = "spans")]
+            (command, [file]) if command == "describe-comments" => describe_comments(file, is_json),
+            (command, [schema, paths @ ..]) if command == "codegen" && !paths.is_empty() => {
+                codegen(schema, paths, target)
+            }
+            (command, [schema]) if command == "export" => {
+                export(schema, export_format, &self.graph_filter)
+            }
+            (command, [schema, dir]) if command == "docs" => {
+                docs(schema, dir, export_format, is_json)
+            }
+            (command, paths) if command == "compose" && !paths.is_empty() => compose(paths),
+            #[cfg(feature = "registry")]
+            (command, [action, dir, schema]) if command == "registry" && action == "publish" => {
+                registry_publish(dir, schema, self.message.as_deref(), is_json)
+            }
+            #[cfg(feature = "registry")]
+            (command, [action, dir, schema]) if command == "registry" && action == "check" => {
+                registry_check(dir, schema, is_json)
+            }
+            #[cfg(feature = "registry")]
+            (command, [action, dir]) if command == "registry" && action == "history" => {
+                registry_history(dir, is_json)
+            }
+            (command, []) if command == "lsp" => lsp(None),
+            (command, [schema]) if command == "lsp" => lsp(Some(schema)),
+            _ => return None,
+        })
+    }
+}
+
+/// Print the output of a command, or its errors, and return the status to
+/// exit with
+fn report(result: CommandResult, is_json: bool) -> i32 {
+    match result {
+        Ok(Output::Text(text)) => print!("{}", text),
+        Ok(Output::Json(json)) => println!("{}", serde_json::to_string_pretty(&json).unwrap()),
+        Ok(Output::Failure(output)) => {
+            report(Ok(*output), is_json);
+            return 1;
+        }
+        Ok(Output::Warnings(output, diagnostics)) => {
+            report(Ok(*output), is_json);
+
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic);
+            }
+        }
+        Err(diagnostics) if is_json => {
+            let errors = diagnostics
+                .iter()
+                .map(Diagnostic::to_json)
+                .collect::<Vec<_>>();
+
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json!({ "errors": errors })).unwrap()
+            );
+            return 1;
+        }
+        Err(diagnostics) => {
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic);
+            }
+            return 1;
+        }
+    }
+
+    0
+}
 
 fn main() {
-    let buffer = Vec::new();
+    let command_line = CommandLine::parse(env::args().skip(1));
+
+    let Some(result) = command_line.run() else {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    };
+
+    process::exit(report(result, command_line.is_json));
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, Vec<Diagnostic>> {
+    fs::read(path).map_err(|err| vec![Diagnostic::new(path, format!("failed to read: {}", err))])
+}
+
+fn parse(path: &str, is_json: bool) -> CommandResult {
+    let buffer = read_file(path)?;
+
+    let result = GraphqlParser::parse_resolvable(&buffer)
+        .map_err(|err| vec![Diagnostic::from_parse_error(path, &buffer, &err)])?;
+
+    Ok(if is_json {
+        Output::Json(graphql_js_ast_from_document(
+            &result.document,
+            &result.interner,
+        ))
+    } else {
+        Output::Text(format!("{:#?}\n", result.document))
+    })
+}
+
+fn validate(schema_path: &str, query_paths: &[String], is_json: bool) -> CommandResult {
+    let mut interner = Rodeo::default();
+
+    let buffer = read_file(schema_path)?;
+
+    let mut schema = GraphqlParser::parse_schema_with_interner(&buffer, &mut interner)
+        .map_err(|err| vec![Diagnostic::from_parse_error(schema_path, &buffer, &err)])?;
+
+    let mut diagnostics = schema
+        .apply_extensions()
+        .into_iter()
+        .chain(schema.validate())
+        .chain(schema.validate_default_values(&interner))
+        .chain(schema.validate_directive_usages(&interner))
+        .map(|err| Diagnostic {
+            location: err.span().map(|span| span.start_location(&buffer)),
+            ..Diagnostic::new(schema_path, err.message(&interner))
+        })
+        .collect::<Vec<_>>();
+
+    for query_path in query_paths {
+        let buffer = read_file(query_path)?;
+
+        let query = match GraphqlParser::parse_query_with_interner(&buffer, &mut interner) {
+            Ok(query) => query,
+            Err(err) => {
+                diagnostics.push(Diagnostic::from_parse_error(query_path, &buffer, &err));
+                continue;
+            }
+        };
+
+        for err in validate_executable_document(&schema, &query, &interner) {
+            diagnostics.push(Diagnostic {
+                location: Some(err.span.start_location(&buffer)),
+                path: err.path,
+                ..Diagnostic::new(query_path, err.kind.to_string())
+            });
+        }
+    }
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    Ok(if is_json {
+        Output::Json(json!({ "errors": [] }))
+    } else {
+        Output::Text(String::new())
+    })
+}
+
+#[cfg(feature = "project")]
+fn validate_project(is_watching: bool, is_json: bool) -> CommandResult {
+    #[cfg(feature = "watch")]
+    let start = Instant::now();
+
+    let dir = env::current_dir()
+        .map_err(|err| vec![Diagnostic::new(".", format!("failed to read: {}", err))])?;
+
+    let project = Project::discover(&dir, None).map_err(|err| {
+        vec![Diagnostic::new(
+            &err.path.display().to_string(),
+            err.kind.to_string(),
+        )]
+    })?;
+
+    if is_watching {
+        #[cfg(feature = "watch")]
+        return watch_project(project, &dir, start, is_json);
+
+        #[cfg(not(feature = "watch"))]
+        return Err(vec![Diagnostic::new(
+            "graphql",
+            "--watch requires the `watch` feature".to_owned(),
+        )]);
+    }
+
+    let diagnostics = project_diagnostics(&project, &dir, project.files());
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    Ok(if is_json {
+        Output::Json(json!({ "errors": [] }))
+    } else {
+        Output::Text(String::new())
+    })
+}
+
+/// Print the diagnostics of the files that changed each time the project
+/// changes, until the watcher fails
+#[cfg(feature = "watch")]
+fn watch_project(project: Project, dir: &Path, mut start: Instant, is_json: bool) -> CommandResult {
+    let watcher_error = |err: notify::Error| vec![Diagnostic::new("graphql", err.to_string())];
+
+    let mut watcher = ProjectWatcher::new(project).map_err(watcher_error)?;
+    let mut files = watcher
+        .project()
+        .files()
+        .map(|file| file.path.clone())
+        .collect::<Vec<_>>();
+
+    loop {
+        let project = watcher.project();
+
+        let diagnostics = project_diagnostics(
+            project,
+            dir,
+            files.iter().filter_map(|path| project.file(path)),
+        );
+
+        if is_json {
+            let errors = diagnostics
+                .iter()
+                .map(Diagnostic::to_json)
+                .collect::<Vec<_>>();
+
+            println!(
+                "{}",
+                json!({ "files": files, "errors": errors, "ms": start.elapsed().as_millis() })
+            );
+        } else {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic);
+            }
+
+            eprintln!(
+                "checked {} files in {}ms, {} errors",
+                files.len(),
+                start.elapsed().as_millis(),
+                diagnostics.len()
+            );
+        }
+
+        files = watcher.wait().map_err(watcher_error)?;
+        start = Instant::now();
+    }
+}
+
+/// The diagnostics of the project itself, and of `files`
+#[cfg(feature = "project")]
+fn project_diagnostics<'a>(
+    project: &Project,
+    dir: &Path,
+    files: impl Iterator<Item = &'a ProjectFile>,
+) -> Vec<Diagnostic> {
+    let project_diagnostics = project.diagnostics.iter().map(|diagnostic| {
+        Diagnostic::new(
+            &project.root.display().to_string(),
+            diagnostic.message.clone(),
+        )
+    });
+
+    let file_diagnostics = files.flat_map(|file| {
+        let path = file.path.strip_prefix(dir).unwrap_or(&file.path);
+
+        file.diagnostics.iter().map(move |diagnostic| Diagnostic {
+            location: diagnostic.location(&file.source),
+            ..Diagnostic::new(&path.display().to_string(), diagnostic.message.clone())
+        })
+    });
+
+    project_diagnostics.chain(file_diagnostics).collect()
+}
+
+fn format(path: &str, is_minified: bool, is_sorted: bool, is_json: bool) -> CommandResult {
+    let buffer = read_file(path)?;
+
+    let mut result = GraphqlParser::parse_resolvable(&buffer)
+        .map_err(|err| vec![Diagnostic::from_parse_error(path, &buffer, &err)])?;
+
+    // the printer only prints definitions, so rather than lose comments and
+    // replaced definitions, the document isn't formatted
+    let (_, comments) = GraphqlParser::parse_with_comments(&buffer)
+        .map_err(|err| vec![Diagnostic::from_parse_error(path, &buffer, &err)])?;
+
+    let lost = comments
+        .iter()
+        .map(|comment| {
+            (
+                LineColumn::from_offset(&buffer, comment.start),
+                "formatting would remove this comment",
+            )
+        })
+        .chain(result.document.duplicates.iter().map(|definition| {
+            (
+                definition.span().start_location(&buffer),
+                "formatting would remove this definition, which a later one replaces",
+            )
+        }))
+        .map(|(location, message)| Diagnostic {
+            location: Some(location),
+            ..Diagnostic::new(path, message.to_owned())
+        })
+        .collect::<Vec<_>>();
+
+    if !lost.is_empty() {
+        return Err(lost);
+    }
+
+    if is_sorted {
+        result.document = result.document.sorted(&result.interner);
+    }
+
+    let formatted = if is_minified {
+        print_minified(&result.document, &result.interner)
+    } else {
+        print_document(&result.document, &result.interner)
+    };
+
+    Ok(if is_json {
+        Output::Json(json!({ "formatted": formatted }))
+    } else if is_minified {
+        Output::Text(formatted + "\n")
+    } else {
+        Output::Text(formatted)
+    })
+}
+
+fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+fn introspect(path: &str) -> CommandResult {
+    if is_url(path) {
+        return Err(vec![Diagnostic::new(
+            path,
+            "fetching a schema over http requires the `client` feature".to_owned(),
+        )]);
+    }
+
+    let mut interner = Rodeo::default();
+
+    if path.ends_with(".json") {
+        let buffer = read_file(path)?;
+
+        let json = serde_json::from_slice::<JsonValue>(&buffer)
+            .map_err(|err| vec![Diagnostic::new(path, format!("invalid json: {}", err))])?;
+
+        let document = document_from_introspection(&json, &mut interner)
+            .map_err(|err| vec![Diagnostic::new(path, err.to_string())])?;
+
+        return Ok(Output::Text(print_document(&document, &interner)));
+    }
+
+    let schema = parse_schema(path, &mut interner)?;
+
+    introspection_from_document(&schema, &interner)
+        .map(Output::Json)
+        .map_err(|err| vec![Diagnostic::new(path, err.to_string())])
+}
+
+/// Fetch the schema of the server at `url` and write it to `schema.graphql`.
+/// Each of `headers` is of the form `Name: value`
+#[cfg(feature = "client")]
+fn fetch_schema(url: &str, headers: &[String], is_json: bool) -> CommandResult {
+    const OUTPUT: &str = "schema.graphql";
+
+    let mut client = Client::new(url);
+
+    for header in headers {
+        let (name, value) = header.split_once(':').ok_or_else(|| {
+            vec![Diagnostic::new(
+                url,
+                format!(
+                    "expected a header of the form `Name: value`, found `{}`",
+                    header
+                ),
+            )]
+        })?;
+
+        client
+            .header(name.trim(), value.trim())
+            .map_err(|err| vec![Diagnostic::new(url, err.to_string())])?;
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| vec![Diagnostic::new(url, err.to_string())])?;
+
+    let mut interner = Rodeo::default();
+
+    let schema = runtime
+        .block_on(client.introspect(&mut interner))
+        .map_err(|err| vec![Diagnostic::new(url, err.to_string())])?;
+
+    fs::write(OUTPUT, print_document(&schema, &interner))
+        .map_err(|err| vec![Diagnostic::new(OUTPUT, format!("failed to write: {}", err))])?;
+
+    Ok(if is_json {
+        Output::Json(json!({ "file": OUTPUT }))
+    } else {
+        Output::Text(format!("wrote {}\n", OUTPUT))
+    })
+}
+
+fn parse_schema(path: &str, interner: &mut Rodeo) -> Result<Document, Vec<Diagnostic>> {
+    let buffer = read_file(path)?;
+
+    let mut schema = GraphqlParser::parse_schema_with_interner(&buffer, interner)
+        .map_err(|err| vec![Diagnostic::from_parse_error(path, &buffer, &err)])?;
+
+    let errors = schema.apply_extensions();
+
+    if !errors.is_empty() {
+        return Err(errors
+            .iter()
+            .map(|err| Diagnostic::new(path, err.message(interner)))
+            .collect());
+    }
+
+    Ok(schema)
+}
+
+fn check_variables(schema_path: &str, operation_path: &str, variables_path: &str) -> CommandResult {
+    let mut interner = Rodeo::default();
+
+    let schema = parse_schema(schema_path, &mut interner)?;
+
+    let buffer = read_file(operation_path)?;
+    let document = GraphqlParser::parse_query_with_interner(&buffer, &mut interner)
+        .map_err(|err| vec![Diagnostic::from_parse_error(operation_path, &buffer, &err)])?;
+
+    let operation = match document.operations.values().collect::<Vec<_>>()[..] {
+        [operation] => operation,
+        ref operations => {
+            return Err(vec![Diagnostic::new(
+                operation_path,
+                format!("expected a single operation, found {}", operations.len()),
+            )])
+        }
+    };
+
+    let buffer = read_file(variables_path)?;
+    let variables = match serde_json::from_slice::<JsonValue>(&buffer) {
+        Ok(JsonValue::Object(variables)) => variables,
+        Ok(..) => {
+            return Err(vec![Diagnostic::new(
+                variables_path,
+                "expected an object of variable values".to_owned(),
+            )])
+        }
+        Err(err) => {
+            return Err(vec![Diagnostic::new(
+                variables_path,
+                format!("invalid json: {}", err),
+            )])
+        }
+    };
+
+    let coerced = coerce_variable_values(
+        &schema,
+        &operation.variable_definitions,
+        &variables,
+        &mut interner,
+    )
+    .map_err(|errors| {
+        errors
+            .iter()
+            .map(|err| Diagnostic::new(variables_path, err.to_string()))
+            .collect::<Vec<_>>()
+    })?;
+
+    let coerced = coerced
+        .into_iter()
+        .map(|(name, value)| {
+            let value = value
+                .to_json(&interner)
+                .map_err(|err| vec![Diagnostic::new(operation_path, err.to_string())])?;
+
+            Ok((interner.resolve(&name).to_owned(), value))
+        })
+        .collect::<Result<serde_json::Map<_, _>, Vec<Diagnostic>>>()?;
+
+    Ok(Output::Json(JsonValue::Object(coerced)))
+}
+
+/// With a usage report or operations, changes to parts of the old schema
+/// that aren't used are downgraded to safe
+fn diff(
+    old_path: &str,
+    new_path: &str,
+    usage_report: Option<&str>,
+    operations: &[String],
+    is_json: bool,
+) -> CommandResult {
+    let mut interner = Rodeo::default();
+
+    let old = parse_schema(old_path, &mut interner)?;
+    let new = parse_schema(new_path, &mut interner)?;
+
+    let mut changes = diff_schemas(&old, &new, &interner);
+
+    let mut usage = match usage_report {
+        Some(path) => {
+            let buffer = read_file(path)?;
+
+            let json = serde_json::from_slice::<JsonValue>(&buffer)
+                .map_err(|err| vec![Diagnostic::new(path, format!("invalid json: {}", err))])?;
+
+            Some(
+                SchemaUsage::from_json(&json)
+                    .map_err(|message| vec![Diagnostic::new(path, message)])?,
+            )
+        }
+        None => None,
+    };
+
+    if !operations.is_empty() {
+        let usage = usage.get_or_insert_with(|| SchemaUsage::new(&old, &interner));
+        record_corpus(usage, &old, operations, &mut interner)?;
+    }
+
+    if let Some(usage) = &usage {
+        apply_usage(&mut changes, &old, usage, &interner);
+    }
+
+    let output = if is_json {
+        let changes = changes
+            .iter()
+            .map(|change| {
+                json!({
+                    "criticality": change.criticality.as_str(),
+                    "path": change.path,
+                    "message": change.kind.to_string(),
+                    "unused": change.is_unused,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Output::Json(json!({ "changes": changes }))
+    } else {
+        Output::Text(
+            changes
+                .iter()
+                .map(|change| format!("{}: {}\n", change.criticality.as_str(), change))
+                .collect(),
+        )
+    };
+
+    let is_breaking = changes
+        .iter()
+        .any(|change| change.criticality == Criticality::Breaking);
+
+    Ok(if is_breaking {
+        Output::Failure(Box::new(output))
+    } else {
+        output
+    })
+}
+
+/// The `.graphql` and `.gql` files among `paths`, searching directories
+/// recursively
+fn document_files(paths: &[String]) -> Result<Vec<String>, Vec<Diagnostic>> {
+    let mut files = Vec::new();
+    let mut pending = paths.iter().rev().cloned().collect::<Vec<_>>();
+
+    while let Some(path) = pending.pop() {
+        let metadata = fs::metadata(&path)
+            .map_err(|err| vec![Diagnostic::new(&path, format!("failed to read: {}", err))])?;
+
+        if !metadata.is_dir() {
+            files.push(path);
+            continue;
+        }
+
+        let entries = fs::read_dir(&path)
+            .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+            .map_err(|err| vec![Diagnostic::new(&path, format!("failed to read: {}", err))])?;
+
+        let mut children = entries
+            .into_iter()
+            .map(|entry| entry.path())
+            .filter(|child| {
+                child.is_dir()
+                    || child
+                        .extension()
+                        .is_some_and(|extension| extension == "graphql" || extension == "gql")
+            })
+            .map(|child| child.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
+        children.sort();
+        pending.extend(children.into_iter().rev());
+    }
+
+    Ok(files)
+}
+
+/// Record the uses of `schema` by the documents among `paths` in `usage`
+fn record_corpus(
+    usage: &mut SchemaUsage,
+    schema: &Document,
+    paths: &[String],
+    interner: &mut Rodeo,
+) -> Result<(), Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+
+    for path in document_files(paths)? {
+        let buffer = read_file(&path)?;
+
+        match GraphqlParser::parse_query_with_interner(&buffer, interner) {
+            Ok(document) => usage.record(schema, &document, interner),
+            Err(err) => diagnostics.push(Diagnostic::from_parse_error(&path, &buffer, &err)),
+        }
+    }
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    Ok(())
+}
+
+fn usage(schema_path: &str, paths: &[String], is_json: bool) -> CommandResult {
+    let mut interner = Rodeo::default();
+
+    let schema = parse_schema(schema_path, &mut interner)?;
+    let mut usage = SchemaUsage::new(&schema, &interner);
+    record_corpus(&mut usage, &schema, paths, &mut interner)?;
+
+    Ok(if is_json {
+        Output::Json(usage.to_json())
+    } else {
+        Output::Text(usage.to_string())
+    })
+}
+
+fn subset(schema_path: &str, paths: &[String]) -> CommandResult {
+    let mut interner = Rodeo::default();
+
+    let schema = parse_schema(schema_path, &mut interner)?;
+    let mut usage = SchemaUsage::new(&schema, &interner);
+    record_corpus(&mut usage, &schema, paths, &mut interner)?;
+
+    let subset = schema_subset(&schema, &usage, &interner);
+
+    Ok(Output::Text(print_document(&subset, &interner)))
+}
+
+#[cfg(feature = "spans")]
+fn describe_comments(path: &str, is_json: bool) -> CommandResult {
+    let buffer = read_file(path)?;
+
+    let migration = comments_to_descriptions(&buffer)
+        .map_err(|err| vec![Diagnostic::from_parse_error(path, &buffer, &err)])?;
+
+    let ambiguities = migration
+        .ambiguities
+        .iter()
+        .map(|ambiguity| Diagnostic {
+            location: Some(ambiguity.span.start_location(&buffer)),
+            ..Diagnostic::new(path, format!("comment not converted: {}", ambiguity.kind))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(if is_json {
+        Output::Json(json!({
+            "source": migration.source,
+            "converted": migration.converted,
+            "ambiguities": ambiguities.iter().map(Diagnostic::to_json).collect::<Vec<_>>(),
+        }))
+    } else {
+        Output::Warnings(Box::new(Output::Text(migration.source)), ambiguities)
+    })
+}
+
+/// Parse the documents among `paths` into one, so that operations can spread
+/// fragments defined in other files, and validate it against `schema`
+fn parse_operations(
+    schema: &Document,
+    paths: &[String],
+    interner: &mut Rodeo,
+) -> Result<Document, Vec<Diagnostic>> {
+    let mut document = Document::new();
+    let mut diagnostics = Vec::new();
+    // the file each operation and fragment is defined in, by the first
+    // element of the paths of validation errors in it
+    let mut files = HashMap::new();
+
+    let files_to_parse = document_files(paths)?;
+
+    for path in &files_to_parse {
+        let buffer = read_file(path)?;
+
+        let parsed = match GraphqlParser::parse_query_with_interner(&buffer, interner) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                diagnostics.push(Diagnostic::from_parse_error(path, &buffer, &err));
+                continue;
+            }
+        };
+
+        for (key, operation) in parsed.operations {
+            let label = match operation.name {
+                Some(name) => format!("{} {}", operation.kind.as_str(), interner.resolve(&name)),
+                None => format!("anonymous {}", operation.kind.as_str()),
+            };
+
+            files.insert(label, path.as_str());
+            document.operations.insert(key, operation);
+        }
+
+        for (name, fragment) in parsed.fragments {
+            files.insert(
+                format!("fragment {}", interner.resolve(&name)),
+                path.as_str(),
+            );
+            document.fragments.insert(name, fragment);
+        }
+    }
+
+    diagnostics.extend(
+        validate_executable_document(schema, &document, interner)
+            .into_iter()
+            .map(|err| {
+                let file = err
+                    .path
+                    .first()
+                    .and_then(|label| files.get(label))
+                    .map_or(files_to_parse[0].as_str(), |file| *file);
+
+                Diagnostic {
+                    path: err.path,
+                    ..Diagnostic::new(file, err.kind.to_string())
+                }
+            }),
+    );
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    Ok(document)
+}
+
+/// Generate code for every operation among `paths`
+fn codegen(schema_path: &str, paths: &[String], target: Option<&str>) -> CommandResult {
+    let mut interner = Rodeo::default();
+
+    let schema = parse_schema(schema_path, &mut interner)?;
+    let document = parse_operations(&schema, paths, &mut interner)?;
+
+    match target {
+        Some("typescript") => Ok(Output::Text(generate_typescript_operations(
+            &schema,
+            &document,
+            &interner,
+            &TypeScriptCodegenConfig::default(),
+        ))),
+        #[cfg(feature = "persisted-queries")]
+        Some(target @ ("apollo" | "relay")) => {
+            artifacts(&schema, &document, target, &paths[0], &interner)
+        }
+        #[cfg(not(feature = "persisted-queries"))]
+        Some(target @ ("apollo" | "relay")) => Err(vec![Diagnostic::new(
+            "<args>",
+            format!(
+                "the `{}` target requires the `persisted-queries` feature",
+                target
+            ),
+        )]),
+        Some(target) => Err(vec![Diagnostic::new(
+            "<args>",
+            format!("unknown codegen target `{}`", target),
+        )]),
+        None => Err(vec![Diagnostic::new(
+            "<args>",
+            "missing `--target`".to_owned(),
+        )]),
+    }
+}
+
+/// The `apollo` or `relay` artifacts of every operation of `document`, keyed
+/// by operation name
+#[cfg(feature = "persisted-queries")]
+fn artifacts(
+    schema: &Document,
+    document: &Document,
+    target: &str,
+    path: &str,
+    interner: &Rodeo,
+) -> CommandResult {
+    let mut operations = document.operations.values().collect::<Vec<_>>();
+    operations.sort_by_key(|operation| operation.name.map(|name| interner.resolve(&name)));
+
+    let artifacts = operations
+        .into_iter()
+        .map(|operation| {
+            let name = operation.name.map_or("", |name| interner.resolve(&name));
+
+            let artifact = if target == "relay" {
+                relay_artifact(schema, document, operation, interner)
+                    .map_err(|err| vec![Diagnostic::new(path, err.to_string())])?
+            } else {
+                apollo_artifact(document, operation, interner)
+            };
+
+            Ok((name.to_owned(), artifact))
+        })
+        .collect::<Result<_, Vec<Diagnostic>>>()?;
+
+    Ok(Output::Json(JsonValue::Object(artifacts)))
+}
+
+/// The parts of the type graph to export with `--format dot` or
+/// `--format mermaid`
+#[derive(Default)]
+struct GraphFilter {
+    focus: Option<String>,
+    depth: Option<String>,
+    exclude_scalars: bool,
+}
+
+fn export(schema_path: &str, format: Option<&str>, graph_filter: &GraphFilter) -> CommandResult {
+    let mut interner = Rodeo::default();
+
+    let schema = parse_schema(schema_path, &mut interner)?;
+
+    match format {
+        Some("json-schema") => Ok(Output::Json(json_schema_from_document(&schema, &interner))),
+        Some("openapi") => Ok(Output::Json(openapi_from_document(
+            &schema,
+            &interner,
+            &OpenApiConfig::default(),
+        ))),
+        Some("proto") => Ok(Output::Text(proto_from_document(
+            &schema,
+            &interner,
+            &ProtoConfig::default(),
+        ))),
+        Some("sql") => sql_from_document(&schema, &interner)
+            .map(Output::Text)
+            .map_err(|errors| {
+                errors
+                    .iter()
+                    .map(|err| Diagnostic::new(schema_path, err.to_string()))
+                    .collect()
+            }),
+        Some(format @ ("dot" | "mermaid")) => {
+            let graph = type_graph(&schema, &interner, graph_filter)?;
+
+            Ok(Output::Text(if format == "dot" {
+                graph.to_dot(&interner)
+            } else {
+                graph.to_mermaid(&interner)
+            }))
+        }
+        Some(format) => Err(vec![Diagnostic::new(
+            "<args>",
+            format!("unknown export format `{}`", format),
+        )]),
+        None => Err(vec![Diagnostic::new(
+            "<args>",
+            "missing `--format`".to_owned(),
+        )]),
+    }
+}
+
+fn type_graph(
+    schema: &Document,
+    interner: &Rodeo,
+    filter: &GraphFilter,
+) -> Result<TypeGraph, Vec<Diagnostic>> {
+    let options = TypeGraphOptions {
+        scalars: !filter.exclude_scalars,
+        ..TypeGraphOptions::default()
+    };
+
+    let graph = TypeGraph::new(schema, interner, &options);
+
+    let focus = match &filter.focus {
+        Some(focus) => focus,
+        None => return Ok(graph),
+    };
+
+    let depth = match filter.depth.as_deref().map(str::parse::<usize>) {
+        None => 1,
+        Some(Ok(depth)) => depth,
+        Some(Err(..)) => {
+            return Err(vec![Diagnostic::new(
+                "<args>",
+                "expected a number for `--depth`".to_owned(),
+            )])
+        }
+    };
+
+    match interner.get(focus) {
+        Some(name) if schema.type_kind(name).is_some() => Ok(graph.neighborhood(name, depth)),
+        _ => Err(vec![Diagnostic::new(
+            "<args>",
+            format!("unknown type `{}`", focus),
+        )]),
+    }
+}
+
+fn docs(schema_path: &str, dir: &str, format: Option<&str>, is_json: bool) -> CommandResult {
+    let format = match format {
+        None | Some("markdown") => DocsFormat::Markdown,
+        Some("html") => DocsFormat::Html,
+        Some(format) => {
+            return Err(vec![Diagnostic::new(
+                "<args>",
+                format!("unknown docs format `{}`", format),
+            )])
+        }
+    };
+
+    let mut interner = Rodeo::default();
+
+    let schema = parse_schema(schema_path, &mut interner)?;
+
+    let config = DocsConfig {
+        format,
+        ..DocsConfig::default()
+    };
+
+    let pages = generate_docs(&schema, &interner, &config);
+
+    fs::create_dir_all(dir)
+        .map_err(|err| vec![Diagnostic::new(dir, format!("failed to create: {}", err))])?;
+
+    for page in &pages {
+        let path = Path::new(dir).join(&page.path);
+
+        fs::write(&path, &page.content).map_err(|err| {
+            vec![Diagnostic::new(
+                &path.display().to_string(),
+                format!("failed to write: {}", err),
+            )]
+        })?;
+    }
+
+    Ok(if is_json {
+        let files = pages.iter().map(|page| &page.path).collect::<Vec<_>>();
+
+        Output::Json(json!({ "files": files }))
+    } else {
+        Output::Text(format!("wrote {} pages to {}\n", pages.len(), dir))
+    })
+}
+
+fn compose(paths: &[String]) -> CommandResult {
+    let mut interner = Rodeo::default();
+    let mut subgraphs = Vec::new();
+    // the path and source of each subgraph, by name
+    let mut sources = HashMap::new();
+    let mut diagnostics = Vec::new();
+
+    for path in paths {
+        let buffer = read_file(path)?;
+
+        let name = Path::new(path)
+            .file_stem()
+            .map_or_else(|| path.clone(), |stem| stem.to_string_lossy().into_owned());
+
+        let document = match GraphqlParser::parse_schema_with_interner(&buffer, &mut interner) {
+            Ok(document) => document,
+            Err(err) => {
+                diagnostics.push(Diagnostic::from_parse_error(path, &buffer, &err));
+                continue;
+            }
+        };
+
+        let (subgraph, errors) = Subgraph::new(name.clone(), document, &mut interner);
+
+        diagnostics.extend(errors.iter().map(|err| Diagnostic {
+            location: Some(err.span.start_location(&buffer)),
+            ..Diagnostic::new(path, err.to_string())
+        }));
+
+        subgraphs.push(subgraph);
+        sources.insert(name, (path, buffer));
+    }
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    compose_supergraph(&subgraphs, &interner)
+        .map(Output::Text)
+        .map_err(|errors| {
+            errors
+                .iter()
+                .flat_map(|error| {
+                    error.locations.iter().map(|location| {
+                        let (path, buffer) = &sources[&location.subgraph];
+
+                        Diagnostic {
+                            location: Some(location.span.start_location(buffer)),
+                            ..Diagnostic::new(path, error.to_string())
+                        }
+                    })
+                })
+                .collect()
+        })
+}
+
+#[cfg(feature = "registry")]
+fn open_registry(dir: &str) -> Result<Registry, Vec<Diagnostic>> {
+    Registry::open(dir).map_err(|err| registry_error(dir, err))
+}
+
+#[cfg(feature = "registry")]
+fn registry_error(dir: &str, err: RegistryError) -> Vec<Diagnostic> {
+    vec![Diagnostic::new(dir, err.to_string())]
+}
+
+#[cfg(feature = "registry")]
+fn version_json(version: &SchemaVersion) -> JsonValue {
+    json!({
+        "version": version.version,
+        "hash": version.hash,
+        "publishedAt": version.published_at,
+        "message": version.message,
+    })
+}
+
+#[cfg(feature = "registry")]
+fn registry_publish(
+    dir: &str,
+    schema_path: &str,
+    message: Option<&str>,
+    is_json: bool,
+) -> CommandResult {
+    let mut interner = Rodeo::default();
+    let schema = parse_schema(schema_path, &mut interner)?;
+
+    let mut registry = open_registry(dir)?;
+
+    let outcome = registry
+        .publish(&schema, &interner, message)
+        .map_err(|err| registry_error(dir, err))?;
+
+    let (version, is_published) = match &outcome {
+        PublishOutcome::Published(version) => (version, true),
+        PublishOutcome::Unchanged(version) => (version, false),
+    };
+
+    if is_json {
+        return Ok(Output::Json(json!({
+            "published": is_published,
+            "version": version_json(version),
+        })));
+    }
+
+    Ok(Output::Text(if is_published {
+        format!("published version {} ({})\n", version.version, version.hash)
+    } else {
+        format!("unchanged from version {}\n", version.version)
+    }))
+}
+
+#[cfg(feature = "registry")]
+fn registry_check(dir: &str, schema_path: &str, is_json: bool) -> CommandResult {
+    let mut interner = Rodeo::default();
+    let schema = parse_schema(schema_path, &mut interner)?;
+
+    let registry = open_registry(dir)?;
+
+    let result = registry
+        .check(&schema, &mut interner)
+        .map_err(|err| registry_error(dir, err))?;
+
+    let breaking = result
+        .breaking_changes()
+        .map(|change| Diagnostic::new(schema_path, format!("breaking change: {}", change)))
+        .collect::<Vec<_>>();
+
+    if !breaking.is_empty() {
+        return Err(breaking);
+    }
+
+    if is_json {
+        let changes = result
+            .changes
+            .iter()
+            .map(|change| {
+                json!({
+                    "criticality": change.criticality.as_str(),
+                    "path": change.path,
+                    "message": change.kind.to_string(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        return Ok(Output::Json(json!({
+            "base": result.base.as_ref().map(version_json),
+            "changes": changes,
+        })));
+    }
+
+    Ok(Output::Text(
+        result
+            .changes
+            .iter()
+            .map(|change| format!("{}: {}\n", change.criticality.as_str(), change))
+            .collect(),
+    ))
+}
+
+#[cfg(feature = "registry")]
+fn registry_history(dir: &str, is_json: bool) -> CommandResult {
+    let registry = open_registry(dir)?;
+
+    registry.verify().map_err(|err| registry_error(dir, err))?;
+
+    if is_json {
+        let versions = registry
+            .history()
+            .iter()
+            .map(version_json)
+            .collect::<Vec<_>>();
+
+        return Ok(Output::Json(json!({ "versions": versions })));
+    }
+
+    Ok(Output::Text(
+        registry
+            .history()
+            .iter()
+            .map(|version| {
+                format!(
+                    "{} {} {}{}\n",
+                    version.version,
+                    &version.hash[..12],
+                    version.published_at,
+                    version
+                        .message
+                        .as_ref()
+                        .map_or_else(String::new, |message| format!(" {}", message))
+                )
+            })
+            .collect(),
+    ))
+}
+
+fn lsp(schema_path: Option<&str>) -> CommandResult {
+    let mut server = match schema_path {
+        Some(path) => {
+            let source = String::from_utf8(read_file(path)?)
+                .map_err(|_| vec![Diagnostic::new(path, "invalid utf-8".to_owned())])?;
+
+            let uri = match fs::canonicalize(path) {
+                Ok(path) => format!("file://{}", path.display()),
+                Err(err) => {
+                    return Err(vec![Diagnostic::new(
+                        path,
+                        format!("failed to read: {}", err),
+                    )])
+                }
+            };
+
+            LanguageServer::with_schema(uri, source)
+        }
+        None => LanguageServer::new(),
+    };
+
+    server
+        .run(io::stdin().lock(), io::stdout().lock())
+        .map_err(|err| {
+            vec![Diagnostic::new(
+                "<stdin>",
+                format!("failed to serve: {}", err),
+            )]
+        })?;
+
+    Ok(Output::Text(String::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::{report, CommandLine, Diagnostic, Output};
+
+    fn command_line(args: &[&str]) -> CommandLine {
+        CommandLine::parse(args.iter().map(|arg| (*arg).to_owned()))
+    }
+
+    /// Write `contents` to a temporary file named `name`, returning its path
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("graphql-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// The messages of the errors the command fails with, and its exit status
+    fn run(args: &[&str]) -> (Vec<String>, i32) {
+        let result = command_line(args).run().expect("unknown command");
+
+        let messages = match &result {
+            Ok(..) => Vec::new(),
+            Err(diagnostics) => diagnostics.iter().map(ToString::to_string).collect(),
+        };
+
+        (messages, report(result, false))
+    }
+
+    #[test]
+    fn flags_anywhere() {
+        let command_line = command_line(&[
+            "--json",
+            "diff",
+            "--usage",
+            "usage.json",
+            "old.graphql",
+            "--operations",
+            "a",
+            "--operations",
+            "b",
+            "new.graphql",
+        ]);
+
+        assert!(command_line.is_json);
+        assert!(!command_line.is_minified);
+        assert_eq!(command_line.usage_report.as_deref(), Some("usage.json"));
+        assert_eq!(command_line.operations, ["a", "b"]);
+        assert_eq!(command_line.args, ["diff", "old.graphql", "new.graphql"]);
+    }
+
+    #[test]
+    fn graph_filter() {
+        let command_line = command_line(&[
+            "export",
+            "schema.graphql",
+            "--format",
+            "dot",
+            "--focus",
+            "User",
+            "--depth",
+            "2",
+            "--exclude-scalars",
+        ]);
+
+        assert_eq!(command_line.export_format.as_deref(), Some("dot"));
+        assert_eq!(command_line.graph_filter.focus.as_deref(), Some("User"));
+        assert_eq!(command_line.graph_filter.depth.as_deref(), Some("2"));
+        assert!(command_line.graph_filter.exclude_scalars);
+    }
+
+    #[test]
+    fn unknown_commands() {
+        for args in [
+            &[][..],
+            &["nope"],
+            &["parse"],
+            &["parse", "a", "b"],
+            &["format", "--minify"],
+            &["diff", "old.graphql"],
+            &["usage", "schema.graphql"],
+            &["subset", "schema.graphql"],
+        ] {
+            assert!(command_line(args).run().is_none(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(report(Ok(Output::Text(String::new())), false), 0);
+        assert_eq!(report(Ok(Output::Json(serde_json::json!({}))), true), 0);
+        assert_eq!(
+            report(
+                Ok(Output::Failure(Box::new(Output::Text(String::new())))),
+                false
+            ),
+            1
+        );
+        assert_eq!(
+            report(Err(vec![Diagnostic::new("a", "b".to_owned())]), false),
+            1
+        );
+        assert_eq!(
+            report(Err(vec![Diagnostic::new("a", "b".to_owned())]), true),
+            1
+        );
+
+        let (messages, code) = run(&["parse", "/nonexistent/graphql/file"]);
+        assert_eq!(code, 1);
+        assert!(messages[0].contains("failed to read"), "{:?}", messages);
+    }
+
+    #[test]
+    fn format_keeps_or_rejects() {
+        let formatted = temp_file("format.graphql", "type Query{a:Int}");
+        let path = formatted.to_str().unwrap();
+        assert_eq!(run(&["format", path]), (Vec::new(), 0));
+
+        let commented = temp_file("format-comment.graphql", "type Query {\n  # a\n  a: Int\n}");
+        let path = commented.to_str().unwrap();
+        assert_eq!(
+            run(&["format", path]),
+            (
+                vec![format!(
+                    "{}:2:3: formatting would remove this comment",
+                    path
+                )],
+                1
+            )
+        );
+
+        let duplicated = temp_file(
+            "format-duplicate.graphql",
+            "type Query { a: Int }\ntype Query { b: Int }",
+        );
+        let path = duplicated.to_str().unwrap();
+        let (messages, code) = run(&["format", path]);
+        assert_eq!(code, 1);
+        assert_eq!(
+            messages,
+            [format!(
+                "{}:1:1: formatting would remove this definition, which a later one replaces",
+                path
+            )]
+        );
+
+        for path in [formatted, commented, duplicated] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn check_variables() {
+        let schema = temp_file(
+            "variables-schema.graphql",
+            "type Query { a(b: Int!, c: Filter): Int } input Filter { d: [Int!]! }",
+        );
+        let operation = temp_file(
+            "variables-operation.graphql",
+            "query Q($b: Int!, $c: Filter) { a(b: $b, c: $c) }",
+        );
+        let valid = temp_file("variables-valid.json", r#"{ "b": 1, "c": { "d": 2 } }"#);
+        let invalid = temp_file("variables-invalid.json", r#"{ "c": { "d": [1, "2"] } }"#);
+        let paths = [&schema, &operation, &valid, &invalid].map(|path| path.to_str().unwrap());
+
+        let coerced = match command_line(&["check-variables", paths[0], paths[1], paths[2]]).run() {
+            Some(Ok(Output::Json(json))) => json,
+            _ => panic!("expected the coerced variables"),
+        };
+        // a single value is coerced to a list of one
+        assert_eq!(coerced, serde_json::json!({ "b": 1, "c": { "d": [2] } }));
+
+        let (mut messages, code) = run(&["check-variables", paths[0], paths[1], paths[3]]);
+        messages.sort();
+        assert_eq!(code, 1);
+        assert_eq!(
+            messages,
+            [
+                format!(
+                    "{}: variable `$b`: a value of type `Int!` is required",
+                    paths[3]
+                ),
+                format!(
+                    "{}: variable `$c.d[1]`: expected a value of type `Int`, found a string",
+                    paths[3]
+                ),
+            ]
+        );
+
+        for path in [schema, operation, valid, invalid] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn diff_reports_breaking_changes() {
+        let old = temp_file("diff-old.graphql", "type Query { a: Int b: Int }");
+        let safe = temp_file("diff-safe.graphql", "type Query { a: Int b: Int c: Int }");
+        let breaking = temp_file("diff-breaking.graphql", "type Query { a: Int }");
+        let old_path = old.to_str().unwrap();
+
+        let changes = |new: &PathBuf| {
+            let result = command_line(&["diff", "--json", old_path, new.to_str().unwrap()])
+                .run()
+                .expect("unknown command");
+            let (output, code) = match result {
+                Ok(Output::Failure(output)) => (*output, 1),
+                Ok(output) => (output, 0),
+                Err(..) => panic!("diff failed"),
+            };
+            let json = match output {
+                Output::Json(json) => json,
+                _ => panic!("expected JSON output"),
+            };
+            (json["changes"].as_array().unwrap().len(), code)
+        };
+
+        assert_eq!(changes(&safe), (1, 0));
+        assert_eq!(changes(&breaking), (1, 1));
+
+        for path in [old, safe, breaking] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    #[cfg(feature = "spans")]
+    #[test]
+    fn describe_comments() {
+        let schema = temp_file(
+            "describe-comments.graphql",
+            "# Copyright\n\n# The root\ntype Query {\n  # A\n  a: Int # trailing\n}\n",
+        );
+
+        let (printed, warnings) =
+            match command_line(&["describe-comments", schema.to_str().unwrap()]).run() {
+                Some(Ok(Output::Warnings(output, warnings))) => match *output {
+                    Output::Text(text) => (text, warnings),
+                    _ => panic!("expected text output"),
+                },
+                _ => panic!("expected the rewritten schema"),
+            };
+        assert_eq!(
+            printed,
+            "# Copyright\n\n\"\"\"The root\"\"\"\ntype Query {\n  \"\"\"A\"\"\"\n  a: Int # trailing\n}\n"
+        );
+        assert_eq!(
+            warnings
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect::<Vec<_>>(),
+            [
+                format!(
+                    "{}:1:1: comment not converted: a blank line separates the comments from what follows them",
+                    schema.display()
+                ),
+                format!(
+                    "{}:6:10: comment not converted: the comment follows other tokens on its line",
+                    schema.display()
+                ),
+            ]
+        );
+
+        let _ = fs::remove_file(schema);
+    }
 
-    let start = std::time::Instant::now();
+    #[test]
+    fn subset() {
+        let schema = temp_file(
+            "subset-schema.graphql",
+            "type Query { a: A b: Int } type A { c: Int d: Int } type Unused { e: Int }",
+        );
+        let operation = temp_file("subset-operation.graphql", "{ a { c } }");
 
-    let document = GraphqlParser::parse(&buffer).unwrap();
+        let printed = match command_line(&[
+            "subset",
+            schema.to_str().unwrap(),
+            operation.to_str().unwrap(),
+        ])
+        .run()
+        {
+            Some(Ok(Output::Text(text))) => text,
+            _ => panic!("expected the subset"),
+        };
+        assert_eq!(
+            printed,
+            "type A {\n  c: Int\n}\n\ntype Query {\n  a: A\n}\n"
+        );
 
-    dbg!(start.elapsed());
+        for path in [schema, operation] {
+            let _ = fs::remove_file(path);
+        }
+    }
 }
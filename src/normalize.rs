@@ -0,0 +1,462 @@
+//! Canonical forms of documents. Documents that differ only in ways that
+//! don't change their meaning normalize to the same document, and print to
+//! the same text, which makes the result suitable as a key for caches and for
+//! deduplicating persisted operations
+//!
+//! Normalization:
+//!
+//! - sorts the arguments of fields and directives, the directives at each
+//!   location and the variable definitions of operations by name. Directives
+//!   with the same name keep their order
+//! - inlines trivial fragments: fragments spread exactly once, where neither
+//!   the definition nor the spread has directives, become inline fragments,
+//!   and inline fragments without directives on the type of their enclosing
+//!   selection set are replaced by their selections
+//! - strips descriptions
+//!
+//! Ignored tokens are dropped by printing the result with
+//! [`print_minified`]. The order of selections is kept, as it determines the
+//! order of the fields in a response
+
+use std::collections::{HashMap, HashSet};
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        Argument, Directive, Document, FieldDefinition, InputObjectField, Selection, Span,
+        TypeExtension,
+    },
+    fmt::print_minified,
+};
+
+/// The canonical form of `document`
+pub fn normalize(document: &Document, interner: &Rodeo) -> Document {
+    let mut spreads = HashMap::<Spur, usize>::new();
+
+    for operation in document.operations.values() {
+        count_spreads(&operation.selection_set, &mut spreads);
+    }
+
+    for fragment in document.fragments.values() {
+        count_spreads(&fragment.selection_set, &mut spreads);
+    }
+
+    let trivial = document
+        .fragments
+        .values()
+        .filter(|fragment| {
+            fragment.directives.is_empty() && spreads.get(&fragment.name) == Some(&1)
+        })
+        .map(|fragment| fragment.name)
+        .collect();
+
+    let mut normalizer = Normalizer {
+        document,
+        interner,
+        trivial,
+        inlining: Vec::new(),
+        inlined: HashSet::new(),
+    };
+
+    let mut normalized = document.clone();
+
+    for operation in normalized.operations.values_mut() {
+        operation.selection_set = normalizer.selection_set(&operation.selection_set, None);
+        normalizer.directives(&mut operation.directives);
+        operation
+            .variable_definitions
+            .sort_by(|a, b| interner.resolve(&a.name).cmp(interner.resolve(&b.name)));
+    }
+
+    // fragments that were inlined are removed, so trivial fragments are only
+    // normalized on their own if they're never inlined, as in a cycle
+    let mut fragments = normalized.fragments.keys().copied().collect::<Vec<_>>();
+    fragments.sort_by_key(|name| (normalizer.trivial.contains(name), interner.resolve(name)));
+
+    for name in fragments {
+        if normalizer.inlined.contains(&name) {
+            continue;
+        }
+
+        let fragment = normalized.fragments.get_mut(&name).unwrap();
+
+        normalizer.inlining.push(name);
+        fragment.selection_set =
+            normalizer.selection_set(&fragment.selection_set, Some(fragment.on));
+        normalizer.inlining.pop();
+
+        normalizer.directives(&mut fragment.directives);
+    }
+
+    normalized
+        .fragments
+        .retain(|name, _| !normalizer.inlined.contains(name));
+
+    normalizer.type_system(&mut normalized);
+
+    normalized
+}
+
+/// The text of the canonical form of `document`, printed without ignored
+/// tokens
+pub fn canonical_text(document: &Document, interner: &Rodeo) -> String {
+    print_minified(&normalize(document, interner), interner)
+}
+
+fn count_spreads(selection_set: &[Selection], spreads: &mut HashMap<Spur, usize>) {
+    for selection in selection_set {
+        match selection {
+            Selection::Field { selection_set, .. } => {
+                count_spreads(selection_set.as_deref().unwrap_or_default(), spreads);
+            }
+            Selection::InlineFragment { selection_set, .. } => {
+                count_spreads(selection_set, spreads);
+            }
+            Selection::FragmentSpread {
+                name, directives, ..
+            } => {
+                // a spread with directives can't be inlined, so it counts as
+                // a second spread
+                let count = if directives.is_empty() { 1 } else { 2 };
+                *spreads.entry(*name).or_default() += count;
+            }
+        }
+    }
+}
+
+struct Normalizer<'a> {
+    document: &'a Document,
+    interner: &'a Rodeo,
+    /// The fragments to inline at their spread
+    trivial: HashSet<Spur>,
+    /// The fragments currently being inlined or normalized, to stop at
+    /// fragments that spread themselves
+    inlining: Vec<Spur>,
+    inlined: HashSet<Spur>,
+}
+
+impl Normalizer<'_> {
+    /// `parent_type` is the type the selection set is on, if it's known
+    /// without a schema
+    fn selection_set(
+        &mut self,
+        selection_set: &[Selection],
+        parent_type: Option<Spur>,
+    ) -> Vec<Selection> {
+        let mut normalized = Vec::new();
+
+        for selection in selection_set {
+            match selection {
+                Selection::Field {
+                    alias,
+                    name,
+                    arguments,
+                    directives,
+                    selection_set,
+                    span,
+                } => {
+                    let mut arguments = arguments.clone();
+                    let mut directives = directives.clone();
+
+                    self.arguments(arguments.as_deref_mut().unwrap_or_default());
+                    self.directives(&mut directives);
+
+                    normalized.push(Selection::Field {
+                        alias: *alias,
+                        name: *name,
+                        arguments,
+                        directives,
+                        selection_set: selection_set
+                            .as_ref()
+                            .map(|selection_set| self.selection_set(selection_set, None)),
+                        span: *span,
+                    });
+                }
+                Selection::FragmentSpread { name, span, .. }
+                    if self.trivial.contains(name) && !self.inlining.contains(name) =>
+                {
+                    let fragment = &self.document.fragments[name];
+
+                    self.inlining.push(*name);
+                    let selection_set =
+                        self.selection_set(&fragment.selection_set, Some(fragment.on));
+                    self.inlining.pop();
+
+                    self.inlined.insert(*name);
+
+                    self.inline_fragment(
+                        &mut normalized,
+                        Some(fragment.on),
+                        Vec::new(),
+                        selection_set,
+                        *span,
+                        parent_type,
+                    );
+                }
+                Selection::FragmentSpread {
+                    name,
+                    directives,
+                    span,
+                } => {
+                    let mut directives = directives.clone();
+                    self.directives(&mut directives);
+
+                    normalized.push(Selection::FragmentSpread {
+                        name: *name,
+                        directives,
+                        span: *span,
+                    });
+                }
+                Selection::InlineFragment {
+                    on,
+                    directives,
+                    selection_set,
+                    span,
+                } => {
+                    let mut directives = directives.clone();
+                    self.directives(&mut directives);
+
+                    // without a type condition, the fragment is on the parent type
+                    let on = on.or(parent_type);
+                    let selection_set = self.selection_set(selection_set, on);
+
+                    self.inline_fragment(
+                        &mut normalized,
+                        on,
+                        directives,
+                        selection_set,
+                        *span,
+                        parent_type,
+                    );
+                }
+            }
+        }
+
+        normalized
+    }
+
+    /// Push an inline fragment, or only its selections if it has no
+    /// directives and is on the type of the enclosing selection set
+    fn inline_fragment(
+        &self,
+        normalized: &mut Vec<Selection>,
+        on: Option<Spur>,
+        directives: Vec<Directive>,
+        selection_set: Vec<Selection>,
+        span: Span,
+        parent_type: Option<Spur>,
+    ) {
+        if directives.is_empty() && (on.is_none() || parent_type == on) {
+            normalized.extend(selection_set);
+        } else {
+            normalized.push(Selection::InlineFragment {
+                on,
+                directives,
+                selection_set,
+                span,
+            });
+        }
+    }
+
+    fn arguments(&self, arguments: &mut [Argument]) {
+        arguments.sort_by(|a, b| self.name(a.name).cmp(self.name(b.name)));
+    }
+
+    fn directives(&self, directives: &mut [Directive]) {
+        directives.sort_by(|a, b| self.name(a.name).cmp(self.name(b.name)));
+
+        for directive in directives {
+            self.arguments(directive.arguments.as_deref_mut().unwrap_or_default());
+        }
+    }
+
+    fn name(&self, name: Spur) -> &str {
+        self.interner.resolve(&name)
+    }
+
+    /// Strip the descriptions of the type system definitions of `document`,
+    /// and sort the directives applied to them
+    fn type_system(&self, document: &mut Document) {
+        if let Some(schema) = &mut document.schema {
+            schema.description = None;
+            self.directives(&mut schema.directives);
+        }
+
+        for extension in &mut document.schema_extensions {
+            self.directives(&mut extension.directives);
+        }
+
+        for directive in document.directive_definitions.values_mut() {
+            directive.description = None;
+            self.input_values(directive.arguments.as_deref_mut().unwrap_or_default());
+        }
+
+        for scalar in document.scalars.values_mut() {
+            scalar.description = None;
+            self.directives(&mut scalar.directives);
+        }
+
+        for object in document.output_objects.values_mut() {
+            object.description = None;
+            self.directives(&mut object.directives);
+            self.fields(object.fields.as_deref_mut().unwrap_or_default());
+        }
+
+        for interface in document.interfaces.values_mut() {
+            interface.description = None;
+            self.directives(&mut interface.directives);
+            self.fields(&mut interface.fields);
+        }
+
+        for union in document.unions.values_mut() {
+            union.description = None;
+            self.directives(&mut union.directives);
+        }
+
+        for enum_ty in document.enums.values_mut() {
+            enum_ty.description = None;
+            self.directives(&mut enum_ty.directives);
+
+            for variant in &mut enum_ty.variants {
+                variant.description = None;
+                self.directives(&mut variant.directives);
+            }
+        }
+
+        for input in document.input_objects.values_mut() {
+            input.description = None;
+            self.directives(&mut input.directives);
+            self.input_values(input.fields.as_deref_mut().unwrap_or_default());
+        }
+
+        for extension in &mut document.type_extensions {
+            match extension {
+                TypeExtension::Scalar(scalar) => self.directives(&mut scalar.directives),
+                TypeExtension::Object(object) => {
+                    self.directives(&mut object.directives);
+                    self.fields(object.fields.as_deref_mut().unwrap_or_default());
+                }
+                TypeExtension::Interface(interface) => {
+                    self.directives(&mut interface.directives);
+                    self.fields(&mut interface.fields);
+                }
+                TypeExtension::Union(union) => self.directives(&mut union.directives),
+                TypeExtension::Enum(enum_ty) => {
+                    self.directives(&mut enum_ty.directives);
+
+                    for variant in &mut enum_ty.variants {
+                        variant.description = None;
+                        self.directives(&mut variant.directives);
+                    }
+                }
+                TypeExtension::InputObject(input) => {
+                    self.directives(&mut input.directives);
+                    self.input_values(input.fields.as_deref_mut().unwrap_or_default());
+                }
+            }
+        }
+    }
+
+    fn fields(&self, fields: &mut [FieldDefinition]) {
+        for field in fields {
+            field.description = None;
+            self.directives(&mut field.directives);
+            self.input_values(field.arguments.as_deref_mut().unwrap_or_default());
+        }
+    }
+
+    /// Arguments and input fields keep their order, as it's visible through
+    /// introspection
+    fn input_values(&self, values: &mut [InputObjectField]) {
+        for value in values {
+            value.description = None;
+            self.directives(&mut value.directives);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::canonical_text;
+    use crate::GraphqlParser;
+
+    fn canonical(source: &str) -> String {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse document");
+
+        canonical_text(&document, &interner)
+    }
+
+    fn hash(source: &str) -> u64 {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse document");
+
+        document.canonical_hash(&interner)
+    }
+
+    #[test]
+    fn sorts_arguments_directives_and_variables() {
+        assert_eq!(
+            canonical(concat!(
+                "query Q($b: Int, $a: Int) @live @cached(ttl: 1, scope: PUBLIC) ",
+                "{ users(last: $b, first: $a) ",
+                "@tag(name: \"y\") @include(if: true) @tag(name: \"x\") }",
+            )),
+            concat!(
+                "query Q($a:Int$b:Int)@cached(scope:PUBLIC ttl:1)@live",
+                "{users(first:$a last:$b)@include(if:true)@tag(name:\"y\")@tag(name:\"x\")}",
+            )
+        );
+    }
+
+    #[test]
+    fn inlines_trivial_fragments() {
+        assert_eq!(
+            canonical(concat!(
+                "{ user { ...F ... on User { id } } node { ...G ...G } } ",
+                "fragment F on User { name ...H } fragment H on User { age } ",
+                "fragment G on Node { id }",
+            )),
+            concat!(
+                "{user{...on User{name age}...on User{id}}node{...G...G}}",
+                "fragment G on Node{id}",
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_fragments_with_directives() {
+        assert_eq!(
+            canonical(concat!(
+                "query Q($a: Boolean!) { user { ...F @include(if: $a) ...G } } ",
+                "fragment F on User { name } fragment G on User @tag { id }",
+            )),
+            concat!(
+                "query Q($a:Boolean!){user{...F@include(if:$a)...G}}",
+                "fragment F on User{name}fragment G on User@tag{id}",
+            )
+        );
+    }
+
+    #[test]
+    fn strips_descriptions() {
+        assert_eq!(
+            canonical("\"A user\" type User @key(b: 1, a: 2) { \"The id\" id(\"x\" x: Int): ID! }"),
+            "type User@key(a:2 b:1){id(x:Int):ID!}"
+        );
+    }
+
+    #[test]
+    fn equivalent_documents_hash_the_same() {
+        assert_eq!(
+            hash("query ($a: Int, $b: Int) { f(x: $a, y: $b) { ...F } } fragment F on T { id }"),
+            hash("# comment\nquery($b:Int,$a:Int){f(y:$b x:$a){...on T{id}}}")
+        );
+        assert_ne!(hash("{ a b }"), hash("{ b a }"));
+    }
+}
@@ -1,36 +1,458 @@
 use std::collections::HashMap;
 
-use lasso::Spur;
+use lasso::{Rodeo, Spur};
 
 use crate::{
     ast::{
-        Argument, Directive, Document, Enum, EnumVariant, FieldDefinition, Fragment, InputObject,
-        InputObjectField, Interface, Keyword, NamedType, ObjectType, Operation, OperationKind,
-        Scalar, Selection, Token, Type, Union, Value, VariableDefinition,
+        Argument, Comment, Definition, Directive, DirectiveDefinition, DirectiveLocation, Document,
+        Enum, EnumVariant, FieldDefinition, Fragment, InputObject, InputObjectField, Interface,
+        Keyword, NamedType, ObjectFieldSpan, ObjectType, Operation, OperationKind,
+        RootOperationTypeDefinition, Scalar, SchemaDefinition, SchemaExtension, Selection, Span,
+        Token, Type, TypeDefinition, TypeDefinitionRef, TypeExtension, Union, Value, ValueSpan,
+        VariableDefinition,
     },
-    error::GraphqlParseError,
-    lexer::Lexer,
+    error::{GraphqlParseError, ParseLimit},
+    lexer::{Lexer, ParseLimits},
+    source_map::SourceMap,
 };
 
+#[cfg(feature = "rayon")]
+use crate::remap::Remapper;
+
+/// A parsed document along with the interner its names were interned into
+#[derive(Debug)]
+pub struct ParseResult {
+    pub document: Document,
+    pub interner: Rodeo,
+}
+
+impl ParseResult {
+    /// The string an interned name refers to
+    pub fn resolve(&self, name: Spur) -> &str {
+        self.interner.resolve(&name)
+    }
+
+    pub fn get_type_by_name(&self, name: &str) -> Option<TypeDefinitionRef<'_>> {
+        self.document.get_type_by_name(name, &self.interner)
+    }
+}
+
+/// The definitions in a buffer, each parsed only once it is requested. Created
+/// by [`GraphqlParser::definitions`]
+///
+/// Iteration stops after the first error
+pub struct Definitions<'a> {
+    parser: GraphqlParser<'a>,
+    is_done: bool,
+}
+
+impl<'a> Definitions<'a> {
+    /// The interner names in the yielded definitions are interned into
+    pub fn interner(&self) -> &Rodeo {
+        &self.parser.lexer.interner
+    }
+
+    pub fn into_interner(self) -> Rodeo {
+        self.parser.lexer.interner
+    }
+}
+
+impl<'a> Iterator for Definitions<'a> {
+    type Item = Result<Definition, GraphqlParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+
+        let definition = self.parser.next_definition().transpose();
+
+        if !matches!(definition, Some(Ok(..))) {
+            self.is_done = true;
+        }
+
+        definition
+    }
+}
+
+impl<'a> std::iter::FusedIterator for Definitions<'a> {}
+
 pub struct GraphqlParser<'a> {
     lexer: Lexer<'a>,
     document: Document,
+    kind: DocumentKind,
+    /// Whether to record spans even if the `spans` feature is disabled
+    is_recording_spans: bool,
+    /// How many selection sets, lists and input objects we're inside of
+    depth: usize,
+}
+
+/// Which definitions a document may contain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DocumentKind {
+    /// Any definition
+    Mixed,
+    /// Only type system definitions and extensions
+    Schema,
+    /// Only operations and fragments
+    Executable,
 }
 
 impl<'a> GraphqlParser<'a> {
     pub fn parse(buffer: &'a [u8]) -> Result<Document, GraphqlParseError> {
-        let mut parser = Self {
-            lexer: Lexer::new(buffer),
-            document: Document::new(),
-        };
+        Self::from_lexer(Lexer::new(buffer)).parse_document()
+    }
+
+    /// Parse a schema, rejecting any operations or fragments
+    pub fn parse_schema(buffer: &'a [u8]) -> Result<Document, GraphqlParseError> {
+        Self::parse_schema_with_interner(buffer, &mut Rodeo::default())
+    }
+
+    /// Parse an executable document, such as a request sent by a client,
+    /// rejecting any type system definitions or extensions
+    pub fn parse_query(buffer: &'a [u8]) -> Result<Document, GraphqlParseError> {
+        Self::parse_query_with_interner(buffer, &mut Rodeo::default())
+    }
+
+    /// Like [`GraphqlParser::parse_schema`], interning names into `interner`
+    pub fn parse_schema_with_interner(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+    ) -> Result<Document, GraphqlParseError> {
+        Self::parse_kind_with_interner(
+            buffer,
+            interner,
+            DocumentKind::Schema,
+            ParseLimits::default(),
+        )
+    }
+
+    /// Like [`GraphqlParser::parse_query`], interning names into `interner`
+    pub fn parse_query_with_interner(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+    ) -> Result<Document, GraphqlParseError> {
+        Self::parse_kind_with_interner(
+            buffer,
+            interner,
+            DocumentKind::Executable,
+            ParseLimits::default(),
+        )
+    }
+
+    /// Like [`GraphqlParser::parse_query_with_interner`], failing with
+    /// [`GraphqlParseError::LimitExceeded`] as soon as the document goes over
+    /// `limits`, so that adversarial requests can't exhaust memory or the
+    /// stack
+    pub fn parse_query_with_limits(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+        limits: ParseLimits,
+    ) -> Result<Document, GraphqlParseError> {
+        Self::parse_kind_with_interner(buffer, interner, DocumentKind::Executable, limits)
+    }
+
+    /// Like [`GraphqlParser::parse_schema_with_interner`], failing as soon as
+    /// the document goes over `limits`
+    pub fn parse_schema_with_limits(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+        limits: ParseLimits,
+    ) -> Result<Document, GraphqlParseError> {
+        Self::parse_kind_with_interner(buffer, interner, DocumentKind::Schema, limits)
+    }
+
+    /// Like [`GraphqlParser::parse_with_interner`], failing as soon as the
+    /// document goes over `limits`
+    pub fn parse_with_limits(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+        limits: ParseLimits,
+    ) -> Result<Document, GraphqlParseError> {
+        Self::parse_kind_with_interner(buffer, interner, DocumentKind::Mixed, limits)
+    }
+
+    /// Parse a document, keeping the interner so that names in the document
+    /// can be resolved back to strings
+    pub fn parse_resolvable(buffer: &'a [u8]) -> Result<ParseResult, GraphqlParseError> {
+        let mut interner = Rodeo::default();
+
+        let document = Self::parse_with_interner(buffer, &mut interner)?;
+
+        Ok(ParseResult { document, interner })
+    }
+
+    /// Lazily parse the definitions in `buffer` one at a time, without
+    /// collecting them into a [`Document`]
+    pub fn definitions(buffer: &'a [u8]) -> Definitions<'a> {
+        Self::definitions_with_interner(buffer, Rodeo::default())
+    }
+
+    /// Like [`GraphqlParser::definitions`], interning names into `interner`,
+    /// which can be taken back with [`Definitions::into_interner`]
+    pub fn definitions_with_interner(buffer: &'a [u8], interner: Rodeo) -> Definitions<'a> {
+        Self::definitions_with_limits(buffer, interner, ParseLimits::default())
+    }
+
+    /// Like [`GraphqlParser::definitions_with_interner`], yielding an error
+    /// and stopping once the input goes over `limits`
+    pub fn definitions_with_limits(
+        buffer: &'a [u8],
+        interner: Rodeo,
+        limits: ParseLimits,
+    ) -> Definitions<'a> {
+        let mut lexer = Lexer::with_interner(buffer, interner);
+        lexer.set_limits(limits);
+
+        Definitions {
+            parser: Self::from_lexer(lexer),
+            is_done: false,
+        }
+    }
+
+    /// Parse a document, additionally returning every `#` comment in the order
+    /// it appears in the source
+    pub fn parse_with_comments(
+        buffer: &'a [u8],
+    ) -> Result<(Document, Vec<Comment>), GraphqlParseError> {
+        let mut parser = Self::from_lexer(Lexer::with_comments(buffer));
+
+        parser.parse_definitions()?;
+
+        Ok((parser.document, parser.lexer.take_comments()))
+    }
+
+    /// Parse a document without stopping at the first error. After an error,
+    /// parsing resumes at the next definition that starts on a new line, so
+    /// the returned document holds every definition that parsed successfully,
+    /// alongside an error for each one that didn't
+    pub fn parse_with_recovery(buffer: &'a [u8]) -> (Document, Vec<GraphqlParseError>) {
+        Self::parse_with_recovery_and_interner(buffer, &mut Rodeo::default())
+    }
+
+    /// Like [`GraphqlParser::parse_with_recovery`], interning names into
+    /// `interner`
+    pub fn parse_with_recovery_and_interner(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+    ) -> (Document, Vec<GraphqlParseError>) {
+        Self::parse_with_recovery_and_limits(buffer, interner, ParseLimits::default())
+    }
+
+    /// Like [`GraphqlParser::parse_with_recovery_and_interner`], except that
+    /// going over `limits` isn't recovered from: parsing stops at that error,
+    /// keeping the definitions before it
+    pub fn parse_with_recovery_and_limits(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+        limits: ParseLimits,
+    ) -> (Document, Vec<GraphqlParseError>) {
+        let mut lexer = Lexer::with_interner(buffer, std::mem::take(interner));
+        lexer.set_limits(limits);
+
+        let mut parser = Self::from_lexer(lexer);
+        let mut errors = Vec::new();
 
         loop {
-            if !parser.next_definition()? {
-                break;
+            let start = parser.lexer.next_token_start();
+
+            match parser.next_definition() {
+                Ok(Some(definition)) => parser.document.add_definition(definition),
+                Ok(None) => break,
+                Err(err) => {
+                    errors.push(err);
+                    parser.skip_to_next_definition(start);
+                }
             }
         }
 
-        Ok(parser.document)
+        *interner = parser.lexer.interner;
+
+        (parser.document, errors)
+    }
+
+    /// Parse a document, interning names into `interner` rather than a fresh
+    /// interner that is thrown away after parsing. This is needed to resolve
+    /// names in the returned document, and lets several documents share
+    /// names
+    pub fn parse_with_interner(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+    ) -> Result<Document, GraphqlParseError> {
+        Self::parse_kind_with_interner(
+            buffer,
+            interner,
+            DocumentKind::Mixed,
+            ParseLimits::default(),
+        )
+    }
+
+    /// Parse several independent documents, such as the files of a project,
+    /// interning names into `interner`. With the `rayon` feature, the
+    /// documents are parsed in parallel, each into its own interner, which
+    /// are then merged into `interner` in order
+    pub fn parse_many(
+        buffers: &[&[u8]],
+        interner: &mut Rodeo,
+    ) -> Vec<Result<Document, GraphqlParseError>> {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            let parsed = buffers
+                .par_iter()
+                .map(|buffer| {
+                    let mut local = Rodeo::default();
+                    let result = GraphqlParser::parse_with_interner(buffer, &mut local);
+                    (result, local)
+                })
+                .collect::<Vec<_>>();
+
+            parsed
+                .into_iter()
+                .map(|(result, local)| {
+                    let remapper = Remapper::new(&local, interner);
+
+                    result
+                        .map(|document| remapper.document(document))
+                        .map_err(|error| remapper.error(error))
+                })
+                .collect()
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            buffers
+                .iter()
+                .map(|buffer| GraphqlParser::parse_with_interner(buffer, interner))
+                .collect()
+        }
+    }
+
+    /// Like [`GraphqlParser::parse_with_interner`], also returning the span
+    /// of each node in a [`SourceMap`]. Spans are recorded in the document
+    /// too, even if the `spans` feature is disabled
+    pub fn parse_with_source_map(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+    ) -> Result<(Document, SourceMap), GraphqlParseError> {
+        let lexer = Lexer::with_interner(buffer, std::mem::take(interner));
+
+        let mut parser = Self::from_lexer(lexer);
+        parser.is_recording_spans = true;
+
+        let result = parser.parse_definitions();
+
+        *interner = parser.lexer.interner;
+
+        result?;
+
+        let source_map = SourceMap::new(&parser.document, interner);
+
+        Ok((parser.document, source_map))
+    }
+
+    fn parse_kind_with_interner(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+        kind: DocumentKind,
+        limits: ParseLimits,
+    ) -> Result<Document, GraphqlParseError> {
+        let mut lexer = Lexer::with_interner(buffer, std::mem::take(interner));
+        lexer.set_limits(limits);
+
+        let mut parser = Self::from_lexer(lexer);
+        parser.kind = kind;
+
+        let result = parser.parse_definitions();
+
+        *interner = parser.lexer.interner;
+
+        result.map(|()| parser.document)
+    }
+
+    /// Parse a single value literal, such as the `defaultValue` strings found
+    /// in introspection results
+    pub fn parse_value_with_interner(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+    ) -> Result<Value, GraphqlParseError> {
+        let lexer = Lexer::with_interner(buffer, std::mem::take(interner));
+
+        let mut parser = Self::from_lexer(lexer);
+
+        let result = parser
+            .parse_value()
+            .and_then(|value| match parser.lexer.next_token()? {
+                None => Ok(value),
+                token => Err(parser.unexpected(token, "end of input")),
+            });
+
+        *interner = parser.lexer.interner;
+
+        result
+    }
+
+    /// Parse a single type reference, such as `[ID!]!`
+    pub fn parse_type_with_interner(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+    ) -> Result<Type, GraphqlParseError> {
+        let lexer = Lexer::with_interner(buffer, std::mem::take(interner));
+
+        let mut parser = Self::from_lexer(lexer);
+
+        let result = parser
+            .parse_type()
+            .and_then(|ty| match parser.lexer.next_token()? {
+                None => Ok(ty),
+                token => Err(parser.unexpected(token, "end of input")),
+            });
+
+        *interner = parser.lexer.interner;
+
+        result
+    }
+
+    /// Parse a selection set without its enclosing braces, such as the
+    /// `fields` argument of a federation `@key`
+    pub fn parse_field_set_with_interner(
+        buffer: &'a [u8],
+        interner: &mut Rodeo,
+    ) -> Result<Vec<Selection>, GraphqlParseError> {
+        let lexer = Lexer::with_interner(buffer, std::mem::take(interner));
+
+        let mut parser = Self::from_lexer(lexer);
+
+        let result = parser.parse_field_set();
+
+        *interner = parser.lexer.interner;
+
+        result
+    }
+
+    fn from_lexer(lexer: Lexer<'a>) -> Self {
+        Self {
+            lexer,
+            document: Document::new(),
+            kind: DocumentKind::Mixed,
+            is_recording_spans: cfg!(feature = "spans"),
+            depth: 0,
+        }
+    }
+
+    fn parse_document(mut self) -> Result<Document, GraphqlParseError> {
+        self.parse_definitions()?;
+
+        Ok(self.document)
+    }
+
+    fn parse_definitions(&mut self) -> Result<(), GraphqlParseError> {
+        while let Some(definition) = self.next_definition()? {
+            self.document.add_definition(definition);
+        }
+
+        Ok(())
     }
 
     #[track_caller]
@@ -40,7 +462,22 @@ impl<'a> GraphqlParser<'a> {
             Some(Token::Keyword(keyword)) => {
                 Ok(self.lexer.interner.get_or_intern(keyword.as_str()))
             }
-            token => todo!("{:?}", token),
+            token => Err(self.unexpected(token, "a name")),
+        }
+    }
+
+    /// An error for when `found` isn't what we wanted. Must be called right
+    /// after `found` was lexed so the span points at it
+    fn unexpected(&self, found: Option<Token>, expected: &'static str) -> GraphqlParseError {
+        let span = self.lexer.last_span();
+
+        match found {
+            Some(found) => GraphqlParseError::UnexpectedToken {
+                found,
+                expected,
+                span,
+            },
+            None => GraphqlParseError::UnexpectedEof { expected, span },
         }
     }
 
@@ -51,50 +488,142 @@ impl<'a> GraphqlParser<'a> {
             return Ok(());
         }
 
-        Err(GraphqlParseError::ExpectedToken { token, found: next })
+        Err(GraphqlParseError::ExpectedToken {
+            token,
+            found: next,
+            span: self.lexer.last_span(),
+        })
     }
 
-    #[track_caller]
     fn parse_value(&mut self) -> Result<Value, GraphqlParseError> {
-        Ok(match self.lexer.next_token()? {
+        Ok(self.parse_spanned_value()?.0)
+    }
+
+    /// Parse a value, along with its span and those of its items or fields
+    fn parse_spanned_value(&mut self) -> Result<(Value, ValueSpan), GraphqlParseError> {
+        let start = self.lexer.next_token_start();
+        let mut value_span = ValueSpan::default();
+
+        let value = match self.lexer.next_token()? {
             Some(Token::String(string)) => Value::String(string),
             Some(Token::Keyword(Keyword::True)) => Value::True,
             Some(Token::Keyword(Keyword::False)) => Value::False,
             Some(Token::Keyword(Keyword::Null)) => Value::Null,
-            Some(Token::Dollar) => Value::Variable(self.expect_name()?),
+            Some(Token::Variable(name)) => Value::Variable(name),
             Some(Token::Name(name)) => Value::EnumVariant(name),
-            Some(Token::OpenSquareBrace) => Value::List(self.parse_list_value()?),
-            Some(Token::OpenCurlyBrace) => Value::Object(self.parse_object_value()?),
-            token => todo!("{:?}", token),
-        })
+            // enum values may be any name other than `true`, `false` or `null`
+            Some(Token::Keyword(keyword)) => {
+                Value::EnumVariant(self.lexer.interner.get_or_intern(keyword.as_str()))
+            }
+            Some(Token::OpenSquareBrace) => {
+                Value::List(self.nested(|parser| parser.parse_list_value(&mut value_span))?)
+            }
+            Some(Token::OpenCurlyBrace) => {
+                Value::Object(self.nested(|parser| parser.parse_object_value(&mut value_span))?)
+            }
+            Some(Token::IntValue(text)) => {
+                Value::Int(self.lexer.interner.resolve(&text).parse().map_err(|_| {
+                    GraphqlParseError::InvalidNumber {
+                        span: self.lexer.last_span(),
+                    }
+                })?)
+            }
+            Some(Token::FloatValue(text)) => Value::Float(
+                self.lexer
+                    .interner
+                    .resolve(&text)
+                    .parse()
+                    .ok()
+                    // floats too large for an `f64` parse as infinity
+                    .filter(|float: &f64| float.is_finite())
+                    .ok_or_else(|| GraphqlParseError::InvalidNumber {
+                        span: self.lexer.last_span(),
+                    })?,
+            ),
+            token => return Err(self.unexpected(token, "a value")),
+        };
+
+        value_span.span = self.span_from(start);
+
+        Ok((value, value_span))
     }
 
-    fn parse_list_value(&mut self) -> Result<Vec<Value>, GraphqlParseError> {
+    /// The items of a list, recording their spans in `list_span` if the
+    /// `spans` feature is enabled
+    fn parse_list_value(
+        &mut self,
+        list_span: &mut ValueSpan,
+    ) -> Result<Vec<Value>, GraphqlParseError> {
         let mut values = Vec::new();
 
         while !self.lexer.consume_byte_if_eq(b']') {
-            values.push(self.parse_value()?);
+            let (value, value_span) = self.parse_spanned_value()?;
+
+            values.push(value);
+
+            if cfg!(feature = "spans") {
+                list_span.items.push(value_span);
+            }
         }
 
         Ok(values)
     }
 
-    fn parse_object_value(&mut self) -> Result<HashMap<Spur, Value>, GraphqlParseError> {
+    /// The fields of an input object, recording their spans in `object_span`
+    /// if the `spans` feature is enabled
+    fn parse_object_value(
+        &mut self,
+        object_span: &mut ValueSpan,
+    ) -> Result<HashMap<Spur, Value>, GraphqlParseError> {
         let mut values = HashMap::new();
 
         while !self.lexer.consume_byte_if_eq(b'}') {
+            let start = self.lexer.next_token_start();
             let key = self.expect_name()?;
 
             self.lexer.expect_byte(b':')?;
 
-            let value = self.parse_value()?;
+            let (value, value_span) = self.parse_spanned_value()?;
 
             values.insert(key, value);
+
+            if cfg!(feature = "spans") {
+                object_span.fields.insert(
+                    key,
+                    ObjectFieldSpan {
+                        span: self.span_from(start),
+                        value: value_span,
+                    },
+                );
+            }
         }
 
         Ok(values)
     }
 
+    /// Parse a selection set, list or input object with `parse`, failing if
+    /// that nests them deeper than the limit
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, GraphqlParseError>,
+    ) -> Result<T, GraphqlParseError> {
+        if let Some(max) = self.lexer.limits.max_depth {
+            if self.depth >= max {
+                let span = self.lexer.consumed_span();
+
+                return Err(self.lexer.limit_exceeded(ParseLimit::Depth, max, span));
+            }
+        }
+
+        self.depth += 1;
+
+        let result = parse(self);
+
+        self.depth -= 1;
+
+        result
+    }
+
     fn consume_token_if_eq(&mut self, token: Token) -> Result<bool, GraphqlParseError> {
         let next = self.lexer.peek_token()?;
 
@@ -107,20 +636,34 @@ impl<'a> GraphqlParser<'a> {
         })
     }
 
-    pub(crate) fn next_definition(&mut self) -> Result<bool, GraphqlParseError> {
+    /// The span from `start` to the end of the last consumed token, or an
+    /// empty span if spans aren't being recorded
+    fn span_from(&self, start: usize) -> Span {
+        if self.is_recording_spans {
+            Span::new(start, self.lexer.consumed_span().end)
+        } else {
+            Span::default()
+        }
+    }
+
+    /// Parse the next definition, or `None` at the end of the input
+    fn next_definition(&mut self) -> Result<Option<Definition>, GraphqlParseError> {
+        let start = self.lexer.next_token_start();
+
         let description = self.parse_optional_description()?;
+        let description_span = self.lexer.last_span();
 
-        match self.lexer.next_token()? {
-            Some(Token::Keyword(Keyword::Enum)) => {
-                let enum_def = self.parse_enum(description)?;
+        let token = self.lexer.next_token()?;
 
-                self.document.enums.insert(enum_def.name, enum_def);
-            }
-            Some(Token::Keyword(Keyword::Type)) => {
-                let obj_def = self.parse_object_type_definition(description)?;
+        self.check_definition_allowed(&token)?;
 
-                self.document.output_objects.insert(obj_def.name, obj_def);
+        Ok(Some(match token {
+            Some(Token::Keyword(Keyword::Enum)) => {
+                Definition::TypeDecl(TypeDefinition::Enum(self.parse_enum(description, start)?))
             }
+            Some(Token::Keyword(Keyword::Type)) => Definition::TypeDecl(TypeDefinition::Object(
+                self.parse_object_type_definition(description, start)?,
+            )),
             Some(Token::Keyword(
                 kind_keyword @ (Keyword::Query | Keyword::Mutation | Keyword::Subscription),
             )) => {
@@ -131,75 +674,324 @@ impl<'a> GraphqlParser<'a> {
                     _ => unreachable!(),
                 };
 
-                let operation_def = self.parse_operation(kind)?;
-
-                self.document
-                    .operations
-                    .insert((operation_def.name, operation_def.kind), operation_def);
+                Definition::Operation(self.parse_operation(kind, start)?)
+            }
+            // query shorthand, `{ field }`
+            Some(Token::OpenCurlyBrace) => {
+                let selection_set = self.parse_selection_set()?;
+
+                Definition::Operation(Operation {
+                    kind: OperationKind::Query,
+                    name: None,
+                    variable_definitions: Vec::new(),
+                    directives: Vec::new(),
+                    selection_set,
+                    span: self.span_from(start),
+                })
             }
             Some(Token::Keyword(Keyword::Fragment)) => {
-                let fragment_def = self.parse_fragment_definition()?;
-
-                self.document
-                    .fragments
-                    .insert(fragment_def.name, fragment_def);
+                Definition::Fragment(self.parse_fragment_definition(start)?)
             }
             Some(Token::Keyword(Keyword::Union)) => {
-                let union_def = self.parse_union(description)?;
-
-                self.document.unions.insert(union_def.name, union_def);
+                Definition::TypeDecl(TypeDefinition::Union(self.parse_union(description, start)?))
             }
             Some(Token::Keyword(Keyword::Input)) => {
-                let input_def = self.parse_input_object_definition(description)?;
+                Definition::TypeDecl(TypeDefinition::InputObject(
+                    self.parse_input_object_definition(description, start)?,
+                ))
+            }
+            Some(Token::Keyword(Keyword::Scalar)) => Definition::TypeDecl(TypeDefinition::Scalar(
+                self.parse_scalar(description, start)?,
+            )),
+            Some(Token::Keyword(Keyword::Interface)) => Definition::TypeDecl(
+                TypeDefinition::Interface(self.parse_interface(description, start)?),
+            ),
+            Some(Token::Keyword(Keyword::Schema)) => {
+                Definition::Schema(self.parse_schema_definition(description, start)?)
+            }
+            Some(Token::Keyword(Keyword::Directive)) => {
+                Definition::Directive(self.parse_directive_definition(description, start)?)
+            }
+            Some(Token::Keyword(Keyword::Extend)) => {
+                if description.is_some() {
+                    return Err(GraphqlParseError::UnexpectedDescription {
+                        span: description_span,
+                    });
+                }
+
+                self.parse_extension(start)?
+            }
+            None => return Ok(None),
+            token => return Err(self.unexpected(token, "a definition")),
+        }))
+    }
+
+    /// Move the lexer to the next token after the definition starting at
+    /// `start` that looks like the start of another definition: a definition
+    /// keyword, description or query shorthand at the start of a line.
+    /// Rescanning from `start` rather than from the error means a definition
+    /// that was swallowed by an unclosed one before it is still found
+    fn skip_to_next_definition(&mut self, start: usize) {
+        self.lexer.seek(start);
 
-                self.document
-                    .input_objects
-                    .insert(input_def.name, input_def);
+        // always skip the first token, so that we make progress
+        let mut is_first = true;
+
+        loop {
+            let token_start = self.lexer.next_token_start();
+
+            let token = match self.lexer.next_token() {
+                Ok(Some(token)) => token,
+                Ok(None) => return,
+                // invalid tokens are always consumed, so just keep going
+                Err(..) => continue,
+            };
+
+            let is_boundary = matches!(
+                token,
+                Token::String(..)
+                    | Token::OpenCurlyBrace
+                    | Token::Keyword(
+                        Keyword::Query
+                            | Keyword::Mutation
+                            | Keyword::Subscription
+                            | Keyword::Fragment
+                            | Keyword::Enum
+                            | Keyword::Type
+                            | Keyword::Union
+                            | Keyword::Input
+                            | Keyword::Scalar
+                            | Keyword::Interface
+                            | Keyword::Schema
+                            | Keyword::Directive
+                            | Keyword::Extend
+                    )
+            );
+
+            if !is_first && is_boundary && self.lexer.starts_line(token_start) {
+                self.lexer.seek(token_start);
+                return;
             }
-            Some(Token::Keyword(Keyword::Scalar)) => {
-                let scalar_def = self.parse_scalar(description)?;
 
-                self.document.scalars.insert(scalar_def.name, scalar_def);
+            is_first = false;
+        }
+    }
+
+    /// Reject definitions that can't appear in the kind of document being
+    /// parsed. Must be called right after `token` was lexed so the span points
+    /// at it
+    fn check_definition_allowed(&self, token: &Option<Token>) -> Result<(), GraphqlParseError> {
+        let is_executable = match token {
+            Some(Token::Keyword(
+                Keyword::Query | Keyword::Mutation | Keyword::Subscription | Keyword::Fragment,
+            ))
+            | Some(Token::OpenCurlyBrace) => true,
+            Some(Token::Keyword(
+                Keyword::Enum
+                | Keyword::Type
+                | Keyword::Union
+                | Keyword::Input
+                | Keyword::Scalar
+                | Keyword::Interface
+                | Keyword::Schema
+                | Keyword::Directive
+                | Keyword::Extend,
+            )) => false,
+            // not the start of a definition, which is reported by the caller
+            _ => return Ok(()),
+        };
+
+        let span = self.lexer.last_span();
+
+        match (self.kind, is_executable) {
+            (DocumentKind::Schema, true) => {
+                Err(GraphqlParseError::ExecutableDefinitionInSchema { span })
+            }
+            (DocumentKind::Executable, false) => {
+                Err(GraphqlParseError::TypeSystemDefinitionInExecutable { span })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_directive_definition(
+        &mut self,
+        description: Option<Spur>,
+        start: usize,
+    ) -> Result<DirectiveDefinition, GraphqlParseError> {
+        self.lexer.expect_byte(b'@')?;
+
+        let name = self.expect_name()?;
+
+        let arguments = self.parse_optional_field_arguments()?;
+
+        let repeatable = self.consume_token_if_eq(Token::Keyword(Keyword::Repeatable))?;
+
+        self.expect_token(Token::Keyword(Keyword::On))?;
+
+        let mut locations = Vec::new();
+
+        // a leading `|` is allowed before the first location
+        self.lexer.consume_byte_if_eq(b'|');
+
+        locations.push(self.parse_directive_location()?);
+
+        while self.lexer.consume_byte_if_eq(b'|') {
+            locations.push(self.parse_directive_location()?);
+        }
+
+        Ok(DirectiveDefinition {
+            description,
+            name,
+            arguments,
+            repeatable,
+            locations,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_directive_location(&mut self) -> Result<DirectiveLocation, GraphqlParseError> {
+        let name = self.expect_name()?;
+        let name = self.lexer.interner.resolve(&name);
+
+        DirectiveLocation::from_name(name).ok_or_else(|| {
+            GraphqlParseError::UnknownDirectiveLocation {
+                name: name.to_owned(),
+                span: self.lexer.last_span(),
+            }
+        })
+    }
+
+    fn parse_extension(&mut self, start: usize) -> Result<Definition, GraphqlParseError> {
+        let extension = match self.lexer.next_token()? {
+            Some(Token::Keyword(Keyword::Schema)) => {
+                return Ok(Definition::SchemaExtension(
+                    self.parse_schema_extension(start)?,
+                ));
+            }
+            Some(Token::Keyword(Keyword::Scalar)) => {
+                TypeExtension::Scalar(self.parse_scalar(None, start)?)
+            }
+            Some(Token::Keyword(Keyword::Type)) => {
+                TypeExtension::Object(self.parse_object_type_definition(None, start)?)
             }
             Some(Token::Keyword(Keyword::Interface)) => {
-                let interface_def = self.parse_interface(description)?;
+                TypeExtension::Interface(self.parse_interface(None, start)?)
+            }
+            Some(Token::Keyword(Keyword::Union)) => {
+                TypeExtension::Union(self.parse_union(None, start)?)
+            }
+            Some(Token::Keyword(Keyword::Enum)) => {
+                TypeExtension::Enum(self.parse_enum(None, start)?)
+            }
+            Some(Token::Keyword(Keyword::Input)) => {
+                TypeExtension::InputObject(self.parse_input_object_definition(None, start)?)
+            }
+            token => return Err(self.unexpected(token, "a type or schema extension")),
+        };
+
+        Ok(Definition::TypeExtension(extension))
+    }
+
+    fn parse_schema_definition(
+        &mut self,
+        description: Option<Spur>,
+        start: usize,
+    ) -> Result<SchemaDefinition, GraphqlParseError> {
+        let directives = self.parse_optional_directives()?;
+
+        self.lexer.expect_byte(b'{')?;
+
+        let mut operation_types = Vec::new();
+
+        while !self.lexer.consume_byte_if_eq(b'}') {
+            operation_types.push(self.parse_root_operation_type_definition()?);
+        }
+
+        Ok(SchemaDefinition {
+            description,
+            directives,
+            operation_types,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_schema_extension(
+        &mut self,
+        start: usize,
+    ) -> Result<SchemaExtension, GraphqlParseError> {
+        let directives = self.parse_optional_directives()?;
+
+        let mut operation_types = Vec::new();
 
-                self.document
-                    .interfaces
-                    .insert(interface_def.name, interface_def);
+        if self.lexer.consume_byte_if_eq(b'{') {
+            while !self.lexer.consume_byte_if_eq(b'}') {
+                operation_types.push(self.parse_root_operation_type_definition()?);
             }
-            None => return Ok(false),
-            Some(token) => todo!("{:?}", token),
+        }
+
+        Ok(SchemaExtension {
+            directives,
+            operation_types,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_root_operation_type_definition(
+        &mut self,
+    ) -> Result<RootOperationTypeDefinition, GraphqlParseError> {
+        let kind = match self.lexer.next_token()? {
+            Some(Token::Keyword(Keyword::Query)) => OperationKind::Query,
+            Some(Token::Keyword(Keyword::Mutation)) => OperationKind::Mutation,
+            Some(Token::Keyword(Keyword::Subscription)) => OperationKind::Subscription,
+            token => return Err(self.unexpected(token, "an operation type")),
         };
 
-        Ok(true)
+        let start = self.lexer.consumed_span().start;
+
+        self.expect_token(Token::Colon)?;
+
+        let ty = NamedType(self.expect_name()?);
+
+        Ok(RootOperationTypeDefinition {
+            kind,
+            ty,
+            span: self.span_from(start),
+        })
     }
 
     fn parse_interface(
         &mut self,
         description: Option<Spur>,
+        start: usize,
     ) -> Result<Interface, GraphqlParseError> {
         let name = self.expect_name()?;
 
-        let directives = self.parse_optional_directives()?;
-
-        let mut fields = Vec::new();
+        let implements = if self.consume_token_if_eq(Token::Keyword(Keyword::Implements))? {
+            self.parse_implements()?
+        } else {
+            Vec::new()
+        };
 
-        self.lexer.expect_byte(b'{')?;
+        let directives = self.parse_optional_directives()?;
 
-        while !self.lexer.consume_byte_if_eq(b'}') {
-            fields.push(self.parse_field_definition()?);
-        }
+        let fields = self.parse_optional_fields_definition()?.unwrap_or_default();
 
         Ok(Interface {
+            implements,
             description,
             name,
             directives,
             fields,
+            span: self.span_from(start),
         })
     }
 
-    fn parse_scalar(&mut self, description: Option<Spur>) -> Result<Scalar, GraphqlParseError> {
+    fn parse_scalar(
+        &mut self,
+        description: Option<Spur>,
+        start: usize,
+    ) -> Result<Scalar, GraphqlParseError> {
         let name = self.expect_name()?;
 
         let directives = self.parse_optional_directives()?;
@@ -208,20 +1000,25 @@ impl<'a> GraphqlParser<'a> {
             description,
             name,
             directives,
+            span: self.span_from(start),
         })
     }
 
-    fn parse_enum(&mut self, description: Option<Spur>) -> Result<Enum, GraphqlParseError> {
+    fn parse_enum(
+        &mut self,
+        description: Option<Spur>,
+        start: usize,
+    ) -> Result<Enum, GraphqlParseError> {
         let name = self.expect_name()?;
 
         let directives = self.parse_optional_directives()?;
 
-        self.lexer.expect_byte(b'{')?;
-
         let mut variants = Vec::new();
 
-        while !self.lexer.consume_byte_if_eq(b'}') {
-            variants.push(self.parse_enum_variant()?);
+        if self.lexer.consume_byte_if_eq(b'{') {
+            while !self.lexer.consume_byte_if_eq(b'}') {
+                variants.push(self.parse_enum_variant()?);
+            }
         }
 
         Ok(Enum {
@@ -229,10 +1026,13 @@ impl<'a> GraphqlParser<'a> {
             name,
             directives,
             variants,
+            span: self.span_from(start),
         })
     }
 
     fn parse_enum_variant(&mut self) -> Result<EnumVariant, GraphqlParseError> {
+        let start = self.lexer.next_token_start();
+
         let description = self.parse_optional_description()?;
 
         let name = self.expect_name()?;
@@ -243,46 +1043,51 @@ impl<'a> GraphqlParser<'a> {
             description,
             name,
             directives,
+            span: self.span_from(start),
         })
     }
 
     fn parse_input_object_definition(
         &mut self,
         description: Option<Spur>,
+        start: usize,
     ) -> Result<InputObject, GraphqlParseError> {
         let name = self.expect_name()?;
 
         let directives = self.parse_optional_directives()?;
 
-        let mut fields = Vec::new();
+        let fields = if self.lexer.consume_byte_if_eq(b'{') {
+            let mut fields = Vec::new();
 
-        self.lexer.expect_byte(b'{')?;
+            while !self.lexer.consume_byte_if_eq(b'}') {
+                fields.push(self.parse_input_field_definition()?);
+            }
 
-        while !self.lexer.consume_byte_if_eq(b'}') {
-            fields.push(self.parse_input_field_definition()?);
-        }
+            Some(fields)
+        } else {
+            None
+        };
 
         Ok(InputObject {
             description,
             name,
             directives,
-            fields: Some(fields),
+            fields,
+            span: self.span_from(start),
         })
     }
 
     fn parse_input_field_definition(&mut self) -> Result<InputObjectField, GraphqlParseError> {
+        let start = self.lexer.next_token_start();
+
         let description = self.parse_optional_description()?;
         let name = self.expect_name()?;
 
         self.lexer.expect_byte(b':')?;
 
-        let ty = self.parse_type()?;
+        let (ty, ty_span) = self.parse_spanned_type()?;
 
-        let default = if self.lexer.consume_byte_if_eq(b'=') {
-            Some(self.parse_value()?)
-        } else {
-            None
-        };
+        let (default, default_span) = self.parse_optional_default_value()?;
 
         let directives = self.parse_optional_directives()?;
 
@@ -290,11 +1095,27 @@ impl<'a> GraphqlParser<'a> {
             description,
             name,
             ty,
+            ty_span,
             default,
+            default_span,
             directives,
+            span: self.span_from(start),
         })
     }
 
+    /// A default value following an `=`, if there is one
+    fn parse_optional_default_value(
+        &mut self,
+    ) -> Result<(Option<Value>, ValueSpan), GraphqlParseError> {
+        if !self.lexer.consume_byte_if_eq(b'=') {
+            return Ok((None, ValueSpan::default()));
+        }
+
+        let (value, value_span) = self.parse_spanned_value()?;
+
+        Ok((Some(value), value_span))
+    }
+
     fn parse_optional_description(&mut self) -> Result<Option<Spur>, GraphqlParseError> {
         match self.lexer.peek_token()? {
             Some(Token::String(string)) => {
@@ -310,6 +1131,8 @@ impl<'a> GraphqlParser<'a> {
         let mut directives = Vec::new();
 
         while self.lexer.consume_byte_if_eq(b'@') {
+            let start = self.lexer.consumed_span().start;
+
             let name = self.expect_name()?;
             let arguments = if self.lexer.consume_byte_if_eq(b'(') {
                 Some(self.parse_arguments()?)
@@ -317,7 +1140,11 @@ impl<'a> GraphqlParser<'a> {
                 None
             };
 
-            directives.push(Directive { name, arguments })
+            directives.push(Directive {
+                name,
+                arguments,
+                span: self.span_from(start),
+            })
         }
 
         Ok(directives)
@@ -327,13 +1154,20 @@ impl<'a> GraphqlParser<'a> {
         let mut arguments = Vec::new();
 
         while !self.lexer.consume_byte_if_eq(b')') {
+            let start = self.lexer.next_token_start();
+
             let name = self.expect_name()?;
 
             self.lexer.expect_byte(b':')?;
 
-            let value = self.parse_value()?;
+            let (value, value_span) = self.parse_spanned_value()?;
 
-            arguments.push(Argument { name, value })
+            arguments.push(Argument {
+                name,
+                value,
+                value_span,
+                span: self.span_from(start),
+            })
         }
 
         Ok(arguments)
@@ -342,6 +1176,7 @@ impl<'a> GraphqlParser<'a> {
     fn parse_object_type_definition(
         &mut self,
         description: Option<Spur>,
+        start: usize,
     ) -> Result<ObjectType, GraphqlParseError> {
         let name = self.expect_name()?;
 
@@ -353,23 +1188,34 @@ impl<'a> GraphqlParser<'a> {
 
         let directives = self.parse_optional_directives()?;
 
-        let mut fields = Vec::new();
-
-        self.lexer.expect_byte(b'{')?;
-
-        while !self.lexer.consume_byte_if_eq(b'}') {
-            fields.push(self.parse_field_definition()?);
-        }
+        let fields = self.parse_optional_fields_definition()?;
 
         Ok(ObjectType {
             implements,
             description,
             name,
             directives,
-            fields: Some(fields),
+            fields,
+            span: self.span_from(start),
         })
     }
 
+    fn parse_optional_fields_definition(
+        &mut self,
+    ) -> Result<Option<Vec<FieldDefinition>>, GraphqlParseError> {
+        if !self.lexer.consume_byte_if_eq(b'{') {
+            return Ok(None);
+        }
+
+        let mut fields = Vec::new();
+
+        while !self.lexer.consume_byte_if_eq(b'}') {
+            fields.push(self.parse_field_definition()?);
+        }
+
+        Ok(Some(fields))
+    }
+
     fn parse_implements(&mut self) -> Result<Vec<NamedType>, GraphqlParseError> {
         let mut types = Vec::new();
 
@@ -382,12 +1228,14 @@ impl<'a> GraphqlParser<'a> {
         Ok(types)
     }
 
-    fn parse_operation(&mut self, kind: OperationKind) -> Result<Operation, GraphqlParseError> {
-        let name = if let Some(Token::Name(name)) = self.lexer.peek_token()? {
-            self.lexer.next_token()?;
-            Some(name)
-        } else {
-            None
+    fn parse_operation(
+        &mut self,
+        kind: OperationKind,
+        start: usize,
+    ) -> Result<Operation, GraphqlParseError> {
+        let name = match self.lexer.peek_token()? {
+            Some(Token::Name(..) | Token::Keyword(..)) => Some(self.expect_name()?),
+            _ => None,
         };
 
         let variable_definitions = if self.lexer.consume_byte_if_eq(b'(') {
@@ -408,6 +1256,7 @@ impl<'a> GraphqlParser<'a> {
             variable_definitions,
             directives,
             selection_set,
+            span: self.span_from(start),
         })
     }
 
@@ -415,28 +1264,33 @@ impl<'a> GraphqlParser<'a> {
         let mut variable_definitions = Vec::new();
 
         while !self.lexer.consume_byte_if_eq(b')') {
-            // todo: variables should be their own token. avoid input like `$ a`
-            self.expect_token(Token::Dollar)?;
+            let name = match self.lexer.next_token()? {
+                Some(Token::Variable(name)) => name,
+                token => return Err(self.unexpected(token, "a variable")),
+            };
 
-            let name = self.expect_name()?;
+            let start = self.lexer.consumed_span().start;
 
             self.expect_token(Token::Colon)?;
 
-            let ty = self.parse_type()?;
+            let (ty, ty_span) = self.parse_spanned_type()?;
 
-            let default = if self.lexer.consume_byte_if_eq(b'=') {
-                Some(self.parse_value()?)
-            } else {
-                None
-            };
+            let (default, default_span) = self.parse_optional_default_value()?;
 
-            variable_definitions.push(VariableDefinition { name, ty, default })
+            variable_definitions.push(VariableDefinition {
+                name,
+                ty,
+                ty_span,
+                default,
+                default_span,
+                span: self.span_from(start),
+            })
         }
 
         Ok(variable_definitions)
     }
 
-    fn parse_fragment_definition(&mut self) -> Result<Fragment, GraphqlParseError> {
+    fn parse_fragment_definition(&mut self, start: usize) -> Result<Fragment, GraphqlParseError> {
         let name = self.expect_name()?;
 
         self.expect_token(Token::Keyword(Keyword::On))?;
@@ -454,15 +1308,37 @@ impl<'a> GraphqlParser<'a> {
             on,
             directives,
             selection_set,
+            span: self.span_from(start),
         })
     }
 
     fn parse_selection_set(&mut self) -> Result<Vec<Selection>, GraphqlParseError> {
+        self.nested(|parser| {
+            let mut selection_set = Vec::new();
+
+            while !parser.lexer.consume_byte_if_eq(b'}') {
+                if parser.consume_token_if_eq(Token::DotDotDot)? {
+                    let start = parser.lexer.consumed_span().start;
+
+                    selection_set.push(parser.parse_inline_or_spread_fragment(start)?);
+                    continue;
+                }
+
+                selection_set.push(parser.parse_fragment_field()?);
+            }
+
+            Ok(selection_set)
+        })
+    }
+
+    fn parse_field_set(&mut self) -> Result<Vec<Selection>, GraphqlParseError> {
         let mut selection_set = Vec::new();
 
-        while !self.lexer.consume_byte_if_eq(b'}') {
+        while self.lexer.peek_token()?.is_some() {
             if self.consume_token_if_eq(Token::DotDotDot)? {
-                selection_set.push(self.parse_inline_or_spread_fragment()?);
+                let start = self.lexer.consumed_span().start;
+
+                selection_set.push(self.parse_inline_or_spread_fragment(start)?);
                 continue;
             }
 
@@ -472,19 +1348,37 @@ impl<'a> GraphqlParser<'a> {
         Ok(selection_set)
     }
 
-    fn parse_inline_or_spread_fragment(&mut self) -> Result<Selection, GraphqlParseError> {
+    fn parse_inline_or_spread_fragment(
+        &mut self,
+        start: usize,
+    ) -> Result<Selection, GraphqlParseError> {
         if self.consume_token_if_eq(Token::Keyword(Keyword::On))? {
-            return self.parse_inline_fragment();
+            let on = self.expect_name()?;
+            return self.parse_inline_fragment(Some(on), start);
+        }
+
+        if matches!(
+            self.lexer.peek_token()?,
+            Some(Token::AtSign | Token::OpenCurlyBrace)
+        ) {
+            return self.parse_inline_fragment(None, start);
         }
 
         let name = self.expect_name()?;
         let directives = self.parse_optional_directives()?;
 
-        Ok(Selection::FragmentSpread { name, directives })
+        Ok(Selection::FragmentSpread {
+            name,
+            directives,
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_inline_fragment(&mut self) -> Result<Selection, GraphqlParseError> {
-        let on = self.expect_name()?;
+    fn parse_inline_fragment(
+        &mut self,
+        on: Option<Spur>,
+        start: usize,
+    ) -> Result<Selection, GraphqlParseError> {
         let directives = self.parse_optional_directives()?;
 
         self.expect_token(Token::OpenCurlyBrace)?;
@@ -495,10 +1389,13 @@ impl<'a> GraphqlParser<'a> {
             on,
             directives,
             selection_set,
+            span: self.span_from(start),
         })
     }
 
     fn parse_fragment_field(&mut self) -> Result<Selection, GraphqlParseError> {
+        let start = self.lexer.next_token_start();
+
         let alias_or_name = self.expect_name()?;
 
         let (alias, name) = if self.lexer.consume_byte_if_eq(b':') {
@@ -527,21 +1424,29 @@ impl<'a> GraphqlParser<'a> {
             arguments,
             directives,
             selection_set,
+            span: self.span_from(start),
         })
     }
 
-    fn parse_union(&mut self, description: Option<Spur>) -> Result<Union, GraphqlParseError> {
+    fn parse_union(
+        &mut self,
+        description: Option<Spur>,
+        start: usize,
+    ) -> Result<Union, GraphqlParseError> {
         let name = self.expect_name()?;
         let directives = self.parse_optional_directives()?;
 
-        self.lexer.expect_byte(b'=')?;
-
         let mut types = Vec::new();
 
-        types.push(NamedType(self.expect_name()?));
+        if self.lexer.consume_byte_if_eq(b'=') {
+            // a leading `|` is allowed before the first member
+            self.lexer.consume_byte_if_eq(b'|');
 
-        while self.lexer.consume_byte_if_eq(b'|') {
             types.push(NamedType(self.expect_name()?));
+
+            while self.lexer.consume_byte_if_eq(b'|') {
+                types.push(NamedType(self.expect_name()?));
+            }
         }
 
         Ok(Union {
@@ -549,10 +1454,13 @@ impl<'a> GraphqlParser<'a> {
             description,
             directives,
             types,
+            span: self.span_from(start),
         })
     }
 
     fn parse_field_definition(&mut self) -> Result<FieldDefinition, GraphqlParseError> {
+        let start = self.lexer.next_token_start();
+
         let description = self.parse_optional_description()?;
         let name = self.expect_name()?;
 
@@ -560,7 +1468,7 @@ impl<'a> GraphqlParser<'a> {
 
         self.lexer.expect_byte(b':')?;
 
-        let ty = self.parse_type()?;
+        let (ty, ty_span) = self.parse_spanned_type()?;
 
         let directives = self.parse_optional_directives()?;
 
@@ -569,7 +1477,9 @@ impl<'a> GraphqlParser<'a> {
             name,
             arguments,
             ty,
+            ty_span,
             directives,
+            span: self.span_from(start),
         })
     }
 
@@ -593,22 +1503,33 @@ impl<'a> GraphqlParser<'a> {
         Ok(arguments)
     }
 
+    fn parse_spanned_type(&mut self) -> Result<(Type, Span), GraphqlParseError> {
+        let start = self.lexer.next_token_start();
+        let ty = self.parse_type()?;
+
+        Ok((ty, self.span_from(start)))
+    }
+
     fn parse_type(&mut self) -> Result<Type, GraphqlParseError> {
         let mut base = match self.lexer.next_token()? {
             Some(Token::Name(name)) => Type::Named {
                 name,
                 nullable: true,
             },
+            Some(Token::Keyword(keyword)) => Type::Named {
+                name: self.lexer.interner.get_or_intern(keyword.as_str()),
+                nullable: true,
+            },
             Some(Token::OpenSquareBrace) => {
                 let ty = Type::List {
-                    base: Box::new(self.parse_type()?),
+                    base: Box::new(self.nested(Self::parse_type)?),
                     nullable: true,
                 };
                 self.lexer.expect_byte(b']')?;
 
                 ty
             }
-            _ => todo!(),
+            token => return Err(self.unexpected(token, "a type")),
         };
 
         if self.lexer.consume_byte_if_eq(b'!') {
@@ -618,3 +1539,665 @@ impl<'a> GraphqlParser<'a> {
         Ok(base)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use crate::{
+        ast::{
+            Definition, Enum, InputObject, Interface, ObjectType, Scalar, Span, Token, Type,
+            TypeDefinition, Union,
+        },
+        lexer::ParseLimits,
+        GraphqlParseError, GraphqlParser, ParseLimit,
+    };
+
+    fn error(source: &str) -> GraphqlParseError {
+        GraphqlParser::parse(source.as_bytes()).unwrap_err()
+    }
+
+    /// The source text `err` points to
+    fn error_text<'a>(source: &'a str, err: &GraphqlParseError) -> &'a str {
+        let span = err.span();
+        &source[span.start..span.end]
+    }
+
+    /// Whether each level of `ty` is nullable, from the outside in
+    fn nullability(ty: &Type) -> Vec<bool> {
+        match ty {
+            Type::Named { nullable, .. } => vec![*nullable],
+            Type::List { base, nullable } => {
+                let mut levels = vec![*nullable];
+                levels.extend(nullability(base));
+                levels
+            }
+        }
+    }
+
+    #[test]
+    fn non_null_types() {
+        let document = GraphqlParser::parse(
+            b"type T { a: Int b: Int! c: [Int!] d: [Int]! e: [Int!]! f: [[Int!]]! }",
+        )
+        .unwrap();
+
+        let object = document.output_objects.values().next().unwrap();
+        let types = object
+            .fields
+            .iter()
+            .flatten()
+            .map(|field| nullability(&field.ty))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            types,
+            [
+                vec![true],
+                vec![false],
+                vec![true, false],
+                vec![false, true],
+                vec![false, false],
+                vec![false, true, false],
+            ]
+        );
+    }
+
+    /// The text, start and end of each comment in `source`
+    fn comments(source: &str) -> Vec<(String, usize, usize)> {
+        let (_, comments) = GraphqlParser::parse_with_comments(source.as_bytes())
+            .expect("failed to parse document");
+
+        comments
+            .iter()
+            .map(|comment| {
+                (
+                    comment.text(source.as_bytes()).into_owned(),
+                    comment.start,
+                    comment.end,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn leading_comment() {
+        assert_eq!(
+            comments("# a query\nquery Q { a }"),
+            [(" a query".to_owned(), 0, 9)]
+        );
+    }
+
+    #[test]
+    fn trailing_comment() {
+        let source = "query Q {\n  a # the a field\r\n  b\n}";
+
+        assert_eq!(comments(source), [(" the a field".to_owned(), 14, 27)]);
+        assert_eq!(&source[14..27], "# the a field");
+    }
+
+    #[test]
+    fn comment_at_end_of_input() {
+        assert_eq!(comments("query Q { a }\n#"), [(String::new(), 14, 15)]);
+        assert_eq!(
+            comments("query Q { a } # done"),
+            [(" done".to_owned(), 14, 20)]
+        );
+    }
+
+    #[test]
+    fn hash_in_string_is_not_a_comment() {
+        let source = "query Q { a(b: \"# not\", c: \"\"\"\n# nor this\n\"\"\") } # but this";
+
+        assert_eq!(
+            comments(source),
+            [(" but this".to_owned(), source.len() - 10, source.len())]
+        );
+    }
+
+    #[test]
+    fn unexpected_end_of_input() {
+        for source in ["{ a", "query Q(", "{ a(b: ", "fragment F on", "type T {"] {
+            let err = error(source);
+
+            assert!(
+                err.to_string().ends_with("found end of input"),
+                "{}: {}",
+                source,
+                err
+            );
+            assert_eq!(err.span().start, source.len(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn whitespace_after_dollar() {
+        for source in ["query Q($ a: Int) { a }", "{ a(b: $ c) }"] {
+            let err = error(source);
+
+            assert!(
+                matches!(
+                    err,
+                    GraphqlParseError::UnexpectedToken {
+                        found: Token::Dollar,
+                        ..
+                    }
+                ),
+                "{}: {:?}",
+                source,
+                err
+            );
+            assert_eq!(err.span().start, source.find('$').unwrap());
+            assert_eq!(error_text(source, &err), "$");
+        }
+    }
+
+    #[test]
+    fn unexpected_token() {
+        let source = "query Q { a(b: 1) { c } ) }";
+        let err = error(source);
+
+        assert!(
+            matches!(err, GraphqlParseError::UnexpectedToken { .. }),
+            "{:?}",
+            err
+        );
+        assert_eq!(error_text(source, &err), ")");
+    }
+
+    #[test]
+    fn keyword_values() {
+        assert!(GraphqlParser::parse(b"{ a(b: true, c: false, d: null, e: ENUM) }").is_ok());
+
+        let source = "{ a(b: $) }";
+        assert_eq!(error_text(source, &error(source)), "$");
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        for (source, number) in [
+            ("{ a(b: 01) }", "01"),
+            ("{ a(b: 1.) }", "1."),
+            ("{ a(b: 1e) }", "1e"),
+            ("{ a(b: -) }", "-"),
+            ("{ a(b: 1.5.) }", "1.5."),
+            ("{ a(b: 1e400) }", "1e400"),
+            ("{ a(b: -1.5e400) }", "-1.5e400"),
+        ] {
+            let err = error(source);
+
+            assert!(
+                matches!(err, GraphqlParseError::InvalidNumber { .. }),
+                "{}: {:?}",
+                source,
+                err
+            );
+            assert_eq!(error_text(source, &err), number, "{}", source);
+        }
+
+        assert!(GraphqlParser::parse(b"{ a(b: 0, c: -1.5e10, d: 2E-3) }").is_ok());
+    }
+
+    #[test]
+    fn invalid_character() {
+        let source = "{ a ? }";
+        let err = error(source);
+
+        assert!(
+            matches!(err, GraphqlParseError::InvalidCharacter { found: '?', .. }),
+            "{:?}",
+            err
+        );
+        assert_eq!(err.span(), Span::new(4, 5));
+    }
+
+    #[test]
+    fn unterminated_string() {
+        for source in ["{ a(b: \"c) }", "{ a(b: \"\"\"c) }", "{ a(b: \"c\nd\") }"] {
+            assert!(
+                GraphqlParser::parse(source.as_bytes()).is_err(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_ellipsis() {
+        for (source, found) in [("{ ..F }", ".."), ("{ . F }", "."), ("{ . . . F }", ".")] {
+            let err = error(source);
+
+            assert!(
+                matches!(err, GraphqlParseError::InvalidToken { found: f, .. } if f == found),
+                "{}: {:?}",
+                source,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let err = GraphqlParser::parse(b"{ a(b: \"\xff\") }").unwrap_err();
+
+        assert!(
+            matches!(err, GraphqlParseError::InvalidUtf8 { .. }),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn description_on_extension() {
+        let source = "\"desc\" extend type T { a: Int }";
+        let err = error(source);
+
+        assert!(
+            matches!(err, GraphqlParseError::UnexpectedDescription { .. }),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn unknown_directive_location() {
+        let source = "directive @a on FIELD | NOWHERE";
+        let err = error(source);
+
+        match &err {
+            GraphqlParseError::UnknownDirectiveLocation { name, .. } => assert_eq!(name, "NOWHERE"),
+            err => panic!("{:?}", err),
+        }
+        assert_eq!(error_text(source, &err), "NOWHERE");
+    }
+
+    #[test]
+    fn definitions_of_the_wrong_kind() {
+        let err = GraphqlParser::parse_schema(b"type T { a: Int } { a }").unwrap_err();
+        assert!(
+            matches!(err, GraphqlParseError::ExecutableDefinitionInSchema { .. }),
+            "{:?}",
+            err
+        );
+
+        let err = GraphqlParser::parse_query(b"{ a } scalar S").unwrap_err();
+        assert!(
+            matches!(
+                err,
+                GraphqlParseError::TypeSystemDefinitionInExecutable { .. }
+            ),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn error_location() {
+        let source = "{\n  a\n  a(b: \"é\", c: ?)\n}";
+        let err = error(source);
+
+        let location = err.location(source.as_bytes());
+        assert_eq!((location.line, location.column), (3, 16));
+    }
+
+    #[test]
+    fn definitions_in_source_order() {
+        let mut definitions = GraphqlParser::definitions(
+            concat!(
+                "scalar A query Q { a } type B { b: Int } fragment F on B { b } ",
+                "directive @d on FIELD extend type B { c: Int } schema { query: B } ",
+                "extend schema @d enum C { D } { a }"
+            )
+            .as_bytes(),
+        );
+
+        let parsed = definitions.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        let interner = definitions.interner();
+        let name = |name| interner.resolve(&name);
+
+        let labels = parsed
+            .iter()
+            .map(|definition| match definition {
+                Definition::Schema(..) => "schema".to_owned(),
+                Definition::SchemaExtension(..) => "extend schema".to_owned(),
+                Definition::Operation(operation) => match operation.name {
+                    Some(operation) => format!("operation {}", name(operation)),
+                    None => "anonymous operation".to_owned(),
+                },
+                Definition::Fragment(fragment) => format!("fragment {}", name(fragment.name)),
+                Definition::TypeDecl(ty) => format!(
+                    "type {}",
+                    name(match ty {
+                        TypeDefinition::Scalar(Scalar { name, .. })
+                        | TypeDefinition::Object(ObjectType { name, .. })
+                        | TypeDefinition::Interface(Interface { name, .. })
+                        | TypeDefinition::Union(Union { name, .. })
+                        | TypeDefinition::Enum(Enum { name, .. })
+                        | TypeDefinition::InputObject(InputObject { name, .. }) => *name,
+                    })
+                ),
+                Definition::Directive(directive) => format!("directive @{}", name(directive.name)),
+                Definition::TypeExtension(extension) => {
+                    format!("extend {}", name(extension.name()))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            labels,
+            [
+                "type A",
+                "operation Q",
+                "type B",
+                "fragment F",
+                "directive @d",
+                "extend B",
+                "schema",
+                "extend schema",
+                "type C",
+                "anonymous operation",
+            ]
+        );
+    }
+
+    #[test]
+    fn definitions_stop_after_error() {
+        let mut definitions = GraphqlParser::definitions(b"scalar A type B { ? } scalar C");
+
+        assert!(definitions.next().unwrap().is_ok());
+        assert!(definitions.next().unwrap().is_err());
+        assert!(definitions.next().is_none());
+        assert!(definitions.next().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "spans")]
+    fn spans() {
+        let source = concat!(
+            "type Query { user(id: ID! = \"1\"): User @deprecated(reason: \"no\") }\n",
+            "{ user(id: \"1\", filter: { tags: [\"a\", \"b\"] }) { name } }",
+        );
+        let mut interner = Rodeo::default();
+
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse document");
+        let text = |span: Span| &source[span.start..span.end];
+
+        let query = document.get_type_by_name("Query", &interner).unwrap();
+        assert_eq!(text(query.span()), source.lines().next().unwrap());
+
+        let field = &document.fields_of(query.name()).unwrap()[0];
+        assert_eq!(
+            text(field.span),
+            "user(id: ID! = \"1\"): User @deprecated(reason: \"no\")"
+        );
+        assert_eq!(text(field.ty_span), "User");
+        assert_eq!(
+            text(field.directives[0].span),
+            "@deprecated(reason: \"no\")"
+        );
+
+        let argument = &field.arguments.as_ref().unwrap()[0];
+        assert_eq!(text(argument.ty_span), "ID!");
+        assert_eq!(text(argument.default_span.span), "\"1\"");
+
+        let operation = document.operations.values().next().unwrap();
+        let selection = &operation.selection_set[0];
+        assert_eq!(
+            text(selection.span()),
+            "user(id: \"1\", filter: { tags: [\"a\", \"b\"] }) { name }"
+        );
+
+        let filter = match selection {
+            crate::ast::Selection::Field { arguments, .. } => &arguments.as_ref().unwrap()[1],
+            selection => panic!("{:?}", selection),
+        };
+        assert_eq!(text(filter.span), "filter: { tags: [\"a\", \"b\"] }");
+        assert_eq!(text(filter.value_span.span), "{ tags: [\"a\", \"b\"] }");
+
+        let tags = &filter.value_span.fields[&interner.get("tags").unwrap()];
+        assert_eq!(text(tags.span), "tags: [\"a\", \"b\"]");
+        assert_eq!(text(tags.value.span), "[\"a\", \"b\"]");
+        assert_eq!(text(tags.value.items[1].span), "\"b\"");
+    }
+
+    #[test]
+    fn limits() {
+        fn limit_error(source: &str, limits: ParseLimits) -> GraphqlParseError {
+            GraphqlParser::parse_query_with_limits(source.as_bytes(), &mut Rodeo::default(), limits)
+                .unwrap_err()
+        }
+
+        let source = "{ a(b: [[1]]) { c { d } } }";
+        let err = limit_error(
+            source,
+            ParseLimits {
+                max_depth: Some(2),
+                ..ParseLimits::default()
+            },
+        );
+        assert!(
+            matches!(
+                err,
+                GraphqlParseError::LimitExceeded {
+                    limit: ParseLimit::Depth,
+                    max: 2,
+                    ..
+                }
+            ),
+            "{:?}",
+            err
+        );
+        assert_eq!(error_text(source, &err), "[");
+
+        let err = limit_error(
+            source,
+            ParseLimits {
+                max_tokens: Some(10),
+                ..ParseLimits::default()
+            },
+        );
+        assert_eq!(error_text(source, &err), ")");
+
+        let source = "{ a(b: \"too long\") }";
+        let err = limit_error(
+            source,
+            ParseLimits {
+                max_string_length: Some(3),
+                ..ParseLimits::default()
+            },
+        );
+        assert_eq!(error_text(source, &err), "\"too ");
+
+        let err = limit_error(
+            source,
+            ParseLimits {
+                max_input_size: Some(8),
+                ..ParseLimits::default()
+            },
+        );
+        assert_eq!(error_text(source, &err), "too long\") }");
+
+        let limits = ParseLimits {
+            max_tokens: Some(18),
+            max_depth: Some(3),
+            max_string_length: Some(8),
+            max_input_size: Some(27),
+        };
+        for source in ["{ a(b: [[1]]) { c { d } } }", source] {
+            let result = GraphqlParser::parse_query_with_limits(
+                source.as_bytes(),
+                &mut Rodeo::default(),
+                limits,
+            );
+
+            assert!(result.is_ok(), "{}: {:?}", source, result);
+        }
+    }
+
+    #[test]
+    fn limits_of_schemas_and_mixed_documents() {
+        let depth = ParseLimits {
+            max_depth: Some(1),
+            ..ParseLimits::default()
+        };
+
+        let source = "type T { a(b: [[Int]]): Int }";
+        let err = GraphqlParser::parse_schema_with_limits(
+            source.as_bytes(),
+            &mut Rodeo::default(),
+            depth,
+        )
+        .unwrap_err();
+        assert_eq!(error_text(source, &err), "[");
+
+        let source = "type T { a: Int }\n{ a(b: { c: { d: 1 } }) }";
+        let err = GraphqlParser::parse_with_limits(source.as_bytes(), &mut Rodeo::default(), depth)
+            .unwrap_err();
+        assert_eq!(error_text(source, &err), "{");
+        assert_eq!(err.span().start, source.find("{ c").unwrap());
+
+        let strings = ParseLimits {
+            max_string_length: Some(4),
+            ..ParseLimits::default()
+        };
+        let source = "\"\"\"long description\"\"\" scalar S";
+        let err = GraphqlParser::parse_schema_with_limits(
+            source.as_bytes(),
+            &mut Rodeo::default(),
+            strings,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                GraphqlParseError::LimitExceeded {
+                    limit: ParseLimit::StringLength,
+                    ..
+                }
+            ),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn definitions_stop_at_limit() {
+        let limits = ParseLimits {
+            max_tokens: Some(8),
+            ..ParseLimits::default()
+        };
+
+        let results = GraphqlParser::definitions_with_limits(
+            b"scalar A scalar B type C { c: Int } scalar D",
+            Rodeo::default(),
+            limits,
+        )
+        .collect::<Vec<_>>();
+
+        assert_eq!(results.len(), 3, "{:?}", results);
+        assert!(results[..2].iter().all(Result::is_ok));
+        assert!(matches!(
+            results[2],
+            Err(GraphqlParseError::LimitExceeded {
+                limit: ParseLimit::Tokens,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn recovery_stops_at_limit() {
+        let source = "scalar A\ntype B { b: [[Int]] }\nscalar C";
+        let mut interner = Rodeo::default();
+
+        let (document, errors) = GraphqlParser::parse_with_recovery_and_limits(
+            source.as_bytes(),
+            &mut interner,
+            ParseLimits {
+                max_depth: Some(1),
+                ..ParseLimits::default()
+            },
+        );
+
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(error_text(source, &errors[0]), "[");
+        assert_eq!(definition_names(&document, &interner), ["A"]);
+
+        let (_, errors) = GraphqlParser::parse_with_recovery_and_limits(
+            source.as_bytes(),
+            &mut interner,
+            ParseLimits {
+                max_input_size: Some(10),
+                ..ParseLimits::default()
+            },
+        );
+
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+    }
+
+    /// The names of the types and operations in `document`, sorted
+    fn definition_names(document: &crate::Document, interner: &Rodeo) -> Vec<String> {
+        let mut names = document
+            .types()
+            .map(|ty| interner.resolve(&ty.name()).to_owned())
+            .chain(
+                document
+                    .operations
+                    .keys()
+                    .filter_map(|(name, _)| name.map(|name| interner.resolve(&name).to_owned())),
+            )
+            .collect::<Vec<_>>();
+
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn recovery_keeps_valid_definitions() {
+        let source =
+            "type A { a: Int }\ntype B { b: }\ntype C { c: Int }\nquery Q { a( }\nquery R { a }";
+        let mut interner = Rodeo::default();
+
+        let (document, errors) =
+            GraphqlParser::parse_with_recovery_and_interner(source.as_bytes(), &mut interner);
+
+        assert_eq!(definition_names(&document, &interner), ["A", "C", "R"]);
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!(errors[0].location(source.as_bytes()).line, 2);
+        assert_eq!(errors[1].location(source.as_bytes()).line, 4);
+    }
+
+    #[test]
+    fn recovery_resumes_on_a_new_line() {
+        // definitions that don't start at the beginning of a line are skipped
+        let source = "type A { a: ? } type B { b: Int }\n  type C { c: Int }\ntype D { d: Int }";
+        let mut interner = Rodeo::default();
+
+        let (document, errors) =
+            GraphqlParser::parse_with_recovery_and_interner(source.as_bytes(), &mut interner);
+
+        assert_eq!(definition_names(&document, &interner), ["D"]);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+    }
+
+    #[test]
+    fn recovery_of_valid_document() {
+        let source = "type A { a: Int }\n{ a }";
+
+        let (document, errors) = GraphqlParser::parse_with_recovery(source.as_bytes());
+
+        assert!(errors.is_empty());
+        assert_eq!(document.operations.len(), 1);
+        assert_eq!(document.types().count(), 1);
+    }
+
+    #[test]
+    fn recovery_at_end_of_input() {
+        for source in ["{", "type A {", "query Q(", "\"unterminated", "{ a }\n}"] {
+            let (_, errors) = GraphqlParser::parse_with_recovery(source.as_bytes());
+
+            assert_eq!(errors.len(), 1, "{}: {:?}", source, errors);
+        }
+    }
+}
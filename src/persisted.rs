@@ -0,0 +1,347 @@
+//! Persisted query manifests. Each operation of a document is printed in a
+//! normalized form, together with the fragments it spreads, and identified by
+//! the SHA-256 hash of that text
+//!
+//! Printing drops comments and insignificant whitespace, and puts definitions
+//! and object fields in a stable order, so that the same operation written
+//! differently gets the same id
+//!
+//! Manifests written by other tools can be read with [`read_manifest`], and
+//! converted to this normalized form with [`renormalize`]
+
+use std::fmt;
+
+use lasso::Rodeo;
+use serde_json::{json, Map, Value as JsonValue};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    ast::{Definition, Document, Operation, OperationKind},
+    fmt::print_document,
+    GraphqlParser, ParseResult,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    /// `{ "format": "apollo-persisted-query-manifest", "version": 1,
+    /// "operations": [{ "id", "name", "type", "body" }] }`
+    Apollo,
+    /// An object from each id to its body, as read by Relay, and by urql
+    /// through the persisted documents of GraphQL Code Generator
+    Relay,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PersistedOperation {
+    /// The hex encoded SHA-256 hash of `body`
+    pub id: String,
+    pub name: Option<String>,
+    pub kind: OperationKind,
+    /// The normalized operation followed by the fragments it spreads
+    pub body: String,
+}
+
+/// Normalize and hash every operation of `document`, sorted by name
+pub fn persisted_operations(document: &Document, interner: &Rodeo) -> Vec<PersistedOperation> {
+    let mut operations = document
+        .operations
+        .values()
+        .map(|operation| {
+            let body = normalize_operation(document, operation, interner);
+
+            PersistedOperation {
+                id: sha256_hex(&body),
+                name: operation
+                    .name
+                    .map(|name| interner.resolve(&name).to_owned()),
+                kind: operation.kind,
+                body,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    operations.sort_by(|a, b| (&a.name, a.kind as u8).cmp(&(&b.name, b.kind as u8)));
+
+    operations
+}
+
+/// Print `operation` followed by the fragments of `document` it spreads,
+/// directly or through other fragments
+pub fn normalize_operation(document: &Document, operation: &Operation, interner: &Rodeo) -> String {
+    print_document(&document.extract(operation), interner)
+}
+
+pub(crate) fn sha256_hex(body: &str) -> String {
+    Sha256::digest(body.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The manifest listing `operations`, in the given format
+pub fn manifest(operations: &[PersistedOperation], format: ManifestFormat) -> JsonValue {
+    match format {
+        ManifestFormat::Apollo => {
+            let operations = operations
+                .iter()
+                .map(|operation| {
+                    json!({
+                        "id": operation.id,
+                        "name": operation.name,
+                        "type": operation.kind.as_str(),
+                        "body": operation.body,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            json!({
+                "format": "apollo-persisted-query-manifest",
+                "version": 1,
+                "operations": operations,
+            })
+        }
+        ManifestFormat::Relay => JsonValue::Object(
+            operations
+                .iter()
+                .map(|operation| (operation.id.clone(), operation.body.clone().into()))
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    /// The JSON is neither an Apollo manifest nor an object from ids to
+    /// bodies
+    UnknownFormat,
+    /// The body of the operation `id` isn't a document with a single
+    /// operation
+    InvalidBody { id: String, message: String },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFormat => write!(
+                f,
+                "expected an Apollo manifest or an object from ids to bodies"
+            ),
+            Self::InvalidBody { id, message } => write!(f, "operation `{}`: {}", id, message),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// Read a manifest in any [`ManifestFormat`]. The name and kind of each
+/// operation are read from its body, and its id is kept as given, as other
+/// tools may hash bodies differently
+pub fn read_manifest(json: &JsonValue) -> Result<Vec<PersistedOperation>, ManifestError> {
+    let object = json.as_object().ok_or(ManifestError::UnknownFormat)?;
+
+    let entries = if json["format"] == "apollo-persisted-query-manifest" {
+        json["operations"]
+            .as_array()
+            .ok_or(ManifestError::UnknownFormat)?
+            .iter()
+            .map(|operation| match (&operation["id"], &operation["body"]) {
+                (JsonValue::String(id), JsonValue::String(body)) => Ok((id, body)),
+                _ => Err(ManifestError::UnknownFormat),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        object
+            .iter()
+            .map(|(id, body)| match body {
+                JsonValue::String(body) => Ok((id, body)),
+                _ => Err(ManifestError::UnknownFormat),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    entries
+        .into_iter()
+        .map(|(id, body)| {
+            let result = parse_body(id, body)?;
+            let operation = result.document.operations.values().next().unwrap();
+
+            Ok(PersistedOperation {
+                id: id.clone(),
+                name: operation
+                    .name
+                    .map(|name| result.interner.resolve(&name).to_owned()),
+                kind: operation.kind,
+                body: body.clone(),
+            })
+        })
+        .collect()
+}
+
+/// `operations` with their bodies normalized, and identified by the hash of
+/// the normalized body, as [`persisted_operations`] would list them. The
+/// order is kept, so that old and new ids can be matched up
+pub fn renormalize(
+    operations: &[PersistedOperation],
+) -> Result<Vec<PersistedOperation>, ManifestError> {
+    operations
+        .iter()
+        .map(|operation| {
+            let result = parse_body(&operation.id, &operation.body)?;
+
+            Ok(persisted_operations(&result.document, &result.interner)
+                .pop()
+                .unwrap())
+        })
+        .collect()
+}
+
+/// Parse the body of the operation `id`, which must contain a single
+/// operation
+fn parse_body(id: &str, body: &str) -> Result<ParseResult, ManifestError> {
+    let invalid = |message: String| ManifestError::InvalidBody {
+        id: id.to_owned(),
+        message,
+    };
+
+    let result =
+        GraphqlParser::parse_resolvable(body.as_bytes()).map_err(|err| invalid(err.to_string()))?;
+
+    // operations replaced by a later one of the same name count too
+    let replaced = result
+        .document
+        .duplicates
+        .iter()
+        .filter(|definition| matches!(definition, Definition::Operation(..)))
+        .count();
+
+    match result.document.operations.len() + replaced {
+        1 => Ok(result),
+        len => Err(invalid(format!(
+            "expected a single operation, found {}",
+            len
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{
+        manifest, persisted_operations, read_manifest, renormalize, ManifestError, ManifestFormat,
+    };
+    use crate::GraphqlParser;
+
+    fn bodies(source: &str) -> Vec<String> {
+        let result = GraphqlParser::parse_resolvable(source.as_bytes()).unwrap();
+
+        persisted_operations(&result.document, &result.interner)
+            .into_iter()
+            .map(|operation| operation.body)
+            .collect()
+    }
+
+    fn ids(source: &str) -> Vec<String> {
+        let result = GraphqlParser::parse_resolvable(source.as_bytes()).unwrap();
+
+        persisted_operations(&result.document, &result.interner)
+            .into_iter()
+            .map(|operation| operation.id)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_non_null_variables() {
+        let body = &bodies("query User($id: ID!, $ids: [ID!]!) { user(id: $id) { id } }")[0];
+
+        assert!(body.contains("($id: ID!, $ids: [ID!]!)"), "{}", body);
+    }
+
+    #[test]
+    fn non_null_changes_id() {
+        let strict = ids("query User($id: ID!) { user(id: $id) { id } }");
+        let lax = ids("query User($id: ID) { user(id: $id) { id } }");
+
+        assert_ne!(strict, lax);
+    }
+
+    #[test]
+    fn same_operation_written_differently_has_same_id() {
+        let a = ids("query A { b(x: 1, y: { p: 1, q: 2 }) { ...F } } fragment F on T { c }");
+        let b = ids(concat!(
+            "# comment\n",
+            "fragment F on T {\n  c\n}\n",
+            "query A {\n  b(x: 1, y: {q: 2, p: 1}) { ...F }\n}",
+        ));
+
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn read_manifests() {
+        let result = GraphqlParser::parse_resolvable(
+            b"query A { a } mutation B { ...F } fragment F on Mutation { b }",
+        )
+        .unwrap();
+        let operations = persisted_operations(&result.document, &result.interner);
+
+        for format in [ManifestFormat::Apollo, ManifestFormat::Relay] {
+            let mut read = read_manifest(&manifest(&operations, format)).unwrap();
+            read.sort_by(|a, b| a.name.cmp(&b.name));
+
+            assert_eq!(read, operations, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn renormalize_other_manifests() {
+        let operations = read_manifest(&json!({
+            "abc": "query A { b(y: 2, x: 1) }",
+            "def": "query C {\n  d\n}",
+        }))
+        .unwrap();
+
+        let renormalized = renormalize(&operations).unwrap();
+
+        assert_eq!(
+            renormalized
+                .iter()
+                .map(|operation| operation.body.as_str())
+                .collect::<Vec<_>>(),
+            bodies("query A { b(y: 2, x: 1) } query C { d }")
+        );
+        assert_eq!(
+            renormalized
+                .iter()
+                .map(|operation| operation.id.clone())
+                .collect::<Vec<_>>(),
+            ids("query A { b(y: 2, x: 1) } query C { d }")
+        );
+    }
+
+    #[test]
+    fn invalid_manifests() {
+        for json in [
+            json!([]),
+            json!({ "abc": 1 }),
+            json!({ "format": "apollo-persisted-query-manifest", "operations": {} }),
+        ] {
+            assert_eq!(
+                read_manifest(&json),
+                Err(ManifestError::UnknownFormat),
+                "{}",
+                json
+            );
+        }
+
+        for body in ["query A { a } query B { b }", "{ a } { b }"] {
+            assert_eq!(
+                read_manifest(&json!({ "abc": body })),
+                Err(ManifestError::InvalidBody {
+                    id: "abc".to_owned(),
+                    message: "expected a single operation, found 2".to_owned(),
+                })
+            );
+        }
+    }
+}
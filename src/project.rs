@@ -0,0 +1,1037 @@
+//! Projects described by a [graphql-config](https://the-guild.dev/graphql/config)
+//! file, such as `.graphqlrc.yml`
+//!
+//! A project is a schema, split across any number of files, and the
+//! operations and fragments written against it. [`Project::load`] reads and
+//! parses every file the config's globs match, merges the schema files into
+//! one schema, and validates each document file against it, collecting
+//! diagnostics for each file
+//!
+//! Schema files are SDL, or introspection results if they end in `.json`.
+//! Fetching a schema from a URL isn't supported
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use lasso::{Rodeo, Spur};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    ast::{Document, Selection, Span},
+    error::LineColumn,
+    fmt::print_document,
+    introspection::document_from_introspection,
+    validation::{validate_executable_document, ValidationErrorKind},
+    visit::{self, Visitor},
+    GraphqlParser,
+};
+
+/// The names a config file may have, in the order they are looked for
+pub const CONFIG_FILE_NAMES: &[&str] = &[
+    ".graphqlrc",
+    ".graphqlrc.yml",
+    ".graphqlrc.yaml",
+    ".graphqlrc.json",
+    "graphql.config.yml",
+    "graphql.config.yaml",
+    "graphql.config.json",
+];
+
+#[derive(Debug)]
+pub struct ProjectError {
+    /// The config file, or the directory a config file was looked for in
+    pub path: PathBuf,
+    pub kind: ProjectErrorKind,
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.kind)
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+#[derive(Debug)]
+pub enum ProjectErrorKind {
+    /// No config file was found in the directory or any of its ancestors
+    ConfigNotFound,
+    Io(std::io::Error),
+    InvalidConfig(String),
+    /// The config has no project with this name
+    UnknownProject(String),
+}
+
+impl fmt::Display for ProjectErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConfigNotFound => write!(
+                f,
+                "no graphql config file found, expected one of {}",
+                CONFIG_FILE_NAMES.join(", ")
+            ),
+            Self::Io(err) => write!(f, "failed to read: {}", err),
+            Self::InvalidConfig(message) => write!(f, "invalid config: {}", message),
+            Self::UnknownProject(name) => write!(f, "no project named `{}`", name),
+        }
+    }
+}
+
+/// The files of one project in a config file. Paths and globs are relative
+/// to the directory of the config file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectConfig {
+    /// `None` for the project at the top level of the config, rather than
+    /// under `projects`
+    pub name: Option<String>,
+    pub schema: Vec<String>,
+    pub documents: Vec<String>,
+    /// Globs of files to leave out of `schema` and `documents`
+    pub exclude: Vec<String>,
+}
+
+impl ProjectConfig {
+    /// Read every project in a config file, which may be YAML or JSON
+    pub fn from_file(path: &Path) -> Result<Vec<Self>, ProjectError> {
+        let error = |kind| ProjectError {
+            path: path.to_owned(),
+            kind,
+        };
+
+        let text = fs::read_to_string(path).map_err(|err| error(ProjectErrorKind::Io(err)))?;
+
+        let json = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str::<JsonValue>(&text).map_err(|err| err.to_string())
+        } else {
+            // JSON is also valid YAML
+            serde_yaml::from_str::<JsonValue>(&text).map_err(|err| err.to_string())
+        };
+
+        json.and_then(|json| Self::from_json(&json))
+            .map_err(|message| error(ProjectErrorKind::InvalidConfig(message)))
+    }
+
+    /// Read every project in a parsed config file
+    pub fn from_json(json: &JsonValue) -> Result<Vec<Self>, String> {
+        let object = json
+            .as_object()
+            .ok_or_else(|| "expected an object".to_owned())?;
+
+        let projects = match object.get("projects") {
+            Some(JsonValue::Object(projects)) => projects,
+            Some(..) => return Err("`projects` must be an object".to_owned()),
+            None => return Ok(vec![Self::from_object(None, json)?]),
+        };
+
+        projects
+            .iter()
+            .map(|(name, project)| Self::from_object(Some(name.clone()), project))
+            .collect()
+    }
+
+    fn from_object(name: Option<String>, json: &JsonValue) -> Result<Self, String> {
+        let strings = |key: &str| match json.get(key) {
+            None | Some(JsonValue::Null) => Ok(Vec::new()),
+            Some(value) => pointers(value)
+                .ok_or_else(|| format!("`{}` must be a string, or a list of strings", key)),
+        };
+
+        Ok(Self {
+            schema: strings("schema")?,
+            documents: strings("documents")?,
+            exclude: strings("exclude")?,
+            name,
+        })
+    }
+}
+
+/// A glob, or list of globs. Schemas may also be given as an object with the
+/// pointer as its only key, whose value configures how it is loaded
+fn pointers(value: &JsonValue) -> Option<Vec<String>> {
+    match value {
+        JsonValue::String(pointer) => Some(vec![pointer.clone()]),
+        JsonValue::Array(values) => values
+            .iter()
+            .map(|value| match value {
+                JsonValue::String(pointer) => Some(pointer.clone()),
+                JsonValue::Object(object) if object.len() == 1 => object.keys().next().cloned(),
+                _ => None,
+            })
+            .collect(),
+        JsonValue::Object(object) if object.len() == 1 => {
+            object.keys().next().cloned().map(|k| vec![k])
+        }
+        _ => None,
+    }
+}
+
+/// The config file in `dir`, or the nearest of its ancestors that has one
+pub fn find_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find_map(|dir| {
+        CONFIG_FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    /// Where in the file the problem is, if known
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    fn new(message: String, span: Option<Span>) -> Self {
+        Self { message, span }
+    }
+
+    pub fn location(&self, source: &[u8]) -> Option<LineColumn> {
+        self.span.map(|span| span.start_location(source))
+    }
+}
+
+#[derive(Debug)]
+pub struct ProjectFile {
+    pub path: PathBuf,
+    pub source: Vec<u8>,
+    /// The definitions of a document file. Schema files are parsed together
+    /// into [`Project::schema`], so this is empty for them
+    pub document: Document,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The number of diagnostics, at the start of `diagnostics`, found while
+    /// parsing the file rather than validating it
+    syntax_errors: usize,
+}
+
+#[derive(Debug)]
+pub struct Project {
+    /// The directory of the config file
+    pub root: PathBuf,
+    pub config: ProjectConfig,
+    /// The interner the schema and every document were parsed with
+    pub interner: Rodeo,
+    /// The definitions of every schema file, with extensions applied
+    pub schema: Document,
+    pub schema_files: Vec<ProjectFile>,
+    pub document_files: Vec<ProjectFile>,
+    /// Problems that don't belong to a single file, such as a schema glob
+    /// that matches nothing
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Project {
+    /// Find the config file for `dir` and load the project named `name`, or
+    /// the only project if `name` is `None`. A config with several projects
+    /// is loaded as its `default` project if no name is given
+    pub fn discover(dir: &Path, name: Option<&str>) -> Result<Self, ProjectError> {
+        let not_found = |kind| ProjectError {
+            path: dir.to_owned(),
+            kind,
+        };
+
+        let dir = dir
+            .canonicalize()
+            .map_err(|err| not_found(ProjectErrorKind::Io(err)))?;
+
+        let path = find_config(&dir).ok_or_else(|| not_found(ProjectErrorKind::ConfigNotFound))?;
+
+        let configs = ProjectConfig::from_file(&path)?;
+
+        let name = name.or_else(|| (configs.len() > 1).then_some("default"));
+
+        let config = configs
+            .into_iter()
+            .find(|config| name.is_none() || config.name.as_deref() == name)
+            .ok_or_else(|| ProjectError {
+                path: path.clone(),
+                kind: ProjectErrorKind::UnknownProject(name.unwrap_or_default().to_owned()),
+            })?;
+
+        let root = path.parent().unwrap_or(Path::new(".")).to_owned();
+
+        Ok(Self::load(root, config))
+    }
+
+    /// Read and parse the files of the project described by `config`, whose
+    /// paths are relative to `root`
+    pub fn load(root: PathBuf, config: ProjectConfig) -> Self {
+        let mut project = Self {
+            root,
+            config,
+            interner: Rodeo::default(),
+            schema: Document::new(),
+            schema_files: Vec::new(),
+            document_files: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+
+        let (schema_paths, document_paths) = project.expand_globs();
+
+        project.schema_files = schema_paths
+            .into_iter()
+            .filter_map(|path| project.read_file(path))
+            .collect();
+
+        project.build_schema();
+
+        project.document_files = document_paths
+            .into_iter()
+            .filter_map(|path| project.read_document(path))
+            .collect();
+
+        project.validate_documents((0..project.document_files.len()).collect());
+
+        project
+    }
+
+    /// Bring the project up to date after the files at `paths` were created,
+    /// changed or removed, returning the files whose diagnostics may have
+    /// changed, including removed files
+    ///
+    /// Only the changed files are read again. A change to a document only
+    /// revalidates that document and those sharing fragments with it, while a
+    /// change to the schema revalidates every document
+    pub fn reload(&mut self, paths: &[PathBuf]) -> Vec<PathBuf> {
+        let is_schema_changed = paths.iter().any(|path| self.is_schema_file(path));
+
+        let mut changed = paths
+            .iter()
+            .filter(|path| self.is_document_file(path))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if is_schema_changed {
+            self.diagnostics.clear();
+
+            let (schema_paths, document_paths) = self.expand_globs();
+
+            let mut old_files = std::mem::take(&mut self.schema_files);
+
+            for path in schema_paths {
+                let old = old_files.iter().position(|file| file.path == path);
+
+                let file = match old {
+                    Some(idx) if !paths.contains(&path) => {
+                        let mut file = old_files.swap_remove(idx);
+                        file.diagnostics.clear();
+                        Some(file)
+                    }
+                    _ => self.read_file(path),
+                };
+
+                self.schema_files.extend(file);
+            }
+
+            self.build_schema();
+
+            // documents may have been created or removed without an event
+            // for them, such as when a directory is renamed
+            changed.extend(
+                document_paths
+                    .iter()
+                    .filter(|path| !self.document_files.iter().any(|file| &file.path == *path))
+                    .cloned(),
+            );
+            changed.extend(
+                self.document_files
+                    .iter()
+                    .filter(|file| !document_paths.contains(&file.path))
+                    .map(|file| file.path.clone()),
+            );
+        }
+
+        changed.sort();
+        changed.dedup();
+
+        // the fragments defined or spread by the changed documents, before
+        // and after the change
+        let mut fragments = Vec::new();
+
+        for path in &changed {
+            if let Some(idx) = self
+                .document_files
+                .iter()
+                .position(|file| &file.path == path)
+            {
+                let file = self.document_files.remove(idx);
+                fragments.extend(fragment_names(&file.document));
+            }
+
+            if path.is_file() {
+                if let Some(file) = self.read_document(path.clone()) {
+                    fragments.extend(fragment_names(&file.document));
+
+                    let idx = self
+                        .document_files
+                        .partition_point(|other| other.path < file.path);
+                    self.document_files.insert(idx, file);
+                }
+            }
+        }
+
+        let definitions = self.fragment_definitions();
+
+        let affected_documents = (0..self.document_files.len())
+            .filter(|&idx| {
+                let file = &self.document_files[idx];
+
+                is_schema_changed
+                    || changed.contains(&file.path)
+                    || fragment_names(&file.document)
+                        .chain(self.foreign_fragments(idx, &definitions))
+                        .any(|name| fragments.contains(&name))
+            })
+            .collect::<Vec<_>>();
+
+        let mut affected = changed;
+
+        for &idx in &affected_documents {
+            let file = &mut self.document_files[idx];
+            file.diagnostics.truncate(file.syntax_errors);
+            affected.push(file.path.clone());
+        }
+
+        self.validate_documents(affected_documents);
+
+        if is_schema_changed {
+            affected.extend(self.schema_files.iter().map(|file| file.path.clone()));
+            affected.extend(
+                paths
+                    .iter()
+                    .filter(|path| self.is_schema_file(path))
+                    .cloned(),
+            );
+        }
+
+        affected.sort();
+        affected.dedup();
+
+        affected
+    }
+
+    /// Whether any file, or the project itself, has a diagnostic
+    pub fn has_diagnostics(&self) -> bool {
+        !self.diagnostics.is_empty() || self.files().any(|file| !file.diagnostics.is_empty())
+    }
+
+    /// The schema files followed by the document files
+    pub fn files(&self) -> impl Iterator<Item = &ProjectFile> {
+        self.schema_files.iter().chain(&self.document_files)
+    }
+
+    pub fn file(&self, path: &Path) -> Option<&ProjectFile> {
+        self.files().find(|file| file.path == path)
+    }
+
+    /// Whether `path` matches the config's schema globs, whether or not it
+    /// exists
+    pub fn is_schema_file(&self, path: &Path) -> bool {
+        self.matches(&self.config.schema, path) && !self.is_excluded(path)
+    }
+
+    /// Whether `path` matches the config's document globs, whether or not it
+    /// exists
+    pub fn is_document_file(&self, path: &Path) -> bool {
+        self.matches(&self.config.documents, path)
+            && !self.is_excluded(path)
+            && !self.is_schema_file(path)
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.matches(&self.config.exclude, path)
+    }
+
+    fn matches(&self, globs: &[String], path: &Path) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..glob::MatchOptions::new()
+        };
+
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+
+        globs
+            .iter()
+            .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+            .any(|pattern| pattern.matches_path_with(relative, options))
+    }
+
+    /// The schema files and document files matching the config's globs, each
+    /// sorted
+    fn expand_globs(&mut self) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let schema = self.expand(&self.config.schema.clone(), true);
+        let documents = self
+            .expand(&self.config.documents.clone(), false)
+            .into_iter()
+            .filter(|path| !schema.contains(path))
+            .collect();
+
+        (schema, documents)
+    }
+
+    /// The files matching `globs`, sorted, and without those matching the
+    /// config's `exclude`
+    fn expand(&mut self, globs: &[String], is_schema: bool) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+
+        for pattern in globs {
+            if pattern.starts_with("http://") || pattern.starts_with("https://") {
+                self.diagnostics.push(Diagnostic::new(
+                    format!("fetching a schema over http is not supported: {}", pattern),
+                    None,
+                ));
+                continue;
+            }
+
+            let matches = match glob::glob(&self.root.join(pattern).to_string_lossy()) {
+                Ok(matches) => matches,
+                Err(err) => {
+                    self.diagnostics.push(Diagnostic::new(
+                        format!("invalid glob `{}`: {}", pattern, err),
+                        None,
+                    ));
+                    continue;
+                }
+            };
+
+            let len = paths.len();
+
+            paths.extend(
+                matches
+                    .filter_map(Result::ok)
+                    .filter(|path| path.is_file() && !self.is_excluded(path)),
+            );
+
+            if is_schema && paths.len() == len {
+                self.diagnostics.push(Diagnostic::new(
+                    format!("no schema files match `{}`", pattern),
+                    None,
+                ));
+            }
+        }
+
+        paths.sort();
+        paths.dedup();
+
+        paths
+    }
+
+    fn read_file(&mut self, path: PathBuf) -> Option<ProjectFile> {
+        match fs::read(&path) {
+            Ok(source) => Some(ProjectFile {
+                path,
+                source,
+                document: Document::new(),
+                diagnostics: Vec::new(),
+                syntax_errors: 0,
+            }),
+            Err(err) => {
+                self.diagnostics.push(Diagnostic::new(
+                    format!("failed to read {}: {}", path.display(), err),
+                    None,
+                ));
+                None
+            }
+        }
+    }
+
+    fn read_document(&mut self, path: PathBuf) -> Option<ProjectFile> {
+        let mut file = self.read_file(path)?;
+
+        let (document, errors) =
+            GraphqlParser::parse_with_recovery_and_interner(&file.source, &mut self.interner);
+
+        file.document = document;
+        file.diagnostics = errors
+            .iter()
+            .map(|err| Diagnostic::new(err.to_string(), Some(err.span())))
+            .collect();
+        file.syntax_errors = file.diagnostics.len();
+
+        Some(file)
+    }
+
+    /// Parse the schema files as one document, so that the spans of errors
+    /// can be traced back to the file they were found in
+    fn build_schema(&mut self) {
+        let mut buffer = Vec::new();
+        // where each file is in `buffer`. Introspection results are converted
+        // to SDL first, so spans inside them can't be mapped back to the file
+        let mut ranges = Vec::new();
+
+        for file in &mut self.schema_files {
+            let start = buffer.len();
+
+            if file.path.extension().is_some_and(|ext| ext == "json") {
+                match schema_from_introspection(&file.source, &mut self.interner) {
+                    Ok(sdl) => buffer.extend_from_slice(sdl.as_bytes()),
+                    Err(message) => file.diagnostics.push(Diagnostic::new(message, None)),
+                }
+            } else {
+                buffer.extend_from_slice(&file.source);
+            }
+
+            ranges.push(start..buffer.len());
+            buffer.push(b'\n');
+        }
+
+        let (mut schema, errors) =
+            GraphqlParser::parse_with_recovery_and_interner(&buffer, &mut self.interner);
+
+        let errors = errors
+            .iter()
+            .map(|err| (err.to_string(), Some(err.span())))
+            .chain(
+                schema
+                    .apply_extensions()
+                    .into_iter()
+                    .chain(schema.validate())
+                    .chain(schema.validate_default_values(&self.interner))
+                    .chain(schema.validate_directive_usages(&self.interner))
+                    .map(|err| (err.message(&self.interner), err.span())),
+            )
+            .collect::<Vec<_>>();
+
+        for (message, span) in errors {
+            let idx = span.and_then(|span| {
+                ranges
+                    .iter()
+                    .position(|range| range.start <= span.start && span.start <= range.end)
+            });
+
+            let Some(idx) = idx else {
+                self.diagnostics.push(Diagnostic::new(message, None));
+                continue;
+            };
+
+            let file = &mut self.schema_files[idx];
+
+            let span = span
+                .filter(|_| file.path.extension().is_none_or(|ext| ext != "json"))
+                .map(|span| {
+                    let start = ranges[idx].start;
+                    Span::new(span.start - start, span.end.min(ranges[idx].end) - start)
+                });
+
+            file.diagnostics.push(Diagnostic::new(message, span));
+        }
+
+        self.schema = schema;
+    }
+
+    /// The index of the document file each fragment of the project is
+    /// defined in
+    fn fragment_definitions(&self) -> HashMap<Spur, usize> {
+        let mut definitions = HashMap::new();
+
+        for (idx, file) in self.document_files.iter().enumerate() {
+            for name in file.document.fragments.keys() {
+                definitions.entry(*name).or_insert(idx);
+            }
+        }
+
+        definitions
+    }
+
+    /// The fragments not defined in document file `idx` that it spreads,
+    /// directly or through the fragments they spread, whether or not they are
+    /// defined anywhere
+    fn foreign_fragments(&self, idx: usize, definitions: &HashMap<Spur, usize>) -> Vec<Spur> {
+        let document = &self.document_files[idx].document;
+
+        let mut fragments = Vec::new();
+        let mut seen = HashSet::new();
+        let mut spreads = fragment_spreads(document);
+
+        while let Some(name) = spreads.pop() {
+            if document.fragments.contains_key(&name) || !seen.insert(name) {
+                continue;
+            }
+
+            fragments.push(name);
+
+            if let Some(&other) = definitions.get(&name) {
+                let mut collector = SpreadCollector {
+                    spreads: Vec::new(),
+                };
+                collector.visit_fragment(&self.document_files[other].document.fragments[&name]);
+                spreads.extend(collector.spreads);
+            }
+        }
+
+        fragments
+    }
+
+    /// Validate document files against the schema. Fragments may be defined in
+    /// any document file of the project
+    fn validate_documents(&mut self, indices: Vec<usize>) {
+        let definitions = self.fragment_definitions();
+
+        let spreads = self
+            .document_files
+            .iter()
+            .flat_map(|file| fragment_spreads(&file.document))
+            .collect::<HashSet<_>>();
+
+        for idx in indices {
+            let mut document = self.document_files[idx].document.clone();
+            let mut foreign_fragments = Vec::new();
+
+            for name in self.foreign_fragments(idx, &definitions) {
+                if let Some(&other) = definitions.get(&name) {
+                    let fragment = self.document_files[other].document.fragments[&name].clone();
+                    document.fragments.insert(name, fragment);
+                    foreign_fragments.push(format!("fragment {}", self.interner.resolve(&name)));
+                }
+            }
+
+            let errors = validate_executable_document(&self.schema, &document, &self.interner)
+                .into_iter()
+                // errors in another file's fragments are reported for that
+                // file, and a fragment may be used by other files
+                .filter(|err| {
+                    err.path
+                        .first()
+                        .is_none_or(|first| !foreign_fragments.contains(first))
+                })
+                .filter(|err| match &err.kind {
+                    ValidationErrorKind::UnusedFragment { fragment } => self
+                        .interner
+                        .get(fragment)
+                        .is_none_or(|name| !spreads.contains(&name)),
+                    _ => true,
+                })
+                .map(|err| Diagnostic::new(err.to_string(), Some(err.span)))
+                .collect::<Vec<_>>();
+
+            self.document_files[idx].diagnostics.extend(errors);
+
+            // fragments are shared by every document file, so their names
+            // must be unique across the project, not only within a file
+            let mut duplicates = self.document_files[idx]
+                .document
+                .fragments
+                .values()
+                .filter_map(|fragment| {
+                    let first = definitions[&fragment.name];
+
+                    (first != idx).then(|| {
+                        let path = &self.document_files[first].path;
+
+                        Diagnostic::new(
+                            format!(
+                                "fragment `{}` is already defined in {}",
+                                self.interner.resolve(&fragment.name),
+                                path.strip_prefix(&self.root).unwrap_or(path).display()
+                            ),
+                            Some(fragment.span),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            duplicates.sort_by_key(|diagnostic| diagnostic.span.map(|span| span.start));
+
+            self.document_files[idx].diagnostics.extend(duplicates);
+        }
+    }
+}
+
+fn schema_from_introspection(source: &[u8], interner: &mut Rodeo) -> Result<String, String> {
+    let json = serde_json::from_slice::<JsonValue>(source)
+        .map_err(|err| format!("invalid json: {}", err))?;
+
+    let document = document_from_introspection(&json, interner).map_err(|err| err.to_string())?;
+
+    Ok(print_document(&document, interner))
+}
+
+/// The fragments spread anywhere in `document`
+fn fragment_spreads(document: &Document) -> Vec<Spur> {
+    let mut collector = SpreadCollector {
+        spreads: Vec::new(),
+    };
+
+    visit::walk_document(&mut collector, document);
+
+    collector.spreads
+}
+
+/// The fragments defined or spread in `document`
+fn fragment_names(document: &Document) -> impl Iterator<Item = Spur> + '_ {
+    document
+        .fragments
+        .keys()
+        .copied()
+        .chain(fragment_spreads(document))
+}
+
+struct SpreadCollector {
+    spreads: Vec<Spur>,
+}
+
+impl<'a> Visitor<'a> for SpreadCollector {
+    fn visit_selection(&mut self, selection: &'a Selection) {
+        if let Selection::FragmentSpread { name, .. } = selection {
+            self.spreads.push(*name);
+        }
+
+        visit::walk_selection(self, selection);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use serde_json::json;
+
+    use super::{find_config, Project, ProjectConfig, ProjectErrorKind};
+
+    /// A directory of files that is removed when dropped
+    pub(crate) struct TestDir(pub(crate) PathBuf);
+
+    impl TestDir {
+        pub(crate) fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("graphql-{}-{}", name, std::process::id()));
+
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+
+            let dir = Self(path.canonicalize().unwrap());
+
+            for (name, contents) in files {
+                dir.write(name, contents);
+            }
+
+            dir
+        }
+
+        pub(crate) fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+
+        pub(crate) fn write(&self, name: &str, contents: &str) {
+            let path = self.path(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const CONFIG: &str = "
+schema: schema/*.graphql
+documents: src/**/*.graphql
+exclude: src/generated/*
+";
+
+    fn messages(project: &Project, path: &Path) -> Vec<String> {
+        project
+            .file(path)
+            .unwrap_or_else(|| panic!("no file {}", path.display()))
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn config_projects() {
+        let single = ProjectConfig::from_json(&json!({
+            "schema": "schema.graphql",
+            "documents": ["a/*.graphql", { "b/*.graphql": { "noRequire": true } }],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            single,
+            [ProjectConfig {
+                name: None,
+                schema: vec!["schema.graphql".to_owned()],
+                documents: vec!["a/*.graphql".to_owned(), "b/*.graphql".to_owned()],
+                exclude: Vec::new(),
+            }]
+        );
+
+        let many = ProjectConfig::from_json(&json!({
+            "projects": { "a": { "schema": "a.graphql" }, "b": { "schema": "b.graphql" } },
+        }))
+        .unwrap();
+
+        let names = many
+            .iter()
+            .map(|config| config.name.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(names, [Some("a"), Some("b")]);
+
+        assert!(ProjectConfig::from_json(&json!([])).is_err());
+        assert!(ProjectConfig::from_json(&json!({ "schema": 1 })).is_err());
+        assert!(ProjectConfig::from_json(&json!({ "projects": [] })).is_err());
+    }
+
+    #[test]
+    fn load_and_validate() {
+        let dir = TestDir::new(
+            "project-load",
+            &[
+                (".graphqlrc.yml", CONFIG),
+                ("schema/query.graphql", "type Query { user: User }"),
+                (
+                    "schema/user.graphql",
+                    "type User { id: ID! } extend type Query { me: User }",
+                ),
+                ("src/valid.graphql", "query A { me { ...F } }"),
+                ("src/nested/fragment.graphql", "fragment F on User { id }"),
+                ("src/invalid.graphql", "query B { user { name } }"),
+                ("src/syntax.graphql", "query C {"),
+                ("src/generated/ignored.graphql", "query D { nope }"),
+            ],
+        );
+
+        let project = Project::discover(&dir.path("src/nested"), None).unwrap();
+
+        assert_eq!(project.root, dir.0);
+        assert_eq!(project.schema_files.len(), 2);
+
+        let documents = project
+            .document_files
+            .iter()
+            .map(|file| file.path.strip_prefix(&dir.0).unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            documents,
+            [
+                "src/invalid.graphql",
+                "src/nested/fragment.graphql",
+                "src/syntax.graphql",
+                "src/valid.graphql",
+            ]
+        );
+
+        assert_eq!(
+            messages(&project, &dir.path("src/valid.graphql")),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            messages(&project, &dir.path("src/invalid.graphql")).len(),
+            1
+        );
+        assert_eq!(messages(&project, &dir.path("src/syntax.graphql")).len(), 1);
+        assert!(project.has_diagnostics());
+
+        let invalid = project.file(&dir.path("src/invalid.graphql")).unwrap();
+        let location = invalid.diagnostics[0].location(&invalid.source).unwrap();
+        assert_eq!((location.line, location.column), (1, 18));
+    }
+
+    #[test]
+    fn reload_changed_files() {
+        let dir = TestDir::new(
+            "project-reload",
+            &[
+                (".graphqlrc.yml", CONFIG),
+                ("schema/schema.graphql", "type Query { a: Int }"),
+                ("src/a.graphql", "query A { a b }"),
+                (
+                    "src/b.graphql",
+                    "query B { ...F } fragment F on Query { a }",
+                ),
+                ("src/c.graphql", "query C { a }"),
+            ],
+        );
+
+        let mut project = Project::discover(&dir.0, None).unwrap();
+        assert_eq!(messages(&project, &dir.path("src/a.graphql")).len(), 1);
+
+        // documents only revalidate themselves
+        dir.write("src/a.graphql", "query A { a }");
+        let changed = project.reload(&[dir.path("src/a.graphql")]);
+        assert_eq!(changed, [dir.path("src/a.graphql")]);
+        assert!(!project.has_diagnostics());
+
+        // and documents that use their fragments
+        dir.write("src/d.graphql", "fragment F on Query { a }");
+        let changed = project.reload(&[dir.path("src/d.graphql")]);
+        assert_eq!(
+            changed,
+            [dir.path("src/b.graphql"), dir.path("src/d.graphql")]
+        );
+        assert_eq!(
+            messages(&project, &dir.path("src/d.graphql")),
+            ["fragment `F` is already defined in src/b.graphql"]
+        );
+
+        fs::remove_file(dir.path("src/d.graphql")).unwrap();
+        let changed = project.reload(&[dir.path("src/d.graphql")]);
+        assert_eq!(
+            changed,
+            [dir.path("src/b.graphql"), dir.path("src/d.graphql")]
+        );
+        assert!(project.file(&dir.path("src/d.graphql")).is_none());
+        assert!(!project.has_diagnostics());
+
+        // schema changes revalidate everything
+        dir.write("schema/schema.graphql", "type Query { b: Int }");
+        let changed = project.reload(&[dir.path("schema/schema.graphql")]);
+        assert_eq!(changed.len(), 4);
+        assert_eq!(messages(&project, &dir.path("src/c.graphql")).len(), 1);
+    }
+
+    #[test]
+    fn duplicate_fragments() {
+        let dir = TestDir::new(
+            "project-duplicates",
+            &[
+                (".graphqlrc.yml", CONFIG),
+                ("schema/schema.graphql", "type Query { a: Int }"),
+                (
+                    "src/b.graphql",
+                    "query B { ...F } fragment F on Query { a }",
+                ),
+                ("src/d.graphql", "fragment F on Query { a }"),
+            ],
+        );
+
+        let project = Project::discover(&dir.0, None).unwrap();
+
+        assert_eq!(
+            messages(&project, &dir.path("src/b.graphql")),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            messages(&project, &dir.path("src/d.graphql")),
+            ["fragment `F` is already defined in src/b.graphql"]
+        );
+    }
+
+    #[test]
+    fn missing_config() {
+        let dir = TestDir::new("project-missing", &[("a.graphql", "")]);
+
+        // a config in an ancestor of the temporary directory would be found
+        if find_config(&dir.0).is_none() {
+            let error = Project::discover(&dir.0, None).unwrap_err();
+            assert!(matches!(error.kind, ProjectErrorKind::ConfigNotFound));
+        }
+
+        dir.write(
+            "graphql.config.json",
+            r#"{ "projects": { "a": { "schema": "a.graphql" } } }"#,
+        );
+        let error = Project::discover(&dir.0, Some("b")).unwrap_err();
+        assert!(matches!(error.kind, ProjectErrorKind::UnknownProject(name) if name == "b"));
+    }
+}
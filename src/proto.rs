@@ -0,0 +1,375 @@
+//! Conversion of a schema to Protocol Buffers (proto3) definitions, for
+//! services that share a GraphQL contract with gRPC backends
+//!
+//! Objects and input objects become messages, with fields numbered in the
+//! order they're defined, enums become enums with an unspecified zero value,
+//! and interfaces and unions become messages with a `oneof` of their possible
+//! types. Nullable scalars use the `google.protobuf` wrapper types so that a
+//! missing value can be told apart from the default, and nullable enums are
+//! `optional`. Lists become `repeated` fields, and lists of lists go through
+//! a wrapper message, as protobuf can't nest them directly
+
+use std::collections::BTreeSet;
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{Document, Type, TypeDefinitionRef, TypeKind},
+    codegen::to_snake_case,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct ProtoConfig {
+    /// The package the definitions are declared in, if any
+    pub package: Option<String>,
+}
+
+/// The proto3 definitions of the types of `schema`, in alphabetical order
+pub fn proto_from_document(schema: &Document, interner: &Rodeo, config: &ProtoConfig) -> String {
+    let mut writer = ProtoWriter {
+        schema,
+        interner,
+        uses_wrappers: false,
+        list_messages: BTreeSet::new(),
+        buffer: String::new(),
+    };
+
+    let mut types = schema
+        .types()
+        .filter(|ty| {
+            let name = writer.name(ty.name());
+            !name.starts_with("__") && !matches!(ty, TypeDefinitionRef::Scalar(..))
+        })
+        .collect::<Vec<_>>();
+
+    types.sort_by_key(|ty| writer.name(ty.name()));
+
+    for ty in types {
+        writer.write_type(&ty);
+    }
+
+    writer.write_list_messages();
+
+    let mut header = "syntax = \"proto3\";\n\n".to_owned();
+
+    if let Some(package) = &config.package {
+        header.push_str(&format!("package {};\n\n", package));
+    }
+
+    if writer.uses_wrappers {
+        header.push_str("import \"google/protobuf/wrappers.proto\";\n\n");
+    }
+
+    header + writer.buffer.trim_end() + "\n"
+}
+
+struct ProtoWriter<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+    /// Whether any field uses a `google.protobuf` wrapper type
+    uses_wrappers: bool,
+    /// The item types of the lists nested in other lists, which need a
+    /// wrapper message of their own
+    list_messages: BTreeSet<String>,
+    buffer: String,
+}
+
+impl<'a> ProtoWriter<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn write_comment(&mut self, description: Option<Spur>, indent: &str) {
+        if let Some(description) = description {
+            for line in self.name(description).lines() {
+                self.buffer.push_str(indent);
+                self.buffer.push_str("//");
+
+                if !line.is_empty() {
+                    self.buffer.push(' ');
+                    self.buffer.push_str(line);
+                }
+
+                self.buffer.push('\n');
+            }
+        }
+    }
+
+    fn write_type(&mut self, ty: &TypeDefinitionRef<'_>) {
+        self.write_comment(ty.description(), "");
+
+        let name = self.name(ty.name());
+
+        match ty {
+            TypeDefinitionRef::Object(object) => {
+                let fields = object
+                    .fields
+                    .iter()
+                    .flatten()
+                    .map(|field| (field.name, &field.ty, field.description))
+                    .collect();
+
+                self.write_message(name, fields);
+            }
+            TypeDefinitionRef::Interface(interface) => {
+                let possible_types = self.schema.possible_types(interface.name);
+
+                if possible_types.is_empty() {
+                    let fields = interface
+                        .fields
+                        .iter()
+                        .map(|field| (field.name, &field.ty, field.description))
+                        .collect();
+
+                    self.write_message(name, fields);
+                } else {
+                    self.write_one_of(name, possible_types);
+                }
+            }
+            TypeDefinitionRef::Union(union) => {
+                self.write_one_of(name, union.types.iter().map(|ty| ty.0).collect())
+            }
+            TypeDefinitionRef::Enum(enum_def) => {
+                let prefix = to_snake_case(name).to_ascii_uppercase();
+
+                self.buffer.push_str(&format!(
+                    "enum {} {{\n  {}_UNSPECIFIED = 0;\n",
+                    name, prefix
+                ));
+
+                for (idx, variant) in enum_def.variants.iter().enumerate() {
+                    self.write_comment(variant.description, "  ");
+                    self.buffer.push_str(&format!(
+                        "  {}_{} = {};\n",
+                        prefix,
+                        self.name(variant.name),
+                        idx + 1
+                    ));
+                }
+
+                self.buffer.push_str("}\n\n");
+            }
+            TypeDefinitionRef::InputObject(input) => {
+                let fields = input
+                    .fields
+                    .iter()
+                    .flatten()
+                    .map(|field| (field.name, &field.ty, field.description))
+                    .collect();
+
+                self.write_message(name, fields);
+            }
+            TypeDefinitionRef::Scalar(..) => {}
+        }
+    }
+
+    fn write_message(&mut self, name: &str, fields: Vec<(Spur, &Type, Option<Spur>)>) {
+        self.buffer.push_str(&format!("message {} {{\n", name));
+
+        for (idx, (field, ty, description)) in fields.into_iter().enumerate() {
+            self.write_comment(description, "  ");
+
+            let ty = self.field_type(ty);
+
+            self.buffer.push_str(&format!(
+                "  {} {} = {};\n",
+                ty,
+                to_snake_case(self.name(field)),
+                idx + 1
+            ));
+        }
+
+        self.buffer.push_str("}\n\n");
+    }
+
+    fn write_one_of(&mut self, name: &str, mut types: Vec<Spur>) {
+        types.sort_by_key(|ty| self.name(*ty));
+
+        self.buffer
+            .push_str(&format!("message {} {{\n  oneof value {{\n", name));
+
+        for (idx, ty) in types.into_iter().enumerate() {
+            let ty = self.name(ty);
+
+            self.buffer.push_str(&format!(
+                "    {} {} = {};\n",
+                ty,
+                to_snake_case(ty),
+                idx + 1
+            ));
+        }
+
+        self.buffer.push_str("  }\n}\n\n");
+    }
+
+    /// The type of a field of type `ty`, including its `repeated` or
+    /// `optional` label
+    fn field_type(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Named { name, nullable } => {
+                let is_scalar = self.is_scalar(*name);
+
+                match (is_scalar, *nullable) {
+                    (true, true) => {
+                        self.uses_wrappers = true;
+                        format!("google.protobuf.{}", self.wrapper_type(*name))
+                    }
+                    (true, false) => self.scalar_type(*name).to_owned(),
+                    _ if *nullable && self.schema.type_kind(*name) == Some(TypeKind::Enum) => {
+                        format!("optional {}", self.name(*name))
+                    }
+                    _ => self.name(*name).to_owned(),
+                }
+            }
+            Type::List { base, .. } => format!("repeated {}", self.item_type(base)),
+        }
+    }
+
+    /// The type of the items of a list of `ty`, which protobuf can't make
+    /// nullable or nest in another list directly
+    fn item_type(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Named { name, .. } if self.is_scalar(*name) => self.scalar_type(*name).to_owned(),
+            Type::Named { name, .. } => self.name(*name).to_owned(),
+            Type::List { base, .. } => {
+                let item = self.item_type(base);
+                let message = format!("{}List", to_pascal_case(&item));
+
+                self.list_messages.insert(item);
+
+                message
+            }
+        }
+    }
+
+    fn write_list_messages(&mut self) {
+        for item in std::mem::take(&mut self.list_messages) {
+            self.buffer.push_str(&format!(
+                "message {}List {{\n  repeated {} values = 1;\n}}\n\n",
+                to_pascal_case(&item),
+                item
+            ));
+        }
+    }
+
+    fn is_scalar(&self, name: Spur) -> bool {
+        matches!(
+            self.name(name),
+            "Int" | "Float" | "String" | "ID" | "Boolean"
+        ) || self.schema.type_kind(name) == Some(TypeKind::Scalar)
+    }
+
+    fn scalar_type(&self, name: Spur) -> &'static str {
+        match self.name(name) {
+            "Int" => "int32",
+            "Float" => "double",
+            "Boolean" => "bool",
+            // custom scalars are serialized as strings
+            _ => "string",
+        }
+    }
+
+    fn wrapper_type(&self, name: Spur) -> &'static str {
+        match self.name(name) {
+            "Int" => "Int32Value",
+            "Float" => "DoubleValue",
+            "Boolean" => "BoolValue",
+            _ => "StringValue",
+        }
+    }
+}
+
+/// A protobuf type name, such as `int32` or `StringList`, as the start of a
+/// message name
+fn to_pascal_case(name: &str) -> String {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{proto_from_document, ProtoConfig};
+    use crate::GraphqlParser;
+
+    fn proto(schema: &str, config: &ProtoConfig) -> String {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(schema.as_bytes(), &mut interner).unwrap();
+
+        proto_from_document(&schema, &interner, config)
+    }
+
+    #[test]
+    fn messages_and_enums() {
+        let schema = concat!(
+            "\"A user\" type User { id: ID! userName: String age: Int! role: Role ",
+            "tags: [String!]! grid: [[Int]] joined: Date! } ",
+            "enum Role { ADMIN READ_ONLY } ",
+            "scalar Date ",
+            "input Filter { role: Role! }",
+        );
+
+        let expected = concat!(
+            "syntax = \"proto3\";\n\n",
+            "package example.v1;\n\n",
+            "import \"google/protobuf/wrappers.proto\";\n\n",
+            "message Filter {\n",
+            "  Role role = 1;\n",
+            "}\n\n",
+            "enum Role {\n",
+            "  ROLE_UNSPECIFIED = 0;\n",
+            "  ROLE_ADMIN = 1;\n",
+            "  ROLE_READ_ONLY = 2;\n",
+            "}\n\n",
+            "// A user\n",
+            "message User {\n",
+            "  string id = 1;\n",
+            "  google.protobuf.StringValue user_name = 2;\n",
+            "  int32 age = 3;\n",
+            "  optional Role role = 4;\n",
+            "  repeated string tags = 5;\n",
+            "  repeated Int32List grid = 6;\n",
+            "  string joined = 7;\n",
+            "}\n\n",
+            "message Int32List {\n",
+            "  repeated int32 values = 1;\n",
+            "}\n",
+        );
+
+        let config = ProtoConfig {
+            package: Some("example.v1".to_owned()),
+        };
+
+        assert_eq!(proto(schema, &config), expected);
+    }
+
+    #[test]
+    fn abstract_types() {
+        let proto = proto(
+            concat!(
+                "interface Node { id: ID! } ",
+                "type User implements Node { id: ID! } ",
+                "type Post implements Node { id: ID! } ",
+                "union Result = User",
+            ),
+            &ProtoConfig::default(),
+        );
+
+        assert!(!proto.contains("import"), "{}", proto);
+        assert!(
+            proto.contains(concat!(
+                "message Node {\n  oneof value {\n",
+                "    Post post = 1;\n    User user = 2;\n  }\n}\n\n",
+                "message Post {\n  string id = 1;\n}\n\n",
+                "message Result {\n  oneof value {\n    User user = 1;\n  }\n}",
+            )),
+            "{}",
+            proto
+        );
+    }
+}
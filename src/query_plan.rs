@@ -0,0 +1,1089 @@
+//! Planning of operations against a federated graph. A [`QueryPlan`] splits an
+//! operation into fetches from the subgraphs that resolve its fields, as a
+//! gateway does before executing it
+//!
+//! Root fields are fetched from the first subgraph that defines them. Fields a
+//! subgraph can't resolve are fetched from another subgraph through its
+//! `_entities` field, by a `@key` of the entity that the first subgraph can
+//! provide, along with the fields that the other subgraph's field
+//! `@requires`. Fields that an entity's field `@provides` are resolved by the
+//! subgraph providing them. Fetches run after the fetches they depend on, and
+//! fetches that don't depend on each other run in parallel
+//!
+//! Fetches also select the keys and required fields that later fetches need,
+//! which a gateway leaves out of the response if the operation doesn't select
+//! them. The operation is expected to be valid against the composed schema
+
+use std::fmt;
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{Document, FieldDefinition, Operation, OperationKind, Selection, Span, Type, Value},
+    federation::{Entity, EntityField, Subgraph},
+    fmt::{minify, print_selection_set, print_type, print_value},
+};
+
+#[derive(Debug, Clone)]
+pub struct QueryPlan {
+    /// `None` if the operation selects nothing that a subgraph resolves, such
+    /// as only `__typename`
+    pub node: Option<PlanNode>,
+}
+
+impl QueryPlan {
+    /// The plan as indented text, with selection sets minified
+    pub fn print(&self, interner: &Rodeo) -> String {
+        let mut buffer = String::new();
+
+        if let Some(node) = &self.node {
+            write_node(node, 0, &mut buffer, interner);
+        }
+
+        buffer
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PlanNode {
+    Fetch(FetchNode),
+    /// Nodes that run one after the other
+    Sequence(Vec<PlanNode>),
+    /// Nodes that run at the same time
+    Parallel(Vec<PlanNode>),
+    /// A fetch of entities, whose representations are taken from the objects
+    /// at `path` in the response so far, and whose results are merged into
+    /// them. The path is made of response keys, with `@` for the items of a
+    /// list
+    Flatten {
+        path: Vec<String>,
+        node: Box<PlanNode>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchNode {
+    /// The name of the subgraph to fetch from
+    pub subgraph: String,
+    /// For fetches of entities, the fields of each object to send as its
+    /// representation
+    pub requires: Option<Vec<Selection>>,
+    /// What the fetch selects, on the root type or on the entities
+    pub selection_set: Vec<Selection>,
+    /// The variables of the operation that the fetch uses
+    pub variable_usages: Vec<Spur>,
+    /// The operation sent to the subgraph
+    pub operation: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// No subgraph defines a root type for the kind of operation
+    MissingRootType { kind: OperationKind },
+    /// No subgraph resolves a selected field, or none that the entity can be
+    /// fetched from
+    UnresolvableField { coordinate: String },
+    /// The subgraph `from` can't provide any key of the entity that the
+    /// subgraph `to` resolves it by
+    MissingKey {
+        type_name: String,
+        from: String,
+        to: String,
+    },
+    /// The fields that a field `@requires` can only be fetched after it
+    UnsatisfiableRequires { coordinate: String },
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingRootType { kind } => {
+                write!(f, "no subgraph defines a {} root type", kind.as_str())
+            }
+            Self::UnresolvableField { coordinate } => {
+                write!(f, "no subgraph can resolve `{}`", coordinate)
+            }
+            Self::MissingKey {
+                type_name,
+                from,
+                to,
+            } => write!(
+                f,
+                "subgraph `{}` can't provide a key that subgraph `{}` resolves `{}` by",
+                from, to, type_name
+            ),
+            Self::UnsatisfiableRequires { coordinate } => write!(
+                f,
+                "the fields `{}` requires can't be fetched before it",
+                coordinate
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+/// Plans operations against a fixed set of subgraphs
+pub struct QueryPlanner<'a> {
+    /// In order of preference, for fields that more than one subgraph resolves
+    subgraphs: &'a [Subgraph],
+    typename: Spur,
+}
+
+impl<'a> QueryPlanner<'a> {
+    pub fn new(subgraphs: &'a [Subgraph], interner: &mut Rodeo) -> Self {
+        Self {
+            subgraphs,
+            typename: interner.get_or_intern_static("__typename"),
+        }
+    }
+
+    /// Plan `operation`, which is part of `document`
+    pub fn plan(
+        &self,
+        document: &Document,
+        operation: &Operation,
+        interner: &Rodeo,
+    ) -> Result<QueryPlan, PlanError> {
+        Planning {
+            subgraphs: self.subgraphs,
+            typename: self.typename,
+            document,
+            operation,
+            interner,
+        }
+        .plan()
+    }
+}
+
+/// The selections of a fetch, and the fetches that depend on it. The fetches
+/// of each stage run after those of the stage before
+#[derive(Default)]
+struct Level {
+    selections: Vec<Selection>,
+    stages: Vec<Vec<PlanNode>>,
+}
+
+impl Level {
+    fn add_to_stage(&mut self, stage: usize, nodes: Vec<PlanNode>) {
+        match self.stages.get_mut(stage) {
+            Some(existing) => existing.extend(nodes),
+            None => self.stages.push(nodes),
+        }
+    }
+
+    fn merge_stages(&mut self, stages: Vec<Vec<PlanNode>>) {
+        for (stage, nodes) in stages.into_iter().enumerate() {
+            self.add_to_stage(stage, nodes);
+        }
+    }
+}
+
+/// The fields to fetch from a subgraph as part of an entity
+struct EntityFetch {
+    subgraph: usize,
+    selections: Vec<Selection>,
+    /// The fields that `selections` require from other subgraphs
+    requires: Vec<Selection>,
+}
+
+struct Planning<'a> {
+    subgraphs: &'a [Subgraph],
+    typename: Spur,
+    document: &'a Document,
+    operation: &'a Operation,
+    interner: &'a Rodeo,
+}
+
+impl<'a> Planning<'a> {
+    fn plan(&self) -> Result<QueryPlan, PlanError> {
+        let kind = self.operation.kind;
+
+        let root = self
+            .subgraphs
+            .iter()
+            .find_map(|subgraph| {
+                subgraph
+                    .schema
+                    .root_operation_type_or_default(kind, self.interner)
+            })
+            .ok_or(PlanError::MissingRootType { kind })?;
+
+        // mutation fields run one after the other, so only consecutive
+        // fields are fetched together
+        let mut groups: Vec<(usize, Vec<Selection>)> = Vec::new();
+
+        for selection in self.expand(&self.operation.selection_set, root) {
+            let name = match &selection {
+                Selection::Field { name, .. } if *name != self.typename => *name,
+                // resolved by the gateway
+                _ => continue,
+            };
+
+            let subgraph = (0..self.subgraphs.len())
+                .find(|&subgraph| self.resolves(subgraph, root, name, None))
+                .ok_or_else(|| self.unresolvable(root, name))?;
+
+            let group = match kind {
+                OperationKind::Mutation => groups.last_mut(),
+                _ => groups.iter_mut().find(|(idx, _)| *idx == subgraph),
+            };
+
+            match group {
+                Some((idx, selections)) if *idx == subgraph => selections.push(selection),
+                _ => groups.push((subgraph, vec![selection])),
+            }
+        }
+
+        let mut nodes = Vec::new();
+
+        for (subgraph, selections) in groups {
+            let level = self.plan_level(subgraph, root, &selections, &[], None)?;
+            let fetch = self.fetch(subgraph, None, level.selections);
+
+            nodes.push(sequence(PlanNode::Fetch(fetch), level.stages));
+        }
+
+        let node = match (kind, nodes.len()) {
+            (_, 0) => None,
+            (_, 1) => nodes.pop(),
+            (OperationKind::Mutation, _) => Some(PlanNode::Sequence(nodes)),
+            _ => Some(PlanNode::Parallel(nodes)),
+        };
+
+        Ok(QueryPlan { node })
+    }
+
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn unresolvable(&self, parent: Spur, field: Spur) -> PlanError {
+        PlanError::UnresolvableField {
+            coordinate: format!("{}.{}", self.name(parent), self.name(field)),
+        }
+    }
+
+    fn field_definition(
+        &self,
+        subgraph: usize,
+        parent: Spur,
+        field: Spur,
+    ) -> Option<&'a FieldDefinition> {
+        self.subgraphs[subgraph]
+            .schema
+            .fields_of(parent)?
+            .iter()
+            .find(|definition| definition.name == field)
+    }
+
+    /// Whether `subgraph` resolves `field` of `parent`. External fields are
+    /// only resolved if they're `provided`, or part of a key of the entity
+    fn resolves(
+        &self,
+        subgraph: usize,
+        parent: Spur,
+        field: Spur,
+        provided: Option<&[Selection]>,
+    ) -> bool {
+        if self.field_definition(subgraph, parent, field).is_none() {
+            return false;
+        }
+
+        let entity = match self.subgraphs[subgraph].federation.entity(parent) {
+            Some(entity) => entity,
+            None => return true,
+        };
+
+        let is_external = entity
+            .fields
+            .iter()
+            .any(|entity_field| entity_field.name == field && entity_field.external);
+
+        !is_external
+            || selects(provided.unwrap_or_default(), field)
+            || entity
+                .keys
+                .iter()
+                .any(|key| selects(&key.fields.selection_set, field))
+    }
+
+    fn entity_field<T>(
+        &self,
+        subgraph: usize,
+        parent: Spur,
+        field: Spur,
+        f: impl FnOnce(&'a EntityField) -> Option<T>,
+    ) -> Option<T> {
+        self.subgraphs[subgraph]
+            .federation
+            .entity(parent)?
+            .fields
+            .iter()
+            .find(|entity_field| entity_field.name == field)
+            .and_then(f)
+    }
+
+    /// The fields of the returned entity that `subgraph` resolves through
+    /// `field` of `parent`
+    fn provides(&self, subgraph: usize, parent: Spur, field: Spur) -> Option<&'a [Selection]> {
+        self.entity_field(subgraph, parent, field, |entity_field| {
+            Some(entity_field.provides.as_ref()?.selection_set.as_slice())
+        })
+    }
+
+    /// The fields of `parent` that `subgraph` needs to resolve `field`
+    fn requires(&self, subgraph: usize, parent: Spur, field: Spur) -> &'a [Selection] {
+        self.entity_field(subgraph, parent, field, |entity_field| {
+            Some(entity_field.requires.as_ref()?.selection_set.as_slice())
+        })
+        .unwrap_or_default()
+    }
+
+    fn typename_field(&self) -> Selection {
+        Selection::Field {
+            alias: None,
+            name: self.typename,
+            arguments: None,
+            directives: Vec::new(),
+            selection_set: None,
+            span: Span::default(),
+        }
+    }
+
+    /// `selections`, with fragment spreads replaced by inline fragments, and
+    /// fragments on `parent` replaced by their selections, which take on the
+    /// directives of the fragment
+    fn expand(&self, selections: &[Selection], parent: Spur) -> Vec<Selection> {
+        let mut expanded = Vec::new();
+
+        for selection in selections {
+            let (on, directives, selection_set, span) = match selection {
+                Selection::Field { .. } => {
+                    expanded.push(selection.clone());
+                    continue;
+                }
+                Selection::FragmentSpread {
+                    name,
+                    directives,
+                    span,
+                } => match self.document.fragments.get(name) {
+                    Some(fragment) => (fragment.on, directives, &fragment.selection_set, *span),
+                    None => continue,
+                },
+                Selection::InlineFragment {
+                    on,
+                    directives,
+                    selection_set,
+                    span,
+                } => (on.unwrap_or(parent), directives, selection_set, *span),
+            };
+
+            if on != parent {
+                expanded.push(Selection::InlineFragment {
+                    on: Some(on),
+                    directives: directives.clone(),
+                    selection_set: selection_set.clone(),
+                    span,
+                });
+                continue;
+            }
+
+            for mut selection in self.expand(selection_set, parent) {
+                if let Selection::Field {
+                    directives: inner, ..
+                }
+                | Selection::InlineFragment {
+                    directives: inner, ..
+                } = &mut selection
+                {
+                    inner.extend(directives.iter().cloned());
+                }
+
+                expanded.push(selection);
+            }
+        }
+
+        expanded
+    }
+
+    /// Plan the fetch of `selections` on `parent` from `subgraph`, at `path` in
+    /// the response. `provided` are the fields of `parent` that the subgraph
+    /// resolves here even though they're external
+    fn plan_level(
+        &self,
+        subgraph: usize,
+        parent: Spur,
+        selections: &[Selection],
+        path: &[String],
+        provided: Option<&[Selection]>,
+    ) -> Result<Level, PlanError> {
+        let mut level = Level::default();
+        let mut pending = Vec::new();
+
+        for selection in self.expand(selections, parent) {
+            match selection {
+                Selection::Field { name, .. }
+                    if name != self.typename
+                        && !self.resolves(subgraph, parent, name, provided) =>
+                {
+                    pending.push(selection);
+                }
+                Selection::Field {
+                    alias,
+                    name,
+                    arguments,
+                    directives,
+                    selection_set,
+                    span,
+                } => {
+                    let definition = self.field_definition(subgraph, parent, name);
+
+                    let selection_set = match (selection_set, definition) {
+                        (Some(selection_set), Some(definition)) => {
+                            let mut path = path.to_vec();
+                            path.push(self.name(alias.unwrap_or(name)).to_owned());
+                            path.extend(std::iter::repeat_n(
+                                "@".to_owned(),
+                                list_depth(&definition.ty),
+                            ));
+
+                            let child = self.plan_level(
+                                subgraph,
+                                definition.ty.name(),
+                                &selection_set,
+                                &path,
+                                self.provides(subgraph, parent, name),
+                            )?;
+
+                            level.merge_stages(child.stages);
+
+                            Some(child.selections)
+                        }
+                        (selection_set, _) => selection_set,
+                    };
+
+                    level.selections.push(Selection::Field {
+                        alias,
+                        name,
+                        arguments,
+                        directives,
+                        selection_set,
+                        span,
+                    });
+                }
+                Selection::InlineFragment {
+                    on,
+                    directives,
+                    selection_set,
+                    span,
+                } => {
+                    let on = on.unwrap_or(parent);
+                    let child = self.plan_level(subgraph, on, &selection_set, path, provided)?;
+
+                    level.merge_stages(child.stages);
+                    level.selections.push(Selection::InlineFragment {
+                        on: Some(on),
+                        directives,
+                        selection_set: child.selections,
+                        span,
+                    });
+                }
+                Selection::FragmentSpread { .. } => unreachable!("fragment spreads are expanded"),
+            }
+        }
+
+        if !pending.is_empty() {
+            self.plan_entity_fetches(subgraph, parent, pending, path, provided, &mut level)?;
+        }
+
+        Ok(level)
+    }
+
+    /// Plan fetches of the fields in `pending`, which `subgraph` doesn't
+    /// resolve, from the subgraphs that do. The keys and required fields
+    /// those fetches need are added to `level`
+    fn plan_entity_fetches(
+        &self,
+        subgraph: usize,
+        parent: Spur,
+        mut pending: Vec<Selection>,
+        path: &[String],
+        provided: Option<&[Selection]>,
+        level: &mut Level,
+    ) -> Result<(), PlanError> {
+        let mut groups: Vec<EntityFetch> = Vec::new();
+        let mut idx = 0;
+
+        // fields required from other subgraphs are added to `pending` as
+        // they're found
+        while idx < pending.len() {
+            let name = match &pending[idx] {
+                Selection::Field { name, .. } => *name,
+                _ => unreachable!("only fields are pending"),
+            };
+
+            let target = (0..self.subgraphs.len())
+                .filter(|&target| target != subgraph)
+                .find(|&target| {
+                    self.resolves(target, parent, name, None)
+                        && self.subgraphs[target]
+                            .federation
+                            .entity(parent)
+                            .is_some_and(Entity::is_resolvable)
+                })
+                .ok_or_else(|| self.unresolvable(parent, name))?;
+
+            let requires = self.requires(target, parent, name);
+
+            for required in requires {
+                let required_name = match required {
+                    Selection::Field { name, .. } => *name,
+                    _ => continue,
+                };
+
+                if self.resolves(subgraph, parent, required_name, provided) {
+                    add_selection(&mut level.selections, required.clone());
+                } else if !selects(&pending, required_name) {
+                    pending.push(required.clone());
+                }
+            }
+
+            let selection = pending[idx].clone();
+
+            match groups.iter_mut().find(|group| group.subgraph == target) {
+                Some(group) => {
+                    group.selections.push(selection);
+
+                    for required in requires {
+                        add_selection(&mut group.requires, required.clone());
+                    }
+                }
+                None => groups.push(EntityFetch {
+                    subgraph: target,
+                    selections: vec![selection],
+                    requires: requires.to_vec(),
+                }),
+            }
+
+            idx += 1;
+        }
+
+        let mut available = level
+            .selections
+            .iter()
+            .filter_map(response_key)
+            .collect::<Vec<_>>();
+
+        let mut stage = 0;
+
+        while !groups.is_empty() {
+            let (ready, waiting): (Vec<_>, Vec<_>) = groups.into_iter().partition(|group| {
+                group
+                    .requires
+                    .iter()
+                    .filter_map(response_key)
+                    .all(|name| available.contains(&name))
+            });
+
+            if ready.is_empty() {
+                let field = waiting[0].selections.iter().find_map(response_key);
+
+                return Err(PlanError::UnsatisfiableRequires {
+                    coordinate: format!(
+                        "{}.{}",
+                        self.name(parent),
+                        field.map_or("", |field| self.name(field))
+                    ),
+                });
+            }
+
+            let mut nodes = Vec::new();
+
+            for group in ready {
+                available.extend(group.selections.iter().filter_map(response_key));
+                nodes.push(self.entity_fetch(subgraph, parent, group, path, provided, level)?);
+            }
+
+            level.add_to_stage(stage, nodes);
+
+            groups = waiting;
+            stage += 1;
+        }
+
+        Ok(())
+    }
+
+    fn entity_fetch(
+        &self,
+        subgraph: usize,
+        parent: Spur,
+        group: EntityFetch,
+        path: &[String],
+        provided: Option<&[Selection]>,
+        level: &mut Level,
+    ) -> Result<PlanNode, PlanError> {
+        let target = &self.subgraphs[group.subgraph];
+
+        let key = target
+            .federation
+            .entity(parent)
+            .into_iter()
+            .flat_map(|entity| &entity.keys)
+            .filter(|key| key.resolvable)
+            .find(|key| {
+                key.fields.selection_set.iter().all(|selection| {
+                    response_key(selection)
+                        .is_some_and(|name| self.resolves(subgraph, parent, name, provided))
+                })
+            })
+            .ok_or_else(|| PlanError::MissingKey {
+                type_name: self.name(parent).to_owned(),
+                from: self.subgraphs[subgraph].name.clone(),
+                to: target.name.clone(),
+            })?;
+
+        let mut representation = vec![self.typename_field()];
+
+        add_selection(&mut level.selections, self.typename_field());
+
+        for selection in &key.fields.selection_set {
+            add_selection(&mut level.selections, selection.clone());
+            add_selection(&mut representation, selection.clone());
+        }
+
+        for selection in group.requires {
+            add_selection(&mut representation, selection);
+        }
+
+        let entity_level =
+            self.plan_level(group.subgraph, parent, &group.selections, path, None)?;
+
+        let on = |selection_set| {
+            vec![Selection::InlineFragment {
+                on: Some(parent),
+                directives: Vec::new(),
+                selection_set,
+                span: Span::default(),
+            }]
+        };
+
+        let fetch = self.fetch(
+            group.subgraph,
+            Some(on(representation)),
+            on(entity_level.selections),
+        );
+
+        let node = PlanNode::Flatten {
+            path: path.to_vec(),
+            node: Box::new(PlanNode::Fetch(fetch)),
+        };
+
+        Ok(sequence(node, entity_level.stages))
+    }
+
+    fn fetch(
+        &self,
+        subgraph: usize,
+        requires: Option<Vec<Selection>>,
+        selection_set: Vec<Selection>,
+    ) -> FetchNode {
+        let mut variable_usages = Vec::new();
+        collect_variables(&selection_set, &mut variable_usages);
+
+        let mut variables = self
+            .operation
+            .variable_definitions
+            .iter()
+            .filter(|variable| variable_usages.contains(&variable.name))
+            .map(|variable| {
+                let mut definition = format!(
+                    "${}: {}",
+                    self.name(variable.name),
+                    print_type(&variable.ty, self.interner)
+                );
+
+                if let Some(default) = &variable.default {
+                    definition.push_str(" = ");
+                    definition.push_str(&print_value(default, self.interner));
+                }
+
+                definition
+            })
+            .collect::<Vec<_>>();
+
+        let selections = print_selection_set(&selection_set, self.interner);
+
+        let operation = if requires.is_some() {
+            variables.insert(0, "$representations: [_Any!]!".to_owned());
+
+            format!(
+                "query({}) {{ _entities(representations: $representations) {} }}",
+                variables.join(", "),
+                selections
+            )
+        } else if variables.is_empty() && self.operation.kind == OperationKind::Query {
+            selections
+        } else if variables.is_empty() {
+            format!("{} {}", self.operation.kind.as_str(), selections)
+        } else {
+            format!(
+                "{}({}) {}",
+                self.operation.kind.as_str(),
+                variables.join(", "),
+                selections
+            )
+        };
+
+        FetchNode {
+            subgraph: self.subgraphs[subgraph].name.clone(),
+            requires,
+            selection_set,
+            variable_usages,
+            operation: minify(&operation),
+        }
+    }
+}
+
+/// `first`, followed by each stage of the nodes that depend on it. Sequences
+/// nested directly in the sequence are merged into it
+fn sequence(first: PlanNode, stages: Vec<Vec<PlanNode>>) -> PlanNode {
+    if stages.is_empty() {
+        return first;
+    }
+
+    let mut nodes = vec![first];
+
+    for mut stage in stages {
+        match stage.len() {
+            1 => match stage.pop().unwrap() {
+                PlanNode::Sequence(sequence) => nodes.extend(sequence),
+                node => nodes.push(node),
+            },
+            _ => nodes.push(PlanNode::Parallel(stage)),
+        }
+    }
+
+    PlanNode::Sequence(nodes)
+}
+
+fn response_key(selection: &Selection) -> Option<Spur> {
+    match selection {
+        Selection::Field { alias, name, .. } => Some(alias.unwrap_or(*name)),
+        _ => None,
+    }
+}
+
+/// Whether `selections` selects the field `name` without an alias
+fn selects(selections: &[Selection], name: Spur) -> bool {
+    selections
+        .iter()
+        .any(|selection| response_key(selection) == Some(name))
+}
+
+/// Add `selection` to `selections`, unless a field with the same response key
+/// is already selected
+fn add_selection(selections: &mut Vec<Selection>, selection: Selection) {
+    match response_key(&selection) {
+        Some(key) if selects(selections, key) => {}
+        _ => selections.push(selection),
+    }
+}
+
+fn list_depth(ty: &Type) -> usize {
+    match ty {
+        Type::Named { .. } => 0,
+        Type::List { base, .. } => 1 + list_depth(base),
+    }
+}
+
+/// The variables used in `selections`, in the order they're first used
+fn collect_variables(selections: &[Selection], variables: &mut Vec<Spur>) {
+    for selection in selections {
+        let (arguments, directives, selection_set) = match selection {
+            Selection::Field {
+                arguments,
+                directives,
+                selection_set,
+                ..
+            } => (
+                arguments.as_deref().unwrap_or_default(),
+                directives,
+                selection_set.as_deref().unwrap_or_default(),
+            ),
+            Selection::InlineFragment {
+                directives,
+                selection_set,
+                ..
+            } => (&[][..], directives, selection_set.as_slice()),
+            Selection::FragmentSpread { directives, .. } => (&[][..], directives, &[][..]),
+        };
+
+        let values = arguments
+            .iter()
+            .chain(
+                directives
+                    .iter()
+                    .flat_map(|directive| directive.arguments.iter().flatten()),
+            )
+            .map(|argument| &argument.value);
+
+        for value in values {
+            collect_value_variables(value, variables);
+        }
+
+        collect_variables(selection_set, variables);
+    }
+}
+
+fn collect_value_variables(value: &Value, variables: &mut Vec<Spur>) {
+    match value {
+        Value::Variable(name) if !variables.contains(name) => variables.push(*name),
+        Value::List(values) => {
+            for value in values {
+                collect_value_variables(value, variables);
+            }
+        }
+        Value::Object(fields) => {
+            for value in fields.values() {
+                collect_value_variables(value, variables);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn write_node(node: &PlanNode, depth: usize, buffer: &mut String, interner: &Rodeo) {
+    let indent = "  ".repeat(depth);
+    let minified =
+        |selection_set: &[Selection]| minify(&print_selection_set(selection_set, interner));
+
+    match node {
+        PlanNode::Fetch(fetch) => {
+            buffer.push_str(&format!("{}Fetch({}) ", indent, fetch.subgraph));
+
+            if let Some(requires) = &fetch.requires {
+                buffer.push_str(&minified(requires));
+                buffer.push_str(" => ");
+            }
+
+            buffer.push_str(&minified(&fetch.selection_set));
+            buffer.push('\n');
+        }
+        PlanNode::Sequence(nodes) | PlanNode::Parallel(nodes) => {
+            let name = match node {
+                PlanNode::Sequence(..) => "Sequence",
+                _ => "Parallel",
+            };
+
+            buffer.push_str(&format!("{}{} {{\n", indent, name));
+
+            for node in nodes {
+                write_node(node, depth + 1, buffer, interner);
+            }
+
+            buffer.push_str(&format!("{}}}\n", indent));
+        }
+        PlanNode::Flatten { path, node } => {
+            buffer.push_str(&format!("{}Flatten({}) {{\n", indent, path.join(".")));
+            write_node(node, depth + 1, buffer, interner);
+            buffer.push_str(&format!("{}}}\n", indent));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{PlanError, QueryPlanner};
+    use crate::{federation::Subgraph, GraphqlParser};
+
+    const FEDERATION_2: &str = concat!(
+        "extend schema @link(url: \"https://specs.apollo.dev/federation/v2.3\", ",
+        "import: [\"@key\", \"@external\", \"@requires\", \"@provides\"]) ",
+    );
+
+    fn subgraphs(interner: &mut Rodeo) -> Vec<Subgraph> {
+        let schemas = [
+            (
+                "accounts",
+                concat!(
+                    "type Query { me: User } ",
+                    "type User @key(fields: \"id\") { id: ID! name: String username: String }",
+                ),
+            ),
+            (
+                "products",
+                concat!(
+                    "type Query { topProducts(first: Int = 5): [Product] } ",
+                    "type Product @key(fields: \"upc\") { upc: String! name: String ",
+                    "price: Int weight: Int }",
+                ),
+            ),
+            (
+                "inventory",
+                concat!(
+                    "type Product @key(fields: \"upc\") { upc: String! ",
+                    "weight: Int @external price: Int @external inStock: Boolean ",
+                    "shippingEstimate: Int @requires(fields: \"price weight\") }",
+                ),
+            ),
+            (
+                "reviews",
+                concat!(
+                    "type Review @key(fields: \"id\") { id: ID! body: String ",
+                    "author: User @provides(fields: \"username\") product: Product } ",
+                    "type User @key(fields: \"id\") { id: ID! username: String @external ",
+                    "reviews: [Review] } ",
+                    "type Product @key(fields: \"upc\") { upc: String! reviews: [Review] }",
+                ),
+            ),
+        ];
+
+        schemas
+            .into_iter()
+            .map(|(name, schema)| {
+                let document = GraphqlParser::parse_with_interner(
+                    format!("{}{}", FEDERATION_2, schema).as_bytes(),
+                    interner,
+                )
+                .unwrap();
+
+                let (subgraph, errors) = Subgraph::new(name, document, interner);
+                assert!(errors.is_empty(), "{:?}", errors);
+
+                subgraph
+            })
+            .collect()
+    }
+
+    fn plan(operation: &str) -> Result<String, PlanError> {
+        let mut interner = Rodeo::default();
+        let subgraphs = subgraphs(&mut interner);
+        let planner = QueryPlanner::new(&subgraphs, &mut interner);
+
+        let document =
+            GraphqlParser::parse_with_interner(operation.as_bytes(), &mut interner).unwrap();
+        let operation = document.operations.values().next().unwrap();
+
+        planner
+            .plan(&document, operation, &interner)
+            .map(|plan| plan.print(&interner))
+    }
+
+    #[test]
+    fn entity_fetches() {
+        let plan = plan(concat!(
+            "query($n: Int) { me { name reviews { body author { username name } ",
+            "product { name } } } topProducts(first: $n) { name inStock } }",
+        ));
+
+        assert_eq!(
+            plan.unwrap(),
+            concat!(
+                "Parallel {\n",
+                "  Sequence {\n",
+                "    Fetch(accounts) {me{name __typename id}}\n",
+                "    Flatten(me) {\n",
+                "      Fetch(reviews) {...on User{__typename id}} => ",
+                "{...on User{reviews{body author{username __typename id}",
+                "product{__typename upc}}}}\n",
+                "    }\n",
+                "    Parallel {\n",
+                "      Flatten(me.reviews.@.author) {\n",
+                "        Fetch(accounts) {...on User{__typename id}} => {...on User{name}}\n",
+                "      }\n",
+                "      Flatten(me.reviews.@.product) {\n",
+                "        Fetch(products) {...on Product{__typename upc}} => ",
+                "{...on Product{name}}\n",
+                "      }\n",
+                "    }\n",
+                "  }\n",
+                "  Sequence {\n",
+                "    Fetch(products) {topProducts(first:$n){name __typename upc}}\n",
+                "    Flatten(topProducts.@) {\n",
+                "      Fetch(inventory) {...on Product{__typename upc}} => ",
+                "{...on Product{inStock}}\n",
+                "    }\n",
+                "  }\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn requires() {
+        let mut interner = Rodeo::default();
+        let subgraphs = subgraphs(&mut interner);
+        let planner = QueryPlanner::new(&subgraphs, &mut interner);
+
+        let document = GraphqlParser::parse_with_interner(
+            b"query Top($n: Int) { topProducts(first: $n) { shippingEstimate } }",
+            &mut interner,
+        )
+        .unwrap();
+        let operation = document.operations.values().next().unwrap();
+
+        let plan = planner.plan(&document, operation, &interner).unwrap();
+
+        assert_eq!(
+            plan.print(&interner),
+            concat!(
+                "Sequence {\n",
+                "  Fetch(products) {topProducts(first:$n){price weight __typename upc}}\n",
+                "  Flatten(topProducts.@) {\n",
+                "    Fetch(inventory) {...on Product{__typename upc price weight}} => ",
+                "{...on Product{shippingEstimate}}\n",
+                "  }\n",
+                "}\n",
+            )
+        );
+
+        let super::PlanNode::Sequence(nodes) = plan.node.unwrap() else {
+            panic!("expected a sequence");
+        };
+        let super::PlanNode::Fetch(root) = &nodes[0] else {
+            panic!("expected a fetch");
+        };
+
+        assert_eq!(
+            root.operation,
+            "query($n:Int){topProducts(first:$n){price weight __typename upc}}"
+        );
+    }
+
+    #[test]
+    fn requires_from_another_entity_fetch() {
+        let plan = plan("{ me { reviews { product { shippingEstimate } } } }");
+
+        assert_eq!(
+            plan.unwrap(),
+            concat!(
+                "Sequence {\n",
+                "  Fetch(accounts) {me{__typename id}}\n",
+                "  Flatten(me) {\n",
+                "    Fetch(reviews) {...on User{__typename id}} => ",
+                "{...on User{reviews{product{__typename upc}}}}\n",
+                "  }\n",
+                "  Flatten(me.reviews.@.product) {\n",
+                "    Fetch(products) {...on Product{__typename upc}} => ",
+                "{...on Product{price weight}}\n",
+                "  }\n",
+                "  Flatten(me.reviews.@.product) {\n",
+                "    Fetch(inventory) {...on Product{__typename upc price weight}} => ",
+                "{...on Product{shippingEstimate}}\n",
+                "  }\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn unresolvable_fields() {
+        assert_eq!(
+            plan("{ me { email } }").unwrap_err().to_string(),
+            "no subgraph can resolve `User.email`"
+        );
+    }
+}
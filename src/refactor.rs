@@ -0,0 +1,792 @@
+//! Refactorings of documents, for editor actions and schema migration
+//! scripts. Each refactoring checks that it leaves the document consistent
+//! before changing anything, and applies to the type system definitions and
+//! the operations and fragments of the document alike
+//!
+//! Operations and fragments kept in other documents can be updated with
+//! [`Document::rename_type_references`] and
+//! [`Document::rename_field_selections`]
+//!
+//! Type extensions should have been applied with
+//! [`Document::apply_extensions`] first, as fields defined by extensions
+//! aren't found
+
+use std::{collections::HashMap, mem};
+
+use lasso::{Rodeo, Spur};
+
+use crate::ast::{Document, FieldDefinition, NamedType, Selection, Type, TypeExtension, TypeKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefactorError {
+    UnknownType(Spur),
+    UnknownField {
+        ty: Spur,
+        field: Spur,
+    },
+    /// A type is already defined with the new name
+    TypeTaken(Spur),
+    /// `ty` already has a field with the new name
+    FieldTaken {
+        ty: Spur,
+        field: Spur,
+    },
+    /// `field` of `ty` is required by `interface`, so it must be changed on
+    /// the interface instead
+    InterfaceField {
+        ty: Spur,
+        field: Spur,
+        interface: Spur,
+    },
+    /// A field can't be added to an interface that `ty` implements, as `ty`
+    /// doesn't have it
+    NotImplemented {
+        ty: Spur,
+        field: Spur,
+    },
+    /// A field's type must be a scalar, object, interface, union or enum
+    NotOutputType(Spur),
+    /// An operation or fragment of the document selects the field
+    FieldInUse {
+        ty: Spur,
+        field: Spur,
+    },
+}
+
+impl RefactorError {
+    /// A human readable description of this error, resolving names through
+    /// the interner the document was parsed with
+    pub fn message(&self, interner: &Rodeo) -> String {
+        let name = |name: &Spur| interner.resolve(name);
+
+        match self {
+            Self::UnknownType(ty) => format!("unknown type `{}`", name(ty)),
+            Self::UnknownField { ty, field } => {
+                format!("`{}` has no field `{}`", name(ty), name(field))
+            }
+            Self::TypeTaken(ty) => format!("type `{}` is already defined", name(ty)),
+            Self::FieldTaken { ty, field } => {
+                format!("`{}` already has a field `{}`", name(ty), name(field))
+            }
+            Self::InterfaceField {
+                ty,
+                field,
+                interface,
+            } => format!(
+                "`{}.{}` is required by the interface `{}`, so it must be changed there",
+                name(ty),
+                name(field),
+                name(interface)
+            ),
+            Self::NotImplemented { ty, field } => format!(
+                "`{}` implements the interface, but has no field `{}`",
+                name(ty),
+                name(field)
+            ),
+            Self::NotOutputType(ty) => format!("`{}` is not an output type", name(ty)),
+            Self::FieldInUse { ty, field } => format!(
+                "`{}.{}` is selected by an operation or fragment",
+                name(ty),
+                name(field)
+            ),
+        }
+    }
+}
+
+impl Document {
+    /// Rename the type `old` to `new`, along with every reference to it
+    pub fn rename_type(&mut self, old: Spur, new: Spur) -> Result<(), RefactorError> {
+        if self.get_type(old).is_none() {
+            return Err(RefactorError::UnknownType(old));
+        }
+
+        if self.type_kind(new).is_some() {
+            return Err(RefactorError::TypeTaken(new));
+        }
+
+        fn rekey<T>(
+            types: &mut HashMap<Spur, T>,
+            old: Spur,
+            new: Spur,
+            name: fn(&mut T) -> &mut Spur,
+        ) {
+            if let Some(mut ty) = types.remove(&old) {
+                *name(&mut ty) = new;
+                types.insert(new, ty);
+            }
+        }
+
+        rekey(&mut self.scalars, old, new, |ty| &mut ty.name);
+        rekey(&mut self.output_objects, old, new, |ty| &mut ty.name);
+        rekey(&mut self.interfaces, old, new, |ty| &mut ty.name);
+        rekey(&mut self.unions, old, new, |ty| &mut ty.name);
+        rekey(&mut self.enums, old, new, |ty| &mut ty.name);
+        rekey(&mut self.input_objects, old, new, |ty| &mut ty.name);
+
+        for extension in &mut self.type_extensions {
+            let name = match extension {
+                TypeExtension::Scalar(ty) => &mut ty.name,
+                TypeExtension::Object(ty) => &mut ty.name,
+                TypeExtension::Interface(ty) => &mut ty.name,
+                TypeExtension::Union(ty) => &mut ty.name,
+                TypeExtension::Enum(ty) => &mut ty.name,
+                TypeExtension::InputObject(ty) => &mut ty.name,
+            };
+
+            if *name == old {
+                *name = new;
+            }
+        }
+
+        self.rename_type_references(old, new);
+
+        Ok(())
+    }
+
+    /// Rename every reference to the type `old`, without renaming its
+    /// definition: the types of fields, arguments, input fields and
+    /// variables, union members, implemented interfaces, root operation types
+    /// and type conditions
+    pub fn rename_type_references(&mut self, old: Spur, new: Spur) {
+        let rename_named = |ty: &mut NamedType| {
+            if ty.0 == old {
+                ty.0 = new;
+            }
+        };
+
+        for operation_type in self
+            .schema
+            .iter_mut()
+            .flat_map(|schema| &mut schema.operation_types)
+            .chain(
+                self.schema_extensions
+                    .iter_mut()
+                    .flat_map(|extension| &mut extension.operation_types),
+            )
+        {
+            rename_named(&mut operation_type.ty);
+        }
+
+        for object in self.output_objects.values_mut() {
+            object.implements.iter_mut().for_each(rename_named);
+            rename_field_types(object.fields.as_deref_mut().unwrap_or_default(), old, new);
+        }
+
+        for interface in self.interfaces.values_mut() {
+            interface.implements.iter_mut().for_each(rename_named);
+            rename_field_types(&mut interface.fields, old, new);
+        }
+
+        for union in self.unions.values_mut() {
+            union.types.iter_mut().for_each(rename_named);
+        }
+
+        let input_fields = self
+            .input_objects
+            .values_mut()
+            .flat_map(|input| input.fields.iter_mut().flatten())
+            .chain(
+                self.directive_definitions
+                    .values_mut()
+                    .flat_map(|directive| directive.arguments.iter_mut().flatten()),
+            );
+
+        for field in input_fields {
+            rename_type(&mut field.ty, old, new);
+        }
+
+        for extension in &mut self.type_extensions {
+            match extension {
+                TypeExtension::Object(object) => {
+                    object.implements.iter_mut().for_each(rename_named);
+                    rename_field_types(object.fields.as_deref_mut().unwrap_or_default(), old, new);
+                }
+                TypeExtension::Interface(interface) => {
+                    interface.implements.iter_mut().for_each(rename_named);
+                    rename_field_types(&mut interface.fields, old, new);
+                }
+                TypeExtension::Union(union) => union.types.iter_mut().for_each(rename_named),
+                TypeExtension::InputObject(input) => {
+                    for field in input.fields.iter_mut().flatten() {
+                        rename_type(&mut field.ty, old, new);
+                    }
+                }
+                TypeExtension::Scalar(..) | TypeExtension::Enum(..) => {}
+            }
+        }
+
+        for operation in self.operations.values_mut() {
+            for variable in &mut operation.variable_definitions {
+                rename_type(&mut variable.ty, old, new);
+            }
+
+            rename_type_conditions(&mut operation.selection_set, old, new);
+        }
+
+        for fragment in self.fragments.values_mut() {
+            if fragment.on == old {
+                fragment.on = new;
+            }
+
+            rename_type_conditions(&mut fragment.selection_set, old, new);
+        }
+    }
+
+    /// Rename the field `old` of the object type or interface `ty` to `new`,
+    /// along with its selections. Renaming a field of an interface renames it
+    /// in the types implementing the interface too
+    pub fn rename_field(
+        &mut self,
+        ty: Spur,
+        old: Spur,
+        new: Spur,
+        interner: &Rodeo,
+    ) -> Result<(), RefactorError> {
+        self.check_field(ty, old)?;
+
+        let targets = self.implementors(ty);
+
+        if let Some(&ty) = targets.iter().find(|&&ty| self.field(ty, new).is_some()) {
+            return Err(RefactorError::FieldTaken { ty, field: new });
+        }
+
+        let mut executable = self.take_executable();
+        executable.rename_field_selections(self, ty, old, new, interner);
+        self.restore_executable(executable);
+
+        for target in targets {
+            if let Some(field) = self.field_mut(target, old) {
+                field.name = new;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rename the selections of the field `old` of `ty`, or of any type
+    /// implementing `ty`, to `new`, resolving the types of selections
+    /// through `schema`, which still has the field `old`
+    pub fn rename_field_selections(
+        &mut self,
+        schema: &Document,
+        ty: Spur,
+        old: Spur,
+        new: Spur,
+        interner: &Rodeo,
+    ) {
+        let targets = schema.implementors(ty);
+
+        for_each_field_selection(self, schema, interner, &mut |parent, name| {
+            if *name == old && targets.contains(&parent) {
+                *name = new;
+            }
+        });
+    }
+
+    /// Add `field` to the object type or interface `ty`. Every type
+    /// implementing an interface must already have the field
+    pub fn add_field(
+        &mut self,
+        ty: Spur,
+        field: FieldDefinition,
+        interner: &Rodeo,
+    ) -> Result<(), RefactorError> {
+        if self.fields_of(ty).is_none() {
+            return Err(RefactorError::UnknownType(ty));
+        }
+
+        if self.field(ty, field.name).is_some() {
+            return Err(RefactorError::FieldTaken {
+                ty,
+                field: field.name,
+            });
+        }
+
+        self.check_output_type(&field.ty, interner)?;
+
+        for argument in field.arguments.iter().flatten() {
+            let name = argument.ty.name();
+
+            if !self.is_defined(name, interner) {
+                return Err(RefactorError::UnknownType(name));
+            }
+        }
+
+        if let Some(&implementor) = self.implementors(ty).iter().find(|&&implementor| {
+            implementor != ty && self.field(implementor, field.name).is_none()
+        }) {
+            return Err(RefactorError::NotImplemented {
+                ty: implementor,
+                field: field.name,
+            });
+        }
+
+        if let Some(object) = self.output_objects.get_mut(&ty) {
+            object.fields.get_or_insert_with(Vec::new).push(field);
+        } else if let Some(interface) = self.interfaces.get_mut(&ty) {
+            interface.fields.push(field);
+        }
+
+        Ok(())
+    }
+
+    /// Remove the field `name` from `ty`, and return it. The field can't be
+    /// selected by the document's operations or fragments. Removing a field
+    /// of an interface leaves the types implementing it unchanged
+    pub fn remove_field(
+        &mut self,
+        ty: Spur,
+        name: Spur,
+        interner: &Rodeo,
+    ) -> Result<FieldDefinition, RefactorError> {
+        self.check_field(ty, name)?;
+
+        if self.is_selected(ty, name, interner) {
+            return Err(RefactorError::FieldInUse { ty, field: name });
+        }
+
+        let fields = match self.output_objects.get_mut(&ty) {
+            Some(object) => object.fields.get_or_insert_with(Vec::new),
+            None => &mut self.interfaces.get_mut(&ty).unwrap().fields,
+        };
+
+        let idx = fields.iter().position(|field| field.name == name).unwrap();
+
+        Ok(fields.remove(idx))
+    }
+
+    /// Change the type of the field `field` of `ty` to `new_ty`, and return
+    /// its old type. Changing the type of a field of an interface changes it
+    /// in the types implementing the interface too
+    ///
+    /// If the named type changes, the field can't be selected by the
+    /// document's operations or fragments, as their selection sets would no
+    /// longer apply
+    pub fn set_field_type(
+        &mut self,
+        ty: Spur,
+        field: Spur,
+        new_ty: Type,
+        interner: &Rodeo,
+    ) -> Result<Type, RefactorError> {
+        self.check_field(ty, field)?;
+        self.check_output_type(&new_ty, interner)?;
+
+        let old_ty = self.field(ty, field).unwrap().ty.clone();
+
+        if old_ty.name() != new_ty.name() && self.is_selected(ty, field, interner) {
+            return Err(RefactorError::FieldInUse { ty, field });
+        }
+
+        for target in self.implementors(ty) {
+            if let Some(definition) = self.field_mut(target, field) {
+                definition.ty = new_ty.clone();
+            }
+        }
+
+        Ok(old_ty)
+    }
+
+    /// Check that `ty` has the field `field`, and that it isn't required by
+    /// an interface `ty` implements
+    fn check_field(&self, ty: Spur, field: Spur) -> Result<(), RefactorError> {
+        let implements = match (self.output_objects.get(&ty), self.interfaces.get(&ty)) {
+            (Some(object), _) => &object.implements,
+            (None, Some(interface)) => &interface.implements,
+            (None, None) => return Err(RefactorError::UnknownType(ty)),
+        };
+
+        if self.field(ty, field).is_none() {
+            return Err(RefactorError::UnknownField { ty, field });
+        }
+
+        if let Some(interface) = implements
+            .iter()
+            .find(|interface| self.field(interface.0, field).is_some())
+        {
+            return Err(RefactorError::InterfaceField {
+                ty,
+                field,
+                interface: interface.0,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn check_output_type(&self, ty: &Type, interner: &Rodeo) -> Result<(), RefactorError> {
+        let name = ty.name();
+
+        if !self.is_defined(name, interner) {
+            return Err(RefactorError::UnknownType(name));
+        }
+
+        if self.type_kind(name) == Some(TypeKind::InputObject) {
+            return Err(RefactorError::NotOutputType(name));
+        }
+
+        Ok(())
+    }
+
+    /// Whether `name` is a type of this document or a built-in scalar
+    fn is_defined(&self, name: Spur, interner: &Rodeo) -> bool {
+        self.type_kind(name).is_some()
+            || matches!(
+                interner.resolve(&name),
+                "Int" | "Float" | "String" | "Boolean" | "ID"
+            )
+    }
+
+    fn field(&self, ty: Spur, name: Spur) -> Option<&FieldDefinition> {
+        self.fields_of(ty)?.iter().find(|field| field.name == name)
+    }
+
+    fn field_mut(&mut self, ty: Spur, name: Spur) -> Option<&mut FieldDefinition> {
+        let fields = match self.output_objects.get_mut(&ty) {
+            Some(object) => object.fields.as_deref_mut().unwrap_or_default(),
+            None => &mut self.interfaces.get_mut(&ty)?.fields,
+        };
+
+        fields.iter_mut().find(|field| field.name == name)
+    }
+
+    /// `ty`, followed by the object types and interfaces that implement it
+    /// if it's an interface
+    fn implementors(&self, ty: Spur) -> Vec<Spur> {
+        let mut implementors = vec![ty];
+
+        if self.interfaces.contains_key(&ty) {
+            implementors.extend(
+                self.output_objects
+                    .values()
+                    .filter(|object| object.implements.iter().any(|interface| interface.0 == ty))
+                    .map(|object| object.name),
+            );
+            implementors.extend(
+                self.interfaces
+                    .values()
+                    .filter(|interface| interface.implements.iter().any(|other| other.0 == ty))
+                    .map(|interface| interface.name),
+            );
+        }
+
+        implementors
+    }
+
+    /// Whether the document's operations or fragments select the field
+    /// `field` of `ty`
+    fn is_selected(&mut self, ty: Spur, field: Spur, interner: &Rodeo) -> bool {
+        let mut executable = self.take_executable();
+        let mut is_selected = false;
+
+        for_each_field_selection(&mut executable, self, interner, &mut |parent, name| {
+            is_selected |= parent == ty && *name == field;
+        });
+
+        self.restore_executable(executable);
+
+        is_selected
+    }
+
+    /// Move the operations and fragments of this document into a document of
+    /// their own, so they can be changed while this one is read as a schema
+    fn take_executable(&mut self) -> Document {
+        Document {
+            operations: mem::take(&mut self.operations),
+            fragments: mem::take(&mut self.fragments),
+            ..Document::new()
+        }
+    }
+
+    fn restore_executable(&mut self, executable: Document) {
+        self.operations = executable.operations;
+        self.fragments = executable.fragments;
+    }
+}
+
+fn rename_type(ty: &mut Type, old: Spur, new: Spur) {
+    match ty {
+        Type::Named { name, .. } => {
+            if *name == old {
+                *name = new;
+            }
+        }
+        Type::List { base, .. } => rename_type(base, old, new),
+    }
+}
+
+fn rename_field_types(fields: &mut [FieldDefinition], old: Spur, new: Spur) {
+    for field in fields {
+        rename_type(&mut field.ty, old, new);
+
+        for argument in field.arguments.iter_mut().flatten() {
+            rename_type(&mut argument.ty, old, new);
+        }
+    }
+}
+
+fn rename_type_conditions(selection_set: &mut [Selection], old: Spur, new: Spur) {
+    for selection in selection_set {
+        match selection {
+            Selection::Field { selection_set, .. } => {
+                rename_type_conditions(selection_set.as_deref_mut().unwrap_or_default(), old, new);
+            }
+            Selection::InlineFragment {
+                on, selection_set, ..
+            } => {
+                if *on == Some(old) {
+                    *on = Some(new);
+                }
+
+                rename_type_conditions(selection_set, old, new);
+            }
+            Selection::FragmentSpread { .. } => {}
+        }
+    }
+}
+
+/// Call `f` with the type each field selected by the operations and
+/// fragments of `document` is selected on, and the field's name, which `f`
+/// may change. Types are resolved through `schema`
+fn for_each_field_selection(
+    document: &mut Document,
+    schema: &Document,
+    interner: &Rodeo,
+    f: &mut dyn FnMut(Spur, &mut Spur),
+) {
+    for operation in document.operations.values_mut() {
+        if let Some(root) = schema.root_operation_type_or_default(operation.kind, interner) {
+            walk_field_selections(&mut operation.selection_set, root, schema, f);
+        }
+    }
+
+    for fragment in document.fragments.values_mut() {
+        walk_field_selections(&mut fragment.selection_set, fragment.on, schema, f);
+    }
+}
+
+fn walk_field_selections(
+    selection_set: &mut [Selection],
+    parent: Spur,
+    schema: &Document,
+    f: &mut dyn FnMut(Spur, &mut Spur),
+) {
+    for selection in selection_set {
+        match selection {
+            Selection::Field {
+                name,
+                selection_set,
+                ..
+            } => {
+                let ty = schema
+                    .fields_of(parent)
+                    .and_then(|fields| fields.iter().find(|field| field.name == *name))
+                    .map(|field| field.ty.name());
+
+                f(parent, name);
+
+                if let (Some(ty), Some(selection_set)) = (ty, selection_set) {
+                    walk_field_selections(selection_set, ty, schema, f);
+                }
+            }
+            Selection::InlineFragment {
+                on, selection_set, ..
+            } => walk_field_selections(selection_set, on.unwrap_or(parent), schema, f),
+            Selection::FragmentSpread { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::RefactorError;
+    use crate::{ast::Document, fmt::print_minified, GraphqlParser};
+
+    const DOCUMENT: &str = concat!(
+        "type Query { node: Node user: User } ",
+        "interface Node { id: ID! } ",
+        "type User implements Node { id: ID! name: String friends: [User!] } ",
+        "input Filter { user: ID } ",
+        "query Q($u: ID) { user { id name friends { name } } node { id ... on User { name } } } ",
+        "fragment F on User { name }",
+    );
+
+    fn parse(interner: &mut Rodeo) -> Document {
+        GraphqlParser::parse_with_interner(DOCUMENT.as_bytes(), interner).unwrap()
+    }
+
+    #[test]
+    fn rename_type() {
+        let mut interner = Rodeo::default();
+        let mut document = parse(&mut interner);
+
+        let user = interner.get_or_intern("User");
+        let member = interner.get_or_intern("Member");
+
+        document.rename_type(user, member).unwrap();
+
+        assert_eq!(
+            print_minified(&document, &interner),
+            concat!(
+                "interface Node{id:ID!}",
+                "type Member implements Node{id:ID!name:String friends:[Member!]}",
+                "type Query{node:Node user:Member}",
+                "input Filter{user:ID}",
+                "query Q($u:ID){user{id name friends{name}}node{id...on Member{name}}}",
+                "fragment F on Member{name}",
+            )
+        );
+
+        let query = interner.get_or_intern("Query");
+        assert_eq!(
+            document.rename_type(member, query),
+            Err(RefactorError::TypeTaken(query))
+        );
+    }
+
+    #[test]
+    fn rename_field() {
+        let mut interner = Rodeo::default();
+        let mut document = parse(&mut interner);
+
+        let user = interner.get_or_intern("User");
+        let node = interner.get_or_intern("Node");
+        let id = interner.get_or_intern("id");
+        let name = interner.get_or_intern("name");
+        let key = interner.get_or_intern("key");
+        let full_name = interner.get_or_intern("fullName");
+
+        document
+            .rename_field(user, name, full_name, &interner)
+            .unwrap();
+        document.rename_field(node, id, key, &interner).unwrap();
+
+        assert_eq!(
+            print_minified(&document, &interner),
+            concat!(
+                "interface Node{key:ID!}",
+                "type Query{node:Node user:User}",
+                "type User implements Node{key:ID!fullName:String friends:[User!]}",
+                "input Filter{user:ID}",
+                "query Q($u:ID){user{key fullName friends{fullName}}",
+                "node{key...on User{fullName}}}",
+                "fragment F on User{fullName}",
+            )
+        );
+
+        assert_eq!(
+            document
+                .rename_field(user, key, id, &interner)
+                .unwrap_err()
+                .message(&interner),
+            "`User.key` is required by the interface `Node`, so it must be changed there"
+        );
+    }
+
+    #[test]
+    fn add_and_remove_fields() {
+        let mut interner = Rodeo::default();
+        let mut document = parse(&mut interner);
+
+        let user = interner.get_or_intern("User");
+        let node = interner.get_or_intern("Node");
+        let name = interner.get_or_intern("name");
+        let friends = interner.get_or_intern("friends");
+
+        let field = GraphqlParser::parse_with_interner(b"type T { age: Int }", &mut interner)
+            .unwrap()
+            .output_objects
+            .into_values()
+            .next()
+            .unwrap()
+            .fields
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(
+            document.add_field(node, field.clone(), &interner),
+            Err(RefactorError::NotImplemented {
+                ty: user,
+                field: field.name
+            })
+        );
+        document.add_field(user, field, &interner).unwrap();
+
+        assert_eq!(
+            document.remove_field(user, name, &interner).unwrap_err(),
+            RefactorError::FieldInUse {
+                ty: user,
+                field: name
+            }
+        );
+
+        document.operations.clear();
+        document.fragments.clear();
+
+        document.remove_field(user, friends, &interner).unwrap();
+        document.remove_field(user, name, &interner).unwrap();
+
+        assert_eq!(
+            print_minified(&document, &interner),
+            concat!(
+                "interface Node{id:ID!}",
+                "type Query{node:Node user:User}",
+                "type User implements Node{id:ID!age:Int}",
+                "input Filter{user:ID}",
+            )
+        );
+    }
+
+    #[test]
+    fn set_field_type() {
+        let mut interner = Rodeo::default();
+        let mut document = parse(&mut interner);
+
+        let user = interner.get_or_intern("User");
+        let node = interner.get_or_intern("Node");
+        let filter = interner.get_or_intern("Filter");
+        let id = interner.get_or_intern("id");
+        let friends = interner.get_or_intern("friends");
+
+        let ty = |source: &str, interner: &mut Rodeo| {
+            GraphqlParser::parse_type_with_interner(source.as_bytes(), interner).unwrap()
+        };
+
+        let nullable = ty("ID", &mut interner);
+        let old = document
+            .set_field_type(node, id, nullable, &interner)
+            .unwrap();
+        assert_eq!(old, ty("ID!", &mut interner));
+
+        let string = ty("String!", &mut interner);
+        assert_eq!(
+            document.set_field_type(node, id, string, &interner),
+            Err(RefactorError::FieldInUse {
+                ty: node,
+                field: id
+            })
+        );
+
+        let input = ty("Filter", &mut interner);
+        assert_eq!(
+            document.set_field_type(user, friends, input, &interner),
+            Err(RefactorError::NotOutputType(filter))
+        );
+
+        let nodes = ty("[Node]", &mut interner);
+        assert_eq!(
+            document.set_field_type(user, friends, nodes, &interner),
+            Err(RefactorError::FieldInUse {
+                ty: user,
+                field: friends
+            })
+        );
+
+        assert!(print_minified(&document, &interner).starts_with(concat!(
+            "interface Node{id:ID}type Query{node:Node user:User}",
+            "type User implements Node{id:ID name:String",
+        )));
+    }
+}
@@ -0,0 +1,435 @@
+//! A schema registry kept in a directory, recording each version of a schema
+//! as it is published, so that CI can check a new schema against the one that
+//! is deployed
+//!
+//! Schemas are stored content-addressed, printed in canonical form, at
+//! `schemas/{hash}.graphql`, where `hash` is the hex encoded SHA-256 hash of
+//! the text. `history.json` lists the published versions in order, as
+//! `{ "versions": [{ "version", "hash", "publishedAt", "message" }] }`, with
+//! `publishedAt` in seconds since the Unix epoch. Republishing a schema that
+//! was published before stores no new text
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use lasso::Rodeo;
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    ast::Document,
+    diff::{diff_schemas, Criticality, SchemaChange},
+    fmt::print_document,
+    parse::GraphqlParser,
+    persisted::sha256_hex,
+};
+
+#[derive(Debug)]
+pub enum RegistryError {
+    Io(io::Error),
+    /// `history.json` isn't in the expected format
+    InvalidHistory(String),
+    /// The stored text of a schema doesn't match its hash, or is missing
+    Corrupted {
+        hash: String,
+    },
+    /// A stored schema no longer parses
+    InvalidSchema {
+        hash: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::InvalidHistory(message) => write!(f, "invalid history: {}", message),
+            Self::Corrupted { hash } => {
+                write!(f, "the stored schema `{}` is missing or corrupted", hash)
+            }
+            Self::InvalidSchema { hash, message } => {
+                write!(f, "the stored schema `{}` is invalid: {}", hash, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+impl From<io::Error> for RegistryError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaVersion {
+    /// Counts up from 1
+    pub version: u32,
+    /// The hash of the schema's text, which names the file it is stored in
+    pub hash: String,
+    /// Seconds since the Unix epoch
+    pub published_at: u64,
+    pub message: Option<String>,
+}
+
+impl SchemaVersion {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "version": self.version,
+            "hash": self.hash,
+            "publishedAt": self.published_at,
+            "message": self.message,
+        })
+    }
+
+    fn from_json(json: &JsonValue) -> Option<Self> {
+        Some(Self {
+            version: u32::try_from(json["version"].as_u64()?).ok()?,
+            hash: json["hash"].as_str()?.to_owned(),
+            published_at: json["publishedAt"].as_u64()?,
+            message: match &json["message"] {
+                JsonValue::Null => None,
+                message => Some(message.as_str()?.to_owned()),
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishOutcome {
+    Published(SchemaVersion),
+    /// The schema is the same as the latest version, which is returned
+    Unchanged(SchemaVersion),
+}
+
+/// The changes between the latest version and a proposed schema
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// `None` if nothing was published yet
+    pub base: Option<SchemaVersion>,
+    pub changes: Vec<SchemaChange>,
+}
+
+impl CheckResult {
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &SchemaChange> {
+        self.changes
+            .iter()
+            .filter(|change| change.criticality == Criticality::Breaking)
+    }
+
+    /// Whether the schema can be deployed without breaking clients of the
+    /// latest version
+    pub fn is_compatible(&self) -> bool {
+        self.breaking_changes().next().is_none()
+    }
+}
+
+pub struct Registry {
+    root: PathBuf,
+    versions: Vec<SchemaVersion>,
+}
+
+impl Registry {
+    /// Open the registry in the directory `root`, which is created if it
+    /// doesn't exist
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, RegistryError> {
+        let root = root.into();
+
+        fs::create_dir_all(root.join("schemas"))?;
+
+        let versions = match fs::read(root.join("history.json")) {
+            Ok(buffer) => parse_history(&buffer)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self { root, versions })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Every published version, oldest first
+    pub fn history(&self) -> &[SchemaVersion] {
+        &self.versions
+    }
+
+    pub fn latest(&self) -> Option<&SchemaVersion> {
+        self.versions.last()
+    }
+
+    /// The text of a published version, checked against its hash
+    pub fn source(&self, version: &SchemaVersion) -> Result<String, RegistryError> {
+        let corrupted = || RegistryError::Corrupted {
+            hash: version.hash.clone(),
+        };
+
+        let source =
+            fs::read_to_string(self.schema_path(&version.hash)).map_err(|err| {
+                match err.kind() {
+                    io::ErrorKind::NotFound | io::ErrorKind::InvalidData => corrupted(),
+                    _ => err.into(),
+                }
+            })?;
+
+        if sha256_hex(&source) != version.hash {
+            return Err(corrupted());
+        }
+
+        Ok(source)
+    }
+
+    /// Parse a published version. `interner` is the one the returned schema
+    /// is parsed with
+    pub fn schema(
+        &self,
+        version: &SchemaVersion,
+        interner: &mut Rodeo,
+    ) -> Result<Document, RegistryError> {
+        let source = self.source(version)?;
+
+        GraphqlParser::parse_schema_with_interner(source.as_bytes(), interner).map_err(|err| {
+            RegistryError::InvalidSchema {
+                hash: version.hash.clone(),
+                message: err.to_string(),
+            }
+        })
+    }
+
+    /// Check every published version against its hash, as CI can before
+    /// trusting the registry
+    pub fn verify(&self) -> Result<(), RegistryError> {
+        for version in &self.versions {
+            self.source(version)?;
+        }
+
+        Ok(())
+    }
+
+    /// Compare `schema` against the latest version
+    pub fn check(
+        &self,
+        schema: &Document,
+        interner: &mut Rodeo,
+    ) -> Result<CheckResult, RegistryError> {
+        let base = match self.latest() {
+            Some(base) => base.clone(),
+            None => {
+                return Ok(CheckResult {
+                    base: None,
+                    changes: Vec::new(),
+                })
+            }
+        };
+
+        let old = self.schema(&base, interner)?;
+
+        Ok(CheckResult {
+            changes: diff_schemas(&old, schema, interner),
+            base: Some(base),
+        })
+    }
+
+    /// Store `schema` as a new version, unless it is the same as the latest
+    pub fn publish(
+        &mut self,
+        schema: &Document,
+        interner: &Rodeo,
+        message: Option<&str>,
+    ) -> Result<PublishOutcome, RegistryError> {
+        let source = print_document(schema, interner);
+        let hash = sha256_hex(&source);
+
+        if let Some(latest) = self.latest().filter(|latest| latest.hash == hash) {
+            return Ok(PublishOutcome::Unchanged(latest.clone()));
+        }
+
+        let path = self.schema_path(&hash);
+
+        if !path.exists() {
+            write_atomically(&path, source.as_bytes())?;
+        }
+
+        let version = SchemaVersion {
+            version: self.latest().map_or(1, |latest| latest.version + 1),
+            hash,
+            published_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+            message: message.map(str::to_owned),
+        };
+
+        self.versions.push(version.clone());
+
+        let history = json!({
+            "versions": self.versions.iter().map(SchemaVersion::to_json).collect::<Vec<_>>(),
+        });
+
+        let history = serde_json::to_string_pretty(&history).expect("history is valid json");
+
+        if let Err(err) = write_atomically(&self.root.join("history.json"), history.as_bytes()) {
+            self.versions.pop();
+            return Err(err.into());
+        }
+
+        Ok(PublishOutcome::Published(version))
+    }
+
+    fn schema_path(&self, hash: &str) -> PathBuf {
+        self.root.join("schemas").join(format!("{}.graphql", hash))
+    }
+}
+
+fn parse_history(buffer: &[u8]) -> Result<Vec<SchemaVersion>, RegistryError> {
+    let json = serde_json::from_slice::<JsonValue>(buffer)
+        .map_err(|err| RegistryError::InvalidHistory(err.to_string()))?;
+
+    let versions = json["versions"]
+        .as_array()
+        .ok_or_else(|| RegistryError::InvalidHistory("`versions` must be an array".to_owned()))?;
+
+    versions
+        .iter()
+        .enumerate()
+        .map(|(idx, version)| {
+            SchemaVersion::from_json(version).ok_or_else(|| {
+                RegistryError::InvalidHistory(format!("version {} is malformed", idx + 1))
+            })
+        })
+        .collect()
+}
+
+/// Write to a temporary file first, so that readers never see part of the
+/// contents
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temporary = path.with_extension("tmp");
+
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use lasso::Rodeo;
+
+    use super::{PublishOutcome, Registry, RegistryError};
+    use crate::{Document, GraphqlParser};
+
+    /// A registry directory that is removed when dropped
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "graphql-registry-{}-{}",
+                name,
+                std::process::id()
+            ));
+
+            let _ = fs::remove_dir_all(&path);
+
+            Self(path)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn parse(source: &str, interner: &mut Rodeo) -> Document {
+        GraphqlParser::parse_schema_with_interner(source.as_bytes(), interner).unwrap()
+    }
+
+    #[test]
+    fn publish_and_history() {
+        let dir = TestDir::new("publish");
+        let mut interner = Rodeo::default();
+
+        let mut registry = Registry::open(&dir.0).unwrap();
+        assert!(registry.latest().is_none());
+
+        let first = parse("type Query { a: Int }", &mut interner);
+        let second = parse("type Query { a: Int b: String }", &mut interner);
+
+        let PublishOutcome::Published(v1) = registry
+            .publish(&first, &interner, Some("initial"))
+            .unwrap()
+        else {
+            panic!("expected the first schema to be published");
+        };
+
+        assert_eq!(v1.version, 1);
+        assert!(matches!(
+            registry.publish(&first, &interner, None).unwrap(),
+            PublishOutcome::Unchanged(version) if version == v1
+        ));
+        assert!(matches!(
+            registry.publish(&second, &interner, None).unwrap(),
+            PublishOutcome::Published(version) if version.version == 2
+        ));
+
+        let reopened = Registry::open(&dir.0).unwrap();
+        let history = reopened.history();
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0], v1);
+        assert_eq!(history[0].message.as_deref(), Some("initial"));
+        assert_eq!(
+            reopened.source(&history[1]).unwrap(),
+            "type Query {\n  a: Int\n  b: String\n}\n"
+        );
+        assert!(reopened.verify().is_ok());
+    }
+
+    #[test]
+    fn check_against_latest() {
+        let dir = TestDir::new("check");
+        let mut interner = Rodeo::default();
+        let mut registry = Registry::open(&dir.0).unwrap();
+
+        let proposed = parse("type Query { a: Int }", &mut interner);
+        let result = registry.check(&proposed, &mut interner).unwrap();
+        assert!(result.base.is_none());
+        assert!(result.is_compatible());
+
+        let published = parse("type Query { a: Int b: String }", &mut interner);
+        registry.publish(&published, &interner, None).unwrap();
+
+        let result = registry.check(&proposed, &mut interner).unwrap();
+        let breaking = result
+            .breaking_changes()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(result.base.as_ref().unwrap().version, 1);
+        assert!(!result.is_compatible());
+        assert_eq!(breaking, ["Query.b: field removed"]);
+    }
+
+    #[test]
+    fn corrupted_schemas() {
+        let dir = TestDir::new("corrupted");
+        let mut interner = Rodeo::default();
+        let mut registry = Registry::open(&dir.0).unwrap();
+
+        let schema = parse("type Query { a: Int }", &mut interner);
+        registry.publish(&schema, &interner, None).unwrap();
+
+        let version = registry.latest().unwrap().clone();
+        fs::write(registry.schema_path(&version.hash), "type Query { b: Int }").unwrap();
+
+        assert!(matches!(
+            registry.verify(),
+            Err(RegistryError::Corrupted { hash }) if hash == version.hash
+        ));
+    }
+}
@@ -0,0 +1,610 @@
+//! Recognition of Relay-style connections, and checking them against the
+//! cursor connections specification
+//!
+//! A connection is an object or interface type named `*Connection`, or with
+//! an `edges` field whose type has a `node` field. Connections have a list of
+//! edges, each with a `node` and a `cursor`, and a `pageInfo` field, and the
+//! fields returning them take `first` and `after` or `last` and `before`
+//! arguments to page through them
+
+use std::{collections::HashSet, fmt};
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{Document, FieldDefinition, Span, Type, TypeDefinitionRef, TypeKind, BUILT_IN_SCALARS},
+    fmt::print_type,
+};
+
+/// A connection type defined by a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection {
+    pub name: Spur,
+    /// The type of the connection's edges, if it has an `edges` field
+    pub edge: Option<Spur>,
+    /// The type of the edges' nodes, if they have a `node` field
+    pub node: Option<Spur>,
+}
+
+/// A way a connection, or a field returning one, doesn't follow the spec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionProblem {
+    /// The schema coordinate of the type, field or argument with the problem,
+    /// such as `UserEdge.cursor`
+    pub coordinate: String,
+    pub message: String,
+    /// The change that fixes the problem, such as ``add `cursor: String!` ``
+    pub suggestion: String,
+    pub span: Span,
+}
+
+impl fmt::Display for ConnectionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}; {}", self.message, self.suggestion)
+    }
+}
+
+/// The connection types of `schema`, ordered by name
+pub fn find_connections(schema: &Document, interner: &Rodeo) -> Vec<Connection> {
+    let mut connections = schema
+        .types()
+        .filter(|ty| is_connection(schema, ty, interner))
+        .map(|ty| {
+            let edge = field(schema, ty.name(), "edges", interner).map(|edges| edges.ty.name());
+            let node = edge
+                .and_then(|edge| field(schema, edge, "node", interner))
+                .map(|node| node.ty.name());
+
+            Connection {
+                name: ty.name(),
+                edge,
+                node,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    connections.sort_by_key(|connection| interner.resolve(&connection.name));
+
+    connections
+}
+
+/// Check the connection types of `schema`, their edge and page info types,
+/// and the fields returning them, in source order
+pub fn check_connections(schema: &Document, interner: &Rodeo) -> Vec<ConnectionProblem> {
+    let connections = find_connections(schema, interner);
+
+    let mut checker = ConnectionChecker {
+        schema,
+        interner,
+        checked: HashSet::new(),
+        problems: Vec::new(),
+    };
+
+    for connection in &connections {
+        checker.check_connection(connection);
+    }
+
+    let names = connections
+        .iter()
+        .map(|connection| connection.name)
+        .collect::<HashSet<_>>();
+
+    for ty in schema.types() {
+        if let Some(fields) = schema.fields_of(ty.name()) {
+            for field in fields
+                .iter()
+                .filter(|field| names.contains(&field.ty.name()))
+            {
+                checker.check_paginated_field(ty.name(), field);
+            }
+        }
+    }
+
+    // problems without spans, or at the same position, are ordered by the
+    // coordinate they're about
+    checker.problems.sort_by(|a, b| {
+        (a.span.start, &a.coordinate, &a.message).cmp(&(b.span.start, &b.coordinate, &b.message))
+    });
+
+    checker.problems
+}
+
+fn is_connection(schema: &Document, ty: &TypeDefinitionRef<'_>, interner: &Rodeo) -> bool {
+    if !matches!(
+        ty,
+        TypeDefinitionRef::Object(..) | TypeDefinitionRef::Interface(..)
+    ) {
+        return false;
+    }
+
+    interner.resolve(&ty.name()).ends_with("Connection")
+        || field(schema, ty.name(), "edges", interner)
+            .and_then(|edges| field(schema, edges.ty.name(), "node", interner))
+            .is_some()
+}
+
+/// The field `name` of the object or interface `ty`
+fn field<'a>(
+    schema: &'a Document,
+    ty: Spur,
+    name: &str,
+    interner: &Rodeo,
+) -> Option<&'a FieldDefinition> {
+    schema
+        .fields_of(ty)?
+        .iter()
+        .find(|field| interner.resolve(&field.name) == name)
+}
+
+struct ConnectionChecker<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+    /// The edge and page info types already checked, which may be shared by
+    /// many connections
+    checked: HashSet<Spur>,
+    problems: Vec<ConnectionProblem>,
+}
+
+impl ConnectionChecker<'_> {
+    fn name(&self, name: Spur) -> &str {
+        self.interner.resolve(&name)
+    }
+
+    fn report(&mut self, coordinate: String, message: String, suggestion: String, span: Span) {
+        self.problems.push(ConnectionProblem {
+            coordinate,
+            message,
+            suggestion,
+            span,
+        });
+    }
+
+    fn is_scalar(&self, ty: &Type) -> bool {
+        matches!(ty, Type::Named { .. })
+            && (BUILT_IN_SCALARS.contains(&self.name(ty.name()))
+                || self.schema.type_kind(ty.name()) == Some(TypeKind::Scalar))
+    }
+
+    fn check_connection(&mut self, connection: &Connection) {
+        let ty = self.name(connection.name).to_owned();
+        let span = self
+            .schema
+            .get_type(connection.name)
+            .map_or_else(Span::default, |ty| ty.span());
+        let base = ty.strip_suffix("Connection").unwrap_or(&ty).to_owned();
+
+        match field(self.schema, connection.name, "edges", self.interner) {
+            None => self.report(
+                ty.clone(),
+                format!("connection `{}` has no `edges` field", ty),
+                format!("add `edges: [{}Edge]`", base),
+                span,
+            ),
+            Some(edges) => {
+                let is_list = matches!(edges.ty, Type::List { ref base, .. }
+                    if matches!(**base, Type::Named { .. }));
+
+                if !is_list {
+                    self.report(
+                        format!("{}.edges", ty),
+                        format!("`{}.edges` isn't a list of edges", ty),
+                        format!("change its type to `[{}]`", self.name(edges.ty.name())),
+                        edges.span,
+                    );
+                }
+
+                self.check_edge(edges.ty.name(), edges.span);
+            }
+        }
+
+        match field(self.schema, connection.name, "pageInfo", self.interner) {
+            None => self.report(
+                ty.clone(),
+                format!("connection `{}` has no `pageInfo` field", ty),
+                "add `pageInfo: PageInfo!`".to_owned(),
+                span,
+            ),
+            Some(page_info) => {
+                if page_info.ty.is_nullable() || matches!(page_info.ty, Type::List { .. }) {
+                    self.report(
+                        format!("{}.pageInfo", ty),
+                        format!(
+                            "`{}.pageInfo` has type `{}`, not a non-null object",
+                            ty,
+                            print_type(&page_info.ty, self.interner)
+                        ),
+                        format!("change its type to `{}!`", self.name(page_info.ty.name())),
+                        page_info.span,
+                    );
+                }
+
+                self.check_page_info(page_info.ty.name(), page_info.span);
+            }
+        }
+    }
+
+    /// Check the edge type `edge`, which an `edges` field defined at `span`
+    /// has
+    fn check_edge(&mut self, edge: Spur, span: Span) {
+        if !self.checked.insert(edge) {
+            return;
+        }
+
+        let ty = self.name(edge).to_owned();
+
+        if self.schema.fields_of(edge).is_none() {
+            self.report(
+                ty.clone(),
+                format!("edge type `{}` isn't an object or interface", ty),
+                "define it as an object type with `node` and `cursor` fields".to_owned(),
+                span,
+            );
+
+            return;
+        }
+
+        let span = self
+            .schema
+            .get_type(edge)
+            .map_or_else(Span::default, |ty| ty.span());
+
+        match field(self.schema, edge, "node", self.interner) {
+            None => self.report(
+                ty.clone(),
+                format!("edge `{}` has no `node` field", ty),
+                "add a `node` field with the type being paginated".to_owned(),
+                span,
+            ),
+            Some(node) if matches!(node.ty, Type::List { .. }) => self.report(
+                format!("{}.node", ty),
+                format!("`{}.node` is a list", ty),
+                format!("change its type to `{}`", self.name(node.ty.name())),
+                node.span,
+            ),
+            Some(..) => {}
+        }
+
+        match field(self.schema, edge, "cursor", self.interner) {
+            None => self.report(
+                ty.clone(),
+                format!("edge `{}` has no `cursor` field", ty),
+                "add `cursor: String!`".to_owned(),
+                span,
+            ),
+            Some(cursor) if cursor.ty.is_nullable() || !self.is_scalar(&cursor.ty) => self.report(
+                format!("{}.cursor", ty),
+                format!(
+                    "`{}.cursor` has type `{}`, not a non-null scalar",
+                    ty,
+                    print_type(&cursor.ty, self.interner)
+                ),
+                "change its type to `String!`".to_owned(),
+                cursor.span,
+            ),
+            Some(..) => {}
+        }
+    }
+
+    /// Check the page info type `page_info`, which a `pageInfo` field defined
+    /// at `span` has
+    fn check_page_info(&mut self, page_info: Spur, span: Span) {
+        if !self.checked.insert(page_info) {
+            return;
+        }
+
+        let ty = self.name(page_info).to_owned();
+
+        if self.schema.fields_of(page_info).is_none() {
+            self.report(
+                ty.clone(),
+                format!("page info type `{}` isn't an object", ty),
+                "define it as an object type with the fields of `PageInfo`".to_owned(),
+                span,
+            );
+
+            return;
+        }
+
+        let span = self
+            .schema
+            .get_type(page_info)
+            .map_or_else(Span::default, |ty| ty.span());
+
+        for name in ["hasNextPage", "hasPreviousPage"] {
+            match field(self.schema, page_info, name, self.interner) {
+                None => self.report(
+                    ty.clone(),
+                    format!("page info `{}` has no `{}` field", ty, name),
+                    format!("add `{}: Boolean!`", name),
+                    span,
+                ),
+                Some(field)
+                    if field.ty.is_nullable()
+                        || !self.is_scalar(&field.ty)
+                        || self.name(field.ty.name()) != "Boolean" =>
+                {
+                    self.report(
+                        format!("{}.{}", ty, name),
+                        format!(
+                            "`{}.{}` has type `{}`, not `Boolean!`",
+                            ty,
+                            name,
+                            print_type(&field.ty, self.interner)
+                        ),
+                        "change its type to `Boolean!`".to_owned(),
+                        field.span,
+                    )
+                }
+                Some(..) => {}
+            }
+        }
+
+        for name in ["startCursor", "endCursor"] {
+            match field(self.schema, page_info, name, self.interner) {
+                None => self.report(
+                    ty.clone(),
+                    format!("page info `{}` has no `{}` field", ty, name),
+                    format!("add `{}: String`", name),
+                    span,
+                ),
+                Some(field) if !field.ty.is_nullable() || !self.is_scalar(&field.ty) => self
+                    .report(
+                        format!("{}.{}", ty, name),
+                        format!(
+                            "`{}.{}` has type `{}`, not a nullable scalar",
+                            ty,
+                            name,
+                            print_type(&field.ty, self.interner)
+                        ),
+                        "change its type to `String`".to_owned(),
+                        field.span,
+                    ),
+                Some(..) => {}
+            }
+        }
+    }
+
+    /// Check the field `field` of `parent`, which returns a connection
+    fn check_paginated_field(&mut self, parent: Spur, field: &FieldDefinition) {
+        let coordinate = format!("{}.{}", self.name(parent), self.name(field.name));
+
+        if matches!(field.ty, Type::List { .. }) {
+            self.report(
+                coordinate.clone(),
+                format!("`{}` returns a list of connections", coordinate),
+                format!("change its type to `{}`", self.name(field.ty.name())),
+                field.span,
+            );
+        }
+
+        let argument = |name: &str| {
+            field
+                .arguments
+                .iter()
+                .flatten()
+                .find(|argument| self.name(argument.name) == name)
+        };
+
+        let directions = [("first", "after"), ("last", "before")];
+
+        if directions
+            .iter()
+            .all(|(count, _)| argument(count).is_none())
+        {
+            self.report(
+                coordinate.clone(),
+                format!(
+                    "`{}` returns a connection but can't be paginated",
+                    coordinate
+                ),
+                "add `first: Int` and `after: String` arguments".to_owned(),
+                field.span,
+            );
+
+            return;
+        }
+
+        let mut problems = Vec::new();
+
+        for (count, cursor) in directions {
+            let (count_argument, cursor_argument) = (argument(count), argument(cursor));
+
+            if let Some(argument) = count_argument {
+                if matches!(argument.ty, Type::List { .. })
+                    || self.name(argument.ty.name()) != "Int"
+                {
+                    problems.push((
+                        format!("{}({}:)", coordinate, count),
+                        format!(
+                            "`{}({}:)` has type `{}`, not `Int`",
+                            coordinate,
+                            count,
+                            print_type(&argument.ty, self.interner)
+                        ),
+                        "change its type to `Int`".to_owned(),
+                        argument.span,
+                    ));
+                }
+            }
+
+            if let Some(argument) = cursor_argument {
+                if !self.is_scalar(&argument.ty) {
+                    problems.push((
+                        format!("{}({}:)", coordinate, cursor),
+                        format!(
+                            "`{}({}:)` has type `{}`, not a cursor scalar",
+                            coordinate,
+                            cursor,
+                            print_type(&argument.ty, self.interner)
+                        ),
+                        "change its type to `String`".to_owned(),
+                        argument.span,
+                    ));
+                }
+            }
+
+            match (count_argument, cursor_argument) {
+                (Some(..), None) => problems.push((
+                    coordinate.clone(),
+                    format!("`{}` takes `{}` without `{}`", coordinate, count, cursor),
+                    format!("add `{}: String`", cursor),
+                    field.span,
+                )),
+                (None, Some(..)) => problems.push((
+                    coordinate.clone(),
+                    format!("`{}` takes `{}` without `{}`", coordinate, cursor, count),
+                    format!("add `{}: Int`", count),
+                    field.span,
+                )),
+                _ => {}
+            }
+        }
+
+        for (coordinate, message, suggestion, span) in problems {
+            self.report(coordinate, message, suggestion, span);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{check_connections, find_connections};
+    use crate::GraphqlParser;
+
+    const SCHEMA: &str = concat!(
+        "type Query { ",
+        "users(first: Int, after: String, last: Int, before: String): UserConnection! ",
+        "posts(first: Int): PostConnection ",
+        "comments: [CommentConnection] } ",
+        "type User { id: ID! } ",
+        "type Post { id: ID! } ",
+        "type UserConnection { edges: [UserEdge] pageInfo: PageInfo! } ",
+        "type UserEdge { node: User cursor: String! } ",
+        "type PageInfo { hasNextPage: Boolean! hasPreviousPage: Boolean! ",
+        "startCursor: String endCursor: String } ",
+        "type PostConnection { edges: PostEdge } ",
+        "type PostEdge { node: [Post] cursor: Int } ",
+        "type Comments { edges: [CommentEdge!]! pageInfo: PageInfo! } ",
+        "type CommentEdge { node: ID cursor: String! } ",
+        "type CommentConnection { pageInfo: Info } ",
+        "type Info { hasNextPage: Boolean startCursor: String! endCursor: String }",
+    );
+
+    #[test]
+    fn recognizes_connections() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+
+        let connections = find_connections(&schema, &interner)
+            .into_iter()
+            .map(|connection| {
+                let name = |name: Option<_>| name.map(|name| interner.resolve(&name));
+
+                (
+                    interner.resolve(&connection.name),
+                    name(connection.edge),
+                    name(connection.node),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            connections,
+            [
+                ("CommentConnection", None, None),
+                ("Comments", Some("CommentEdge"), Some("ID")),
+                ("PostConnection", Some("PostEdge"), Some("Post")),
+                ("UserConnection", Some("UserEdge"), Some("User")),
+            ]
+        );
+    }
+
+    #[test]
+    fn problems_with_suggestions() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+
+        let problems = check_connections(&schema, &interner)
+            .iter()
+            .map(|problem| (problem.coordinate.clone(), problem.to_string()))
+            .collect::<Vec<_>>();
+
+        let mut expected = [
+            (
+                "Query.posts",
+                "`Query.posts` takes `first` without `after`; add `after: String`",
+            ),
+            (
+                "Query.comments",
+                concat!(
+                    "`Query.comments` returns a connection but can't be paginated; ",
+                    "add `first: Int` and `after: String` arguments"
+                ),
+            ),
+            (
+                "Query.comments",
+                concat!(
+                    "`Query.comments` returns a list of connections; ",
+                    "change its type to `CommentConnection`"
+                ),
+            ),
+            (
+                "PostConnection",
+                "connection `PostConnection` has no `pageInfo` field; add `pageInfo: PageInfo!`",
+            ),
+            (
+                "PostConnection.edges",
+                "`PostConnection.edges` isn't a list of edges; change its type to `[PostEdge]`",
+            ),
+            (
+                "PostEdge.node",
+                "`PostEdge.node` is a list; change its type to `Post`",
+            ),
+            (
+                "PostEdge.cursor",
+                concat!(
+                    "`PostEdge.cursor` has type `Int`, not a non-null scalar; ",
+                    "change its type to `String!`"
+                ),
+            ),
+            (
+                "CommentConnection",
+                "connection `CommentConnection` has no `edges` field; add `edges: [CommentEdge]`",
+            ),
+            (
+                "CommentConnection.pageInfo",
+                concat!(
+                    "`CommentConnection.pageInfo` has type `Info`, not a non-null object; ",
+                    "change its type to `Info!`"
+                ),
+            ),
+            (
+                "Info",
+                "page info `Info` has no `hasPreviousPage` field; add `hasPreviousPage: Boolean!`",
+            ),
+            (
+                "Info.hasNextPage",
+                concat!(
+                    "`Info.hasNextPage` has type `Boolean`, not `Boolean!`; ",
+                    "change its type to `Boolean!`"
+                ),
+            ),
+            (
+                "Info.startCursor",
+                concat!(
+                    "`Info.startCursor` has type `String!`, not a nullable scalar; ",
+                    "change its type to `String`"
+                ),
+            ),
+        ]
+        .map(|(coordinate, problem)| (coordinate.to_owned(), problem.to_owned()));
+        // without spans, problems are ordered by coordinate
+        if !cfg!(feature = "spans") {
+            expected.sort();
+        }
+
+        assert_eq!(problems, expected);
+    }
+}
@@ -0,0 +1,568 @@
+//! Moving documents and parse errors from the interner they were parsed with
+//! into another, by replacing each name and string with the key the other
+//! interner has for the same string
+
+use std::collections::HashMap;
+
+use lasso::{Key, Rodeo, Spur};
+
+use crate::{
+    ast::{
+        Argument, Definition, Directive, DirectiveDefinition, Document, Enum, EnumVariant,
+        FieldDefinition, Fragment, InputObject, InputObjectField, Interface, NamedType,
+        ObjectFieldSpan, ObjectType, Operation, RootOperationTypeDefinition, Scalar,
+        SchemaDefinition, SchemaExtension, Selection, Token, Type, TypeDefinition, TypeExtension,
+        Union, Value, ValueSpan, VariableDefinition,
+    },
+    error::GraphqlParseError,
+};
+
+pub struct Remapper {
+    /// The key in the new interner for each key of the old one, by index
+    keys: Vec<Spur>,
+}
+
+impl Remapper {
+    /// Intern every string of `from` into `into`, in order
+    pub fn new(from: &Rodeo, into: &mut Rodeo) -> Self {
+        let keys = from
+            .strings()
+            .map(|string| into.get_or_intern(string))
+            .collect();
+
+        Self { keys }
+    }
+
+    fn key(&self, key: Spur) -> Spur {
+        self.keys[key.into_usize()]
+    }
+
+    fn keys<T>(&self, map: HashMap<Spur, T>, f: impl Fn(&Self, T) -> T) -> HashMap<Spur, T> {
+        map.into_iter()
+            .map(|(key, value)| (self.key(key), f(self, value)))
+            .collect()
+    }
+
+    pub fn error(&self, error: GraphqlParseError) -> GraphqlParseError {
+        match error {
+            GraphqlParseError::ExpectedToken { token, found, span } => {
+                GraphqlParseError::ExpectedToken {
+                    token: self.token(token),
+                    found: found.map(|found| self.token(found)),
+                    span,
+                }
+            }
+            GraphqlParseError::UnexpectedToken {
+                found,
+                expected,
+                span,
+            } => GraphqlParseError::UnexpectedToken {
+                found: self.token(found),
+                expected,
+                span,
+            },
+            error => error,
+        }
+    }
+
+    fn token(&self, token: Token) -> Token {
+        match token {
+            Token::Name(key) => Token::Name(self.key(key)),
+            Token::String(key) => Token::String(self.key(key)),
+            Token::Variable(key) => Token::Variable(self.key(key)),
+            Token::IntValue(key) => Token::IntValue(self.key(key)),
+            Token::FloatValue(key) => Token::FloatValue(self.key(key)),
+            token => token,
+        }
+    }
+
+    pub fn document(&self, document: Document) -> Document {
+        Document {
+            schema: document.schema.map(|schema| self.schema_definition(schema)),
+            operations: document
+                .operations
+                .into_values()
+                .map(|operation| {
+                    let operation = self.operation(operation);
+                    ((operation.name, operation.kind), operation)
+                })
+                .collect(),
+            fragments: self.keys(document.fragments, Self::fragment),
+            input_objects: self.keys(document.input_objects, Self::input_object),
+            output_objects: self.keys(document.output_objects, Self::object_type),
+            interfaces: self.keys(document.interfaces, Self::interface),
+            scalars: self.keys(document.scalars, Self::scalar),
+            unions: self.keys(document.unions, Self::union),
+            enums: self.keys(document.enums, Self::enum_def),
+            directive_definitions: self
+                .keys(document.directive_definitions, Self::directive_definition),
+            type_extensions: document
+                .type_extensions
+                .into_iter()
+                .map(|extension| self.type_extension(extension))
+                .collect(),
+            schema_extensions: document
+                .schema_extensions
+                .into_iter()
+                .map(|extension| self.schema_extension(extension))
+                .collect(),
+            duplicates: document
+                .duplicates
+                .into_iter()
+                .map(|definition| self.definition(definition))
+                .collect(),
+        }
+    }
+
+    fn definition(&self, definition: Definition) -> Definition {
+        match definition {
+            Definition::Schema(schema) => Definition::Schema(self.schema_definition(schema)),
+            Definition::Operation(operation) => Definition::Operation(self.operation(operation)),
+            Definition::Fragment(fragment) => Definition::Fragment(self.fragment(fragment)),
+            Definition::TypeDecl(ty) => Definition::TypeDecl(self.type_definition(ty)),
+            Definition::Directive(directive) => {
+                Definition::Directive(self.directive_definition(directive))
+            }
+            Definition::TypeExtension(extension) => {
+                Definition::TypeExtension(self.type_extension(extension))
+            }
+            Definition::SchemaExtension(extension) => {
+                Definition::SchemaExtension(self.schema_extension(extension))
+            }
+        }
+    }
+
+    fn type_definition(&self, ty: TypeDefinition) -> TypeDefinition {
+        match ty {
+            TypeDefinition::Scalar(scalar) => TypeDefinition::Scalar(self.scalar(scalar)),
+            TypeDefinition::Object(object) => TypeDefinition::Object(self.object_type(object)),
+            TypeDefinition::Interface(interface) => {
+                TypeDefinition::Interface(self.interface(interface))
+            }
+            TypeDefinition::Union(union) => TypeDefinition::Union(self.union(union)),
+            TypeDefinition::Enum(enum_def) => TypeDefinition::Enum(self.enum_def(enum_def)),
+            TypeDefinition::InputObject(input) => {
+                TypeDefinition::InputObject(self.input_object(input))
+            }
+        }
+    }
+
+    fn type_extension(&self, extension: TypeExtension) -> TypeExtension {
+        match extension {
+            TypeExtension::Scalar(scalar) => TypeExtension::Scalar(self.scalar(scalar)),
+            TypeExtension::Object(object) => TypeExtension::Object(self.object_type(object)),
+            TypeExtension::Interface(interface) => {
+                TypeExtension::Interface(self.interface(interface))
+            }
+            TypeExtension::Union(union) => TypeExtension::Union(self.union(union)),
+            TypeExtension::Enum(enum_def) => TypeExtension::Enum(self.enum_def(enum_def)),
+            TypeExtension::InputObject(input) => {
+                TypeExtension::InputObject(self.input_object(input))
+            }
+        }
+    }
+
+    fn schema_definition(&self, schema: SchemaDefinition) -> SchemaDefinition {
+        SchemaDefinition {
+            description: schema.description.map(|key| self.key(key)),
+            directives: self.directives(schema.directives),
+            operation_types: self.root_operation_types(schema.operation_types),
+            span: schema.span,
+        }
+    }
+
+    fn schema_extension(&self, extension: SchemaExtension) -> SchemaExtension {
+        SchemaExtension {
+            directives: self.directives(extension.directives),
+            operation_types: self.root_operation_types(extension.operation_types),
+            span: extension.span,
+        }
+    }
+
+    fn root_operation_types(
+        &self,
+        operation_types: Vec<RootOperationTypeDefinition>,
+    ) -> Vec<RootOperationTypeDefinition> {
+        operation_types
+            .into_iter()
+            .map(|operation_type| RootOperationTypeDefinition {
+                ty: self.named_type(operation_type.ty),
+                ..operation_type
+            })
+            .collect()
+    }
+
+    fn operation(&self, operation: Operation) -> Operation {
+        Operation {
+            kind: operation.kind,
+            name: operation.name.map(|key| self.key(key)),
+            variable_definitions: operation
+                .variable_definitions
+                .into_iter()
+                .map(|variable| self.variable_definition(variable))
+                .collect(),
+            directives: self.directives(operation.directives),
+            selection_set: self.selection_set(operation.selection_set),
+            span: operation.span,
+        }
+    }
+
+    fn variable_definition(&self, variable: VariableDefinition) -> VariableDefinition {
+        VariableDefinition {
+            name: self.key(variable.name),
+            ty: self.ty(variable.ty),
+            ty_span: variable.ty_span,
+            default: variable.default.map(|value| self.value(value)),
+            default_span: self.value_span(variable.default_span),
+            span: variable.span,
+        }
+    }
+
+    fn fragment(&self, fragment: Fragment) -> Fragment {
+        Fragment {
+            name: self.key(fragment.name),
+            on: self.key(fragment.on),
+            directives: self.directives(fragment.directives),
+            selection_set: self.selection_set(fragment.selection_set),
+            span: fragment.span,
+        }
+    }
+
+    fn selection_set(&self, selection_set: Vec<Selection>) -> Vec<Selection> {
+        selection_set
+            .into_iter()
+            .map(|selection| self.selection(selection))
+            .collect()
+    }
+
+    fn selection(&self, selection: Selection) -> Selection {
+        match selection {
+            Selection::Field {
+                alias,
+                name,
+                arguments,
+                directives,
+                selection_set,
+                span,
+            } => Selection::Field {
+                alias: alias.map(|key| self.key(key)),
+                name: self.key(name),
+                arguments: arguments.map(|arguments| self.arguments(arguments)),
+                directives: self.directives(directives),
+                selection_set: selection_set.map(|selection_set| self.selection_set(selection_set)),
+                span,
+            },
+            Selection::FragmentSpread {
+                name,
+                directives,
+                span,
+            } => Selection::FragmentSpread {
+                name: self.key(name),
+                directives: self.directives(directives),
+                span,
+            },
+            Selection::InlineFragment {
+                on,
+                directives,
+                selection_set,
+                span,
+            } => Selection::InlineFragment {
+                on: on.map(|on| self.key(on)),
+                directives: self.directives(directives),
+                selection_set: self.selection_set(selection_set),
+                span,
+            },
+        }
+    }
+
+    fn scalar(&self, scalar: Scalar) -> Scalar {
+        Scalar {
+            description: scalar.description.map(|key| self.key(key)),
+            name: self.key(scalar.name),
+            directives: self.directives(scalar.directives),
+            span: scalar.span,
+        }
+    }
+
+    fn object_type(&self, object: ObjectType) -> ObjectType {
+        ObjectType {
+            implements: self.named_types(object.implements),
+            description: object.description.map(|key| self.key(key)),
+            name: self.key(object.name),
+            directives: self.directives(object.directives),
+            fields: object.fields.map(|fields| self.field_definitions(fields)),
+            span: object.span,
+        }
+    }
+
+    fn interface(&self, interface: Interface) -> Interface {
+        Interface {
+            implements: self.named_types(interface.implements),
+            description: interface.description.map(|key| self.key(key)),
+            name: self.key(interface.name),
+            directives: self.directives(interface.directives),
+            fields: self.field_definitions(interface.fields),
+            span: interface.span,
+        }
+    }
+
+    fn union(&self, union: Union) -> Union {
+        Union {
+            description: union.description.map(|key| self.key(key)),
+            name: self.key(union.name),
+            types: self.named_types(union.types),
+            directives: self.directives(union.directives),
+            span: union.span,
+        }
+    }
+
+    fn enum_def(&self, enum_def: Enum) -> Enum {
+        Enum {
+            description: enum_def.description.map(|key| self.key(key)),
+            name: self.key(enum_def.name),
+            directives: self.directives(enum_def.directives),
+            variants: enum_def
+                .variants
+                .into_iter()
+                .map(|variant| EnumVariant {
+                    description: variant.description.map(|key| self.key(key)),
+                    name: self.key(variant.name),
+                    directives: self.directives(variant.directives),
+                    span: variant.span,
+                })
+                .collect(),
+            span: enum_def.span,
+        }
+    }
+
+    fn input_object(&self, input: InputObject) -> InputObject {
+        InputObject {
+            description: input.description.map(|key| self.key(key)),
+            name: self.key(input.name),
+            directives: self.directives(input.directives),
+            fields: input.fields.map(|fields| self.input_values(fields)),
+            span: input.span,
+        }
+    }
+
+    fn directive_definition(&self, directive: DirectiveDefinition) -> DirectiveDefinition {
+        DirectiveDefinition {
+            description: directive.description.map(|key| self.key(key)),
+            name: self.key(directive.name),
+            arguments: directive
+                .arguments
+                .map(|arguments| self.input_values(arguments)),
+            repeatable: directive.repeatable,
+            locations: directive.locations,
+            span: directive.span,
+        }
+    }
+
+    fn field_definitions(&self, fields: Vec<FieldDefinition>) -> Vec<FieldDefinition> {
+        fields
+            .into_iter()
+            .map(|field| FieldDefinition {
+                description: field.description.map(|key| self.key(key)),
+                name: self.key(field.name),
+                ty: self.ty(field.ty),
+                ty_span: field.ty_span,
+                arguments: field
+                    .arguments
+                    .map(|arguments| self.input_values(arguments)),
+                directives: self.directives(field.directives),
+                span: field.span,
+            })
+            .collect()
+    }
+
+    fn input_values(&self, input_values: Vec<InputObjectField>) -> Vec<InputObjectField> {
+        input_values
+            .into_iter()
+            .map(|input_value| InputObjectField {
+                description: input_value.description.map(|key| self.key(key)),
+                name: self.key(input_value.name),
+                ty: self.ty(input_value.ty),
+                ty_span: input_value.ty_span,
+                default: input_value.default.map(|value| self.value(value)),
+                default_span: self.value_span(input_value.default_span),
+                directives: self.directives(input_value.directives),
+                span: input_value.span,
+            })
+            .collect()
+    }
+
+    fn directives(&self, directives: Vec<Directive>) -> Vec<Directive> {
+        directives
+            .into_iter()
+            .map(|directive| Directive {
+                name: self.key(directive.name),
+                arguments: directive
+                    .arguments
+                    .map(|arguments| self.arguments(arguments)),
+                span: directive.span,
+            })
+            .collect()
+    }
+
+    fn arguments(&self, arguments: Vec<Argument>) -> Vec<Argument> {
+        arguments
+            .into_iter()
+            .map(|argument| Argument {
+                name: self.key(argument.name),
+                value: self.value(argument.value),
+                value_span: self.value_span(argument.value_span),
+                span: argument.span,
+            })
+            .collect()
+    }
+
+    fn value_span(&self, value_span: ValueSpan) -> ValueSpan {
+        ValueSpan {
+            span: value_span.span,
+            items: value_span
+                .items
+                .into_iter()
+                .map(|item| self.value_span(item))
+                .collect(),
+            fields: self.keys(value_span.fields, |remapper, field| ObjectFieldSpan {
+                span: field.span,
+                value: remapper.value_span(field.value),
+            }),
+        }
+    }
+
+    fn value(&self, value: Value) -> Value {
+        match value {
+            Value::String(key) => Value::String(self.key(key)),
+            Value::Variable(key) => Value::Variable(self.key(key)),
+            Value::EnumVariant(key) => Value::EnumVariant(self.key(key)),
+            Value::List(values) => {
+                Value::List(values.into_iter().map(|value| self.value(value)).collect())
+            }
+            Value::Object(fields) => Value::Object(self.keys(fields, Self::value)),
+            value @ (Value::True
+            | Value::False
+            | Value::Null
+            | Value::Float(..)
+            | Value::Int(..)) => value,
+        }
+    }
+
+    fn ty(&self, ty: Type) -> Type {
+        match ty {
+            Type::Named { name, nullable } => Type::Named {
+                name: self.key(name),
+                nullable,
+            },
+            Type::List { base, nullable } => Type::List {
+                base: Box::new(self.ty(*base)),
+                nullable,
+            },
+        }
+    }
+
+    fn named_type(&self, ty: NamedType) -> NamedType {
+        NamedType(self.key(ty.0))
+    }
+
+    fn named_types(&self, types: Vec<NamedType>) -> Vec<NamedType> {
+        types.into_iter().map(|ty| self.named_type(ty)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::Remapper;
+    use crate::{ast::Token, fmt::print_document, GraphqlParseError, GraphqlParser};
+
+    const SOURCE: &str = concat!(
+        "\"schema\" schema @a(b: \"c\") { query: Query } ",
+        "directive @a(b: String = \"d\") repeatable on SCHEMA | FIELD ",
+        "type Query implements Node { id: ID! list(first: Int = 1): [[Query!]] @a(b: \"e\") } ",
+        "interface Node { id: ID! } union U = Query enum E { A @a } ",
+        "input In { a: E = A, b: [In!] = [{ a: A }] } scalar S ",
+        "extend type Query { extra: Float } extend union U = Query ",
+        "query Q($v: In = { a: A }) @a ",
+        "{ list(first: $v) @a(b: \"f\") { ...F ... on Query { id } } } ",
+        "fragment F on Query { id }",
+    );
+
+    #[test]
+    fn remapped_document_prints_the_same() {
+        let mut from = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(SOURCE.as_bytes(), &mut from).unwrap();
+
+        let mut into = Rodeo::default();
+        // offset the keys of the new interner
+        into.get_or_intern("unrelated");
+        into.get_or_intern("id");
+
+        let remapped = Remapper::new(&from, &mut into).document(document.clone());
+
+        assert_eq!(
+            print_document(&remapped, &into),
+            print_document(&document, &from)
+        );
+    }
+
+    #[test]
+    fn remapped_errors_refer_to_the_new_interner() {
+        let mut from = Rodeo::default();
+        let error =
+            GraphqlParser::parse_with_interner(b"type T { a: Int } foo", &mut from).unwrap_err();
+
+        let mut into = Rodeo::default();
+        into.get_or_intern("unrelated");
+
+        let error = Remapper::new(&from, &mut into).error(error);
+
+        match error {
+            GraphqlParseError::UnexpectedToken {
+                found: Token::Name(name),
+                ..
+            } => assert_eq!(into.resolve(&name), "foo"),
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn parse_many_matches_parsing_in_order() {
+        let sources: [&[u8]; 4] = [
+            b"type Query { a: Int }",
+            b"query { a }",
+            b"type T { a b }",
+            b"fragment F on Query { a b }",
+        ];
+
+        let mut shared = Rodeo::default();
+        let expected = sources
+            .iter()
+            .map(|source| {
+                GraphqlParser::parse_with_interner(source, &mut shared)
+                    .map(|document| print_document(&document, &shared))
+                    .map_err(|error| error.to_string())
+            })
+            .collect::<Vec<_>>();
+
+        let mut interner = Rodeo::default();
+        let parsed = GraphqlParser::parse_many(&sources, &mut interner)
+            .into_iter()
+            .map(|result| {
+                result
+                    .map(|document| print_document(&document, &interner))
+                    .map_err(|error| error.to_string())
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(parsed, expected);
+        assert!(parsed[2].is_err());
+
+        // strings are interned in the same order as parsing one at a time
+        assert_eq!(
+            interner.strings().collect::<Vec<_>>(),
+            shared.strings().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! The JSON body of a GraphQL-over-HTTP request
+//!
+//! See https://graphql.github.io/graphql-over-http/draft/#sec-Request-Parameters
+
+use std::fmt;
+
+use lasso::Rodeo;
+use serde_json::{json, Map, Value as JsonValue};
+
+use crate::ast::Document;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestError {
+    /// The document has no operation named `name`
+    UnknownOperation { name: String },
+    /// No operation name was given, but the document doesn't contain exactly
+    /// one operation, so the server couldn't tell which to execute
+    AmbiguousOperation { operations: usize },
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownOperation { name } => {
+                write!(f, "the document has no operation named `{}`", name)
+            }
+            Self::AmbiguousOperation { operations: 0 } => {
+                write!(f, "the document has no operations")
+            }
+            Self::AmbiguousOperation { operations } => write!(
+                f,
+                "the document has {} operations, so an operation name is required",
+                operations
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+/// The JSON body of a request to execute `query`, which was parsed into
+/// `document`: `{ "query", "operationName", "variables" }`
+///
+/// Fails if `document` doesn't contain the operation that the server would
+/// execute, either the one named `operation_name` or, without a name, its
+/// only operation
+pub fn request_body(
+    document: &Document,
+    query: &str,
+    operation_name: Option<&str>,
+    variables: Map<String, JsonValue>,
+    interner: &Rodeo,
+) -> Result<JsonValue, RequestError> {
+    if document
+        .extract_operation(operation_name, interner)
+        .is_none()
+    {
+        return Err(match operation_name {
+            Some(name) => RequestError::UnknownOperation {
+                name: name.to_owned(),
+            },
+            None => RequestError::AmbiguousOperation {
+                operations: document.operations.len(),
+            },
+        });
+    }
+
+    Ok(json!({
+        "query": query,
+        "operationName": operation_name,
+        "variables": variables,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+    use serde_json::{json, Map};
+
+    use super::{request_body, RequestError};
+    use crate::GraphqlParser;
+
+    const QUERY: &str = "query A { a } mutation B { b }";
+
+    fn body(query: &str, operation_name: Option<&str>) -> Result<serde_json::Value, RequestError> {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(query.as_bytes(), &mut interner).unwrap();
+
+        let mut variables = Map::new();
+        variables.insert("first".to_owned(), json!(10));
+
+        request_body(&document, query, operation_name, variables, &interner)
+    }
+
+    #[test]
+    fn named_operation() {
+        assert_eq!(
+            body(QUERY, Some("B")),
+            Ok(json!({
+                "query": QUERY,
+                "operationName": "B",
+                "variables": { "first": 10 },
+            }))
+        );
+    }
+
+    #[test]
+    fn single_anonymous_operation() {
+        assert_eq!(
+            body("{ a }", None),
+            Ok(json!({
+                "query": "{ a }",
+                "operationName": null,
+                "variables": { "first": 10 },
+            }))
+        );
+    }
+
+    #[test]
+    fn missing_operation() {
+        assert_eq!(
+            body(QUERY, Some("C")),
+            Err(RequestError::UnknownOperation {
+                name: "C".to_owned()
+            })
+        );
+        assert_eq!(
+            body(QUERY, None),
+            Err(RequestError::AmbiguousOperation { operations: 2 })
+        );
+        assert_eq!(
+            body("fragment F on Query { a }", None)
+                .unwrap_err()
+                .to_string(),
+            "the document has no operations"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Rust-side coercion of custom scalars
+//!
+//! Without a registered coercion, a custom scalar accepts any input value and
+//! serializes whatever its resolver returns. A [`ScalarRegistry`] associates
+//! functions with scalar names, which validation uses to check literals,
+//! execution uses to coerce arguments and variables and to serialize results,
+//! and `variables::coerce_variable_values_with_scalars` uses to coerce the
+//! JSON variables of a request
+//!
+//! The built-in scalars always use the coercion rules of the spec, so they
+//! can't be registered
+
+use std::{collections::HashMap, fmt};
+
+use lasso::Rodeo;
+
+use crate::{
+    ast::{Value, BUILT_IN_SCALARS},
+    execute::ResponseValue,
+};
+
+type ParseFn = dyn Fn(&Value, &Rodeo) -> Result<Value, String>;
+type SerializeFn = dyn Fn(&ResponseValue) -> Result<ResponseValue, String>;
+
+struct ScalarCoercion {
+    parse: Box<ParseFn>,
+    serialize: Box<SerializeFn>,
+}
+
+#[derive(Default)]
+pub struct ScalarRegistry {
+    scalars: HashMap<String, ScalarCoercion>,
+}
+
+impl fmt::Debug for ScalarRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.scalars.keys()).finish()
+    }
+}
+
+impl ScalarRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Coerce values of the scalar `name` with `parse`, for input values, and
+    /// `serialize`, for values returned by resolvers. Either returns a
+    /// message describing why the value is invalid
+    ///
+    /// Input values are given after any variables in them are substituted,
+    /// except when validating, where a literal may contain variables nested
+    /// in lists or objects
+    ///
+    /// # Panics
+    ///
+    /// If `name` is a built-in scalar
+    pub fn register(
+        &mut self,
+        name: &str,
+        parse: impl Fn(&Value, &Rodeo) -> Result<Value, String> + 'static,
+        serialize: impl Fn(&ResponseValue) -> Result<ResponseValue, String> + 'static,
+    ) -> &mut Self {
+        assert!(
+            !BUILT_IN_SCALARS.contains(&name),
+            "the built-in scalar `{}` can't be registered",
+            name
+        );
+
+        self.scalars.insert(
+            name.to_owned(),
+            ScalarCoercion {
+                parse: Box::new(parse),
+                serialize: Box::new(serialize),
+            },
+        );
+
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.scalars.contains_key(name)
+    }
+
+    /// Coerce an input `value` of the scalar `name`, or `None` if no
+    /// coercion is registered for it
+    pub fn parse(
+        &self,
+        name: &str,
+        value: &Value,
+        interner: &Rodeo,
+    ) -> Option<Result<Value, String>> {
+        self.scalars
+            .get(name)
+            .map(|scalar| (scalar.parse)(value, interner))
+    }
+
+    /// Serialize a `value` of the scalar `name` returned by a resolver, or
+    /// `None` if no coercion is registered for it
+    pub fn serialize(
+        &self,
+        name: &str,
+        value: &ResponseValue,
+    ) -> Option<Result<ResponseValue, String>> {
+        self.scalars
+            .get(name)
+            .map(|scalar| (scalar.serialize)(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lasso::Rodeo;
+
+    use super::ScalarRegistry;
+    use crate::{
+        ast::{Document, Value},
+        execute::{
+            execute, ExecutionRequest, FieldContext, FieldError, ResolvedValue, Resolver,
+            ResponseValue,
+        },
+        validation::validate_with_scalars,
+        GraphqlParser,
+    };
+
+    const SCHEMA: &str = r#"
+scalar Date @specifiedBy(url: "https://tools.ietf.org/html/rfc3339")
+scalar Json
+type Query { today: Date!, tomorrow: Date, echo(date: Date): Date, json: Json }
+"#;
+
+    fn is_date(date: &str) -> bool {
+        let bytes = date.as_bytes();
+
+        bytes.len() == 10
+            && bytes.iter().enumerate().all(|(idx, byte)| match idx {
+                4 | 7 => *byte == b'-',
+                _ => byte.is_ascii_digit(),
+            })
+    }
+
+    fn registry() -> ScalarRegistry {
+        let mut scalars = ScalarRegistry::new();
+
+        scalars.register(
+            "Date",
+            |value, interner| match value {
+                Value::String(date) if is_date(interner.resolve(date)) => Ok(value.clone()),
+                _ => Err("expected a date formatted as YYYY-MM-DD".to_owned()),
+            },
+            |value| match value {
+                ResponseValue::String(date) if is_date(date) => Ok(value.clone()),
+                _ => Err("could not serialize a date".to_owned()),
+            },
+        );
+
+        scalars
+    }
+
+    struct DateResolver(ScalarRegistry);
+
+    impl Resolver for DateResolver {
+        type Object = ();
+
+        fn resolve_field(
+            &self,
+            _: &(),
+            field: &FieldContext<'_>,
+        ) -> Result<ResolvedValue<()>, FieldError> {
+            Ok(match field.name {
+                "today" => ResolvedValue::String("2024-01-02".to_owned()),
+                "tomorrow" => ResolvedValue::String("soon".to_owned()),
+                "echo" => match field.argument("date") {
+                    Some(Value::String(date)) => {
+                        ResolvedValue::String(field.interner.resolve(date).to_owned())
+                    }
+                    _ => ResolvedValue::Null,
+                },
+                _ => ResolvedValue::Boolean(true),
+            })
+        }
+
+        fn resolve_type(&self, _: &(), _: &str) -> Option<String> {
+            None
+        }
+
+        fn scalars(&self) -> Option<&ScalarRegistry> {
+            Some(&self.0)
+        }
+    }
+
+    fn parse(source: &str, interner: &mut Rodeo) -> Document {
+        GraphqlParser::parse_with_interner(source.as_bytes(), interner).unwrap()
+    }
+
+    #[test]
+    fn specified_by_url() {
+        let mut interner = Rodeo::default();
+        let schema = parse(SCHEMA, &mut interner);
+        let url =
+            |name: &str| schema.scalars[&interner.get(name).unwrap()].specified_by_url(&interner);
+
+        assert_eq!(url("Date"), Some("https://tools.ietf.org/html/rfc3339"));
+        assert_eq!(url("Json"), None);
+    }
+
+    #[test]
+    fn validation_checks_registered_scalars() {
+        let mut interner = Rodeo::default();
+        let schema = parse(SCHEMA, &mut interner);
+        let scalars = registry();
+
+        let mut errors = |source: &str| {
+            let document = parse(source, &mut interner);
+
+            validate_with_scalars(&schema, &document, &interner, Some(&scalars))
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(errors(r#"{ echo(date: "2024-01-02") json }"#).is_empty());
+        assert_eq!(errors(r#"{ echo(date: "today") }"#).len(), 1);
+        assert_eq!(errors("{ echo(date: 20240102) }").len(), 1);
+    }
+
+    #[test]
+    fn execution_coerces_registered_scalars() {
+        let mut interner = Rodeo::default();
+        let schema = parse(SCHEMA, &mut interner);
+        let resolver = DateResolver(registry());
+
+        let mut run = |source: &str| {
+            let document = parse(source, &mut interner);
+            let request = ExecutionRequest {
+                document: &document,
+                operation_name: None,
+                variables: &HashMap::new(),
+            };
+
+            let result = execute(&schema, &interner, request, &resolver, &());
+            let errors = result
+                .errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+
+            (result.data, errors)
+        };
+
+        let string = |s: &str| ResponseValue::String(s.to_owned());
+
+        assert_eq!(
+            run(r#"{ today echo(date: "2024-03-04") json }"#),
+            (
+                Some(ResponseValue::Object(vec![
+                    ("today".to_owned(), string("2024-01-02")),
+                    ("echo".to_owned(), string("2024-03-04")),
+                    ("json".to_owned(), ResponseValue::Boolean(true)),
+                ])),
+                Vec::new()
+            )
+        );
+        assert_eq!(
+            run("{ tomorrow }"),
+            (
+                Some(ResponseValue::Object(vec![(
+                    "tomorrow".to_owned(),
+                    ResponseValue::Null
+                )])),
+                vec!["tomorrow: could not serialize a date".to_owned()]
+            )
+        );
+        assert_eq!(run(r#"{ echo(date: "later") }"#).1.len(), 1);
+    }
+
+    #[test]
+    #[should_panic = "the built-in scalar `ID` can't be registered"]
+    fn built_in_scalars_cannot_be_registered() {
+        ScalarRegistry::new().register(
+            "ID",
+            |value, _| Ok(value.clone()),
+            |value| Ok(value.clone()),
+        );
+    }
+}
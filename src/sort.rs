@@ -0,0 +1,265 @@
+//! Stable ordering of type system definitions, so that printed schemas can be
+//! snapshotted and diffed without noise from the order things were written in
+//!
+//! [`print_document`](crate::fmt::print_document) already prints definitions
+//! in a fixed order: the schema definition, then directive definitions, then
+//! types grouped by kind (scalars, interfaces, objects, unions, enums and
+//! input objects), sorted by name within each kind. [`Document::sorted`]
+//! additionally sorts the contents of each definition:
+//!
+//! - fields, arguments, input fields, enum values, union members and
+//!   implemented interfaces by name
+//! - the directives at each location by name, and their arguments by name.
+//!   Directives with the same name keep their order, as repeatable
+//!   directives may be order dependent
+//! - the locations of directive definitions by name, and the root operation
+//!   types of the schema by operation
+//! - type extensions by the name of the type they extend. Extensions of the
+//!   same type keep their order
+//!
+//! Descriptions are kept. Operations and fragments are left untouched, as the
+//! order of selections determines the order of the fields in a response
+
+use std::cmp::Ordering;
+
+use lasso::{Rodeo, Spur};
+
+use crate::ast::{
+    Argument, Directive, Document, Enum, FieldDefinition, InputObject, InputObjectField, Interface,
+    NamedType, ObjectType, RootOperationTypeDefinition, Scalar, TypeExtension, Union,
+};
+
+impl Document {
+    /// A copy of this document with the contents of its type system
+    /// definitions sorted
+    pub fn sorted(&self, interner: &Rodeo) -> Document {
+        let mut document = self.clone();
+        let sorter = Sorter { interner };
+
+        if let Some(schema) = &mut document.schema {
+            sorter.directives(&mut schema.directives);
+            sorter.operation_types(&mut schema.operation_types);
+        }
+
+        for extension in &mut document.schema_extensions {
+            sorter.directives(&mut extension.directives);
+            sorter.operation_types(&mut extension.operation_types);
+        }
+
+        for directive in document.directive_definitions.values_mut() {
+            sorter.input_values(directive.arguments.as_deref_mut().unwrap_or_default());
+            directive
+                .locations
+                .sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        }
+
+        for scalar in document.scalars.values_mut() {
+            sorter.scalar(scalar);
+        }
+
+        for object in document.output_objects.values_mut() {
+            sorter.object(object);
+        }
+
+        for interface in document.interfaces.values_mut() {
+            sorter.interface(interface);
+        }
+
+        for union in document.unions.values_mut() {
+            sorter.union(union);
+        }
+
+        for enum_ty in document.enums.values_mut() {
+            sorter.enum_ty(enum_ty);
+        }
+
+        for input in document.input_objects.values_mut() {
+            sorter.input_object(input);
+        }
+
+        for extension in &mut document.type_extensions {
+            match extension {
+                TypeExtension::Scalar(scalar) => sorter.scalar(scalar),
+                TypeExtension::Object(object) => sorter.object(object),
+                TypeExtension::Interface(interface) => sorter.interface(interface),
+                TypeExtension::Union(union) => sorter.union(union),
+                TypeExtension::Enum(enum_ty) => sorter.enum_ty(enum_ty),
+                TypeExtension::InputObject(input) => sorter.input_object(input),
+            }
+        }
+
+        document
+            .type_extensions
+            .sort_by(|a, b| sorter.cmp(a.name(), b.name()));
+
+        document
+    }
+}
+
+struct Sorter<'a> {
+    interner: &'a Rodeo,
+}
+
+impl Sorter<'_> {
+    fn cmp(&self, a: Spur, b: Spur) -> Ordering {
+        self.interner.resolve(&a).cmp(self.interner.resolve(&b))
+    }
+
+    fn operation_types(&self, operation_types: &mut [RootOperationTypeDefinition]) {
+        operation_types.sort_by_key(|operation_type| operation_type.kind as u8);
+    }
+
+    fn scalar(&self, scalar: &mut Scalar) {
+        self.directives(&mut scalar.directives);
+    }
+
+    fn object(&self, object: &mut ObjectType) {
+        self.named_types(&mut object.implements);
+        self.directives(&mut object.directives);
+        self.fields(object.fields.as_deref_mut().unwrap_or_default());
+    }
+
+    fn interface(&self, interface: &mut Interface) {
+        self.named_types(&mut interface.implements);
+        self.directives(&mut interface.directives);
+        self.fields(&mut interface.fields);
+    }
+
+    fn union(&self, union: &mut Union) {
+        self.directives(&mut union.directives);
+        self.named_types(&mut union.types);
+    }
+
+    fn enum_ty(&self, enum_ty: &mut Enum) {
+        self.directives(&mut enum_ty.directives);
+        enum_ty.variants.sort_by(|a, b| self.cmp(a.name, b.name));
+
+        for variant in &mut enum_ty.variants {
+            self.directives(&mut variant.directives);
+        }
+    }
+
+    fn input_object(&self, input: &mut InputObject) {
+        self.directives(&mut input.directives);
+        self.input_values(input.fields.as_deref_mut().unwrap_or_default());
+    }
+
+    fn fields(&self, fields: &mut [FieldDefinition]) {
+        fields.sort_by(|a, b| self.cmp(a.name, b.name));
+
+        for field in fields {
+            self.directives(&mut field.directives);
+            self.input_values(field.arguments.as_deref_mut().unwrap_or_default());
+        }
+    }
+
+    fn input_values(&self, values: &mut [InputObjectField]) {
+        values.sort_by(|a, b| self.cmp(a.name, b.name));
+
+        for value in values {
+            self.directives(&mut value.directives);
+        }
+    }
+
+    fn named_types(&self, types: &mut [NamedType]) {
+        types.sort_by(|a, b| self.cmp(a.0, b.0));
+    }
+
+    fn directives(&self, directives: &mut [Directive]) {
+        directives.sort_by(|a, b| self.cmp(a.name, b.name));
+
+        for directive in directives {
+            self.arguments(directive.arguments.as_deref_mut().unwrap_or_default());
+        }
+    }
+
+    fn arguments(&self, arguments: &mut [Argument]) {
+        arguments.sort_by(|a, b| self.cmp(a.name, b.name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use crate::{fmt::print_document, GraphqlParser};
+
+    fn sorted(source: &str) -> String {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(source.as_bytes(), &mut interner)
+            .expect("failed to parse document");
+
+        print_document(&document.sorted(&interner), &interner)
+    }
+
+    #[test]
+    fn sorts_definition_contents_and_keeps_descriptions() {
+        assert_eq!(
+            sorted(concat!(
+                "type User implements Node & Entity @key(fields: \"id\") @auth(role: ADMIN) {\n",
+                "  \"The user's name\"\n",
+                "  name(upper: Boolean, locale: String): String\n",
+                "  id: ID!\n",
+                "}\n",
+                "enum Role { USER ADMIN }\n",
+                "union Result = User | Error\n",
+                "directive @auth(role: Role, scope: String) on OBJECT | FIELD_DEFINITION\n",
+            )),
+            sorted(concat!(
+                "directive @auth(scope: String, role: Role) on FIELD_DEFINITION | OBJECT\n",
+                "union Result = Error | User\n",
+                "enum Role { ADMIN USER }\n",
+                "type User implements Entity & Node @auth(role: ADMIN) @key(fields: \"id\") {\n",
+                "  id: ID!\n",
+                "  \"The user's name\"\n",
+                "  name(locale: String, upper: Boolean): String\n",
+                "}\n",
+            )),
+        );
+    }
+
+    #[test]
+    fn prints_sorted_sdl() {
+        assert_eq!(
+            sorted(concat!(
+                "schema { mutation: M query: Q }\n",
+                "\"An input\" input I { b: Int a: Int @tag(name: \"y\") @tag(name: \"x\") }\n",
+            )),
+            concat!(
+                "schema {\n",
+                "  query: Q\n",
+                "  mutation: M\n",
+                "}\n",
+                "\n",
+                "\"\"\"An input\"\"\"\n",
+                "input I {\n",
+                "  a: Int @tag(name: \"y\") @tag(name: \"x\")\n",
+                "  b: Int\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn sorts_extensions() {
+        assert_eq!(
+            sorted(concat!(
+                "extend type B { y: Int x: Int }\n",
+                "extend type A @b @a\n",
+                "extend type B { w: Int }\n",
+            )),
+            concat!(
+                "extend type A @a @b\n",
+                "\n",
+                "extend type B {\n",
+                "  x: Int\n",
+                "  y: Int\n",
+                "}\n",
+                "\n",
+                "extend type B {\n",
+                "  w: Int\n",
+                "}\n",
+            )
+        );
+    }
+}
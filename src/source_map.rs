@@ -0,0 +1,306 @@
+//! Locations of the nodes of a document, kept apart from the document itself
+//!
+//! Each node with a span is identified by a [`NodeId`], its index in a
+//! pre-order traversal that visits the definitions stored in the document's
+//! maps sorted by name, so the same source always gives the same ids. A
+//! [`SourceMap`] records the span of each node, which lets a document parsed
+//! without the `spans` feature still be given precise diagnostics. See
+//! [`GraphqlParser::parse_with_source_map`](crate::GraphqlParser::parse_with_source_map)
+
+use lasso::Rodeo;
+
+use crate::{
+    ast::{
+        Argument, Directive, DirectiveDefinition, Document, EnumVariant, FieldDefinition, Fragment,
+        InputObjectField, Operation, SchemaDefinition, SchemaExtension, Selection, Span,
+        TypeDefinitionRef, TypeExtension, VariableDefinition,
+    },
+    visit::{self, Visitor},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub u32);
+
+/// A node with a span
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    SchemaDefinition(&'a SchemaDefinition),
+    SchemaExtension(&'a SchemaExtension),
+    Operation(&'a Operation),
+    Fragment(&'a Fragment),
+    VariableDefinition(&'a VariableDefinition),
+    Selection(&'a Selection),
+    TypeDefinition(TypeDefinitionRef<'a>),
+    TypeExtension(&'a TypeExtension),
+    DirectiveDefinition(&'a DirectiveDefinition),
+    FieldDefinition(&'a FieldDefinition),
+    /// An argument definition or an input object field
+    InputValueDefinition(&'a InputObjectField),
+    EnumVariant(&'a EnumVariant),
+    Directive(&'a Directive),
+    Argument(&'a Argument),
+}
+
+impl<'a> Node<'a> {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::SchemaDefinition(schema) => schema.span,
+            Self::SchemaExtension(extension) => extension.span,
+            Self::Operation(operation) => operation.span,
+            Self::Fragment(fragment) => fragment.span,
+            Self::VariableDefinition(variable) => variable.span,
+            Self::Selection(selection) => selection.span(),
+            Self::TypeDefinition(ty) => ty.span(),
+            Self::TypeExtension(extension) => extension.span(),
+            Self::DirectiveDefinition(directive) => directive.span,
+            Self::FieldDefinition(field) => field.span,
+            Self::InputValueDefinition(input_value) => input_value.span,
+            Self::EnumVariant(variant) => variant.span,
+            Self::Directive(directive) => directive.span,
+            Self::Argument(argument) => argument.span,
+        }
+    }
+}
+
+/// The nodes of `document`, each at the index of its [`NodeId`]
+pub fn nodes<'a>(document: &'a Document, interner: &Rodeo) -> Vec<Node<'a>> {
+    let mut collector = NodeCollector { nodes: Vec::new() };
+
+    let name = |name| interner.resolve(&name);
+
+    if let Some(schema) = &document.schema {
+        collector.visit_schema_definition(schema);
+    }
+
+    let mut directives = document.directive_definitions.values().collect::<Vec<_>>();
+    directives.sort_by_key(|directive| name(directive.name));
+
+    for directive in directives {
+        collector.visit_directive_definition(directive);
+    }
+
+    let mut types = document.types().collect::<Vec<_>>();
+    types.sort_by_key(|ty| name(ty.name()));
+
+    for ty in types {
+        collector.visit_type_definition(ty);
+    }
+
+    for extension in &document.schema_extensions {
+        collector.visit_schema_extension(extension);
+    }
+
+    for extension in &document.type_extensions {
+        collector.visit_type_extension(extension);
+    }
+
+    let mut operations = document.operations.values().collect::<Vec<_>>();
+    operations.sort_by_key(|operation| (operation.name.map(name), operation.kind as u8));
+
+    for operation in operations {
+        collector.visit_operation(operation);
+    }
+
+    let mut fragments = document.fragments.values().collect::<Vec<_>>();
+    fragments.sort_by_key(|fragment| name(fragment.name));
+
+    for fragment in fragments {
+        collector.visit_fragment(fragment);
+    }
+
+    collector.nodes
+}
+
+/// The span of each node of a document, by [`NodeId`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    spans: Vec<Span>,
+}
+
+impl SourceMap {
+    pub fn new(document: &Document, interner: &Rodeo) -> Self {
+        Self {
+            spans: nodes(document, interner).iter().map(Node::span).collect(),
+        }
+    }
+
+    pub fn span(&self, id: NodeId) -> Option<Span> {
+        self.spans.get(id.0 as usize).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// The innermost node whose span contains `offset`
+    pub fn node_at(&self, offset: usize) -> Option<NodeId> {
+        self.spans
+            .iter()
+            .enumerate()
+            .filter(|(_, span)| span.start <= offset && offset < span.end)
+            // later nodes are nested inside earlier ones with the same span
+            .min_by_key(|(idx, span)| (span.end - span.start, usize::MAX - idx))
+            .map(|(idx, _)| NodeId(idx as u32))
+    }
+}
+
+struct NodeCollector<'a> {
+    nodes: Vec<Node<'a>>,
+}
+
+impl<'a> Visitor<'a> for NodeCollector<'a> {
+    fn visit_schema_definition(&mut self, schema: &'a SchemaDefinition) {
+        self.nodes.push(Node::SchemaDefinition(schema));
+        visit::walk_schema_definition(self, schema);
+    }
+
+    fn visit_schema_extension(&mut self, extension: &'a SchemaExtension) {
+        self.nodes.push(Node::SchemaExtension(extension));
+        visit::walk_schema_extension(self, extension);
+    }
+
+    fn visit_operation(&mut self, operation: &'a Operation) {
+        self.nodes.push(Node::Operation(operation));
+        visit::walk_operation(self, operation);
+    }
+
+    fn visit_fragment(&mut self, fragment: &'a Fragment) {
+        self.nodes.push(Node::Fragment(fragment));
+        visit::walk_fragment(self, fragment);
+    }
+
+    fn visit_variable_definition(&mut self, variable: &'a VariableDefinition) {
+        self.nodes.push(Node::VariableDefinition(variable));
+        visit::walk_variable_definition(self, variable);
+    }
+
+    fn visit_selection(&mut self, selection: &'a Selection) {
+        self.nodes.push(Node::Selection(selection));
+        visit::walk_selection(self, selection);
+    }
+
+    fn visit_type_definition(&mut self, ty: TypeDefinitionRef<'a>) {
+        self.nodes.push(Node::TypeDefinition(ty));
+        visit::walk_type_definition(self, ty);
+    }
+
+    fn visit_type_extension(&mut self, extension: &'a TypeExtension) {
+        self.nodes.push(Node::TypeExtension(extension));
+        visit::walk_type_extension(self, extension);
+    }
+
+    fn visit_directive_definition(&mut self, directive: &'a DirectiveDefinition) {
+        self.nodes.push(Node::DirectiveDefinition(directive));
+        visit::walk_directive_definition(self, directive);
+    }
+
+    fn visit_field_definition(&mut self, field: &'a FieldDefinition) {
+        self.nodes.push(Node::FieldDefinition(field));
+        visit::walk_field_definition(self, field);
+    }
+
+    fn visit_input_value_definition(&mut self, input_value: &'a InputObjectField) {
+        self.nodes.push(Node::InputValueDefinition(input_value));
+        visit::walk_input_value_definition(self, input_value);
+    }
+
+    fn visit_enum_variant(&mut self, variant: &'a EnumVariant) {
+        self.nodes.push(Node::EnumVariant(variant));
+        visit::walk_enum_variant(self, variant);
+    }
+
+    fn visit_directive(&mut self, directive: &'a Directive) {
+        self.nodes.push(Node::Directive(directive));
+        visit::walk_directive(self, directive);
+    }
+
+    fn visit_argument(&mut self, argument: &'a Argument) {
+        self.nodes.push(Node::Argument(argument));
+        visit::walk_argument(self, argument);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{nodes, Node, NodeId};
+    use crate::{ast::Selection, GraphqlParser};
+
+    const SOURCE: &str = "query Q($id: ID) { user(id: $id) { name @a } }
+type User { name: String }
+fragment F on User { name }
+";
+
+    #[test]
+    fn spans_of_nodes() {
+        let mut interner = Rodeo::default();
+        let (document, source_map) =
+            GraphqlParser::parse_with_source_map(SOURCE.as_bytes(), &mut interner).unwrap();
+
+        let nodes = nodes(&document, &interner);
+        assert_eq!(nodes.len(), source_map.len());
+
+        let text = |id: NodeId| {
+            let span = source_map.span(id).unwrap();
+            &SOURCE[span.start..span.end]
+        };
+
+        // types come before operations, which come before fragments
+        assert!(matches!(nodes[0], Node::TypeDefinition(..)));
+        assert_eq!(text(NodeId(0)), "type User { name: String }");
+        assert_eq!(text(NodeId(1)), "name: String");
+
+        let field = nodes
+            .iter()
+            .position(|node| {
+                matches!(node, Node::Selection(Selection::Field { name, .. })
+                    if interner.resolve(name) == "user")
+            })
+            .unwrap();
+        assert_eq!(text(NodeId(field as u32)), "user(id: $id) { name @a }");
+
+        assert!(source_map.span(NodeId(nodes.len() as u32)).is_none());
+    }
+
+    #[test]
+    fn node_at_finds_innermost_node() {
+        let mut interner = Rodeo::default();
+        let (_, source_map) =
+            GraphqlParser::parse_with_source_map(SOURCE.as_bytes(), &mut interner).unwrap();
+
+        let text_at = |offset: usize| {
+            let span = source_map
+                .span(source_map.node_at(offset).unwrap())
+                .unwrap();
+            &SOURCE[span.start..span.end]
+        };
+
+        assert_eq!(text_at(SOURCE.find("$id:").unwrap()), "$id: ID");
+        assert_eq!(text_at(SOURCE.find("id: $id").unwrap()), "id: $id");
+        assert_eq!(text_at(SOURCE.find("@a").unwrap()), "@a");
+        assert_eq!(text_at(SOURCE.find("name @a").unwrap()), "name @a");
+        assert_eq!(text_at(0), &SOURCE[..SOURCE.find('\n').unwrap()]);
+        assert_eq!(source_map.node_at(SOURCE.find('\n').unwrap()), None);
+    }
+
+    #[test]
+    fn ids_are_stable() {
+        let mut interner = Rodeo::default();
+        let (_, first) =
+            GraphqlParser::parse_with_source_map(SOURCE.as_bytes(), &mut interner).unwrap();
+
+        // a fresh interner gives different keys, but the same node ids
+        let mut interner = Rodeo::default();
+        interner.get_or_intern("unrelated");
+        let (_, second) =
+            GraphqlParser::parse_with_source_map(SOURCE.as_bytes(), &mut interner).unwrap();
+
+        assert_eq!(first, second);
+        assert!(!first.is_empty());
+    }
+}
@@ -0,0 +1,359 @@
+//! Experimental generation of SQL tables from annotated object types, for
+//! prototyping a database from a schema
+//!
+//! Object types with `@table` become tables, named by its `name` argument or
+//! by the type's name in snake_case. Scalar and enum fields become columns,
+//! which are `NOT NULL` when the field is non-null, and fields whose type is
+//! another table become foreign key columns named `{field}_id`. `@column`
+//! overrides a column's `name` and `type`, and can mark it as the
+//! `primaryKey`, which is otherwise an `id: ID!` field
+//!
+//! Lists of scalars become arrays, as in PostgreSQL, while lists of tables
+//! are left to a foreign key on the other side. Foreign keys are added after
+//! every table is created, so tables can refer to each other
+
+use std::fmt;
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{Directive, Document, FieldDefinition, ObjectType, Type, TypeKind, Value},
+    codegen::to_snake_case,
+};
+
+/// The definitions of the directives that annotate tables and columns, for
+/// schemas to include
+pub const SQL_DIRECTIVES: &str = concat!(
+    "directive @table(name: String) on OBJECT\n",
+    "directive @column(name: String, type: String, primaryKey: Boolean) on FIELD_DEFINITION\n",
+);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqlError {
+    /// A field refers to a table without a primary key, so there is nothing
+    /// for its foreign key to reference
+    MissingPrimaryKey { coordinate: String, table: String },
+}
+
+impl fmt::Display for SqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPrimaryKey { coordinate, table } => write!(
+                f,
+                "`{}` refers to table `{}`, which has no primary key",
+                coordinate, table
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SqlError {}
+
+/// `CREATE TABLE` statements for the `@table` object types of `schema`, in
+/// order of table name, followed by `ALTER TABLE` statements adding their
+/// foreign keys
+pub fn sql_from_document(schema: &Document, interner: &Rodeo) -> Result<String, Vec<SqlError>> {
+    let generator = SqlGenerator { schema, interner };
+
+    let mut tables = schema
+        .output_objects
+        .values()
+        .filter(|object| generator.directive(&object.directives, "table").is_some())
+        .collect::<Vec<_>>();
+
+    tables.sort_by_key(|object| generator.table_name(object));
+
+    let mut statements = Vec::new();
+    let mut foreign_keys = Vec::new();
+    let mut errors = Vec::new();
+
+    for object in tables {
+        let table = generator.table_name(object);
+        let mut columns = Vec::new();
+        let mut primary_key = None;
+
+        for field in object.fields.iter().flatten() {
+            let column = match generator.column(object, field) {
+                Ok(Some(column)) => column,
+                Ok(None) => continue,
+                Err(err) => {
+                    errors.push(err);
+                    continue;
+                }
+            };
+
+            if generator.is_primary_key(field) {
+                primary_key = Some(column.name.clone());
+            }
+
+            if let Some(reference) = column.reference {
+                foreign_keys.push(format!(
+                    "ALTER TABLE {} ADD FOREIGN KEY ({}) REFERENCES {} ({});",
+                    table, column.name, reference.0, reference.1
+                ));
+            }
+
+            let not_null = if column.is_nullable { "" } else { " NOT NULL" };
+            columns.push(format!("  {} {}{}", column.name, column.ty, not_null));
+        }
+
+        if let Some(primary_key) = primary_key {
+            columns.push(format!("  PRIMARY KEY ({})", primary_key));
+        }
+
+        statements.push(format!(
+            "CREATE TABLE {} (\n{}\n);",
+            table,
+            columns.join(",\n")
+        ));
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    statements.extend(foreign_keys);
+
+    Ok(statements.join("\n\n") + "\n")
+}
+
+struct Column {
+    name: String,
+    ty: String,
+    is_nullable: bool,
+    /// The table and column a foreign key refers to
+    reference: Option<(String, String)>,
+}
+
+struct SqlGenerator<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+}
+
+impl<'a> SqlGenerator<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn directive<'b>(&self, directives: &'b [Directive], name: &str) -> Option<&'b Directive> {
+        directives
+            .iter()
+            .find(|directive| self.name(directive.name) == name)
+    }
+
+    fn argument<'b>(&self, directive: Option<&'b Directive>, name: &str) -> Option<&'b Value> {
+        directive?
+            .arguments
+            .iter()
+            .flatten()
+            .find(|argument| self.name(argument.name) == name)
+            .map(|argument| &argument.value)
+    }
+
+    fn string_argument(&self, directive: Option<&Directive>, name: &str) -> Option<&'a str> {
+        match self.argument(directive, name)? {
+            Value::String(value) => Some(self.name(*value)),
+            _ => None,
+        }
+    }
+
+    fn table_name(&self, object: &ObjectType) -> String {
+        let table = self.directive(&object.directives, "table");
+
+        self.string_argument(table, "name")
+            .map_or_else(|| to_snake_case(self.name(object.name)), str::to_owned)
+    }
+
+    fn is_primary_key(&self, field: &FieldDefinition) -> bool {
+        let column = self.directive(&field.directives, "column");
+
+        match self.argument(column, "primaryKey") {
+            Some(value) => matches!(value, Value::True),
+            None => {
+                self.name(field.name) == "id"
+                    && matches!(
+                        field.ty,
+                        Type::Named {
+                            nullable: false,
+                            ..
+                        }
+                    )
+                    && self.name(field.ty.name()) == "ID"
+            }
+        }
+    }
+
+    /// The primary key of the table `object`, along with its type
+    fn primary_key(&self, object: &ObjectType) -> Option<(String, String)> {
+        let field = object
+            .fields
+            .iter()
+            .flatten()
+            .find(|field| self.is_primary_key(field))?;
+
+        let column = self.directive(&field.directives, "column");
+
+        Some((
+            self.column_name(field, ""),
+            self.string_argument(column, "type").map_or_else(
+                || self.scalar_type(field.ty.name()).to_owned(),
+                str::to_owned,
+            ),
+        ))
+    }
+
+    fn column_name(&self, field: &FieldDefinition, suffix: &str) -> String {
+        let column = self.directive(&field.directives, "column");
+
+        self.string_argument(column, "name").map_or_else(
+            || to_snake_case(self.name(field.name)) + suffix,
+            str::to_owned,
+        )
+    }
+
+    fn scalar_type(&self, name: Spur) -> &'static str {
+        match self.name(name) {
+            "Int" => "INTEGER",
+            "Float" => "DOUBLE PRECISION",
+            "Boolean" => "BOOLEAN",
+            // as well as enums and custom scalars
+            _ => "TEXT",
+        }
+    }
+
+    fn is_scalar(&self, name: Spur) -> bool {
+        matches!(
+            self.name(name),
+            "Int" | "Float" | "String" | "ID" | "Boolean"
+        ) || matches!(
+            self.schema.type_kind(name),
+            Some(TypeKind::Scalar | TypeKind::Enum)
+        )
+    }
+
+    /// The column for `field` of the table `object`, or `None` if it isn't
+    /// stored in the table
+    fn column(
+        &self,
+        object: &ObjectType,
+        field: &FieldDefinition,
+    ) -> Result<Option<Column>, SqlError> {
+        let column = self.directive(&field.directives, "column");
+        let explicit_type = self.string_argument(column, "type");
+
+        let (ty, reference) = match &field.ty {
+            Type::Named { name, .. } if self.is_scalar(*name) => {
+                (self.scalar_type(*name).to_owned(), None)
+            }
+            Type::List { base, .. } if matches!(**base, Type::Named { .. }) => {
+                if !self.is_scalar(base.name()) {
+                    return Ok(None);
+                }
+
+                (format!("{}[]", self.scalar_type(base.name())), None)
+            }
+            Type::Named { name, .. } => {
+                let table = match self.schema.output_objects.get(name) {
+                    Some(table) if self.directive(&table.directives, "table").is_some() => table,
+                    _ => return Ok(None),
+                };
+
+                let (key, key_type) =
+                    self.primary_key(table)
+                        .ok_or_else(|| SqlError::MissingPrimaryKey {
+                            coordinate: format!(
+                                "{}.{}",
+                                self.name(object.name),
+                                self.name(field.name)
+                            ),
+                            table: self.table_name(table),
+                        })?;
+
+                let name = self.column_name(field, "_id");
+
+                return Ok(Some(Column {
+                    name,
+                    ty: explicit_type.map_or(key_type, str::to_owned),
+                    is_nullable: field.ty.is_nullable(),
+                    reference: Some((self.table_name(table), key)),
+                }));
+            }
+            Type::List { .. } => return Ok(None),
+        };
+
+        Ok(Some(Column {
+            name: self.column_name(field, ""),
+            ty: explicit_type.map_or(ty, str::to_owned),
+            is_nullable: field.ty.is_nullable(),
+            reference,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{sql_from_document, SQL_DIRECTIVES};
+    use crate::GraphqlParser;
+
+    fn sql(schema: &str) -> Result<String, Vec<String>> {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(
+            format!("{}{}", SQL_DIRECTIVES, schema).as_bytes(),
+            &mut interner,
+        )
+        .unwrap();
+
+        assert!(schema.validate_directive_usages(&interner).is_empty());
+
+        sql_from_document(&schema, &interner)
+            .map_err(|errors| errors.iter().map(ToString::to_string).collect())
+    }
+
+    #[test]
+    fn tables() {
+        let sql = sql(concat!(
+            "type User @table(name: \"users\") { id: ID! displayName: String ",
+            "age: Int! @column(type: \"SMALLINT\") role: Role! tags: [String!]! ",
+            "posts: [Post!]! bestFriend: User } ",
+            "type Post @table { slug: String! @column(primaryKey: true) ",
+            "author: User! @column(name: \"written_by\") score: Float } ",
+            "type Stats { views: Int } ",
+            "enum Role { ADMIN USER }",
+        ));
+
+        assert_eq!(
+            sql.unwrap(),
+            concat!(
+                "CREATE TABLE post (\n",
+                "  slug TEXT NOT NULL,\n",
+                "  written_by TEXT NOT NULL,\n",
+                "  score DOUBLE PRECISION,\n",
+                "  PRIMARY KEY (slug)\n",
+                ");\n\n",
+                "CREATE TABLE users (\n",
+                "  id TEXT NOT NULL,\n",
+                "  display_name TEXT,\n",
+                "  age SMALLINT NOT NULL,\n",
+                "  role TEXT NOT NULL,\n",
+                "  tags TEXT[] NOT NULL,\n",
+                "  best_friend_id TEXT,\n",
+                "  PRIMARY KEY (id)\n",
+                ");\n\n",
+                "ALTER TABLE post ADD FOREIGN KEY (written_by) REFERENCES users (id);\n\n",
+                "ALTER TABLE users ADD FOREIGN KEY (best_friend_id) REFERENCES users (id);\n",
+            )
+        );
+    }
+
+    #[test]
+    fn references_need_primary_keys() {
+        assert_eq!(
+            sql("type A @table { b: B } type B @table { name: String }"),
+            Err(vec![
+                "`A.b` refers to table `b`, which has no primary key".to_owned()
+            ])
+        );
+    }
+}
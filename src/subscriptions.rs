@@ -0,0 +1,608 @@
+//! A client for the graphql-transport-ws protocol, which executes operations,
+//! usually subscriptions, over a WebSocket
+//!
+//! A [`Connection`] multiplexes any number of [`Subscription`]s, each a
+//! [`Stream`] of responses. Messages are routed by a task spawned onto the
+//! current tokio runtime, which runs until the connection and all of its
+//! subscriptions are dropped or the server closes the connection
+//!
+//! See https://github.com/enisdenjo/graphql-ws/blob/master/PROTOCOL.md
+
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+};
+
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures_util::{stream, SinkExt, Stream, StreamExt};
+use lasso::Rodeo;
+use serde_json::{json, Map, Value as JsonValue};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{self, client::IntoClientRequest, http::HeaderValue, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{
+    ast::Document,
+    client::{Response, ResponseError},
+    request::{request_body, RequestError},
+};
+
+const PROTOCOL: &str = "graphql-transport-ws";
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug)]
+pub enum SubscriptionError {
+    /// The document doesn't contain the operation to execute
+    Request(RequestError),
+    /// The connection couldn't be established
+    Transport(tungstenite::Error),
+    /// The server sent a message that doesn't follow the protocol
+    Protocol { message: String },
+    /// The connection was closed or lost after it was established
+    Disconnected { reason: String },
+    /// The server rejected the operation
+    Graphql(Vec<ResponseError>),
+}
+
+impl fmt::Display for SubscriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(err) => write!(f, "{}", err),
+            Self::Transport(err) => write!(f, "connection failed: {}", err),
+            Self::Protocol { message } => write!(f, "protocol error: {}", message),
+            Self::Disconnected { reason } => write!(f, "disconnected: {}", reason),
+            Self::Graphql(errors) => {
+                let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+                write!(f, "{}", messages.join("\n"))
+            }
+        }
+    }
+}
+
+impl std::error::Error for SubscriptionError {}
+
+impl From<RequestError> for SubscriptionError {
+    fn from(err: RequestError) -> Self {
+        Self::Request(err)
+    }
+}
+
+impl From<tungstenite::Error> for SubscriptionError {
+    fn from(err: tungstenite::Error) -> Self {
+        Self::Transport(err)
+    }
+}
+
+fn protocol_error(message: impl Into<String>) -> SubscriptionError {
+    SubscriptionError::Protocol {
+        message: message.into(),
+    }
+}
+
+/// The JSON object of a protocol message, or `None` for WebSocket frames that
+/// aren't protocol messages, such as pings
+fn read_message(message: Message) -> Result<Option<Map<String, JsonValue>>, SubscriptionError> {
+    let text = match message {
+        Message::Text(text) => text,
+        Message::Close(frame) => {
+            return Err(SubscriptionError::Disconnected {
+                reason: frame.map_or_else(
+                    || "connection closed".to_owned(),
+                    |frame| format!("{} {}", u16::from(frame.code), frame.reason),
+                ),
+            })
+        }
+        _ => return Ok(None),
+    };
+
+    match serde_json::from_str(&text) {
+        Ok(JsonValue::Object(message)) if message.get("type").is_some_and(JsonValue::is_string) => {
+            Ok(Some(message))
+        }
+        _ => Err(protocol_error(format!("invalid message `{}`", text))),
+    }
+}
+
+fn message_type(message: &Map<String, JsonValue>) -> &str {
+    message["type"].as_str().unwrap_or_default()
+}
+
+fn text(message: JsonValue) -> Message {
+    Message::text(message.to_string())
+}
+
+type Responses = UnboundedSender<Result<Response, SubscriptionError>>;
+
+enum Command {
+    Subscribe {
+        id: String,
+        payload: JsonValue,
+        responses: Responses,
+    },
+    /// Stop the operation `id`, whose stream was dropped
+    Complete { id: String },
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Subscribe { id, .. } => write!(f, "Subscribe({})", id),
+            Self::Complete { id } => write!(f, "Complete({})", id),
+        }
+    }
+}
+
+/// A connection to a GraphQL server over which operations can be executed
+#[derive(Debug)]
+pub struct Connection {
+    commands: UnboundedSender<Command>,
+    next_id: AtomicU64,
+}
+
+impl Connection {
+    /// Connect to `url`, a `ws://` or `wss://` URL, sending `payload` with the
+    /// `connection_init` message, which servers commonly use to authenticate
+    ///
+    /// # Panics
+    ///
+    /// If not called from within a tokio runtime
+    pub async fn connect(url: &str, payload: Option<JsonValue>) -> Result<Self, SubscriptionError> {
+        let mut request = url.into_client_request()?;
+        request
+            .headers_mut()
+            .insert("Sec-WebSocket-Protocol", HeaderValue::from_static(PROTOCOL));
+
+        let (mut socket, _) = connect_async(request).await?;
+
+        let mut init = json!({ "type": "connection_init" });
+
+        if let Some(payload) = payload {
+            init["payload"] = payload;
+        }
+
+        socket.send(text(init)).await?;
+
+        loop {
+            let message = match socket.next().await {
+                Some(message) => read_message(message?)?,
+                None => return Err(protocol_error("closed before `connection_ack`")),
+            };
+
+            match message.as_ref().map(message_type) {
+                Some("connection_ack") => break,
+                Some("ping") => socket.send(text(json!({ "type": "pong" }))).await?,
+                Some(ty) => {
+                    return Err(protocol_error(format!(
+                        "expected `connection_ack`, found `{}`",
+                        ty
+                    )))
+                }
+                None => {}
+            }
+        }
+
+        let (commands, receiver) = unbounded();
+
+        tokio::spawn(route_messages(socket, receiver));
+
+        Ok(Self {
+            commands,
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Execute `query`, which was parsed into `document`. See
+    /// [`request_body`] for how the operation is chosen
+    ///
+    /// Dropping the returned stream stops the operation
+    pub fn subscribe(
+        &self,
+        document: &Document,
+        query: &str,
+        operation_name: Option<&str>,
+        variables: Map<String, JsonValue>,
+        interner: &Rodeo,
+    ) -> Result<Subscription, SubscriptionError> {
+        let payload = request_body(document, query, operation_name, variables, interner)?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let (responses, receiver) = unbounded();
+
+        self.commands
+            .unbounded_send(Command::Subscribe {
+                id: id.clone(),
+                payload,
+                responses,
+            })
+            .map_err(|_| SubscriptionError::Disconnected {
+                reason: "connection closed".to_owned(),
+            })?;
+
+        Ok(Subscription {
+            id,
+            responses: receiver,
+            commands: self.commands.clone(),
+        })
+    }
+}
+
+/// The responses to an operation, which end when the server completes it
+#[derive(Debug)]
+pub struct Subscription {
+    id: String,
+    responses: UnboundedReceiver<Result<Response, SubscriptionError>>,
+    commands: UnboundedSender<Command>,
+}
+
+impl Stream for Subscription {
+    type Item = Result<Response, SubscriptionError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.responses.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let _ = self.commands.unbounded_send(Command::Complete {
+            id: std::mem::take(&mut self.id),
+        });
+    }
+}
+
+enum Event {
+    Message(Result<Message, tungstenite::Error>),
+    Command(Command),
+    /// Every handle to the connection was dropped
+    Shutdown,
+}
+
+/// Send the commands of a connection and its subscriptions to the server,
+/// and route the server's messages to the subscriptions they're for
+async fn route_messages(socket: Socket, mut commands: UnboundedReceiver<Command>) {
+    let (mut sink, source) = socket.split();
+
+    let pending = (&mut commands)
+        .map(Event::Command)
+        .chain(stream::iter([Event::Shutdown]));
+    let mut events = stream::select(source.map(Event::Message), pending);
+
+    let mut subscriptions = HashMap::<String, Responses>::new();
+
+    let error = loop {
+        let event = match events.next().await {
+            Some(event) => event,
+            None => {
+                break SubscriptionError::Disconnected {
+                    reason: "connection closed".to_owned(),
+                }
+            }
+        };
+
+        let message = match event {
+            Event::Message(Ok(message)) => match read_message(message) {
+                Ok(Some(message)) => message,
+                Ok(None) => continue,
+                Err(err) => break err,
+            },
+            Event::Message(Err(err)) => {
+                break SubscriptionError::Disconnected {
+                    reason: err.to_string(),
+                }
+            }
+            Event::Command(Command::Subscribe {
+                id,
+                payload,
+                responses,
+            }) => {
+                let message = json!({ "id": id, "type": "subscribe", "payload": payload });
+                subscriptions.insert(id, responses);
+
+                match sink.send(text(message)).await {
+                    Ok(()) => continue,
+                    Err(err) => break err.into(),
+                }
+            }
+            Event::Command(Command::Complete { id }) => {
+                if subscriptions.remove(&id).is_none() {
+                    continue;
+                }
+
+                match sink
+                    .send(text(json!({ "id": id, "type": "complete" })))
+                    .await
+                {
+                    Ok(()) => continue,
+                    Err(err) => break err.into(),
+                }
+            }
+            Event::Shutdown => {
+                let _ = sink.close().await;
+                return;
+            }
+        };
+
+        let id = message.get("id").and_then(JsonValue::as_str);
+
+        match (message_type(&message), id) {
+            ("next", Some(id)) => {
+                let response = message
+                    .get("payload")
+                    .cloned()
+                    .ok_or_else(|| protocol_error("`next` without a payload"))
+                    .and_then(|payload| {
+                        Response::from_json(payload).map_err(|err| protocol_error(err.to_string()))
+                    });
+
+                if let Some(responses) = subscriptions.get(id) {
+                    let _ = responses.unbounded_send(response);
+                }
+            }
+            ("error", Some(id)) => {
+                let errors = match message.get("payload") {
+                    Some(JsonValue::Array(errors)) => errors
+                        .iter()
+                        .map(ResponseError::from_json)
+                        .collect::<Option<_>>()
+                        .map(SubscriptionError::Graphql)
+                        .unwrap_or_else(|| protocol_error("malformed error")),
+                    _ => protocol_error("`error` without a list of errors"),
+                };
+
+                if let Some(responses) = subscriptions.remove(id) {
+                    let _ = responses.unbounded_send(Err(errors));
+                }
+            }
+            ("complete", Some(id)) => {
+                subscriptions.remove(id);
+            }
+            ("ping", _) => {
+                if let Err(err) = sink.send(text(json!({ "type": "pong" }))).await {
+                    break err.into();
+                }
+            }
+            ("pong", _) => {}
+            (ty, _) => break protocol_error(format!("unexpected message `{}`", ty)),
+        }
+    };
+
+    let reason = match error {
+        SubscriptionError::Disconnected { reason } => reason,
+        err => err.to_string(),
+    };
+
+    drop(events);
+    commands.close();
+
+    // operations subscribed to after the connection was lost fail too
+    while let Ok(command) = commands.try_recv() {
+        if let Command::Subscribe { id, responses, .. } = command {
+            subscriptions.insert(id, responses);
+        }
+    }
+
+    for (_, responses) in subscriptions.drain() {
+        let _ = responses.unbounded_send(Err(SubscriptionError::Disconnected {
+            reason: reason.clone(),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{SinkExt, StreamExt};
+    use lasso::Rodeo;
+    use serde_json::{json, Map, Value as JsonValue};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::{
+        accept_hdr_async,
+        tungstenite::{
+            handshake::server::{ErrorResponse, Request, Response as HandshakeResponse},
+            http::HeaderValue,
+            Message,
+        },
+        WebSocketStream,
+    };
+
+    use super::{Connection, SubscriptionError, PROTOCOL};
+    use crate::GraphqlParser;
+
+    const SUBSCRIPTION: &str = "subscription OnTick { tick }";
+
+    type ServerSocket = WebSocketStream<tokio::net::TcpStream>;
+
+    async fn receive(socket: &mut ServerSocket) -> JsonValue {
+        loop {
+            match socket.next().await.unwrap().unwrap() {
+                Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+                Message::Close(..) => return JsonValue::Null,
+                _ => {}
+            }
+        }
+    }
+
+    async fn send(socket: &mut ServerSocket, message: JsonValue) {
+        socket
+            .send(Message::text(message.to_string()))
+            .await
+            .unwrap();
+    }
+
+    // the signature of a handshake callback is tungstenite's
+    #[allow(clippy::result_large_err)]
+    fn accept_protocol(
+        request: &Request,
+        mut response: HandshakeResponse,
+    ) -> Result<HandshakeResponse, ErrorResponse> {
+        assert_eq!(request.headers()["Sec-WebSocket-Protocol"], PROTOCOL);
+
+        response
+            .headers_mut()
+            .insert("Sec-WebSocket-Protocol", HeaderValue::from_static(PROTOCOL));
+
+        Ok(response)
+    }
+
+    /// Accept a single connection, check its initialization and hand it to
+    /// `handler`, returning the URL to connect to
+    async fn serve<F, Fut>(handler: F) -> String
+    where
+        F: FnOnce(ServerSocket) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = ()> + Send,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/graphql", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+
+            let mut socket = accept_hdr_async(stream, accept_protocol).await.unwrap();
+
+            assert_eq!(
+                receive(&mut socket).await,
+                json!({ "type": "connection_init", "payload": { "token": "secret" } })
+            );
+            send(&mut socket, json!({ "type": "connection_ack" })).await;
+
+            handler(socket).await;
+        });
+
+        url
+    }
+
+    fn run(test: impl std::future::Future<Output = ()>) {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(test);
+    }
+
+    async fn connect(url: &str) -> Connection {
+        Connection::connect(url, Some(json!({ "token": "secret" })))
+            .await
+            .unwrap()
+    }
+
+    fn document(interner: &mut Rodeo) -> crate::Document {
+        GraphqlParser::parse_with_interner(SUBSCRIPTION.as_bytes(), interner).unwrap()
+    }
+
+    #[test]
+    fn streams_responses() {
+        run(async {
+            let url = serve(|mut socket| async move {
+                assert_eq!(
+                    receive(&mut socket).await,
+                    json!({
+                        "id": "1",
+                        "type": "subscribe",
+                        "payload": {
+                            "query": SUBSCRIPTION,
+                            "operationName": "OnTick",
+                            "variables": {},
+                        },
+                    })
+                );
+
+                send(&mut socket, json!({ "type": "ping" })).await;
+                assert_eq!(receive(&mut socket).await, json!({ "type": "pong" }));
+
+                for tick in 0..2 {
+                    let payload = json!({ "data": { "tick": tick } });
+                    send(
+                        &mut socket,
+                        json!({ "id": "1", "type": "next", "payload": payload }),
+                    )
+                    .await;
+                }
+
+                send(&mut socket, json!({ "id": "1", "type": "complete" })).await;
+                receive(&mut socket).await;
+            })
+            .await;
+
+            let mut interner = Rodeo::default();
+            let document = document(&mut interner);
+            let connection = connect(&url).await;
+
+            let ticks = connection
+                .subscribe(
+                    &document,
+                    SUBSCRIPTION,
+                    Some("OnTick"),
+                    Map::new(),
+                    &interner,
+                )
+                .unwrap()
+                .map(|response| response.unwrap().data.unwrap())
+                .collect::<Vec<_>>()
+                .await;
+
+            assert_eq!(ticks, [json!({ "tick": 0 }), json!({ "tick": 1 })]);
+        });
+    }
+
+    #[test]
+    fn errors_and_cancellation() {
+        run(async {
+            let url = serve(|mut socket| async move {
+                assert_eq!(receive(&mut socket).await["id"], "1");
+                assert_eq!(receive(&mut socket).await["id"], "2");
+
+                let errors = json!([{ "message": "unknown field `tock`" }]);
+                send(
+                    &mut socket,
+                    json!({ "id": "1", "type": "error", "payload": errors }),
+                )
+                .await;
+
+                assert_eq!(
+                    receive(&mut socket).await,
+                    json!({ "id": "2", "type": "complete" })
+                );
+
+                socket.close(None).await.unwrap();
+            })
+            .await;
+
+            let mut interner = Rodeo::default();
+            let document = document(&mut interner);
+            let connection = connect(&url).await;
+
+            let subscribe = || {
+                connection
+                    .subscribe(&document, SUBSCRIPTION, None, Map::new(), &interner)
+                    .unwrap()
+            };
+
+            let mut rejected = subscribe();
+            let cancelled = subscribe();
+
+            match rejected.next().await {
+                Some(Err(SubscriptionError::Graphql(errors))) => {
+                    assert_eq!(errors[0].message, "unknown field `tock`")
+                }
+                other => panic!("expected errors, found {:?}", other),
+            }
+            assert!(rejected.next().await.is_none());
+
+            drop(cancelled);
+
+            let mut after_close = subscribe();
+
+            assert!(matches!(
+                after_close.next().await,
+                Some(Err(SubscriptionError::Disconnected { .. }))
+            ));
+        });
+    }
+}
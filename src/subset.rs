@@ -0,0 +1,539 @@
+//! Extraction of the part of a schema that a corpus of operations uses, for
+//! slim client bundles and per-team views of a schema
+//!
+//! The subset has the types, fields, arguments and directives that a
+//! [`SchemaUsage`] counts as used, and whatever else they need to form a
+//! valid schema:
+//!
+//! - the types of kept fields, arguments and input fields
+//! - the required arguments of kept fields and directives
+//! - the fields and arguments of kept interfaces, on the types implementing
+//!   them
+//!
+//! Enums and input objects keep all of their values and fields, as variables
+//! and responses may contain any of them. Interfaces and union members are
+//! only kept when they're used themselves, and directives applied in the
+//! schema only when their definition is kept or they're built in. Types used
+//! only through `__typename` keep no fields
+
+use std::collections::HashSet;
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        Definition, Directive, DirectiveDefinition, Document, FieldDefinition, InputObjectField,
+        NamedType, TypeDefinition, TypeDefinitionRef,
+    },
+    coordinate::SchemaCoordinate,
+    usage::SchemaUsage,
+};
+
+/// The part of `schema` used by the operations recorded in `usage`, which
+/// must have been computed against `schema`
+///
+/// `schema` should have had [`Document::apply_extensions`] called on it, as
+/// extensions aren't part of the subset
+pub fn schema_subset(schema: &Document, usage: &SchemaUsage, interner: &Rodeo) -> Document {
+    let mut subset = Subset {
+        schema,
+        usage,
+        interner,
+        types: HashSet::new(),
+    };
+
+    subset.close_over_types();
+
+    let mut document = Document::new();
+
+    for ty in schema.types() {
+        if subset.types.contains(&ty.name()) {
+            document.add_definition(Definition::TypeDecl(subset.ty(ty)));
+        }
+    }
+
+    for directive in schema.directive_definitions.values() {
+        if subset.keeps_directive(directive) {
+            document.add_definition(Definition::Directive(
+                subset.directive_definition(directive),
+            ));
+        }
+    }
+
+    document.schema = schema.schema.as_ref().map(|definition| {
+        let mut definition = definition.clone();
+        definition
+            .operation_types
+            .retain(|operation_type| subset.types.contains(&operation_type.ty.0));
+        definition.directives = subset.directives(&definition.directives);
+        definition
+    });
+
+    document
+}
+
+struct Subset<'a> {
+    schema: &'a Document,
+    usage: &'a SchemaUsage,
+    interner: &'a Rodeo,
+    /// The names of the kept types
+    types: HashSet<Spur>,
+}
+
+impl<'a> Subset<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn is_used(&self, coordinate: SchemaCoordinate) -> bool {
+        self.usage
+            .count(&coordinate.to_string())
+            .is_some_and(|count| count > 0)
+    }
+
+    /// Keep the used types, and the types they reference, until no more are
+    /// referenced. Keeping an interface can keep more fields of the types
+    /// implementing it, so this repeats until nothing changes
+    fn close_over_types(&mut self) {
+        self.types = self
+            .schema
+            .types()
+            .map(|ty| ty.name())
+            .filter(|ty| self.is_used(SchemaCoordinate::Type(self.name(*ty).to_owned())))
+            .collect();
+
+        loop {
+            let mut referenced = Vec::new();
+
+            for ty in self.schema.types() {
+                if !self.types.contains(&ty.name()) {
+                    continue;
+                }
+
+                match ty {
+                    TypeDefinitionRef::Object(object) => {
+                        let fields = object.fields.as_deref().unwrap_or_default();
+                        referenced.extend(self.field_types(ty.name(), &object.implements, fields));
+                    }
+                    TypeDefinitionRef::Interface(interface) => referenced.extend(self.field_types(
+                        ty.name(),
+                        &interface.implements,
+                        &interface.fields,
+                    )),
+                    TypeDefinitionRef::InputObject(input) => referenced
+                        .extend(input.fields.iter().flatten().map(|field| field.ty.name())),
+                    TypeDefinitionRef::Scalar(..)
+                    | TypeDefinitionRef::Union(..)
+                    | TypeDefinitionRef::Enum(..) => {}
+                }
+            }
+
+            for directive in self.schema.directive_definitions.values() {
+                if self.keeps_directive(directive) {
+                    let arguments = directive.arguments.as_deref().unwrap_or_default();
+                    let parent = SchemaCoordinate::directive(self.name(directive.name));
+
+                    referenced.extend(
+                        arguments
+                            .iter()
+                            .filter(|argument| self.keeps_argument(&parent, argument))
+                            .map(|argument| argument.ty.name()),
+                    );
+                }
+            }
+
+            let len = self.types.len();
+            self.types.extend(
+                referenced
+                    .into_iter()
+                    .filter(|ty| self.schema.type_kind(*ty).is_some()),
+            );
+
+            if self.types.len() == len {
+                break;
+            }
+        }
+    }
+
+    /// The types of the kept fields of `ty`, and of their kept arguments
+    fn field_types(
+        &self,
+        ty: Spur,
+        implements: &[NamedType],
+        fields: &[FieldDefinition],
+    ) -> Vec<Spur> {
+        self.fields(ty, implements, fields)
+            .into_iter()
+            .flat_map(|field| {
+                let arguments = field.arguments.unwrap_or_default();
+
+                std::iter::once(field.ty.name())
+                    .chain(arguments.into_iter().map(|argument| argument.ty.name()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// The kept fields of `ty`, with only their kept arguments
+    fn fields(
+        &self,
+        ty: Spur,
+        implements: &[NamedType],
+        fields: &[FieldDefinition],
+    ) -> Vec<FieldDefinition> {
+        fields
+            .iter()
+            .filter(|field| self.keeps_field(ty, implements, field.name, &mut Vec::new()))
+            .map(|field| {
+                let coordinate = SchemaCoordinate::member(self.name(ty), self.name(field.name));
+
+                let mut field = field.clone();
+                field.arguments = field.arguments.map(|arguments| {
+                    arguments
+                        .into_iter()
+                        .filter(|argument| {
+                            self.keeps_argument(&coordinate, argument)
+                                || self.interface_keeps_argument(
+                                    implements,
+                                    field.name,
+                                    argument.name,
+                                    &mut vec![ty],
+                                )
+                        })
+                        .collect()
+                });
+                field.directives = self.directives(&field.directives);
+                field
+            })
+            .collect()
+    }
+
+    /// Whether the field `field` of `ty` is used, or is a field of a kept
+    /// interface `ty` implements that's kept there. `seen` guards against
+    /// interfaces that implement themselves, which validation reports
+    fn keeps_field(
+        &self,
+        ty: Spur,
+        implements: &[NamedType],
+        field: Spur,
+        seen: &mut Vec<Spur>,
+    ) -> bool {
+        if seen.contains(&ty) {
+            return false;
+        }
+
+        seen.push(ty);
+
+        self.is_used(SchemaCoordinate::member(self.name(ty), self.name(field)))
+            || implements.iter().any(|NamedType(interface)| {
+                match self.schema.interfaces.get(interface) {
+                    Some(definition) if self.types.contains(interface) => {
+                        definition.fields.iter().any(|f| f.name == field)
+                            && self.keeps_field(*interface, &definition.implements, field, seen)
+                    }
+                    _ => false,
+                }
+            })
+    }
+
+    /// Whether a kept interface among `implements` keeps the argument
+    /// `argument` of its field `field`. `seen` guards against interfaces that
+    /// implement themselves, as in [`Self::keeps_field`]
+    fn interface_keeps_argument(
+        &self,
+        implements: &[NamedType],
+        field: Spur,
+        argument: Spur,
+        seen: &mut Vec<Spur>,
+    ) -> bool {
+        implements.iter().any(|NamedType(interface)| {
+            let definition = match self.schema.interfaces.get(interface) {
+                Some(definition) if self.types.contains(interface) => definition,
+                _ => return false,
+            };
+
+            if seen.contains(interface) {
+                return false;
+            }
+
+            seen.push(*interface);
+
+            let definition_argument = definition
+                .fields
+                .iter()
+                .filter(|f| f.name == field)
+                .flat_map(|f| f.arguments.iter().flatten())
+                .find(|a| a.name == argument);
+
+            let argument = match definition_argument {
+                Some(argument) => argument,
+                None => return false,
+            };
+
+            let coordinate = SchemaCoordinate::member(self.name(*interface), self.name(field));
+
+            (self.keeps_field(*interface, &definition.implements, field, &mut Vec::new())
+                && self.keeps_argument(&coordinate, argument))
+                || self.interface_keeps_argument(&definition.implements, field, argument.name, seen)
+        })
+    }
+
+    fn keeps_argument(&self, parent: &SchemaCoordinate, argument: &InputObjectField) -> bool {
+        let is_required = !argument.ty.is_nullable() && argument.default.is_none();
+
+        is_required
+            || parent
+                .argument(self.name(argument.name))
+                .is_some_and(|coordinate| self.is_used(coordinate))
+    }
+
+    fn keeps_directive(&self, directive: &DirectiveDefinition) -> bool {
+        self.is_used(SchemaCoordinate::directive(self.name(directive.name)))
+    }
+
+    fn directive_definition(&self, directive: &DirectiveDefinition) -> DirectiveDefinition {
+        let parent = SchemaCoordinate::directive(self.name(directive.name));
+
+        let mut directive = directive.clone();
+        directive.arguments = directive.arguments.map(|arguments| {
+            arguments
+                .into_iter()
+                .filter(|argument| self.keeps_argument(&parent, argument))
+                .collect()
+        });
+        directive
+    }
+
+    /// The applied `directives` that are built in or whose definitions are
+    /// kept
+    fn directives(&self, directives: &[Directive]) -> Vec<Directive> {
+        directives
+            .iter()
+            .filter(|directive| {
+                self.schema
+                    .directive_definitions
+                    .get(&directive.name)
+                    .is_none_or(|definition| self.keeps_directive(definition))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn ty(&self, ty: TypeDefinitionRef) -> TypeDefinition {
+        let kept_types = |types: &[NamedType]| {
+            types
+                .iter()
+                .filter(|NamedType(name)| self.types.contains(name))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        match ty {
+            TypeDefinitionRef::Object(object) => {
+                let mut object = object.clone();
+                object.fields = object
+                    .fields
+                    .as_deref()
+                    .map(|fields| self.fields(object.name, &object.implements, fields));
+                object.implements = kept_types(&object.implements);
+                object.directives = self.directives(&object.directives);
+                TypeDefinition::Object(object)
+            }
+            TypeDefinitionRef::Interface(interface) => {
+                let mut interface = interface.clone();
+                interface.fields =
+                    self.fields(interface.name, &interface.implements, &interface.fields);
+                interface.implements = kept_types(&interface.implements);
+                interface.directives = self.directives(&interface.directives);
+                TypeDefinition::Interface(interface)
+            }
+            TypeDefinitionRef::Union(union) => {
+                let mut union = union.clone();
+                union.types = kept_types(&union.types);
+                union.directives = self.directives(&union.directives);
+                TypeDefinition::Union(union)
+            }
+            TypeDefinitionRef::Scalar(scalar) => {
+                let mut scalar = scalar.clone();
+                scalar.directives = self.directives(&scalar.directives);
+                TypeDefinition::Scalar(scalar)
+            }
+            TypeDefinitionRef::Enum(enum_def) => {
+                let mut enum_def = enum_def.clone();
+                enum_def.directives = self.directives(&enum_def.directives);
+                TypeDefinition::Enum(enum_def)
+            }
+            TypeDefinitionRef::InputObject(input) => {
+                let mut input = input.clone();
+                input.directives = self.directives(&input.directives);
+                TypeDefinition::InputObject(input)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::schema_subset;
+    use crate::{fmt::print_document, usage::SchemaUsage, GraphqlParser};
+
+    const SCHEMA: &str = r#"
+type Query {
+  user(id: ID!, cache: Boolean): User
+  node(id: ID!): Node
+  search(filter: Filter): [Result!]!
+  unused: Post
+}
+
+type Mutation {
+  like(id: ID!): Post
+}
+
+interface Node {
+  id: ID!
+  createdAt: String
+}
+
+type User implements Node {
+  id: ID!
+  createdAt: String
+  name(format: NameFormat = FULL, locale: String): String @cached(ttl: 60)
+  email: String @deprecated
+}
+
+type Post implements Node {
+  id: ID!
+  createdAt: String
+  title: String
+}
+
+union Result = User | Post
+
+enum NameFormat {
+  FULL
+  SHORT
+}
+
+input Filter {
+  role: Role
+  text: String
+}
+
+enum Role {
+  ADMIN
+  USER
+}
+
+directive @cached(ttl: Int) on FIELD_DEFINITION
+
+directive @log(level: Int, tag: String!) on FIELD
+"#;
+
+    fn subset(operations: &str) -> String {
+        subset_of(SCHEMA, operations)
+    }
+
+    fn subset_of(schema: &str, operations: &str) -> String {
+        let mut interner = Rodeo::default();
+        let schema =
+            GraphqlParser::parse_schema_with_interner(schema.as_bytes(), &mut interner).unwrap();
+        let operations =
+            GraphqlParser::parse_query_with_interner(operations.as_bytes(), &mut interner).unwrap();
+
+        let mut usage = SchemaUsage::new(&schema, &interner);
+        usage.record(&schema, &operations, &interner);
+
+        print_document(&schema_subset(&schema, &usage, &interner), &interner)
+    }
+
+    #[test]
+    fn keeps_only_what_operations_use() {
+        let expected = r#"interface Node {
+  id: ID!
+}
+
+type Query {
+  user(id: ID!): User
+  node(id: ID!): Node
+}
+
+type User implements Node {
+  id: ID!
+  name(locale: String): String
+}
+"#;
+
+        assert_eq!(
+            subset(r#"{ user(id: 1) { name(locale: "en") } node(id: 2) { id } }"#),
+            expected
+        );
+    }
+
+    #[test]
+    fn keeps_referenced_types() {
+        let expected = r#"directive @log(tag: String!) on FIELD
+
+type Post {
+  title: String
+}
+
+type Query {
+  search(filter: Filter): [Result!]!
+}
+
+union Result = Post
+
+enum Role {
+  ADMIN
+  USER
+}
+
+input Filter {
+  role: Role
+  text: String
+}
+"#;
+
+        assert_eq!(
+            subset(
+                "query($f: Filter) { search(filter: $f) { ... on Post { title @log(tag: \"a\") } } }"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn keeps_root_types_of_used_operations() {
+        let printed = subset("mutation { like(id: 1) { id } }");
+
+        assert!(
+            printed.contains("type Mutation {\n  like(id: ID!): Post\n}"),
+            "{}",
+            printed
+        );
+        assert!(!printed.contains("type Query"), "{}", printed);
+    }
+
+    #[test]
+    fn keeps_arguments_of_kept_interface_fields() {
+        let schema = concat!(
+            "type Query { node: Node } ",
+            "interface Node { name(short: Boolean, locale: String): String } ",
+            "interface Named implements Named & Node { name(short: Boolean, locale: String): String } ",
+            "type User implements Node { name(short: Boolean, locale: String): String age: Int } ",
+        );
+
+        let printed = subset_of(schema, "{ node { name(short: true) ... on User { age } } }");
+
+        assert!(
+            printed.contains(
+                "type User implements Node {\n  name(short: Boolean): String\n  age: Int\n}"
+            ),
+            "{}",
+            printed
+        );
+        assert!(!printed.contains("Named"), "{}", printed);
+    }
+}
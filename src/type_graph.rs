@@ -0,0 +1,711 @@
+//! The graph of references between the types of a schema: an edge from a type
+//! to the type of each of its fields (and optionally their arguments), from a
+//! type to each interface it implements, and from a union to each of its
+//! members. The graph can be narrowed to the neighborhood of a type and
+//! printed as GraphViz DOT or a Mermaid flowchart
+//!
+//! The graph also backs analyses for schema cleanup on [`Document`]: the
+//! types unreachable from the root operation types, the cycles between types
+//! and the shortest chain of references from one type to another
+//!
+//! Types that are referenced without being defined, such as the built-in
+//! scalars, are included as scalars. Introspection types aren't included
+
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+
+use lasso::{Rodeo, Spur};
+
+use crate::ast::{Document, FieldDefinition, InputObjectField, NamedType, OperationKind, TypeKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeEdgeKind {
+    /// A field of the source type has the target type
+    Field(Spur),
+    /// An argument of a field of the source type has the target type
+    Argument { field: Spur, argument: Spur },
+    /// The source type implements the target interface
+    Implements,
+    /// The target type is a member of the source union
+    Member,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeEdge {
+    pub from: Spur,
+    pub to: Spur,
+    pub kind: TypeEdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeNode {
+    pub name: Spur,
+    pub kind: TypeKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeGraphOptions {
+    /// Include scalar types, and the fields and arguments referencing them
+    pub scalars: bool,
+    /// Include enum types, and the fields and arguments referencing them
+    pub enums: bool,
+    /// Include edges for the types of field arguments
+    pub arguments: bool,
+}
+
+impl Default for TypeGraphOptions {
+    fn default() -> Self {
+        Self {
+            scalars: true,
+            enums: true,
+            arguments: true,
+        }
+    }
+}
+
+/// The types of a schema, sorted by name, and the references between them.
+/// Edges are ordered by the name of their source type, then as declared
+#[derive(Debug, Clone)]
+pub struct TypeGraph {
+    pub nodes: Vec<TypeNode>,
+    pub edges: Vec<TypeEdge>,
+}
+
+impl TypeGraph {
+    pub fn new(schema: &Document, interner: &Rodeo, options: &TypeGraphOptions) -> Self {
+        let mut builder = TypeGraphBuilder {
+            schema,
+            interner,
+            options,
+            kinds: HashMap::new(),
+            edges: Vec::new(),
+        };
+
+        let mut types = schema
+            .types()
+            .filter(|ty| !interner.resolve(&ty.name()).starts_with("__"))
+            .collect::<Vec<_>>();
+
+        types.sort_by_key(|ty| interner.resolve(&ty.name()));
+
+        for ty in types {
+            let name = ty.name();
+
+            if !builder.include(name) {
+                continue;
+            }
+
+            builder.kinds.insert(name, builder.kind(name));
+
+            if let Some(object) = schema.output_objects.get(&name) {
+                builder.implements(name, &object.implements);
+                builder.fields(name, object.fields.as_deref().unwrap_or_default());
+            } else if let Some(interface) = schema.interfaces.get(&name) {
+                builder.implements(name, &interface.implements);
+                builder.fields(name, &interface.fields);
+            } else if let Some(union) = schema.unions.get(&name) {
+                for member in &union.types {
+                    builder.edge(name, member.0, TypeEdgeKind::Member);
+                }
+            } else if let Some(input) = schema.input_objects.get(&name) {
+                builder.input_fields(name, input.fields.as_deref().unwrap_or_default());
+            }
+        }
+
+        let mut nodes = builder
+            .kinds
+            .iter()
+            .map(|(name, kind)| TypeNode {
+                name: *name,
+                kind: *kind,
+            })
+            .collect::<Vec<_>>();
+
+        nodes.sort_by_key(|node| interner.resolve(&node.name));
+
+        Self {
+            nodes,
+            edges: builder.edges,
+        }
+    }
+
+    /// The part of this graph within `depth` edges of the type named `name`,
+    /// following edges in either direction. Empty if there is no such type
+    pub fn neighborhood(&self, name: Spur, depth: usize) -> Self {
+        let mut neighbors = HashMap::<Spur, Vec<Spur>>::new();
+
+        for edge in &self.edges {
+            neighbors.entry(edge.from).or_default().push(edge.to);
+            neighbors.entry(edge.to).or_default().push(edge.from);
+        }
+
+        let mut included = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        if self.nodes.iter().any(|node| node.name == name) {
+            included.insert(name);
+            queue.push_back((name, 0));
+        }
+
+        while let Some((ty, distance)) = queue.pop_front() {
+            if distance == depth {
+                continue;
+            }
+
+            for neighbor in neighbors.get(&ty).map(Vec::as_slice).unwrap_or_default() {
+                if included.insert(*neighbor) {
+                    queue.push_back((*neighbor, distance + 1));
+                }
+            }
+        }
+
+        Self {
+            nodes: self
+                .nodes
+                .iter()
+                .filter(|node| included.contains(&node.name))
+                .copied()
+                .collect(),
+            edges: self
+                .edges
+                .iter()
+                .filter(|edge| included.contains(&edge.from) && included.contains(&edge.to))
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// This graph in the GraphViz DOT language. Each kind of type has its own
+    /// node shape, and implementations and union memberships are dashed
+    pub fn to_dot(&self, interner: &Rodeo) -> String {
+        let mut dot = String::from("digraph schema {\n    rankdir=LR;\n");
+
+        for node in &self.nodes {
+            let shape = match node.kind {
+                TypeKind::Object => "box",
+                TypeKind::Interface => "box, style=rounded",
+                TypeKind::Union => "hexagon",
+                TypeKind::Enum => "folder",
+                TypeKind::InputObject => "parallelogram",
+                TypeKind::Scalar => "ellipse",
+            };
+
+            dot.push_str(&format!(
+                "    \"{}\" [shape={}];\n",
+                interner.resolve(&node.name),
+                shape
+            ));
+        }
+
+        for edge in &self.edges {
+            let attributes = match edge.kind {
+                TypeEdgeKind::Field(..) | TypeEdgeKind::Argument { .. } => {
+                    format!("label=\"{}\"", edge_label(edge, interner))
+                }
+                TypeEdgeKind::Implements => "style=dashed, arrowhead=empty".to_owned(),
+                TypeEdgeKind::Member => "style=dashed".to_owned(),
+            };
+
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [{}];\n",
+                interner.resolve(&edge.from),
+                interner.resolve(&edge.to),
+                attributes
+            ));
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    /// This graph as a Mermaid flowchart. Each kind of type has its own node
+    /// shape, and implementations and union memberships are dotted
+    pub fn to_mermaid(&self, interner: &Rodeo) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+
+        for node in &self.nodes {
+            let name = interner.resolve(&node.name);
+
+            let (open, close) = match node.kind {
+                TypeKind::Object => ("[", "]"),
+                TypeKind::Interface => ("{{", "}}"),
+                TypeKind::Union => ("{", "}"),
+                TypeKind::Enum => (">", "]"),
+                TypeKind::InputObject => ("[/", "/]"),
+                TypeKind::Scalar => ("([", "])"),
+            };
+
+            mermaid.push_str(&format!("    {}{}\"{}\"{}\n", name, open, name, close));
+        }
+
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                TypeEdgeKind::Field(..) | TypeEdgeKind::Argument { .. } => {
+                    format!("-->|\"{}\"|", edge_label(edge, interner))
+                }
+                TypeEdgeKind::Implements => "-.->|implements|".to_owned(),
+                TypeEdgeKind::Member => "-.->|member|".to_owned(),
+            };
+
+            mermaid.push_str(&format!(
+                "    {} {} {}\n",
+                interner.resolve(&edge.from),
+                arrow,
+                interner.resolve(&edge.to)
+            ));
+        }
+
+        mermaid
+    }
+}
+
+impl Document {
+    /// The types that can't be reached from the root operation types or the
+    /// arguments of directive definitions, sorted by name. An interface
+    /// reaches the types implementing it, as they may be returned in its place
+    pub fn unreachable_types(&self, interner: &Rodeo) -> Vec<Spur> {
+        let graph = TypeGraph::new(self, interner, &TypeGraphOptions::default());
+        let mut references = HashMap::<Spur, Vec<Spur>>::new();
+
+        for edge in &graph.edges {
+            references.entry(edge.from).or_default().push(edge.to);
+
+            if edge.kind == TypeEdgeKind::Implements {
+                references.entry(edge.to).or_default().push(edge.from);
+            }
+        }
+
+        let roots = [
+            OperationKind::Query,
+            OperationKind::Mutation,
+            OperationKind::Subscription,
+        ]
+        .into_iter()
+        .filter_map(|kind| self.root_operation_type_or_default(kind, interner))
+        .chain(
+            self.directive_definitions
+                .values()
+                .flat_map(|directive| directive.arguments.as_deref().unwrap_or_default())
+                .map(|argument| argument.ty.name()),
+        );
+
+        let mut reachable = HashSet::new();
+        let mut stack = roots.collect::<Vec<_>>();
+
+        while let Some(ty) = stack.pop() {
+            if reachable.insert(ty) {
+                stack.extend(references.get(&ty).map(Vec::as_slice).unwrap_or_default());
+            }
+        }
+
+        graph
+            .nodes
+            .iter()
+            .map(|node| node.name)
+            .filter(|name| !reachable.contains(name) && self.type_kind(*name).is_some())
+            .collect()
+    }
+
+    /// The cycles of references between types: the strongly connected
+    /// components of the type graph with more than one type, or with a type
+    /// referencing itself. Each cycle is sorted by name, as are the cycles by
+    /// their first type
+    pub fn type_cycles(&self, interner: &Rodeo) -> Vec<Vec<Spur>> {
+        let graph = TypeGraph::new(self, interner, &TypeGraphOptions::default());
+        let mut tarjan = Tarjan::default();
+
+        for edge in &graph.edges {
+            tarjan.edges.entry(edge.from).or_default().push(edge.to);
+        }
+
+        for node in &graph.nodes {
+            if !tarjan.indices.contains_key(&node.name) {
+                tarjan.visit(node.name);
+            }
+        }
+
+        let mut cycles = tarjan
+            .components
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1
+                    || graph
+                        .edges
+                        .iter()
+                        .any(|edge| edge.from == component[0] && edge.to == component[0])
+            })
+            .map(|mut component| {
+                component.sort_by_key(|name| interner.resolve(name));
+                component
+            })
+            .collect::<Vec<_>>();
+
+        cycles.sort_by_key(|cycle| interner.resolve(&cycle[0]));
+
+        cycles
+    }
+
+    /// The shortest chain of references from the type `from` to the type
+    /// `to`, as the edges of the type graph to follow, or `None` if `to` can't
+    /// be reached. Empty if they are the same type
+    pub fn shortest_type_path(
+        &self,
+        from: Spur,
+        to: Spur,
+        interner: &Rodeo,
+    ) -> Option<Vec<TypeEdge>> {
+        let graph = TypeGraph::new(self, interner, &TypeGraphOptions::default());
+        let mut edges = HashMap::<Spur, Vec<&TypeEdge>>::new();
+
+        for edge in &graph.edges {
+            edges.entry(edge.from).or_default().push(edge);
+        }
+
+        // the edge each type was first reached through
+        let mut reached_by = HashMap::<Spur, Option<&TypeEdge>>::new();
+        let mut queue = VecDeque::from([from]);
+
+        reached_by.insert(from, None);
+
+        while let Some(ty) = queue.pop_front() {
+            if ty == to {
+                let mut path = Vec::new();
+
+                while let Some(Some(edge)) =
+                    reached_by.get(&path.last().map_or(to, |edge: &TypeEdge| edge.from))
+                {
+                    path.push(**edge);
+                }
+
+                path.reverse();
+
+                return Some(path);
+            }
+
+            for edge in edges.get(&ty).map(Vec::as_slice).unwrap_or_default() {
+                if let Entry::Vacant(entry) = reached_by.entry(edge.to) {
+                    entry.insert(Some(edge));
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Tarjan's algorithm for the strongly connected components of a graph
+#[derive(Default)]
+struct Tarjan {
+    edges: HashMap<Spur, Vec<Spur>>,
+    indices: HashMap<Spur, usize>,
+    low_links: HashMap<Spur, usize>,
+    stack: Vec<Spur>,
+    on_stack: HashSet<Spur>,
+    components: Vec<Vec<Spur>>,
+}
+
+impl Tarjan {
+    fn visit(&mut self, node: Spur) {
+        let index = self.indices.len();
+
+        self.indices.insert(node, index);
+        self.low_links.insert(node, index);
+        self.stack.push(node);
+        self.on_stack.insert(node);
+
+        let successors = self.edges.get(&node).cloned().unwrap_or_default();
+
+        for successor in successors {
+            if !self.indices.contains_key(&successor) {
+                self.visit(successor);
+
+                let low_link = self.low_links[&node].min(self.low_links[&successor]);
+                self.low_links.insert(node, low_link);
+            } else if self.on_stack.contains(&successor) {
+                let low_link = self.low_links[&node].min(self.indices[&successor]);
+                self.low_links.insert(node, low_link);
+            }
+        }
+
+        if self.low_links[&node] == index {
+            let mut component = Vec::new();
+
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(&member);
+                component.push(member);
+
+                if member == node {
+                    break;
+                }
+            }
+
+            self.components.push(component);
+        }
+    }
+}
+
+/// The field, or `field(argument:)`, an edge is for
+fn edge_label(edge: &TypeEdge, interner: &Rodeo) -> String {
+    match edge.kind {
+        TypeEdgeKind::Field(field) => interner.resolve(&field).to_owned(),
+        TypeEdgeKind::Argument { field, argument } => format!(
+            "{}({}:)",
+            interner.resolve(&field),
+            interner.resolve(&argument)
+        ),
+        TypeEdgeKind::Implements => "implements".to_owned(),
+        TypeEdgeKind::Member => "member".to_owned(),
+    }
+}
+
+struct TypeGraphBuilder<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+    options: &'a TypeGraphOptions,
+    kinds: HashMap<Spur, TypeKind>,
+    edges: Vec<TypeEdge>,
+}
+
+impl TypeGraphBuilder<'_> {
+    /// The kind of the type named `name`. Types that aren't defined are
+    /// assumed to be built-in scalars
+    fn kind(&self, name: Spur) -> TypeKind {
+        self.schema.type_kind(name).unwrap_or(TypeKind::Scalar)
+    }
+
+    fn include(&self, name: Spur) -> bool {
+        if self.interner.resolve(&name).starts_with("__") {
+            return false;
+        }
+
+        match self.kind(name) {
+            TypeKind::Scalar => self.options.scalars,
+            TypeKind::Enum => self.options.enums,
+            _ => true,
+        }
+    }
+
+    fn edge(&mut self, from: Spur, to: Spur, kind: TypeEdgeKind) {
+        if !self.include(to) {
+            return;
+        }
+
+        let to_kind = self.kind(to);
+
+        self.kinds.insert(to, to_kind);
+        self.edges.push(TypeEdge { from, to, kind });
+    }
+
+    fn implements(&mut self, name: Spur, interfaces: &[NamedType]) {
+        for interface in interfaces {
+            self.edge(name, interface.0, TypeEdgeKind::Implements);
+        }
+    }
+
+    fn fields(&mut self, name: Spur, fields: &[FieldDefinition]) {
+        for field in fields {
+            self.edge(name, field.ty.name(), TypeEdgeKind::Field(field.name));
+
+            if !self.options.arguments {
+                continue;
+            }
+
+            for argument in field.arguments.as_deref().unwrap_or_default() {
+                let kind = TypeEdgeKind::Argument {
+                    field: field.name,
+                    argument: argument.name,
+                };
+
+                self.edge(name, argument.ty.name(), kind);
+            }
+        }
+    }
+
+    fn input_fields(&mut self, name: Spur, fields: &[InputObjectField]) {
+        for field in fields {
+            self.edge(name, field.ty.name(), TypeEdgeKind::Field(field.name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{TypeEdge, TypeEdgeKind, TypeGraph, TypeGraphOptions};
+    use crate::{ast::Document, GraphqlParser};
+
+    const SCHEMA: &str = "
+        type Query { user(id: ID!): User search(filter: Filter): [SearchResult!]! }
+        interface Node { id: ID! }
+        type User implements Node { id: ID! role: Role friends: [User!]! }
+        type Post implements Node { id: ID! author: User }
+        union SearchResult = User | Post
+        input Filter { text: String }
+        enum Role { ADMIN USER }
+    ";
+
+    fn parse(interner: &mut Rodeo) -> Document {
+        GraphqlParser::parse_schema_with_interner(SCHEMA.as_bytes(), interner)
+            .expect("failed to parse schema")
+    }
+
+    #[test]
+    fn mermaid_without_scalars_or_arguments() {
+        let mut interner = Rodeo::default();
+        let schema = parse(&mut interner);
+
+        let options = TypeGraphOptions {
+            scalars: false,
+            arguments: false,
+            ..TypeGraphOptions::default()
+        };
+
+        assert_eq!(
+            TypeGraph::new(&schema, &interner, &options).to_mermaid(&interner),
+            concat!(
+                "flowchart LR\n",
+                "    Filter[/\"Filter\"/]\n",
+                "    Node{{\"Node\"}}\n",
+                "    Post[\"Post\"]\n",
+                "    Query[\"Query\"]\n",
+                "    Role>\"Role\"]\n",
+                "    SearchResult{\"SearchResult\"}\n",
+                "    User[\"User\"]\n",
+                "    Post -.->|implements| Node\n",
+                "    Post -->|\"author\"| User\n",
+                "    Query -->|\"user\"| User\n",
+                "    Query -->|\"search\"| SearchResult\n",
+                "    SearchResult -.->|member| User\n",
+                "    SearchResult -.->|member| Post\n",
+                "    User -.->|implements| Node\n",
+                "    User -->|\"role\"| Role\n",
+                "    User -->|\"friends\"| User\n",
+            )
+        );
+    }
+
+    #[test]
+    fn dot_of_neighborhood() {
+        let mut interner = Rodeo::default();
+        let schema = parse(&mut interner);
+
+        let graph = TypeGraph::new(&schema, &interner, &TypeGraphOptions::default())
+            .neighborhood(interner.get("Filter").unwrap(), 1);
+
+        assert_eq!(
+            graph.to_dot(&interner),
+            concat!(
+                "digraph schema {\n",
+                "    rankdir=LR;\n",
+                "    \"Filter\" [shape=parallelogram];\n",
+                "    \"Query\" [shape=box];\n",
+                "    \"String\" [shape=ellipse];\n",
+                "    \"Filter\" -> \"String\" [label=\"text\"];\n",
+                "    \"Query\" -> \"Filter\" [label=\"search(filter:)\"];\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_neighborhood_is_empty() {
+        let mut interner = Rodeo::default();
+        let schema = parse(&mut interner);
+        let missing = interner.get_or_intern("Missing");
+
+        let graph = TypeGraph::new(&schema, &interner, &TypeGraphOptions::default())
+            .neighborhood(missing, 2);
+
+        assert!(graph.nodes.is_empty());
+        assert!(graph.edges.is_empty());
+    }
+
+    #[test]
+    fn unreachable_types() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_schema_with_interner(
+            b"
+            type Query { node: Node }
+            interface Node { id: ID! }
+            type User implements Node { id: ID! address: Address }
+            type Address { city: String }
+            type Orphan { sibling: Orphan filter: Filter }
+            input Filter { text: String }
+            input Limit { first: Int }
+            directive @paginate(limit: Limit) on FIELD_DEFINITION
+            ",
+            &mut interner,
+        )
+        .expect("failed to parse schema");
+
+        let unreachable = schema
+            .unreachable_types(&interner)
+            .into_iter()
+            .map(|name| interner.resolve(&name))
+            .collect::<Vec<_>>();
+
+        assert_eq!(unreachable, ["Filter", "Orphan"]);
+    }
+
+    #[test]
+    fn type_cycles() {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_schema_with_interner(
+            b"
+            type Query { a: A self: Self }
+            type A { b: B }
+            type B { c: C name: String }
+            type C { a: A }
+            type Self { parent: Self }
+            ",
+            &mut interner,
+        )
+        .expect("failed to parse schema");
+
+        let cycles = schema
+            .type_cycles(&interner)
+            .into_iter()
+            .map(|cycle| {
+                cycle
+                    .into_iter()
+                    .map(|name| interner.resolve(&name))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(cycles, [vec!["A", "B", "C"], vec!["Self"]]);
+    }
+
+    #[test]
+    fn shortest_type_path() {
+        let mut interner = Rodeo::default();
+        let schema = parse(&mut interner);
+        let query = interner.get("Query").unwrap();
+        let user = interner.get("User").unwrap();
+        let role = interner.get("Role").unwrap();
+        let filter = interner.get("Filter").unwrap();
+
+        assert_eq!(
+            schema.shortest_type_path(query, role, &interner),
+            Some(vec![
+                TypeEdge {
+                    from: query,
+                    to: user,
+                    kind: TypeEdgeKind::Field(interner.get("user").unwrap()),
+                },
+                TypeEdge {
+                    from: user,
+                    to: role,
+                    kind: TypeEdgeKind::Field(interner.get("role").unwrap()),
+                },
+            ])
+        );
+        assert_eq!(
+            schema.shortest_type_path(user, user, &interner),
+            Some(Vec::new())
+        );
+        assert_eq!(schema.shortest_type_path(filter, query, &interner), None);
+    }
+}
@@ -0,0 +1,498 @@
+//! Reporting of which parts of a schema a corpus of operations uses, and how
+//! often, to find types, fields, arguments and enum values that no client
+//! references
+//!
+//! Everything is identified by its schema coordinate: `User`, `User.name`,
+//! `Query.user(id:)`, `Role.ADMIN`, `Filter.role`, `@cached` or
+//! `@cached(ttl:)`. Built-in scalars and directives aren't counted unless the
+//! schema defines them
+//!
+//! Fields selected through an interface count as uses of the interface's
+//! field, not of the fields of the types implementing it. Enum values and
+//! input fields are only counted when written as literals, as the values of
+//! variables aren't known
+
+use std::{collections::BTreeMap, fmt};
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        Argument, Directive, Document, InputObjectField, Selection, Type, TypeDefinitionRef, Value,
+    },
+    coordinate::SchemaCoordinate,
+};
+
+/// The number of times each schema coordinate is referenced
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaUsage {
+    counts: BTreeMap<String, usize>,
+}
+
+impl SchemaUsage {
+    /// Every coordinate of `schema`, unused
+    pub fn new(schema: &Document, interner: &Rodeo) -> Self {
+        let name = |name: &Spur| interner.resolve(name);
+        let mut coordinates = Vec::new();
+
+        let mut add_with_arguments = |parent: SchemaCoordinate, arguments: &[InputObjectField]| {
+            for argument in arguments {
+                coordinates.extend(parent.argument(name(&argument.name)));
+            }
+
+            coordinates.push(parent);
+        };
+
+        for ty in schema.types() {
+            let type_name = name(&ty.name());
+
+            add_with_arguments(SchemaCoordinate::Type(type_name.to_owned()), &[]);
+
+            match ty {
+                TypeDefinitionRef::Object(object) => {
+                    for field in object.fields.iter().flatten() {
+                        add_with_arguments(
+                            SchemaCoordinate::member(type_name, name(&field.name)),
+                            field.arguments.as_deref().unwrap_or_default(),
+                        );
+                    }
+                }
+                TypeDefinitionRef::Interface(interface) => {
+                    for field in &interface.fields {
+                        add_with_arguments(
+                            SchemaCoordinate::member(type_name, name(&field.name)),
+                            field.arguments.as_deref().unwrap_or_default(),
+                        );
+                    }
+                }
+                TypeDefinitionRef::Enum(enum_) => {
+                    for variant in &enum_.variants {
+                        add_with_arguments(
+                            SchemaCoordinate::member(type_name, name(&variant.name)),
+                            &[],
+                        );
+                    }
+                }
+                TypeDefinitionRef::InputObject(input) => {
+                    for field in input.fields.iter().flatten() {
+                        add_with_arguments(
+                            SchemaCoordinate::member(type_name, name(&field.name)),
+                            &[],
+                        );
+                    }
+                }
+                TypeDefinitionRef::Scalar(..) | TypeDefinitionRef::Union(..) => {}
+            }
+        }
+
+        for directive in schema.directive_definitions.values() {
+            add_with_arguments(
+                SchemaCoordinate::directive(name(&directive.name)),
+                directive.arguments.as_deref().unwrap_or_default(),
+            );
+        }
+
+        Self {
+            counts: coordinates
+                .into_iter()
+                .map(|coordinate| (coordinate.to_string(), 0))
+                .collect(),
+        }
+    }
+
+    /// Count the uses of `schema` by the operations and fragments of
+    /// `document`. Fragments are counted once, where they're defined, rather
+    /// than at each spread
+    ///
+    /// Both documents must have been parsed with the same `interner`
+    pub fn record(&mut self, schema: &Document, document: &Document, interner: &Rodeo) {
+        let mut recorder = Recorder {
+            schema,
+            interner,
+            counts: &mut self.counts,
+        };
+
+        for operation in document.operations.values() {
+            for variable in &operation.variable_definitions {
+                recorder.add(SchemaCoordinate::Type(
+                    recorder.name(variable.ty.name()).to_owned(),
+                ));
+
+                if let Some(default) = &variable.default {
+                    recorder.record_value(default, &variable.ty);
+                }
+            }
+
+            recorder.record_directives(&operation.directives);
+
+            if let Some(root) = schema.root_operation_type_or_default(operation.kind, interner) {
+                recorder.add(SchemaCoordinate::Type(recorder.name(root).to_owned()));
+                recorder.record_selection_set(&operation.selection_set, root);
+            }
+        }
+
+        for fragment in document.fragments.values() {
+            recorder.add(SchemaCoordinate::Type(
+                recorder.name(fragment.on).to_owned(),
+            ));
+            recorder.record_directives(&fragment.directives);
+            recorder.record_selection_set(&fragment.selection_set, fragment.on);
+        }
+    }
+
+    /// The number of uses of `coordinate`, or `None` if it isn't part of the
+    /// schema
+    pub fn count(&self, coordinate: &str) -> Option<usize> {
+        self.counts.get(coordinate).copied()
+    }
+
+    /// Every coordinate of the schema and its number of uses, sorted by
+    /// coordinate
+    pub fn counts(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.counts
+            .iter()
+            .map(|(coordinate, count)| (coordinate.as_str(), *count))
+    }
+
+    /// The coordinates that are never used, sorted
+    pub fn unused(&self) -> Vec<&str> {
+        self.counts()
+            .filter(|(_, count)| *count == 0)
+            .map(|(coordinate, _)| coordinate)
+            .collect()
+    }
+
+    /// `{ "coordinates": { "User.name": 2, ... }, "unused": ["User.age", ...] }`
+    #[cfg(feature = "serde_json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "coordinates": self.counts,
+            "unused": self.unused(),
+        })
+    }
+
+    /// Read a report written by [`SchemaUsage::to_json`]
+    #[cfg(feature = "serde_json")]
+    pub fn from_json(json: &serde_json::Value) -> Result<Self, String> {
+        let coordinates = json
+            .get("coordinates")
+            .and_then(serde_json::Value::as_object)
+            .ok_or_else(|| "expected an object with a `coordinates` object".to_owned())?;
+
+        let counts = coordinates
+            .iter()
+            .map(|(coordinate, count)| {
+                let count = count
+                    .as_u64()
+                    .and_then(|count| usize::try_from(count).ok())
+                    .ok_or_else(|| format!("the count of `{}` must be an integer", coordinate))?;
+
+                Ok((coordinate.clone(), count))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self { counts })
+    }
+}
+
+/// A report listing unused coordinates, followed by the used ones and how
+/// often they're used
+impl fmt::Display for SchemaUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unused = self.unused();
+
+        writeln!(
+            f,
+            "{} of {} schema coordinates are used",
+            self.counts.len() - unused.len(),
+            self.counts.len()
+        )?;
+
+        if !unused.is_empty() {
+            writeln!(f, "\nunused:")?;
+
+            for coordinate in unused {
+                writeln!(f, "  {}", coordinate)?;
+            }
+        }
+
+        let mut used = self
+            .counts()
+            .filter(|(_, count)| *count > 0)
+            .collect::<Vec<_>>();
+
+        if !used.is_empty() {
+            used.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+            writeln!(f, "\nused:")?;
+
+            for (coordinate, count) in used {
+                writeln!(f, "  {} ({})", coordinate, count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct Recorder<'a> {
+    schema: &'a Document,
+    interner: &'a Rodeo,
+    counts: &'a mut BTreeMap<String, usize>,
+}
+
+impl<'a> Recorder<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    /// Count a use of `coordinate`, if it's part of the schema
+    fn add(&mut self, coordinate: SchemaCoordinate) {
+        if let Some(count) = self.counts.get_mut(&coordinate.to_string()) {
+            *count += 1;
+        }
+    }
+
+    fn record_selection_set(&mut self, selection_set: &[Selection], parent_type: Spur) {
+        for selection in selection_set {
+            match selection {
+                Selection::Field {
+                    name,
+                    arguments,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    self.record_directives(directives);
+
+                    // unknown fields are reported by validation
+                    let field = match self
+                        .schema
+                        .fields_of(parent_type)
+                        .and_then(|fields| fields.iter().find(|field| field.name == *name))
+                    {
+                        Some(field) => field,
+                        None => continue,
+                    };
+
+                    let coordinate =
+                        SchemaCoordinate::member(self.name(parent_type), self.name(*name));
+
+                    self.record_arguments(
+                        &coordinate,
+                        arguments.as_deref().unwrap_or_default(),
+                        field.arguments.as_deref().unwrap_or_default(),
+                    );
+                    self.add(coordinate);
+                    self.add(SchemaCoordinate::Type(
+                        self.name(field.ty.name()).to_owned(),
+                    ));
+
+                    if let Some(selection_set) = selection_set {
+                        self.record_selection_set(selection_set, field.ty.name());
+                    }
+                }
+                Selection::InlineFragment {
+                    on,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    if let Some(on) = on {
+                        self.add(SchemaCoordinate::Type(self.name(*on).to_owned()));
+                    }
+                    self.record_directives(directives);
+                    self.record_selection_set(selection_set, on.unwrap_or(parent_type));
+                }
+                Selection::FragmentSpread { directives, .. } => self.record_directives(directives),
+            }
+        }
+    }
+
+    fn record_directives(&mut self, directives: &[Directive]) {
+        for directive in directives {
+            let definition = match self.schema.directive_definitions.get(&directive.name) {
+                Some(definition) => definition,
+                None => continue,
+            };
+
+            let coordinate = SchemaCoordinate::directive(self.name(directive.name));
+
+            self.record_arguments(
+                &coordinate,
+                directive.arguments.as_deref().unwrap_or_default(),
+                definition.arguments.as_deref().unwrap_or_default(),
+            );
+            self.add(coordinate);
+        }
+    }
+
+    /// `parent` is the field or directive the arguments are given to
+    fn record_arguments(
+        &mut self,
+        parent: &SchemaCoordinate,
+        arguments: &[Argument],
+        definitions: &[InputObjectField],
+    ) {
+        for argument in arguments {
+            if let Some(definition) = definitions.iter().find(|def| def.name == argument.name) {
+                self.add(
+                    parent
+                        .argument(self.name(argument.name))
+                        .expect("only fields and directives have arguments"),
+                );
+                self.add(SchemaCoordinate::Type(
+                    self.name(definition.ty.name()).to_owned(),
+                ));
+                self.record_value(&argument.value, &definition.ty);
+            }
+        }
+    }
+
+    /// Count the enum values and input object fields in a literal `value` of
+    /// type `ty`
+    fn record_value(&mut self, value: &Value, ty: &Type) {
+        match value {
+            Value::EnumVariant(variant) => {
+                self.add(SchemaCoordinate::member(
+                    self.name(ty.name()),
+                    self.name(*variant),
+                ));
+            }
+            Value::List(values) => {
+                // a single value may be given where a list is expected
+                let item_ty = match ty {
+                    Type::List { base, .. } => base,
+                    ty => ty,
+                };
+
+                for value in values {
+                    self.record_value(value, item_ty);
+                }
+            }
+            Value::Object(fields) => {
+                let type_name = ty.name();
+
+                let definitions = match self.schema.input_objects.get(&type_name) {
+                    Some(input) => input.fields.as_deref().unwrap_or_default(),
+                    None => return,
+                };
+
+                for (name, value) in fields {
+                    if let Some(definition) = definitions.iter().find(|def| def.name == *name) {
+                        self.add(SchemaCoordinate::member(
+                            self.name(type_name),
+                            self.name(*name),
+                        ));
+                        self.add(SchemaCoordinate::Type(
+                            self.name(definition.ty.name()).to_owned(),
+                        ));
+                        self.record_value(value, &definition.ty);
+                    }
+                }
+            }
+            Value::True
+            | Value::False
+            | Value::Null
+            | Value::String(..)
+            | Value::Variable(..)
+            | Value::Float(..)
+            | Value::Int(..) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::SchemaUsage;
+    use crate::GraphqlParser;
+
+    const SCHEMA: &str = "
+directive @cached(ttl: Int) on FIELD
+type Query {
+  user(id: ID!, role: Role): User
+  users(filter: Filter): [User!]!
+  legacy: String
+}
+interface Node { id: ID! }
+type User implements Node { id: ID! name: String age: Int }
+input Filter { role: Role, name: String }
+enum Role { ADMIN USER }
+";
+
+    fn usage(documents: &[&str]) -> SchemaUsage {
+        let mut interner = Rodeo::default();
+        let schema = GraphqlParser::parse_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+
+        let mut usage = SchemaUsage::new(&schema, &interner);
+
+        for document in documents {
+            let document =
+                GraphqlParser::parse_with_interner(document.as_bytes(), &mut interner).unwrap();
+            usage.record(&schema, &document, &interner);
+        }
+
+        usage
+    }
+
+    #[test]
+    fn counts_uses_across_documents() {
+        let usage = usage(&[
+            "query A($id: ID!) { user(id: $id, role: ADMIN) { id ...F } }",
+            "fragment F on User { name @cached(ttl: 10) }",
+            "{ users(filter: { role: USER }) { ... on Node { id } } }",
+        ]);
+
+        let count = |coordinate: &str| usage.count(coordinate).unwrap();
+
+        assert_eq!(count("Query"), 2);
+        assert_eq!(count("Query.user"), 1);
+        assert_eq!(count("Query.user(id:)"), 1);
+        assert_eq!(count("User"), 3);
+        assert_eq!(count("User.id"), 1);
+        assert_eq!(count("Node.id"), 1);
+        assert_eq!(count("Role"), 2);
+        assert_eq!(count("Role.ADMIN"), 1);
+        assert_eq!(count("Filter.role"), 1);
+        assert_eq!(count("@cached(ttl:)"), 1);
+        assert_eq!(usage.count("ID"), None);
+
+        assert_eq!(usage.unused(), ["Filter.name", "Query.legacy", "User.age"]);
+    }
+
+    #[test]
+    fn report() {
+        let usage = usage(&["{ legacy users { id } }", "{ users { id } }"]);
+
+        let report = usage.to_string();
+        let (summary, rest) = report.split_once("\n\nunused:\n").unwrap();
+        let (unused, used) = rest.split_once("\n\nused:\n").unwrap();
+
+        assert_eq!(summary, "5 of 21 schema coordinates are used");
+        assert_eq!(unused.lines().count(), 16);
+        assert_eq!(
+            used,
+            concat!(
+                "  Query (2)\n",
+                "  Query.users (2)\n",
+                "  User (2)\n",
+                "  User.id (2)\n",
+                "  Query.legacy (1)\n",
+            )
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn json_round_trip() {
+        let usage = usage(&["{ legacy users { id } }"]);
+
+        assert_eq!(SchemaUsage::from_json(&usage.to_json()), Ok(usage));
+        assert_eq!(
+            SchemaUsage::from_json(&serde_json::json!({ "coordinates": { "Query": "x" } })),
+            Err("the count of `Query` must be an integer".to_owned())
+        );
+    }
+}
@@ -0,0 +1,2008 @@
+//! Validation of executable documents (operations and fragments) against a
+//! schema
+//!
+//! See https://spec.graphql.org/October2021/#sec-Validation
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use lasso::{Rodeo, Spur};
+
+use crate::{
+    ast::{
+        built_in_directive_locations, pair_duplicates, Argument, Definition, Directive,
+        DirectiveLocation, Document, FieldDefinition, InputObjectField, Operation, OperationKind,
+        Selection, Span, Type, TypeKind, Value, BUILT_IN_SCALARS,
+    },
+    fmt::print_type,
+    scalar::ScalarRegistry,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub kind: ValidationErrorKind,
+    /// Where the error occurred: the enclosing operation or fragment, such as
+    /// `query GetUser` or `fragment UserFields`, followed by the response keys
+    /// of the fields leading to the error
+    pub path: Vec<String>,
+    /// The innermost operation, fragment, variable definition, selection,
+    /// directive or argument the error was found in
+    pub span: Span,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join("."))?;
+        }
+
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    /// The schema has no root type for this kind of operation
+    MissingRootType {
+        operation: OperationKind,
+    },
+    /// An anonymous operation was found in a document with other operations
+    AnonymousOperationNotAlone,
+    /// Two operations share a name, whether or not they are of the same
+    /// kind. `first` is the span of the first operation of the name and
+    /// `second` of a later one
+    DuplicateOperationName {
+        operation: String,
+        first: Span,
+        second: Span,
+    },
+    DuplicateFragmentName {
+        fragment: String,
+        first: Span,
+        second: Span,
+    },
+    UnknownField {
+        type_name: String,
+        field: String,
+    },
+    /// `field` is either a field name or a directive name, prefixed with `@`
+    UnknownArgument {
+        field: String,
+        argument: String,
+    },
+    DuplicateArgument {
+        argument: String,
+    },
+    MissingRequiredArgument {
+        field: String,
+        argument: String,
+    },
+    /// A literal value that can't be coerced to the type it's used as
+    InvalidValue {
+        argument: String,
+        expected: String,
+    },
+    UnknownDirective {
+        directive: String,
+    },
+    /// A directive used in a location its definition doesn't list
+    MisplacedDirective {
+        directive: String,
+        location: DirectiveLocation,
+    },
+    /// A directive that isn't `repeatable` is used more than once in the
+    /// same location
+    DuplicateDirective {
+        directive: String,
+    },
+    UnknownType {
+        type_name: String,
+    },
+    /// A fragment or inline fragment whose type condition is a scalar, enum or
+    /// input type. `fragment` is `None` for inline fragments
+    FragmentOnNonCompositeType {
+        fragment: Option<String>,
+        type_name: String,
+    },
+    UnknownFragment {
+        fragment: String,
+    },
+    UnusedFragment {
+        fragment: String,
+    },
+    /// Fragments that spread each other in a loop. The first fragment is
+    /// repeated at the end, such as `["A", "B", "A"]`
+    FragmentCycle {
+        fragments: Vec<String>,
+    },
+    /// A fragment spread whose type condition can never apply to the parent
+    /// type. `fragment` is `None` for inline fragments
+    ImpossibleFragmentSpread {
+        fragment: Option<String>,
+        parent_type: String,
+        type_condition: String,
+    },
+    UndefinedVariable {
+        variable: String,
+    },
+    UnusedVariable {
+        variable: String,
+    },
+    /// A variable used where a value of a different type is expected
+    IncompatibleVariable {
+        variable: String,
+        variable_type: String,
+        expected: String,
+    },
+    DuplicateVariable {
+        variable: String,
+    },
+    NonInputTypeVariable {
+        variable: String,
+        type_name: String,
+    },
+    /// A field of scalar or enum type has a selection set
+    SelectionOnLeafField {
+        field: String,
+        type_name: String,
+    },
+    /// A field of object, interface or union type has no selection set
+    MissingSelectionSet {
+        field: String,
+        type_name: String,
+    },
+}
+
+impl fmt::Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingRootType { operation } => {
+                write!(
+                    f,
+                    "schema does not support {} operations",
+                    operation.as_str()
+                )
+            }
+            Self::AnonymousOperationNotAlone => write!(
+                f,
+                "an anonymous operation must be the only operation in its document"
+            ),
+            Self::DuplicateOperationName { operation, .. } => {
+                write!(f, "there is more than one operation named `{}`", operation)
+            }
+            Self::DuplicateFragmentName { fragment, .. } => {
+                write!(f, "there is more than one fragment named `{}`", fragment)
+            }
+            Self::UnknownField { type_name, field } => {
+                write!(f, "type `{}` has no field `{}`", type_name, field)
+            }
+            Self::UnknownArgument { field, argument } => {
+                write!(f, "`{}` has no argument `{}`", field, argument)
+            }
+            Self::DuplicateArgument { argument } => {
+                write!(f, "argument `{}` is given more than once", argument)
+            }
+            Self::MissingRequiredArgument { field, argument } => {
+                write!(f, "`{}` requires argument `{}`", field, argument)
+            }
+            Self::InvalidValue { argument, expected } => {
+                write!(f, "`{}` expects a value of type `{}`", argument, expected)
+            }
+            Self::UnknownDirective { directive } => write!(f, "unknown directive `@{}`", directive),
+            Self::MisplacedDirective {
+                directive,
+                location,
+            } => write!(
+                f,
+                "directive `@{}` may not be used on {}",
+                directive,
+                location.as_str()
+            ),
+            Self::DuplicateDirective { directive } => write!(
+                f,
+                "directive `@{}` is not repeatable, but is used more than once",
+                directive
+            ),
+            Self::UnknownType { type_name } => write!(f, "unknown type `{}`", type_name),
+            Self::FragmentOnNonCompositeType {
+                fragment,
+                type_name,
+            } => match fragment {
+                Some(fragment) => write!(
+                    f,
+                    "fragment `{}` cannot condition on non-composite type `{}`",
+                    fragment, type_name
+                ),
+                None => write!(
+                    f,
+                    "inline fragment cannot condition on non-composite type `{}`",
+                    type_name
+                ),
+            },
+            Self::UnknownFragment { fragment } => write!(f, "unknown fragment `{}`", fragment),
+            Self::UnusedFragment { fragment } => write!(f, "fragment `{}` is never used", fragment),
+            Self::FragmentCycle { fragments } => {
+                write!(f, "fragments spread each other in a cycle: ")?;
+
+                for (idx, fragment) in fragments.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, " -> ")?;
+                    }
+
+                    write!(f, "`{}`", fragment)?;
+                }
+
+                Ok(())
+            }
+            Self::ImpossibleFragmentSpread {
+                fragment,
+                parent_type,
+                type_condition,
+            } => match fragment {
+                Some(fragment) => write!(
+                    f,
+                    "fragment `{}` on `{}` can never be spread within `{}`",
+                    fragment, type_condition, parent_type
+                ),
+                None => write!(
+                    f,
+                    "inline fragment on `{}` can never be spread within `{}`",
+                    type_condition, parent_type
+                ),
+            },
+            Self::UndefinedVariable { variable } => {
+                write!(f, "variable `${}` is not defined", variable)
+            }
+            Self::UnusedVariable { variable } => {
+                write!(f, "variable `${}` is never used", variable)
+            }
+            Self::IncompatibleVariable {
+                variable,
+                variable_type,
+                expected,
+            } => write!(
+                f,
+                "variable `${}` of type `{}` cannot be used where `{}` is expected",
+                variable, variable_type, expected
+            ),
+            Self::DuplicateVariable { variable } => {
+                write!(f, "variable `${}` is defined more than once", variable)
+            }
+            Self::NonInputTypeVariable {
+                variable,
+                type_name,
+            } => write!(
+                f,
+                "variable `${}` cannot be of non-input type `{}`",
+                variable, type_name
+            ),
+            Self::SelectionOnLeafField { field, type_name } => write!(
+                f,
+                "field `{}` of type `{}` must not have a selection set",
+                field, type_name
+            ),
+            Self::MissingSelectionSet { field, type_name } => write!(
+                f,
+                "field `{}` of type `{}` must have a selection set",
+                field, type_name
+            ),
+        }
+    }
+}
+
+/// Validate the operations and fragments in `document` against `schema`
+///
+/// Both documents must have been parsed with the same `interner`, see
+/// [`GraphqlParser::parse_with_interner`](crate::GraphqlParser::parse_with_interner)
+pub fn validate_executable_document(
+    schema: &Document,
+    document: &Document,
+    interner: &Rodeo,
+) -> Vec<ValidationError> {
+    validate_with_scalars(schema, document, interner, None)
+}
+
+/// Like [`validate_executable_document`], but checking literals of custom
+/// scalars with the coercions registered in `scalars`
+pub fn validate_with_scalars(
+    schema: &Document,
+    document: &Document,
+    interner: &Rodeo,
+    scalars: Option<&ScalarRegistry>,
+) -> Vec<ValidationError> {
+    let mut validator = Validator {
+        schema,
+        document,
+        interner,
+        scalars,
+        errors: Vec::new(),
+        path: Vec::new(),
+        span: Span::default(),
+    };
+
+    validator.validate();
+
+    validator.errors
+}
+
+struct Validator<'a> {
+    schema: &'a Document,
+    document: &'a Document,
+    interner: &'a Rodeo,
+    scalars: Option<&'a ScalarRegistry>,
+    errors: Vec<ValidationError>,
+    path: Vec<String>,
+    span: Span,
+}
+
+/// Variables and fragments referenced directly by a selection set
+#[derive(Default)]
+struct References {
+    variables: Vec<VariableUsage>,
+    fragments: HashSet<Spur>,
+}
+
+struct VariableUsage {
+    name: Spur,
+    /// The type expected where the variable is used, or `None` if that isn't
+    /// known, such as for an unknown argument
+    ty: Option<Type>,
+    /// Whether the argument or input field the variable is used for has a
+    /// default value
+    location_has_default: bool,
+    path: Vec<String>,
+    span: Span,
+}
+
+impl<'a> Validator<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    fn error(&mut self, kind: ValidationErrorKind) {
+        self.errors.push(ValidationError {
+            kind,
+            path: self.path.clone(),
+            span: self.span,
+        });
+    }
+
+    /// Like [`Document::type_kind`], but also knowing about built-in scalars
+    fn type_kind(&self, name: Spur) -> Option<TypeKind> {
+        self.schema.type_kind(name).or_else(|| {
+            if BUILT_IN_SCALARS.contains(&self.name(name)) {
+                Some(TypeKind::Scalar)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn root_operation_type(&self, kind: OperationKind) -> Option<Spur> {
+        self.schema
+            .root_operation_type_or_default(kind, self.interner)
+    }
+
+    fn validate(&mut self) {
+        let document = self.document;
+
+        // replaced duplicates are included, as they're operations too
+        let has_anonymous = document.operations.keys().any(|(name, _)| name.is_none());
+        let operation_count = document.operations.len()
+            + document
+                .duplicates
+                .iter()
+                .filter(|definition| matches!(definition, Definition::Operation(..)))
+                .count();
+
+        if has_anonymous && operation_count > 1 {
+            self.error(ValidationErrorKind::AnonymousOperationNotAlone);
+        }
+
+        self.validate_unique_names();
+
+        let mut fragment_references = HashMap::new();
+
+        for fragment in document.fragments.values() {
+            self.path = vec![format!("fragment {}", self.name(fragment.name))];
+            self.span = fragment.span;
+
+            let mut references = References::default();
+
+            self.validate_directives(
+                &fragment.directives,
+                DirectiveLocation::FragmentDefinition,
+                &mut references,
+            );
+
+            if self.check_type_condition(fragment.on, Some(fragment.name)) {
+                self.validate_selection_set(&fragment.selection_set, fragment.on, &mut references);
+            }
+
+            fragment_references.insert(fragment.name, references);
+        }
+
+        self.detect_fragment_cycles(&fragment_references);
+
+        let mut used_fragments = HashSet::new();
+
+        for operation in document.operations.values() {
+            self.path = vec![match operation.name {
+                Some(name) => format!("{} {}", operation.kind.as_str(), self.name(name)),
+                None => operation.kind.as_str().to_owned(),
+            }];
+            self.span = operation.span;
+
+            let mut references = References::default();
+
+            self.validate_variable_definitions(operation);
+            self.validate_directives(
+                &operation.directives,
+                DirectiveLocation::of_operation(operation.kind),
+                &mut references,
+            );
+
+            match self.root_operation_type(operation.kind) {
+                Some(root) => {
+                    self.validate_selection_set(&operation.selection_set, root, &mut references)
+                }
+                None => self.error(ValidationErrorKind::MissingRootType {
+                    operation: operation.kind,
+                }),
+            }
+
+            // collect everything used by this operation, including through
+            // (possibly nested) fragment spreads
+            let mut usages = references.variables.iter().collect::<Vec<_>>();
+            let mut pending = sorted_fragments(&references.fragments, self.interner);
+            let mut visited = HashSet::new();
+
+            while let Some(fragment) = pending.pop() {
+                if !visited.insert(fragment) {
+                    continue;
+                }
+
+                if let Some(references) = fragment_references.get(&fragment) {
+                    usages.extend(references.variables.iter());
+                    pending.extend(sorted_fragments(&references.fragments, self.interner));
+                }
+            }
+
+            self.validate_variable_usages(operation, &usages);
+
+            used_fragments.extend(visited);
+        }
+
+        let mut unused_fragments = document
+            .fragments
+            .values()
+            .filter(|fragment| !used_fragments.contains(&fragment.name))
+            .map(|fragment| (self.name(fragment.name), fragment.span))
+            .collect::<Vec<_>>();
+
+        unused_fragments.sort_unstable_by_key(|(name, _)| *name);
+
+        for (fragment, span) in unused_fragments {
+            self.path = vec![format!("fragment {}", fragment)];
+            self.span = span;
+            self.error(ValidationErrorKind::UnusedFragment {
+                fragment: fragment.to_owned(),
+            });
+        }
+
+        self.path.clear();
+        self.span = Span::default();
+    }
+
+    /// Report operations and fragments that share a name with an earlier one
+    ///
+    /// See https://spec.graphql.org/October2021/#sec-Operation-Name-Uniqueness
+    /// and https://spec.graphql.org/October2021/#sec-Fragment-Name-Uniqueness
+    fn validate_unique_names(&mut self) {
+        let document = self.document;
+
+        let operations = document
+            .duplicates
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Operation(operation) => Some((operation.name?, operation)),
+                _ => None,
+            });
+
+        for (name, first, second) in pair_duplicates(operations, |name| {
+            document
+                .operations
+                .values()
+                .find(|operation| operation.name == Some(name))
+        }) {
+            self.path = vec![format!("{} {}", second.kind.as_str(), self.name(name))];
+            self.span = second.span;
+            self.error(ValidationErrorKind::DuplicateOperationName {
+                operation: self.name(name).to_owned(),
+                first: first.span,
+                second: second.span,
+            });
+        }
+
+        let fragments = document
+            .duplicates
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Fragment(fragment) => Some((fragment.name, fragment.span)),
+                _ => None,
+            });
+
+        for (name, first, second) in pair_duplicates(fragments, |name| {
+            document.fragments.get(&name).map(|kept| kept.span)
+        }) {
+            self.path = vec![format!("fragment {}", self.name(name))];
+            self.span = second;
+            self.error(ValidationErrorKind::DuplicateFragmentName {
+                fragment: self.name(name).to_owned(),
+                first,
+                second,
+            });
+        }
+    }
+
+    /// Report every cycle in the graph of fragment spreads, each only once
+    ///
+    /// See https://spec.graphql.org/October2021/#sec-Fragment-spreads-must-not-form-cycles
+    fn detect_fragment_cycles(&mut self, fragment_references: &HashMap<Spur, References>) {
+        let mut fragments = fragment_references.keys().copied().collect::<Vec<_>>();
+        fragments.sort_by_key(|fragment| self.name(*fragment));
+
+        let mut visited = HashSet::new();
+
+        for fragment in fragments {
+            let mut chain = Vec::new();
+            self.visit_fragment_spreads(fragment, fragment_references, &mut visited, &mut chain);
+        }
+    }
+
+    /// Depth-first search from `fragment`, where `chain` holds the fragments
+    /// currently being spread
+    fn visit_fragment_spreads(
+        &mut self,
+        fragment: Spur,
+        fragment_references: &HashMap<Spur, References>,
+        visited: &mut HashSet<Spur>,
+        chain: &mut Vec<Spur>,
+    ) {
+        if let Some(idx) = chain.iter().position(|f| *f == fragment) {
+            let fragments = chain[idx..]
+                .iter()
+                .chain(std::iter::once(&fragment))
+                .map(|f| self.name(*f).to_owned())
+                .collect();
+
+            self.path = vec![format!("fragment {}", self.name(fragment))];
+            self.span = self
+                .document
+                .fragments
+                .get(&fragment)
+                .map_or_else(Span::default, |fragment| fragment.span);
+            self.error(ValidationErrorKind::FragmentCycle { fragments });
+
+            return;
+        }
+
+        if !visited.insert(fragment) {
+            return;
+        }
+
+        let references = match fragment_references.get(&fragment) {
+            Some(references) => references,
+            // unknown fragments are reported where they're spread
+            None => return,
+        };
+
+        let mut spreads = references.fragments.iter().copied().collect::<Vec<_>>();
+        spreads.sort_by_key(|spread| self.name(*spread));
+
+        chain.push(fragment);
+
+        for spread in spreads {
+            self.visit_fragment_spreads(spread, fragment_references, visited, chain);
+        }
+
+        chain.pop();
+    }
+
+    /// Check that every variable used by `operation` is defined with a type
+    /// that is allowed where it's used, and that every defined variable is used
+    ///
+    /// See https://spec.graphql.org/October2021/#sec-All-Variable-Usages-are-Allowed
+    fn validate_variable_usages(&mut self, operation: &Operation, usages: &[&VariableUsage]) {
+        let operation_path = self.path.clone();
+        let operation_span = self.span;
+
+        let mut reported = HashSet::new();
+
+        for usage in usages {
+            self.path.clone_from(&usage.path);
+            self.span = usage.span;
+
+            let variable_name = self.name(usage.name);
+
+            let definition = match operation
+                .variable_definitions
+                .iter()
+                .find(|variable| variable.name == usage.name)
+            {
+                Some(definition) => definition,
+                None => {
+                    if reported.insert(usage.name) {
+                        self.error(ValidationErrorKind::UndefinedVariable {
+                            variable: variable_name.to_owned(),
+                        });
+                    }
+                    continue;
+                }
+            };
+
+            let location_ty = match &usage.ty {
+                Some(ty) => ty,
+                None => continue,
+            };
+
+            // a nullable variable may be used for a non-null argument if either
+            // of them provides a default to use in place of null
+            let has_default = usage.location_has_default
+                || definition
+                    .default
+                    .as_ref()
+                    .is_some_and(|value| !matches!(value, Value::Null));
+
+            let ignore_nullability = has_default && !location_ty.is_nullable();
+
+            if !are_types_compatible(&definition.ty, location_ty, ignore_nullability) {
+                self.error(ValidationErrorKind::IncompatibleVariable {
+                    variable: variable_name.to_owned(),
+                    variable_type: print_type(&definition.ty, self.interner),
+                    expected: print_type(location_ty, self.interner),
+                });
+            }
+        }
+
+        self.path = operation_path;
+        self.span = operation_span;
+
+        for variable in &operation.variable_definitions {
+            if !usages.iter().any(|usage| usage.name == variable.name) {
+                self.span = variable.span;
+                self.error(ValidationErrorKind::UnusedVariable {
+                    variable: self.name(variable.name).to_owned(),
+                });
+            }
+        }
+
+        self.span = operation_span;
+    }
+
+    fn validate_variable_definitions(&mut self, operation: &Operation) {
+        let mut seen = HashSet::new();
+
+        for variable in &operation.variable_definitions {
+            self.span = variable.span;
+
+            let name = self.name(variable.name).to_owned();
+
+            if !seen.insert(variable.name) {
+                self.error(ValidationErrorKind::DuplicateVariable {
+                    variable: name.clone(),
+                });
+            }
+
+            let type_name = variable.ty.name();
+
+            match self.type_kind(type_name) {
+                Some(kind) if kind.is_input() => {}
+                Some(..) => self.error(ValidationErrorKind::NonInputTypeVariable {
+                    variable: name.clone(),
+                    type_name: self.name(type_name).to_owned(),
+                }),
+                None => self.error(ValidationErrorKind::UnknownType {
+                    type_name: self.name(type_name).to_owned(),
+                }),
+            }
+
+            if let Some(default) = &variable.default {
+                self.validate_literal(default, &variable.ty, &format!("${}", name), false);
+            }
+        }
+
+        self.span = operation.span;
+    }
+
+    /// Check that a fragment's type condition is a known composite type,
+    /// returning whether its selection set can be validated
+    fn check_type_condition(&mut self, type_condition: Spur, fragment: Option<Spur>) -> bool {
+        match self.type_kind(type_condition) {
+            Some(kind) if kind.is_composite() => true,
+            Some(..) => {
+                self.error(ValidationErrorKind::FragmentOnNonCompositeType {
+                    fragment: fragment.map(|fragment| self.name(fragment).to_owned()),
+                    type_name: self.name(type_condition).to_owned(),
+                });
+                false
+            }
+            None => {
+                self.error(ValidationErrorKind::UnknownType {
+                    type_name: self.name(type_condition).to_owned(),
+                });
+                false
+            }
+        }
+    }
+
+    fn check_spread_is_possible(
+        &mut self,
+        parent_type: Spur,
+        type_condition: Spur,
+        fragment: Option<Spur>,
+    ) {
+        let parent_types = self.schema.possible_types(parent_type);
+        let condition_types = self.schema.possible_types(type_condition);
+
+        if !parent_types.iter().any(|ty| condition_types.contains(ty)) {
+            self.error(ValidationErrorKind::ImpossibleFragmentSpread {
+                fragment: fragment.map(|fragment| self.name(fragment).to_owned()),
+                parent_type: self.name(parent_type).to_owned(),
+                type_condition: self.name(type_condition).to_owned(),
+            });
+        }
+    }
+
+    /// `parent_type` must be a composite type known to the schema
+    fn validate_selection_set(
+        &mut self,
+        selection_set: &[Selection],
+        parent_type: Spur,
+        references: &mut References,
+    ) {
+        for selection in selection_set {
+            let outer_span = std::mem::replace(&mut self.span, selection.span());
+
+            match selection {
+                Selection::Field {
+                    alias,
+                    name,
+                    arguments,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    self.path.push(self.name(alias.unwrap_or(*name)).to_owned());
+
+                    self.validate_directives(directives, DirectiveLocation::Field, references);
+                    self.validate_field(
+                        *name,
+                        arguments.as_deref().unwrap_or_default(),
+                        selection_set.as_deref(),
+                        parent_type,
+                        references,
+                    );
+
+                    self.path.pop();
+                }
+                Selection::FragmentSpread {
+                    name, directives, ..
+                } => {
+                    self.validate_directives(
+                        directives,
+                        DirectiveLocation::FragmentSpread,
+                        references,
+                    );
+
+                    references.fragments.insert(*name);
+
+                    match self.document.fragments.get(name) {
+                        Some(fragment) => {
+                            // unknown or non-composite type conditions are
+                            // reported on the fragment definition itself
+                            if self
+                                .type_kind(fragment.on)
+                                .is_some_and(|kind| kind.is_composite())
+                            {
+                                self.check_spread_is_possible(
+                                    parent_type,
+                                    fragment.on,
+                                    Some(*name),
+                                );
+                            }
+                        }
+                        None => self.error(ValidationErrorKind::UnknownFragment {
+                            fragment: self.name(*name).to_owned(),
+                        }),
+                    }
+                }
+                Selection::InlineFragment {
+                    on,
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    self.validate_directives(
+                        directives,
+                        DirectiveLocation::InlineFragment,
+                        references,
+                    );
+
+                    match on {
+                        Some(on) => {
+                            if self.check_type_condition(*on, None) {
+                                self.check_spread_is_possible(parent_type, *on, None);
+                                self.validate_selection_set(selection_set, *on, references);
+                            }
+                        }
+                        // without a type condition, the fragment is on the parent type
+                        None => self.validate_selection_set(selection_set, parent_type, references),
+                    }
+                }
+            }
+
+            self.span = outer_span;
+        }
+    }
+
+    fn validate_field(
+        &mut self,
+        name: Spur,
+        arguments: &[Argument],
+        selection_set: Option<&[Selection]>,
+        parent_type: Spur,
+        references: &mut References,
+    ) {
+        let field_name = self.name(name);
+
+        if field_name == "__typename" {
+            self.collect_untyped_variables(arguments, references);
+
+            if selection_set.is_some() {
+                self.error(ValidationErrorKind::SelectionOnLeafField {
+                    field: field_name.to_owned(),
+                    type_name: "String".to_owned(),
+                });
+            }
+
+            return;
+        }
+
+        if matches!(field_name, "__schema" | "__type")
+            && self.root_operation_type(OperationKind::Query) == Some(parent_type)
+        {
+            self.collect_untyped_variables(arguments, references);
+
+            // the introspection types are only known if they were added with
+            // `Document::add_built_in_definitions`
+            let introspection_type = if field_name == "__schema" {
+                "__Schema"
+            } else {
+                "__Type"
+            };
+
+            let introspection_type = self
+                .interner
+                .get(introspection_type)
+                .filter(|name| self.schema.output_objects.contains_key(name));
+
+            if let (Some(type_name), Some(selection_set)) = (introspection_type, selection_set) {
+                self.validate_selection_set(selection_set, type_name, references);
+            }
+
+            return;
+        }
+
+        let field = match self
+            .schema
+            .fields_of(parent_type)
+            .and_then(|fields| fields.iter().find(|field| field.name == name))
+        {
+            Some(field) => field,
+            None => {
+                self.error(ValidationErrorKind::UnknownField {
+                    type_name: self.name(parent_type).to_owned(),
+                    field: field_name.to_owned(),
+                });
+                self.collect_untyped_variables(arguments, references);
+                return;
+            }
+        };
+
+        self.validate_arguments(
+            field_name,
+            arguments,
+            field.arguments.as_deref().unwrap_or_default(),
+            references,
+        );
+
+        self.validate_field_selection_set(field, selection_set, references);
+    }
+
+    fn validate_field_selection_set(
+        &mut self,
+        field: &FieldDefinition,
+        selection_set: Option<&[Selection]>,
+        references: &mut References,
+    ) {
+        let type_name = field.ty.name();
+
+        let kind = match self.type_kind(type_name) {
+            Some(kind) => kind,
+            // a schema error, not something to report against the query
+            None => return,
+        };
+
+        match (kind.is_composite(), selection_set) {
+            (true, Some(selection_set)) => {
+                self.validate_selection_set(selection_set, type_name, references)
+            }
+            (true, None) => self.error(ValidationErrorKind::MissingSelectionSet {
+                field: self.name(field.name).to_owned(),
+                type_name: print_type(&field.ty, self.interner),
+            }),
+            (false, Some(..)) => self.error(ValidationErrorKind::SelectionOnLeafField {
+                field: self.name(field.name).to_owned(),
+                type_name: print_type(&field.ty, self.interner),
+            }),
+            (false, None) => {}
+        }
+    }
+
+    fn validate_directives(
+        &mut self,
+        directives: &[Directive],
+        location: DirectiveLocation,
+        references: &mut References,
+    ) {
+        let outer_span = self.span;
+
+        for (idx, directive) in directives.iter().enumerate() {
+            self.span = directive.span;
+
+            let arguments = directive.arguments.as_deref().unwrap_or_default();
+
+            let name = self.name(directive.name);
+
+            let definition = self.schema.directive_definitions.get(&directive.name);
+
+            let placement = match definition {
+                Some(definition) => Some((&*definition.locations, definition.repeatable)),
+                None => built_in_directive_locations(name).map(|locations| (locations, false)),
+            };
+
+            if let Some((locations, repeatable)) = placement {
+                if !locations.contains(&location) {
+                    self.error(ValidationErrorKind::MisplacedDirective {
+                        directive: name.to_owned(),
+                        location,
+                    });
+                }
+
+                if !repeatable
+                    && directives[..idx]
+                        .iter()
+                        .any(|other| other.name == directive.name)
+                {
+                    self.error(ValidationErrorKind::DuplicateDirective {
+                        directive: name.to_owned(),
+                    });
+                }
+            }
+
+            let definitions = match definition {
+                Some(definition) => definition.arguments.as_deref().unwrap_or_default(),
+                None if matches!(name, "skip" | "include") => {
+                    self.validate_conditional_directive(name, arguments, references);
+                    continue;
+                }
+                None if matches!(name, "deprecated" | "specifiedBy") => {
+                    self.collect_untyped_variables(arguments, references);
+                    continue;
+                }
+                None => {
+                    self.error(ValidationErrorKind::UnknownDirective {
+                        directive: name.to_owned(),
+                    });
+                    self.collect_untyped_variables(arguments, references);
+                    continue;
+                }
+            };
+
+            self.validate_arguments(&format!("@{}", name), arguments, definitions, references);
+        }
+
+        self.span = outer_span;
+    }
+
+    /// `@skip(if: Boolean!)` and `@include(if: Boolean!)`, which don't need to
+    /// be declared in the schema
+    fn validate_conditional_directive(
+        &mut self,
+        name: &str,
+        arguments: &[Argument],
+        references: &mut References,
+    ) {
+        let field = format!("@{}", name);
+
+        // if `Boolean` was never interned, no variable can have been defined
+        // with that type. We can't construct the type to compare against, so
+        // the variable is only checked for being defined
+        let boolean = self.interner.get("Boolean").map(|name| Type::Named {
+            name,
+            nullable: false,
+        });
+
+        let mut has_if = false;
+
+        let directive_span = self.span;
+
+        for argument in arguments {
+            self.span = argument.span;
+
+            let argument_name = self.name(argument.name);
+
+            if argument_name != "if" {
+                self.error(ValidationErrorKind::UnknownArgument {
+                    field: field.clone(),
+                    argument: argument_name.to_owned(),
+                });
+                self.collect_variables(&argument.value, None, false, references);
+                continue;
+            }
+
+            has_if = true;
+
+            self.collect_variables(&argument.value, boolean.as_ref(), false, references);
+
+            if !matches!(
+                argument.value,
+                Value::True | Value::False | Value::Variable(..)
+            ) {
+                self.error(ValidationErrorKind::InvalidValue {
+                    argument: "if".to_owned(),
+                    expected: "Boolean!".to_owned(),
+                });
+            }
+        }
+
+        self.span = directive_span;
+
+        if !has_if {
+            self.error(ValidationErrorKind::MissingRequiredArgument {
+                field,
+                argument: "if".to_owned(),
+            });
+        }
+    }
+
+    /// `field` is used in error messages, and is either a field name or a
+    /// directive name prefixed with `@`
+    fn validate_arguments(
+        &mut self,
+        field: &str,
+        arguments: &[Argument],
+        definitions: &[InputObjectField],
+        references: &mut References,
+    ) {
+        let mut seen = HashSet::new();
+
+        let outer_span = self.span;
+
+        for argument in arguments {
+            self.span = argument.span;
+
+            let argument_name = self.name(argument.name);
+
+            if !seen.insert(argument.name) {
+                self.error(ValidationErrorKind::DuplicateArgument {
+                    argument: argument_name.to_owned(),
+                });
+            }
+
+            match definitions.iter().find(|def| def.name == argument.name) {
+                Some(definition) => {
+                    self.validate_value(&argument.value, &definition.ty, argument_name);
+                    self.collect_variables(
+                        &argument.value,
+                        Some(&definition.ty),
+                        definition.default.is_some(),
+                        references,
+                    );
+                }
+                None => {
+                    self.error(ValidationErrorKind::UnknownArgument {
+                        field: field.to_owned(),
+                        argument: argument_name.to_owned(),
+                    });
+                    self.collect_variables(&argument.value, None, false, references);
+                }
+            }
+        }
+
+        self.span = outer_span;
+
+        for definition in definitions {
+            let is_required = !definition.ty.is_nullable() && definition.default.is_none();
+
+            if is_required && !seen.contains(&definition.name) {
+                self.error(ValidationErrorKind::MissingRequiredArgument {
+                    field: field.to_owned(),
+                    argument: self.name(definition.name).to_owned(),
+                });
+            }
+        }
+    }
+
+    fn collect_untyped_variables(&self, arguments: &[Argument], references: &mut References) {
+        for argument in arguments {
+            self.collect_variables(&argument.value, None, false, references);
+        }
+    }
+
+    /// Record each variable in `value`, along with the type expected where
+    /// it's used, given that `value` is used where `ty` is expected
+    fn collect_variables(
+        &self,
+        value: &Value,
+        ty: Option<&Type>,
+        location_has_default: bool,
+        references: &mut References,
+    ) {
+        match value {
+            Value::Variable(name) => references.variables.push(VariableUsage {
+                name: *name,
+                ty: ty.cloned(),
+                location_has_default,
+                path: self.path.clone(),
+                span: self.span,
+            }),
+            Value::List(values) => {
+                let item_ty = match ty {
+                    Some(Type::List { base, .. }) => Some(&**base),
+                    _ => None,
+                };
+
+                for value in values {
+                    self.collect_variables(value, item_ty, false, references);
+                }
+            }
+            Value::Object(fields) => {
+                let definitions = ty
+                    .and_then(|ty| match ty {
+                        Type::Named { name, .. } => self.schema.input_objects.get(name),
+                        Type::List { .. } => None,
+                    })
+                    .and_then(|input| input.fields.as_deref())
+                    .unwrap_or_default();
+
+                for (name, value) in fields {
+                    let definition = definitions.iter().find(|def| def.name == *name);
+
+                    self.collect_variables(
+                        value,
+                        definition.map(|def| &def.ty),
+                        definition.is_some_and(|def| def.default.is_some()),
+                        references,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn validate_value(&mut self, value: &Value, ty: &Type, argument: &str) {
+        self.validate_literal(value, ty, argument, true);
+    }
+
+    /// Check a literal `value` of type `ty`. Variables are only allowed if
+    /// `allow_variables` is set, as default values must be constant
+    fn validate_literal(
+        &mut self,
+        value: &Value,
+        ty: &Type,
+        argument: &str,
+        allow_variables: bool,
+    ) {
+        let literals = Literals {
+            schema: self.schema,
+            interner: self.interner,
+            scalars: self.scalars,
+            allow_variables,
+        };
+
+        if !literals.is_valid(value, ty) {
+            self.error(ValidationErrorKind::InvalidValue {
+                argument: argument.to_owned(),
+                expected: print_type(ty, self.interner),
+            });
+        }
+    }
+}
+
+/// Checks whether literal values can be coerced to their types
+pub(crate) struct Literals<'a> {
+    pub schema: &'a Document,
+    pub interner: &'a Rodeo,
+    pub scalars: Option<&'a ScalarRegistry>,
+    /// Whether variables are allowed, in which case they are assumed to be
+    /// valid, as their types are checked separately
+    pub allow_variables: bool,
+}
+
+impl<'a> Literals<'a> {
+    fn name(&self, name: Spur) -> &'a str {
+        self.interner.resolve(&name)
+    }
+
+    /// Whether `value` can be coerced to `ty`, checking the items of lists
+    /// and the fields of input objects
+    ///
+    /// See https://spec.graphql.org/October2021/#sec-Values-of-Correct-Type
+    pub fn is_valid(&self, value: &Value, ty: &Type) -> bool {
+        if let Value::Variable(..) = value {
+            return self.allow_variables;
+        }
+        if let Value::Null = value {
+            return ty.is_nullable();
+        }
+
+        let name = match ty {
+            Type::List { base, .. } => {
+                return match value {
+                    Value::List(values) => values.iter().all(|value| self.is_valid(value, base)),
+                    // a single item is coerced to a list of one
+                    value => self.is_valid(value, base),
+                };
+            }
+            Type::Named { name, .. } => *name,
+        };
+
+        let kind = self.schema.type_kind(name).or_else(|| {
+            BUILT_IN_SCALARS
+                .contains(&self.name(name))
+                .then_some(TypeKind::Scalar)
+        });
+
+        match kind {
+            Some(TypeKind::Scalar) => match self.name(name) {
+                "Int" => matches!(value, Value::Int(int) if i32::try_from(*int).is_ok()),
+                "Float" => matches!(value, Value::Int(..) | Value::Float(..)),
+                "String" => matches!(value, Value::String(..)),
+                "Boolean" => matches!(value, Value::True | Value::False),
+                "ID" => matches!(value, Value::String(..) | Value::Int(..)),
+                // custom scalars may accept any literal, unless a coercion is
+                // registered for them
+                custom => self
+                    .scalars
+                    .and_then(|scalars| scalars.parse(custom, value, self.interner))
+                    .is_none_or(|parsed| parsed.is_ok()),
+            },
+            Some(TypeKind::Enum) => match value {
+                Value::EnumVariant(variant) => self.schema.enums[&name]
+                    .variants
+                    .iter()
+                    .any(|v| v.name == *variant),
+                _ => false,
+            },
+            Some(TypeKind::InputObject) => {
+                let fields = match value {
+                    Value::Object(fields) => fields,
+                    _ => return false,
+                };
+
+                let definitions = self.schema.input_objects[&name]
+                    .fields
+                    .as_deref()
+                    .unwrap_or_default();
+
+                let all_known = fields.iter().all(|(field, value)| {
+                    definitions
+                        .iter()
+                        .find(|def| def.name == *field)
+                        .is_some_and(|def| self.is_valid(value, &def.ty))
+                });
+
+                let has_required = definitions.iter().all(|def| {
+                    def.ty.is_nullable() || def.default.is_some() || fields.contains_key(&def.name)
+                });
+
+                all_known && has_required
+            }
+            // output types and unknown types can't have a valid value
+            _ => false,
+        }
+    }
+}
+
+/// Whether a variable of type `variable_ty` may be used where `location_ty` is
+/// expected. If `ignore_nullability` is set, a nullable variable is accepted
+/// for a non-null location
+///
+/// See https://spec.graphql.org/October2021/#AreTypesCompatible()
+fn are_types_compatible(variable_ty: &Type, location_ty: &Type, ignore_nullability: bool) -> bool {
+    if !ignore_nullability && !location_ty.is_nullable() && variable_ty.is_nullable() {
+        return false;
+    }
+
+    match (variable_ty, location_ty) {
+        (
+            Type::List { base, .. },
+            Type::List {
+                base: location_base,
+                ..
+            },
+        ) => are_types_compatible(base, location_base, false),
+        (
+            Type::Named { name, .. },
+            Type::Named {
+                name: location_name,
+                ..
+            },
+        ) => name == location_name,
+        // unlike literals, variables are never coerced into a list
+        _ => false,
+    }
+}
+
+fn sorted_fragments(fragments: &HashSet<Spur>, interner: &Rodeo) -> Vec<Spur> {
+    let mut fragments = fragments.iter().copied().collect::<Vec<_>>();
+    // reversed, as these are popped off the end of a stack
+    fragments.sort_by_key(|fragment| std::cmp::Reverse(interner.resolve(fragment)));
+    fragments
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{validate_executable_document, ValidationErrorKind};
+    use crate::{
+        ast::{DirectiveLocation, OperationKind},
+        GraphqlParser,
+    };
+
+    const SCHEMA: &str = r#"
+        type Query {
+          user(id: ID!): User
+          users(filter: UserFilter, first: Int = 10): [User!]!
+          node(id: ID!): Node
+          search(term: String!): [SearchResult!]!
+        }
+
+        interface Node { id: ID! }
+
+        type User implements Node {
+          id: ID!
+          name: String
+          friends(first: Int): [User!]!
+          role: Role
+        }
+
+        type Post implements Node { id: ID! title: String! }
+
+        union SearchResult = User | Post
+
+        enum Role { ADMIN USER }
+
+        input UserFilter { role: Role, name: String!, limit: Int = 5 }
+
+        directive @live on QUERY
+        directive @tag(name: String) repeatable on FIELD | FRAGMENT_SPREAD
+    "#;
+
+    fn errors(query: &str) -> Vec<ValidationErrorKind> {
+        let mut interner = Rodeo::default();
+
+        let schema =
+            GraphqlParser::parse_schema_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let query =
+            GraphqlParser::parse_query_with_interner(query.as_bytes(), &mut interner).unwrap();
+
+        validate_executable_document(&schema, &query, &interner)
+            .into_iter()
+            .map(|err| err.kind)
+            .collect()
+    }
+
+    fn assert_valid(query: &str) {
+        assert_eq!(errors(query), Vec::new(), "{}", query);
+    }
+
+    fn assert_error(query: &str, expected: ValidationErrorKind) {
+        assert_eq!(errors(query), vec![expected], "{}", query);
+    }
+
+    #[test]
+    fn valid_document() {
+        assert_valid(
+            r#"
+            query Q($id: ID!, $first: Int) {
+              user(id: $id) { ...UserFields friends(first: $first) { name } }
+              users(filter: { name: "a", role: ADMIN }) { id }
+              search(term: "a") {
+                __typename
+                ... on User { name }
+                ... on Post { title }
+              }
+              node(id: "1") { id ... on User { role } }
+            }
+
+            fragment UserFields on User { id name @include(if: true) }
+            "#,
+        );
+    }
+
+    #[test]
+    fn missing_root_type() {
+        assert_error(
+            "mutation { a }",
+            ValidationErrorKind::MissingRootType {
+                operation: OperationKind::Mutation,
+            },
+        );
+    }
+
+    #[test]
+    fn anonymous_operation_not_alone() {
+        assert_error(
+            "{ users { id } } query Q { users { id } }",
+            ValidationErrorKind::AnonymousOperationNotAlone,
+        );
+    }
+
+    #[test]
+    fn duplicate_operation_name() {
+        let errors = errors("query Q { users { id } } query Q { users { name } }");
+
+        match &errors[..] {
+            [ValidationErrorKind::DuplicateOperationName { operation, .. }] => {
+                assert_eq!(operation, "Q")
+            }
+            errors => panic!("{:?}", errors),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "spans")]
+    fn duplicate_operation_names_of_different_kinds() {
+        let source = "query Q { users { id } } mutation Q { a } query Q { users { name } }";
+
+        let spans = errors(source)
+            .into_iter()
+            .map(|error| match error {
+                ValidationErrorKind::DuplicateOperationName { first, second, .. } => {
+                    (first.start, second.start)
+                }
+                error => panic!("{:?}", error),
+            })
+            .collect::<Vec<_>>();
+
+        // each later operation is reported against the first
+        assert_eq!(
+            spans,
+            [
+                (0, source.find("mutation").unwrap()),
+                (0, source.rfind("query").unwrap())
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_fragment_name() {
+        let errors =
+            errors("{ users { ...F } } fragment F on User { id } fragment F on User { name }");
+
+        match &errors[..] {
+            [ValidationErrorKind::DuplicateFragmentName { fragment, .. }] => {
+                assert_eq!(fragment, "F")
+            }
+            errors => panic!("{:?}", errors),
+        }
+    }
+
+    #[test]
+    fn unknown_field() {
+        assert_error(
+            "{ users { id email } }",
+            ValidationErrorKind::UnknownField {
+                type_name: "User".to_owned(),
+                field: "email".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn inline_fragment_without_type_condition() {
+        assert!(errors("{ users { ... @include(if: true) { id } } }").is_empty());
+        assert_error(
+            "{ users { ... { id email } } }",
+            ValidationErrorKind::UnknownField {
+                type_name: "User".to_owned(),
+                field: "email".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn unknown_argument() {
+        assert_error(
+            "{ users(last: 1) { id } }",
+            ValidationErrorKind::UnknownArgument {
+                field: "users".to_owned(),
+                argument: "last".to_owned(),
+            },
+        );
+        assert_eq!(
+            errors("{ users @skip(when: true) { id } }"),
+            vec![
+                ValidationErrorKind::UnknownArgument {
+                    field: "@skip".to_owned(),
+                    argument: "when".to_owned(),
+                },
+                ValidationErrorKind::MissingRequiredArgument {
+                    field: "@skip".to_owned(),
+                    argument: "if".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_argument() {
+        assert_error(
+            "{ users(first: 1, first: 2) { id } }",
+            ValidationErrorKind::DuplicateArgument {
+                argument: "first".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn missing_required_argument() {
+        assert_error(
+            "{ user { id } }",
+            ValidationErrorKind::MissingRequiredArgument {
+                field: "user".to_owned(),
+                argument: "id".to_owned(),
+            },
+        );
+        assert_error(
+            "{ user(id: null) { id } }",
+            ValidationErrorKind::InvalidValue {
+                argument: "id".to_owned(),
+                expected: "ID!".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn invalid_value() {
+        for (query, argument, expected) in [
+            ("{ users(first: \"1\") { id } }", "first", "Int"),
+            ("{ users(first: 1.5) { id } }", "first", "Int"),
+            (
+                "{ users(filter: { name: \"a\", role: OWNER }) { id } }",
+                "filter",
+                "UserFilter",
+            ),
+            (
+                "{ users(filter: { role: ADMIN }) { id } }",
+                "filter",
+                "UserFilter",
+            ),
+            (
+                "{ users(filter: { name: \"a\", age: 1 }) { id } }",
+                "filter",
+                "UserFilter",
+            ),
+        ] {
+            let errors = errors(query);
+
+            assert!(
+                matches!(
+                    &errors[..],
+                    [ValidationErrorKind::InvalidValue { argument: a, expected: e }]
+                        if a == argument && e.starts_with(expected)
+                ),
+                "{}: {:?}",
+                query,
+                errors
+            );
+        }
+
+        // a single value is coerced to a list
+        assert_valid("{ search(term: \"a\") { __typename } user(id: 1) { id } }");
+    }
+
+    #[test]
+    fn unknown_directive() {
+        assert_error(
+            "{ users @cached { id } }",
+            ValidationErrorKind::UnknownDirective {
+                directive: "cached".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn misplaced_directive() {
+        assert_valid("query Q @live { users @tag(name: \"a\") { id } }");
+        assert_error(
+            "{ users @live { id } }",
+            ValidationErrorKind::MisplacedDirective {
+                directive: "live".to_owned(),
+                location: DirectiveLocation::Field,
+            },
+        );
+        assert_error(
+            "query Q @skip(if: true) { users { id } }",
+            ValidationErrorKind::MisplacedDirective {
+                directive: "skip".to_owned(),
+                location: DirectiveLocation::Query,
+            },
+        );
+    }
+
+    #[test]
+    fn duplicate_directive() {
+        assert_valid("{ users @tag(name: \"a\") @tag(name: \"b\") { id } }");
+        assert_error(
+            "query Q($a: Boolean!) { users @include(if: $a) @include(if: true) { id } }",
+            ValidationErrorKind::DuplicateDirective {
+                directive: "include".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn unknown_type() {
+        assert_error(
+            "{ node(id: 1) { ... on Comment { id } } }",
+            ValidationErrorKind::UnknownType {
+                type_name: "Comment".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn fragment_on_non_composite_type() {
+        assert_error(
+            "{ users { ...F } } fragment F on Role { id }",
+            ValidationErrorKind::FragmentOnNonCompositeType {
+                fragment: Some("F".to_owned()),
+                type_name: "Role".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn unknown_fragment() {
+        assert_error(
+            "{ users { ...F } }",
+            ValidationErrorKind::UnknownFragment {
+                fragment: "F".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn unused_fragment() {
+        assert_error(
+            "{ users { id } } fragment F on User { id }",
+            ValidationErrorKind::UnusedFragment {
+                fragment: "F".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn impossible_fragment_spread() {
+        assert_error(
+            "{ users { ... on Post { id } } }",
+            ValidationErrorKind::ImpossibleFragmentSpread {
+                fragment: None,
+                parent_type: "User".to_owned(),
+                type_condition: "Post".to_owned(),
+            },
+        );
+
+        // an interface may be spread on a type implementing it, and the reverse
+        assert_valid("{ users { ... on Node { id } } node(id: 1) { ... on Post { id } } }");
+    }
+
+    #[test]
+    fn undefined_variable() {
+        assert_error(
+            "query Q { user(id: $id) { id } }",
+            ValidationErrorKind::UndefinedVariable {
+                variable: "id".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn unused_variable() {
+        assert_error(
+            "query Q($id: ID) { users { id } }",
+            ValidationErrorKind::UnusedVariable {
+                variable: "id".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn duplicate_variable() {
+        assert_error(
+            "query Q($id: ID!, $id: ID!) { user(id: $id) { id } }",
+            ValidationErrorKind::DuplicateVariable {
+                variable: "id".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn non_input_type_variable() {
+        assert_eq!(
+            errors("query Q($user: User) { users { id } }"),
+            vec![
+                ValidationErrorKind::NonInputTypeVariable {
+                    variable: "user".to_owned(),
+                    type_name: "User".to_owned(),
+                },
+                ValidationErrorKind::UnusedVariable {
+                    variable: "user".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn selection_on_leaf_field() {
+        assert_error(
+            "{ users { id { a } } }",
+            ValidationErrorKind::SelectionOnLeafField {
+                field: "id".to_owned(),
+                type_name: "ID!".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn missing_selection_set() {
+        assert_error(
+            "{ users }",
+            ValidationErrorKind::MissingSelectionSet {
+                field: "users".to_owned(),
+                type_name: "[User!]!".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn fragment_cycle() {
+        let errors = errors(concat!(
+            "{ users { ...A } }",
+            "fragment A on User { ...B }",
+            "fragment B on User { friends { ...A } }",
+        ));
+
+        assert_eq!(
+            errors,
+            vec![ValidationErrorKind::FragmentCycle {
+                fragments: vec!["A".to_owned(), "B".to_owned(), "A".to_owned()],
+            }]
+        );
+    }
+
+    #[test]
+    fn fragment_spreading_itself() {
+        assert_eq!(
+            errors("{ users { ...A } } fragment A on User { friends { ...A } }"),
+            vec![ValidationErrorKind::FragmentCycle {
+                fragments: vec!["A".to_owned(), "A".to_owned()],
+            }]
+        );
+    }
+
+    #[test]
+    fn fragments_spread_twice_are_not_a_cycle() {
+        assert_valid(
+            "{ users { ...A ...B } } fragment A on User { ...B } fragment B on User { id }",
+        );
+    }
+
+    fn incompatible(variable: &str, variable_type: &str, expected: &str) -> ValidationErrorKind {
+        ValidationErrorKind::IncompatibleVariable {
+            variable: variable.to_owned(),
+            variable_type: variable_type.to_owned(),
+            expected: expected.to_owned(),
+        }
+    }
+
+    #[test]
+    fn nullable_variable_for_non_null_argument() {
+        assert_error(
+            "query Q($id: ID) { user(id: $id) { id } }",
+            incompatible("id", "ID", "ID!"),
+        );
+
+        // unless the variable has a default
+        assert_valid("query Q($id: ID = 1) { user(id: $id) { id } }");
+    }
+
+    #[test]
+    fn non_null_variable_for_nullable_argument() {
+        assert_valid("query Q($first: Int!) { users(first: $first) { id } }");
+    }
+
+    #[test]
+    fn variable_of_a_different_type() {
+        assert_error(
+            "query Q($first: String) { users(first: $first) { id } }",
+            incompatible("first", "String", "Int"),
+        );
+    }
+
+    #[test]
+    fn list_variables() {
+        assert_error(
+            "query Q($first: [Int]) { users(first: $first) { id } }",
+            incompatible("first", "[Int]", "Int"),
+        );
+        assert_error(
+            "query Q($id: ID!) { search(term: $id) { __typename } }",
+            incompatible("id", "ID!", "String!"),
+        );
+    }
+
+    #[test]
+    fn variables_in_input_objects_and_lists() {
+        assert_valid("query Q($name: String!) { users(filter: { name: $name }) { id } }");
+        assert_error(
+            "query Q($name: String) { users(filter: { name: $name }) { id } }",
+            incompatible("name", "String", "String!"),
+        );
+
+        // an input field with a default accepts a nullable variable
+        assert_valid(concat!(
+            "query Q($name: String!, $limit: Int) {",
+            "  users(filter: { name: $name, limit: $limit }) { id }",
+            "}",
+        ));
+    }
+
+    #[test]
+    fn variables_in_directives_and_fragments() {
+        assert_error(
+            "query Q($if: Boolean) { users @include(if: $if) { id } }",
+            incompatible("if", "Boolean", "Boolean!"),
+        );
+        assert_error(
+            "query Q($id: Int!) { ...F } fragment F on Query { user(id: $id) { id } }",
+            incompatible("id", "Int!", "ID!"),
+        );
+    }
+
+    #[test]
+    fn variable_default_values() {
+        let invalid = |variable: &str, expected: &str| ValidationErrorKind::InvalidValue {
+            argument: variable.to_owned(),
+            expected: expected.to_owned(),
+        };
+
+        assert_valid(concat!(
+            "query Q($first: Int = 5, $filter: UserFilter = { name: \"a\", role: ADMIN }) {",
+            "  users(first: $first, filter: $filter) { id }",
+            "}",
+        ));
+        assert_error(
+            "query Q($first: Int = \"five\") { users(first: $first) { id } }",
+            invalid("$first", "Int"),
+        );
+        assert_eq!(
+            errors("query Q($ids: [ID!] = [1, null]) { users { id } }"),
+            [
+                invalid("$ids", "[ID!]"),
+                ValidationErrorKind::UnusedVariable {
+                    variable: "ids".to_owned(),
+                },
+            ]
+        );
+        // default values must be constant
+        assert_eq!(
+            errors("query Q($a: Int, $b: Int = $a) { users(first: $b) { id } }")[0],
+            invalid("$b", "Int"),
+        );
+        // missing required fields are checked inside input objects
+        assert_error(
+            "query Q($f: UserFilter = { role: ADMIN }) { users(filter: $f) { id } }",
+            invalid("$f", "UserFilter"),
+        );
+    }
+
+    #[test]
+    fn schema_default_values() {
+        let schema = r#"
+            directive @limit(max: Int = 1.5) on FIELD
+            type Query {
+              users(first: Int = 10, filter: Filter = { roles: [ADMIN, OWNER] }): [ID!]!
+              posts(after: String = null, before: ID! = null): [ID!]!
+            }
+            interface Node { children(depth: Int = "deep"): [Node!]! }
+            enum Role { ADMIN USER }
+            input Filter { roles: [Role!] = [USER], nested: Filter = { nested: { roles: USER } } }
+            input Broken { first: Int = 2147483648, filter: Filter = { unknown: 1 } }
+        "#;
+
+        let mut interner = Rodeo::default();
+        let schema =
+            GraphqlParser::parse_schema_with_interner(schema.as_bytes(), &mut interner).unwrap();
+
+        let messages = schema
+            .validate_default_values(&interner)
+            .iter()
+            .map(|err| err.message(&interner))
+            .collect::<Vec<_>>();
+
+        let mut expected = [
+            "the default value of `@limit(max:)` is not a valid `Int`",
+            "the default value of `Query.users(filter:)` is not a valid `Filter`",
+            "the default value of `Query.posts(before:)` is not a valid `ID!`",
+            "the default value of `Node.children(depth:)` is not a valid `Int`",
+            "the default value of `Broken.first` is not a valid `Int`",
+            "the default value of `Broken.filter` is not a valid `Filter`",
+        ];
+        // without spans, errors are ordered by their message
+        if !cfg!(feature = "spans") {
+            expected.sort();
+        }
+
+        assert_eq!(messages, expected);
+    }
+
+    #[test]
+    fn schema_directive_usages() {
+        let schema = r#"
+            directive @auth(role: String!) on OBJECT | FIELD_DEFINITION
+            directive @tag(name: String) repeatable on OBJECT | INTERFACE
+            type Query @auth(role: "admin") @tag(name: "a") @tag(name: "b") {
+              users: [ID!]! @auth(role: 1) @deprecated
+              posts(first: Int @auth(role: "x")): [ID!]! @auth
+            }
+            interface Node @auth(role: "a", scope: 1) @cached { id: ID! }
+            scalar Date @specifiedBy(url: "a") @specifiedBy(url: "b")
+        "#;
+
+        let mut interner = Rodeo::default();
+        let schema =
+            GraphqlParser::parse_schema_with_interner(schema.as_bytes(), &mut interner).unwrap();
+
+        let messages = schema
+            .validate_directive_usages(&interner)
+            .iter()
+            .map(|err| err.message(&interner))
+            .collect::<Vec<_>>();
+
+        let mut expected = [
+            "argument `role` of `@auth` expects a value of type `String!`",
+            "directive `@auth` may not be used on ARGUMENT_DEFINITION",
+            "`@auth` requires argument `role`",
+            "directive `@auth` may not be used on INTERFACE",
+            "`@auth` has no argument `scope`",
+            "unknown directive `@cached`",
+            "directive `@specifiedBy` is not repeatable, but is used more than once",
+        ];
+        // without spans, errors are ordered by their message
+        if !cfg!(feature = "spans") {
+            expected.sort();
+        }
+
+        assert_eq!(messages, expected);
+    }
+}
@@ -0,0 +1,730 @@
+//! Coercion of variable values, as sent in the JSON body of a request, against
+//! the variable definitions of an operation
+//!
+//! See https://spec.graphql.org/October2021/#CoerceVariableValues()
+
+use std::{collections::HashMap, fmt};
+
+use lasso::{Rodeo, Spur};
+use serde_json::{Map, Value as JsonValue};
+
+use crate::{
+    ast::{
+        Document, InputObjectField, Type, TypeKind, Value, VariableDefinition, BUILT_IN_SCALARS,
+    },
+    execute::PathSegment,
+    fmt::print_type,
+    scalar::ScalarRegistry,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableError {
+    /// The name of the variable, without the `$`
+    pub variable: String,
+    /// The list indices and input object fields leading to the error, inside
+    /// the variable's value
+    pub path: Vec<PathSegment>,
+    pub kind: VariableErrorKind,
+}
+
+impl fmt::Display for VariableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "variable `${}", self.variable)?;
+
+        for segment in &self.path {
+            match segment {
+                PathSegment::Key(key) => write!(f, ".{}", key)?,
+                PathSegment::Index(idx) => write!(f, "[{}]", idx)?,
+            }
+        }
+
+        write!(f, "`: {}", self.kind)
+    }
+}
+
+impl std::error::Error for VariableError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariableErrorKind {
+    /// A non-null variable without a default value wasn't provided
+    Missing {
+        type_name: String,
+    },
+    /// `null` was given for a non-null type
+    Null {
+        type_name: String,
+    },
+    /// `found` describes the JSON value, such as `a string`
+    InvalidValue {
+        type_name: String,
+        found: String,
+    },
+    UnknownEnumValue {
+        type_name: String,
+        value: String,
+    },
+    UnknownField {
+        type_name: String,
+        field: String,
+    },
+    /// A non-null input object field without a default value wasn't provided
+    MissingField {
+        type_name: String,
+        field: String,
+    },
+    /// The variable is declared with an output type, or a type the schema
+    /// doesn't define
+    NotInputType {
+        type_name: String,
+    },
+    /// The coercion registered for a custom scalar rejected the value
+    InvalidScalar {
+        type_name: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for VariableErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { type_name } => {
+                write!(f, "a value of type `{}` is required", type_name)
+            }
+            Self::Null { type_name } => {
+                write!(f, "null given for the non-null type `{}`", type_name)
+            }
+            Self::InvalidValue { type_name, found } => write!(
+                f,
+                "expected a value of type `{}`, found {}",
+                type_name, found
+            ),
+            Self::UnknownEnumValue { type_name, value } => {
+                write!(f, "enum `{}` has no value `{}`", type_name, value)
+            }
+            Self::UnknownField { type_name, field } => {
+                write!(f, "`{}` has no field `{}`", type_name, field)
+            }
+            Self::MissingField { type_name, field } => {
+                write!(f, "field `{}.{}` is required", type_name, field)
+            }
+            Self::NotInputType { type_name } => {
+                write!(f, "`{}` is not an input type", type_name)
+            }
+            Self::InvalidScalar { type_name, message } => {
+                write!(f, "invalid value of type `{}`: {}", type_name, message)
+            }
+        }
+    }
+}
+
+/// Coerce the JSON `variables` of a request against `variable_definitions`,
+/// the variables declared by the operation being executed. The result can be
+/// passed to [`execute`](crate::execute::execute)
+///
+/// Variables that weren't provided take their default value, if they have
+/// one. Strings and object keys are interned into `interner`, which must be
+/// the interner `schema` was parsed with
+pub fn coerce_variable_values(
+    schema: &Document,
+    variable_definitions: &[VariableDefinition],
+    variables: &Map<String, JsonValue>,
+    interner: &mut Rodeo,
+) -> Result<HashMap<Spur, Value>, Vec<VariableError>> {
+    coerce_variable_values_with_scalars(schema, variable_definitions, variables, interner, None)
+}
+
+/// Like [`coerce_variable_values`], but coercing the values of custom scalars
+/// with the coercions registered in `scalars`
+pub fn coerce_variable_values_with_scalars(
+    schema: &Document,
+    variable_definitions: &[VariableDefinition],
+    variables: &Map<String, JsonValue>,
+    interner: &mut Rodeo,
+    scalars: Option<&ScalarRegistry>,
+) -> Result<HashMap<Spur, Value>, Vec<VariableError>> {
+    let mut coercer = Coercer {
+        schema,
+        interner,
+        scalars,
+        errors: Vec::new(),
+        variable: String::new(),
+        path: Vec::new(),
+    };
+
+    let mut coerced = HashMap::new();
+
+    for definition in variable_definitions {
+        coercer.variable = coercer.name(definition.name).to_owned();
+
+        let value = match (variables.get(&coercer.variable), &definition.default) {
+            (Some(value), _) => value,
+            (None, Some(default)) => {
+                coerced.insert(definition.name, default.clone());
+                continue;
+            }
+            (None, None) if definition.ty.is_nullable() => continue,
+            (None, None) => {
+                let type_name = print_type(&definition.ty, coercer.interner);
+                coercer.error(VariableErrorKind::Missing { type_name });
+                continue;
+            }
+        };
+
+        if let Some(value) = coercer.coerce(value, &definition.ty) {
+            coerced.insert(definition.name, value);
+        }
+    }
+
+    if coercer.errors.is_empty() {
+        Ok(coerced)
+    } else {
+        Err(coercer.errors)
+    }
+}
+
+/// A description of a JSON value for error messages
+fn describe(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(..) => "a boolean",
+        JsonValue::Number(number) if number.is_f64() => "a float",
+        JsonValue::Number(..) => "an integer",
+        JsonValue::String(..) => "a string",
+        JsonValue::Array(..) => "a list",
+        JsonValue::Object(..) => "an object",
+    }
+    .to_owned()
+}
+
+struct Coercer<'a> {
+    schema: &'a Document,
+    interner: &'a mut Rodeo,
+    scalars: Option<&'a ScalarRegistry>,
+    errors: Vec<VariableError>,
+    /// The variable being coerced
+    variable: String,
+    path: Vec<PathSegment>,
+}
+
+impl<'a> Coercer<'a> {
+    fn name(&self, name: Spur) -> &str {
+        self.interner.resolve(&name)
+    }
+
+    fn error(&mut self, kind: VariableErrorKind) {
+        self.errors.push(VariableError {
+            variable: self.variable.clone(),
+            path: self.path.clone(),
+            kind,
+        });
+    }
+
+    fn invalid_value(&mut self, value: &JsonValue, type_name: Spur) {
+        self.error(VariableErrorKind::InvalidValue {
+            type_name: self.name(type_name).to_owned(),
+            found: describe(value),
+        });
+    }
+
+    /// Coerce `value` to `ty`, returning `None` if an error was reported
+    ///
+    /// See https://spec.graphql.org/October2021/#sec-Input-Values
+    fn coerce(&mut self, value: &JsonValue, ty: &Type) -> Option<Value> {
+        if value.is_null() {
+            if ty.is_nullable() {
+                return Some(Value::Null);
+            }
+
+            let type_name = print_type(ty, self.interner);
+            self.error(VariableErrorKind::Null { type_name });
+            return None;
+        }
+
+        let name = match ty {
+            Type::List { base, .. } => {
+                let values = match value {
+                    JsonValue::Array(values) => values,
+                    // a single item is coerced to a list of one
+                    value => return Some(Value::List(vec![self.coerce(value, base)?])),
+                };
+
+                let mut coerced = Vec::new();
+                let mut is_valid = true;
+
+                for (idx, value) in values.iter().enumerate() {
+                    self.path.push(PathSegment::Index(idx));
+
+                    match self.coerce(value, base) {
+                        Some(value) => coerced.push(value),
+                        None => is_valid = false,
+                    }
+
+                    self.path.pop();
+                }
+
+                return is_valid.then_some(Value::List(coerced));
+            }
+            Type::Named { name, .. } => *name,
+        };
+
+        match self.schema.type_kind(name) {
+            Some(TypeKind::Enum) => self.coerce_enum(value, name),
+            Some(TypeKind::InputObject) => self.coerce_input_object(value, name),
+            Some(TypeKind::Scalar) => self.coerce_scalar(value, name),
+            None if self.is_built_in_scalar(name) => self.coerce_scalar(value, name),
+            _ => {
+                let type_name = self.name(name).to_owned();
+                self.error(VariableErrorKind::NotInputType { type_name });
+                None
+            }
+        }
+    }
+
+    fn is_built_in_scalar(&self, name: Spur) -> bool {
+        BUILT_IN_SCALARS.contains(&self.name(name))
+    }
+
+    fn coerce_scalar(&mut self, value: &JsonValue, name: Spur) -> Option<Value> {
+        if !self.is_built_in_scalar(name) {
+            return self.coerce_custom_scalar(value, name);
+        }
+
+        let coerced = match (self.name(name), value) {
+            ("Int", JsonValue::Number(number)) => {
+                let int = number.as_i64().or_else(|| {
+                    number
+                        .as_f64()
+                        .filter(|float| float.fract() == 0.0)
+                        .map(|float| float as i64)
+                });
+
+                match int {
+                    Some(int) if i32::try_from(int).is_err() => {
+                        self.error(VariableErrorKind::InvalidValue {
+                            type_name: "Int".to_owned(),
+                            found: "an integer that doesn't fit in 32 bits".to_owned(),
+                        });
+                        return None;
+                    }
+                    int => int.map(Value::Int),
+                }
+            }
+            ("Float", JsonValue::Number(number)) => number.as_f64().map(Value::Float),
+            ("String", JsonValue::String(string)) => {
+                Some(Value::String(self.interner.get_or_intern(string)))
+            }
+            ("Boolean", JsonValue::Bool(true)) => Some(Value::True),
+            ("Boolean", JsonValue::Bool(false)) => Some(Value::False),
+            ("ID", JsonValue::String(string)) => {
+                Some(Value::String(self.interner.get_or_intern(string)))
+            }
+            ("ID", JsonValue::Number(number)) if number.is_i64() || number.is_u64() => Some(
+                Value::String(self.interner.get_or_intern(number.to_string())),
+            ),
+            _ => None,
+        };
+
+        if coerced.is_none() {
+            self.invalid_value(value, name);
+        }
+
+        coerced
+    }
+
+    /// Custom scalars may accept any value, unless a coercion is registered
+    /// for them
+    fn coerce_custom_scalar(&mut self, value: &JsonValue, name: Spur) -> Option<Value> {
+        let value = Value::from_json(value, self.interner);
+        let type_name = self.interner.resolve(&name);
+
+        match self
+            .scalars
+            .and_then(|scalars| scalars.parse(type_name, &value, self.interner))
+        {
+            Some(Ok(coerced)) => Some(coerced),
+            Some(Err(message)) => {
+                self.error(VariableErrorKind::InvalidScalar {
+                    type_name: type_name.to_owned(),
+                    message,
+                });
+                None
+            }
+            None => Some(value),
+        }
+    }
+
+    fn coerce_enum(&mut self, value: &JsonValue, name: Spur) -> Option<Value> {
+        let variant = match value {
+            JsonValue::String(variant) => variant,
+            _ => {
+                self.invalid_value(value, name);
+                return None;
+            }
+        };
+
+        let found = self.schema.enums[&name]
+            .variants
+            .iter()
+            .find(|v| self.name(v.name) == variant);
+
+        match found {
+            Some(found) => Some(Value::EnumVariant(found.name)),
+            None => {
+                self.error(VariableErrorKind::UnknownEnumValue {
+                    type_name: self.name(name).to_owned(),
+                    value: variant.clone(),
+                });
+                None
+            }
+        }
+    }
+
+    fn coerce_input_object(&mut self, value: &JsonValue, name: Spur) -> Option<Value> {
+        let fields = match value {
+            JsonValue::Object(fields) => fields,
+            _ => {
+                self.invalid_value(value, name);
+                return None;
+            }
+        };
+
+        let definitions: &[InputObjectField] = self.schema.input_objects[&name]
+            .fields
+            .as_deref()
+            .unwrap_or_default();
+
+        let mut is_valid = true;
+
+        for field in fields.keys() {
+            if !definitions.iter().any(|def| self.name(def.name) == field) {
+                self.error(VariableErrorKind::UnknownField {
+                    type_name: self.name(name).to_owned(),
+                    field: field.clone(),
+                });
+                is_valid = false;
+            }
+        }
+
+        let mut coerced = HashMap::new();
+
+        for definition in definitions {
+            let field = self.name(definition.name).to_owned();
+
+            let value = match (fields.get(&field), &definition.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => {
+                    coerced.insert(definition.name, default.clone());
+                    continue;
+                }
+                (None, None) if definition.ty.is_nullable() => continue,
+                (None, None) => {
+                    self.error(VariableErrorKind::MissingField {
+                        type_name: self.name(name).to_owned(),
+                        field,
+                    });
+                    is_valid = false;
+                    continue;
+                }
+            };
+
+            self.path.push(PathSegment::Key(field));
+
+            match self.coerce(value, &definition.ty) {
+                Some(value) => {
+                    coerced.insert(definition.name, value);
+                }
+                None => is_valid = false,
+            }
+
+            self.path.pop();
+        }
+
+        is_valid.then_some(Value::Object(coerced))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lasso::{Rodeo, Spur};
+    use serde_json::{json, Value as JsonValue};
+
+    use super::{coerce_variable_values_with_scalars, VariableError, VariableErrorKind};
+    use crate::{ast::Value, execute::PathSegment, scalar::ScalarRegistry, GraphqlParser};
+
+    const SCHEMA: &str = r#"
+        type Query { a: Int }
+        enum Role { ADMIN USER }
+        input Filter { role: Role, name: String!, limit: Int = 5, tags: [String!] }
+        scalar Date
+    "#;
+
+    fn coerce(
+        variables: &str,
+        json: JsonValue,
+    ) -> (Result<HashMap<Spur, Value>, Vec<VariableError>>, Rodeo) {
+        coerce_with_scalars(variables, json, None)
+    }
+
+    fn coerce_with_scalars(
+        variables: &str,
+        json: JsonValue,
+        scalars: Option<&ScalarRegistry>,
+    ) -> (Result<HashMap<Spur, Value>, Vec<VariableError>>, Rodeo) {
+        let mut interner = Rodeo::default();
+
+        let schema =
+            GraphqlParser::parse_schema_with_interner(SCHEMA.as_bytes(), &mut interner).unwrap();
+        let query = format!("query Q({}) {{ a }}", variables);
+        let query =
+            GraphqlParser::parse_query_with_interner(query.as_bytes(), &mut interner).unwrap();
+        let operation = query.operations.values().next().unwrap();
+
+        let result = coerce_variable_values_with_scalars(
+            &schema,
+            &operation.variable_definitions,
+            json.as_object().unwrap(),
+            &mut interner,
+            scalars,
+        );
+
+        (result, interner)
+    }
+
+    fn errors(variables: &str, json: JsonValue) -> Vec<(Vec<PathSegment>, VariableErrorKind)> {
+        coerce(variables, json)
+            .0
+            .unwrap_err()
+            .into_iter()
+            .map(|err| (err.path, err.kind))
+            .collect()
+    }
+
+    fn coerced(variables: &str, json: JsonValue) -> HashMap<String, JsonValue> {
+        let (result, interner) = coerce(variables, json);
+
+        result
+            .unwrap()
+            .into_iter()
+            .map(|(name, value)| {
+                let name = interner.resolve(&name).to_owned();
+                (name, value.to_json(&interner).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn null_for_non_null() {
+        assert_eq!(
+            errors("$id: ID!", json!({ "id": null })),
+            vec![(
+                Vec::new(),
+                VariableErrorKind::Null {
+                    type_name: "ID!".to_owned()
+                }
+            )]
+        );
+        assert_eq!(
+            errors("$ids: [ID!]", json!({ "ids": [1, null] })),
+            vec![(
+                vec![PathSegment::Index(1)],
+                VariableErrorKind::Null {
+                    type_name: "ID!".to_owned()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn missing_non_null() {
+        assert_eq!(
+            errors("$id: ID!", json!({})),
+            vec![(
+                Vec::new(),
+                VariableErrorKind::Missing {
+                    type_name: "ID!".to_owned()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn nullable_and_defaults() {
+        let values = coerced(
+            "$a: Int, $b: Int, $c: Int! = 3, $d: Int = 4",
+            json!({ "b": null, "d": null }),
+        );
+
+        assert_eq!(values.get("a"), None);
+        assert_eq!(values["b"], JsonValue::Null);
+        assert_eq!(values["c"], json!(3));
+        // an explicit null overrides the default
+        assert_eq!(values["d"], JsonValue::Null);
+    }
+
+    #[test]
+    fn scalars() {
+        let values = coerced(
+            "$int: Int, $float: Float, $id: ID, $id2: ID, $date: Date",
+            json!({ "int": 1, "float": 1, "id": 7, "id2": "x", "date": { "y": 2020 } }),
+        );
+
+        assert_eq!(values["int"], json!(1));
+        assert_eq!(values["float"], json!(1.0));
+        assert_eq!(values["id"], json!("7"));
+        assert_eq!(values["id2"], json!("x"));
+        assert_eq!(values["date"], json!({ "y": 2020 }));
+
+        for (ty, value) in [
+            ("Int", json!(1.5)),
+            ("Int", json!("1")),
+            ("Int", json!(4_000_000_000u64)),
+            ("Boolean", json!(0)),
+            ("String", json!(1)),
+            ("ID", json!(true)),
+        ] {
+            let errors = errors(&format!("$v: {}", ty), json!({ "v": value }));
+
+            assert!(
+                matches!(&errors[..], [(_, VariableErrorKind::InvalidValue { .. })]),
+                "{} {}: {:?}",
+                ty,
+                value,
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn registered_scalars() {
+        let mut scalars = ScalarRegistry::new();
+        scalars.register(
+            "Date",
+            |value, interner| match value {
+                Value::String(date) if interner.resolve(date).len() == 10 => Ok(value.clone()),
+                Value::Int(..) => Ok(Value::Null),
+                _ => Err("expected a date such as 2020-01-01".to_owned()),
+            },
+            |value| Ok(value.clone()),
+        );
+
+        let (result, interner) = coerce_with_scalars(
+            "$a: Date, $b: [Date]",
+            json!({ "a": "2020-01-01", "b": 1 }),
+            Some(&scalars),
+        );
+        let values = result.unwrap();
+        let value = |name: &str| {
+            values[&interner.get(name).unwrap()]
+                .to_json(&interner)
+                .unwrap()
+        };
+
+        assert_eq!(value("a"), json!("2020-01-01"));
+        // the coerced value replaces the given one
+        assert_eq!(value("b"), json!([null]));
+
+        let errors = coerce_with_scalars(
+            "$a: Date, $b: [Date]",
+            json!({ "a": "today", "b": ["2020-01-01", true] }),
+            Some(&scalars),
+        )
+        .0
+        .unwrap_err();
+        let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert_eq!(
+            errors,
+            [
+                "variable `$a`: invalid value of type `Date`: expected a date such as 2020-01-01",
+                "variable `$b[1]`: invalid value of type `Date`: expected a date such as 2020-01-01",
+            ]
+        );
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(
+            coerced("$r: Role", json!({ "r": "ADMIN" }))["r"],
+            json!("ADMIN")
+        );
+        assert_eq!(
+            errors("$r: Role", json!({ "r": "OWNER" })),
+            vec![(
+                Vec::new(),
+                VariableErrorKind::UnknownEnumValue {
+                    type_name: "Role".to_owned(),
+                    value: "OWNER".to_owned()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn lists() {
+        // a single value is coerced to a list of one
+        assert_eq!(coerced("$l: [Int]", json!({ "l": 1 }))["l"], json!([1]));
+        assert_eq!(
+            coerced("$l: [[Int]]", json!({ "l": [[1], [2, 3]] }))["l"],
+            json!([[1], [2, 3]])
+        );
+    }
+
+    #[test]
+    fn input_objects() {
+        assert_eq!(
+            coerced("$f: Filter", json!({ "f": { "name": "a", "tags": "t" } }))["f"],
+            json!({ "name": "a", "limit": 5, "tags": ["t"] })
+        );
+
+        assert_eq!(
+            errors(
+                "$f: Filter",
+                json!({ "f": { "role": "OWNER", "age": 1, "tags": [null] } })
+            ),
+            vec![
+                (
+                    Vec::new(),
+                    VariableErrorKind::UnknownField {
+                        type_name: "Filter".to_owned(),
+                        field: "age".to_owned()
+                    }
+                ),
+                (
+                    vec![PathSegment::Key("role".to_owned())],
+                    VariableErrorKind::UnknownEnumValue {
+                        type_name: "Role".to_owned(),
+                        value: "OWNER".to_owned()
+                    }
+                ),
+                (
+                    Vec::new(),
+                    VariableErrorKind::MissingField {
+                        type_name: "Filter".to_owned(),
+                        field: "name".to_owned()
+                    }
+                ),
+                (
+                    vec![PathSegment::Key("tags".to_owned()), PathSegment::Index(0)],
+                    VariableErrorKind::Null {
+                        type_name: "String!".to_owned()
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn not_input_type() {
+        assert_eq!(
+            errors("$q: Query", json!({ "q": {} })),
+            vec![(
+                Vec::new(),
+                VariableErrorKind::NotInputType {
+                    type_name: "Query".to_owned()
+                }
+            )]
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! Traversal of a [`Document`]
+//!
+//! Implement [`Visitor`], overriding the methods for the nodes you care about,
+//! and pass it to [`walk_document`]. Each `visit_*` method defaults to calling
+//! the matching `walk_*` function, which visits the node's children; an
+//! overriding method should call it too in order to keep descending.
+//!
+//! Definitions stored in the document's maps, and the fields of object
+//! values, are visited in no particular order. Everything else, such as
+//! selections and arguments, is visited in source order.
+
+use crate::ast::{
+    Argument, Directive, DirectiveDefinition, Document, EnumVariant, FieldDefinition, Fragment,
+    InputObjectField, NamedType, Operation, SchemaDefinition, SchemaExtension, Selection, Type,
+    TypeDefinitionRef, TypeExtension, Value, VariableDefinition,
+};
+
+pub trait Visitor<'a> {
+    fn visit_schema_definition(&mut self, schema: &'a SchemaDefinition) {
+        walk_schema_definition(self, schema);
+    }
+
+    fn visit_schema_extension(&mut self, extension: &'a SchemaExtension) {
+        walk_schema_extension(self, extension);
+    }
+
+    fn visit_operation(&mut self, operation: &'a Operation) {
+        walk_operation(self, operation);
+    }
+
+    fn visit_fragment(&mut self, fragment: &'a Fragment) {
+        walk_fragment(self, fragment);
+    }
+
+    fn visit_variable_definition(&mut self, variable: &'a VariableDefinition) {
+        walk_variable_definition(self, variable);
+    }
+
+    fn visit_selection(&mut self, selection: &'a Selection) {
+        walk_selection(self, selection);
+    }
+
+    fn visit_type_definition(&mut self, ty: TypeDefinitionRef<'a>) {
+        walk_type_definition(self, ty);
+    }
+
+    fn visit_type_extension(&mut self, extension: &'a TypeExtension) {
+        walk_type_extension(self, extension);
+    }
+
+    fn visit_directive_definition(&mut self, directive: &'a DirectiveDefinition) {
+        walk_directive_definition(self, directive);
+    }
+
+    fn visit_field_definition(&mut self, field: &'a FieldDefinition) {
+        walk_field_definition(self, field);
+    }
+
+    /// An argument definition or an input object field
+    fn visit_input_value_definition(&mut self, input_value: &'a InputObjectField) {
+        walk_input_value_definition(self, input_value);
+    }
+
+    fn visit_enum_variant(&mut self, variant: &'a EnumVariant) {
+        walk_enum_variant(self, variant);
+    }
+
+    fn visit_directive(&mut self, directive: &'a Directive) {
+        walk_directive(self, directive);
+    }
+
+    fn visit_argument(&mut self, argument: &'a Argument) {
+        walk_argument(self, argument);
+    }
+
+    fn visit_value(&mut self, value: &'a Value) {
+        walk_value(self, value);
+    }
+
+    fn visit_type(&mut self, _ty: &'a Type) {}
+
+    /// A type referenced by name, such as in an `implements` clause or as a
+    /// member of a union
+    fn visit_named_type(&mut self, _ty: &'a NamedType) {}
+}
+
+pub fn walk_document<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, document: &'a Document) {
+    if let Some(schema) = &document.schema {
+        visitor.visit_schema_definition(schema);
+    }
+
+    for directive in document.directive_definitions.values() {
+        visitor.visit_directive_definition(directive);
+    }
+
+    for ty in document.types() {
+        visitor.visit_type_definition(ty);
+    }
+
+    for extension in &document.schema_extensions {
+        visitor.visit_schema_extension(extension);
+    }
+
+    for extension in &document.type_extensions {
+        visitor.visit_type_extension(extension);
+    }
+
+    for operation in document.operations.values() {
+        visitor.visit_operation(operation);
+    }
+
+    for fragment in document.fragments.values() {
+        visitor.visit_fragment(fragment);
+    }
+}
+
+pub fn walk_schema_definition<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    schema: &'a SchemaDefinition,
+) {
+    walk_directives(visitor, &schema.directives);
+
+    for operation_type in &schema.operation_types {
+        visitor.visit_named_type(&operation_type.ty);
+    }
+}
+
+pub fn walk_schema_extension<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    extension: &'a SchemaExtension,
+) {
+    walk_directives(visitor, &extension.directives);
+
+    for operation_type in &extension.operation_types {
+        visitor.visit_named_type(&operation_type.ty);
+    }
+}
+
+pub fn walk_operation<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, operation: &'a Operation) {
+    for variable in &operation.variable_definitions {
+        visitor.visit_variable_definition(variable);
+    }
+
+    walk_directives(visitor, &operation.directives);
+    walk_selection_set(visitor, &operation.selection_set);
+}
+
+pub fn walk_fragment<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, fragment: &'a Fragment) {
+    walk_directives(visitor, &fragment.directives);
+    walk_selection_set(visitor, &fragment.selection_set);
+}
+
+pub fn walk_variable_definition<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    variable: &'a VariableDefinition,
+) {
+    visitor.visit_type(&variable.ty);
+
+    if let Some(default) = &variable.default {
+        visitor.visit_value(default);
+    }
+}
+
+pub fn walk_selection_set<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    selection_set: &'a [Selection],
+) {
+    for selection in selection_set {
+        visitor.visit_selection(selection);
+    }
+}
+
+pub fn walk_selection<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, selection: &'a Selection) {
+    match selection {
+        Selection::Field {
+            arguments,
+            directives,
+            selection_set,
+            ..
+        } => {
+            walk_arguments(visitor, arguments.as_deref().unwrap_or_default());
+            walk_directives(visitor, directives);
+
+            if let Some(selection_set) = selection_set {
+                walk_selection_set(visitor, selection_set);
+            }
+        }
+        Selection::FragmentSpread { directives, .. } => walk_directives(visitor, directives),
+        Selection::InlineFragment {
+            directives,
+            selection_set,
+            ..
+        } => {
+            walk_directives(visitor, directives);
+            walk_selection_set(visitor, selection_set);
+        }
+    }
+}
+
+pub fn walk_type_definition<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    ty: TypeDefinitionRef<'a>,
+) {
+    walk_directives(visitor, ty.directives());
+
+    match ty {
+        TypeDefinitionRef::Scalar(..) => {}
+        TypeDefinitionRef::Object(object) => {
+            walk_named_types(visitor, &object.implements);
+            walk_field_definitions(visitor, object.fields.as_deref().unwrap_or_default());
+        }
+        TypeDefinitionRef::Interface(interface) => {
+            walk_named_types(visitor, &interface.implements);
+            walk_field_definitions(visitor, &interface.fields);
+        }
+        TypeDefinitionRef::Union(union) => walk_named_types(visitor, &union.types),
+        TypeDefinitionRef::Enum(enum_def) => {
+            for variant in &enum_def.variants {
+                visitor.visit_enum_variant(variant);
+            }
+        }
+        TypeDefinitionRef::InputObject(input) => {
+            walk_input_value_definitions(visitor, input.fields.as_deref().unwrap_or_default());
+        }
+    }
+}
+
+pub fn walk_type_extension<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    extension: &'a TypeExtension,
+) {
+    // extensions share their shape with definitions, so their children are
+    // walked the same way
+    let ty = match extension {
+        TypeExtension::Scalar(scalar) => TypeDefinitionRef::Scalar(scalar),
+        TypeExtension::Object(object) => TypeDefinitionRef::Object(object),
+        TypeExtension::Interface(interface) => TypeDefinitionRef::Interface(interface),
+        TypeExtension::Union(union) => TypeDefinitionRef::Union(union),
+        TypeExtension::Enum(enum_def) => TypeDefinitionRef::Enum(enum_def),
+        TypeExtension::InputObject(input) => TypeDefinitionRef::InputObject(input),
+    };
+
+    walk_type_definition(visitor, ty);
+}
+
+pub fn walk_directive_definition<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    directive: &'a DirectiveDefinition,
+) {
+    walk_input_value_definitions(visitor, directive.arguments.as_deref().unwrap_or_default());
+}
+
+pub fn walk_field_definition<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    field: &'a FieldDefinition,
+) {
+    walk_input_value_definitions(visitor, field.arguments.as_deref().unwrap_or_default());
+    visitor.visit_type(&field.ty);
+    walk_directives(visitor, &field.directives);
+}
+
+pub fn walk_input_value_definition<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    input_value: &'a InputObjectField,
+) {
+    visitor.visit_type(&input_value.ty);
+
+    if let Some(default) = &input_value.default {
+        visitor.visit_value(default);
+    }
+
+    walk_directives(visitor, &input_value.directives);
+}
+
+pub fn walk_enum_variant<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, variant: &'a EnumVariant) {
+    walk_directives(visitor, &variant.directives);
+}
+
+pub fn walk_directive<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, directive: &'a Directive) {
+    walk_arguments(visitor, directive.arguments.as_deref().unwrap_or_default());
+}
+
+pub fn walk_argument<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, argument: &'a Argument) {
+    visitor.visit_value(&argument.value);
+}
+
+pub fn walk_value<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, value: &'a Value) {
+    match value {
+        Value::List(values) => {
+            for value in values {
+                visitor.visit_value(value);
+            }
+        }
+        Value::Object(fields) => {
+            for value in fields.values() {
+                visitor.visit_value(value);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn walk_directives<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, directives: &'a [Directive]) {
+    for directive in directives {
+        visitor.visit_directive(directive);
+    }
+}
+
+fn walk_arguments<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, arguments: &'a [Argument]) {
+    for argument in arguments {
+        visitor.visit_argument(argument);
+    }
+}
+
+fn walk_named_types<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, types: &'a [NamedType]) {
+    for ty in types {
+        visitor.visit_named_type(ty);
+    }
+}
+
+fn walk_field_definitions<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    fields: &'a [FieldDefinition],
+) {
+    for field in fields {
+        visitor.visit_field_definition(field);
+    }
+}
+
+fn walk_input_value_definitions<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    input_values: &'a [InputObjectField],
+) {
+    for input_value in input_values {
+        visitor.visit_input_value_definition(input_value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::{walk_document, walk_selection, Visitor};
+    use crate::{
+        ast::{Directive, NamedType, Selection, Type, Value},
+        GraphqlParser,
+    };
+
+    /// Records the names of what it visits
+    struct Recorder<'a> {
+        interner: &'a Rodeo,
+        directives: Vec<&'a str>,
+        named_types: Vec<&'a str>,
+        types: Vec<&'a str>,
+        fields: Vec<&'a str>,
+        ints: Vec<i64>,
+        /// Fields whose selection sets aren't visited
+        stop_at: Option<&'a str>,
+    }
+
+    impl<'a> Recorder<'a> {
+        fn new(interner: &'a Rodeo) -> Self {
+            Self {
+                interner,
+                directives: Vec::new(),
+                named_types: Vec::new(),
+                types: Vec::new(),
+                fields: Vec::new(),
+                ints: Vec::new(),
+                stop_at: None,
+            }
+        }
+    }
+
+    impl<'a> Visitor<'a> for Recorder<'a> {
+        fn visit_directive(&mut self, directive: &'a Directive) {
+            self.directives.push(self.interner.resolve(&directive.name));
+            super::walk_directive(self, directive);
+        }
+
+        fn visit_named_type(&mut self, ty: &'a NamedType) {
+            self.named_types.push(self.interner.resolve(&ty.0));
+        }
+
+        fn visit_type(&mut self, ty: &'a Type) {
+            self.types.push(self.interner.resolve(&ty.name()));
+        }
+
+        fn visit_selection(&mut self, selection: &'a Selection) {
+            if let Selection::Field { name, .. } = selection {
+                let name = self.interner.resolve(name);
+                self.fields.push(name);
+
+                if self.stop_at == Some(name) {
+                    return;
+                }
+            }
+
+            walk_selection(self, selection);
+        }
+
+        fn visit_value(&mut self, value: &'a Value) {
+            if let Value::Int(int) = value {
+                self.ints.push(*int);
+            }
+
+            super::walk_value(self, value);
+        }
+    }
+
+    fn sorted<T: Ord>(mut values: Vec<T>) -> Vec<T> {
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn schema_definitions() {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(
+            concat!(
+                "schema @a { query: Query } directive @a(x: Int = 1) on SCHEMA | OBJECT ",
+                "type Query implements Node @a { id: ID! @b(x: 2) list(y: [Int] = [3]): [Query] } ",
+                "interface Node { id: ID! } union U @c = Query ",
+                "enum E { A @d } input In { f: E = A @e } extend type Query @f",
+            )
+            .as_bytes(),
+            &mut interner,
+        )
+        .unwrap();
+
+        let mut recorder = Recorder::new(&interner);
+        walk_document(&mut recorder, &document);
+
+        assert_eq!(
+            sorted(recorder.directives),
+            ["a", "a", "b", "c", "d", "e", "f"]
+        );
+        assert_eq!(sorted(recorder.named_types), ["Node", "Query", "Query"]);
+        assert_eq!(
+            sorted(recorder.types),
+            ["E", "ID", "ID", "Int", "Int", "Query"]
+        );
+        assert_eq!(sorted(recorder.ints), [1, 2, 3]);
+    }
+
+    #[test]
+    fn executable_definitions() {
+        let mut interner = Rodeo::default();
+        let document = GraphqlParser::parse_with_interner(
+            concat!(
+                "query($a: Int = 1) @q { a(x: [2, { y: 3 }]) { b @skip(if: false) ...F } ",
+                "... on Query @i { c } } ",
+                "fragment F on Query { d }",
+            )
+            .as_bytes(),
+            &mut interner,
+        )
+        .unwrap();
+
+        let mut recorder = Recorder::new(&interner);
+        walk_document(&mut recorder, &document);
+
+        assert_eq!(recorder.fields, ["a", "b", "c", "d"]);
+        assert_eq!(recorder.directives, ["q", "skip", "i"]);
+        assert_eq!(recorder.types, ["Int"]);
+        assert_eq!(sorted(recorder.ints), [1, 2, 3]);
+    }
+
+    #[test]
+    fn overriding_without_walking_stops_descent() {
+        let mut interner = Rodeo::default();
+        let document =
+            GraphqlParser::parse_with_interner(b"{ a { b { c } } d { e } }", &mut interner)
+                .unwrap();
+
+        let mut recorder = Recorder::new(&interner);
+        recorder.stop_at = Some("a");
+        walk_document(&mut recorder, &document);
+
+        assert_eq!(recorder.fields, ["a", "d", "e"]);
+    }
+}
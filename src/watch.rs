@@ -0,0 +1,167 @@
+//! Revalidating a [`Project`] as its files change
+//!
+//! [`ProjectWatcher`] listens for changes to the files under the project's
+//! root and passes them to [`Project::reload`], which only re-reads and
+//! re-parses the changed files, reusing the project's interner, and only
+//! revalidates the documents the change can affect
+
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::project::Project;
+
+/// How long to wait for further events after one arrives. Editors often save
+/// a file in several steps, such as by writing a temporary file and renaming
+/// it, and these are handled as one change
+const DEBOUNCE: Duration = Duration::from_millis(10);
+
+pub struct ProjectWatcher {
+    project: Project,
+    /// The project's root, as the absolute path events are reported with
+    root: PathBuf,
+    /// Kept alive so that events keep being sent
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl ProjectWatcher {
+    /// Start watching every file under the root of `project`
+    pub fn new(project: Project) -> notify::Result<Self> {
+        let root = project.root.canonicalize()?;
+
+        let (sender, events) = mpsc::channel();
+
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            project,
+            root,
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    pub fn project(&self) -> &Project {
+        &self.project
+    }
+
+    pub fn into_project(self) -> Project {
+        self.project
+    }
+
+    /// Block until files of the project change, then reload them, returning
+    /// the files whose diagnostics may have changed
+    pub fn wait(&mut self) -> notify::Result<Vec<PathBuf>> {
+        loop {
+            let mut paths = Vec::new();
+
+            let event = self.events.recv().map_err(|_| disconnected())?;
+            self.push_paths(event?, &mut paths);
+
+            loop {
+                match self.events.recv_timeout(DEBOUNCE) {
+                    Ok(event) => self.push_paths(event?, &mut paths),
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return Err(disconnected()),
+                }
+            }
+
+            paths.sort();
+            paths.dedup();
+
+            if paths.is_empty() {
+                continue;
+            }
+
+            let changed = self.project.reload(&paths);
+
+            if !changed.is_empty() {
+                return Ok(changed);
+            }
+        }
+    }
+
+    /// Add the paths of the project's files that `event` changed
+    fn push_paths(&self, event: Event, paths: &mut Vec<PathBuf>) {
+        if matches!(event.kind, EventKind::Access(..)) {
+            return;
+        }
+
+        for path in event.paths {
+            let Ok(relative) = path.strip_prefix(&self.root) else {
+                continue;
+            };
+
+            let path = self.project.root.join(relative);
+
+            let is_project_file = self.project.is_schema_file(&path)
+                || self.project.is_document_file(&path)
+                || self.project.file(&path).is_some();
+
+            if is_project_file {
+                paths.push(path);
+            }
+        }
+    }
+}
+
+fn disconnected() -> notify::Error {
+    notify::Error::generic("file watcher stopped")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use super::ProjectWatcher;
+    use crate::project::{tests::TestDir, Project};
+
+    #[test]
+    fn revalidates_changed_files() {
+        let dir = TestDir::new(
+            "watch",
+            &[
+                (
+                    ".graphqlrc.yml",
+                    "schema: schema.graphql\ndocuments: '*.query.graphql'",
+                ),
+                ("schema.graphql", "type Query { a: Int }"),
+                ("a.query.graphql", "{ b }"),
+            ],
+        );
+
+        let project = Project::discover(&dir.0, None).unwrap();
+        assert!(project.has_diagnostics());
+
+        let mut watcher = ProjectWatcher::new(project).unwrap();
+
+        // wait for the change on another thread, so that a missed event fails
+        // the test rather than hanging it
+        let (sender, receiver) = mpsc::channel();
+
+        let waiter = thread::spawn(move || {
+            let changed = watcher.wait();
+            let _ = sender.send(());
+            (changed, watcher)
+        });
+
+        // files that aren't part of the project are ignored
+        dir.write("notes.txt", "");
+        dir.write("a.query.graphql", "{ a }");
+
+        receiver
+            .recv_timeout(Duration::from_secs(10))
+            .expect("no change was reported");
+
+        let (changed, watcher) = waiter.join().unwrap();
+
+        assert_eq!(changed.unwrap(), [dir.path("a.query.graphql")]);
+        assert!(!watcher.project().has_diagnostics());
+    }
+}